@@ -17,6 +17,8 @@ pub fn build(b: *std.Build) void {
     });
 
     exe.linkFramework("AppKit");
+    // Accessibility API, for the focused window's title
+    exe.linkFramework("ApplicationServices");
 
     b.installArtifact(exe);
 
@@ -42,6 +44,7 @@ pub fn build(b: *std.Build) void {
     });
 
     unit_tests.linkFramework("AppKit");
+    unit_tests.linkFramework("ApplicationServices");
 
     const run_unit_tests = b.addRunArtifact(unit_tests);
 
@@ -0,0 +1,247 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Mutex, OnceLock,
+    },
+    thread,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+static LOG: OnceLock<Mutex<AccessLog>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessKind {
+    /// Shown in the preview pane, or a masked secret revealed.
+    Viewed,
+    /// Copied back to the clipboard.
+    Copied,
+    /// Written out by Export… or a snapshot.
+    Exported,
+}
+
+impl AccessKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            AccessKind::Viewed => "Viewed",
+            AccessKind::Copied => "Copied",
+            AccessKind::Exported => "Exported",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AccessEvent {
+    pub id: u64,
+    pub kind: AccessKind,
+    /// Unix milliseconds, like entry timestamps.
+    pub at: i64,
+}
+
+/// When each entry was viewed, copied or exported, for `[privacy] access_log`.
+///
+/// Kept in `access_log.jsonl` next to the config file, one event per line, appended
+/// as they happen. Nothing leaves the machine; purging rewrites the file.
+#[derive(Debug, Default)]
+struct AccessLog {
+    events: Vec<AccessEvent>,
+    /// The writer thread, when there is a file to keep.
+    writer: Option<Sender<FileChange>>,
+}
+
+/// A change to the file, made on the writer thread since views are recorded from
+/// `render`. Changes are applied in order, so a purge never races an append.
+#[derive(Debug)]
+enum FileChange {
+    Append(AccessEvent),
+    Rewrite(Vec<AccessEvent>),
+}
+
+impl AccessLog {
+    fn path() -> Option<PathBuf> {
+        Config::path().map(|config| config.with_file_name("access_log.jsonl"))
+    }
+
+    fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let events = std::fs::read_to_string(&path)
+            .map(|text| parse(&text))
+            .unwrap_or_default();
+        Self {
+            events,
+            writer: Some(spawn_writer(path)),
+        }
+    }
+
+    fn push(&mut self, event: AccessEvent) {
+        self.events.push(event);
+        self.write(FileChange::Append(event));
+    }
+
+    fn purge(&mut self, id: Option<u64>) {
+        match id {
+            Some(id) => self.events.retain(|event| event.id != id),
+            None => self.events.clear(),
+        }
+        self.write(FileChange::Rewrite(self.events.clone()));
+    }
+
+    fn write(&self, change: FileChange) {
+        if let Some(writer) = &self.writer {
+            let _ = writer.send(change);
+        }
+    }
+
+    fn for_entry(&self, id: u64) -> Vec<AccessEvent> {
+        self.events
+            .iter()
+            .rev()
+            .filter(|event| event.id == id)
+            .copied()
+            .collect()
+    }
+}
+
+fn spawn_writer(path: PathBuf) -> Sender<FileChange> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for change in rx {
+            if let Err(e) = apply(&path, change) {
+                eprintln!("Failed to write access log: {:#}", e);
+            }
+        }
+    });
+    tx
+}
+
+fn apply(path: &Path, change: FileChange) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create config directory")?;
+    }
+    match change {
+        FileChange::Append(event) => {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            writeln!(file, "{}", serde_json::to_string(&event)?)
+                .with_context(|| format!("failed to write {}", path.display()))
+        }
+        FileChange::Rewrite(events) => {
+            let mut text = String::new();
+            for event in &events {
+                text += &serde_json::to_string(event)?;
+                text.push('\n');
+            }
+            std::fs::write(path, text)
+                .with_context(|| format!("failed to write {}", path.display()))
+        }
+    }
+}
+
+/// Events from the file, skipping lines that don't parse.
+fn parse(text: &str) -> Vec<AccessEvent> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Starts logging. Until this is called every other function does nothing.
+pub fn enable() {
+    LOG.get_or_init(|| Mutex::new(AccessLog::load()));
+}
+
+pub fn is_enabled() -> bool {
+    LOG.get().is_some()
+}
+
+pub fn record(id: u64, kind: AccessKind) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    log.lock().unwrap().push(AccessEvent { id, kind, at });
+}
+
+/// The entry's events, newest first.
+pub fn for_entry(id: u64) -> Vec<AccessEvent> {
+    LOG.get()
+        .map(|log| log.lock().unwrap().for_entry(id))
+        .unwrap_or_default()
+}
+
+/// Forgets the events of one entry, or of every entry.
+pub fn purge(id: Option<u64>) {
+    if let Some(log) = LOG.get() {
+        log.lock().unwrap().purge(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_kept_per_entry_and_purged() {
+        let mut log = AccessLog {
+            writer: None,
+            events: parse(
+                "{\"id\":1,\"kind\":\"viewed\",\"at\":10}\n\
+                 not json\n\
+                 \n\
+                 {\"id\":2,\"kind\":\"exported\",\"at\":20}\n",
+            ),
+        };
+        log.push(AccessEvent {
+            id: 1,
+            kind: AccessKind::Copied,
+            at: 30,
+        });
+
+        let kinds: Vec<_> = log.for_entry(1).iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [AccessKind::Copied, AccessKind::Viewed]);
+        assert_eq!(
+            serde_json::to_string(&log.for_entry(2)[0]).unwrap(),
+            "{\"id\":2,\"kind\":\"exported\",\"at\":20}"
+        );
+
+        log.purge(Some(1));
+        assert!(log.for_entry(1).is_empty());
+        assert_eq!(log.for_entry(2).len(), 1);
+        log.purge(None);
+        assert!(log.events.is_empty());
+    }
+
+    #[test]
+    fn the_file_takes_appends_and_rewrites_in_order() {
+        let path = std::env::temp_dir().join(format!("clipz-access-{}.jsonl", std::process::id()));
+        let event = |id| AccessEvent {
+            id,
+            kind: AccessKind::Viewed,
+            at: 10,
+        };
+        apply(&path, FileChange::Append(event(1))).unwrap();
+        apply(&path, FileChange::Append(event(2))).unwrap();
+        assert_eq!(
+            parse(&std::fs::read_to_string(&path).unwrap()),
+            [event(1), event(2)]
+        );
+        apply(&path, FileChange::Rewrite(vec![event(2)])).unwrap();
+        assert_eq!(parse(&std::fs::read_to_string(&path).unwrap()), [event(2)]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+
+/// Directory under the system temp dir where generated archives are written.
+fn archive_dir() -> PathBuf {
+    std::env::temp_dir().join("clipz_archives")
+}
+
+/// Compresses `paths` into a fresh zip archive in the temp directory and returns its path.
+///
+/// Files are stored flat (`zip -j`), so the archive contains just the file names
+/// rather than their full source paths.
+pub fn zip_paths(paths: &[PathBuf]) -> Result<PathBuf> {
+    if paths.is_empty() {
+        bail!("nothing to compress");
+    }
+
+    let dir = archive_dir();
+    std::fs::create_dir_all(&dir).context("failed to create archive directory")?;
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let dest = dir.join(format!("Clipz-{}.zip", stamp));
+
+    let status = Command::new("/usr/bin/zip")
+        .arg("-j")
+        .arg("-q")
+        .arg(&dest)
+        .args(paths)
+        .status()
+        .context("failed to run zip")?;
+    if !status.success() {
+        bail!("zip exited with {}", status);
+    }
+
+    Ok(dest)
+}
+
+/// Whether an entry pointing at `path` can be added to an archive.
+pub fn is_archivable(path: &Path) -> bool {
+    path.is_file()
+}
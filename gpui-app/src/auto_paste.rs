@@ -0,0 +1,108 @@
+/// The app that was frontmost when the popover opened, to paste back into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub struct PreviousApp {
+    pid: i32,
+}
+
+/// Returns the frontmost app, unless it is Clipz itself.
+#[cfg(target_os = "macos")]
+pub fn frontmost_app() -> Option<PreviousApp> {
+    macos::frontmost_app()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn frontmost_app() -> Option<PreviousApp> {
+    None
+}
+
+/// Brings `app` back to the front, as when the popover is hidden without pasting.
+#[cfg(target_os = "macos")]
+pub fn activate(app: PreviousApp) {
+    macos::activate(app)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn activate(_app: PreviousApp) {}
+
+/// Re-activates `app` and synthesizes Cmd+V in it.
+///
+/// Events are posted through Quartz, so macOS silently drops them unless Clipz has been
+/// granted Accessibility permission.
+#[cfg(target_os = "macos")]
+pub fn paste_into(app: PreviousApp) {
+    macos::paste_into(app)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn paste_into(_app: PreviousApp) {}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::{ffi::c_void, thread, time::Duration};
+
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    use super::PreviousApp;
+
+    // kVK_ANSI_V
+    const KEY_CODE_V: u16 = 9;
+    const EVENT_FLAG_COMMAND: u64 = 1 << 20;
+    const HID_EVENT_TAP: u32 = 0;
+    const ACTIVATE_IGNORING_OTHER_APPS: u64 = 1 << 1;
+    // Give the target app time to become key before the keystroke arrives
+    const ACTIVATION_DELAY: Duration = Duration::from_millis(120);
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn CGEventCreateKeyboardEvent(source: *const c_void, key: u16, down: bool) -> *mut c_void;
+        fn CGEventSetFlags(event: *mut c_void, flags: u64);
+        fn CGEventPost(tap: u32, event: *mut c_void);
+        fn CFRelease(cf: *const c_void);
+    }
+
+    pub fn frontmost_app() -> Option<PreviousApp> {
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let app: id = msg_send![workspace, frontmostApplication];
+            if app == nil {
+                return None;
+            }
+            let pid: i32 = msg_send![app, processIdentifier];
+            (pid as u32 != std::process::id()).then_some(PreviousApp { pid })
+        }
+    }
+
+    pub fn activate(app: PreviousApp) {
+        unsafe {
+            let running: id = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationWithProcessIdentifier: app.pid
+            ];
+            if running == nil {
+                return;
+            }
+            let _: bool = msg_send![running, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+        }
+    }
+
+    pub fn paste_into(app: PreviousApp) {
+        activate(app);
+
+        thread::spawn(|| {
+            thread::sleep(ACTIVATION_DELAY);
+            for down in [true, false] {
+                unsafe {
+                    let event = CGEventCreateKeyboardEvent(std::ptr::null(), KEY_CODE_V, down);
+                    if event.is_null() {
+                        return;
+                    }
+                    CGEventSetFlags(event, EVENT_FLAG_COMMAND);
+                    CGEventPost(HID_EVENT_TAP, event);
+                    CFRelease(event);
+                }
+            }
+        });
+    }
+}
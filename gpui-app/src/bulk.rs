@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+
+use crate::{
+    navigation::civil_from_days,
+    protocol::{Command, Entry},
+};
+
+/// Commands sent per pass of the event loop. Larger batches go out over several, so the
+/// backend isn't flooded and the selection bar can show how far along they are.
+const BATCH: usize = 25;
+
+/// Tags like `expires-2026-10-23` mark the day an entry is removed. Keeping the date
+/// in a tag stores it with the entry, and shows it in the row like any other tag.
+const EXPIRY_PREFIX: &str = "expires-";
+
+/// A change made to every selected entry at once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BulkEdit {
+    /// Adds tags to each entry, keeping the ones it already has.
+    AddTags(Vec<String>),
+    Pin(bool),
+    /// Removes each entry this many days from now, pinned or not.
+    Expire {
+        days: u64,
+    },
+}
+
+impl BulkEdit {
+    fn label(&self) -> &'static str {
+        match self {
+            BulkEdit::AddTags(_) => "Tagging",
+            BulkEdit::Pin(true) => "Pinning",
+            BulkEdit::Pin(false) => "Unpinning",
+            BulkEdit::Expire { .. } => "Setting expiry on",
+        }
+    }
+
+    fn done_label(&self) -> &'static str {
+        match self {
+            BulkEdit::AddTags(_) => "Tagged",
+            BulkEdit::Pin(true) => "Pinned",
+            BulkEdit::Pin(false) => "Unpinned",
+            BulkEdit::Expire { .. } => "Set expiry on",
+        }
+    }
+
+    /// The command that makes the change to `entry`, or None when it already has it.
+    fn command(&self, entry: &Entry, now_secs: i64) -> Option<Command> {
+        match self {
+            BulkEdit::AddTags(tags) => {
+                let mut merged = entry.tags.clone();
+                merged.extend(tags.iter().filter(|t| !entry.tags.contains(t)).cloned());
+                (merged.len() != entry.tags.len()).then_some(Command::SetTags {
+                    id: entry.id,
+                    tags: merged,
+                })
+            }
+            BulkEdit::Pin(pin) => {
+                (entry.pinned != *pin).then_some(Command::TogglePin { id: entry.id })
+            }
+            BulkEdit::Expire { days } => {
+                let mut tags: Vec<String> = entry
+                    .tags
+                    .iter()
+                    .filter(|t| !t.starts_with(EXPIRY_PREFIX))
+                    .cloned()
+                    .collect();
+                tags.push(expiry_tag(now_secs, *days));
+                (tags != entry.tags).then_some(Command::SetTags { id: entry.id, tags })
+            }
+        }
+    }
+}
+
+/// The commands of one bulk edit, still to be sent, and those that would put every
+/// entry back the way it was.
+pub struct BulkJob {
+    pub label: &'static str,
+    pub done_label: &'static str,
+    pub total: usize,
+    pending: VecDeque<Command>,
+    undo: Vec<Command>,
+}
+
+impl BulkJob {
+    /// Entries that already have the change are left out.
+    pub fn new(edit: &BulkEdit, entries: &[Entry], now_secs: i64) -> Self {
+        let mut pending = VecDeque::new();
+        let mut undo = Vec::new();
+        for entry in entries {
+            let Some(command) = edit.command(entry, now_secs) else {
+                continue;
+            };
+            undo.push(match command {
+                Command::TogglePin { id } => Command::TogglePin { id },
+                _ => Command::SetTags {
+                    id: entry.id,
+                    tags: entry.tags.clone(),
+                },
+            });
+            pending.push_back(command);
+        }
+        Self {
+            label: edit.label(),
+            done_label: edit.done_label(),
+            total: pending.len(),
+            pending,
+            undo,
+        }
+    }
+
+    /// Reverses the whole job as one step. The undo itself can't be undone.
+    pub fn undo(&self) -> Self {
+        Self {
+            label: "Undoing",
+            done_label: "Undid",
+            total: self.undo.len(),
+            pending: self.undo.iter().cloned().collect(),
+            undo: Vec::new(),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// The next commands to send.
+    pub fn next_batch(&mut self) -> Vec<Command> {
+        let count = self.pending.len().min(BATCH);
+        self.pending.drain(..count).collect()
+    }
+
+    pub fn sent(&self) -> usize {
+        self.total - self.pending.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Whether the job takes more than one batch, and so is worth showing progress for.
+    pub fn is_large(&self) -> bool {
+        self.total > BATCH
+    }
+}
+
+fn expiry_tag(now_secs: i64, days: u64) -> String {
+    let (year, month, day) = civil_from_days(now_secs.div_euclid(86_400) + days as i64);
+    format!("{}{:04}-{:02}-{:02}", EXPIRY_PREFIX, year, month, day)
+}
+
+/// Whether an expiry set on `entry` has come, as of `now_secs` (UTC days).
+pub fn is_expired(entry: &Entry, now_secs: i64) -> bool {
+    let today = expiry_tag(now_secs, 0);
+    entry
+        .tags
+        .iter()
+        .any(|tag| tag.starts_with(EXPIRY_PREFIX) && *tag <= today)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::EntryType;
+
+    fn entry(id: u64, pinned: bool, tags: &[&str]) -> Entry {
+        Entry {
+            id,
+            content: "text".into(),
+            pinned,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn jobs_skip_entries_already_changed_and_undo_in_one_step() {
+        let entries: Vec<Entry> = (0..60).map(|id| entry(id, id % 2 == 0, &[])).collect();
+        let mut pin = BulkJob::new(&BulkEdit::Pin(true), &entries, 0);
+        assert_eq!(pin.total, 30);
+        assert!(pin.is_large());
+        assert_eq!(pin.next_batch().len(), BATCH);
+        assert_eq!(pin.sent(), BATCH);
+        assert_eq!(pin.next_batch().len(), 5);
+        assert!(pin.is_finished());
+        let mut undo = pin.undo();
+        assert_eq!(undo.next_batch()[0], Command::TogglePin { id: 1 });
+        assert!(!undo.can_undo());
+
+        let tagged = [entry(1, false, &["work"]), entry(2, false, &["home"])];
+        let mut tag = BulkJob::new(&BulkEdit::AddTags(vec!["work".into()]), &tagged, 0);
+        assert_eq!(
+            tag.next_batch(),
+            [Command::SetTags {
+                id: 2,
+                tags: vec!["home".into(), "work".into()],
+            }]
+        );
+        assert_eq!(
+            tag.undo().next_batch(),
+            [Command::SetTags {
+                id: 2,
+                tags: vec!["home".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn expiry_is_kept_as_a_dated_tag() {
+        // 2024-02-27 00:00 UTC
+        let now = 1_708_992_000;
+        let mut job = BulkJob::new(
+            &BulkEdit::Expire { days: 3 },
+            &[entry(1, true, &["work", "expires-2030-01-01"])],
+            now,
+        );
+        let [Command::SetTags { tags, .. }] = &job.next_batch()[..] else {
+            panic!("expected one SetTags");
+        };
+        assert_eq!(tags, &["work", "expires-2024-03-01"]);
+
+        let expiring = entry(1, true, &["expires-2024-03-01"]);
+        assert!(!is_expired(&expiring, now + 2 * 86_400));
+        assert!(is_expired(&expiring, now + 3 * 86_400));
+        assert!(!is_expired(&entry(2, false, &["work"]), now));
+    }
+}
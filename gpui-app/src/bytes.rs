@@ -0,0 +1,40 @@
+use std::io::Read;
+
+/// Lowercase hex, two digits per byte.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The bytes `hex` spells in either case; None unless it is whole pairs of hex digits.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    // from_str_radix alone would take a sign, as in "+1"
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `N` bytes from the system's random source, for keys and nonces.
+pub fn random<const N: usize>() -> std::io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        assert_eq!(to_hex(&[0x00, 0x0f, 0xab]), "000fab");
+        assert_eq!(from_hex("000fAB"), Some(vec![0x00, 0x0f, 0xab]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("+1"), None);
+        assert_eq!(from_hex("\u{e9}a"), None);
+        assert_ne!(random::<16>().unwrap(), random::<16>().unwrap());
+    }
+}
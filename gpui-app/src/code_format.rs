@@ -0,0 +1,246 @@
+use std::{process::Command, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    config::FormatterConfig,
+    highlight, pipe,
+    protocol::{Entry, EntryType},
+    transforms,
+};
+
+// External formatters get this long before they are killed
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Languages Format Code knows how to format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Json,
+    Rust,
+    Python,
+    JavaScript,
+    Sql,
+}
+
+impl Language {
+    /// The language of a text entry, as the preview's highlighting detects it, if it
+    /// is one Format Code handles.
+    pub fn detect(entry: &Entry) -> Option<Self> {
+        if entry.entry_type != EntryType::Text {
+            return None;
+        }
+        match highlight::detect(&entry.content)?.name.as_str() {
+            "JSON" => Some(Language::Json),
+            "Rust" => Some(Language::Rust),
+            "Python" => Some(Language::Python),
+            "JavaScript" => Some(Language::JavaScript),
+            "SQL" => Some(Language::Sql),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Language::Json => "JSON",
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+            Language::JavaScript => "JavaScript",
+            Language::Sql => "SQL",
+        }
+    }
+
+    fn command(self, config: &FormatterConfig) -> &str {
+        match self {
+            Language::Json => &config.json,
+            Language::Rust => &config.rust,
+            Language::Python => &config.python,
+            Language::JavaScript => &config.javascript,
+            Language::Sql => &config.sql,
+        }
+    }
+}
+
+/// Formats `text` with the command configured for `language`, or the built-in
+/// formatter when none is. Can block for up to `TIMEOUT`, so call it off the UI thread.
+pub fn format(text: &str, language: Language, config: &FormatterConfig) -> Result<String> {
+    let command = language.command(config).trim();
+    let formatted = if !command.is_empty() {
+        run(command, text)?
+    } else {
+        match language {
+            Language::Json => transforms::reformat_json(text, Some("  "))
+                .ok_or_else(|| anyhow!("not valid JSON"))?,
+            Language::Rust => format_rust(text)?,
+            Language::Sql => sqlformat::format(
+                text,
+                &sqlformat::QueryParams::None,
+                sqlformat::FormatOptions::default(),
+            ),
+            Language::Python | Language::JavaScript => {
+                bail!("no formatter set for {}", language.label())
+            }
+        }
+    };
+    // Formatters end files with a newline, which a copied snippet doesn't want
+    Ok(formatted.trim_end().to_string())
+}
+
+/// Formats with `rustfmt` when it is installed. The built-in formatter drops comments,
+/// so without rustfmt, code that has any is refused rather than changed.
+fn format_rust(text: &str) -> Result<String> {
+    match as_file_or_body(text, rustfmt) {
+        Err(e) if pipe::is_not_found(&e) => {}
+        formatted => return formatted,
+    }
+    if has_comments(text) {
+        bail!("install rustfmt to format Rust with comments; they would be lost otherwise");
+    }
+    as_file_or_body(text, prettyplease)
+}
+
+fn prettyplease(source: &str) -> Result<String> {
+    let file = syn::parse_file(source).map_err(|e| anyhow!("not valid Rust: {}", e))?;
+    Ok(prettyplease::unparse(&file))
+}
+
+fn rustfmt(source: &str) -> Result<String> {
+    let mut command = formatter_command("rustfmt --edition 2021")?;
+    pipe::run(&mut command, source.as_bytes().to_vec(), "rustfmt", TIMEOUT)
+}
+
+/// Whole files are formatted as they are; anything else is tried as the body of a
+/// function, so a few statements or an expression can be formatted too.
+fn as_file_or_body(text: &str, format: impl Fn(&str) -> Result<String>) -> Result<String> {
+    match format(text) {
+        Err(e) if pipe::is_not_found(&e) => return Err(e),
+        Err(_) => {}
+        formatted => return formatted,
+    }
+    let wrapped = format!("fn snippet() {{\n{}\n}}", text);
+    let formatted = format(&wrapped)?;
+    let lines: Vec<&str> = formatted.trim_end().lines().collect();
+    let body = lines
+        .get(1..lines.len().saturating_sub(1))
+        .unwrap_or_default();
+    Ok(body
+        .iter()
+        .map(|line| line.strip_prefix("    ").unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Whether Rust source has a `//` or `/* */` comment outside string literals.
+fn has_comments(text: &str) -> bool {
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && matches!(chars.peek(), Some('/' | '*')) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn run(command: &str, text: &str) -> Result<String> {
+    let program = command.split_whitespace().next().unwrap_or(command);
+    let mut command = formatter_command(command)?;
+    pipe::run(&mut command, text.as_bytes().to_vec(), program, TIMEOUT)
+}
+
+/// `command` split on whitespace, ready to run.
+fn formatter_command(command: &str) -> Result<Command> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().context("empty formatter command")?;
+    let mut command = Command::new(program);
+    command.args(parts);
+    // Apps started from Finder don't get the shell's PATH, where formatters tend to live
+    #[cfg(target_os = "macos")]
+    command.env(
+        "PATH",
+        format!(
+            "/opt/homebrew/bin:/usr/local/bin:{}/.cargo/bin:{}",
+            std::env::var("HOME").unwrap_or_default(),
+            std::env::var("PATH").unwrap_or_default()
+        ),
+    );
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formattable_languages() {
+        assert_eq!(
+            Language::detect(&Entry::text("{\"a\":1}")),
+            Some(Language::Json)
+        );
+        assert_eq!(
+            Language::detect(&Entry::text("SELECT id FROM users WHERE active")),
+            Some(Language::Sql)
+        );
+        assert_eq!(
+            Language::detect(&Entry::text(
+                "#include <stdio.h>\nint main() { printf(\"hi\"); }"
+            )),
+            None
+        );
+        assert_eq!(Language::detect(&Entry::text("just some words")), None);
+    }
+
+    #[test]
+    fn built_in_formatters() {
+        let config = FormatterConfig::default();
+        assert_eq!(
+            format("{\"a\":[1,2]}", Language::Json, &config).unwrap(),
+            "{\n  \"a\": [\n    1,\n    2\n  ]\n}"
+        );
+        assert_eq!(
+            format("fn main(){let x=1;}", Language::Rust, &config).unwrap(),
+            "fn main() {\n    let x = 1;\n}"
+        );
+        assert_eq!(
+            as_file_or_body("let x=1;if x>0{x}else{0}", prettyplease).unwrap(),
+            "let x = 1;\nif x > 0 { x } else { 0 }"
+        );
+        assert!(format("fn (", Language::Rust, &config).is_err());
+        // Either rustfmt keeps the comment or nothing is changed
+        match format("fn main(){ // keep me\nlet x=1;}", Language::Rust, &config) {
+            Ok(formatted) => assert!(formatted.contains("// keep me")),
+            Err(e) => assert!(e.to_string().contains("rustfmt")),
+        }
+        assert_eq!(
+            format("select a, b from t where a = 1", Language::Sql, &config).unwrap(),
+            "select\n  a,\n  b\nfrom\n  t\nwhere\n  a = 1"
+        );
+    }
+
+    #[test]
+    fn comments_are_found_outside_strings() {
+        assert!(has_comments("let x = 1; // one"));
+        assert!(has_comments("/* block */ fn f() {}"));
+        assert!(!has_comments("let url = \"https://example.com\";"));
+        assert!(!has_comments("let s = \"a \\\" // b\";"));
+        assert!(!has_comments("let half = 1 / 2;"));
+    }
+
+    #[test]
+    fn external_formatters_read_stdin() {
+        let config = FormatterConfig {
+            json: "tr a-z A-Z".into(),
+            rust: "false".into(),
+            ..FormatterConfig::default()
+        };
+        assert_eq!(
+            format("{\"a\":1}\n", Language::Json, &config).unwrap(),
+            "{\"A\":1}"
+        );
+        assert!(format("fn main() {}", Language::Rust, &config).is_err());
+    }
+}
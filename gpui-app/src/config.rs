@@ -0,0 +1,771 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{Context, Result};
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::{notice, theme::ThemeName};
+
+/// Set when the config file was there but didn't load, so the first save keeps the
+/// user's edits aside instead of writing the defaults over them.
+static LOAD_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// User configuration loaded from `~/.config/clipz/config.toml` at startup.
+///
+/// Every field is optional in the file; anything missing falls back to the
+/// built-in default, so an empty (or absent) file behaves like the stock app.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub hotkey: HotkeyConfig,
+    pub window: WindowConfig,
+    pub history: HistoryConfig,
+    pub backend: BackendConfig,
+    pub behavior: BehaviorConfig,
+    pub appearance: AppearanceConfig,
+    pub startup: StartupConfig,
+    pub privacy: PrivacyConfig,
+    pub sync: SyncConfig,
+    pub formatters: FormatterConfig,
+    /// Chords for popover actions by name, replacing the defaults, e.g.
+    /// `down = ["down", "ctrl+n"]`. See `KeyAction::name` for the names.
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    /// Global toggle chord in global-hotkey syntax, e.g. `"cmd+alt+="` or `"ctrl+shift+KeyV"`.
+    pub toggle: String,
+    /// Modifiers that, with 1 to 9, paste the first nine snippets from any app, e.g.
+    /// `"ctrl+alt"`. Empty leaves them unbound.
+    pub snippets: String,
+    /// Pastes the next entry queued with Add to Paste Stack. Only registered while
+    /// something is queued, so the chord works as usual in other apps otherwise. Empty
+    /// turns the paste stack off.
+    pub paste_stack: String,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            toggle: "super+alt+Equal".into(),
+            snippets: String::new(),
+            paste_stack: "super+alt+KeyV".into(),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    pub fn toggle_hotkey(&self) -> HotKey {
+        self.toggle.parse().unwrap_or_else(|e| {
+            eprintln!("Invalid hotkey {:?} in config: {}", self.toggle, e);
+            HotKey::new(Some(Modifiers::SUPER | Modifiers::ALT), Code::Equal)
+        })
+    }
+
+    pub fn paste_stack_hotkey(&self) -> Option<HotKey> {
+        if self.paste_stack.is_empty() {
+            return None;
+        }
+        self.paste_stack
+            .parse()
+            .map_err(|e| {
+                eprintln!(
+                    "Invalid paste stack hotkey {:?} in config: {}",
+                    self.paste_stack, e
+                )
+            })
+            .ok()
+    }
+
+    /// The `snippets` modifiers with 1 to 9, in snippet order.
+    pub fn snippet_hotkeys(&self) -> Vec<HotKey> {
+        if self.snippets.is_empty() {
+            return Vec::new();
+        }
+        let digits = (1..=9).map(|n| format!("{}+Digit{}", self.snippets, n).parse::<HotKey>());
+        match digits.collect() {
+            Ok(hotkeys) => hotkeys,
+            Err(e) => {
+                eprintln!(
+                    "Invalid snippet modifiers {:?} in config: {}",
+                    self.snippets, e
+                );
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+    /// Lines of content shown per row, 1 to 3.
+    pub preview_lines: usize,
+    /// Screen edge to dock the popover to instead of hanging it off the menu bar icon.
+    pub dock: DockEdge,
+    /// Which Spaces the popover shows on.
+    pub spaces: SpaceBehavior,
+    /// Hide the popover when another window takes focus; off, only Escape, the hotkey
+    /// or the menu bar icon hide it.
+    pub hide_on_blur: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 320.0,
+            height: 400.0,
+            preview_lines: 1,
+            dock: DockEdge::default(),
+            spaces: SpaceBehavior::default(),
+            hide_on_blur: true,
+        }
+    }
+}
+
+impl WindowConfig {
+    pub fn preview_lines(&self) -> usize {
+        self.preview_lines.clamp(1, 3)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DockEdge {
+    /// Open below the menu bar icon.
+    #[default]
+    None,
+    /// Keep a thin strip on the left edge of the main display; hovering it opens
+    /// the popover flush against that edge.
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpaceBehavior {
+    /// Open on the Space in use, and move there when you switch Spaces while it is open.
+    #[default]
+    ActiveSpace,
+    /// Show on every Space at once.
+    AllSpaces,
+    /// Stay on the Space it was opened on.
+    AssignedSpace,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// 0 keeps every entry.
+    pub max_entries: usize,
+    pub order: EntryOrder,
+    /// Remove unpinned entries copied more than this many days ago; 0 keeps them until
+    /// `max_entries` pushes them out.
+    pub retention_days: u64,
+    /// Days until entries given an expiry from the selection bar are removed; 0 hides
+    /// the button.
+    pub selection_expiry_days: u64,
+    /// Copying content already in the history moves that entry to the top with a ×N
+    /// count; off, such copies are ignored.
+    pub coalesce_duplicates: bool,
+    /// Where image entries copied as files point: at the original, or at a copy the
+    /// backend keeps.
+    pub image_transport: ImageTransport,
+    /// Below this much free disk space, in MB, copied images stop being saved and the
+    /// popover offers to free some up; 0 turns the check off.
+    pub low_disk_mb: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 10,
+            order: EntryOrder::default(),
+            retention_days: 0,
+            selection_expiry_days: 7,
+            coalesce_duplicates: true,
+            image_transport: ImageTransport::default(),
+            low_disk_mb: 1024,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// `max_entries` as sent to the backend; None when the history is unlimited.
+    pub fn limit(&self) -> Option<usize> {
+        (self.max_entries > 0).then_some(self.max_entries)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryOrder {
+    /// Newest copy first, as the backend sends them.
+    #[default]
+    Recent,
+    /// Entries copied back often and lately float to the top.
+    Frecency,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageTransport {
+    /// The entry is the copied file's path, and breaks if the file moves.
+    #[default]
+    Path,
+    /// The backend copies the image into its own store, so the entry outlives the file.
+    Store,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct BackendConfig {
+    pub profile: PerformanceProfile,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PerformanceProfile {
+    #[default]
+    LowPower,
+    Balanced,
+    Responsive,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct BehaviorConfig {
+    pub click: ClickBehavior,
+    /// After copying an entry, switch back to the app that was frontmost when the
+    /// popover opened and press Cmd+V there. Needs Accessibility permission.
+    pub auto_paste: bool,
+    /// Read the text in image entries in the background so search finds screenshots.
+    pub ocr: bool,
+    /// Fetch the title of copied web pages and show it in place of the URL.
+    pub fetch_titles: bool,
+    /// What goes between entries when several selected ones are combined into one.
+    pub combine_separator: CombineSeparator,
+    /// Once a week, show the Stats view's digest of the week as a notification.
+    pub weekly_digest: bool,
+    /// Vim-style keys in the list: j/k, gg/G and dd, with / to start a search. They can
+    /// be rebound in `[keys]` like the rest.
+    pub vim_mode: bool,
+    /// Start Clipz at login so copies are recorded from boot. Also in the menu bar menu.
+    pub launch_at_login: bool,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            click: ClickBehavior::default(),
+            auto_paste: false,
+            ocr: true,
+            fetch_titles: false,
+            combine_separator: CombineSeparator::default(),
+            weekly_digest: false,
+            vim_mode: false,
+            launch_at_login: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CombineSeparator {
+    #[default]
+    Newline,
+    Comma,
+    Space,
+}
+
+impl CombineSeparator {
+    /// Shown on the selection bar's toggle.
+    pub fn label(self) -> &'static str {
+        match self {
+            CombineSeparator::Newline => "\u{21b5}",
+            CombineSeparator::Comma => ",",
+            CombineSeparator::Space => "\u{2423}",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CombineSeparator::Newline => CombineSeparator::Comma,
+            CombineSeparator::Comma => CombineSeparator::Space,
+            CombineSeparator::Space => CombineSeparator::Newline,
+        }
+    }
+
+    pub fn join(self, parts: &[&str]) -> String {
+        parts.join(match self {
+            CombineSeparator::Newline => "\n",
+            CombineSeparator::Comma => ", ",
+            CombineSeparator::Space => " ",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct AppearanceConfig {
+    pub theme: ThemeName,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Bundle IDs (`"com.1password.1password"`) or app names (`"Terminal"`) whose
+    /// copies are never recorded. Matched against the frontmost app, ignoring case.
+    pub excluded_apps: Vec<String>,
+    /// Show likely passwords, API keys, card numbers and one-time codes as `••••`
+    /// until revealed.
+    pub mask_sensitive: bool,
+    /// Remove unpinned sensitive entries this many seconds after they were copied;
+    /// 0 keeps them like any other entry.
+    pub expire_sensitive_secs: u64,
+    /// Save the history file encrypted with a key kept in the login Keychain. Recognized
+    /// image text, link titles and recent searches are then kept in memory only;
+    /// snippets, which are typed rather than copied, are still saved as they are.
+    pub encrypt_history: bool,
+    /// Keep the history for this session only: purge it when Clipz quits.
+    pub forget_on_quit: ForgetOnQuit,
+    /// Log locally when each entry is viewed, copied or exported; shown under the
+    /// preview and cleared from the status item menu.
+    pub access_log: bool,
+    /// Record the focused window's title (the document or browser tab) with each
+    /// copy, shown in the preview and matched by search. Titles can be private.
+    pub capture_window_titles: bool,
+}
+
+/// What a session-only history forgets on quit.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForgetOnQuit {
+    #[default]
+    Off,
+    /// Everything except pinned entries.
+    Unpinned,
+    All,
+}
+
+impl ForgetOnQuit {
+    /// Value for the backend's `--forget-on-quit`.
+    fn arg(self) -> Option<&'static str> {
+        match self {
+            ForgetOnQuit::Off => None,
+            ForgetOnQuit::Unpinned => Some("unpinned"),
+            ForgetOnQuit::All => Some("all"),
+        }
+    }
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            excluded_apps: Vec::new(),
+            mask_sensitive: true,
+            expire_sensitive_secs: 0,
+            encrypt_history: false,
+            forget_on_quit: ForgetOnQuit::Off,
+            access_log: false,
+            capture_window_titles: false,
+        }
+    }
+}
+
+/// Sharing new entries with Clipz on other Macs on the local network.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub enabled: bool,
+    /// Passphrase every synced Mac must share; messages signed with anything else are
+    /// dropped. Sync stays off while it is empty.
+    pub secret: String,
+    /// TCP port this Mac listens on for entries from its peers.
+    pub port: u16,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            secret: String::new(),
+            port: 47_811,
+        }
+    }
+}
+
+/// Commands Format Code pipes an entry through, per language. Each is a program and
+/// its arguments split on whitespace; the code goes to its stdin and the formatted code
+/// comes back on stdout. An empty command means the built-in formatter, which exists
+/// for JSON, Rust and SQL.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct FormatterConfig {
+    pub json: String,
+    /// Empty uses rustfmt when installed, else a built-in formatter that refuses code
+    /// with comments, since it would drop them.
+    pub rust: String,
+    pub python: String,
+    pub javascript: String,
+    pub sql: String,
+}
+
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self {
+            json: String::new(),
+            rust: String::new(),
+            python: "black --quiet -".to_string(),
+            javascript: "prettier --stdin-filepath clip.js".to_string(),
+            sql: String::new(),
+        }
+    }
+}
+
+/// Actions run once the backend reports it is ready after launch.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct StartupConfig {
+    pub actions: Vec<StartupAction>,
+    /// Only log what would run, to check the list before trusting it.
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartupAction {
+    /// Start with recording paused, as if chosen from the status item menu.
+    PauseRecording,
+    /// Clear unpinned history left over from the last session.
+    ClearHistory,
+    ShowPopover,
+}
+
+impl StartupAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            StartupAction::PauseRecording => "pause-recording",
+            StartupAction::ClearHistory => "clear-history",
+            StartupAction::ShowPopover => "show-popover",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClickBehavior {
+    /// A single click copies the entry.
+    #[default]
+    Single,
+    /// A single click only focuses the entry; double-click (or Enter) copies it.
+    Double,
+}
+
+impl PerformanceProfile {
+    fn flag(self) -> Option<&'static str> {
+        match self {
+            PerformanceProfile::LowPower => Some("--low-power"),
+            PerformanceProfile::Balanced => None,
+            PerformanceProfile::Responsive => Some("--responsive"),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+            return Some(PathBuf::from(dir).join("clipz/config.toml"));
+        }
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/clipz/config.toml"))
+    }
+
+    /// Loads the config file, falling back to defaults when it is missing or invalid.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+        match Self::load_from(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to load {}: {:#}", path.display(), e);
+                LOAD_FAILED.store(true, Ordering::SeqCst);
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the config back to its file, e.g. after the hotkey is re-recorded in the app.
+    /// Comments in a hand-edited file are not preserved. A file that failed to load is
+    /// first moved to `config.toml.invalid`, so fixing it by hand loses nothing.
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path().context("no config directory")?)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("failed to create config directory")?;
+        }
+        if LOAD_FAILED.load(Ordering::SeqCst) && path.exists() {
+            let backup = path.with_extension("toml.invalid");
+            std::fs::rename(path, &backup).context("failed to set aside the invalid config")?;
+            notice::show(format!(
+                "{} didn't load, so it was kept as {}",
+                path.display(),
+                backup.display()
+            ));
+        }
+        LOAD_FAILED.store(false, Ordering::SeqCst);
+        let text = toml::to_string_pretty(self).context("failed to serialize config")?;
+        std::fs::write(path, text).context("failed to write config")
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).context("failed to read config")?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).context("failed to parse config")
+    }
+
+    /// Command-line arguments for the backend process.
+    pub fn backend_args(&self) -> Vec<String> {
+        let mut args = vec!["--json-api".to_string()];
+        if let Some(flag) = self.backend.profile.flag() {
+            args.push(flag.to_string());
+        }
+        args.push("--max-entries".to_string());
+        args.push(match self.history.limit() {
+            Some(limit) => limit.to_string(),
+            None => "unlimited".to_string(),
+        });
+        for app in &self.privacy.excluded_apps {
+            let app = app.trim();
+            if !app.is_empty() {
+                args.push("--exclude-app".to_string());
+                args.push(app.to_string());
+            }
+        }
+        if !self.history.coalesce_duplicates {
+            args.push("--no-coalesce".to_string());
+        }
+        if self.history.image_transport == ImageTransport::Store {
+            args.push("--store-images".to_string());
+        }
+        if self.privacy.encrypt_history {
+            args.push("--encrypt-history".to_string());
+        }
+        if let Some(what) = self.privacy.forget_on_quit.arg() {
+            args.push("--forget-on-quit".to_string());
+            args.push(what.to_string());
+        }
+        if self.privacy.capture_window_titles {
+            args.push("--window-titles".to_string());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_over_a_config_that_failed_to_load_keeps_it_aside() {
+        let dir = std::env::temp_dir().join(format!("clipz-config-test-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "[window\nwidth = 500").unwrap();
+        assert!(Config::load_from(&path).is_err());
+
+        LOAD_FAILED.store(true, Ordering::SeqCst);
+        Config::default().save_to(&path).unwrap();
+        let backup = std::fs::read_to_string(dir.join("config.toml.invalid")).unwrap();
+        assert_eq!(backup, "[window\nwidth = 500");
+        assert_eq!(Config::load_from(&path).unwrap(), Config::default());
+
+        // Later saves just overwrite
+        std::fs::remove_file(dir.join("config.toml.invalid")).unwrap();
+        Config::default().save_to(&path).unwrap();
+        assert!(!dir.join("config.toml.invalid").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_file_uses_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(
+            config.backend_args(),
+            ["--json-api", "--low-power", "--max-entries", "10"]
+        );
+
+        let unlimited = Config::parse("[history]\nmax_entries = 0").unwrap();
+        assert_eq!(unlimited.history.limit(), None);
+        assert_eq!(unlimited.backend_args()[3], "unlimited");
+    }
+
+    #[test]
+    fn partial_file_overrides_only_given_fields() {
+        let config = Config::parse(
+            r#"
+            [hotkey]
+            toggle = "ctrl+shift+KeyV"
+            snippets = "ctrl+alt"
+            paste_stack = ""
+
+            [window]
+            height = 600
+            preview_lines = 9
+            dock = "right"
+            spaces = "all-spaces"
+            hide_on_blur = false
+
+            [history]
+            max_entries = 50
+            order = "frecency"
+            retention_days = 7
+            selection_expiry_days = 30
+            coalesce_duplicates = false
+            image_transport = "store"
+            low_disk_mb = 0
+
+            [backend]
+            profile = "responsive"
+
+            [behavior]
+            click = "double"
+            auto_paste = true
+            ocr = false
+            fetch_titles = true
+            combine_separator = "comma"
+            weekly_digest = true
+            vim_mode = true
+            launch_at_login = true
+
+            [startup]
+            actions = ["pause-recording", "show-popover"]
+
+            [privacy]
+            excluded_apps = ["com.1password.1password", " ", "Terminal"]
+            expire_sensitive_secs = 60
+            encrypt_history = true
+            forget_on_quit = "unpinned"
+            access_log = true
+            capture_window_titles = true
+
+            [sync]
+            enabled = true
+            secret = "correct horse"
+
+            [formatters]
+            rust = "rustfmt --edition 2021"
+
+            [keys]
+            down = ["down", "ctrl+n"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.window.width, 320.0);
+        assert_eq!(config.window.height, 600.0);
+        assert_eq!(config.window.preview_lines(), 3);
+        assert_eq!(config.window.dock, DockEdge::Right);
+        assert_eq!(config.window.spaces, SpaceBehavior::AllSpaces);
+        assert!(!config.window.hide_on_blur);
+        assert!(Config::default().window.hide_on_blur);
+        assert_eq!(config.history.order, EntryOrder::Frecency);
+        assert_eq!(config.history.retention_days, 7);
+        assert_eq!(config.history.selection_expiry_days, 30);
+        assert_eq!(config.history.low_disk_mb, 0);
+        assert_eq!(config.history.image_transport, ImageTransport::Store);
+        assert_eq!(Config::default().history.low_disk_mb, 1024);
+        assert_eq!(config.behavior.click, ClickBehavior::Double);
+        assert!(config.behavior.auto_paste);
+        assert!(!config.behavior.ocr);
+        assert!(Config::default().behavior.ocr);
+        assert!(config.behavior.weekly_digest);
+        assert!(!Config::default().behavior.weekly_digest);
+        assert!(config.behavior.vim_mode);
+        assert!(config.behavior.launch_at_login);
+        assert!(config.behavior.fetch_titles);
+        assert!(!Config::default().behavior.fetch_titles);
+        assert_eq!(config.behavior.combine_separator, CombineSeparator::Comma);
+        assert_eq!(config.behavior.combine_separator.join(&["a", "b"]), "a, b");
+        assert_eq!(CombineSeparator::Space.next(), CombineSeparator::Newline);
+        assert_eq!(
+            config.startup.actions,
+            [StartupAction::PauseRecording, StartupAction::ShowPopover]
+        );
+        assert!(!config.startup.dry_run);
+        assert!(config.privacy.mask_sensitive);
+        assert_eq!(config.privacy.expire_sensitive_secs, 60);
+        assert!(config.privacy.access_log);
+        assert!(config.sync.enabled);
+        assert_eq!(config.sync.port, 47_811);
+        assert_eq!(config.formatters.rust, "rustfmt --edition 2021");
+        assert_eq!(config.formatters.python, "black --quiet -");
+        assert_eq!(config.keys["down"], ["down", "ctrl+n"]);
+        assert_eq!(
+            config.hotkey.toggle_hotkey(),
+            HotKey::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyV)
+        );
+        let snippet_hotkeys = config.hotkey.snippet_hotkeys();
+        assert_eq!(snippet_hotkeys.len(), 9);
+        assert_eq!(
+            snippet_hotkeys[0],
+            HotKey::new(Some(Modifiers::CONTROL | Modifiers::ALT), Code::Digit1)
+        );
+        assert!(Config::default().hotkey.snippet_hotkeys().is_empty());
+        assert_eq!(config.hotkey.paste_stack_hotkey(), None);
+        assert_eq!(
+            Config::default().hotkey.paste_stack_hotkey(),
+            Some(HotKey::new(
+                Some(Modifiers::SUPER | Modifiers::ALT),
+                Code::KeyV
+            ))
+        );
+        assert_eq!(
+            config.backend_args(),
+            [
+                "--json-api",
+                "--responsive",
+                "--max-entries",
+                "50",
+                "--exclude-app",
+                "com.1password.1password",
+                "--exclude-app",
+                "Terminal",
+                "--no-coalesce",
+                "--store-images",
+                "--encrypt-history",
+                "--forget-on-quit",
+                "unpinned",
+                "--window-titles"
+            ]
+        );
+    }
+
+    #[test]
+    fn saved_config_round_trips() {
+        let mut config = Config::default();
+        config.hotkey.toggle = "cmd+shift+KeyV".into();
+        config.backend.profile = PerformanceProfile::Balanced;
+        config.appearance.theme = ThemeName::Light;
+
+        let text = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(Config::parse(&text).unwrap(), config);
+    }
+}
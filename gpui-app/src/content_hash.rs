@@ -0,0 +1,55 @@
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bytes,
+    protocol::{Entry, EntryType},
+};
+
+/// SHA-256 over the type name, a zero byte and the content: the backend's
+/// `hashing.zig`, for content it hasn't hashed (entries from peers, old exports).
+pub fn digest(entry_type: EntryType, content: &str) -> [u8; 32] {
+    let type_name = match entry_type {
+        EntryType::Text => "text",
+        EntryType::Image => "image",
+        EntryType::File => "file",
+        EntryType::Url => "url",
+        EntryType::Color => "color",
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(type_name);
+    hasher.update([0]);
+    hasher.update(content);
+    hasher.finalize().into()
+}
+
+/// The entry's hash as lowercase hex: the one the backend sent, or computed the same
+/// way when it is missing.
+pub fn for_entry(entry: &Entry) -> String {
+    if !entry.hash.is_empty() {
+        return entry.hash.clone();
+    }
+    bytes::to_hex(&digest(entry.entry_type, &entry.content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_backend_hash() {
+        let mut entry = Entry {
+            id: 1,
+            content: "hi".into(),
+            ..Default::default()
+        };
+        // Same test vector as hashing.zig
+        assert_eq!(
+            for_entry(&entry),
+            "50c3eef8a60a9c745a11c5cd295f9ddea43833cba5a195c3f76bc280e8c90d1f"
+        );
+        assert_ne!(digest(EntryType::Url, "hi"), digest(EntryType::Text, "hi"));
+
+        entry.hash = "ab".into();
+        assert_eq!(for_entry(&entry), "ab");
+    }
+}
@@ -0,0 +1,92 @@
+/// A `clipz://` URL other apps and scripts can open to jump into the history.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeepLink {
+    /// `clipz://search?q=foo` — open the popover filtered to a query.
+    Search(String),
+    /// `clipz://entry/42` — open the popover with an entry focused.
+    Entry(u64),
+}
+
+const SCHEME: &str = "clipz://";
+
+impl DeepLink {
+    pub fn parse(url: &str) -> Option<Self> {
+        let scheme = url.get(..SCHEME.len())?;
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return None;
+        }
+        let rest = &url[SCHEME.len()..];
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let path = path.trim_matches('/');
+
+        if path == "search" {
+            let q = query
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| *key == "q")
+                .map(|(_, value)| percent_decode(value))
+                .unwrap_or_default();
+            return Some(DeepLink::Search(q));
+        }
+        if let Some(id) = path.strip_prefix("entry/") {
+            return id.parse().ok().map(DeepLink::Entry);
+        }
+        None
+    }
+}
+
+/// Decodes `%XX` escapes and `+` (form-encoded space) in a query value.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let digit = |at: usize| bytes.get(at).and_then(|&b| (b as char).to_digit(16));
+                match (digit(i + 1), digit(i + 2)) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_search_and_entry_links() {
+        assert_eq!(
+            DeepLink::parse("clipz://search?q=git%20clone"),
+            Some(DeepLink::Search("git clone".into()))
+        );
+        assert_eq!(
+            DeepLink::parse("CLIPZ://search/?src=raycast&q=a+b"),
+            Some(DeepLink::Search("a b".into()))
+        );
+        assert_eq!(
+            DeepLink::parse("clipz://entry/42"),
+            Some(DeepLink::Entry(42))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_links() {
+        assert_eq!(DeepLink::parse("clipz://entry/abc"), None);
+        assert_eq!(DeepLink::parse("clipz://settings"), None);
+        assert_eq!(DeepLink::parse("https://search?q=x"), None);
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%+1%-f"), "% 1%-f");
+        assert_eq!(percent_decode("a%41"), "aA");
+    }
+}
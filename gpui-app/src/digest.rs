@@ -0,0 +1,193 @@
+use std::{collections::HashMap, path::PathBuf, process::Command, sync::Mutex};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    config::Config,
+    frecency::Usage,
+    protocol::{Entry, EntryType},
+};
+
+pub const WEEK_SECS: i64 = 7 * 24 * 60 * 60;
+// Apps listed in the Stats view
+const TOP_APPS: usize = 5;
+
+/// The digest as of the last history update, for the Stats view.
+pub static DIGEST: Mutex<Option<Digest>> = Mutex::new(None);
+
+/// The last seven days of copying, worked out locally from the history's metadata:
+/// when each entry was last copied, the app it came from and what was copied back.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Digest {
+    /// Entries last copied in the week.
+    pub copied: usize,
+    /// Entries copied back from the history in the week.
+    pub reused: usize,
+    /// The week's entries per type, most first.
+    pub by_type: Vec<(EntryType, usize)>,
+    /// Apps most copied from, most first; copies with no known app are left out.
+    pub top_apps: Vec<(String, usize)>,
+}
+
+impl Digest {
+    pub fn compute(entries: &[Entry], usage: &Usage, now_secs: i64) -> Self {
+        let since = now_secs - WEEK_SECS;
+        let week: Vec<&Entry> = entries
+            .iter()
+            .filter(|e| e.timestamp / 1000 >= since)
+            .collect();
+
+        let mut by_type: Vec<(EntryType, usize)> = EntryType::ALL
+            .iter()
+            .map(|&t| (t, week.iter().filter(|e| e.entry_type == t).count()))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        by_type.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        let mut apps: HashMap<&str, usize> = HashMap::new();
+        for entry in week.iter().filter(|e| !e.source_app.is_empty()) {
+            *apps.entry(&entry.source_app).or_insert(0) += 1;
+        }
+        let mut top_apps: Vec<(String, usize)> = apps
+            .into_iter()
+            .map(|(app, count)| (app.to_string(), count))
+            .collect();
+        // Ties go alphabetically, so the list doesn't reshuffle between updates
+        top_apps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_apps.truncate(TOP_APPS);
+
+        Self {
+            copied: week.len(),
+            reused: usage.used_since(since),
+            by_type,
+            top_apps,
+        }
+    }
+
+    /// One line, e.g. "You copied 412 items, top app: Safari, 18 items reused".
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("You copied {}", items(self.copied))];
+        if let Some((app, _)) = self.top_apps.first() {
+            parts.push(format!("top app: {}", app));
+        }
+        parts.push(format!("{} reused", items(self.reused)));
+        parts.join(", ")
+    }
+}
+
+fn items(count: usize) -> String {
+    match count {
+        1 => "1 item".to_string(),
+        n => format!("{} items", n),
+    }
+}
+
+fn sent_path() -> Option<PathBuf> {
+    Config::path().map(|config| config.with_file_name("digest-sent"))
+}
+
+/// Whether a week has passed since the last digest notification. The first check
+/// only starts the clock, so the first digest covers a whole week of use.
+pub fn notification_due(now_secs: i64) -> bool {
+    let Some(path) = sent_path() else {
+        return false;
+    };
+    match std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| text.trim().parse::<i64>().ok())
+    {
+        Some(sent) => now_secs - sent >= WEEK_SECS,
+        None => {
+            if let Err(e) = mark_sent(now_secs) {
+                eprintln!("Failed to save the digest time: {:#}", e);
+            }
+            false
+        }
+    }
+}
+
+pub fn mark_sent(now_secs: i64) -> Result<()> {
+    let path = sent_path().context("no config directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create config directory")?;
+    }
+    std::fs::write(&path, now_secs.to_string())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Shows `digest` as a system notification.
+pub fn notify(digest: &Digest) -> Result<()> {
+    // The text goes in as an argument, so app names need no escaping
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("/usr/bin/osascript");
+        command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 1 of argv) with title \"Clipz this week\"",
+            "-e",
+            "end run",
+        ]);
+        command
+    };
+    #[cfg(not(target_os = "macos"))]
+    let mut command = {
+        let mut command = Command::new("notify-send");
+        command.arg("Clipz this week");
+        command
+    };
+
+    let status = command
+        .arg(digest.summary())
+        .status()
+        .context("failed to show a notification")?;
+    if !status.success() {
+        bail!("notifier exited with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn entry(id: u64, age_days: i64, entry_type: EntryType, app: &str) -> Entry {
+        Entry {
+            id,
+            content: id.to_string(),
+            timestamp: (100 * DAY - age_days * DAY) * 1000,
+            entry_type,
+            source_app: app.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn digest_counts_the_last_week_only() {
+        let entries = [
+            entry(1, 0, EntryType::Text, "Safari"),
+            entry(2, 1, EntryType::Url, "Safari"),
+            entry(3, 2, EntryType::Text, "Terminal"),
+            entry(4, 3, EntryType::Text, ""),
+            entry(5, 10, EntryType::Image, "Preview"),
+        ];
+        let digest = Digest::compute(&entries, &Usage::default(), 100 * DAY);
+        assert_eq!(digest.copied, 4);
+        assert_eq!(digest.by_type, [(EntryType::Text, 3), (EntryType::Url, 1)]);
+        assert_eq!(
+            digest.top_apps,
+            [("Safari".to_string(), 2), ("Terminal".to_string(), 1)]
+        );
+        assert_eq!(
+            digest.summary(),
+            "You copied 4 items, top app: Safari, 0 items reused"
+        );
+        assert_eq!(
+            Digest::default().summary(),
+            "You copied 0 items, 0 items reused"
+        );
+    }
+}
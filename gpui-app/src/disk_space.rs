@@ -0,0 +1,160 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{atomic::AtomicBool, Mutex, OnceLock},
+    thread::{self, Thread},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::protocol::{Entry, EntryType};
+
+/// Where the backend writes copied images; see `IMAGE_STORAGE_DIR` in
+/// `image_storage.zig`. Only these files are deleted with their entries.
+pub const IMAGE_DIR: &str = "/tmp/clipz_images";
+
+/// Entries at least this big are offered for deletion while space is low.
+pub const LARGE_ENTRY_BYTES: u64 = 1024 * 1024;
+
+/// Free bytes on the fuller of the disks Clipz writes to, while under `[history]
+/// low_disk_mb`; None while there is room.
+pub static LOW_SPACE: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Set whenever `LOW_SPACE` changes so the event loop knows to tell the backend and
+/// repaint.
+pub static SPACE_UPDATED: AtomicBool = AtomicBool::new(false);
+
+// Free space rarely changes fast enough to need checking more often
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// Most entries one click deletes, so a cleanup never takes out half the history
+const PURGE_LIMIT: usize = 20;
+
+static WATCHER: OnceLock<Thread> = OnceLock::new();
+
+/// Checks free space on a worker thread every minute, keeping `LOW_SPACE` up to date.
+/// A threshold of 0 turns the check off.
+pub fn watch(threshold_mb: u64) {
+    if threshold_mb == 0 {
+        return;
+    }
+    let threshold = threshold_mb * 1024 * 1024;
+    let watcher = thread::spawn(move || loop {
+        check(threshold);
+        thread::park_timeout(CHECK_INTERVAL);
+    });
+    let _ = WATCHER.set(watcher.thread().clone());
+}
+
+/// Checks again straight away, after something has freed up space.
+pub fn recheck() {
+    if let Some(watcher) = WATCHER.get() {
+        watcher.unpark();
+    }
+}
+
+fn check(threshold: u64) {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let free = [Some(PathBuf::from("/tmp")), home]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| {
+            free_bytes(&path)
+                .map_err(|e| eprintln!("Failed to check free space on {}: {:#}", path.display(), e))
+                .ok()
+        })
+        .min();
+    let low = free.filter(|&bytes| bytes < threshold);
+    let mut current = LOW_SPACE.lock().unwrap();
+    // Whole megabytes, so the warning isn't repainted for every byte written
+    if current.map(|b| b / (1024 * 1024)) != low.map(|b| b / (1024 * 1024)) {
+        *current = low;
+        crate::wake::set(&SPACE_UPDATED);
+    }
+}
+
+/// Bytes available to the user on the disk holding `path`, as `df` reports them.
+pub fn free_bytes(path: &Path) -> Result<u64> {
+    let output = Command::new("df")
+        .arg("-Pk")
+        .arg(path)
+        .output()
+        .context("failed to run df")?;
+    if !output.status.success() {
+        bail!("df exited with {}", output.status);
+    }
+    parse_df(&String::from_utf8_lossy(&output.stdout)).context("unexpected df output")
+}
+
+/// The Available column of POSIX `df -Pk` output, in bytes.
+fn parse_df(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let kilobytes: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Disk space deleting `entry` would give back: its image file if the backend saved
+/// one, otherwise its text.
+pub fn entry_bytes(entry: &Entry) -> u64 {
+    match entry.entry_type {
+        EntryType::Image if entry.content.starts_with(IMAGE_DIR) => {
+            std::fs::metadata(&entry.content).map_or(0, |m| m.len())
+        }
+        EntryType::Image | EntryType::File => 0,
+        _ => entry.content.len() as u64,
+    }
+}
+
+/// The oldest entries of `LARGE_ENTRY_BYTES` or more, with the bytes they take up.
+/// Pinned entries and the one on the clipboard are never offered.
+pub fn large_entries(entries: &[Entry]) -> (Vec<u64>, u64) {
+    let mut large: Vec<(&Entry, u64)> = entries
+        .iter()
+        .filter(|e| !e.pinned && !e.is_current)
+        .map(|e| (e, entry_bytes(e)))
+        .filter(|&(_, bytes)| bytes >= LARGE_ENTRY_BYTES)
+        .collect();
+    large.sort_by_key(|(e, _)| e.timestamp);
+    large.truncate(PURGE_LIMIT);
+    (
+        large.iter().map(|(e, _)| e.id).collect(),
+        large.iter().map(|(_, bytes)| bytes).sum(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, timestamp: i64, len: usize, pinned: bool) -> Entry {
+        Entry {
+            id,
+            content: "x".repeat(len),
+            timestamp,
+            pinned,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn df_output_gives_available_bytes() {
+        let output = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                      /dev/disk3s5 482797652 401239588 51200 89% /System/Volumes/Data\n";
+        assert_eq!(parse_df(output), Some(51200 * 1024));
+        assert_eq!(parse_df("Filesystem 1024-blocks Used Available\n"), None);
+    }
+
+    #[test]
+    fn large_entries_are_the_oldest_unpinned_ones() {
+        let big = LARGE_ENTRY_BYTES as usize;
+        let entries = [
+            entry(1, 300, big, false),
+            entry(2, 200, 10, false),
+            entry(3, 100, big, true),
+            entry(4, 50, big * 2, false),
+        ];
+        let (ids, bytes) = large_entries(&entries);
+        assert_eq!(ids, [4, 1]);
+        assert_eq!(bytes, LARGE_ENTRY_BYTES * 3);
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use gpui::{point, px, size, Bounds, Pixels};
+
+use crate::config::DockEdge;
+
+/// Width of the strip kept at the docked edge; hovering it opens the popover.
+pub const STRIP_WIDTH: f32 = 4.0;
+
+/// How long the docked popover takes to slide out.
+pub const SLIDE_DURATION: Duration = Duration::from_millis(180);
+
+/// The hover strip along `edge` of `screen`, as tall as the popover.
+pub fn strip_bounds(edge: DockEdge, screen: Bounds<Pixels>, height: f32) -> Option<Bounds<Pixels>> {
+    edge_bounds(edge, screen, STRIP_WIDTH, height)
+}
+
+/// The popover flush against `edge`, vertically centered and no taller than the screen.
+pub fn popover_bounds(
+    edge: DockEdge,
+    screen: Bounds<Pixels>,
+    width: f32,
+    height: f32,
+) -> Option<Bounds<Pixels>> {
+    edge_bounds(edge, screen, width, height)
+}
+
+/// How far the popover's content is shifted towards `edge` when `delta` of the way
+/// through sliding out; it starts just past the edge and ends in place.
+pub fn slide_offset(edge: DockEdge, width: f32, delta: f32) -> f32 {
+    let hidden = width * (1.0 - delta);
+    match edge {
+        DockEdge::None => 0.0,
+        DockEdge::Left => -hidden,
+        DockEdge::Right => hidden,
+    }
+}
+
+fn edge_bounds(
+    edge: DockEdge,
+    screen: Bounds<Pixels>,
+    width: f32,
+    height: f32,
+) -> Option<Bounds<Pixels>> {
+    let screen_x = f32::from(screen.origin.x);
+    let screen_y = f32::from(screen.origin.y);
+    let screen_width = f32::from(screen.size.width);
+    let screen_height = f32::from(screen.size.height);
+
+    let x = match edge {
+        DockEdge::None => return None,
+        DockEdge::Left => screen_x,
+        DockEdge::Right => screen_x + screen_width - width,
+    };
+    let height = height.min(screen_height);
+    let y = screen_y + (screen_height - height) / 2.0;
+    Some(Bounds {
+        origin: point(px(x), px(y)),
+        size: size(px(width), px(height)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docked_windows_hug_the_chosen_edge() {
+        let screen = Bounds {
+            origin: point(px(0.0), px(0.0)),
+            size: size(px(1440.0), px(900.0)),
+        };
+        assert_eq!(strip_bounds(DockEdge::None, screen, 400.0), None);
+
+        let strip = strip_bounds(DockEdge::Right, screen, 400.0).unwrap();
+        assert_eq!(strip.origin, point(px(1436.0), px(250.0)));
+
+        let popover = popover_bounds(DockEdge::Left, screen, 320.0, 1200.0).unwrap();
+        assert_eq!(popover.origin, point(px(0.0), px(0.0)));
+        assert_eq!(popover.size, size(px(320.0), px(900.0)));
+
+        assert_eq!(slide_offset(DockEdge::Left, 320.0, 0.0), -320.0);
+        assert_eq!(slide_offset(DockEdge::Right, 320.0, 0.5), 160.0);
+        assert_eq!(slide_offset(DockEdge::Right, 320.0, 1.0), 0.0);
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use crate::protocol::Entry;
+
+/// Starts a native drag of `entry` from the popover into another app.
+///
+/// gpui's drag and drop only works between the app's own elements, so this hands the
+/// current mouse event to AppKit and starts an `NSDraggingSession` instead. Text entries
+/// drag as plain strings; image and file entries drag as file URLs so Finder, Slack and
+/// friends receive the file itself.
+#[cfg(target_os = "macos")]
+pub fn begin(entry: &Entry) -> Result<()> {
+    macos::begin(entry)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn begin(_entry: &Entry) -> Result<()> {
+    anyhow::bail!("dragging entries out is only supported on macOS")
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::{path::Path, sync::OnceLock};
+
+    use anyhow::{bail, Result};
+    use cocoa::{
+        base::{id, nil},
+        foundation::{NSArray, NSPoint, NSRect, NSSize, NSString},
+    };
+    use objc::{
+        class,
+        declare::ClassDecl,
+        msg_send,
+        runtime::{Object, Protocol, Sel},
+        sel, sel_impl,
+    };
+
+    use crate::protocol::{Entry, EntryType};
+
+    const NS_DRAG_OPERATION_COPY: u64 = 1;
+    const DRAG_ICON_SIZE: f64 = 32.0;
+
+    extern "C" fn source_operation_mask(
+        _this: &Object,
+        _cmd: Sel,
+        _session: id,
+        _context: i64,
+    ) -> u64 {
+        NS_DRAG_OPERATION_COPY
+    }
+
+    /// The `NSDraggingSource` for every drag. Created once and kept for the app's
+    /// lifetime, since dragging sessions don't retain their source.
+    fn drag_source() -> id {
+        static SOURCE: OnceLock<usize> = OnceLock::new();
+        *SOURCE.get_or_init(|| unsafe {
+            let mut decl = ClassDecl::new("ClipzDragSource", class!(NSObject)).unwrap();
+            if let Some(protocol) = Protocol::get("NSDraggingSource") {
+                decl.add_protocol(protocol);
+            }
+            decl.add_method(
+                sel!(draggingSession:sourceOperationMaskForDraggingContext:),
+                source_operation_mask as extern "C" fn(&Object, Sel, id, i64) -> u64,
+            );
+            let class = decl.register();
+            let source: id = msg_send![class, new];
+            source as usize
+        }) as id
+    }
+
+    fn ns_string(s: &str) -> id {
+        unsafe {
+            let string = NSString::alloc(nil).init_str(s);
+            msg_send![string, autorelease]
+        }
+    }
+
+    pub fn begin(entry: &Entry) -> Result<()> {
+        unsafe {
+            let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+            let event: id = msg_send![ns_app, currentEvent];
+            if event.is_null() {
+                bail!("no mouse event to start the drag from");
+            }
+            let window: id = msg_send![event, window];
+            if window.is_null() {
+                bail!("mouse event has no window");
+            }
+            let view: id = msg_send![window, contentView];
+
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            // Files copied together drag together, one item each
+            let paths = match entry.entry_type {
+                EntryType::Image => vec![entry.content.as_str()],
+                EntryType::File => entry.file_paths(),
+                _ => Vec::new(),
+            };
+            let mut writers: Vec<(id, id)> = Vec::new();
+            for path in &paths {
+                if !Path::new(path).exists() {
+                    bail!("{} no longer exists", path);
+                }
+                let path = ns_string(path);
+                let url: id = msg_send![class!(NSURL), fileURLWithPath: path];
+                let icon: id = msg_send![workspace, iconForFile: path];
+                writers.push((url, icon));
+            }
+            if writers.is_empty() {
+                let icon: id = msg_send![workspace, iconForFileType: ns_string("txt")];
+                writers.push((ns_string(&entry.content), icon));
+            }
+
+            let location: NSPoint = msg_send![event, locationInWindow];
+            let location: NSPoint = msg_send![view, convertPoint: location fromView: nil];
+            let frame = NSRect::new(
+                NSPoint::new(
+                    location.x - DRAG_ICON_SIZE / 2.0,
+                    location.y - DRAG_ICON_SIZE / 2.0,
+                ),
+                NSSize::new(DRAG_ICON_SIZE, DRAG_ICON_SIZE),
+            );
+            let items: Vec<id> = writers
+                .into_iter()
+                .map(|(writer, icon)| {
+                    let item: id = msg_send![class!(NSDraggingItem), alloc];
+                    let item: id = msg_send![item, initWithPasteboardWriter: writer];
+                    let item: id = msg_send![item, autorelease];
+                    let _: () = msg_send![item, setDraggingFrame: frame contents: icon];
+                    item
+                })
+                .collect();
+            let items = NSArray::arrayWithObjects(nil, &items);
+            let _: id = msg_send![view, beginDraggingSessionWithItems: items
+                                                                event: event
+                                                               source: drag_source()];
+        }
+        Ok(())
+    }
+}
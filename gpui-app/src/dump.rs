@@ -0,0 +1,140 @@
+use std::{io::Write, process::Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    backend_command,
+    config::Config,
+    protocol::{BackendMessage, Entry, Envelope},
+};
+
+/// Output of `--dump`: the whole history as one JSON array, or one entry per line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    Json,
+    Ndjson,
+}
+
+impl DumpFormat {
+    /// `--dump` or `--dump=json` for a JSON array, `--dump=ndjson` for newline-delimited
+    /// JSON. `None` when the flag isn't given, so the app starts normally.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>> {
+        for arg in args {
+            let format = match arg.as_str() {
+                "--dump" | "--dump=json" => DumpFormat::Json,
+                "--dump=ndjson" => DumpFormat::Ndjson,
+                other if other.starts_with("--dump=") => {
+                    bail!("unknown dump format {:?} (expected json or ndjson)", other)
+                }
+                _ => continue,
+            };
+            return Ok(Some(format));
+        }
+        Ok(None)
+    }
+}
+
+/// Prints the entries in the usual history file to stdout and exits, so
+/// `clipz-gpui --dump | jq` works without opening the UI. The backend reads the file
+/// with `--dump`, which neither monitors the clipboard nor writes anything, so a
+/// running Clipz is left alone.
+pub fn run(format: DumpFormat) -> ! {
+    let config = Config::load();
+    let result = fetch_entries(&config)
+        .and_then(|entries| write_entries(&mut std::io::stdout().lock(), &entries, format));
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("clipz: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn fetch_entries(config: &Config) -> Result<Vec<Entry>> {
+    let output = backend_command(config)?
+        .arg("--dump")
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to run clipz backend")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "backend exited with {}: {}",
+            output.status,
+            stderr.lines().last().unwrap_or_default()
+        );
+    }
+    parse_entries(&output.stdout)
+}
+
+fn parse_entries(output: &[u8]) -> Result<Vec<Entry>> {
+    let envelope: Envelope =
+        serde_json::from_slice(output).context("failed to read the backend's entries")?;
+    match envelope.message {
+        BackendMessage::Entries { data, .. } => Ok(data),
+        other => bail!("unexpected response from backend: {:?}", other),
+    }
+}
+
+pub fn write_entries(out: &mut impl Write, entries: &[Entry], format: DumpFormat) -> Result<()> {
+    match format {
+        DumpFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, entries)?;
+            writeln!(out)?;
+        }
+        DumpFormat::Ndjson => {
+            for entry in entries {
+                serde_json::to_writer(&mut *out, entry)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::EntryType;
+
+    #[test]
+    fn ndjson_writes_one_backend_shaped_entry_per_line() {
+        let args = |s: &[&str]| s.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(DumpFormat::from_args(&args(&[])).unwrap(), None);
+        assert_eq!(
+            DumpFormat::from_args(&args(&["--dump=ndjson"])).unwrap(),
+            Some(DumpFormat::Ndjson)
+        );
+        assert!(DumpFormat::from_args(&args(&["--dump=csv"])).is_err());
+
+        let entry = Entry {
+            id: 3,
+            content: "hi".into(),
+            timestamp: 1000,
+            entry_type: EntryType::Url,
+            is_current: true,
+            tags: vec!["work".into()],
+            hash: "50c3".into(),
+            ..Default::default()
+        };
+        let dumped = parse_entries(
+            br#"{"type":"entries","total":1,"offset":0,"data":[{"id":3,"content":"hi","timestamp":1000,"type":"url","isCurrent":true,"pinned":false,"tags":["work"],"hash":"50c3"}]}
+"#,
+        )
+        .unwrap();
+        assert_eq!(dumped, std::slice::from_ref(&entry));
+        assert!(parse_entries(b"").is_err());
+
+        let mut out = Vec::new();
+        write_entries(&mut out, &[entry.clone(), entry], DumpFormat::Ndjson).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            r#"{"id":3,"content":"hi","timestamp":1000,"type":"url","isCurrent":true,"pinned":false,"tags":["work"],"hash":"50c3"}"#
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::protocol::{Entry, EntryType};
+
+/// Editor for a copy of an entry's text, opened with Cmd+E or from the entry menu.
+///
+/// While it is open, typing edits `text` at `cursor` instead of the search query. The
+/// stored entry is never changed; the edit is copied or saved as a new entry.
+pub struct EntryEditor {
+    pub id: u64,
+    pub entry_type: EntryType,
+    pub text: String,
+    /// Byte offset into `text`, always on a character boundary.
+    pub cursor: usize,
+    original: String,
+}
+
+impl EntryEditor {
+    /// `None` for images, files and colors, whose content isn't text to edit. The
+    /// cursor starts at the end.
+    pub fn new(entry: &Entry) -> Option<Self> {
+        matches!(entry.entry_type, EntryType::Text | EntryType::Url).then(|| Self {
+            id: entry.id,
+            entry_type: entry.entry_type,
+            text: entry.content.clone(),
+            cursor: entry.content.len(),
+            original: entry.content.clone(),
+        })
+    }
+
+    /// Whether the text differs from the entry's, and isn't blank.
+    pub fn is_changed(&self) -> bool {
+        self.text != self.original && !self.text.trim().is_empty()
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(prev) = self.prev_boundary() {
+            self.text.replace_range(prev..self.cursor, "");
+            self.cursor = prev;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if let Some(next) = self.next_boundary() {
+            self.text.replace_range(self.cursor..next, "");
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.prev_boundary().unwrap_or(0);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary().unwrap_or(self.text.len());
+    }
+
+    pub fn line_start(&mut self) {
+        self.cursor = self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1);
+    }
+
+    pub fn line_end(&mut self) {
+        self.cursor = self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |i| self.cursor + i);
+    }
+
+    /// Moves to the same column of the line above, or its end when it is shorter.
+    pub fn up(&mut self) {
+        let start = self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1);
+        if start == 0 {
+            self.cursor = 0;
+            return;
+        }
+        let column = self.text[start..self.cursor].chars().count();
+        let above = self.text[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+        self.cursor = Self::column_offset(&self.text, above, start - 1, column);
+    }
+
+    /// Moves to the same column of the line below, or its end when it is shorter.
+    pub fn down(&mut self) {
+        let Some(end) = self.text[self.cursor..].find('\n').map(|i| self.cursor + i) else {
+            self.cursor = self.text.len();
+            return;
+        };
+        let start = self.text[..self.cursor].rfind('\n').map_or(0, |i| i + 1);
+        let column = self.text[start..self.cursor].chars().count();
+        let below = end + 1;
+        let below_end = self.text[below..]
+            .find('\n')
+            .map_or(self.text.len(), |i| below + i);
+        self.cursor = Self::column_offset(&self.text, below, below_end, column);
+    }
+
+    /// The text before and after the cursor, for drawing it in between.
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.text.split_at(self.cursor)
+    }
+
+    fn column_offset(text: &str, start: usize, end: usize, column: usize) -> usize {
+        text[start..end]
+            .char_indices()
+            .nth(column)
+            .map_or(end, |(i, _)| start + i)
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .last()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(content: &str) -> EntryEditor {
+        EntryEditor::new(&Entry {
+            id: 1,
+            content: content.into(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut editor = editor("git comit -m wip");
+        assert!(!editor.is_changed());
+        editor.line_start();
+        for _ in 0..7 {
+            editor.right();
+        }
+        editor.insert("m");
+        assert_eq!(editor.text, "git commit -m wip");
+        assert!(editor.is_changed());
+
+        editor.line_end();
+        editor.backspace();
+        editor.backspace();
+        editor.backspace();
+        editor.insert("“fix”");
+        assert_eq!(editor.text, "git commit -m “fix”");
+        editor.left();
+        editor.backspace();
+        editor.delete();
+        assert_eq!(editor.text, "git commit -m “fi");
+
+        editor.line_start();
+        editor.delete();
+        editor.left();
+        editor.backspace();
+        assert_eq!(editor.split_at_cursor(), ("", "it commit -m “fi"));
+    }
+
+    #[test]
+    fn up_and_down_keep_the_column() {
+        let mut editor = editor("ab\nlonger line\nxyz");
+        editor.up();
+        assert_eq!(editor.split_at_cursor().1, "ger line\nxyz");
+        editor.up();
+        assert_eq!(editor.split_at_cursor().1, "\nlonger line\nxyz");
+        editor.up();
+        assert_eq!(editor.cursor, 0);
+        editor.down();
+        assert_eq!(editor.split_at_cursor().1, "longer line\nxyz");
+        editor.right();
+        editor.down();
+        assert_eq!(editor.split_at_cursor().1, "yz");
+        editor.down();
+        assert_eq!(editor.cursor, editor.text.len());
+
+        editor.text.clear();
+        editor.cursor = 0;
+        assert!(!editor.is_changed());
+    }
+}
@@ -0,0 +1,265 @@
+use std::{fmt::Write as _, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    content_hash,
+    navigation::civil_from_days,
+    protocol::{Entry, EntryType, ImportEntry},
+};
+
+// Content per `import-entries` batch: escaped, it stays under the backend's line
+// limit of twice its 512 KB fetch size
+const IMPORT_BATCH_BYTES: usize = 256 * 1024;
+
+/// File format for "Export…", picked from the extension chosen in the save dialog.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    /// CSV for a `.csv` path, JSON for anything else.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => ExportFormat::Csv,
+            _ => ExportFormat::Json,
+        }
+    }
+}
+
+/// Writes `entries` to `path` as JSON (the same shape as `--dump`) or CSV. Both carry
+/// each entry's content hash.
+pub fn write(path: &Path, entries: &[Entry]) -> Result<()> {
+    let text = match ExportFormat::for_path(path) {
+        ExportFormat::Json => {
+            let hashed: Vec<Entry> = entries
+                .iter()
+                .map(|entry| Entry {
+                    hash: content_hash::for_entry(entry),
+                    ..entry.clone()
+                })
+                .collect();
+            serde_json::to_string_pretty(&hashed)? + "\n"
+        }
+        ExportFormat::Csv => to_csv(entries),
+    };
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads entries from a JSON export, in the order they were exported.
+///
+/// The file must be a JSON array of entries. Entries the backend would reject are
+/// dropped: empty ones, and images whose file does not exist on this machine. Tags
+/// that aren't valid backend tags are dropped from their entry.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse(&text).with_context(|| format!("{} is not a Clipz JSON export", path.display()))
+}
+
+fn parse(text: &str) -> Result<Vec<Entry>> {
+    let entries: Vec<Entry> = serde_json::from_str(text)?;
+    if entries.is_empty() {
+        bail!("no entries");
+    }
+    Ok(entries
+        .into_iter()
+        .filter(|entry| !entry.content.is_empty())
+        .filter(|entry| entry.entry_type != EntryType::Image || Path::new(&entry.content).exists())
+        .map(|mut entry| {
+            entry.tags.retain(|tag| is_valid_tag(tag));
+            entry
+        })
+        .collect())
+}
+
+/// Splits entries from `read` into `import-entries` batches the backend takes in one
+/// line each, in the same order; an entry bigger than a batch goes on its own.
+pub fn import_batches(entries: Vec<Entry>) -> Vec<Vec<ImportEntry>> {
+    let mut batches: Vec<Vec<ImportEntry>> = Vec::new();
+    let mut size = 0;
+    for entry in entries {
+        let len = entry.content.len();
+        match batches.last_mut() {
+            Some(batch) if size + len <= IMPORT_BATCH_BYTES => {
+                size += len;
+                batch.push(entry.into());
+            }
+            _ => {
+                size = len;
+                batches.push(vec![entry.into()]);
+            }
+        }
+    }
+    batches
+}
+
+/// What an import did, added up over its batches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    /// Already in the history, and left as they were there.
+    pub existing: usize,
+    /// Left out because the history was full.
+    pub skipped: usize,
+}
+
+impl ImportReport {
+    /// One line for the popover, e.g. "Imported 40 entries, 3 already in the history".
+    pub fn summary(&self) -> String {
+        let noun = if self.added == 1 { "entry" } else { "entries" };
+        let mut summary = format!("Imported {} {}", self.added, noun);
+        if self.existing > 0 {
+            let _ = write!(summary, ", {} already in the history", self.existing);
+        }
+        if self.skipped > 0 {
+            let _ = write!(
+                summary,
+                ", {} left out as the history is full",
+                self.skipped
+            );
+        }
+        summary
+    }
+}
+
+/// Mirrors the backend's `isValidTag`.
+fn is_valid_tag(tag: &str) -> bool {
+    (1..=32).contains(&tag.len())
+        && tag
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// One row per entry with a header; `copied_at` is the timestamp as UTC ISO 8601 so
+/// spreadsheets can sort on it.
+fn to_csv(entries: &[Entry]) -> String {
+    let mut csv = String::from("id,type,timestamp,copied_at,pinned,tags,hash,content\r\n");
+    for entry in entries {
+        let _ = write!(
+            csv,
+            "{},{},{},{},{},{},{},{}\r\n",
+            entry.id,
+            crate::type_label_for_type(&entry.entry_type),
+            entry.timestamp,
+            iso_utc(entry.timestamp),
+            entry.pinned,
+            csv_field(&entry.tags.join(" ")),
+            content_hash::for_entry(entry),
+            csv_field(&entry.content)
+        );
+    }
+    csv
+}
+
+/// RFC 4180 quoting: fields with commas, quotes or line breaks are wrapped in quotes,
+/// with inner quotes doubled.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn iso_utc(timestamp_ms: i64) -> String {
+    let secs = timestamp_ms.div_euclid(1000);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_content_and_formats_dates() {
+        let entry = Entry {
+            id: 7,
+            content: "say \"hi\",\nthen leave".into(),
+            timestamp: 1_700_000_000_123,
+            pinned: true,
+            tags: vec!["work".into(), "draft".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            to_csv(&[entry]),
+            "id,type,timestamp,copied_at,pinned,tags,hash,content\r\n\
+             7,Text,1700000000123,2023-11-14T22:13:20Z,true,work draft,\
+             9437aee4f67b4e9cc1b1d369d036e1b63d9886ce7aed0d732629dfdb7a8e06a8,\"say \"\"hi\"\",\nthen leave\"\r\n"
+        );
+        assert_eq!(
+            ExportFormat::for_path(Path::new("/tmp/history.CSV")),
+            ExportFormat::Csv
+        );
+        assert_eq!(
+            ExportFormat::for_path(Path::new("/tmp/history")),
+            ExportFormat::Json
+        );
+    }
+
+    #[test]
+    fn import_keeps_valid_entries_in_export_order() {
+        let json = r#"[
+            {"id":3,"content":"newest","timestamp":3,"type":"text","pinned":true,"tags":["work","not valid"]},
+            {"id":2,"content":"","timestamp":2,"type":"text"},
+            {"id":1,"content":"/nonexistent/clipz.png","timestamp":1,"type":"image"},
+            {"id":0,"content":"https://example.com","timestamp":0,"type":"url"}
+        ]"#;
+        let entries = parse(json).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].content, "newest");
+        assert!(entries[0].pinned);
+        assert_eq!(entries[0].tags, ["work"]);
+        assert_eq!(entries[1].entry_type, EntryType::Url);
+
+        assert!(parse("[]").is_err());
+        assert!(parse("id,type\r\n").is_err());
+    }
+
+    #[test]
+    fn imports_go_in_batches_under_the_line_limit() {
+        let big = "x".repeat(IMPORT_BATCH_BYTES - 10);
+        let entries = vec![
+            Entry::text("first"),
+            Entry::text(&big),
+            Entry::text(&"y".repeat(IMPORT_BATCH_BYTES + 1)),
+            Entry::text("last"),
+        ];
+        let batches = import_batches(entries);
+        let contents: Vec<Vec<usize>> = batches
+            .iter()
+            .map(|batch| batch.iter().map(|entry| entry.content.len()).collect())
+            .collect();
+        assert_eq!(
+            contents,
+            [
+                vec![5, IMPORT_BATCH_BYTES - 10],
+                vec![IMPORT_BATCH_BYTES + 1],
+                vec![4]
+            ]
+        );
+        assert!(import_batches(Vec::new()).is_empty());
+
+        let report = ImportReport {
+            added: 1,
+            existing: 3,
+            skipped: 2,
+        };
+        assert_eq!(
+            report.summary(),
+            "Imported 1 entry, 3 already in the history, 2 left out as the history is full"
+        );
+    }
+}
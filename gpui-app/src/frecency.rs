@@ -0,0 +1,146 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, protocol::Entry};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+struct UseRecord {
+    count: u32,
+    /// Unix seconds of the last selection.
+    last_used: i64,
+}
+
+/// How often and how recently each entry was copied back, for frecency ordering.
+///
+/// Kept in `usage.json` next to the config file, keyed by entry id (ids are stable
+/// across launches because the backend persists them).
+#[derive(Debug, Default)]
+pub struct Usage {
+    records: HashMap<u64, UseRecord>,
+}
+
+impl Usage {
+    fn path() -> Option<PathBuf> {
+        Config::path().map(|config| config.with_file_name("usage.json"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let records = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { records }
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().context("no config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("failed to create config directory")?;
+        }
+        let text = serde_json::to_string(&self.records)?;
+        std::fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn record(&mut self, id: u64, now_secs: i64) {
+        let record = self.records.entry(id).or_default();
+        record.count += 1;
+        record.last_used = now_secs;
+        if let Err(e) = self.save() {
+            eprintln!("Failed to save usage: {:#}", e);
+        }
+    }
+
+    /// Drops records for entries no longer in the history.
+    pub fn prune(&mut self, entries: &[Entry]) {
+        let before = self.records.len();
+        self.records
+            .retain(|id, _| entries.iter().any(|e| e.id == *id));
+        if self.records.len() != before {
+            if let Err(e) = self.save() {
+                eprintln!("Failed to save usage: {:#}", e);
+            }
+        }
+    }
+
+    /// How many entries were last copied back from the history at or after `since_secs`.
+    pub fn used_since(&self, since_secs: i64) -> usize {
+        self.records
+            .values()
+            .filter(|record| record.last_used >= since_secs)
+            .count()
+    }
+
+    /// Firefox-style frecency: a recency weight for the last time the entry was copied
+    /// or used, multiplied by how many times it was used (plus one for the copy itself).
+    fn score(&self, entry: &Entry, now_secs: i64) -> u64 {
+        let record = self.records.get(&entry.id).copied().unwrap_or_default();
+        let last_seen = (entry.timestamp / 1000).max(record.last_used);
+        let age_hours = (now_secs - last_seen).max(0) / 3600;
+        let weight = match age_hours {
+            0..4 => 100,
+            4..24 => 70,
+            24..168 => 50,
+            168..720 => 30,
+            _ => 10,
+        };
+        weight * (record.count as u64 + 1)
+    }
+
+    /// Orders `entries` by frecency, keeping the current entry first and pinned entries
+    /// ahead of the rest like the backend does. Ties keep their existing order.
+    pub fn sort(&self, entries: &mut [Entry], now_secs: i64) {
+        entries.sort_by_key(|e| {
+            (
+                !e.is_current,
+                !e.pinned,
+                std::cmp::Reverse(self.score(e, now_secs)),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::EntryType;
+
+    const HOUR: i64 = 3600;
+
+    fn entry(id: u64, age_secs: i64, now: i64) -> Entry {
+        Entry {
+            id,
+            content: id.to_string(),
+            timestamp: (now - age_secs) * 1000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frequently_used_entries_outrank_newer_ones() {
+        let now = 1_000 * HOUR;
+        let mut usage = Usage::default();
+        for _ in 0..3 {
+            usage.records.entry(3).or_default().count += 1;
+        }
+        usage.records.get_mut(&3).unwrap().last_used = now - 48 * HOUR;
+
+        let mut entries = vec![
+            entry(1, HOUR, now),
+            entry(2, 2 * HOUR, now),
+            entry(3, 200 * HOUR, now),
+        ];
+        entries[1].pinned = true;
+        usage.sort(&mut entries, now);
+
+        let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [2, 3, 1]);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    cmp::Ordering,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::protocol::PROTOCOL_VERSION;
+
+/// Restarts tried in a row before the backend is given up on.
+const MAX_RESTARTS: u32 = 5;
+// The first restart waits this long, doubling with each attempt after it
+const FIRST_RESTART_DELAY: Duration = Duration::from_secs(1);
+// A backend that stays up this long has recovered; its next crash starts the count over
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+// How long a failed command keeps the dot amber
+const ERROR_WINDOW: Duration = Duration::from_secs(30);
+
+/// The backend's state as the popover shows it, next to the search field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackendStatus {
+    /// Started but not ready yet.
+    Starting,
+    Ready,
+    /// Ready, but commands failed in the last 30 seconds; the last one's message.
+    Errors {
+        count: usize,
+        last: String,
+    },
+    /// The backend went away and is being started again.
+    Reconnecting {
+        attempt: u32,
+    },
+    /// The backend can't be started, or kept stopping; the list stays as it was.
+    Dead {
+        reason: String,
+    },
+    /// The backend speaks another protocol version; None for one from before `hello`.
+    Mismatch {
+        backend: Option<u32>,
+    },
+}
+
+impl BackendStatus {
+    /// A few words for the search field, or None when all is well.
+    pub fn label(&self) -> Option<String> {
+        match self {
+            BackendStatus::Ready => None,
+            BackendStatus::Starting => Some("Starting\u{2026}".into()),
+            BackendStatus::Errors { count: 1, last } => Some(last.clone()),
+            BackendStatus::Errors { count, .. } => Some(format!("{} commands failed", count)),
+            BackendStatus::Reconnecting { attempt: 1 } => Some("Reconnecting\u{2026}".into()),
+            BackendStatus::Reconnecting { attempt } => {
+                Some(format!("Reconnecting, attempt {}\u{2026}", attempt))
+            }
+            BackendStatus::Dead { reason } => Some(format!("Backend stopped: {}", reason)),
+            BackendStatus::Mismatch { backend } => {
+                match backend.map_or(Ordering::Less, |v| v.cmp(&PROTOCOL_VERSION)) {
+                    Ordering::Greater => Some("Backend is newer, update Clipz".into()),
+                    _ => Some("Backend is outdated, run zig build".into()),
+                }
+            }
+        }
+    }
+}
+
+/// The status as of the event loop's last pass, for the popover.
+pub static STATUS: Mutex<BackendStatus> = Mutex::new(BackendStatus::Starting);
+
+/// Tracks the backend across crashes and restarts, deciding when to start it again.
+pub struct Health {
+    status: BackendStatus,
+    errors: Vec<(Instant, String)>,
+    restarts: u32,
+    started_at: Instant,
+    restart_at: Option<Instant>,
+}
+
+impl Health {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            status: BackendStatus::Starting,
+            errors: Vec::new(),
+            restarts: 0,
+            started_at: now,
+            restart_at: None,
+        }
+    }
+
+    /// The backend couldn't be started at all.
+    pub fn failed_to_start(&mut self, reason: String) {
+        self.status = BackendStatus::Dead { reason };
+        self.restart_at = None;
+    }
+
+    pub fn ready(&mut self) {
+        self.status = BackendStatus::Ready;
+    }
+
+    /// The backend's answer to `hello`: its protocol version, or None when it didn't
+    /// know the command. Anything but this frontend's version is a mismatch.
+    pub fn handshake(&mut self, backend: Option<u32>) {
+        if backend != Some(PROTOCOL_VERSION) {
+            self.status = BackendStatus::Mismatch { backend };
+        }
+    }
+
+    pub fn command_failed(&mut self, message: String, now: Instant) {
+        self.errors.push((now, message));
+    }
+
+    /// The backend exited or stopped reading commands. Schedules a restart, waiting
+    /// longer each time, until too many in a row have failed. Noticing again before the
+    /// restart changes nothing.
+    pub fn lost(&mut self, reason: String, now: Instant) {
+        if self.restart_at.is_some() {
+            return;
+        }
+        if now.duration_since(self.started_at) >= STABLE_AFTER {
+            self.restarts = 0;
+        }
+        if self.restarts >= MAX_RESTARTS {
+            self.failed_to_start(reason);
+            return;
+        }
+        self.restarts += 1;
+        self.status = BackendStatus::Reconnecting {
+            attempt: self.restarts,
+        };
+        self.restart_at = Some(now + FIRST_RESTART_DELAY * 2u32.pow(self.restarts - 1));
+    }
+
+    /// Whether a scheduled restart is due; it is then considered under way.
+    pub fn restart_due(&mut self, now: Instant) -> bool {
+        if self.restart_at.is_some_and(|at| now >= at) {
+            self.restart_at = None;
+            self.started_at = now;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_dead(&self) -> bool {
+        matches!(self.status, BackendStatus::Dead { .. })
+    }
+
+    /// The status to show at `now`: failed commands are only mentioned while recent.
+    pub fn status(&mut self, now: Instant) -> BackendStatus {
+        self.errors
+            .retain(|(at, _)| now.duration_since(*at) < ERROR_WINDOW);
+        match (&self.status, self.errors.last()) {
+            (BackendStatus::Ready, Some((_, last))) => BackendStatus::Errors {
+                count: self.errors.len(),
+                last: last.clone(),
+            },
+            (status, _) => status.clone(),
+        }
+    }
+
+    /// When the status changes by itself: a restart falls due or a failed command
+    /// stops being recent.
+    pub fn next_change(&self) -> Option<Instant> {
+        let fades = self.errors.first().map(|(at, _)| *at + ERROR_WINDOW);
+        self.restart_at.into_iter().chain(fades).min()
+    }
+
+    /// Updates `STATUS`, returning whether it changed.
+    pub fn publish(&mut self, now: Instant) -> bool {
+        let status = self.status(now);
+        let mut shown = STATUS.lock().unwrap();
+        if *shown == status {
+            return false;
+        }
+        *shown = status;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarts_back_off_then_give_up() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        health.ready();
+        assert_eq!(health.status(start), BackendStatus::Ready);
+
+        let mut now = start + Duration::from_secs(1);
+        for attempt in 1..=MAX_RESTARTS {
+            health.lost("exited".into(), now);
+            health.lost("exited".into(), now);
+            assert_eq!(health.status(now), BackendStatus::Reconnecting { attempt });
+            let delay = FIRST_RESTART_DELAY * 2u32.pow(attempt - 1);
+            assert!(!health.restart_due(now + delay - Duration::from_millis(1)));
+            now += delay;
+            assert!(health.restart_due(now));
+            assert!(!health.restart_due(now));
+        }
+        health.lost("exited".into(), now);
+        assert!(health.is_dead());
+        assert!(!health.restart_due(now + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn a_stable_backend_starts_the_count_over() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        health.lost("exited".into(), start);
+        let restarted = start + FIRST_RESTART_DELAY;
+        assert!(health.restart_due(restarted));
+        health.ready();
+        health.lost("exited".into(), restarted + STABLE_AFTER);
+        assert_eq!(
+            health.status(restarted + STABLE_AFTER),
+            BackendStatus::Reconnecting { attempt: 1 }
+        );
+    }
+
+    #[test]
+    fn another_protocol_version_is_a_mismatch() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        health.ready();
+        health.handshake(Some(PROTOCOL_VERSION));
+        assert_eq!(health.status(start), BackendStatus::Ready);
+
+        health.handshake(None);
+        health.command_failed("Unknown command (unknown-command)".into(), start);
+        let status = health.status(start);
+        assert_eq!(status, BackendStatus::Mismatch { backend: None });
+        assert_eq!(
+            status.label().as_deref(),
+            Some("Backend is outdated, run zig build")
+        );
+        let newer = BackendStatus::Mismatch {
+            backend: Some(PROTOCOL_VERSION + 1),
+        };
+        assert_eq!(
+            newer.label().as_deref(),
+            Some("Backend is newer, update Clipz")
+        );
+    }
+
+    #[test]
+    fn failed_commands_show_while_recent() {
+        let start = Instant::now();
+        let mut health = Health::new(start);
+        health.ready();
+        health.command_failed("entry not found".into(), start);
+        assert_eq!(
+            health.status(start).label().as_deref(),
+            Some("entry not found")
+        );
+        health.command_failed("entry not found".into(), start);
+        assert_eq!(
+            health.status(start).label().as_deref(),
+            Some("2 commands failed")
+        );
+        assert_eq!(health.status(start + ERROR_WINDOW), BackendStatus::Ready);
+        assert_eq!(health.status(start).label(), None);
+    }
+}
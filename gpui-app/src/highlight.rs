@@ -0,0 +1,374 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+};
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+};
+
+// Longer entries only have their first lines coloured, to keep the preview snappy
+const MAX_LINES: usize = 2_000;
+// Previews whose colours are kept, so arrowing back and forth doesn't redo them
+const CACHED: usize = 16;
+
+/// Set whenever a preview's colours are ready so the event loop knows to repaint.
+pub static HIGHLIGHTS_UPDATED: AtomicBool = AtomicBool::new(false);
+
+// An entry's ID and whether the theme is dark
+type Key = (u64, bool);
+
+enum Slot {
+    Queued,
+    Ready(Option<Arc<Highlighted>>),
+}
+
+// Newest last; the oldest is dropped past `CACHED`
+static CACHE: Mutex<VecDeque<(Key, Slot)>> = Mutex::new(VecDeque::new());
+
+/// Syntect syntax names, each with snippets that suggest it. The language with the most
+/// distinct snippets wins; fewer than `MIN_SCORE` means the text is not code.
+const LANGUAGES: &[(&str, &[&str])] = &[
+    (
+        "Rust",
+        &[
+            "fn ", "let mut ", "impl ", "pub fn ", "use std", "&self", "-> ", "#[derive", "::<",
+            "Some(", "Ok(",
+        ],
+    ),
+    (
+        "Python",
+        &[
+            "def ", "import ", "self.", "elif ", "print(", "None", "__init__", "):\n", "True",
+        ],
+    ),
+    (
+        "JavaScript",
+        &[
+            "const ",
+            "function ",
+            "=> ",
+            "console.",
+            "require(",
+            "export ",
+            "document.",
+            "===",
+            "undefined",
+        ],
+    ),
+    (
+        "Go",
+        &[
+            "func ",
+            "package ",
+            ":= ",
+            "fmt.",
+            "defer ",
+            "err != nil",
+            "chan ",
+        ],
+    ),
+    (
+        "Bourne Again Shell (bash)",
+        &[
+            "#!/bin/", "echo ", "$(", "fi\n", "sudo ", " && ", " | grep", "done\n", "esac",
+        ],
+    ),
+    (
+        "SQL",
+        &[
+            "SELECT ",
+            "FROM ",
+            "WHERE ",
+            "INSERT INTO",
+            "CREATE TABLE",
+            "JOIN ",
+            "GROUP BY",
+            "ORDER BY",
+        ],
+    ),
+    (
+        "HTML",
+        &[
+            "<div",
+            "</",
+            "<html",
+            "<body",
+            "class=\"",
+            "<span",
+            "<a href",
+            "<!DOCTYPE",
+        ],
+    ),
+    (
+        "CSS",
+        &[
+            "px;",
+            "color:",
+            "margin:",
+            "padding:",
+            "display:",
+            "@media",
+            "font-size:",
+        ],
+    ),
+    (
+        "C",
+        &[
+            "#include", "int main", "printf(", "NULL", "sizeof(", "malloc(", "->",
+        ],
+    ),
+    (
+        "Java",
+        &[
+            "public class",
+            "public static void",
+            "System.out",
+            "import java",
+            "private ",
+            "@Override",
+        ],
+    ),
+    (
+        "Ruby",
+        &[
+            "def ",
+            "end\n",
+            "puts ",
+            "require '",
+            "do |",
+            "attr_",
+            ".each",
+        ],
+    ),
+];
+const MIN_SCORE: usize = 2;
+
+/// A run of one line coloured by the theme.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub range: Range<usize>,
+    pub color: u32,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+/// A code entry split into lines, each with its coloured runs.
+#[derive(Clone, Debug)]
+pub struct Highlighted {
+    pub language: String,
+    pub lines: Vec<Vec<Span>>,
+}
+
+/// Starts the worker, which loads the syntaxes and themes straight away so neither is
+/// loaded on the render thread later.
+pub fn start() {
+    worker();
+}
+
+/// The colours of entry `id`'s `text` for a `dark` or light popover. None while they
+/// are worked out on a background thread, which it is queued on the first time, and
+/// for text that isn't code.
+pub fn cached(id: u64, text: &str, dark: bool) -> Option<Arc<Highlighted>> {
+    let key = (id, dark);
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((_, slot)) = cache.iter().find(|(cached, _)| *cached == key) {
+        return match slot {
+            Slot::Ready(code) => code.clone(),
+            Slot::Queued => None,
+        };
+    }
+    cache.push_back((key, Slot::Queued));
+    if cache.len() > CACHED {
+        cache.pop_front();
+    }
+    let _ = worker().send((key, text.to_string()));
+    None
+}
+
+fn worker() -> &'static Sender<(Key, String)> {
+    static WORKER: OnceLock<Sender<(Key, String)>> = OnceLock::new();
+    WORKER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<(Key, String)>();
+        thread::spawn(move || {
+            syntaxes();
+            themes();
+            for (key, text) in rx {
+                // Skips previews scrolled past and dropped from the cache meanwhile
+                let queued =
+                    |cache: &VecDeque<(Key, Slot)>| cache.iter().any(|(cached, _)| *cached == key);
+                if !queued(&CACHE.lock().unwrap()) {
+                    continue;
+                }
+                let code = detect(&text).map(|syntax| Arc::new(highlight(&text, syntax, key.1)));
+                let mut cache = CACHE.lock().unwrap();
+                if let Some((_, slot)) = cache.iter_mut().find(|(cached, _)| *cached == key) {
+                    *slot = Slot::Ready(code);
+                }
+                drop(cache);
+                crate::wake::set(&HIGHLIGHTS_UPDATED);
+            }
+        });
+        tx
+    })
+}
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_nonewlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+/// The language of a text entry, or None when it doesn't read as code. Shebangs and
+/// other telling first lines decide first, then valid JSON, then the keyword table.
+pub fn detect(text: &str) -> Option<&'static SyntaxReference> {
+    let syntaxes = syntaxes();
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if let Some(syntax) = trimmed
+        .lines()
+        .next()
+        .and_then(|line| syntaxes.find_syntax_by_first_line(line))
+    {
+        return Some(syntax);
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return syntaxes.find_syntax_by_name("JSON");
+    }
+
+    let (name, score) = LANGUAGES
+        .iter()
+        .map(|(name, snippets)| {
+            let score = snippets.iter().filter(|s| text.contains(*s)).count();
+            (*name, score)
+        })
+        .max_by_key(|(_, score)| *score)?;
+    if score < MIN_SCORE {
+        return None;
+    }
+    syntaxes.find_syntax_by_name(name)
+}
+
+/// Colours `text` line by line with a theme that suits a `dark` or light popover.
+/// Lines the parser chokes on, and any past `MAX_LINES`, are left plain.
+pub fn highlight(text: &str, syntax: &SyntaxReference, dark: bool) -> Highlighted {
+    let theme_name = if dark {
+        "base16-ocean.dark"
+    } else {
+        "InspiredGitHub"
+    };
+    let mut highlighter = HighlightLines::new(syntax, &themes().themes[theme_name]);
+    let lines = text
+        .split('\n')
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx >= MAX_LINES {
+                return Vec::new();
+            }
+            let Ok(regions) = highlighter.highlight_line(line, syntaxes()) else {
+                return Vec::new();
+            };
+            let mut start = 0;
+            regions
+                .into_iter()
+                .map(|(style, piece)| {
+                    let range = start..start + piece.len();
+                    start = range.end;
+                    let fg = style.foreground;
+                    Span {
+                        range,
+                        color: u32::from_be_bytes([0, fg.r, fg.g, fg.b]),
+                        bold: style.font_style.contains(FontStyle::BOLD),
+                        italic: style.font_style.contains(FontStyle::ITALIC),
+                    }
+                })
+                .filter(|span| !span.range.is_empty())
+                .collect()
+        })
+        .collect();
+    Highlighted {
+        language: syntax.name.clone(),
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(text: &str) -> Option<&'static str> {
+        detect(text).map(|syntax| syntax.name.as_str())
+    }
+
+    #[test]
+    fn detects_languages_and_skips_prose() {
+        for (name, _) in LANGUAGES {
+            assert!(
+                syntaxes().find_syntax_by_name(name).is_some(),
+                "{} is not a bundled syntax",
+                name
+            );
+        }
+        assert_eq!(
+            language("fn main() {\n    let mut x = Some(1);\n}"),
+            Some("Rust")
+        );
+        assert_eq!(
+            language("def greet(name):\n    print(name)\n"),
+            Some("Python")
+        );
+        assert_eq!(language("SELECT id FROM users WHERE active"), Some("SQL"));
+        assert_eq!(language("#!/bin/sh\nls"), Some("Bourne Again Shell (bash)"));
+        assert_eq!(language("{\"a\": [1, 2]}"), Some("JSON"));
+        assert_eq!(language("Meeting moved to 3pm, see you there"), None);
+        assert_eq!(language("  "), None);
+    }
+
+    #[test]
+    fn previews_are_highlighted_in_the_background() {
+        let text = "fn main() {\n    let mut x = Some(1);\n}";
+        assert!(cached(u64::MAX, text, true).is_none());
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let code = loop {
+            if let Some(code) = cached(u64::MAX, text, true) {
+                break code;
+            }
+            assert!(std::time::Instant::now() < deadline, "never highlighted");
+            thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert_eq!(code.language, "Rust");
+        assert_eq!(code.lines.len(), 3);
+    }
+
+    #[test]
+    fn highlighted_spans_cover_each_line() {
+        let text = "fn main() {\n    let mut x = 1;\n}";
+        let highlighted = highlight(text, detect(text).unwrap(), true);
+        assert_eq!(highlighted.language, "Rust");
+        assert_eq!(highlighted.lines.len(), 3);
+        for (spans, line) in highlighted.lines.iter().zip(text.split('\n')) {
+            assert_eq!(spans.first().map(|s| s.range.start), Some(0));
+            assert_eq!(spans.last().map(|s| s.range.end), Some(line.len()));
+        }
+        // Keywords and plain text get different colours
+        assert!(highlighted.lines[0]
+            .windows(2)
+            .any(|pair| pair[0].color != pair[1].color));
+    }
+}
@@ -0,0 +1,82 @@
+use std::{process::Command, time::Duration};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{bytes, pipe};
+
+/// Environment variable the backend reads its history key from.
+pub const ENV_VAR: &str = "CLIPZ_HISTORY_KEY";
+
+const SERVICE: &str = "Clipz history key";
+const ACCOUNT: &str = "clipz";
+// `security` exits with this when no matching item exists
+const ITEM_NOT_FOUND: i32 = 44;
+
+/// The history encryption key stored in the login Keychain, as 64 hex digits.
+pub fn load() -> Result<Option<String>> {
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", SERVICE, "-a", ACCOUNT, "-w"])
+        .output()
+        .context("failed to run security")?;
+    if output.status.code() == Some(ITEM_NOT_FOUND) {
+        return Ok(None);
+    }
+    if !output.status.success() {
+        bail!(
+            "failed to read history key from Keychain: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let key = String::from_utf8(output.stdout).context("history key is not UTF-8")?;
+    let key = key.trim();
+    if !is_valid_key(key) {
+        bail!("history key in Keychain is not 64 hex digits");
+    }
+    Ok(Some(key.to_string()))
+}
+
+/// Like [`load`], but generates and stores a new random key the first time.
+pub fn load_or_create() -> Result<String> {
+    if let Some(key) = load()? {
+        return Ok(key);
+    }
+    let key = bytes::to_hex(&bytes::random::<32>().context("failed to generate history key")?);
+
+    // The command goes to `security -i` on stdin so the key never shows up in `ps`
+    pipe::run(
+        Command::new("security").arg("-i"),
+        add_command(&key).into_bytes(),
+        "security",
+        Duration::from_secs(10),
+    )?;
+    // `security -i` exits cleanly even when a command fails, so read the key back
+    if load()?.as_deref() != Some(key.as_str()) {
+        bail!("failed to store history key in Keychain");
+    }
+    Ok(key)
+}
+
+fn add_command(key: &str) -> String {
+    format!(
+        "add-generic-password -s \"{}\" -a \"{}\" -w {}\n",
+        SERVICE, ACCOUNT, key
+    )
+}
+
+fn is_valid_key(key: &str) -> bool {
+    bytes::from_hex(key).is_some_and(|key| key.len() == 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_keys_are_what_the_backend_expects() {
+        let key = bytes::to_hex(&[0xab; 32]);
+        assert!(is_valid_key(&key));
+        assert!(key.starts_with("abab"));
+        assert!(!is_valid_key(&key[1..]));
+        assert!(!is_valid_key(&key.replace('a', "g")));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// A "copy as" transformation for image entries, for pasting into tools with size limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizePreset {
+    /// Scale both dimensions to the given percentage.
+    Scale(u32),
+    /// Downscale so the width is at most this many pixels; smaller images are kept as-is.
+    MaxWidth(u32),
+    /// Re-encode as JPEG (and downscale if needed) until the file is under this many bytes.
+    MaxBytes(u64),
+}
+
+pub const PRESETS: [ResizePreset; 3] = [
+    ResizePreset::Scale(50),
+    ResizePreset::MaxWidth(1280),
+    ResizePreset::MaxBytes(500 * 1024),
+];
+
+// JPEG qualities tried in order by `MaxBytes` before falling back to downscaling
+const JPEG_QUALITIES: [u32; 4] = [80, 65, 50, 35];
+const MAX_DOWNSCALE_STEPS: usize = 6;
+
+impl ResizePreset {
+    pub fn label(self) -> String {
+        match self {
+            ResizePreset::Scale(percent) => format!("{}%", percent),
+            ResizePreset::MaxWidth(width) => format!("\u{2264}{}px", width),
+            ResizePreset::MaxBytes(bytes) => format!("<{}KB", bytes / 1024),
+        }
+    }
+
+    /// Width to resample to, or `None` when the image can be used at its current size.
+    fn target_width(self, width: u32) -> Option<u32> {
+        match self {
+            ResizePreset::Scale(percent) => Some((width * percent / 100).max(1)),
+            ResizePreset::MaxWidth(max) if width > max => Some(max),
+            ResizePreset::MaxWidth(_) | ResizePreset::MaxBytes(_) => None,
+        }
+    }
+
+    /// Writes a transformed copy of `source` to the temp directory and returns its path.
+    pub fn apply(self, source: &Path) -> Result<PathBuf> {
+        let dir = std::env::temp_dir().join("clipz_resized");
+        std::fs::create_dir_all(&dir).context("failed to create output directory")?;
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+
+        match self {
+            ResizePreset::Scale(_) | ResizePreset::MaxWidth(_) => {
+                let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+                let dest = dir.join(format!("Clipz-{}.{}", stamp, ext));
+                match self.target_width(pixel_width(source)?) {
+                    Some(width) => sips(&[
+                        "--resampleWidth".as_ref(),
+                        width.to_string().as_ref(),
+                        source.as_os_str(),
+                        "--out".as_ref(),
+                        dest.as_os_str(),
+                    ])?,
+                    None => {
+                        std::fs::copy(source, &dest).context("failed to copy image")?;
+                    }
+                }
+                Ok(dest)
+            }
+            ResizePreset::MaxBytes(limit) => {
+                let dest = dir.join(format!("Clipz-{}.jpg", stamp));
+                compress_below(source, &dest, limit)?;
+                Ok(dest)
+            }
+        }
+    }
+}
+
+fn compress_below(source: &Path, dest: &Path, limit: u64) -> Result<()> {
+    for quality in JPEG_QUALITIES {
+        encode_jpeg(source, dest, quality)?;
+        if file_size(dest)? <= limit {
+            return Ok(());
+        }
+    }
+
+    // Lowest quality still too big: shrink the already-encoded copy until it fits
+    for _ in 0..MAX_DOWNSCALE_STEPS {
+        let width = pixel_width(dest)?;
+        let smaller = (width * 3 / 4).max(1);
+        sips(&[
+            "--resampleWidth".as_ref(),
+            smaller.to_string().as_ref(),
+            dest.as_os_str(),
+        ])?;
+        if file_size(dest)? <= limit {
+            return Ok(());
+        }
+    }
+
+    bail!("could not compress image below {} bytes", limit)
+}
+
+fn encode_jpeg(source: &Path, dest: &Path, quality: u32) -> Result<()> {
+    sips(&[
+        "-s".as_ref(),
+        "format".as_ref(),
+        "jpeg".as_ref(),
+        "-s".as_ref(),
+        "formatOptions".as_ref(),
+        quality.to_string().as_ref(),
+        source.as_os_str(),
+        "--out".as_ref(),
+        dest.as_os_str(),
+    ])
+}
+
+fn file_size(path: &Path) -> Result<u64> {
+    Ok(std::fs::metadata(path)
+        .context("failed to stat image")?
+        .len())
+}
+
+fn pixel_width(path: &Path) -> Result<u32> {
+    let output = Command::new("/usr/bin/sips")
+        .arg("-g")
+        .arg("pixelWidth")
+        .arg(path)
+        .output()
+        .context("failed to run sips")?;
+    if !output.status.success() {
+        bail!("sips exited with {}", output.status);
+    }
+    parse_pixel_width(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| anyhow!("sips did not report a pixel width"))
+}
+
+fn parse_pixel_width(output: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("pixelWidth:")
+            .and_then(|w| w.trim().parse().ok())
+    })
+}
+
+fn sips(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let output = Command::new("/usr/bin/sips")
+        .args(args)
+        .output()
+        .context("failed to run sips")?;
+    if !output.status.success() {
+        bail!("sips exited with {}", output.status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sips_pixel_width() {
+        let output = "/tmp/shot.png\n  pixelWidth: 2880\n";
+        assert_eq!(parse_pixel_width(output), Some(2880));
+        assert_eq!(parse_pixel_width("/tmp/shot.png\n"), None);
+    }
+
+    #[test]
+    fn target_width_only_shrinks_when_needed() {
+        assert_eq!(ResizePreset::Scale(50).target_width(2880), Some(1440));
+        assert_eq!(ResizePreset::MaxWidth(1280).target_width(2880), Some(1280));
+        assert_eq!(ResizePreset::MaxWidth(1280).target_width(800), None);
+        assert_eq!(ResizePreset::MaxBytes(1024).target_width(800), None);
+    }
+}
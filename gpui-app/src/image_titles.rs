@@ -0,0 +1,146 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::ocr;
+
+/// What an image looks like going by the text read from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageKind {
+    Terminal,
+    Code,
+    Error,
+    Receipt,
+    Chart,
+}
+
+impl ImageKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ImageKind::Terminal => "Screenshot of a terminal",
+            ImageKind::Code => "Screenshot of code",
+            ImageKind::Error => "Error message",
+            ImageKind::Receipt => "Receipt",
+            ImageKind::Chart => "Chart or table of figures",
+        }
+    }
+}
+
+fn prompt_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(?:\$ |[❯➜] |\S*[~/]\S* ?[%$#] |[\w.-]+@[\w.-]+[: ])")
+            .expect("valid prompt pattern")
+    })
+}
+
+fn code_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"^(?:fn|pub|let|const|var|def|class|import|from|return|if|for|while",
+            r"|function|struct|impl|use|#include|package)\b",
+            r"|[{};]$",
+        ))
+        .expect("valid code pattern")
+    })
+}
+
+fn amount_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\d+[.,]\d{2}\b").expect("valid amount pattern"))
+}
+
+/// Guesses what kind of image the recognized `text` came from. Like secret
+/// detection these are heuristics: shell prompts, lines of code, error wording, a
+/// total with an amount, or mostly numeric lines.
+pub fn detect(text: &str) -> Option<ImageKind> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    if lines.iter().any(|line| prompt_pattern().is_match(line)) {
+        return Some(ImageKind::Terminal);
+    }
+    let code_lines = lines
+        .iter()
+        .filter(|line| code_pattern().is_match(line))
+        .count();
+    if code_lines >= (lines.len() / 3).max(2) {
+        return Some(ImageKind::Code);
+    }
+    let lower = text.to_lowercase();
+    if ["error", "exception", "failed", "traceback"]
+        .iter()
+        .any(|word| lower.contains(word))
+    {
+        return Some(ImageKind::Error);
+    }
+    if lower.contains("total") && amount_pattern().is_match(text) {
+        return Some(ImageKind::Receipt);
+    }
+    let numeric = lines
+        .iter()
+        .filter(|line| {
+            let digits = line.chars().filter(char::is_ascii_digit).count();
+            digits * 2 >= line.chars().filter(|c| !c.is_whitespace()).count()
+        })
+        .count();
+    if lines.len() >= 4 && numeric * 5 >= lines.len() * 3 {
+        return Some(ImageKind::Chart);
+    }
+    None
+}
+
+/// The row title for an image with recognized `text`: what it looks like, when that
+/// can be told, followed by the first words read from it.
+pub fn title(text: &str) -> String {
+    match detect(text) {
+        Some(kind) => format!("{} \u{2014} {}", kind.label(), ocr::excerpt(text)),
+        None => ocr::excerpt(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_follow_the_recognized_text() {
+        assert_eq!(
+            detect("Last login: Mon\n~/src/clipz % cargo test\nrunning 3 tests"),
+            Some(ImageKind::Terminal)
+        );
+        assert_eq!(
+            detect("fn main() {\n    let x = 1;\n    println!(\"{x}\");\n}"),
+            Some(ImageKind::Code)
+        );
+        assert_eq!(
+            detect("Something went wrong\nConnection failed, try again"),
+            Some(ImageKind::Error)
+        );
+        assert_eq!(
+            detect("Corner Cafe\nLatte 4.50\nTotal 4.50\nThank you"),
+            Some(ImageKind::Receipt)
+        );
+        assert_eq!(
+            detect("Revenue\n2021 120\n2022 180\n2023 240"),
+            Some(ImageKind::Chart)
+        );
+        assert_eq!(detect("Meeting notes\nShip the release"), None);
+        assert_eq!(detect("  \n "), None);
+
+        assert_eq!(
+            title("Meeting notes\nShip the release"),
+            "Meeting notes Ship the release"
+        );
+        assert_eq!(
+            title("Traceback (most recent call last)"),
+            "Error message \u{2014} Traceback (most recent call last)"
+        );
+    }
+}
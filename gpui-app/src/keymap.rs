@@ -0,0 +1,877 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Where keystrokes are going, which decides the shortcuts that apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyContext {
+    List,
+    Search,
+    Preview,
+    Find,
+    Tags,
+    Split,
+    Menu,
+    Snippets,
+    NameSnippet,
+    Edit,
+}
+
+impl KeyContext {
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyContext::List => "List",
+            KeyContext::Search => "Search",
+            KeyContext::Preview => "Preview",
+            KeyContext::Find => "Find in preview",
+            KeyContext::Tags => "Editing tags",
+            KeyContext::Split => "Splitting",
+            KeyContext::Menu => "Entry menu",
+            KeyContext::Snippets => "Snippets",
+            KeyContext::NameSnippet => "Naming a snippet",
+            KeyContext::Edit => "Editing an entry",
+        }
+    }
+}
+
+/// Actions bound to chords, dispatched through `matches` so the popover only reacts to
+/// what is listed. `[keys]` in the config can rebind them by `name`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    MoveUp,
+    MoveDown,
+    ExtendUp,
+    ExtendDown,
+    JumpUp,
+    JumpDown,
+    PreviousSection,
+    NextSection,
+    /// The row is the position of the matched chord, so rebinding keeps the order.
+    CopyNumbered,
+    /// Like `CopyNumbered`, but with a modifier, so it works while typing a search.
+    QuickCopy,
+    Copy,
+    Cancel,
+    Backspace,
+    NextQuickAction,
+    PreviousQuickAction,
+    PickPrevious,
+    PickNext,
+    /// Enter in the prompts and the snippets list; the history's Enter is `Copy`.
+    Confirm,
+    TogglePreview,
+    Find,
+    FindNext,
+    FindPrevious,
+    EditTags,
+    Split,
+    Pin,
+    Undo,
+    Swap,
+    Remove,
+    Repeat,
+    /// Like `CopyNumbered`, the chip is the position of the matched chord.
+    ToggleChip,
+    ToggleSnippets,
+    RemoveSnippet,
+    NewWindow,
+    ToggleMarkdown,
+    Shortcuts,
+    EditEntry,
+    CopyEdit,
+    SaveEdit,
+    VimDown,
+    VimUp,
+    /// Acts on the second press in a row (`gg`), as `VimRemove` does (`dd`).
+    VimTop,
+    VimBottom,
+    VimRemove,
+    VimSearch,
+}
+
+/// A row of the shortcuts overlay.
+pub struct Binding {
+    pub keys: &'static str,
+    pub description: &'static str,
+    contexts: &'static [KeyContext],
+    /// The action and its default chords, in `[keys]` syntax. Other bindings are
+    /// handled where the key is read.
+    command: Option<(KeyAction, &'static [&'static str])>,
+}
+
+impl KeyAction {
+    /// What the action is called in `[keys]`.
+    fn name(self) -> &'static str {
+        match self {
+            KeyAction::MoveUp => "up",
+            KeyAction::MoveDown => "down",
+            KeyAction::ExtendUp => "extend-up",
+            KeyAction::ExtendDown => "extend-down",
+            KeyAction::JumpUp => "jump-up",
+            KeyAction::JumpDown => "jump-down",
+            KeyAction::PreviousSection => "previous-section",
+            KeyAction::NextSection => "next-section",
+            KeyAction::CopyNumbered => "copy-number",
+            KeyAction::QuickCopy => "quick-copy",
+            KeyAction::Copy => "copy",
+            KeyAction::Cancel => "cancel",
+            KeyAction::Backspace => "backspace",
+            KeyAction::NextQuickAction => "next-action",
+            KeyAction::PreviousQuickAction => "previous-action",
+            KeyAction::PickPrevious => "pick-previous",
+            KeyAction::PickNext => "pick-next",
+            KeyAction::Confirm => "confirm",
+            KeyAction::TogglePreview => "preview",
+            KeyAction::Find => "find",
+            KeyAction::FindNext => "find-next",
+            KeyAction::FindPrevious => "find-previous",
+            KeyAction::EditTags => "edit-tags",
+            KeyAction::Split => "split",
+            KeyAction::Pin => "pin",
+            KeyAction::Undo => "undo",
+            KeyAction::Swap => "swap",
+            KeyAction::Remove => "remove",
+            KeyAction::Repeat => "repeat",
+            KeyAction::ToggleChip => "toggle-chip",
+            KeyAction::ToggleSnippets => "snippets",
+            KeyAction::RemoveSnippet => "remove-snippet",
+            KeyAction::NewWindow => "new-window",
+            KeyAction::ToggleMarkdown => "markdown",
+            KeyAction::Shortcuts => "shortcuts",
+            KeyAction::EditEntry => "edit",
+            KeyAction::CopyEdit => "copy-edit",
+            KeyAction::SaveEdit => "save-edit",
+            KeyAction::VimDown => "vim-down",
+            KeyAction::VimUp => "vim-up",
+            KeyAction::VimTop => "vim-top",
+            KeyAction::VimBottom => "vim-bottom",
+            KeyAction::VimRemove => "vim-remove",
+            KeyAction::VimSearch => "vim-search",
+        }
+    }
+
+    /// Only bound while `[behavior] vim_mode` is on.
+    fn is_vim(self) -> bool {
+        matches!(
+            self,
+            KeyAction::VimDown
+                | KeyAction::VimUp
+                | KeyAction::VimTop
+                | KeyAction::VimBottom
+                | KeyAction::VimRemove
+                | KeyAction::VimSearch
+        )
+    }
+}
+
+impl Binding {
+    /// The keys shown in the shortcuts overlay, as rebound in `[keys]` if they were.
+    pub fn keys(&self) -> String {
+        let overrides = OVERRIDES.lock().unwrap();
+        let rebound = self
+            .command
+            .and_then(|(action, _)| overrides.iter().find(|(a, _)| *a == action));
+        match rebound {
+            Some((_, chords)) => chords
+                .iter()
+                .map(Chord::label)
+                .collect::<Vec<_>>()
+                .join(" / "),
+            None => self.keys.to_string(),
+        }
+    }
+}
+
+/// A key and the modifiers held with it, written in `[keys]` like the global hotkeys:
+/// `cmd+backspace`, `ctrl+n` or just `down`. A trailing `+` is the key itself (`cmd++`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Chord {
+    key: String,
+    cmd: bool,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl Chord {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None if text == "+" => ("", "+"),
+            None => text.rsplit_once('+').unwrap_or(("", &text)),
+        };
+        if key.is_empty() {
+            return None;
+        }
+        let mut chord = Chord {
+            key: key.to_string(),
+            ..Default::default()
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier {
+                "cmd" | "command" | "super" => chord.cmd = true,
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" | "option" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return None,
+            }
+        }
+        Some(chord)
+    }
+
+    /// Every modifier has to be as the chord says, so `k` doesn't fire on Shift+K.
+    fn matches(&self, keystroke: &gpui::Keystroke) -> bool {
+        let held = &keystroke.modifiers;
+        keystroke.key == self.key
+            && held.platform == self.cmd
+            && held.control == self.ctrl
+            && held.alt == self.alt
+            && held.shift == self.shift
+    }
+
+    fn label(&self) -> String {
+        let key = match self.key.as_str() {
+            "up" => "\u{2191}".to_string(),
+            "down" => "\u{2193}".to_string(),
+            "left" => "\u{2190}".to_string(),
+            "right" => "\u{2192}".to_string(),
+            key => {
+                let mut chars = key.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+        };
+        [
+            (self.cmd, "Cmd+"),
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+        ]
+        .iter()
+        .filter(|(held, _)| *held)
+        .map(|(_, label)| *label)
+        .chain([key.as_str()])
+        .collect()
+    }
+}
+
+/// Chords set in `[keys]`, in place of the defaults of the actions they name.
+static OVERRIDES: Mutex<Vec<(KeyAction, Vec<Chord>)>> = Mutex::new(Vec::new());
+
+static VIM_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Applies the `[keys]` section of the config, and binds the Vim keys when `vim_mode`
+/// is on.
+pub fn configure(keys: &BTreeMap<String, Vec<String>>, vim_mode: bool) {
+    *OVERRIDES.lock().unwrap() = parse_overrides(keys);
+    VIM_MODE.store(vim_mode, Ordering::SeqCst);
+}
+
+pub fn vim_mode() -> bool {
+    VIM_MODE.load(Ordering::SeqCst)
+}
+
+/// Rebindings by action name. Unknown actions and chords are reported and skipped.
+fn parse_overrides(keys: &BTreeMap<String, Vec<String>>) -> Vec<(KeyAction, Vec<Chord>)> {
+    let mut overrides = Vec::new();
+    for (name, chords) in keys {
+        let action = BINDINGS
+            .iter()
+            .filter_map(|binding| binding.command.map(|(action, _)| action))
+            .find(|action| action.name() == name);
+        let Some(action) = action else {
+            eprintln!("Ignoring unknown action {:?} in [keys]", name);
+            continue;
+        };
+        let chords = chords
+            .iter()
+            .filter_map(|text| {
+                let chord = Chord::parse(text);
+                if chord.is_none() {
+                    eprintln!("Ignoring invalid chord {:?} for {:?} in [keys]", text, name);
+                }
+                chord
+            })
+            .collect();
+        overrides.push((action, chords));
+    }
+    overrides
+}
+
+use KeyContext::*;
+
+const BROWSING: &[KeyContext] = &[List, Search, Preview];
+const EVERYWHERE: &[KeyContext] = &[
+    List,
+    Search,
+    Preview,
+    Find,
+    Tags,
+    Split,
+    Menu,
+    Snippets,
+    NameSnippet,
+    Edit,
+];
+
+const fn key(
+    keys: &'static str,
+    description: &'static str,
+    contexts: &'static [KeyContext],
+) -> Binding {
+    Binding {
+        keys,
+        description,
+        contexts,
+        command: None,
+    }
+}
+
+const fn bind(
+    action: KeyAction,
+    chords: &'static [&'static str],
+    keys: &'static str,
+    description: &'static str,
+    contexts: &'static [KeyContext],
+) -> Binding {
+    Binding {
+        keys,
+        description,
+        contexts,
+        command: Some((action, chords)),
+    }
+}
+
+pub static BINDINGS: &[Binding] = &[
+    bind(
+        KeyAction::MoveUp,
+        &["up"],
+        "\u{2191}",
+        "Move focus up",
+        &[List, Search, Snippets],
+    ),
+    bind(
+        KeyAction::MoveDown,
+        &["down"],
+        "\u{2193}",
+        "Move focus down",
+        &[List, Search, Snippets],
+    ),
+    bind(
+        KeyAction::ExtendUp,
+        &["shift+up"],
+        "Shift+\u{2191}",
+        "Extend the selection up",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::ExtendDown,
+        &["shift+down"],
+        "Shift+\u{2193}",
+        "Extend the selection down",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::JumpUp,
+        &["alt+up"],
+        "Alt+\u{2191}",
+        "Jump 5 entries up",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::JumpDown,
+        &["alt+down"],
+        "Alt+\u{2193}",
+        "Jump 5 entries down",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::PreviousSection,
+        &["cmd+up"],
+        "Cmd+\u{2191}",
+        "Previous date section",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::NextSection,
+        &["cmd+down"],
+        "Cmd+\u{2193}",
+        "Next date section",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::Copy,
+        &["enter"],
+        "Enter",
+        "Copy the focused entry, or run the chosen quick action or recent search",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::CopyNumbered,
+        &["1", "2", "3", "4", "5", "6", "7", "8", "9"],
+        "1\u{2026}9",
+        "Copy the entry with that number, once \u{2191}\u{2193} moved into the list",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::QuickCopy,
+        &[
+            "ctrl+1", "ctrl+2", "ctrl+3", "ctrl+4", "ctrl+5", "ctrl+6", "ctrl+7", "ctrl+8",
+            "ctrl+9",
+        ],
+        "Ctrl+1\u{2026}9",
+        "Copy the entry with that number",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::NextQuickAction,
+        &["tab"],
+        "Tab",
+        "Choose the focused row's next quick action",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::PreviousQuickAction,
+        &["shift+tab"],
+        "Shift+Tab",
+        "Choose the focused row's previous quick action",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::PickPrevious,
+        &["left"],
+        "\u{2190}",
+        "Previous quick action or recent search",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::PickNext,
+        &["right"],
+        "\u{2192}",
+        "Next quick action or recent search",
+        &[List, Search],
+    ),
+    key("Type", "Search (/ for regex, #tag)", &[List, Search]),
+    bind(KeyAction::VimDown, &["j"], "j", "Move focus down", &[List]),
+    bind(KeyAction::VimUp, &["k"], "k", "Move focus up", &[List]),
+    bind(
+        KeyAction::VimTop,
+        &["g"],
+        "gg",
+        "Jump to the first entry",
+        &[List],
+    ),
+    bind(
+        KeyAction::VimBottom,
+        &["shift+g"],
+        "G",
+        "Jump to the last entry",
+        &[List],
+    ),
+    bind(
+        KeyAction::VimRemove,
+        &["d"],
+        "dd",
+        "Remove the focused entry",
+        &[List],
+    ),
+    bind(
+        KeyAction::VimSearch,
+        &["/"],
+        "/",
+        "Search (Enter keeps it, Escape clears it)",
+        &[List],
+    ),
+    bind(
+        KeyAction::Backspace,
+        &["backspace"],
+        "Backspace",
+        "Delete a search character",
+        &[Search],
+    ),
+    bind(
+        KeyAction::Backspace,
+        &["backspace"],
+        "Backspace",
+        "Remove the focused entry (or the selection), or forget the chosen recent search",
+        &[List],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Clear selection, then close",
+        &[List],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Clear search",
+        &[Search],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Close preview",
+        &[Preview],
+    ),
+    bind(
+        KeyAction::TogglePreview,
+        &["cmd+y"],
+        "Cmd+Y",
+        "Preview the focused entry",
+        &[List, Search, Preview],
+    ),
+    bind(
+        KeyAction::Find,
+        &["cmd+f"],
+        "Cmd+F",
+        "Find in preview",
+        &[List, Search, Preview],
+    ),
+    bind(
+        KeyAction::ToggleChip,
+        &["cmd+1", "cmd+2", "cmd+3", "cmd+4", "cmd+5"],
+        "Cmd+1\u{2026}5",
+        "Toggle Text/Image/File/URL/Color",
+        &[List, Search],
+    ),
+    bind(
+        KeyAction::ToggleSnippets,
+        &["cmd+6"],
+        "Cmd+6",
+        "Show / hide snippets",
+        &[List, Search, Snippets],
+    ),
+    bind(KeyAction::Pin, &["cmd+p"], "Cmd+P", "Pin / unpin", BROWSING),
+    bind(
+        KeyAction::Undo,
+        &["cmd+z"],
+        "Cmd+Z",
+        "Undo the last removal or change to the selection",
+        BROWSING,
+    ),
+    bind(
+        KeyAction::EditTags,
+        &["cmd+t"],
+        "Cmd+T",
+        "Edit tags",
+        &[List, Search, Preview, Tags],
+    ),
+    bind(
+        KeyAction::Split,
+        &["cmd+l"],
+        "Cmd+L",
+        "Split into entries",
+        &[List, Search, Preview, Split],
+    ),
+    bind(
+        KeyAction::EditEntry,
+        &["cmd+e"],
+        "Cmd+E",
+        "Edit a copy of the text",
+        &[List, Search, Preview, Edit],
+    ),
+    bind(
+        KeyAction::Swap,
+        &["cmd+s"],
+        "Cmd+S",
+        "Swap with the clipboard",
+        BROWSING,
+    ),
+    bind(
+        KeyAction::Remove,
+        &["cmd+backspace", "delete"],
+        "Cmd+Backspace / Delete",
+        "Remove the focused entry (or the selection)",
+        &[List, Search, Preview],
+    ),
+    bind(
+        KeyAction::Repeat,
+        &["cmd+."],
+        "Cmd+.",
+        "Repeat the last action",
+        BROWSING,
+    ),
+    bind(
+        KeyAction::NewWindow,
+        &["cmd+n"],
+        "Cmd+N",
+        "New window with its own search",
+        BROWSING,
+    ),
+    bind(
+        KeyAction::ToggleMarkdown,
+        &["cmd+m"],
+        "Cmd+M",
+        "Markdown: rendered / source",
+        &[Preview],
+    ),
+    key("Type", "Find text", &[Find]),
+    bind(
+        KeyAction::FindNext,
+        &["enter", "cmd+g"],
+        "Enter / Cmd+G",
+        "Next match",
+        &[Find],
+    ),
+    bind(
+        KeyAction::FindPrevious,
+        &["shift+enter", "cmd+shift+g"],
+        "Shift+Enter / Cmd+Shift+G",
+        "Previous match",
+        &[Find],
+    ),
+    bind(
+        KeyAction::Backspace,
+        &["backspace"],
+        "Backspace",
+        "Delete a character",
+        &[Find, Tags, Split, Snippets, NameSnippet, Edit],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Close find",
+        &[Find],
+    ),
+    key("Type", "Tags, space-separated", &[Tags]),
+    bind(
+        KeyAction::Confirm,
+        &["enter"],
+        "Enter",
+        "Save tags",
+        &[Tags],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Cancel",
+        &[Tags, Split, NameSnippet, Edit],
+    ),
+    key("Type", "Delimiter (\\t for tabs)", &[Split]),
+    bind(KeyAction::Confirm, &["enter"], "Enter", "Split", &[Split]),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Close menu",
+        &[Menu],
+    ),
+    key("Type", "Filter snippets (/ for regex)", &[Snippets]),
+    bind(
+        KeyAction::Confirm,
+        &["enter"],
+        "Enter",
+        "Insert the focused snippet",
+        &[Snippets],
+    ),
+    bind(
+        KeyAction::RemoveSnippet,
+        &["cmd+backspace"],
+        "Cmd+Backspace",
+        "Delete the focused snippet",
+        &[Snippets],
+    ),
+    bind(
+        KeyAction::Cancel,
+        &["escape"],
+        "Escape",
+        "Clear filter, then close snippets",
+        &[Snippets],
+    ),
+    key("Type", "Snippet name", &[NameSnippet]),
+    bind(
+        KeyAction::Confirm,
+        &["enter"],
+        "Enter",
+        "Save snippet",
+        &[NameSnippet],
+    ),
+    key("Type", "Edit the text (Enter for a new line)", &[Edit]),
+    key(
+        "\u{2190}\u{2192}\u{2191}\u{2193}",
+        "Move the cursor (Cmd+\u{2190}\u{2192} to line start / end)",
+        &[Edit],
+    ),
+    bind(
+        KeyAction::CopyEdit,
+        &["cmd+enter"],
+        "Cmd+Enter",
+        "Copy the edited text",
+        &[Edit],
+    ),
+    bind(
+        KeyAction::SaveEdit,
+        &["cmd+s"],
+        "Cmd+S",
+        "Save as a new entry",
+        &[Edit],
+    ),
+    bind(
+        KeyAction::Shortcuts,
+        &["cmd+/"],
+        "? / Cmd+/",
+        "Show these shortcuts",
+        EVERYWHERE,
+    ),
+];
+
+/// Which of the chords bound to `action` `keystroke` is, counting from 0, as rebound in
+/// `[keys]` if it was; None when it is none of them.
+pub fn matched(action: KeyAction, keystroke: &gpui::Keystroke) -> Option<usize> {
+    if action.is_vim() && !vim_mode() {
+        return None;
+    }
+    if let Some((_, chords)) = OVERRIDES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(rebound, _)| *rebound == action)
+    {
+        return chords.iter().position(|chord| chord.matches(keystroke));
+    }
+    let (_, chords) = BINDINGS
+        .iter()
+        .find_map(|binding| binding.command.filter(|(bound, _)| *bound == action))?;
+    chords
+        .iter()
+        .position(|text| Chord::parse(text).is_some_and(|chord| chord.matches(keystroke)))
+}
+
+/// Whether `keystroke` is a chord bound to `action`, as rebound in `[keys]` if it was.
+pub fn matches(action: KeyAction, keystroke: &gpui::Keystroke) -> bool {
+    matched(action, keystroke).is_some()
+}
+
+/// Bindings that apply in `context`, in registry order.
+pub fn for_context(context: KeyContext) -> impl Iterator<Item = &'static Binding> {
+    BINDINGS.iter().filter(move |binding| {
+        binding.contexts.contains(&context)
+            && binding
+                .command
+                .is_none_or(|(action, _)| !action.is_vim() || vim_mode())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keystroke(platform: bool, key: &str) -> gpui::Keystroke {
+        gpui::Keystroke {
+            modifiers: gpui::Modifiers {
+                platform,
+                ..Default::default()
+            },
+            key: key.into(),
+            key_char: None,
+        }
+    }
+
+    #[test]
+    fn chords_are_unambiguous_within_a_context() {
+        for context in EVERYWHERE {
+            let mut seen: Vec<&str> = Vec::new();
+            for binding in for_context(*context) {
+                for key in binding.command.map(|(_, keys)| keys).unwrap_or_default() {
+                    assert!(!seen.contains(key), "{} bound twice in {:?}", key, context);
+                    seen.push(key);
+                }
+            }
+            assert!(for_context(*context).any(|b| b.description == "Show these shortcuts"));
+        }
+
+        assert!(matches(KeyAction::Pin, &keystroke(true, "p")));
+        assert!(!matches(KeyAction::Swap, &keystroke(true, "p")));
+        assert!(!matches(KeyAction::Pin, &keystroke(false, "p")));
+        assert!(matches(KeyAction::ToggleChip, &keystroke(true, "3")));
+        assert!(matches(KeyAction::Remove, &keystroke(true, "backspace")));
+        assert!(!matches(KeyAction::Remove, &keystroke(false, "backspace")));
+        assert!(matches(KeyAction::Remove, &keystroke(false, "delete")));
+        assert!(matches(KeyAction::MoveDown, &keystroke(false, "down")));
+        assert_eq!(
+            matched(KeyAction::ToggleChip, &keystroke(true, "3")),
+            Some(2)
+        );
+        assert_eq!(
+            matched(KeyAction::CopyNumbered, &keystroke(false, "9")),
+            Some(8)
+        );
+        // Rows that share an action share its chords, since only the first is matched
+        for binding in BINDINGS {
+            if let Some((action, chords)) = binding.command {
+                let first = BINDINGS
+                    .iter()
+                    .find_map(|b| b.command.filter(|(bound, _)| *bound == action));
+                assert_eq!(first.map(|(_, keys)| keys), Some(chords), "{:?}", action);
+            }
+        }
+        // Vim keys stay unbound unless `vim_mode` is on
+        assert!(!matches(KeyAction::VimDown, &keystroke(false, "j")));
+    }
+
+    #[test]
+    fn keys_rebind_actions_by_name() {
+        let chord = Chord::parse("Ctrl+Shift+N").unwrap();
+        assert_eq!(chord.label(), "Ctrl+Shift+N");
+        assert!(Chord::parse("hyper+n").is_none());
+        assert!(Chord::parse("cmd+").is_none());
+        assert_eq!(Chord::parse("down").unwrap().label(), "\u{2193}");
+
+        let ctrl_n = gpui::Keystroke {
+            modifiers: gpui::Modifiers {
+                control: true,
+                ..Default::default()
+            },
+            key: "n".into(),
+            key_char: None,
+        };
+        assert!(Chord::parse("ctrl+n").unwrap().matches(&ctrl_n));
+        assert!(!Chord::parse("n").unwrap().matches(&ctrl_n));
+        let plus = Chord::parse("cmd++").unwrap();
+        assert_eq!(plus.key, "+");
+        assert!(plus.cmd);
+        assert_eq!(Chord::parse("+").unwrap().key, "+");
+        let shift_k = gpui::Keystroke {
+            modifiers: gpui::Modifiers {
+                shift: true,
+                ..Default::default()
+            },
+            key: "k".into(),
+            key_char: Some("K".into()),
+        };
+        assert!(!Chord::parse("k").unwrap().matches(&shift_k));
+        assert!(Chord::parse("shift+k").unwrap().matches(&shift_k));
+
+        let keys = BTreeMap::from([
+            (
+                "down".to_string(),
+                vec!["ctrl+n".to_string(), "down".to_string()],
+            ),
+            ("toggle-chip".to_string(), vec!["cmd+9".to_string()]),
+            (
+                "remove".to_string(),
+                vec!["cmd+d".to_string(), "bogus+d".to_string()],
+            ),
+        ]);
+        let overrides = parse_overrides(&keys);
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(overrides[0].0, KeyAction::MoveDown);
+        assert_eq!(overrides[0].1.len(), 2);
+        assert_eq!(overrides[1].0, KeyAction::Remove);
+        assert_eq!(overrides[1].1, [Chord::parse("cmd+d").unwrap()]);
+        assert_eq!(overrides[2].0, KeyAction::ToggleChip);
+    }
+}
@@ -0,0 +1,556 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{
+    bytes,
+    config::SyncConfig,
+    content_hash,
+    protocol::{Entry, EntryType, ImportEntry},
+    sensitive,
+};
+
+const SERVICE_TYPE: &str = "_clipz._tcp";
+// Largest message accepted from a peer
+const MAX_MESSAGE_BYTES: u64 = 8 * 1024 * 1024;
+// Messages sent longer ago than this (or this far in the future) are taken as replays
+const MAX_CLOCK_SKEW_MS: i64 = 5 * 60 * 1000;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+// Peers read at once; later connections are dropped until one finishes
+const MAX_CONNECTIONS: usize = 16;
+// Content hashes remembered to stop echoes, oldest forgotten first
+const MAX_SEEN: usize = 10_000;
+// Mixed with the shared secret to derive the message key
+const KEY_CONTEXT: &[u8] = b"clipz sync v1";
+const NONCE_LEN: usize = 12;
+
+type HmacSha256 = Hmac<Sha256>;
+type Peers = Arc<Mutex<HashMap<String, (String, u16)>>>;
+type SharedSeen = Arc<Mutex<Seen>>;
+
+/// An entry as sent between Macs.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SyncEntry {
+    pub content: String,
+    #[serde(rename = "type")]
+    pub entry_type: EntryType,
+    /// Milliseconds since the epoch, like `Entry::timestamp`.
+    pub timestamp: i64,
+}
+
+impl SyncEntry {
+    /// `None` for images and files, whose content is a path that only exists on this
+    /// Mac, and for anything that looks like a secret.
+    fn from_entry(entry: &Entry) -> Option<Self> {
+        if matches!(entry.entry_type, EntryType::Image | EntryType::File) {
+            return None;
+        }
+        if sensitive::detect(entry).is_some() {
+            return None;
+        }
+        Some(Self {
+            content: entry.content.clone(),
+            entry_type: entry.entry_type,
+            timestamp: entry.timestamp,
+        })
+    }
+
+    /// Identifies the content regardless of which Mac copied it or when.
+    fn hash(&self) -> [u8; 32] {
+        content_hash::digest(self.entry_type, &self.content)
+    }
+}
+
+impl From<SyncEntry> for ImportEntry {
+    fn from(entry: SyncEntry) -> Self {
+        Self {
+            content: entry.content,
+            entry_type: entry.entry_type,
+            timestamp: entry.timestamp,
+            pinned: false,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Hashes of the content sent or received lately, so nothing is echoed back. Only the
+/// newest `MAX_SEEN` are kept; content older than that is long past any echo.
+#[derive(Default)]
+struct Seen {
+    hashes: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+}
+
+impl Seen {
+    /// False when `hash` was already seen.
+    fn insert(&mut self, hash: [u8; 32]) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > MAX_SEEN {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Payload {
+    from: String,
+    sent_at: i64,
+    entries: Vec<SyncEntry>,
+}
+
+/// Peer-to-peer sharing of new entries with Clipz on other Macs on the network.
+///
+/// Each Mac advertises itself over Bonjour with `dns-sd` and listens on a TCP port.
+/// New entries are pushed to every peer found as one line per message: the JSON
+/// payload sealed with AES-256-GCM under a key derived from the shared secret, so
+/// only peers with the secret can read or forge it. Content is hashed so an entry that
+/// arrives from a peer (or from two of them) is never sent back out.
+pub struct LanSync {
+    secret: Arc<Vec<u8>>,
+    name: String,
+    peers: Peers,
+    seen: SharedSeen,
+    incoming: Receiver<Vec<SyncEntry>>,
+    // The `dns-sd` registration and browse processes
+    children: Vec<Child>,
+    // Newest local timestamp already offered, so only later copies are sent
+    offered_until: i64,
+}
+
+impl LanSync {
+    pub fn start(config: &SyncConfig, now_ms: i64) -> Result<Self> {
+        if config.secret.is_empty() {
+            bail!("[sync] secret is empty");
+        }
+        let secret = Arc::new(config.secret.as_bytes().to_vec());
+        let name = format!("Clipz on {}", host_name());
+        let seen = Arc::new(Mutex::new(Seen::default()));
+
+        let listener = TcpListener::bind(("0.0.0.0", config.port))
+            .with_context(|| format!("failed to listen on port {}", config.port))?;
+        let (tx, incoming) = mpsc::channel();
+        {
+            let secret = secret.clone();
+            let seen = seen.clone();
+            thread::spawn(move || accept_loop(listener, secret, seen, tx));
+        }
+
+        let register = Command::new("dns-sd")
+            .args(["-R", &name, SERVICE_TYPE, "local", &config.port.to_string()])
+            .stdout(Stdio::null())
+            .spawn()
+            .context("failed to run dns-sd")?;
+        let mut browse = Command::new("dns-sd")
+            .args(["-B", SERVICE_TYPE, "local"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("failed to run dns-sd")?;
+
+        let peers: Peers = Arc::new(Mutex::new(HashMap::new()));
+        if let Some(stdout) = browse.stdout.take() {
+            let peers = peers.clone();
+            let own_name = name.clone();
+            thread::spawn(move || browse_loop(stdout, own_name, peers));
+        }
+
+        Ok(Self {
+            secret,
+            name,
+            peers,
+            seen,
+            incoming,
+            children: vec![register, browse],
+            offered_until: now_ms,
+        })
+    }
+
+    /// Sends entries copied since the last call to every peer, in the background.
+    pub fn offer(&mut self, entries: &[Entry]) {
+        let since = self.offered_until;
+        self.offered_until = entries
+            .iter()
+            .map(|entry| entry.timestamp)
+            .fold(since, i64::max);
+
+        let fresh: Vec<SyncEntry> = {
+            let mut seen = self.seen.lock().unwrap();
+            entries
+                .iter()
+                .filter(|entry| entry.timestamp > since)
+                .filter_map(SyncEntry::from_entry)
+                .filter(|entry| seen.insert(entry.hash()))
+                .collect()
+        };
+        let peers: Vec<(String, u16)> = self.peers.lock().unwrap().values().cloned().collect();
+        if fresh.is_empty() || peers.is_empty() {
+            return;
+        }
+
+        let payload = Payload {
+            from: self.name.clone(),
+            sent_at: crate::unix_now() * 1000,
+            entries: fresh,
+        };
+        let line = match seal(&self.secret, &payload) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to prepare sync message: {:#}", e);
+                return;
+            }
+        };
+        thread::spawn(move || {
+            for (host, port) in peers {
+                if let Err(e) = send_to(&host, port, &line) {
+                    eprintln!("Failed to sync with {}: {:#}", host, e);
+                }
+            }
+        });
+    }
+
+    /// Entries received from peers since the last call, oldest first. Content sent by
+    /// more than one peer is kept once, as of its latest copy.
+    pub fn take_incoming(&self) -> Vec<SyncEntry> {
+        latest_copies(self.incoming.try_iter().flatten().collect())
+    }
+
+    /// Withdraws the Bonjour advertisement and stops browsing.
+    pub fn stop(&mut self) {
+        for mut child in self.children.drain(..) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for LanSync {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn host_name() -> String {
+    Command::new("scutil")
+        .args(["--get", "ComputerName"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Mac".to_string())
+}
+
+/// One entry per content, with the latest timestamp it was copied at, oldest first.
+fn latest_copies(entries: Vec<SyncEntry>) -> Vec<SyncEntry> {
+    let mut latest: HashMap<[u8; 32], SyncEntry> = HashMap::new();
+    for entry in entries {
+        let hash = entry.hash();
+        match latest.get(&hash) {
+            Some(kept) if kept.timestamp >= entry.timestamp => {}
+            _ => {
+                latest.insert(hash, entry);
+            }
+        }
+    }
+    let mut entries: Vec<SyncEntry> = latest.into_values().collect();
+    entries.sort_by_key(|entry| entry.timestamp);
+    entries
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    secret: Arc<Vec<u8>>,
+    seen: SharedSeen,
+    tx: Sender<Vec<SyncEntry>>,
+) {
+    let open_connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        // Each peer is read on its own thread, so a slow one holds up no one else
+        if open_connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            open_connections.fetch_sub(1, Ordering::SeqCst);
+            continue;
+        }
+        let secret = secret.clone();
+        let seen = seen.clone();
+        let tx = tx.clone();
+        let open_connections = open_connections.clone();
+        thread::spawn(move || {
+            handle_connection(stream, &secret, &seen, &tx);
+            open_connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    secret: &[u8],
+    seen: &Mutex<Seen>,
+    tx: &Sender<Vec<SyncEntry>>,
+) {
+    let peer = stream
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    match receive(stream, secret) {
+        Ok(payload) => {
+            let mut seen = seen.lock().unwrap();
+            let entries: Vec<SyncEntry> = payload
+                .entries
+                .into_iter()
+                .filter(|entry| seen.insert(entry.hash()))
+                .collect();
+            drop(seen);
+            if !entries.is_empty() && tx.send(entries).is_ok() {
+                crate::wake::wake();
+            }
+        }
+        Err(e) => eprintln!("Rejected sync message from {}: {:#}", peer, e),
+    }
+}
+
+fn receive(stream: TcpStream, secret: &[u8]) -> Result<Payload> {
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_BYTES)).read_line(&mut line)?;
+    open(secret, &line, crate::unix_now() * 1000)
+}
+
+fn send_to(host: &str, port: u16, line: &str) -> Result<()> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("no address for {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// The AES-256-GCM cipher for `secret`, keyed by an HMAC-SHA256 of a fixed context.
+fn cipher(secret: &[u8]) -> Aes256Gcm {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(KEY_CONTEXT);
+    Aes256Gcm::new(&mac.finalize().into_bytes())
+}
+
+/// `<hex nonce> <hex sealed payload JSON>\n`, with a fresh random nonce each time.
+fn seal(secret: &[u8], payload: &Payload) -> Result<String> {
+    let json = serde_json::to_string(payload)?;
+    let nonce = bytes::random::<NONCE_LEN>().context("failed to generate nonce")?;
+    let sealed = cipher(secret)
+        .encrypt(Nonce::from_slice(&nonce), json.as_bytes())
+        .map_err(|_| anyhow!("failed to encrypt"))?;
+    Ok(format!(
+        "{} {}\n",
+        bytes::to_hex(&nonce),
+        bytes::to_hex(&sealed)
+    ))
+}
+
+fn open(secret: &[u8], line: &str, now_ms: i64) -> Result<Payload> {
+    let (nonce, sealed) = line
+        .trim_end()
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("malformed message"))?;
+    let nonce = bytes::from_hex(nonce)
+        .filter(|nonce| nonce.len() == NONCE_LEN)
+        .ok_or_else(|| anyhow!("malformed nonce"))?;
+    let sealed = bytes::from_hex(sealed).ok_or_else(|| anyhow!("malformed message"))?;
+    // Fails for a different secret and for any change to the message
+    let json = cipher(secret)
+        .decrypt(Nonce::from_slice(&nonce), sealed.as_slice())
+        .map_err(|_| anyhow!("bad signature"))?;
+
+    let payload: Payload = serde_json::from_slice(&json)?;
+    if (now_ms - payload.sent_at).abs() > MAX_CLOCK_SKEW_MS {
+        bail!("stale message from {}", payload.from);
+    }
+    Ok(payload)
+}
+
+fn browse_loop(stdout: impl Read + Send + 'static, own_name: String, peers: Peers) {
+    for line in BufReader::new(stdout).lines() {
+        let Ok(line) = line else { return };
+        let Some((added, name)) = parse_browse_line(&line) else {
+            continue;
+        };
+        if name == own_name {
+            continue;
+        }
+        if !added {
+            peers.lock().unwrap().remove(&name);
+            continue;
+        }
+        let peers = peers.clone();
+        thread::spawn(move || match resolve(&name) {
+            Some(address) => {
+                peers.lock().unwrap().insert(name, address);
+            }
+            None => eprintln!("Failed to resolve sync peer {}", name),
+        });
+    }
+}
+
+/// Host and port of a Bonjour service instance, via `dns-sd -L`.
+fn resolve(name: &str) -> Option<(String, u16)> {
+    let mut child = Command::new("dns-sd")
+        .args(["-L", name, SERVICE_TYPE, "local"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let stdout = child.stdout.take()?;
+    let child = Arc::new(Mutex::new(child));
+    // dns-sd keeps running after it answers; killing it also ends the read below
+    {
+        let child = child.clone();
+        thread::spawn(move || {
+            thread::sleep(RESOLVE_TIMEOUT);
+            let _ = child.lock().unwrap().kill();
+        });
+    }
+    let address = BufReader::new(stdout)
+        .lines()
+        .map_while(|line| line.ok())
+        .find_map(|line| parse_resolve_line(&line));
+    let mut child = child.lock().unwrap();
+    let _ = child.kill();
+    let _ = child.wait();
+    address
+}
+
+/// `10:51:12.345  Add  3  4 local.  _clipz._tcp.  Clipz on desk` → `(true, "Clipz on desk")`
+fn parse_browse_line(line: &str) -> Option<(bool, String)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let added = match fields.get(1) {
+        Some(&"Add") => true,
+        Some(&"Rmv") => false,
+        _ => return None,
+    };
+    if !fields.get(5)?.starts_with(SERVICE_TYPE) || fields.len() < 7 {
+        return None;
+    }
+    Some((added, fields[6..].join(" ")))
+}
+
+/// `… can be reached at desk.local.:47811 (interface 4)` → `("desk.local", 47811)`
+fn parse_resolve_line(line: &str) -> Option<(String, u16)> {
+    let (_, rest) = line.split_once(" can be reached at ")?;
+    let target = rest.split_whitespace().next()?;
+    let (host, port) = target.rsplit_once(':')?;
+    Some((host.trim_end_matches('.').to_string(), port.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dns_sd_output() {
+        assert_eq!(
+            parse_browse_line(
+                "10:51:12.345  Add        3   4 local.               _clipz._tcp.         Clipz on desk"
+            ),
+            Some((true, "Clipz on desk".to_string()))
+        );
+        assert_eq!(
+            parse_browse_line(
+                "10:52:00.001  Rmv        0   4 local.               _clipz._tcp.         Clipz on desk"
+            ),
+            Some((false, "Clipz on desk".to_string()))
+        );
+        assert_eq!(
+            parse_browse_line(
+                "Timestamp     A/R    Flags  if Domain   Service Type   Instance Name"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_resolve_line(
+                "10:51:12.400  Clipz\\032on\\032desk._clipz._tcp.local. can be reached at desk.local.:47811 (interface 4)"
+            ),
+            Some(("desk.local".to_string(), 47811))
+        );
+    }
+
+    #[test]
+    fn messages_only_open_with_the_same_secret_while_fresh() {
+        let payload = Payload {
+            from: "Clipz on desk".into(),
+            sent_at: 1_000_000,
+            entries: vec![SyncEntry {
+                content: "git pull --rebase".into(),
+                entry_type: EntryType::Text,
+                timestamp: 1_000_000,
+            }],
+        };
+        let line = seal(b"correct horse", &payload).unwrap();
+
+        let opened = open(b"correct horse", &line, 1_060_000).unwrap();
+        assert_eq!(opened.entries, payload.entries);
+        // The content doesn't travel in the clear
+        assert!(!line.contains("git pull"));
+        assert_ne!(line, seal(b"correct horse", &payload).unwrap());
+        assert!(open(b"battery staple", &line, 1_060_000).is_err());
+        let last = line.trim_end().len() - 1;
+        let flipped = if &line[last..=last] == "0" { "1" } else { "0" };
+        let tampered = format!("{}{}\n", &line[..last], flipped);
+        assert!(open(b"correct horse", &tampered, 1_060_000).is_err());
+        assert!(open(b"correct horse", &line, 1_000_000 + MAX_CLOCK_SKEW_MS + 1).is_err());
+    }
+
+    #[test]
+    fn seen_forgets_the_oldest_hashes() {
+        let mut seen = Seen::default();
+        let hash = |n: usize| {
+            let mut hash = [0u8; 32];
+            hash[..8].copy_from_slice(&n.to_le_bytes());
+            hash
+        };
+        assert!(seen.insert(hash(0)));
+        assert!(!seen.insert(hash(0)));
+        for n in 1..=MAX_SEEN {
+            assert!(seen.insert(hash(n)));
+        }
+        assert_eq!(seen.hashes.len(), MAX_SEEN);
+        assert!(seen.insert(hash(0)));
+        assert!(!seen.insert(hash(MAX_SEEN)));
+    }
+
+    #[test]
+    fn content_from_several_peers_keeps_its_latest_copy() {
+        let entry = |content: &str, timestamp| SyncEntry {
+            content: content.into(),
+            entry_type: EntryType::Text,
+            timestamp,
+        };
+        let merged = latest_copies(vec![entry("a", 30), entry("b", 10), entry("a", 50)]);
+        assert_eq!(merged, vec![entry("b", 10), entry("a", 50)]);
+    }
+}
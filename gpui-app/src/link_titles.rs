@@ -0,0 +1,522 @@
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    net::{IpAddr, Ipv4Addr, ToSocketAddrs},
+    process::{Command, Stdio},
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::{
+    protocol::{Entry, EntryType},
+    sensitive, thumbnails,
+};
+
+/// Page titles of URL entries, by URL. URLs queued, failed or without a title map to
+/// an empty string.
+pub static TITLES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Set whenever a title arrives so the event loop knows to repaint.
+pub static TITLES_UPDATED: AtomicBool = AtomicBool::new(false);
+
+// Give up on slow pages after this many seconds
+const TIMEOUT_SECS: u32 = 5;
+// Titles are in the head, so there's no need to download all of a big page
+const MAX_BYTES: usize = 256 * 1024;
+// Longest title kept, in characters
+const TITLE_LEN: usize = 120;
+const MAX_REDIRECTS: usize = 5;
+// Names that only mean something on this Mac or the local network
+const PRIVATE_SUFFIXES: [&str; 6] = [
+    ".localhost",
+    ".local",
+    ".internal",
+    ".lan",
+    ".home.arpa",
+    ".corp",
+];
+
+/// Fetches the `<title>` of copied web pages in the background, so URL rows can show
+/// it instead of the bare address.
+///
+/// Pages are fetched one at a time with `curl` on a worker thread. Titles are cached on
+/// disk next to the thumbnails, keyed by the URL, so each page is only fetched once;
+/// failures are only remembered until Clipz quits. With the history encrypted they
+/// are kept in memory only. URLs that look like secrets (reset links with tokens and
+/// the like) are never fetched, and neither is anything on this Mac or the local
+/// network, before or after a redirect.
+pub struct TitleFetcher {
+    tx: Sender<String>,
+}
+
+impl TitleFetcher {
+    /// `cache_on_disk` is off when the history is encrypted, since titles give away
+    /// what was copied.
+    pub fn start(cache_on_disk: bool) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for url in rx {
+                let fetched = if cache_on_disk {
+                    fetch_cached(&url)
+                } else {
+                    fetch(&url).map(|page| parse_title(&page).unwrap_or_default())
+                };
+                let title = fetched.unwrap_or_else(|e| {
+                    eprintln!("Failed to fetch the title of {}: {:#}", url, e);
+                    String::new()
+                });
+                if !title.is_empty() {
+                    TITLES.lock().unwrap().insert(url, title);
+                    crate::wake::set(&TITLES_UPDATED);
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queues the web page entries that haven't been looked at yet.
+    pub fn queue(&self, entries: &[Entry]) {
+        let mut titles = TITLES.lock().unwrap();
+        for entry in entries {
+            if !is_web_page(entry) || titles.contains_key(&entry.content) {
+                continue;
+            }
+            if sensitive::detect(entry).is_some() {
+                continue;
+            }
+            titles.insert(entry.content.clone(), String::new());
+            let _ = self.tx.send(entry.content.clone());
+        }
+    }
+}
+
+fn is_web_page(entry: &Entry) -> bool {
+    entry.entry_type == EntryType::Url
+        && ["http://", "https://"]
+            .iter()
+            .any(|scheme| entry.content.starts_with(scheme))
+}
+
+/// The fetched title of a URL entry, which search matches too.
+pub fn title_for<'a>(entry: &Entry, titles: &'a BTreeMap<String, String>) -> Option<&'a str> {
+    if entry.entry_type != EntryType::Url {
+        return None;
+    }
+    titles
+        .get(&entry.content)
+        .map(String::as_str)
+        .filter(|title| !title.is_empty())
+}
+
+/// The page title of a URL entry, once it has been fetched.
+pub fn title(entry: &Entry) -> Option<String> {
+    title_for(entry, &TITLES.lock().unwrap()).map(str::to_string)
+}
+
+/// The host of a URL, without `www.`, shown under a row that displays the title.
+pub fn host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    (!host.is_empty()).then_some(host)
+}
+
+fn fetch_cached(url: &str) -> Result<String> {
+    let dir = thumbnails::cache_dir().with_file_name("titles");
+    let cached = dir.join(format!(
+        "{:016x}.txt",
+        thumbnails::cache_key(url.as_bytes())
+    ));
+    if let Ok(title) = std::fs::read_to_string(&cached) {
+        return Ok(title);
+    }
+
+    let title = parse_title(&fetch(url)?).unwrap_or_default();
+    std::fs::create_dir_all(&dir).context("failed to create title cache")?;
+    std::fs::write(&cached, &title).context("failed to cache title")?;
+    Ok(title)
+}
+
+/// The start of the page, up to `MAX_BYTES`. Redirects are followed here rather than
+/// by curl so each hop is checked with [`public_address`].
+fn fetch(url: &str) -> Result<String> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let response = fetch_once(&url)?;
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| anyhow!("malformed response"))?;
+        match redirect_target(head) {
+            Some(location) => url = resolve_location(&url, location)?,
+            None => return Ok(body.to_string()),
+        }
+    }
+    bail!("too many redirects")
+}
+
+/// The response to `url`, headers first; curl is stopped once `MAX_BYTES` arrived.
+fn fetch_once(url: &str) -> Result<String> {
+    let (host, port, address) = public_address(url)?;
+    // Connects to the address that was checked, not whatever the name resolves to next
+    let pinned = match address {
+        IpAddr::V4(v4) => format!("{}:{}:{}", host, port, v4),
+        IpAddr::V6(v6) => format!("{}:{}:[{}]", host, port, v6),
+    };
+    let mut child = Command::new("curl")
+        .args(["--silent", "--include", "--proto", "=http,https"])
+        .args(["--resolve", &pinned])
+        .args(["--max-time", &TIMEOUT_SECS.to_string()])
+        .args(["--header", "Accept: text/html"])
+        .args(["--user-agent", "Mozilla/5.0 (compatible; Clipz)"])
+        .arg("--")
+        .arg(url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run curl")?;
+    let mut body = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        stdout
+            .take(MAX_BYTES as u64)
+            .read_to_end(&mut body)
+            .context("failed to read the page")?;
+    }
+    let _ = child.kill();
+    let status = child.wait().context("failed to wait for curl")?;
+    // Killed after enough of the page arrived is fine; nothing at all is not
+    if body.is_empty() {
+        bail!("curl exited with {}", status);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// The `Location` of a 3xx response, given its status line and headers.
+fn redirect_target(head: &str) -> Option<&str> {
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?;
+    if !status.starts_with('3') {
+        return None;
+    }
+    lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim())
+    })
+}
+
+/// `location` made absolute against `base`, the URL that redirected to it.
+fn resolve_location(base: &str, location: &str) -> Result<String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return Ok(location.to_string());
+    }
+    let (scheme, rest) = base
+        .split_once("://")
+        .ok_or_else(|| anyhow!("bad redirect"))?;
+    if let Some(authority_path) = location.strip_prefix("//") {
+        return Ok(format!("{}://{}", scheme, authority_path));
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    if location.starts_with('/') {
+        return Ok(format!("{}://{}{}", scheme, authority, location));
+    }
+    // Relative to the directory of the current path
+    let path = rest[authority.len()..]
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("");
+    let dir = path.rfind('/').map_or("/", |slash| &path[..=slash]);
+    Ok(format!("{}://{}{}{}", scheme, authority, dir, location))
+}
+
+/// Host, port and the address to connect to for `url`, refusing hosts on this Mac or
+/// the local network: an intranet page, or a link that only works once, would give
+/// itself away or get used up just by being copied.
+fn public_address(url: &str) -> Result<(String, u16, IpAddr)> {
+    let (scheme, rest) = url.split_once("://").ok_or_else(|| anyhow!("not a URL"))?;
+    let default_port = if scheme.eq_ignore_ascii_case("https") {
+        443
+    } else {
+        80
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    let (host, port) = split_host_port(authority, default_port)?;
+    if !is_public_name(host) {
+        bail!("{} is on the local network", host);
+    }
+
+    let addresses: Vec<IpAddr> = (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("failed to resolve {}", host))?
+        .map(|address| address.ip())
+        .collect();
+    if addresses.is_empty() || !addresses.iter().all(is_public_ip) {
+        bail!("{} is on the local network", host);
+    }
+    Ok((host.to_string(), port, addresses[0]))
+}
+
+/// `example.com:8080` → `("example.com", 8080)`; `[::1]` keeps its brackets off.
+fn split_host_port(authority: &str, default_port: u16) -> Result<(&str, u16)> {
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        let (host, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| anyhow!("bad host"))?;
+        (host, after.strip_prefix(':'))
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    let port = match port {
+        Some(port) => port.parse().context("bad port")?,
+        None => default_port,
+    };
+    if host.is_empty() {
+        bail!("no host");
+    }
+    Ok((host, port))
+}
+
+/// False for names that can only be reached from here: `localhost`, single-label
+/// intranet names and the usual local suffixes. IP literals are checked once resolved.
+fn is_public_name(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+    host != "localhost"
+        && host.contains('.')
+        && !PRIVATE_SUFFIXES.iter().any(|suffix| host.ends_with(suffix))
+}
+
+fn is_public_ip(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => is_public_v4(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_v4(&v4),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+fn is_public_v4(v4: &Ipv4Addr) -> bool {
+    let [a, b, ..] = v4.octets();
+    !(v4.is_private()
+        || v4.is_loopback()
+        || v4.is_link_local()
+        || v4.is_unspecified()
+        || v4.is_broadcast()
+        || v4.is_multicast()
+        || v4.is_documentation()
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        || a == 0)
+}
+
+/// The text of the first `<title>` element, with entities decoded and whitespace
+/// collapsed, shortened to `TITLE_LEN` characters.
+fn parse_title(html: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets lined up with `html`
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = decode_entities(&html[start..end])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    if title.chars().count() <= TITLE_LEN {
+        return Some(title);
+    }
+    let mut short: String = title.chars().take(TITLE_LEN - 1).collect();
+    short.push('\u{2026}');
+    Some(short)
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest
+            .find(';')
+            .filter(|&semi| semi <= 10)
+            .and_then(|semi| Some((decode_entity(&rest[1..semi])?, semi)));
+        match entity {
+            Some((c, semi)) => {
+                decoded.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    let code = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if let Some(dec) = name.strip_prefix('#') {
+        dec.parse().ok()?
+    } else {
+        return match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "ndash" => Some('\u{2013}'),
+            "mdash" => Some('\u{2014}'),
+            "middot" => Some('\u{00b7}'),
+            _ => None,
+        };
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_read_from_the_head() {
+        let html = "<html><HEAD><Title data-x=\"1\">\n  GitHub &ndash; clipz\n  issues &amp; PRs &#x1F4CB;</title>\
+                    <title>second</title></head>";
+        assert_eq!(
+            parse_title(html).as_deref(),
+            Some("GitHub \u{2013} clipz issues & PRs \u{1f4cb}")
+        );
+        assert_eq!(parse_title("<title>  </title>"), None);
+        assert_eq!(parse_title("<p>no title</p>"), None);
+        assert_eq!(decode_entities("a & b &bogus; &#65;"), "a & b &bogus; A");
+
+        let long = parse_title(&format!("<title>{}</title>", "word ".repeat(40))).unwrap();
+        assert_eq!(long.chars().count(), TITLE_LEN);
+        assert!(long.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn local_hosts_are_never_fetched() {
+        assert!(is_public_name("github.com"));
+        assert!(is_public_name("93.184.215.14"));
+        assert!(!is_public_name("localhost"));
+        assert!(!is_public_name("LOCALHOST."));
+        assert!(!is_public_name("wiki"));
+        assert!(!is_public_name("printer.local"));
+        assert!(!is_public_name("jira.corp"));
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(is_public_ip(&ip("93.184.215.14")));
+        assert!(is_public_ip(&ip("2606:4700::1111")));
+        for local in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.1",
+        ] {
+            assert!(!is_public_ip(&ip(local)), "{}", local);
+        }
+
+        assert!(public_address("http://127.0.0.1:8080/admin").is_err());
+        assert!(public_address("https://[::1]/").is_err());
+        assert!(public_address("https://user@localhost/").is_err());
+        assert_eq!(
+            split_host_port("[2606:4700::1111]:8443", 443).unwrap(),
+            ("2606:4700::1111", 8443)
+        );
+        assert_eq!(
+            split_host_port("example.com", 80).unwrap(),
+            ("example.com", 80)
+        );
+    }
+
+    #[test]
+    fn redirects_are_followed_by_hand() {
+        let head = "HTTP/1.1 301 Moved Permanently\r\nContent-Length: 0\r\nlocation: /docs/";
+        assert_eq!(redirect_target(head), Some("/docs/"));
+        assert_eq!(redirect_target("HTTP/2 200\r\nLocation: /x"), None);
+        assert_eq!(
+            resolve_location("https://example.com/a/b?q=1", "/docs/").unwrap(),
+            "https://example.com/docs/"
+        );
+        assert_eq!(
+            resolve_location("https://example.com/a/b?q=1", "c").unwrap(),
+            "https://example.com/a/c"
+        );
+        assert_eq!(
+            resolve_location("http://example.com", "//cdn.example.com/x").unwrap(),
+            "http://cdn.example.com/x"
+        );
+        assert_eq!(
+            resolve_location("http://example.com", "https://example.org/").unwrap(),
+            "https://example.org/"
+        );
+    }
+
+    #[test]
+    fn only_web_pages_get_titles() {
+        let url = Entry {
+            id: 1,
+            content: "https://www.github.com/DuarteFaria/clipz/issues?q=is%3Aopen".into(),
+            entry_type: EntryType::Url,
+            ..Default::default()
+        };
+        assert!(is_web_page(&url));
+        assert!(!is_web_page(&Entry {
+            content: "ftp://example.com/file".into(),
+            ..url.clone()
+        }));
+        assert_eq!(host(&url.content), Some("github.com"));
+        assert_eq!(
+            host("https://user@example.com:8080#top"),
+            Some("example.com:8080")
+        );
+        assert_eq!(host("not a url"), None);
+
+        let mut titles = BTreeMap::new();
+        titles.insert(url.content.clone(), String::new());
+        assert_eq!(title_for(&url, &titles), None);
+        titles.insert(url.content.clone(), "Issues".to_string());
+        assert_eq!(title_for(&url, &titles), Some("Issues"));
+        let text = Entry {
+            entry_type: EntryType::Text,
+            ..url.clone()
+        };
+        assert_eq!(title_for(&text, &titles), None);
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::{atomic::AtomicBool, Mutex};
+
+use crate::{protocol::Entry, wake};
+
+/// Entries requested per `get-entries` page: the newest ones at launch, then another
+/// page whenever the list is scrolled to its end or a regex search runs out of entries.
+pub const PAGE_SIZE: usize = 200;
+
+/// How much of the history has loaded, for the popover's footer and loader row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    /// Zero until the first page says how long the history is.
+    pub total: usize,
+    /// A page was asked for and hasn't arrived yet.
+    pub fetching: bool,
+}
+
+impl LoadProgress {
+    /// Whether older entries are left to load.
+    pub fn has_more(&self) -> bool {
+        self.loaded < self.total
+    }
+
+    /// Before the first page, when the list shows placeholder rows.
+    pub fn is_starting(&self) -> bool {
+        self.fetching && self.total == 0 && self.loaded == 0
+    }
+}
+
+/// Published by the event loop after each pass, for the popover.
+pub static LOAD_PROGRESS: Mutex<LoadProgress> = Mutex::new(LoadProgress {
+    loaded: 0,
+    total: 0,
+    fetching: false,
+});
+
+/// Set when the popover wants the next page: its loader row came into view, or a
+/// regex search, which the backend can't run, has older entries still unloaded.
+pub static LOAD_MORE: AtomicBool = AtomicBool::new(false);
+
+pub fn load_more() {
+    wake::set(&LOAD_MORE);
+}
+
+/// Set when something needs the whole history rather than the next page: a deep link
+/// to an entry that isn't loaded, a paste stack entry queued long ago, the digest.
+/// Stays set until a page with the rest of the history has been asked for.
+pub static LOAD_ALL: AtomicBool = AtomicBool::new(false);
+
+pub fn load_all() {
+    wake::set(&LOAD_ALL);
+}
+
+/// Folds an `entries` message into `entries`, the newest part of the history: a page
+/// from the top replaces them, the page right after them extends them, and any other
+/// is stale. Returns whether it was used. The backend keeps its copy of what was sent
+/// the same way, so the `entry-*` changes that follow fit. Shared by the app and
+/// `--replay`, so a replayed session ends up with the list the app showed.
+pub fn receive(entries: &mut Vec<Entry>, data: Vec<Entry>, offset: usize) -> bool {
+    if offset == 0 {
+        *entries = data;
+        true
+    } else if offset == entries.len() && !data.is_empty() {
+        entries.extend(data);
+        true
+    } else {
+        false
+    }
+}
+
+/// Puts a new or changed entry from an `entry-added` or `entry-updated` message right
+/// after the entry `after` (at the top for None), taking it out of its old place first.
+/// Returns false when `after` isn't in the list: it no longer matches the backend's
+/// and needs a full `get-entries`.
+pub fn place(entries: &mut Vec<Entry>, after: Option<u64>, entry: Entry) -> bool {
+    entries.retain(|e| e.id != entry.id);
+    let index = match after {
+        None => 0,
+        Some(after) => match entries.iter().position(|e| e.id == after) {
+            Some(index) => index + 1,
+            None => return false,
+        },
+    };
+    entries.insert(index, entry);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::EntryType;
+
+    fn entries(ids: std::ops::Range<u64>) -> Vec<Entry> {
+        ids.map(|id| Entry {
+            id,
+            content: id.to_string(),
+            is_current: id == 0,
+            ..Default::default()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn pages_extend_the_list_only_where_it_ends() {
+        let mut list = Vec::new();
+        assert!(!receive(&mut list, entries(200..400), 200));
+        assert!(receive(&mut list, entries(0..200), 0));
+        assert!(!receive(&mut list, entries(300..400), 300));
+        assert!(receive(&mut list, entries(200..400), 200));
+        assert_eq!(list.len(), 400);
+        assert!(!receive(&mut list, Vec::new(), 400));
+
+        // A page from the top starts over, as after a restart
+        assert!(receive(&mut list, entries(0..200), 0));
+        assert_eq!(list.len(), 200);
+
+        let progress = LoadProgress {
+            loaded: 200,
+            total: 450,
+            fetching: false,
+        };
+        assert!(progress.has_more() && !progress.is_starting());
+    }
+
+    #[test]
+    fn changes_are_placed_after_their_neighbour() {
+        let ids = |list: &[Entry]| list.iter().map(|e| e.id).collect::<Vec<_>>();
+        let mut list = entries(0..3);
+        let moved = list[2].clone();
+        assert!(place(&mut list, None, moved));
+        assert_eq!(ids(&list), [2, 0, 1]);
+        let added = entries(5..6).remove(0);
+        assert!(place(&mut list, Some(0), added));
+        assert_eq!(ids(&list), [2, 0, 5, 1]);
+
+        let stray = entries(7..8).remove(0);
+        assert!(!place(&mut list, Some(9), stray));
+        assert_eq!(ids(&list), [2, 0, 5, 1]);
+    }
+}
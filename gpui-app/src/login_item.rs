@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Starts Clipz when the user logs in, or stops doing so, as set by `[behavior]
+/// launch_at_login`: a login item registered through SMAppService on macOS, an XDG
+/// autostart entry elsewhere.
+#[cfg(target_os = "macos")]
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    macos::set_enabled(enabled)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    let path = autostart_path().context("no config directory")?;
+    if !enabled {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("failed to remove {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    let exe = std::env::current_exe().context("failed to find the Clipz executable")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("failed to create autostart directory")?;
+    }
+    std::fs::write(&path, desktop_entry(&exe))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// `$XDG_CONFIG_HOME/autostart/clipz.desktop`, where desktop sessions look for programs
+/// to start at login.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn autostart_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("autostart/clipz.desktop"))
+}
+
+/// The autostart entry running `exe`, quoted as the Desktop Entry spec asks for an Exec
+/// key.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn desktop_entry(exe: &Path) -> String {
+    let mut quoted = String::from("\"");
+    for c in exe.to_string_lossy().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Clipz\n\
+         Comment=Clipboard history\n\
+         Exec={}\n\
+         Terminal=false\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quoted
+    )
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use anyhow::{bail, Result};
+    use cocoa::base::{id, nil};
+    use objc::{msg_send, runtime::Class, sel, sel_impl};
+
+    #[link(name = "ServiceManagement", kind = "framework")]
+    extern "C" {}
+
+    /// Registers the app bundle as a login item, as System Settings > General > Login
+    /// Items lists it. Needs macOS 13 and a bundled app.
+    pub fn set_enabled(enabled: bool) -> Result<()> {
+        let Some(class) = Class::get("SMAppService") else {
+            bail!("login items need macOS 13 or later");
+        };
+        unsafe {
+            let service: id = msg_send![class, mainAppService];
+            let mut error: id = nil;
+            let ok: bool = if enabled {
+                msg_send![service, registerAndReturnError: &mut error]
+            } else {
+                msg_send![service, unregisterAndReturnError: &mut error]
+            };
+            if !ok && error != nil {
+                let description: id = msg_send![error, localizedDescription];
+                let text: *const std::os::raw::c_char = msg_send![description, UTF8String];
+                bail!("{}", std::ffi::CStr::from_ptr(text).to_string_lossy());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_entry_quotes_the_executable() {
+        let entry = desktop_entry(Path::new("/opt/my apps/clipz$1"));
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Exec=\"/opt/my apps/clipz\\$1\"\n"));
+    }
+}
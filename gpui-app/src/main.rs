@@ -1,30 +1,118 @@
 #![allow(unexpected_cfgs)]
 
+mod access_log;
+mod archive;
+mod auto_paste;
+mod bulk;
+mod bytes;
+mod code_format;
+mod config;
+mod content_hash;
+mod deep_link;
+mod digest;
+mod disk_space;
+mod dock;
+mod drag_out;
+mod dump;
+mod edit;
+mod export;
+mod frecency;
+mod health;
+mod highlight;
+mod history_key;
+mod image_presets;
+mod image_titles;
+mod keymap;
+mod lan_sync;
+mod link_titles;
+mod loading;
+mod login_item;
+mod markdown;
+mod navigation;
+mod notice;
+mod ocr;
+mod paste_stack;
+mod pipe;
+mod plugins;
+mod preview;
+mod protocol;
+mod qr;
+mod quick_actions;
+mod recent;
+mod search;
+mod search_history;
+mod sensitive;
+mod session;
+mod snapshot;
+mod snippets;
+mod spaces;
+mod split;
+mod status_menu;
+mod tags;
+mod theme;
+mod thumbnails;
+mod timeline;
+mod transforms;
+mod trash;
+mod wake;
+
 use std::{
+    collections::{HashMap, HashSet},
     io::{BufRead, BufReader, Write},
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
     process::{Child, Command, Stdio},
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         mpsc::{self, Receiver, Sender},
         Arc, Mutex,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Context, Result};
-use global_hotkey::{
-    hotkey::{Code, HotKey, Modifiers},
-    GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState,
-};
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use global_hotkey::{hotkey::HotKey, GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
 use gpui::{
-    div, img, point, prelude::*, px, rgb, rgba, size, App, Application, AssetSource, Bounds,
-    Context as GpuiContext, Entity, FocusHandle, Focusable, IntoElement, Pixels, Point,
-    ScrollHandle, SharedString, Window, WindowBackgroundAppearance, WindowBounds, WindowHandle,
-    WindowKind, WindowOptions,
+    anchored, deferred, div, ease_out_quint, img, point, prelude::*, pulsating_between, px, rgb,
+    rgba, size, uniform_list, Animation, AnimationExt, AnyElement, App, Application, AssetSource,
+    Bounds, Context as GpuiContext, Entity, FocusHandle, Focusable, FutureExt, HighlightStyle,
+    IntoElement, MouseButton, MouseMoveEvent, Pixels, Point, ScrollHandle, ScrollStrategy,
+    SharedString, StyledText, TitlebarOptions, UniformListScrollHandle, Window,
+    WindowBackgroundAppearance, WindowBounds, WindowHandle, WindowKind, WindowOptions,
+};
+
+use access_log::AccessKind;
+use bulk::{BulkEdit, BulkJob};
+use config::{
+    ClickBehavior, CombineSeparator, Config, DockEdge, EntryOrder, FormatterConfig, HotkeyConfig,
+    StartupAction,
 };
-use serde::Deserialize;
+use deep_link::DeepLink;
+use edit::EntryEditor;
+use frecency::Usage;
+use health::{BackendStatus, Health};
+use image_presets::ResizePreset;
+use keymap::{KeyAction, KeyContext};
+use lan_sync::LanSync;
+use loading::{LoadProgress, LOAD_ALL, LOAD_MORE, LOAD_PROGRESS, PAGE_SIZE};
+use navigation::{DaySection, ListRow};
+use plugins::{Plugin, PluginAction};
+use preview::FindState;
+use protocol::{
+    BackendMessage, Command as BackendCommand, CommandError, Entry, EntryType, Envelope, Format,
+    ImportEntry, Request, PROTOCOL_VERSION,
+};
+use quick_actions::QuickAction;
+use recent::{EntryAction, RecentActions};
+use search_history::SearchHistory;
+use snippets::{SnippetPrompt, SNIPPETS};
+use split::SplitPrompt;
+use status_menu::{MenuAction, PENDING_MENU_ACTIONS};
+use tags::TagEditor;
+use theme::{tint, Theme, ThemeName, ThemeOverrides, ThemeWatcher};
+use thumbnails::{Thumbnail, Thumbnails, THUMBNAILS_UPDATED};
 
 #[cfg(target_os = "macos")]
 use {
@@ -44,79 +132,207 @@ use {
 
 static MENU_BAR_CLICKED: AtomicBool = AtomicBool::new(false);
 static POPOVER_SHOULD_CLOSE: AtomicBool = AtomicBool::new(false);
+// Cmd+N pressed in a history view; the event loop opens another window
+static NEW_WINDOW_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Pointer reached the docked edge strip; the event loop slides the popover out
+static DOCK_HOVERED: AtomicBool = AtomicBool::new(false);
+// Pointer left the docked popover; the event loop hides it again
+static DOCK_LEFT: AtomicBool = AtomicBool::new(false);
+// Chord recorded in the popover, picked up by the event loop to re-register the hotkey
+static PENDING_HOTKEY: Mutex<Option<String>> = Mutex::new(None);
+// Theme picked in the popover, persisted by the event loop
+static PENDING_THEME: Mutex<Option<ThemeName>> = Mutex::new(None);
+// clipz:// URLs delivered by the OS, handled on the event loop's next pass
+static PENDING_URLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Response to a copy a popover made with auto-paste on, handed to the event loop, which
+// pastes once that request is answered
+static PASTE_REQUEST: Mutex<Option<PendingResponse>> = Mutex::new(None);
 
 #[cfg(target_os = "macos")]
 static mut STATUS_ITEM: *mut Object = std::ptr::null_mut();
 
 // ---------- Backend types ----------
 
-#[derive(Clone, Debug, Deserialize)]
-#[serde(tag = "type")]
-enum BackendMessage {
-    #[serde(rename = "entries")]
-    Entries { data: Vec<Entry> },
-    #[serde(rename = "select-success")]
-    SelectSuccess,
-    #[serde(rename = "remove-success")]
-    RemoveSuccess,
-    #[serde(rename = "pin-toggled")]
-    PinToggled,
-    #[serde(rename = "success")]
-    Success,
-    #[serde(rename = "ready")]
-    Ready {
-        #[serde(default)]
-        #[serde(rename = "supportsIdCommands")]
-        supports_id_commands: bool,
-    },
-    #[serde(other)]
-    Unknown,
+#[derive(Debug, thiserror::Error)]
+enum BackendError {
+    #[error("failed to send command")]
+    SendFailed,
+    #[error("backend closed before responding")]
+    Disconnected,
+    #[error("backend did not respond in time")]
+    TimedOut,
 }
 
-#[derive(Clone, Debug, Deserialize)]
-struct Entry {
-    id: u64,
-    content: String,
-    timestamp: i64,
-    #[serde(default)]
-    #[serde(rename = "type")]
-    entry_type: EntryType,
-    #[serde(default)]
-    #[serde(rename = "isCurrent")]
-    is_current: bool,
-    #[serde(default)]
-    pinned: bool,
+type CommandResult = std::result::Result<BackendMessage, CommandError>;
+type PendingRequests = Arc<Mutex<HashMap<u64, Sender<CommandResult>>>>;
+
+/// Cloneable sender half of the backend connection. Every command gets a request id
+/// so its response can be matched up by `pump_messages`.
+#[derive(Clone)]
+struct BackendClient {
+    // Swapped for the new backend's when it is restarted, in every clone at once
+    tx: Arc<Mutex<Sender<Request>>>,
+    next_request_id: Arc<AtomicU64>,
+    pending: PendingRequests,
+}
+
+impl BackendClient {
+    fn send(&self, command: BackendCommand) -> Result<PendingResponse> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+
+        if self
+            .tx
+            .lock()
+            .unwrap()
+            .send(Request::new(id, command))
+            .is_err()
+        {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(BackendError::SendFailed.into());
+        }
+        Ok(PendingResponse { rx })
+    }
+
+    /// The whole history, however much of it the app has loaded; the app's list gets
+    /// all of it too, as the response is passed on like any other. Blocks, so it is
+    /// for background tasks.
+    fn all_entries(&self) -> Result<Vec<Entry>> {
+        match self
+            .send(BackendCommand::GetEntries)?
+            .wait(Duration::from_secs(5))?
+        {
+            BackendMessage::Entries { data, .. } => Ok(data),
+            other => Err(anyhow!("unexpected response {:?}", other)),
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Default)]
-#[serde(rename_all = "lowercase")]
-enum EntryType {
-    #[default]
-    Text,
-    Image,
-    File,
-    Url,
-    Color,
+/// The eventual response to a single command.
+struct PendingResponse {
+    rx: Receiver<CommandResult>,
 }
 
-#[derive(Debug, thiserror::Error)]
-enum BackendError {
-    #[error("failed to send command")]
-    SendFailed,
+impl PendingResponse {
+    /// Returns the response if it has arrived, without blocking.
+    fn try_result(&self) -> Option<Result<BackendMessage>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result.map_err(Into::into)),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(BackendError::Disconnected.into())),
+        }
+    }
+
+    /// Blocks until the response arrives, giving up after `timeout`.
+    fn wait(self, timeout: Duration) -> Result<BackendMessage> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(result) => result.map_err(Into::into),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(BackendError::TimedOut.into()),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(BackendError::Disconnected.into()),
+        }
+    }
+}
+
+// How long an entry command may go unanswered before the UI offers to retry it
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+// How long Undo is offered after entries are removed; the backend keeps them longer
+const UNDO_REMOVE_WINDOW: Duration = Duration::from_secs(10);
+// How often entries are checked against `[history] retention_days`
+const RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// How often the weekly digest notification is checked for; it is sent once a week
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// How far apart the batches of a bulk edit go out
+const BULK_BATCH_INTERVAL: Duration = Duration::from_millis(100);
+// How often the theme file is checked for edits while a popover or window is open
+const THEME_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// How often sensitive entries are checked for expiry, at most
+const SENSITIVE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A command sent on the user's behalf, watched until its response arrives.
+struct TrackedCommand {
+    command: BackendCommand,
+    response: PendingResponse,
+    sent_at: Instant,
+}
+
+/// A tracked command that failed or got no response in time. A timed-out command's
+/// response is still watched, so a late success clears the failure instead of inviting
+/// a duplicate retry.
+struct FailedCommand {
+    command: BackendCommand,
+    response: Option<PendingResponse>,
+    reason: String,
+}
+
+/// The backend binary with the arguments and history key for `config`.
+fn backend_command(config: &Config) -> Result<Command> {
+    let path = discover_backend_binary()?;
+
+    let mut command = Command::new(path);
+    // A key left over from earlier encrypted sessions still has to be passed, so
+    // the backend can read the old file and rewrite it in plain text
+    let key = if config.privacy.encrypt_history {
+        Some(history_key::load_or_create()?)
+    } else {
+        history_key::load().unwrap_or_else(|e| {
+            eprintln!("{:#}", e);
+            None
+        })
+    };
+    if let Some(key) = key {
+        command.env(history_key::ENV_VAR, key);
+    }
+    command.args(config.backend_args());
+    Ok(command)
 }
 
 struct BackendHandle {
     child: Option<Child>,
-    tx: Sender<String>,
+    client: BackendClient,
+    rx: Receiver<BackendMessage>,
+    // Set by `pump_commands` when writing to the backend fails
+    write_failed: Arc<AtomicBool>,
+    // Set by `pump_messages` once the backend's output closes
+    closed: Arc<AtomicBool>,
+    reader: Option<thread::JoinHandle<()>>,
+}
+
+/// A running backend process with the threads pumping its pipes.
+struct Launched {
+    child: Child,
+    tx: Sender<Request>,
     rx: Receiver<BackendMessage>,
+    reader: thread::JoinHandle<()>,
 }
 
 impl BackendHandle {
-    fn start() -> Result<Self> {
-        let path = discover_backend_binary()?;
+    fn start(config: &Config) -> Result<Self> {
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let write_failed = Arc::new(AtomicBool::new(false));
+        let closed = Arc::new(AtomicBool::new(false));
+        let launched = Self::launch(config, &pending, &write_failed, &closed)?;
+        Ok(Self {
+            child: Some(launched.child),
+            client: BackendClient {
+                tx: Arc::new(Mutex::new(launched.tx)),
+                next_request_id: Arc::new(AtomicU64::new(1)),
+                pending,
+            },
+            rx: launched.rx,
+            write_failed,
+            closed,
+            reader: Some(launched.reader),
+        })
+    }
 
-        let mut child = Command::new(path)
-            .args(["--json-api", "--low-power"])
+    fn launch(
+        config: &Config,
+        pending: &PendingRequests,
+        write_failed: &Arc<AtomicBool>,
+        closed: &Arc<AtomicBool>,
+    ) -> Result<Launched> {
+        let mut child = backend_command(config)?
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit())
@@ -126,61 +342,143 @@ impl BackendHandle {
         let stdin = child.stdin.take().ok_or_else(|| anyhow!("no stdin"))?;
         let stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout"))?;
 
-        let (cmd_tx, cmd_rx) = mpsc::channel::<String>();
+        let (cmd_tx, cmd_rx) = mpsc::channel::<Request>();
         let (msg_tx, msg_rx) = mpsc::channel::<BackendMessage>();
 
-        thread::spawn(move || pump_commands(stdin, cmd_rx));
-        thread::spawn(move || pump_messages(stdout, msg_tx));
+        let pump_failed = write_failed.clone();
+        thread::spawn(move || pump_commands(stdin, cmd_rx, pump_failed));
+        let pump_pending = pending.clone();
+        let pump_closed = closed.clone();
+        let reader =
+            thread::spawn(move || pump_messages(stdout, msg_tx, pump_pending, pump_closed));
 
-        Ok(Self {
-            child: Some(child),
+        Ok(Launched {
+            child,
             tx: cmd_tx,
             rx: msg_rx,
+            reader,
         })
     }
 
-    fn send(&self, command: impl Into<String>) -> Result<()> {
-        self.tx
-            .send(command.into())
-            .map_err(|_| BackendError::SendFailed.into())
+    /// Why the backend can no longer be talked to, if it can't: it exited, or writing
+    /// a command to it failed.
+    fn lost_reason(&mut self) -> Option<String> {
+        let child = self.child.as_mut()?;
+        match child.try_wait() {
+            Ok(Some(status)) => Some(format!("exited with {}", status)),
+            Ok(None) if self.write_failed.load(Ordering::SeqCst) => {
+                Some("stopped reading commands".into())
+            }
+            Ok(None) if self.closed.load(Ordering::SeqCst) => Some("closed its output".into()),
+            Ok(None) => None,
+            Err(e) => Some(format!("failed to check on it: {}", e)),
+        }
     }
-}
 
-impl Drop for BackendHandle {
-    fn drop(&mut self) {
-        let _ = self.tx.send("quit".into());
+    /// Replaces the backend process with a new one. Windows keep their clients, which
+    /// send to the new process from now on.
+    fn restart(&mut self, config: &Config) -> Result<()> {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        // The old reader clears the waiting requests when its pipe closes; joining it
+        // keeps that from wiping requests sent to the new process
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        self.write_failed.store(false, Ordering::SeqCst);
+        self.closed.store(false, Ordering::SeqCst);
+        let launched = Self::launch(
+            config,
+            &self.client.pending,
+            &self.write_failed,
+            &self.closed,
+        )?;
+        *self.client.tx.lock().unwrap() = launched.tx;
+        self.child = Some(launched.child);
+        self.rx = launched.rx;
+        self.reader = Some(launched.reader);
+        Ok(())
+    }
+
+    fn send(&self, command: BackendCommand) -> Result<PendingResponse> {
+        self.client.send(command)
+    }
+
+    /// Asks the backend to quit, reaps it and exits the process with `code`.
+    fn shutdown(&mut self, code: i32) -> ! {
+        let _ = self.client.send(BackendCommand::Quit);
         if let Some(mut child) = self.child.take() {
             thread::sleep(Duration::from_millis(100));
             let _ = child.kill();
             let _ = child.wait();
         }
-        std::process::exit(0);
+        std::process::exit(code);
+    }
+}
+
+impl Drop for BackendHandle {
+    fn drop(&mut self) {
+        self.shutdown(0);
     }
 }
 
-fn pump_commands(mut stdin: impl Write + Send + 'static, rx: Receiver<String>) {
-    for command in rx {
-        if let Err(e) = writeln!(stdin, "{}", command) {
+fn pump_commands(
+    mut stdin: impl Write + Send + 'static,
+    rx: Receiver<Request>,
+    write_failed: Arc<AtomicBool>,
+) {
+    for request in rx {
+        let line = match serde_json::to_string(&request) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to encode command: {}", e);
+                continue;
+            }
+        };
+        session::record(session::Direction::Sent, &line);
+        if let Err(e) = writeln!(stdin, "{}", line) {
             eprintln!("Failed to write command to backend: {}", e);
+            wake::set(&write_failed);
             break;
         }
         if let Err(e) = stdin.flush() {
             eprintln!("Failed to flush stdin: {}", e);
+            wake::set(&write_failed);
             break;
         }
     }
 }
 
-fn pump_messages(stdout: impl std::io::Read + Send + 'static, tx: Sender<BackendMessage>) {
+fn pump_messages(
+    stdout: impl std::io::Read + Send + 'static,
+    tx: Sender<BackendMessage>,
+    pending: PendingRequests,
+    closed: Arc<AtomicBool>,
+) {
     let reader = BufReader::new(stdout);
     for line in reader.lines() {
         match line {
             Ok(line) => {
-                if let Ok(msg) = serde_json::from_str::<BackendMessage>(&line) {
-                    if tx.send(msg).is_err() {
-                        break;
+                let Ok(envelope) = serde_json::from_str::<Envelope>(&line) else {
+                    session::record(session::Direction::Other, &line);
+                    continue;
+                };
+                session::record(session::Direction::Received, &line);
+                if let Some(request_id) = envelope.request_id {
+                    if let Some(waiter) = pending.lock().unwrap().remove(&request_id) {
+                        let result = match &envelope.message {
+                            BackendMessage::Error(err) => Err(err.clone()),
+                            message => Ok(message.clone()),
+                        };
+                        let _ = waiter.send(result);
                     }
                 }
+                if tx.send(envelope.message).is_err() {
+                    break;
+                }
+                wake::wake();
             }
             Err(e) => {
                 eprintln!("Failed to read line from backend: {}", e);
@@ -188,6 +486,9 @@ fn pump_messages(stdout: impl std::io::Read + Send + 'static, tx: Sender<Backend
             }
         }
     }
+    // Dropping the waiters lets any outstanding `PendingResponse` observe the disconnect.
+    pending.lock().unwrap().clear();
+    wake::set(&closed);
 }
 
 struct FileSystemAssets;
@@ -231,6 +532,18 @@ fn filename_from_path(path: &str) -> String {
         .to_string()
 }
 
+/// The paths of a multi-file entry that no longer exist; the backend leaves these out
+/// when it puts the rest back on the clipboard.
+fn missing_files(content: &str) -> Vec<&str> {
+    if !content.contains('\n') {
+        return Vec::new();
+    }
+    content
+        .lines()
+        .filter(|path| !path.is_empty() && !Path::new(path).exists())
+        .collect()
+}
+
 fn parse_hex_color(s: &str) -> Option<u32> {
     let s = s.trim();
     let hex = s.strip_prefix('#')?;
@@ -250,6 +563,72 @@ fn parse_hex_color(s: &str) -> Option<u32> {
     }
 }
 
+/// Where save dialogs start: Downloads, or home when there is none.
+fn save_directory() -> PathBuf {
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let downloads = home.join("Downloads");
+    if downloads.is_dir() {
+        downloads
+    } else {
+        home
+    }
+}
+
+/// Unpinned entries copied more than `max_age_secs` before `now` (Unix seconds). The
+/// current entry never expires while it is still on the clipboard.
+fn expired(entries: &[Entry], max_age_secs: u64, now: i64) -> impl Iterator<Item = &Entry> {
+    // Both come from config.toml unchecked; a huge one must not wrap into the future
+    let max_age_secs = i64::try_from(max_age_secs).unwrap_or(i64::MAX);
+    let cutoff_ms = now.saturating_sub(max_age_secs).saturating_mul(1000);
+    entries
+        .iter()
+        .filter(move |e| !e.pinned && !e.is_current && e.timestamp <= cutoff_ms)
+}
+
+/// Entries past `[history] retention_days` (none when 0) or an expiry date set from
+/// the selection bar, as of `now` (Unix seconds).
+fn stale_entries(entries: &[Entry], retention_days: u64, now: i64) -> Vec<u64> {
+    let mut stale: Vec<u64> = Vec::new();
+    if retention_days > 0 {
+        let max_age_secs = retention_days.saturating_mul(24 * 60 * 60);
+        stale.extend(expired(entries, max_age_secs, now).map(|e| e.id));
+    }
+    let dated: Vec<u64> = entries
+        .iter()
+        .filter(|e| !e.is_current && bulk::is_expired(e, now))
+        .filter(|e| !stale.contains(&e.id))
+        .map(|e| e.id)
+        .collect();
+    stale.extend(dated);
+    stale
+}
+
+/// Drops the oldest unpinned entries until at most `limit` are left, like the backend
+/// does. The current entry is always kept.
+fn trim_to_limit(entries: &mut Vec<Entry>, limit: usize) {
+    while entries.len() > limit {
+        let oldest = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.pinned && !entry.is_current)
+            .min_by_key(|(_, entry)| entry.timestamp)
+            .map(|(index, _)| index);
+        match oldest {
+            Some(index) => entries.remove(index),
+            None => break,
+        };
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
 fn format_timestamp(timestamp: i64) -> String {
     let now = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
         Ok(duration) => duration.as_secs() as i64,
@@ -270,13 +649,13 @@ fn format_timestamp(timestamp: i64) -> String {
     }
 }
 
-fn icon_color_for_type(et: &EntryType) -> u32 {
+fn icon_color_for_type(et: &EntryType, theme: &Theme) -> u32 {
     match et {
-        EntryType::Text => ACCENT_BLUE,
-        EntryType::Image => ACCENT_ORANGE,
-        EntryType::File => ACCENT_GREEN,
-        EntryType::Url => ACCENT_PURPLE,
-        EntryType::Color => ACCENT_PINK,
+        EntryType::Text => theme.accent_blue,
+        EntryType::Image => theme.accent_orange,
+        EntryType::File => theme.accent_green,
+        EntryType::Url => theme.accent_purple,
+        EntryType::Color => theme.accent_pink,
     }
 }
 
@@ -290,29 +669,109 @@ fn type_label_for_type(et: &EntryType) -> &'static str {
     }
 }
 
-const TEXT_PRIMARY: u32 = 0xf7f4ee;
-const TEXT_SECONDARY: u32 = 0xd7d0c2;
-const TEXT_MUTED: u32 = 0xa69c89;
-const TEXT_DIM: u32 = 0x8a7f6b;
-const ACCENT_BLUE: u32 = 0x5ac8fa;
-const ACCENT_ORANGE: u32 = 0xff9f0a;
-const ACCENT_GREEN: u32 = 0x30d158;
-const ACCENT_PURPLE: u32 = 0xbf5af2;
-const ACCENT_PINK: u32 = 0xff375f;
-const DANGER: u32 = 0xff453a;
-const SURFACE_BASE: u32 = 0x14110bf2;
-const SURFACE_BORDER: u32 = 0xffffff24;
-const SURFACE_ROW: u32 = 0xffffff08;
-const SURFACE_ROW_FOCUSED: u32 = 0xffffff14;
-const SURFACE_ROW_CURRENT: u32 = 0xffffff20;
-const SURFACE_ROW_HOVER: u32 = 0xffffff18;
-const SURFACE_ICON_WELL: u32 = 0xffffff10;
+/// Small text button used in the popover's action bars.
+fn action_button(
+    theme: &Theme,
+    id: impl Into<SharedString>,
+    label: impl Into<SharedString>,
+    color: u32,
+) -> gpui::Stateful<gpui::Div> {
+    div()
+        .id(id.into())
+        .px_2()
+        .py(px(2.0))
+        .rounded(px(theme.control_radius))
+        .text_size(px(10.0))
+        .text_color(rgb(color))
+        .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+        .cursor_pointer()
+        .child(label.into())
+}
+
+/// Pulsing stand-in for an image that is still being decoded in the background, or a
+/// row that hasn't loaded yet.
+fn placeholder(id: impl Into<gpui::ElementId>, theme: &Theme) -> impl IntoElement {
+    div()
+        .id(id.into())
+        .size_full()
+        .bg(rgba(theme.surface_icon_well))
+        .with_animation(
+            "image-placeholder",
+            Animation::new(Duration::from_millis(1200))
+                .repeat()
+                .with_easing(pulsating_between(0.3, 0.9)),
+            |placeholder, delta| placeholder.opacity(delta),
+        )
+}
+
+/// An image's blurhash while its thumbnail loads: a grid of the decoded colours,
+/// stretched to fill the parent.
+fn blurred_placeholder(colors: Vec<u32>) -> impl IntoElement {
+    div()
+        .size_full()
+        .flex()
+        .flex_col()
+        .children(colors.chunks(thumbnails::PLACEHOLDER_GRID).map(|row| {
+            div()
+                .flex_1()
+                .flex()
+                .children(row.iter().map(|&color| div().flex_1().bg(rgb(color))))
+        }))
+}
+
+/// Turns a recorded keystroke into a global-hotkey chord string like `"cmd+alt+="`.
+/// Returns `None` for chords without a modifier or keys global-hotkey can't register.
+fn hotkey_chord(keystroke: &gpui::Keystroke) -> Option<String> {
+    let mods = &keystroke.modifiers;
+    let mut parts = Vec::new();
+    if mods.control {
+        parts.push("ctrl");
+    }
+    if mods.alt {
+        parts.push("alt");
+    }
+    if mods.shift {
+        parts.push("shift");
+    }
+    if mods.platform {
+        parts.push("cmd");
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    parts.push(&keystroke.key);
+    let chord = parts.join("+");
+    chord.parse::<HotKey>().ok().map(|_| chord)
+}
+
+// Fixed line height for row labels, so multi-line rows all measure the same
+const ROW_LINE_HEIGHT: f32 = 16.0;
+
+// Pointer travel, in pixels, before a press on a row becomes a drag
+const DRAG_THRESHOLD: f32 = 4.0;
 
 // ---------- NSStatusItem setup (macOS) ----------
 
 #[cfg(target_os = "macos")]
 extern "C" fn status_item_action(_this: &Object, _cmd: Sel, _sender: id) {
-    MENU_BAR_CLICKED.store(true, Ordering::SeqCst);
+    // NSEventTypeRightMouseUp; Control-click counts as a right click, as elsewhere on macOS
+    const RIGHT_MOUSE_UP: u64 = 4;
+    const CONTROL_KEY_MASK: u64 = 1 << 18;
+
+    let (event_type, modifier_flags): (u64, u64) = unsafe {
+        let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+        let event: id = msg_send![ns_app, currentEvent];
+        if event.is_null() {
+            (0, 0)
+        } else {
+            (msg_send![event, type], msg_send![event, modifierFlags])
+        }
+    };
+    if event_type == RIGHT_MOUSE_UP || modifier_flags & CONTROL_KEY_MASK != 0 {
+        status_menu::show(unsafe { STATUS_ITEM });
+    } else {
+        wake::set(&MENU_BAR_CLICKED);
+    }
 }
 
 #[cfg(target_os = "macos")]
@@ -349,13 +808,15 @@ fn setup_menu_bar_icon() {
         let handler: id = msg_send![handler_class, new];
         let _: () = msg_send![button, setTarget: handler];
         let _: () = msg_send![button, setAction: sel!(handleClick:)];
+        // Deliver right clicks too, so they can open the menu (NSEventMaskLeftMouseUp | RightMouseUp)
+        let _: isize = msg_send![button, sendActionOn: (1u64 << 2) | (1u64 << 4)];
 
         STATUS_ITEM = status_item;
     }
 }
 
 #[cfg(target_os = "macos")]
-fn get_status_item_position() -> Option<Point<Pixels>> {
+fn get_status_item_position(popover_width: f32) -> Option<Point<Pixels>> {
     unsafe {
         let status_item = STATUS_ITEM;
         if status_item.is_null() {
@@ -382,7 +843,7 @@ fn get_status_item_position() -> Option<Point<Pixels>> {
         let frame: cocoa::foundation::NSRect = msg_send![button_window, frame];
 
         // Convert to top-left coords: the bottom of the status item = top of popover
-        let x = frame.origin.x + frame.size.width / 2.0 - 160.0; // center horizontally
+        let x = frame.origin.x + frame.size.width / 2.0 - popover_width as f64 / 2.0; // center horizontally
         let y = screen_height - frame.origin.y; // bottom of status item in top-left coords
 
         Some(point(px(x as f32), px(y as f32)))
@@ -393,7 +854,7 @@ fn get_status_item_position() -> Option<Point<Pixels>> {
 fn setup_menu_bar_icon() {}
 
 #[cfg(not(target_os = "macos"))]
-fn get_status_item_position() -> Option<Point<Pixels>> {
+fn get_status_item_position(_popover_width: f32) -> Option<Point<Pixels>> {
     None
 }
 
@@ -403,13 +864,142 @@ type SharedEntries = Arc<Mutex<Vec<Entry>>>;
 
 // ---------- MenuBarPopover ----------
 
+/// Right-click menu for a row: the forms it can be copied as, the usual entry actions
+/// and the plugins found when it was opened.
+struct EntryMenu {
+    id: u64,
+    position: Point<Pixels>,
+    plugins: Vec<Plugin>,
+    kind: MenuKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuKind {
+    Full,
+    /// Opened from the row's formats badge, so only the forms are listed.
+    Formats,
+    /// Opened from the row's files badge, listing each of the copied files.
+    Files,
+}
+
+/// An entry's content as a QR code, shown where its menu was opened so it can be
+/// scanned with a phone.
+struct QrPopover {
+    position: Point<Pixels>,
+    caption: String,
+    image: std::result::Result<PathBuf, String>,
+}
+
 struct MenuBarPopover {
     entries: SharedEntries,
-    backend_tx: Sender<String>,
-    supports_id_commands: Arc<AtomicBool>,
+    backend: BackendClient,
+    thumbnails: Thumbnails,
     focus_handle: FocusHandle,
     focused_index: Option<usize>,
-    scroll_handle: ScrollHandle,
+    // Whether the arrows moved into the list since the search was last changed; plain
+    // digits copy rows only then, and type into the search otherwise
+    list_focused: bool,
+    // The first entry scrolled into view, which the number keys count from
+    first_visible_entry: usize,
+    // A deep-linked entry that wasn't loaded, focused once the rest of the history is
+    pending_focus: Option<u64>,
+    // Typed filter; entries are fuzzy-matched against it, best match first
+    query: String,
+    // Queries that led to a copy, suggested while the query is empty
+    search_history: SearchHistory,
+    // Suggestion highlighted with Left/Right
+    suggestion: Option<usize>,
+    // Types shown by the filter chips; empty means every type
+    type_filter: HashSet<EntryType>,
+    // The backend's matches for the query and chips from the whole history, kept
+    // while the next search is out; see `search_backend`
+    found: Option<search::Found>,
+    pending_search: Option<PendingResponse>,
+    // Entry ids toggled with Cmd-click, for actions that work on several entries at once
+    selected_ids: HashSet<u64>,
+    // Row and window position of the last left mouse-down, until it turns into a drag
+    drag_origin: Option<(u64, Point<Pixels>)>,
+    // Pin/remove/resize actions applied lately, offered again for the focused entry
+    recent_actions: RecentActions,
+    // Quick action on the focused row chosen with Tab; None while the row itself is
+    quick_action: Option<usize>,
+    hotkey_label: String,
+    recording_hotkey: bool,
+    click_behavior: ClickBehavior,
+    auto_paste: bool,
+    formatters: FormatterConfig,
+    // Goes between entries when the selection is combined; the selection bar cycles it
+    combine_separator: CombineSeparator,
+    // Days until entries given an expiry from the selection bar are removed
+    selection_expiry_days: u64,
+    // Tag, pin or expiry change to the selection whose commands are still going out
+    bulk_job: Option<BulkJob>,
+    // The last one to finish, until it is undone or dismissed
+    finished_bulk_job: Option<BulkJob>,
+    // Entries just removed with the × button or from the selection, and when;
+    // Undo brings them back until `UNDO_REMOVE_WINDOW` has passed
+    removed_ids: Option<(Vec<u64>, Instant)>,
+    // `[hotkey] paste_stack` chord, shown in the stack strip; empty when it is off
+    paste_stack_chord: String,
+    // Date headers only make sense while the list is in copy order
+    date_headers: bool,
+    mask_sensitive: bool,
+    // Sensitive entries the user clicked to show in clear text, until the popover closes
+    revealed_ids: HashSet<u64>,
+    // Content lines per row; every row reserves all of them so the list stays uniform
+    preview_lines: usize,
+    theme: Theme,
+    // Palette from theme.toml, reapplied whenever the built-in theme is switched
+    theme_overrides: ThemeOverrides,
+    // Cmd+Y shows the focused entry's full content in place of the list
+    preview_open: bool,
+    // Entry last logged as viewed in the preview, so it isn't logged on every render
+    previewed_id: Option<u64>,
+    // Cmd+M shows Markdown entries as written instead of rendered
+    markdown_source: bool,
+    // Syntax colours of the previewed entry, if it reads as code, for the theme in use
+    // Entry commands not answered yet; their rows' pin and remove controls stay
+    // disabled until the response arrives or `COMMAND_TIMEOUT` passes
+    in_flight: Vec<TrackedCommand>,
+    // The last of them that failed or timed out, offered for retry
+    failed_command: Option<FailedCommand>,
+    find: Option<FindState>,
+    tag_editor: Option<TagEditor>,
+    split_prompt: Option<SplitPrompt>,
+    // Cmd+E edits a copy of a text entry in place of the list
+    entry_editor: Option<EntryEditor>,
+    context_menu: Option<EntryMenu>,
+    qr_code: Option<QrPopover>,
+    // Cmd+6 lists saved snippets, filtered by the query, in place of the history
+    snippets_open: bool,
+    snippet_index: usize,
+    // The Archived view (the backend's trash) in place of the entry list
+    archive_open: bool,
+    // The Stats view, with the weekly digest, in place of the entry list
+    stats_open: bool,
+    // In Vim mode, keystrokes go to the search after `/` until Enter or Escape
+    vim_searching: bool,
+    // First press of a doubled Vim key (`gg`, `dd`), waiting for the second
+    vim_pending: Option<KeyAction>,
+    snippet_prompt: Option<SnippetPrompt>,
+    // `?` lists the shortcuts for where keystrokes were going when it was pressed
+    shortcuts: Option<KeyContext>,
+    preview_scroll: ScrollHandle,
+    // Filtered entries as of the last render, read by the virtualized list
+    visible_entries: Vec<Entry>,
+    // The list's rows as of the last render: the entries, with date headers between
+    list_rows: Vec<ListRow>,
+    scroll_handle: UniformListScrollHandle,
+    // Entry to bring into view once the next render has laid out the rows
+    pending_scroll: Option<usize>,
+    // A Cmd+N window rather than the menu bar popover: it stays open when it loses
+    // focus or an entry is copied, and Escape closes only itself
+    detached: bool,
+    // `[window] hide_on_blur`: the popover hides when another window takes focus
+    hide_on_blur: bool,
+    // `[window] dock` edge the popover slides out from and hides back into when the
+    // pointer leaves; None for the menu bar popover and Cmd+N windows
+    dock: DockEdge,
     _activation_sub: gpui::Subscription,
 }
 
@@ -422,263 +1012,4464 @@ impl Focusable for MenuBarPopover {
 impl MenuBarPopover {
     fn new(
         entries: SharedEntries,
-        backend_tx: Sender<String>,
-        supports_id_commands: Arc<AtomicBool>,
+        backend: BackendClient,
+        thumbnails: Thumbnails,
+        config: &Config,
+        theme_overrides: ThemeOverrides,
         window: &mut Window,
         cx: &mut GpuiContext<Self>,
     ) -> Self {
         let focus_handle = cx.focus_handle();
         window.focus(&focus_handle);
 
-        let activation_sub = cx.observe_window_activation(window, |_this, window, _cx| {
-            if !window.is_window_active() {
-                POPOVER_SHOULD_CLOSE.store(true, Ordering::SeqCst);
+        let activation_sub = cx.observe_window_activation(window, |this, window, _cx| {
+            if !this.detached && this.hide_on_blur && !window.is_window_active() {
+                wake::set(&POPOVER_SHOULD_CLOSE);
             }
         });
 
         Self {
             entries,
-            backend_tx,
-            supports_id_commands,
+            backend,
+            thumbnails,
             focus_handle,
             focused_index: Some(0),
-            scroll_handle: ScrollHandle::new(),
+            list_focused: false,
+            first_visible_entry: 0,
+            pending_focus: None,
+            query: String::new(),
+            search_history: SearchHistory::load(config.privacy.encrypt_history),
+            suggestion: None,
+            type_filter: HashSet::new(),
+            found: None,
+            pending_search: None,
+            selected_ids: HashSet::new(),
+            drag_origin: None,
+            recent_actions: RecentActions::default(),
+            quick_action: None,
+            hotkey_label: config.hotkey.toggle.clone(),
+            recording_hotkey: false,
+            theme: theme_overrides.apply(config.appearance.theme.theme()),
+            theme_overrides,
+            click_behavior: config.behavior.click,
+            auto_paste: config.behavior.auto_paste,
+            formatters: config.formatters.clone(),
+            combine_separator: config.behavior.combine_separator,
+            selection_expiry_days: config.history.selection_expiry_days,
+            bulk_job: None,
+            finished_bulk_job: None,
+            removed_ids: None,
+            paste_stack_chord: config.hotkey.paste_stack.clone(),
+            date_headers: config.history.order == EntryOrder::Recent,
+            mask_sensitive: config.privacy.mask_sensitive,
+            revealed_ids: HashSet::new(),
+            preview_lines: config.window.preview_lines(),
+            preview_open: false,
+            previewed_id: None,
+            markdown_source: false,
+            in_flight: Vec::new(),
+            failed_command: None,
+            find: None,
+            tag_editor: None,
+            split_prompt: None,
+            entry_editor: None,
+            context_menu: None,
+            qr_code: None,
+            snippets_open: false,
+            snippet_index: 0,
+            archive_open: false,
+            stats_open: false,
+            vim_searching: false,
+            vim_pending: None,
+            snippet_prompt: None,
+            shortcuts: None,
+            preview_scroll: ScrollHandle::new(),
+            visible_entries: Vec::new(),
+            list_rows: Vec::new(),
+            scroll_handle: UniformListScrollHandle::new(),
+            pending_scroll: None,
+            detached: false,
+            hide_on_blur: config.window.hide_on_blur,
+            dock: config.window.dock,
             _activation_sub: activation_sub,
         }
     }
 
-    fn select_entry(&self, id: u64, legacy_index: usize) {
-        if self.supports_id_commands.load(Ordering::Acquire) {
-            let _ = self.backend_tx.send(format!("select-entry-id:{id}"));
-        } else {
-            let _ = self.backend_tx.send(format!("select-entry:{legacy_index}"));
-        }
-        let _ = self.backend_tx.send("get-entries".into());
+    /// Entries matching the type chips and the current query: ranked by fuzzy score,
+    /// or filtered by regex for `/pattern` queries. `#tag` words keep only entries with
+    /// a tag starting with that text. An invalid pattern matches nothing. Only the
+    /// loaded entries and what the backend last found are looked through, so older
+    /// entries come from `search_backend` rather than from loading every page.
+    fn filtered(&self) -> Vec<Entry> {
+        let (tags, query) = search::split_tags(&self.query);
+        let Ok(matcher) = search::Matcher::new(&query) else {
+            return Vec::new();
+        };
+        let entries = self.entries.lock().unwrap();
+        let found = self.found.as_ref().map_or(&[][..], |found| &found.entries);
+        // The current entry is always shown in the header card instead
+        let visible: Vec<&Entry> = search::with_found(&entries, found)
+            .into_iter()
+            .filter(|e| !e.is_current)
+            .filter(|e| self.type_filter.is_empty() || self.type_filter.contains(&e.entry_type))
+            .filter(|e| {
+                tags.iter()
+                    .all(|tag| e.tags.iter().any(|t| t.starts_with(tag.as_str())))
+            })
+            .collect();
+        // Images also match on the text recognized in them, web pages on their title, and
+        // every entry on the app and window it was copied from
+        let recognized = ocr::RECOGNIZED.lock().unwrap();
+        let titles = link_titles::TITLES.lock().unwrap();
+        let candidates = visible.iter().map(|e| {
+            let found = match e.entry_type {
+                EntryType::Url => link_titles::title_for(e, &titles),
+                _ => ocr::text_for(e, &recognized),
+            };
+            search::fields(e, found)
+        });
+        matcher
+            .rank(candidates)
+            .into_iter()
+            .map(|idx| visible[idx].clone())
+            .collect()
     }
 
-    fn remove_entry(&self, id: u64, legacy_index: usize) {
-        if self.supports_id_commands.load(Ordering::Acquire) {
-            let _ = self.backend_tx.send(format!("remove-entry-id:{id}"));
-        } else {
-            let _ = self.backend_tx.send(format!("remove-entry:{legacy_index}"));
+    fn toggle_type_filter(&mut self, entry_type: EntryType) {
+        if !self.type_filter.remove(&entry_type) {
+            self.type_filter.insert(entry_type);
         }
-        let _ = self.backend_tx.send("get-entries".into());
+        self.focused_index = Some(0);
+        self.scroll_handle.scroll_to_item(0, ScrollStrategy::Top);
+        self.search_backend();
     }
 
-    fn toggle_pin(&self, id: u64, legacy_index: usize) {
-        if self.supports_id_commands.load(Ordering::Acquire) {
-            let _ = self.backend_tx.send(format!("toggle-pin-id:{id}"));
-        } else {
-            let _ = self.backend_tx.send(format!("toggle-pin:{legacy_index}"));
+    /// Asks the backend for what the query and type chips match in the whole history,
+    /// so entries that haven't loaded are found too. The last answer stays on screen,
+    /// narrowed by `filtered`, until this one arrives in `check_in_flight`.
+    fn search_backend(&mut self) {
+        let recognized = ocr::RECOGNIZED.lock().unwrap();
+        let titles = link_titles::TITLES.lock().unwrap();
+        let found_texts = recognized
+            .iter()
+            .filter_map(|(path, text)| Some((path.to_str()?, text.as_str())))
+            .chain(
+                titles
+                    .iter()
+                    .map(|(url, title)| (url.as_str(), title.as_str())),
+            );
+        let command = search::backend_search(&self.query, &self.type_filter, found_texts);
+        drop((recognized, titles));
+        let Some(command) = command else {
+            self.found = None;
+            self.pending_search = None;
+            return;
+        };
+        match self.backend.send(command) {
+            Ok(response) => self.pending_search = Some(response),
+            Err(e) => eprintln!("Failed to search: {:#}", e),
         }
     }
 
-    fn render_popover_entry(
+    fn render_current_card(
+        &self,
         entry: &Entry,
-        idx: usize,
-        focused_index: Option<usize>,
         view_entity: gpui::Entity<Self>,
-    ) -> impl IntoElement + 'static {
-        let is_focused = focused_index == Some(idx);
-        let id = entry.id;
-        let content = entry.content.clone();
-        let entry_type = entry.entry_type.clone();
-        let is_current = entry.is_current;
-        let is_pinned = entry.pinned;
-        let image_path = entry.content.clone();
-        let path_exists = std::path::Path::new(&image_path).exists();
-        let timestamp_str = format_timestamp(entry.timestamp);
-        let ic = icon_color_for_type(&entry.entry_type);
-        let tl = type_label_for_type(&entry.entry_type);
-
-        let display_label: String = match &entry_type {
-            EntryType::Image | EntryType::File => {
-                if path_exists {
-                    filename_from_path(&content)
-                } else {
-                    content.clone()
-                }
-            }
-            _ => content.clone(),
-        };
-
-        let row_bg = if is_current {
-            rgba(SURFACE_ROW_CURRENT)
-        } else if is_focused {
-            rgba(SURFACE_ROW_FOCUSED)
-        } else {
-            rgba(SURFACE_ROW)
-        };
-
-        let view = view_entity.clone();
-        let view_remove = view_entity.clone();
-        let view_pin = view_entity.clone();
-        let legacy_index = idx + 1;
-        let entry_id_str = SharedString::from(format!("pop-entry-{}", id));
+    ) -> impl IntoElement {
+        let theme = self.theme;
+        let thumbnail = (entry.entry_type == EntryType::Image)
+            .then(|| self.thumbnails.get(Path::new(&entry.content)));
+        div()
+            .flex_shrink_0()
+            .pt(px(6.0))
+            .pb(px(4.0))
+            .border_b_1()
+            .border_color(rgba(theme.surface_border))
+            .child(
+                div()
+                    .px_3()
+                    .pb(px(3.0))
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.accent_green))
+                    .child("On clipboard now"),
+            )
+            // Not a list row, so the card stays out of keyboard navigation
+            .child(self.render_popover_entry(entry, None, thumbnail, view_entity))
+    }
 
+    fn render_type_chips(&self, view_entity: gpui::Entity<Self>) -> impl IntoElement {
+        let theme = self.theme;
+        let view_snippets = view_entity.clone();
+        let view_archive = view_entity.clone();
         div()
-            .id(entry_id_str)
-            .mx(px(6.0))
-            .mb(px(1.0))
             .flex()
             .items_center()
-            .gap(px(8.0))
-            .px(px(8.0))
-            .py(px(7.0))
-            .bg(row_bg)
-            .rounded_lg()
-            .hover(|style| style.bg(rgba(SURFACE_ROW_HOVER)))
-            .cursor_pointer()
-            .child(if entry_type == EntryType::Image && path_exists {
-                let img_path = std::path::Path::new(&image_path);
-                div()
-                    .size(px(28.0))
-                    .rounded(px(6.0))
-                    .overflow_hidden()
-                    .flex_shrink_0()
-                    .child(img(img_path).size(px(28.0)))
-            } else if entry_type == EntryType::Color {
-                let swatch_color = parse_hex_color(&content).unwrap_or(ACCENT_PINK);
-                div()
-                    .size(px(28.0))
-                    .rounded(px(6.0))
-                    .bg(rgba(SURFACE_ICON_WELL))
-                    .flex()
-                    .items_center()
-                    .justify_center()
-                    .flex_shrink_0()
-                    .child(
-                        div()
-                            .size(px(16.0))
-                            .rounded(px(4.0))
-                            .bg(rgb(swatch_color))
-                            .border_1()
-                            .border_color(rgba(0xffffff30)),
-                    )
-            } else {
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .flex_shrink_0()
+            .children(EntryType::ALL.iter().map(|&entry_type| {
+                let active = self.type_filter.contains(&entry_type);
+                let color = icon_color_for_type(&entry_type, &theme);
+                let view = view_entity.clone();
                 div()
-                    .size(px(28.0))
-                    .rounded(px(6.0))
-                    .bg(rgba(SURFACE_ICON_WELL))
-                    .flex()
-                    .items_center()
-                    .justify_center()
-                    .flex_shrink_0()
-                    .child(div().size(px(8.0)).rounded_full().bg(rgb(ic)))
-            })
+                    .id(SharedString::from(format!(
+                        "popover-chip-{}",
+                        type_label_for_type(&entry_type)
+                    )))
+                    .px_2()
+                    .py(px(1.0))
+                    .rounded_full()
+                    .border_1()
+                    .border_color(if active {
+                        rgb(color)
+                    } else {
+                        rgb(theme.text_dim)
+                    })
+                    .when(active, |el| el.bg(rgba(theme.surface_row_current)))
+                    .text_size(px(10.0))
+                    .text_color(if active {
+                        rgb(color)
+                    } else {
+                        rgb(theme.text_muted)
+                    })
+                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                    .cursor_pointer()
+                    .child(type_label_for_type(&entry_type))
+                    .on_click(move |_, _, app| {
+                        view.update(app, |this, cx| {
+                            this.toggle_type_filter(entry_type);
+                            cx.notify();
+                        });
+                    })
+            }))
             .child(
                 div()
-                    .flex()
-                    .flex_col()
-                    .flex_1()
-                    .min_w_0()
-                    .gap(px(1.0))
-                    .child(
-                        div()
-                            .text_xs()
-                            .text_color(rgb(TEXT_PRIMARY))
-                            .truncate()
-                            .child(display_label),
-                    )
-                    .child(
-                        div()
-                            .flex()
-                            .items_center()
-                            .gap_1()
-                            .child(div().text_color(rgb(ic)).text_size(px(10.0)).child(tl))
-                            .when(is_pinned, |el| {
-                                el.child(
-                                    div()
-                                        .text_size(px(10.0))
-                                        .text_color(rgb(TEXT_DIM))
-                                        .child("\u{00b7}"),
-                                )
-                                .child(
-                                    div()
-                                        .text_size(px(10.0))
-                                        .text_color(rgb(ACCENT_ORANGE))
-                                        .child("Pinned"),
-                                )
-                            })
-                            .child(
-                                div()
-                                    .text_size(px(10.0))
-                                    .text_color(rgb(TEXT_DIM))
-                                    .child("\u{00b7}"),
-                            )
-                            .child(
-                                div()
-                                    .text_size(px(10.0))
-                                    .text_color(rgb(TEXT_SECONDARY))
-                                    .child(timestamp_str),
-                            ),
-                    ),
+                    .id("popover-chip-snippets")
+                    .ml_auto()
+                    .px_2()
+                    .py(px(1.0))
+                    .rounded_full()
+                    .border_1()
+                    .border_color(if self.snippets_open {
+                        rgb(theme.accent_purple)
+                    } else {
+                        rgb(theme.text_dim)
+                    })
+                    .when(self.snippets_open, |el| {
+                        el.bg(rgba(theme.surface_row_current))
+                    })
+                    .text_size(px(10.0))
+                    .text_color(if self.snippets_open {
+                        rgb(theme.accent_purple)
+                    } else {
+                        rgb(theme.text_muted)
+                    })
+                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                    .cursor_pointer()
+                    .child("Snippets")
+                    .on_click(move |_, _, app| {
+                        view_snippets.update(app, |this, cx| {
+                            this.toggle_snippets();
+                            cx.notify();
+                        });
+                    }),
             )
             .child(
                 div()
-                    .id(SharedString::from(format!("pop-pin-{}", id)))
-                    .size(px(22.0))
-                    .rounded(px(6.0))
-                    .flex()
-                    .items_center()
-                    .justify_center()
-                    .flex_shrink_0()
-                    .text_size(px(9.0))
-                    .text_color(if is_pinned {
-                        rgb(ACCENT_ORANGE)
+                    .id("popover-chip-archive")
+                    .px_2()
+                    .py(px(1.0))
+                    .rounded_full()
+                    .border_1()
+                    .border_color(if self.archive_open {
+                        rgb(theme.accent_purple)
+                    } else {
+                        rgb(theme.text_dim)
+                    })
+                    .when(self.archive_open, |el| {
+                        el.bg(rgba(theme.surface_row_current))
+                    })
+                    .text_size(px(10.0))
+                    .text_color(if self.archive_open {
+                        rgb(theme.accent_purple)
                     } else {
-                        rgb(TEXT_MUTED)
+                        rgb(theme.text_muted)
                     })
-                    .hover(|style| style.bg(rgba(0xff9f0a18)).text_color(rgb(ACCENT_ORANGE)))
+                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
                     .cursor_pointer()
-                    .child(if is_pinned { "\u{25CF}" } else { "\u{25CB}" })
+                    .child("Archived")
                     .on_click(move |_, _, app| {
-                        app.stop_propagation();
-                        view_pin.update(app, |this, cx| {
-                            this.toggle_pin(id, legacy_index);
+                        view_archive.update(app, |this, cx| {
+                            this.toggle_archive();
                             cx.notify();
                         });
                     }),
             )
-            .when(!is_current, |el| {
+            .child(
+                div()
+                    .id("popover-chip-stats")
+                    .px_2()
+                    .py(px(1.0))
+                    .rounded_full()
+                    .border_1()
+                    .border_color(if self.stats_open {
+                        rgb(theme.accent_purple)
+                    } else {
+                        rgb(theme.text_dim)
+                    })
+                    .when(self.stats_open, |el| el.bg(rgba(theme.surface_row_current)))
+                    .text_size(px(10.0))
+                    .text_color(if self.stats_open {
+                        rgb(theme.accent_purple)
+                    } else {
+                        rgb(theme.text_muted)
+                    })
+                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                    .cursor_pointer()
+                    .child("Stats")
+                    .on_click(move |_, _, app| {
+                        view_entity.update(app, |this, cx| {
+                            this.toggle_stats();
+                            cx.notify();
+                        });
+                    }),
+            )
+    }
+
+    /// With an empty query, Left/Right move through recent searches; Enter searches the
+    /// highlighted one again and Backspace forgets it.
+    fn handle_suggestion_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        let count = self.search_history.queries().len();
+        if !self.query.is_empty() || self.preview_open || count == 0 {
+            self.suggestion = None;
+            return false;
+        }
+        let Some(i) = self.suggestion else {
+            if keymap::matches(KeyAction::PickNext, keystroke) {
+                self.suggestion = Some(0);
+                return true;
+            }
+            return false;
+        };
+        if keymap::matches(KeyAction::PickNext, keystroke) {
+            self.suggestion = Some((i + 1).min(count - 1));
+        } else if keymap::matches(KeyAction::PickPrevious, keystroke) {
+            self.suggestion = i.checked_sub(1);
+        } else if keymap::matches(KeyAction::Copy, keystroke) {
+            let query = self.search_history.queries()[i].clone();
+            self.set_query(query);
+        } else if keymap::matches(KeyAction::Backspace, keystroke)
+            || keymap::matches(KeyAction::Remove, keystroke)
+        {
+            self.search_history.remove(i);
+            self.suggestion = (count > 1).then(|| i.min(count - 2));
+        } else if keymap::matches(KeyAction::Cancel, keystroke) {
+            self.suggestion = None;
+        } else {
+            // Moving through the list leaves the recent searches
+            let moves = [
+                KeyAction::MoveUp,
+                KeyAction::MoveDown,
+                KeyAction::ExtendUp,
+                KeyAction::ExtendDown,
+                KeyAction::JumpUp,
+                KeyAction::JumpDown,
+                KeyAction::PreviousSection,
+                KeyAction::NextSection,
+            ];
+            if moves
+                .into_iter()
+                .any(|action| keymap::matches(action, keystroke))
+            {
+                self.suggestion = None;
+            }
+            return false;
+        }
+        true
+    }
+
+    fn render_suggestions(&self, view_entity: gpui::Entity<Self>) -> Option<impl IntoElement> {
+        let queries = self.search_history.queries();
+        if !self.query.is_empty() || self.preview_open || queries.is_empty() {
+            return None;
+        }
+        let theme = self.theme;
+        let chips = queries.iter().enumerate().map(|(i, query)| {
+            let highlighted = self.suggestion == Some(i);
+            let view_search = view_entity.clone();
+            let view_forget = view_entity.clone();
+            let search = query.clone();
+            div()
+                .id(("search-suggestion", i))
+                .flex()
+                .items_center()
+                .gap_1()
+                .flex_shrink_0()
+                .px_2()
+                .py(px(1.0))
+                .rounded_full()
+                .border_1()
+                .border_color(if highlighted {
+                    rgb(theme.accent_blue)
+                } else {
+                    rgba(theme.surface_border)
+                })
+                .text_color(if highlighted {
+                    rgb(theme.text_primary)
+                } else {
+                    rgb(theme.text_secondary)
+                })
+                .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                .cursor_pointer()
+                .child(query.clone())
+                .child(
+                    div()
+                        .id(("search-suggestion-forget", i))
+                        .text_color(rgb(theme.text_dim))
+                        .hover(|style| style.text_color(rgb(theme.danger)))
+                        .child("\u{00d7}")
+                        .on_click(move |_, _, app| {
+                            app.stop_propagation();
+                            view_forget.update(app, |this, cx| {
+                                this.search_history.remove(i);
+                                this.suggestion = None;
+                                cx.notify();
+                            });
+                        }),
+                )
+                .on_click(move |_, _, app| {
+                    view_search.update(app, |this, cx| {
+                        this.set_query(search.clone());
+                        cx.notify();
+                    });
+                })
+        });
+        Some(
+            div()
+                .flex()
+                .items_center()
+                .gap_1()
+                .px_3()
+                .py(px(4.0))
+                .flex_shrink_0()
+                .overflow_hidden()
+                .text_size(px(10.0))
+                .child(div().text_color(rgb(theme.text_muted)).child("Recent"))
+                .children(chips),
+        )
+    }
+
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.list_focused = false;
+        self.suggestion = None;
+        self.snippet_index = 0;
+        self.focused_index = Some(0);
+        self.scroll_handle.scroll_to_item(0, ScrollStrategy::Top);
+        self.search_backend();
+    }
+
+    fn focused_entry(&self) -> Option<Entry> {
+        self.focused_index
+            .and_then(|idx| self.filtered().into_iter().nth(idx))
+    }
+
+    fn preview_hits(&self) -> Vec<preview::Hit> {
+        match (&self.find, self.focused_entry()) {
+            (Some(find), Some(entry)) => {
+                let lines: Vec<&str> = entry.content.split('\n').collect();
+                preview::find_hits(&lines, &find.query)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn step_find(&mut self, forward: bool) {
+        let count = self.preview_hits().len();
+        if let Some(find) = &mut self.find {
+            find.step(count, forward);
+        }
+    }
+
+    fn scroll_to_current_hit(&mut self) {
+        let hits = self.preview_hits();
+        if let Some(find) = &mut self.find {
+            if find.current >= hits.len() {
+                find.current = 0;
+            }
+            if let Some(hit) = hits.get(find.current) {
+                self.preview_scroll.scroll_to_item(hit.line);
+            }
+        }
+    }
+
+    /// Keys for the preview pane and its find bar. Returns true when the key was consumed.
+    fn handle_preview_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        let cmd = keystroke.modifiers.platform;
+        if keymap::matches(KeyAction::TogglePreview, keystroke) {
+            self.preview_open = !self.preview_open;
+            self.find = None;
+            return true;
+        }
+        if self.preview_open && keymap::matches(KeyAction::ToggleMarkdown, keystroke) {
+            self.markdown_source = !self.markdown_source;
+            return true;
+        }
+        if keymap::matches(KeyAction::Find, keystroke) {
+            self.preview_open = true;
+            self.find.get_or_insert_with(FindState::default);
+            return true;
+        }
+
+        if let Some(find) = &mut self.find {
+            if keymap::matches(KeyAction::Cancel, keystroke) {
+                self.find = None;
+            } else if keymap::matches(KeyAction::Backspace, keystroke) {
+                find.query.pop();
+                find.current = 0;
+            } else if keymap::matches(KeyAction::FindNext, keystroke) {
+                self.step_find(true);
+            } else if keymap::matches(KeyAction::FindPrevious, keystroke) {
+                self.step_find(false);
+            } else {
+                let text = keystroke
+                    .key_char
+                    .as_deref()
+                    .filter(|t| !cmd && !t.chars().any(char::is_control));
+                match text {
+                    Some(text) => {
+                        find.query.push_str(text);
+                        find.current = 0;
+                    }
+                    None => return false,
+                }
+            }
+            self.scroll_to_current_hit();
+            return true;
+        }
+
+        if self.preview_open && keymap::matches(KeyAction::Cancel, keystroke) {
+            self.preview_open = false;
+            return true;
+        }
+        false
+    }
+
+    /// The entry's latest accesses under the preview, with a button to forget them.
+    fn render_access_log(
+        theme: Theme,
+        id: u64,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        const SHOWN: usize = 5;
+        let events = access_log::for_entry(id);
+        let summary = if events.is_empty() {
+            "No accesses logged".to_string()
+        } else {
+            let mut summary = events
+                .iter()
+                .take(SHOWN)
+                .map(|event| format!("{} {}", event.kind.label(), format_timestamp(event.at)))
+                .collect::<Vec<_>>()
+                .join(" \u{00b7} ");
+            if events.len() > SHOWN {
+                summary += &format!(" (+{} more)", events.len() - SHOWN);
+            }
+            summary
+        };
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .text_size(px(10.0))
+            .child(div().text_color(rgb(theme.text_muted)).child("Access"))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_color(rgb(theme.text_secondary))
+                    .child(summary),
+            )
+            .when(!events.is_empty(), |el| {
+                el.child(
+                    div()
+                        .id(SharedString::from(format!("access-log-clear-{}", id)))
+                        .px_1()
+                        .rounded(px(4.0))
+                        .text_color(rgb(theme.text_secondary))
+                        .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                        .cursor_pointer()
+                        .child("Clear")
+                        .on_click(move |_, _, app| {
+                            access_log::purge(Some(id));
+                            view_entity.update(app, |_, cx| cx.notify());
+                        }),
+                )
+            })
+    }
+
+    /// Formatted Markdown for the preview: headings, lists, quotes, code blocks and
+    /// inline bold, italic, code and links.
+    fn render_markdown(theme: Theme, text: &str) -> AnyElement {
+        let styled_text = |styled: markdown::Styled| {
+            let highlights: Vec<_> = styled
+                .styles
+                .into_iter()
+                .map(|(range, style)| {
+                    let highlight = match style {
+                        markdown::InlineStyle::Bold => HighlightStyle {
+                            font_weight: Some(gpui::FontWeight::BOLD),
+                            ..Default::default()
+                        },
+                        markdown::InlineStyle::Italic => HighlightStyle {
+                            font_style: Some(gpui::FontStyle::Italic),
+                            ..Default::default()
+                        },
+                        markdown::InlineStyle::Code => HighlightStyle {
+                            background_color: Some(rgba(theme.surface_icon_well).into()),
+                            ..Default::default()
+                        },
+                        markdown::InlineStyle::Link => HighlightStyle {
+                            color: Some(rgb(theme.accent_blue).into()),
+                            underline: Some(gpui::UnderlineStyle {
+                                thickness: px(1.0),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                    };
+                    (range, highlight)
+                })
+                .collect();
+            StyledText::new(styled.text).with_highlights(highlights)
+        };
+
+        let blocks = markdown::parse(text).into_iter().map(|block| match block {
+            markdown::Block::Heading(level, styled) => div()
+                .pt(px(4.0))
+                .font_weight(gpui::FontWeight::BOLD)
+                .text_size(px(match level {
+                    1 => 16.0,
+                    2 => 14.0,
+                    _ => 12.0,
+                }))
+                .child(styled_text(styled)),
+            markdown::Block::Paragraph(styled) => div().child(styled_text(styled)),
+            markdown::Block::Item {
+                depth,
+                marker,
+                text,
+            } => div()
+                .flex()
+                .gap_1()
+                .pl(px(12.0 * depth as f32))
+                .child(
+                    div()
+                        .flex_shrink_0()
+                        .text_color(rgb(theme.text_muted))
+                        .child(marker),
+                )
+                .child(div().flex_1().min_w_0().child(styled_text(text))),
+            markdown::Block::Quote(styled) => div()
+                .pl_2()
+                .border_l_2()
+                .border_color(rgba(theme.surface_border))
+                .text_color(rgb(theme.text_secondary))
+                .child(styled_text(styled)),
+            markdown::Block::Code(code) => div()
+                .p_2()
+                .rounded(px(theme.control_radius))
+                .bg(rgba(theme.surface_icon_well))
+                .children(code.split('\n').map(|line| {
+                    // Empty lines still need height
+                    div().child(if line.is_empty() { " " } else { line }.to_string())
+                })),
+            markdown::Block::Rule => div().my_1().h(px(1.0)).bg(rgba(theme.surface_border)),
+        });
+        div()
+            .px_3()
+            .py_2()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .text_xs()
+            .text_color(rgb(theme.text_primary))
+            .children(blocks)
+            .into_any_element()
+    }
+
+    fn render_preview(&self, entry: &Entry, view_entity: gpui::Entity<Self>) -> impl IntoElement {
+        let theme = self.theme;
+        let hits = self.preview_hits();
+        let current = self.find.as_ref().map(|f| f.current);
+        let hit_style = HighlightStyle {
+            background_color: Some(rgba(tint(theme.accent_orange, 0x40)).into()),
+            ..Default::default()
+        };
+        let current_style = HighlightStyle {
+            background_color: Some(rgba(tint(theme.accent_orange, 0xcc)).into()),
+            color: Some(rgb(0x000000).into()),
+            ..Default::default()
+        };
+
+        let is_image = entry.entry_type == EntryType::Image && Path::new(&entry.content).exists();
+        // Find works on the lines as written, so it shows the source
+        let is_markdown = entry.entry_type == EntryType::Text
+            && !self.markdown_source
+            && self.find.is_none()
+            && markdown::looks_like_markdown(&entry.content);
+        // Secrets stay masked here too until revealed, as in the list
+        let secret = sensitive::detect(entry)
+            .filter(|_| self.mask_sensitive && !self.revealed_ids.contains(&entry.id));
+        // Text is laid out one child per line, so find can scroll to the line of a hit
+        let by_line = !is_image && !is_markdown && secret.is_none();
+        let body: Vec<AnyElement> = if let Some(kind) = secret {
+            let id = entry.id;
+            let view_reveal = view_entity.clone();
+            vec![div()
+                .p_3()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(
+                    div()
+                        .text_color(rgb(theme.text_secondary))
+                        .child(kind.masked()),
+                )
+                .child(
+                    div()
+                        .id(("preview-reveal", id))
+                        .px(px(5.0))
+                        .rounded(px(theme.control_radius))
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_muted))
+                        .hover(|style| {
+                            style
+                                .bg(rgba(tint(theme.accent_blue, 0x18)))
+                                .text_color(rgb(theme.accent_blue))
+                        })
+                        .cursor_pointer()
+                        .child("Show")
+                        .on_click(move |_, _, app| {
+                            view_reveal.update(app, |this, cx| {
+                                this.revealed_ids.insert(id);
+                                access_log::record(id, AccessKind::Viewed);
+                                cx.notify();
+                            });
+                        }),
+                )
+                .into_any_element()]
+        } else if is_image {
+            // Full-size screenshots are scaled down off the render thread first
+            let image = match self.thumbnails.preview(Path::new(&entry.content)) {
+                Thumbnail::Ready(scaled) => {
+                    let asset = SharedString::from(scaled.to_string_lossy().into_owned());
+                    img(asset).w_full().into_any_element()
+                }
+                Thumbnail::Pending => div()
+                    .w_full()
+                    .h(px(160.0))
+                    .rounded(px(theme.control_radius))
+                    .overflow_hidden()
+                    .child(
+                        match self.thumbnails.placeholder(Path::new(&entry.content)) {
+                            Some(colors) => blurred_placeholder(colors).into_any_element(),
+                            None => placeholder(("preview-placeholder", entry.id), &theme)
+                                .into_any_element(),
+                        },
+                    )
+                    .into_any_element(),
+                Thumbnail::Failed => img(PathBuf::from(&entry.content))
+                    .w_full()
+                    .into_any_element(),
+            };
+            vec![div()
+                .p_3()
+                .flex()
+                .flex_col()
+                .gap_2()
+                .child(image)
+                .when_some(ocr::text(entry), |el, text| {
+                    el.child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(theme.text_secondary))
+                            .child(text),
+                    )
+                })
+                .into_any_element()]
+        } else if is_markdown {
+            vec![Self::render_markdown(theme, &entry.content)]
+        } else {
+            // Find shows its hits on plain text rather than over the syntax colours
+            let code = (entry.entry_type == EntryType::Text && self.find.is_none())
+                .then(|| highlight::cached(entry.id, &entry.content, theme.name == ThemeName::Dark))
+                .flatten();
+            let code = code.as_deref();
+            let lines = entry
+                .content
+                .split('\n')
+                .enumerate()
+                .map(|(line_idx, line)| {
+                    if let Some(spans) = code.and_then(|code| code.lines.get(line_idx)) {
+                        let highlights = spans.iter().map(|span| {
+                            (
+                                span.range.clone(),
+                                HighlightStyle {
+                                    color: Some(rgb(span.color).into()),
+                                    font_weight: span.bold.then_some(gpui::FontWeight::BOLD),
+                                    font_style: span.italic.then_some(gpui::FontStyle::Italic),
+                                    ..Default::default()
+                                },
+                            )
+                        });
+                        let text = if line.is_empty() { " " } else { line };
+                        return div()
+                            .px_3()
+                            .child(StyledText::new(text.to_string()).with_highlights(highlights));
+                    }
+                    let highlights: Vec<_> = hits
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, hit)| hit.line == line_idx)
+                        .map(|(hit_idx, hit)| {
+                            let style = if Some(hit_idx) == current {
+                                current_style
+                            } else {
+                                hit_style
+                            };
+                            (hit.range.clone(), style)
+                        })
+                        .collect();
+                    // Empty lines still need height so line indices match scroll items
+                    let text = if line.is_empty() { " " } else { line };
+                    div()
+                        .px_3()
+                        .child(StyledText::new(text.to_string()).with_highlights(highlights))
+                });
+            // Never shown during a find, so line n is the nth child when it scrolls
+            let language = code.map(|code| {
+                div()
+                    .px_3()
+                    .pb_1()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_muted))
+                    .child(code.language.clone())
+                    .into_any_element()
+            });
+            language
+                .into_iter()
+                .chain(lines.map(IntoElement::into_any_element))
+                .collect()
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .flex_1()
+            .min_h_0()
+            .when_some(self.find.as_ref(), |el, find| {
+                let view_prev = view_entity.clone();
+                let view_next = view_entity.clone();
+                let count_label = if hits.is_empty() {
+                    if find.query.is_empty() {
+                        String::new()
+                    } else {
+                        "No matches".to_string()
+                    }
+                } else {
+                    format!("{}/{}", find.current + 1, hits.len())
+                };
+                let nav_button = |id: &'static str, label: &'static str| {
+                    div()
+                        .id(SharedString::from(id))
+                        .px_1()
+                        .rounded(px(4.0))
+                        .text_color(rgb(theme.text_secondary))
+                        .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                        .cursor_pointer()
+                        .child(label)
+                };
+                el.child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .gap_2()
+                        .px_3()
+                        .py(px(4.0))
+                        .border_b_1()
+                        .border_color(rgba(theme.surface_border))
+                        .text_size(px(10.0))
+                        .child(div().text_color(rgb(theme.text_muted)).child("Find"))
+                        .child(
+                            div()
+                                .flex_1()
+                                .min_w_0()
+                                .truncate()
+                                .text_color(rgb(theme.text_primary))
+                                .child(find.query.clone()),
+                        )
+                        .child(div().text_color(rgb(theme.text_muted)).child(count_label))
+                        .child(nav_button("preview-find-prev", "\u{2191}").on_click(
+                            move |_, _, app| {
+                                view_prev.update(app, |this, cx| {
+                                    this.step_find(false);
+                                    this.scroll_to_current_hit();
+                                    cx.notify();
+                                });
+                            },
+                        ))
+                        .child(nav_button("preview-find-next", "\u{2193}").on_click(
+                            move |_, _, app| {
+                                view_next.update(app, |this, cx| {
+                                    this.step_find(true);
+                                    this.scroll_to_current_hit();
+                                    cx.notify();
+                                });
+                            },
+                        )),
+                )
+            })
+            .when_some(entry.source_label(), |el, source| {
+                el.child(
+                    div()
+                        .flex_shrink_0()
+                        .px_3()
+                        .py(px(4.0))
+                        .border_b_1()
+                        .border_color(rgba(theme.surface_border))
+                        .truncate()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_muted))
+                        .child(format!("Copied from {}", source)),
+                )
+            })
+            .child(
+                div()
+                    .id(SharedString::from("popover-preview"))
+                    .flex_1()
+                    .min_h_0()
+                    .overflow_y_scroll()
+                    .track_scroll(&self.preview_scroll)
+                    .when(by_line, |el| {
+                        el.py_2().text_xs().text_color(rgb(theme.text_primary))
+                    })
+                    .children(body),
+            )
+            .when(access_log::is_enabled(), |el| {
+                el.child(Self::render_access_log(
+                    theme,
+                    entry.id,
+                    view_entity.clone(),
+                ))
+            })
+    }
+
+    /// Clears any search and focuses the entry with `id`, if it is in the history. One
+    /// that isn't loaded yet is focused once the rest of the history is.
+    fn focus_entry(&mut self, id: u64) {
+        self.set_query(String::new());
+        self.pending_focus = None;
+        if let Some(idx) = self.filtered().iter().position(|e| e.id == id) {
+            self.focused_index = Some(idx);
+            self.pending_scroll = Some(idx);
+        } else if LOAD_PROGRESS.lock().unwrap().has_more() {
+            self.pending_focus = Some(id);
+            loading::load_all();
+        }
+    }
+
+    fn select_entry(&mut self, id: u64) {
+        if !self.query.is_empty() {
+            self.search_history.record(&self.query);
+        }
+        self.send_copy(BackendCommand::SelectEntry { id });
+    }
+
+    /// Sends a command that puts an entry on the clipboard. With auto-paste on, its
+    /// response goes to the event loop, which pastes once it arrives; the popover is
+    /// gone by then, so there is nothing to retry from. Otherwise it is tracked.
+    fn send_copy(&mut self, command: BackendCommand) {
+        if !self.auto_paste {
+            return self.send_tracked(command);
+        }
+        match self.backend.send(command) {
+            Ok(response) => *PASTE_REQUEST.lock().unwrap() = Some(response),
+            Err(e) => eprintln!("Failed to copy: {:#}", e),
+        }
+    }
+
+    /// Closes the popover once something was copied. Detached windows stay open.
+    fn dismiss(&self) {
+        if !self.detached {
+            wake::set(&MENU_BAR_CLICKED);
+        }
+    }
+
+    fn remove_entry(&mut self, id: u64) {
+        self.remove_entries(vec![id]);
+    }
+
+    /// Removes the entries and offers to undo it, replacing any earlier removal (or
+    /// finished bulk edit) as the one Undo reverses.
+    fn remove_entries(&mut self, ids: Vec<u64>) {
+        if ids.is_empty() {
+            return;
+        }
+        for &id in &ids {
+            self.send_tracked(BackendCommand::RemoveEntry { id });
+        }
+        self.finished_bulk_job = None;
+        self.removed_ids = Some((ids, Instant::now()));
+    }
+
+    /// Restores the entries removed last, if Undo is still offered for them.
+    fn undo_remove(&mut self) -> bool {
+        let Some((ids, _)) = self.removed_ids.take() else {
+            return false;
+        };
+        for id in ids {
+            self.send_tracked(BackendCommand::RestoreEntry { id });
+        }
+        true
+    }
+
+    fn toggle_pin(&mut self, id: u64) {
+        self.send_tracked(BackendCommand::TogglePin { id });
+    }
+
+    fn swap_entry(&mut self, id: u64) {
+        self.send_tracked(BackendCommand::SwapEntry { id });
+    }
+
+    /// Sends `command` and watches for its response; see `check_in_flight`.
+    fn send_tracked(&mut self, command: BackendCommand) {
+        match self.backend.send(command.clone()) {
+            Ok(response) => self.in_flight.push(TrackedCommand {
+                command,
+                response,
+                sent_at: Instant::now(),
+            }),
+            Err(e) => {
+                self.failed_command = Some(FailedCommand {
+                    command,
+                    response: None,
+                    reason: format!("{:#}", e),
+                })
+            }
+        }
+    }
+
+    /// Whether a command about entry `id` is still waiting for its response.
+    fn is_busy(&self, id: u64) -> bool {
+        self.in_flight
+            .iter()
+            .any(|tracked| tracked.command.entry_id() == Some(id))
+    }
+
+    /// Settles commands that were answered or are overdue. Returns whether anything
+    /// changed on screen.
+    fn check_in_flight(&mut self) -> bool {
+        let mut changed = self.advance_bulk_job();
+        if self
+            .removed_ids
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= UNDO_REMOVE_WINDOW)
+        {
+            self.removed_ids = None;
+            changed = true;
+        }
+        let late_success = self
+            .failed_command
+            .as_ref()
+            .and_then(|failed| failed.response.as_ref())
+            .and_then(PendingResponse::try_result)
+            .is_some_and(|result| result.is_ok());
+        if late_success {
+            self.failed_command = None;
+            changed = true;
+        }
+        if let Some(result) = self
+            .pending_search
+            .as_ref()
+            .and_then(PendingResponse::try_result)
+        {
+            self.pending_search = None;
+            match result {
+                Ok(BackendMessage::SearchResults { data, total }) => {
+                    self.found = Some(search::Found {
+                        entries: data,
+                        total,
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Search failed: {:#}", e),
+            }
+            changed = true;
+        }
+
+        // Changes to entries past the loaded ones send no `entry-*` messages, so the
+        // matches are looked up again after each command
+        let mut settled = false;
+        for tracked in std::mem::take(&mut self.in_flight) {
+            let (result, response) = match tracked.response.try_result() {
+                Some(result) => (result, None),
+                None if tracked.sent_at.elapsed() >= COMMAND_TIMEOUT => {
+                    (Err(BackendError::TimedOut.into()), Some(tracked.response))
+                }
+                None => {
+                    self.in_flight.push(tracked);
+                    continue;
+                }
+            };
+            changed = true;
+            if let Err(e) = result {
+                eprintln!("{} failed: {:#}", tracked.command.label(), e);
+                self.failed_command = Some(FailedCommand {
+                    command: tracked.command,
+                    response,
+                    reason: format!("{:#}", e),
+                });
+            } else {
+                settled = true;
+            }
+        }
+        if settled && self.found.is_some() {
+            self.search_backend();
+        }
+        changed
+    }
+
+    /// When `check_in_flight` next has something to do unprompted: a command timing
+    /// out, Undo going away, the next batch of a bulk edit. Responses wake it anyway.
+    fn next_deadline(&self) -> Option<Instant> {
+        let timeouts = self
+            .in_flight
+            .iter()
+            .map(|tracked| tracked.sent_at + COMMAND_TIMEOUT);
+        let undo = self
+            .removed_ids
+            .as_ref()
+            .map(|(_, at)| *at + UNDO_REMOVE_WINDOW);
+        let batch = self
+            .bulk_job
+            .as_ref()
+            .map(|_| Instant::now() + BULK_BATCH_INTERVAL);
+        timeouts.chain(undo).chain(batch).min()
+    }
+
+    fn retry_failed_command(&mut self) {
+        if let Some(failed) = self.failed_command.take_if(|f| f.command.is_retryable()) {
+            self.send_tracked(failed.command);
+        }
+    }
+
+    fn toggle_snippets(&mut self) {
+        self.snippets_open = !self.snippets_open;
+        self.archive_open = false;
+        self.stats_open = false;
+        self.preview_open = false;
+        self.find = None;
+        self.set_query(String::new());
+    }
+
+    /// Shows removed entries instead of the history, asking the backend for them.
+    fn toggle_archive(&mut self) {
+        self.archive_open = !self.archive_open;
+        self.snippets_open = false;
+        self.stats_open = false;
+        self.preview_open = false;
+        self.find = None;
+        self.set_query(String::new());
+        if self.archive_open {
+            self.send_tracked(BackendCommand::GetTrash);
+        }
+    }
+
+    /// Shows the weekly digest instead of the history.
+    fn toggle_stats(&mut self) {
+        self.stats_open = !self.stats_open;
+        if self.stats_open && LOAD_PROGRESS.lock().unwrap().has_more() {
+            loading::load_all();
+        }
+        self.snippets_open = false;
+        self.archive_open = false;
+        self.preview_open = false;
+        self.find = None;
+        self.set_query(String::new());
+    }
+
+    fn matching_snippets(&self) -> Vec<protocol::Snippet> {
+        snippets::matching(&SNIPPETS.lock().unwrap(), &self.query)
+    }
+
+    /// Puts the snippet on the clipboard and closes the popover, pasting it when
+    /// auto-paste is on.
+    fn insert_snippet(&self, snippet: protocol::Snippet) {
+        let response = self.backend.send(BackendCommand::SetClipboard {
+            content: snippet.content,
+            entry_type: EntryType::Text,
+        });
+        match response {
+            Ok(response) if self.auto_paste => *PASTE_REQUEST.lock().unwrap() = Some(response),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to insert snippet: {:#}", e),
+        }
+        self.dismiss();
+    }
+
+    /// Where keystrokes go right now, innermost first.
+    fn key_context(&self) -> KeyContext {
+        if self.context_menu.is_some() {
+            KeyContext::Menu
+        } else if self.entry_editor.is_some() {
+            KeyContext::Edit
+        } else if self.tag_editor.is_some() {
+            KeyContext::Tags
+        } else if self.split_prompt.is_some() {
+            KeyContext::Split
+        } else if self.snippet_prompt.is_some() {
+            KeyContext::NameSnippet
+        } else if self.snippets_open {
+            KeyContext::Snippets
+        } else if self.find.is_some() {
+            KeyContext::Find
+        } else if self.preview_open {
+            KeyContext::Preview
+        } else if !self.query.is_empty() {
+            KeyContext::Search
+        } else {
+            KeyContext::List
+        }
+    }
+
+    /// Shortcuts overlay, listing the keymap bindings that apply in `context`.
+    fn render_shortcuts(theme: Theme, context: KeyContext) -> impl IntoElement {
+        let rows = keymap::for_context(context).map(|binding| {
+            div()
+                .flex()
+                .gap_3()
+                .py(px(2.0))
+                .child(
+                    div()
+                        .w(px(110.0))
+                        .flex_shrink_0()
+                        .text_color(rgb(theme.accent_blue))
+                        .child(binding.keys()),
+                )
+                .child(
+                    div()
+                        .flex_1()
+                        .min_w_0()
+                        .text_color(rgb(theme.text_primary))
+                        .child(binding.description),
+                )
+        });
+        div()
+            .id("shortcuts")
+            .flex_1()
+            .min_h_0()
+            .overflow_y_scroll()
+            .px_3()
+            .py_2()
+            .text_xs()
+            .child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .pb(px(4.0))
+                    .text_size(px(10.0))
+                    .child(
+                        div()
+                            .text_color(rgb(theme.text_secondary))
+                            .child(format!("Shortcuts \u{00b7} {}", context.label())),
+                    )
+                    .child(
+                        div()
+                            .text_color(rgb(theme.text_dim))
+                            .child("any key closes"),
+                    ),
+            )
+            .children(rows)
+    }
+
+    fn open_context_menu(&mut self, id: u64, position: Point<Pixels>) {
+        self.context_menu = Some(EntryMenu {
+            id,
+            position,
+            plugins: plugins::discover(),
+            kind: MenuKind::Full,
+        });
+    }
+
+    fn open_format_menu(&mut self, id: u64, position: Point<Pixels>) {
+        self.context_menu = Some(EntryMenu {
+            id,
+            position,
+            plugins: Vec::new(),
+            kind: MenuKind::Formats,
+        });
+    }
+
+    fn open_files_menu(&mut self, id: u64, position: Point<Pixels>) {
+        self.context_menu = Some(EntryMenu {
+            id,
+            position,
+            plugins: Vec::new(),
+            kind: MenuKind::Files,
+        });
+    }
+
+    /// Puts only `format` of the entry on the clipboard, like selecting it otherwise.
+    fn select_format(&mut self, id: u64, format: Format) {
+        if !self.query.is_empty() {
+            self.search_history.record(&self.query);
+        }
+        self.send_copy(BackendCommand::SelectFormat { id, format });
+    }
+
+    /// Runs `plugin` on the entry off the UI thread and carries out what it replies.
+    fn run_plugin(&self, id: u64, plugin: Plugin) {
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned();
+        let Some(entry) = entry else {
+            return;
+        };
+        let backend = self.backend.clone();
+        thread::spawn(move || {
+            let actions = match plugin.run(&entry) {
+                Ok(actions) => actions,
+                Err(e) => {
+                    eprintln!("Plugin failed: {:#}", e);
+                    return;
+                }
+            };
+            for action in actions {
+                let command = match action {
+                    PluginAction::Copy {
+                        content,
+                        entry_type,
+                    } => BackendCommand::SetClipboard {
+                        content,
+                        entry_type,
+                    },
+                    PluginAction::Add {
+                        content,
+                        entry_type,
+                    } => BackendCommand::AddEntry {
+                        content,
+                        entry_type,
+                        pinned: false,
+                        tags: Vec::new(),
+                    },
+                    PluginAction::SetTags { tags: new_tags } => BackendCommand::SetTags {
+                        id,
+                        tags: tags::parse_tags(&new_tags.join(" ")),
+                    },
+                    PluginAction::Pin { pinned } if pinned != entry.pinned => {
+                        BackendCommand::TogglePin { id }
+                    }
+                    PluginAction::Pin { .. } => continue,
+                    PluginAction::Remove => BackendCommand::RemoveEntry { id },
+                };
+                let _ = backend.send(command);
+            }
+        });
+    }
+
+    fn render_context_menu(
+        &self,
+        menu: &EntryMenu,
+        view_entity: gpui::Entity<Self>,
+    ) -> Option<impl IntoElement> {
+        let theme = self.theme;
+        let id = menu.id;
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()?;
+
+        let item = |key: &str, label: String, color: u32| {
+            div()
+                .id(SharedString::from(format!("entry-menu-{}", key)))
+                .px_2()
+                .py(px(3.0))
+                .rounded(px(theme.control_radius))
+                .text_size(px(11.0))
+                .text_color(rgb(color))
+                .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                .cursor_pointer()
+                .child(label)
+        };
+        // Entries copied in several forms can be copied as just one of them
+        let mut format_items: Vec<AnyElement> = Vec::new();
+        if entry.formats.len() > 1 {
+            for &format in &entry.formats {
+                let view = view_entity.clone();
+                format_items.push(
+                    item(
+                        &format!("copy-as-{:?}", format),
+                        format!("Copy as {}", format.label()),
+                        theme.text_primary,
+                    )
+                    .on_click(move |_, _, app| {
+                        view.update(app, |this, cx| {
+                            this.context_menu = None;
+                            this.select_format(id, format);
+                            this.dismiss();
+                            cx.notify();
+                        });
+                    })
+                    .into_any_element(),
+                );
+            }
+        }
+        // Each of several copied files can be copied on its own; missing ones are dimmed
+        let mut file_items: Vec<AnyElement> = Vec::new();
+        if menu.kind == MenuKind::Files {
+            for (i, path) in entry.file_paths().into_iter().enumerate() {
+                let view = view_entity.clone();
+                let path = path.to_string();
+                let color = if Path::new(&path).exists() {
+                    theme.text_primary
+                } else {
+                    theme.text_dim
+                };
+                file_items.push(
+                    item(&format!("file-{}", i), filename_from_path(&path), color)
+                        .on_click(move |_, _, app| {
+                            view.update(app, |this, cx| {
+                                this.context_menu = None;
+                                let _ = this.backend.send(BackendCommand::SetClipboard {
+                                    content: path.clone(),
+                                    entry_type: EntryType::File,
+                                });
+                                this.dismiss();
+                                cx.notify();
+                            });
+                        })
+                        .into_any_element(),
+                );
+            }
+        }
+        let mut items: Vec<AnyElement> = Vec::new();
+        let mut push_action =
+            |key: &str, label: String, color: u32, action: Option<EntryAction>| {
+                let view = view_entity.clone();
+                items.push(
+                    item(key, label, color)
+                        .on_click(move |_, _, app| {
+                            view.update(app, |this, cx| {
+                                this.context_menu = None;
+                                match action {
+                                    Some(action) => this.apply_action(id, action),
+                                    None => this.open_tag_editor(id),
+                                }
+                                cx.notify();
+                            });
+                        })
+                        .into_any_element(),
+                );
+            };
+        push_action("tags", "Edit Tags\u{2026}".into(), theme.text_primary, None);
+        let pin_label = if entry.pinned { "Unpin" } else { "Pin" };
+        push_action(
+            "pin",
+            pin_label.into(),
+            theme.text_primary,
+            Some(EntryAction::TogglePin),
+        );
+        if EntryAction::Swap.applies_to(&entry) {
+            push_action(
+                "swap",
+                "Swap with Clipboard".into(),
+                theme.text_primary,
+                Some(EntryAction::Swap),
+            );
+        }
+        if EntryAction::Remove.applies_to(&entry) {
+            push_action(
+                "remove",
+                "Remove".into(),
+                theme.danger,
+                Some(EntryAction::Remove),
+            );
+        }
+        if !self.paste_stack_chord.is_empty() {
+            let stacked = paste_stack::STACK.lock().unwrap().position(id).is_some();
+            let label = if stacked {
+                "Remove from Paste Stack"
+            } else {
+                "Add to Paste Stack"
+            };
+            let view = view_entity.clone();
+            items.push(
+                item("paste-stack", label.into(), theme.text_primary)
+                    .on_click(move |_, _, app| {
+                        view.update(app, |this, cx| {
+                            this.context_menu = None;
+                            let mut stack = paste_stack::STACK.lock().unwrap();
+                            if stacked {
+                                stack.remove(id);
+                            } else {
+                                stack.push(id);
+                            }
+                            // The paste stack hotkey is registered while the stack has entries
+                            wake::wake();
+                            cx.notify();
+                        });
+                    })
+                    .into_any_element(),
+            );
+        }
+        if matches!(entry.entry_type, EntryType::Text | EntryType::Url) {
+            let view = view_entity.clone();
+            items.push(
+                item("edit", "Edit\u{2026}".into(), theme.text_primary)
+                    .on_click(move |_, _, app| {
+                        view.update(app, |this, cx| {
+                            this.context_menu = None;
+                            this.open_entry_editor(id);
+                            cx.notify();
+                        });
+                    })
+                    .into_any_element(),
+            );
+            let view = view_entity.clone();
+            let content = entry.content.clone();
+            items.push(
+                item(
+                    "save-snippet",
+                    "Save as Snippet\u{2026}".into(),
+                    theme.text_primary,
+                )
+                .on_click(move |_, _, app| {
+                    view.update(app, |this, cx| {
+                        this.context_menu = None;
+                        this.snippet_prompt = Some(SnippetPrompt::new(&content));
+                        cx.notify();
+                    });
+                })
+                .into_any_element(),
+            );
+        }
+        if qr::applies_to(&entry) {
+            let view = view_entity.clone();
+            let entry = entry.clone();
+            let position = menu.position;
+            items.push(
+                item("qr-code", "Show QR Code".into(), theme.text_primary)
+                    .on_click(move |_, _, app| {
+                        view.update(app, |this, cx| {
+                            this.context_menu = None;
+                            this.show_qr_code(&entry, position);
+                            cx.notify();
+                        });
+                    })
+                    .into_any_element(),
+            );
+        }
+        // The full hash goes on the clipboard; the label shows enough to tell entries apart
+        let hash = content_hash::for_entry(&entry);
+        let view = view_entity.clone();
+        items.push(
+            item(
+                "copy-hash",
+                format!("Copy Hash ({})", hash.get(..8).unwrap_or(&hash)),
+                theme.text_secondary,
+            )
+            .on_click(move |_, _, app| {
+                view.update(app, |this, cx| {
+                    this.context_menu = None;
+                    let _ = this.backend.send(BackendCommand::SetClipboard {
+                        content: hash.clone(),
+                        entry_type: EntryType::Text,
+                    });
+                    cx.notify();
+                });
+            })
+            .into_any_element(),
+        );
+        let separator = || {
+            div()
+                .my(px(3.0))
+                .h(px(1.0))
+                .bg(rgba(theme.surface_border))
+                .into_any_element()
+        };
+        if let Some(language) = code_format::Language::detect(&entry) {
+            items.push(separator());
+            for (key, label, save) in [
+                (
+                    "format-copy",
+                    format!("Format {} and Copy", language.label()),
+                    false,
+                ),
+                (
+                    "format-save",
+                    format!("Format {} as New Entry", language.label()),
+                    true,
+                ),
+            ] {
+                let view = view_entity.clone();
+                let content = entry.content.clone();
+                items.push(
+                    item(key, label, theme.text_secondary)
+                        .on_click(move |_, _, app| {
+                            view.update(app, |this, cx| {
+                                this.context_menu = None;
+                                this.format_code(content.clone(), language, save);
+                                cx.notify();
+                            });
+                        })
+                        .into_any_element(),
+                );
+            }
+        }
+        // Transformed copies go on the clipboard; the stored entry stays as it is
+        let transforms = transforms::available(&entry);
+        if !transforms.is_empty() {
+            items.push(separator());
+        }
+        for (transform, text) in transforms {
+            let view = view_entity.clone();
+            let entry_type = entry.entry_type;
+            items.push(
+                item(
+                    &format!("transform-{:?}", transform),
+                    transform.label().into(),
+                    theme.text_secondary,
+                )
+                .on_click(move |_, _, app| {
+                    view.update(app, |this, cx| {
+                        this.context_menu = None;
+                        let _ = this.backend.send(BackendCommand::SetClipboard {
+                            content: text.clone(),
+                            entry_type,
+                        });
+                        cx.notify();
+                    });
+                })
+                .into_any_element(),
+            );
+        }
+        if !menu.plugins.is_empty() {
+            items.push(separator());
+        }
+        for (i, plugin) in menu.plugins.iter().enumerate() {
+            let view = view_entity.clone();
+            let plugin = plugin.clone();
+            items.push(
+                item(
+                    &format!("plugin-{}", i),
+                    plugin.name.clone(),
+                    theme.accent_purple,
+                )
+                .on_click(move |_, _, app| {
+                    view.update(app, |this, cx| {
+                        this.context_menu = None;
+                        this.run_plugin(id, plugin.clone());
+                        cx.notify();
+                    });
+                })
+                .into_any_element(),
+            );
+        }
+
+        let items = if menu.kind == MenuKind::Files {
+            file_items
+        } else if menu.kind == MenuKind::Formats {
+            format_items
+        } else if format_items.is_empty() {
+            items
+        } else {
+            format_items.push(separator());
+            format_items.extend(items);
+            format_items
+        };
+
+        let view_close = view_entity.clone();
+        Some(deferred(
+            anchored()
+                .position(menu.position)
+                .snap_to_window_with_margin(px(4.0))
+                .child(
+                    div()
+                        .id("entry-menu")
+                        .occlude()
+                        .min_w(px(140.0))
+                        .p(px(3.0))
+                        .flex()
+                        .flex_col()
+                        .bg(rgba(theme.surface_base))
+                        .border_1()
+                        .border_color(rgba(theme.surface_border))
+                        .rounded(px(theme.control_radius))
+                        .shadow_md()
+                        .on_mouse_down_out(move |_, _, app| {
+                            view_close.update(app, |this, cx| {
+                                this.context_menu = None;
+                                cx.notify();
+                            });
+                        })
+                        .children(items),
+                ),
+        ))
+    }
+
+    /// Formats `content` off the UI thread, since an external formatter can take a
+    /// while, then copies the result or adds it to the history with `save`.
+    fn format_code(&self, content: String, language: code_format::Language, save: bool) {
+        let backend = self.backend.clone();
+        let config = self.formatters.clone();
+        thread::spawn(move || {
+            let formatted = match code_format::format(&content, language, &config) {
+                Ok(formatted) => formatted,
+                Err(e) => {
+                    eprintln!("Failed to format {}: {:#}", language.label(), e);
+                    return;
+                }
+            };
+            let command = if save {
+                BackendCommand::AddEntry {
+                    content: formatted,
+                    entry_type: EntryType::Text,
+                    pinned: false,
+                    tags: Vec::new(),
+                }
+            } else {
+                BackendCommand::SetClipboard {
+                    content: formatted,
+                    entry_type: EntryType::Text,
+                }
+            };
+            let _ = backend.send(command);
+        });
+    }
+
+    fn show_qr_code(&mut self, entry: &Entry, position: Point<Pixels>) {
+        // A masked secret stays masked in the caption; the code itself is what was asked for
+        let caption = match sensitive::detect(entry).filter(|_| self.mask_sensitive) {
+            Some(kind) => kind.masked(),
+            None => entry.content.lines().next().unwrap_or_default().to_string(),
+        };
+        self.qr_code = Some(QrPopover {
+            position,
+            caption,
+            image: qr::image(&entry.content).map_err(|e| format!("{:#}", e)),
+        });
+    }
+
+    fn render_qr_code(
+        theme: Theme,
+        qr_code: &QrPopover,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let code = match &qr_code.image {
+            Ok(path) => img(path.clone()).size(px(200.0)).into_any_element(),
+            Err(e) => div()
+                .p_2()
+                .text_xs()
+                .text_color(rgb(theme.danger))
+                .child(format!("Can't show a QR code: {}", e))
+                .into_any_element(),
+        };
+        deferred(
+            anchored()
+                .position(qr_code.position)
+                .snap_to_window_with_margin(px(4.0))
+                .child(
+                    div()
+                        .id("qr-code")
+                        .occlude()
+                        .w(px(216.0))
+                        .p_2()
+                        .flex()
+                        .flex_col()
+                        .items_center()
+                        .gap_2()
+                        .bg(rgba(theme.surface_base))
+                        .border_1()
+                        .border_color(rgba(theme.surface_border))
+                        .rounded(px(theme.control_radius))
+                        .shadow_md()
+                        .on_mouse_down_out(move |_, _, app| {
+                            view_entity.update(app, |this, cx| {
+                                this.qr_code = None;
+                                cx.notify();
+                            });
+                        })
+                        .child(code)
+                        .child(
+                            div()
+                                .w_full()
+                                .truncate()
+                                .text_size(px(10.0))
+                                .text_color(rgb(theme.text_muted))
+                                .child(qr_code.caption.clone()),
+                        ),
+                ),
+        )
+    }
+
+    fn open_entry_editor(&mut self, id: u64) {
+        let entries = self.entries.lock().unwrap();
+        self.entry_editor = entries
+            .iter()
+            .find(|e| e.id == id)
+            .and_then(EntryEditor::new);
+    }
+
+    /// Cmd+E opens the editor on the focused entry. While it is open, typing edits the
+    /// text, Cmd+Enter copies it and Cmd+S saves it as a new entry.
+    fn handle_edit_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if keymap::matches(KeyAction::EditEntry, keystroke) {
+            match self.entry_editor.take() {
+                Some(_) => {}
+                None => {
+                    if let Some(entry) = self.focused_entry() {
+                        self.open_entry_editor(entry.id);
+                    }
+                }
+            }
+            return true;
+        }
+        if self.entry_editor.is_none() {
+            return false;
+        }
+        if keymap::matches(KeyAction::CopyEdit, keystroke) {
+            self.finish_edit(false);
+            return true;
+        }
+        if keymap::matches(KeyAction::SaveEdit, keystroke) {
+            self.finish_edit(true);
+            return true;
+        }
+        let Some(editor) = &mut self.entry_editor else {
+            return false;
+        };
+        let to_line_edge = keystroke.modifiers.platform;
+        match keystroke.key.as_str() {
+            _ if keymap::matches(KeyAction::Cancel, keystroke) => self.entry_editor = None,
+            _ if keymap::matches(KeyAction::Backspace, keystroke) => editor.backspace(),
+            "enter" => editor.insert("\n"),
+            "tab" => editor.insert("\t"),
+            "delete" => editor.delete(),
+            "left" if to_line_edge => editor.line_start(),
+            "right" if to_line_edge => editor.line_end(),
+            "home" => editor.line_start(),
+            "end" => editor.line_end(),
+            "left" => editor.left(),
+            "right" => editor.right(),
+            "up" => editor.up(),
+            "down" => editor.down(),
+            _ => {
+                let text = keystroke
+                    .key_char
+                    .as_deref()
+                    .filter(|t| !keystroke.modifiers.platform && !t.chars().any(char::is_control));
+                if let Some(text) = text {
+                    editor.insert(text);
+                }
+            }
+        }
+        true
+    }
+
+    /// Copies the edited text and closes the popover, or with `save` adds it to the
+    /// history without touching the clipboard. The entry it came from is left as it is.
+    /// Blank text is ignored; unchanged text copies the entry itself.
+    fn finish_edit(&mut self, save: bool) {
+        let Some(editor) = self.entry_editor.take() else {
+            return;
+        };
+        if editor.text.trim().is_empty() {
+            self.entry_editor = Some(editor);
+            return;
+        }
+        if save {
+            if editor.is_changed() {
+                self.send_tracked(BackendCommand::AddEntry {
+                    content: editor.text,
+                    entry_type: editor.entry_type,
+                    pinned: false,
+                    tags: Vec::new(),
+                });
+            }
+            return;
+        }
+        if editor.is_changed() {
+            self.send_tracked(BackendCommand::SetClipboard {
+                content: editor.text,
+                entry_type: editor.entry_type,
+            });
+        } else {
+            self.select_entry(editor.id);
+        }
+        self.dismiss();
+    }
+
+    fn open_tag_editor(&mut self, id: u64) {
+        let entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.iter().find(|e| e.id == id) {
+            self.tag_editor = Some(TagEditor::new(id, &entry.tags));
+        }
+    }
+
+    /// Typing goes to the tag editor while it is open; Enter saves, Escape cancels.
+    fn handle_tag_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if keymap::matches(KeyAction::EditTags, keystroke) {
+            match self.tag_editor.take() {
+                Some(_) => {}
+                None if !self.selected_ids.is_empty() => {
+                    self.tag_editor = Some(TagEditor::for_selection())
+                }
+                None => {
+                    if let Some(entry) = self.focused_entry() {
+                        self.open_tag_editor(entry.id);
+                    }
+                }
+            }
+            return true;
+        }
+        let Some(editor) = &mut self.tag_editor else {
+            return false;
+        };
+        if keymap::matches(KeyAction::Cancel, keystroke) {
+            self.tag_editor = None;
+        } else if keymap::matches(KeyAction::Confirm, keystroke) {
+            let tags = editor.tags();
+            match editor.id {
+                Some(id) => self.send_tracked(BackendCommand::SetTags { id, tags }),
+                None if !tags.is_empty() => self.start_bulk_job(BulkEdit::AddTags(tags)),
+                None => {}
+            }
+            self.tag_editor = None;
+        } else if keymap::matches(KeyAction::Backspace, keystroke) {
+            editor.text.pop();
+        } else {
+            let text = keystroke
+                .key_char
+                .as_deref()
+                .filter(|t| !keystroke.modifiers.platform && !t.chars().any(char::is_control));
+            if let Some(text) = text {
+                editor.text.push_str(text);
+            }
+        }
+        true
+    }
+
+    /// Cmd+L asks for a delimiter to split the focused text entry by; Enter adds one
+    /// entry per piece below the current entry, first piece on top, in one backend
+    /// command. A split with more pieces than the history has room for is refused.
+    fn handle_split_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if keymap::matches(KeyAction::Split, keystroke) {
+            match self.split_prompt.take() {
+                Some(_) => {}
+                None => self.split_prompt = self.focused_entry().and_then(|e| SplitPrompt::new(&e)),
+            }
+            return true;
+        }
+        let Some(prompt) = &mut self.split_prompt else {
+            return false;
+        };
+        if keymap::matches(KeyAction::Cancel, keystroke) {
+            self.split_prompt = None;
+        } else if keymap::matches(KeyAction::Confirm, keystroke) {
+            let parts = prompt.parts();
+            if parts.len() < 2 || !prompt.fits(history_room()) {
+                return true;
+            }
+            // Each piece lands just below the current entry, so the last one goes first
+            let timestamp = unix_now() * 1000;
+            let entries = parts
+                .into_iter()
+                .rev()
+                .map(|content| ImportEntry {
+                    content,
+                    entry_type: EntryType::Text,
+                    timestamp,
+                    pinned: false,
+                    tags: Vec::new(),
+                })
+                .collect();
+            self.split_prompt = None;
+            self.send_tracked(BackendCommand::ImportEntries { entries });
+        } else if keymap::matches(KeyAction::Backspace, keystroke) {
+            prompt.delimiter.pop();
+        } else {
+            let text = keystroke
+                .key_char
+                .as_deref()
+                .filter(|t| !keystroke.modifiers.platform && !t.chars().any(char::is_control));
+            if let Some(text) = text {
+                prompt.delimiter.push_str(text);
+            }
+        }
+        true
+    }
+
+    /// Typing names the snippet being saved from the entry menu while its prompt is
+    /// open. In the snippets list, typing filters, Enter inserts the focused snippet
+    /// and Cmd+Backspace deletes it.
+    fn handle_snippet_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if let Some(prompt) = &mut self.snippet_prompt {
+            if keymap::matches(KeyAction::Cancel, keystroke) {
+                self.snippet_prompt = None;
+            } else if keymap::matches(KeyAction::Confirm, keystroke) {
+                let name = prompt.name.trim().to_string();
+                if name.is_empty() {
+                    return true;
+                }
+                let _ = self.backend.send(BackendCommand::SaveSnippet {
+                    id: None,
+                    name,
+                    content: prompt.content.clone(),
+                });
+                self.snippet_prompt = None;
+            } else if keymap::matches(KeyAction::Backspace, keystroke) {
+                prompt.name.pop();
+            } else {
+                let text = keystroke
+                    .key_char
+                    .as_deref()
+                    .filter(|t| !keystroke.modifiers.platform && !t.chars().any(char::is_control));
+                if let Some(text) = text {
+                    prompt.name.push_str(text);
+                }
+            }
+            return true;
+        }
+        if keymap::matches(KeyAction::ToggleSnippets, keystroke) {
+            self.toggle_snippets();
+            return true;
+        }
+        if !self.snippets_open {
+            return false;
+        }
+
+        let snippets = self.matching_snippets();
+        let focused = snippets.get(self.snippet_index).cloned();
+        if keymap::matches(KeyAction::RemoveSnippet, keystroke) {
+            if let Some(snippet) = focused {
+                let _ = self
+                    .backend
+                    .send(BackendCommand::RemoveSnippet { id: snippet.id });
+            }
+            return true;
+        }
+        if keymap::matches(KeyAction::MoveUp, keystroke) {
+            self.snippet_index = match self.snippet_index {
+                0 => snippets.len().saturating_sub(1),
+                i => i - 1,
+            }
+        } else if keymap::matches(KeyAction::MoveDown, keystroke) {
+            self.snippet_index = if self.snippet_index + 1 < snippets.len() {
+                self.snippet_index + 1
+            } else {
+                0
+            }
+        } else if keymap::matches(KeyAction::Confirm, keystroke) {
+            if let Some(snippet) = focused {
+                self.insert_snippet(snippet);
+            }
+        } else if keymap::matches(KeyAction::Cancel, keystroke) {
+            if self.query.is_empty() {
+                self.snippets_open = false;
+            } else {
+                self.set_query(String::new());
+            }
+        } else if keymap::matches(KeyAction::Backspace, keystroke) {
+            let mut query = self.query.clone();
+            query.pop();
+            self.set_query(query);
+        } else {
+            let text = keystroke.key_char.as_deref().filter(|t| {
+                !keystroke.modifiers.platform
+                    && !keystroke.modifiers.control
+                    && !t.chars().any(char::is_control)
+            });
+            if let Some(text) = text {
+                let query = format!("{}{}", self.query, text);
+                self.set_query(query);
+            }
+        }
+        true
+    }
+
+    /// In the Archived view typing filters it and Escape clears the filter, then
+    /// goes back to the history.
+    /// Vim-style keys, while `[behavior] vim_mode` is on and no search is being typed:
+    /// j/k move, gg and G jump to either end, dd removes and / starts a search. Other
+    /// characters are swallowed rather than searched for; arrows, Enter and Cmd chords
+    /// work as usual.
+    fn handle_vim_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if !keymap::vim_mode() {
+            return false;
+        }
+        if self.vim_searching {
+            if keymap::matches(KeyAction::Copy, keystroke) {
+                self.vim_searching = false;
+            } else if keymap::matches(KeyAction::Cancel, keystroke) {
+                self.vim_searching = false;
+                self.set_query(String::new());
+            } else {
+                return false;
+            }
+            return true;
+        }
+        let pending = self.vim_pending.take();
+        let count = self.filtered().len();
+        if keymap::matches(KeyAction::VimBottom, keystroke) {
+            if count > 0 {
+                self.move_focus(count - 1, false);
+            }
+        } else if keymap::matches(KeyAction::VimDown, keystroke) {
+            let idx = self.focused_index.map_or(0, |idx| idx + 1);
+            if count > 0 {
+                self.move_focus(idx.min(count - 1), false);
+            }
+        } else if keymap::matches(KeyAction::VimUp, keystroke) {
+            let idx = self.focused_index.unwrap_or(0).saturating_sub(1);
+            if count > 0 {
+                self.move_focus(idx.min(count - 1), false);
+            }
+        } else if keymap::matches(KeyAction::VimSearch, keystroke) {
+            self.vim_searching = true;
+        } else if let Some(action) = [KeyAction::VimTop, KeyAction::VimRemove]
+            .into_iter()
+            .find(|&action| keymap::matches(action, keystroke))
+        {
+            if pending != Some(action) {
+                self.vim_pending = Some(action);
+            } else if action == KeyAction::VimRemove {
+                if !self.selected_ids.is_empty() {
+                    self.remove_selected();
+                } else {
+                    self.remove_focused();
+                }
+            } else if count > 0 {
+                self.move_focus(0, false);
+            }
+        } else {
+            let typed = keystroke.key_char.as_deref().is_some_and(|t| {
+                !keystroke.modifiers.platform
+                    && !keystroke.modifiers.control
+                    && !t.chars().any(char::is_control)
+            });
+            // Digits still copy rows, and `?` still lists the shortcuts
+            return typed && !keymap::matches(KeyAction::CopyNumbered, keystroke);
+        }
+        true
+    }
+
+    /// The Stats view has nothing to search, so it only takes Escape, which closes it.
+    /// Cmd chords still reach the usual handling.
+    fn handle_stats_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if !self.stats_open || keystroke.modifiers.platform {
+            return false;
+        }
+        if keymap::matches(KeyAction::Cancel, keystroke) {
+            self.stats_open = false;
+        }
+        true
+    }
+
+    fn handle_archive_key(&mut self, keystroke: &gpui::Keystroke) -> bool {
+        if !self.archive_open {
+            return false;
+        }
+        if keymap::matches(KeyAction::Cancel, keystroke) {
+            if self.query.is_empty() {
+                self.archive_open = false;
+            } else {
+                self.set_query(String::new());
+            }
+        } else if keymap::matches(KeyAction::Backspace, keystroke) {
+            let mut query = self.query.clone();
+            query.pop();
+            self.set_query(query);
+        } else {
+            let text = keystroke.key_char.as_deref().filter(|t| {
+                !keystroke.modifiers.platform
+                    && !keystroke.modifiers.control
+                    && !t.chars().any(char::is_control)
+            });
+            match text {
+                Some(text) => {
+                    let query = format!("{}{}", self.query, text);
+                    self.set_query(query);
+                }
+                // Leaves Cmd chords like Cmd+Z to the usual handling
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn toggle_selected(&mut self, id: u64) {
+        if !self.selected_ids.remove(&id) {
+            self.selected_ids.insert(id);
+        }
+    }
+
+    fn move_focus(&mut self, new_idx: usize, extend_selection: bool) {
+        self.quick_action = None;
+        self.list_focused = true;
+        if extend_selection {
+            let old = self.focused_index.and_then(|i| self.visible_entries.get(i));
+            let new = self.visible_entries.get(new_idx);
+            let ids: Vec<u64> = old.into_iter().chain(new).map(|e| e.id).collect();
+            self.selected_ids.extend(ids);
+        }
+        self.focused_index = Some(new_idx);
+        self.pending_scroll = Some(new_idx);
+    }
+
+    fn day_group_start(&self, idx: usize, forward: bool) -> usize {
+        let now = chrono::Local::now();
+        let days: Vec<DaySection> = self
+            .visible_entries
+            .iter()
+            .map(|e| DaySection::for_entry(e, &now))
+            .collect();
+        navigation::day_group_start(&days, idx, forward)
+    }
+
+    /// Hands the entry to a native drag session once the pointer has moved far enough
+    /// from where it was pressed.
+    fn maybe_start_drag(&mut self, id: u64, position: Point<Pixels>) {
+        let Some((origin_id, origin)) = self.drag_origin else {
+            return;
+        };
+        let delta = position - origin;
+        if origin_id != id || delta.x.abs().max(delta.y.abs()) < px(DRAG_THRESHOLD) {
+            return;
+        }
+        self.drag_origin = None;
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned();
+        if let Some(entry) = entry {
+            if let Err(e) = drag_out::begin(&entry) {
+                eprintln!("Failed to start drag: {:#}", e);
+            }
+        }
+    }
+
+    /// Shift-click: selects every visible entry between the focused one and `idx`.
+    fn select_range_to(&mut self, idx: Option<usize>, id: u64) {
+        let Some(idx) = idx.filter(|&idx| idx < self.visible_entries.len()) else {
+            // The current-entry card isn't part of the list
+            self.toggle_selected(id);
+            return;
+        };
+        let anchor = self.focused_index.unwrap_or(idx);
+        let range = anchor.min(idx)..=anchor.max(idx);
+        let ids: Vec<u64> = self.visible_entries[range].iter().map(|e| e.id).collect();
+        self.selected_ids.extend(ids);
+        self.focused_index = Some(idx);
+    }
+
+    /// Selected entries in history order.
+    fn selected_entries(&self) -> Vec<Entry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| self.selected_ids.contains(&e.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the focused entry. Focus stays put, so it lands on the entry below once
+    /// the list refreshes, or moves up when the last row was removed.
+    fn remove_focused(&mut self) {
+        let entries = self.filtered();
+        let Some(idx) = self.focused_index.filter(|&idx| idx < entries.len()) else {
+            return;
+        };
+        self.apply_action(entries[idx].id, EntryAction::Remove);
+        self.quick_action = None;
+        if idx + 1 == entries.len() {
+            self.focused_index = idx.checked_sub(1);
+        }
+    }
+
+    fn remove_selected(&mut self) {
+        let ids = self
+            .selected_entries()
+            .iter()
+            .filter(|entry| !entry.is_current)
+            .map(|entry| entry.id)
+            .collect();
+        self.remove_entries(ids);
+        self.selected_ids.clear();
+    }
+
+    /// Pins the whole selection, or unpins it when everything selected is already pinned.
+    fn pin_selected(&mut self) {
+        let pin = !self.selected_entries().iter().all(|e| e.pinned);
+        self.start_bulk_job(BulkEdit::Pin(pin));
+    }
+
+    /// Tags the selected entries to be removed `selection_expiry_days` from now.
+    fn expire_selected(&mut self) {
+        self.start_bulk_job(BulkEdit::Expire {
+            days: self.selection_expiry_days,
+        });
+    }
+
+    /// Applies `edit` to the selected entries, a batch per pass of the event loop. Starting another
+    /// edit while one is still going out finishes the first one at once.
+    fn start_bulk_job(&mut self, edit: BulkEdit) {
+        let job = BulkJob::new(&edit, &self.selected_entries(), unix_now());
+        if job.total == 0 {
+            return;
+        }
+        while self.bulk_job.is_some() {
+            self.advance_bulk_job();
+        }
+        self.finished_bulk_job = None;
+        self.removed_ids = None;
+        self.bulk_job = Some(job);
+        self.advance_bulk_job();
+    }
+
+    /// Sends the next batch of the running bulk edit. Returns whether there was one.
+    fn advance_bulk_job(&mut self) -> bool {
+        let Some(job) = &mut self.bulk_job else {
+            return false;
+        };
+        let batch = job.next_batch();
+        if job.is_finished() {
+            self.finished_bulk_job = self.bulk_job.take();
+        }
+        for command in batch {
+            self.send_tracked(command);
+        }
+        true
+    }
+
+    /// Reverses the last bulk edit, across every entry it changed.
+    fn undo_bulk_job(&mut self) {
+        let Some(finished) = self.finished_bulk_job.take() else {
+            return;
+        };
+        if finished.can_undo() {
+            self.bulk_job = Some(finished.undo());
+            self.advance_bulk_job();
+        }
+    }
+
+    /// Joins the selected entries with newlines and copies the result as one text entry.
+    fn copy_selected_combined(&mut self) {
+        let Some(content) = self.combined_selection() else {
+            return;
+        };
+        let _ = self.backend.send(BackendCommand::SetClipboard {
+            content,
+            entry_type: EntryType::Text,
+        });
+        self.selected_ids.clear();
+        self.dismiss();
+    }
+
+    /// Adds the combined selection to the history without touching the clipboard.
+    fn save_selected_combined(&mut self) {
+        let Some(content) = self.combined_selection() else {
+            return;
+        };
+        let _ = self.backend.send(BackendCommand::AddEntry {
+            content,
+            entry_type: EntryType::Text,
+            pinned: false,
+            tags: Vec::new(),
+        });
+        self.selected_ids.clear();
+    }
+
+    /// Queues the selected entries on the paste stack, in history order.
+    fn stack_selected(&mut self) {
+        let mut stack = paste_stack::STACK.lock().unwrap();
+        for entry in self.selected_entries() {
+            stack.push(entry.id);
+        }
+        drop(stack);
+        wake::wake();
+        self.selected_ids.clear();
+    }
+
+    /// The selected entries joined with `combine_separator`, in history order.
+    fn combined_selection(&self) -> Option<String> {
+        let selected = self.selected_entries();
+        let parts: Vec<&str> = selected.iter().map(|e| e.content.as_str()).collect();
+        let content = self.combine_separator.join(&parts);
+        (!content.is_empty()).then_some(content)
+    }
+
+    fn render_selection_bar(
+        &self,
+        all_entries: &[Entry],
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let theme = self.theme;
+        let selected_count = all_entries
+            .iter()
+            .filter(|e| self.selected_ids.contains(&e.id))
+            .count();
+        let archivable_count = self.selected_archivable_paths(all_entries).len();
+        let all_pinned = all_entries
+            .iter()
+            .filter(|e| self.selected_ids.contains(&e.id))
+            .all(|e| e.pinned);
+
+        let action = |id: &'static str, label: String, color: u32, f: fn(&mut Self)| {
+            let view = view_entity.clone();
+            action_button(&theme, id, label, color).on_click(move |_, _, app| {
+                view.update(app, |this, cx| {
+                    f(this);
+                    cx.notify();
+                });
+            })
+        };
+
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(format!("{} selected", selected_count)),
+            )
+            .child(action(
+                "selection-separator",
+                format!("Join {}", self.combine_separator.label()),
+                theme.text_secondary,
+                |this| this.combine_separator = this.combine_separator.next(),
+            ))
+            .child(action(
+                "selection-copy",
+                "Copy".into(),
+                theme.accent_blue,
+                Self::copy_selected_combined,
+            ))
+            .child(action(
+                "selection-save",
+                "Save".into(),
+                theme.accent_blue,
+                Self::save_selected_combined,
+            ))
+            .when(!self.paste_stack_chord.is_empty(), |el| {
+                el.child(action(
+                    "selection-stack",
+                    "Stack".into(),
+                    theme.accent_purple,
+                    Self::stack_selected,
+                ))
+            })
+            .child(action(
+                "selection-pin",
+                if all_pinned { "Unpin" } else { "Pin" }.into(),
+                theme.accent_orange,
+                Self::pin_selected,
+            ))
+            .child(action(
+                "selection-tag",
+                "Tag".into(),
+                theme.accent_purple,
+                |this| this.tag_editor = Some(TagEditor::for_selection()),
+            ))
+            .when(self.selection_expiry_days > 0, |el| {
+                el.child(action(
+                    "selection-expire",
+                    format!("Expire {}d", self.selection_expiry_days),
+                    theme.accent_orange,
+                    Self::expire_selected,
+                ))
+            })
+            .when(archivable_count > 0, |el| {
+                el.child(action(
+                    "selection-zip",
+                    format!("ZIP {}", archivable_count),
+                    theme.accent_green,
+                    Self::compress_selected,
+                ))
+            })
+            .child(action(
+                "selection-export",
+                "Export".into(),
+                theme.accent_purple,
+                Self::export_selected,
+            ))
+            .child(action(
+                "selection-remove",
+                "Remove".into(),
+                theme.danger,
+                Self::remove_selected,
+            ))
+            .child(action(
+                "selection-clear",
+                "\u{00d7}".into(),
+                theme.text_muted,
+                |this| this.selected_ids.clear(),
+            ))
+    }
+
+    /// Paths of the selected file/image entries that still exist on disk.
+    fn selected_archivable_paths(&self, entries: &[Entry]) -> Vec<PathBuf> {
+        entries
+            .iter()
+            .filter(|e| self.selected_ids.contains(&e.id))
+            .flat_map(|e| match e.entry_type {
+                EntryType::Image => vec![PathBuf::from(&e.content)],
+                EntryType::File => e.file_paths().into_iter().map(PathBuf::from).collect(),
+                _ => Vec::new(),
+            })
+            .filter(|p| archive::is_archivable(p))
+            .collect()
+    }
+
+    /// Copies a resized/compressed version of an image entry, then closes the popover.
+    fn copy_image_with_preset(&self, path: PathBuf, preset: ResizePreset) {
+        let backend = self.backend.clone();
+        thread::spawn(move || match preset.apply(&path) {
+            Ok(output) => {
+                let _ = backend.send(BackendCommand::SetClipboard {
+                    content: output.to_string_lossy().into_owned(),
+                    entry_type: EntryType::Image,
+                });
+            }
+            Err(e) => eprintln!("Failed to apply {} preset: {:#}", preset.label(), e),
+        });
+        self.dismiss();
+    }
+
+    /// The entry editor, shown in place of the list: the text with a caret at the cursor,
+    /// under a bar with Copy, Save as New and Cancel.
+    fn render_entry_editor(
+        theme: Theme,
+        editor: &EntryEditor,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let (before, _) = editor.split_at_cursor();
+        let caret_line = before.matches('\n').count();
+        let caret_column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+        let caret_style = HighlightStyle {
+            color: Some(rgb(theme.accent_blue).into()),
+            ..Default::default()
+        };
+        let lines = editor.text.split('\n').enumerate().map(|(line_idx, line)| {
+            if line_idx == caret_line {
+                let (head, tail) = line.split_at(caret_column);
+                let text = format!("{}\u{258f}{}", head, tail);
+                let caret = head.len()..head.len() + '\u{258f}'.len_utf8();
+                return div().child(StyledText::new(text).with_highlights([(caret, caret_style)]));
+            }
+            // Empty lines still need height
+            let text = if line.is_empty() { " " } else { line };
+            div().child(StyledText::new(text.to_string()))
+        });
+        let changed = editor.is_changed();
+        let view_copy = view_entity.clone();
+        let view_save = view_entity.clone();
+        div()
+            .flex()
+            .flex_col()
+            .flex_1()
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_1()
+                    .px_3()
+                    .py(px(4.0))
+                    .border_b_1()
+                    .border_color(rgba(theme.surface_border))
+                    .flex_shrink_0()
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_size(px(10.0))
+                            .text_color(rgb(theme.text_muted))
+                            .child(if changed {
+                                "Editing (changed)"
+                            } else {
+                                "Editing"
+                            }),
+                    )
+                    .child(
+                        action_button(
+                            &theme,
+                            "popover-edit-copy",
+                            "Copy \u{2318}\u{21b5}",
+                            theme.text_primary,
+                        )
+                        .on_click(move |_, _, app| {
+                            view_copy.update(app, |this, cx| {
+                                this.finish_edit(false);
+                                cx.notify();
+                            });
+                        }),
+                    )
+                    .child(
+                        action_button(
+                            &theme,
+                            "popover-edit-save",
+                            "Save as New \u{2318}S",
+                            if changed {
+                                theme.text_primary
+                            } else {
+                                theme.text_dim
+                            },
+                        )
+                        .on_click(move |_, _, app| {
+                            view_save.update(app, |this, cx| {
+                                this.finish_edit(true);
+                                cx.notify();
+                            });
+                        }),
+                    )
+                    .child(
+                        action_button(
+                            &theme,
+                            "popover-edit-cancel",
+                            "Cancel",
+                            theme.text_secondary,
+                        )
+                        .on_click(move |_, _, app| {
+                            view_entity.update(app, |this, cx| {
+                                this.entry_editor = None;
+                                cx.notify();
+                            });
+                        }),
+                    ),
+            )
+            .child(
+                div()
+                    .id("entry-editor")
+                    .flex_1()
+                    .min_h_0()
+                    .overflow_y_scroll()
+                    .px_3()
+                    .py_2()
+                    .text_xs()
+                    .text_color(rgb(theme.text_primary))
+                    .children(lines),
+            )
+    }
+
+    fn render_tag_editor(theme: Theme, editor: &TagEditor) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .text_size(px(10.0))
+            .child(
+                div()
+                    .text_color(rgb(theme.text_muted))
+                    .child(match editor.id {
+                        Some(_) => "Tags",
+                        None => "Add tags to selection",
+                    }),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_color(rgb(theme.accent_purple))
+                    .child(format!("{}\u{258f}", editor.text)),
+            )
+            .child(
+                div()
+                    .text_color(rgb(theme.text_dim))
+                    .child("\u{21b5} save \u{00b7} esc cancel"),
+            )
+    }
+
+    fn render_split_prompt(theme: Theme, prompt: &SplitPrompt) -> impl IntoElement {
+        let count = prompt.parts().len();
+        let room = history_room();
+        let delimiter = if prompt.delimiter.is_empty() {
+            "line breaks".to_string()
+        } else {
+            format!("\"{}\"", prompt.delimiter)
+        };
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .text_size(px(10.0))
+            .child(div().text_color(rgb(theme.text_muted)).child("Split by"))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_color(rgb(theme.accent_blue))
+                    .child(format!("{}\u{258f}", delimiter)),
+            )
+            .child(match room {
+                Some(room) if !prompt.fits(Some(room)) => div()
+                    .text_color(rgb(theme.danger))
+                    .child(format!("{} entries, room for {}", count, room)),
+                _ => div()
+                    .text_color(rgb(if count < 2 {
+                        theme.text_dim
+                    } else {
+                        theme.text_secondary
+                    }))
+                    .child(format!("{} entries", count)),
+            })
+            .child(
+                div()
+                    .text_color(rgb(theme.text_dim))
+                    .child("\u{21b5} split \u{00b7} esc cancel"),
+            )
+    }
+
+    fn render_snippet_prompt(theme: Theme, prompt: &SnippetPrompt) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_2()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .text_size(px(10.0))
+            .child(
+                div()
+                    .text_color(rgb(theme.text_muted))
+                    .child("Snippet name"),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_color(rgb(theme.accent_purple))
+                    .child(format!("{}\u{258f}", prompt.name)),
+            )
+            .child(
+                div()
+                    .text_color(rgb(theme.text_dim))
+                    .child("\u{21b5} save \u{00b7} esc cancel"),
+            )
+    }
+
+    /// Saved snippets matching the query, in place of the entry list.
+    fn render_snippets(&self, view_entity: gpui::Entity<Self>) -> impl IntoElement {
+        let theme = self.theme;
+        let snippets = self.matching_snippets();
+        let empty = snippets.is_empty();
+        let empty_label = if self.query.is_empty() {
+            "No snippets yet. Right-click an entry and choose Save as Snippet\u{2026}"
+        } else {
+            "No matching snippets"
+        };
+        let rows = snippets.into_iter().enumerate().map(|(i, snippet)| {
+            let focused = i == self.snippet_index;
+            let view = view_entity.clone();
+            let preview = snippet
+                .content
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            div()
+                .id(("snippet", snippet.id))
+                .flex()
+                .flex_col()
+                .px_3()
+                .py(px(4.0))
+                .rounded(px(theme.control_radius))
+                .when(focused, |el| el.bg(rgba(theme.surface_row_current)))
+                .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                .cursor_pointer()
+                .child(
+                    div()
+                        .text_xs()
+                        .truncate()
+                        .text_color(rgb(theme.text_primary))
+                        .child(snippet.name.clone()),
+                )
+                .child(
+                    div()
+                        .text_size(px(10.0))
+                        .truncate()
+                        .text_color(rgb(theme.text_muted))
+                        .child(preview),
+                )
+                .on_click(move |_, _, app| {
+                    view.update(app, |this, _| this.insert_snippet(snippet.clone()));
+                })
+        });
+        div()
+            .id("snippets")
+            .flex_1()
+            .min_h_0()
+            .overflow_y_scroll()
+            .px_1()
+            .py(px(LIST_TOP_PADDING))
+            .children(rows)
+            .when(empty, |el| {
+                el.child(
+                    div()
+                        .px_3()
+                        .py_2()
+                        .text_xs()
+                        .text_color(rgb(theme.text_dim))
+                        .child(empty_label),
+                )
+            })
+    }
+
+    /// Removed entries matching the query, in place of the entry list, each with
+    /// Restore and Delete, and Empty for all of them.
+    fn render_archive(&self, view_entity: gpui::Entity<Self>) -> impl IntoElement {
+        let theme = self.theme;
+        let now = unix_now();
+        let trashed = trash::matching(&trash::TRASH.lock().unwrap(), &self.query);
+        let total = trash::TRASH.lock().unwrap().len();
+        let empty_label = if self.query.is_empty() {
+            format!(
+                "Nothing archived. Removed entries are kept here for {} days",
+                trash::RETENTION_DAYS
+            )
+        } else {
+            "No matching archived entries".to_string()
+        };
+        let empty = trashed.is_empty();
+        let view_empty = view_entity.clone();
+        let rows = trashed.into_iter().map(|trashed| {
+            let entry = &trashed.entry;
+            let id = entry.id;
+            let file_count = entry.file_paths().len();
+            let title = match (
+                sensitive::detect(entry).filter(|_| self.mask_sensitive),
+                entry.entry_type,
+            ) {
+                (Some(kind), _) => kind.masked(),
+                (None, EntryType::File) if file_count > 1 => format!("{} files", file_count),
+                (None, EntryType::Image | EntryType::File) => filename_from_path(&entry.content),
+                (None, _) => entry
+                    .content
+                    .lines()
+                    .map(str::trim)
+                    .find(|l| !l.is_empty())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+            let days_left = trash::days_left(&trashed, now);
+            let meta = format!(
+                "{} \u{00b7} removed {} \u{00b7} {}",
+                type_label_for_type(&entry.entry_type),
+                format_timestamp(trashed.removed_at),
+                match days_left {
+                    0 => "purged today".to_string(),
+                    1 => "1 day left".to_string(),
+                    n => format!("{} days left", n),
+                }
+            );
+            let view_restore = view_entity.clone();
+            let view_purge = view_entity.clone();
+            div()
+                .id(("archived", id))
+                .flex()
+                .items_center()
+                .gap_1()
+                .px_3()
+                .py(px(4.0))
+                .rounded(px(theme.control_radius))
+                .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                .child(
+                    div()
+                        .flex()
+                        .flex_col()
+                        .flex_1()
+                        .min_w_0()
+                        .child(
+                            div()
+                                .text_xs()
+                                .truncate()
+                                .text_color(rgb(theme.text_primary))
+                                .child(title),
+                        )
+                        .child(
+                            div()
+                                .text_size(px(10.0))
+                                .truncate()
+                                .text_color(rgb(theme.text_muted))
+                                .child(meta),
+                        ),
+                )
+                .child(
+                    action_button(
+                        &theme,
+                        SharedString::from(format!("popover-restore-{}", id)),
+                        "Restore",
+                        theme.accent_blue,
+                    )
+                    .on_click(move |_, _, app| {
+                        view_restore.update(app, |this, cx| {
+                            this.send_tracked(BackendCommand::RestoreEntry { id });
+                            cx.notify();
+                        });
+                    }),
+                )
+                .child(
+                    action_button(
+                        &theme,
+                        SharedString::from(format!("popover-purge-{}", id)),
+                        "Delete",
+                        theme.danger,
+                    )
+                    .on_click(move |_, _, app| {
+                        view_purge.update(app, |this, cx| {
+                            this.send_tracked(BackendCommand::PurgeEntry { id });
+                            cx.notify();
+                        });
+                    }),
+                )
+        });
+        div()
+            .flex()
+            .flex_col()
+            .flex_1()
+            .min_h_0()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_1()
+                    .px_3()
+                    .py(px(4.0))
+                    .flex_shrink_0()
+                    .child(
+                        div()
+                            .flex_1()
+                            .text_size(px(10.0))
+                            .text_color(rgb(theme.text_secondary))
+                            .child(format!(
+                                "{} archived \u{00b7} deleted after {} days",
+                                total,
+                                trash::RETENTION_DAYS
+                            )),
+                    )
+                    .when(total > 0, |el| {
+                        el.child(
+                            action_button(&theme, "popover-empty-trash", "Empty", theme.danger)
+                                .on_click(move |_, _, app| {
+                                    view_empty.update(app, |this, cx| {
+                                        this.send_tracked(BackendCommand::EmptyTrash);
+                                        cx.notify();
+                                    });
+                                }),
+                        )
+                    }),
+            )
+            .child(
+                div()
+                    .id("archive")
+                    .flex_1()
+                    .min_h_0()
+                    .overflow_y_scroll()
+                    .px_1()
+                    .py(px(LIST_TOP_PADDING))
+                    .children(rows)
+                    .when(empty, |el| {
+                        el.child(
+                            div()
+                                .px_3()
+                                .py_2()
+                                .text_xs()
+                                .text_color(rgb(theme.text_dim))
+                                .child(empty_label),
+                        )
+                    }),
+            )
+    }
+
+    /// The weekly digest as a card, then the week's copies by type and the apps they
+    /// came from.
+    fn render_stats(&self) -> impl IntoElement {
+        let theme = self.theme;
+        // None until the whole history has loaded
+        let digest = digest::DIGEST.lock().unwrap().clone();
+        let summary = match &digest {
+            Some(digest) => digest.summary(),
+            None => "Loading the history…".to_string(),
+        };
+        let digest = digest.unwrap_or_default();
+        let heading = |title: &'static str| {
+            div()
+                .px_3()
+                .pt_2()
+                .pb(px(2.0))
+                .text_size(px(10.0))
+                .text_color(rgb(theme.text_secondary))
+                .child(title)
+        };
+        let row = |label: String, count: usize| {
+            div()
+                .flex()
+                .gap_2()
+                .px_3()
+                .py(px(2.0))
+                .text_xs()
+                .child(
+                    div()
+                        .flex_1()
+                        .min_w_0()
+                        .truncate()
+                        .text_color(rgb(theme.text_primary))
+                        .child(label),
+                )
+                .child(
+                    div()
+                        .text_color(rgb(theme.text_muted))
+                        .child(count.to_string()),
+                )
+        };
+        div()
+            .id("stats")
+            .flex()
+            .flex_col()
+            .flex_1()
+            .min_h_0()
+            .overflow_y_scroll()
+            .py(px(LIST_TOP_PADDING))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .mx_3()
+                    .my_1()
+                    .px_3()
+                    .py_2()
+                    .rounded(px(theme.control_radius))
+                    .bg(rgba(theme.surface_row_current))
+                    .child(
+                        div()
+                            .text_size(px(10.0))
+                            .text_color(rgb(theme.accent_purple))
+                            .child("This week"),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(theme.text_primary))
+                            .child(summary),
+                    ),
+            )
+            .when(!digest.by_type.is_empty(), |el| {
+                el.child(heading("By type"))
+                    .children(digest.by_type.iter().map(|&(entry_type, count)| {
+                        row(type_label_for_type(&entry_type).to_string(), count)
+                    }))
+            })
+            .when(!digest.top_apps.is_empty(), |el| {
+                el.child(heading("Top apps")).children(
+                    digest
+                        .top_apps
+                        .iter()
+                        .map(|(app, count)| row(app.clone(), *count)),
+                )
+            })
+    }
+
+    fn render_preset_bar(
+        theme: Theme,
+        id: u64,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_muted))
+                    .mr_1()
+                    .child("Copy at"),
+            )
+            .children(
+                image_presets::PRESETS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &preset)| {
+                        let view = view_entity.clone();
+                        div()
+                            .id(SharedString::from(format!("popover-preset-{}", i)))
+                            .px_2()
+                            .py(px(2.0))
+                            .rounded(px(theme.control_radius))
+                            .bg(rgba(theme.surface_row))
+                            .text_size(px(10.0))
+                            .text_color(rgb(theme.accent_green))
+                            .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                            .cursor_pointer()
+                            .child(preset.label())
+                            .on_click(move |_, _, app| {
+                                view.update(app, |this, cx| {
+                                    this.apply_action(id, EntryAction::Resize(preset));
+                                    cx.notify();
+                                });
+                            })
+                    }),
+            )
+    }
+
+    /// Switches between the light and dark palette and asks the app to persist it.
+    fn toggle_theme(&mut self) {
+        self.theme = self
+            .theme_overrides
+            .apply(self.theme.name.toggled().theme());
+        *PENDING_THEME.lock().unwrap() = Some(self.theme.name);
+        wake::wake();
+    }
+
+    /// Applies `action` to entry `id` and remembers it for Cmd+. and the recent bar.
+    /// Tab and Shift+Tab step through the focused row's quick actions. Once one is
+    /// chosen, Left and Right move between them, Enter runs it and Escape goes back to
+    /// the row.
+    fn handle_quick_action_key(&mut self, keystroke: &gpui::Keystroke, cx: &mut App) -> bool {
+        if !matches!(self.key_context(), KeyContext::List | KeyContext::Search) {
+            return false;
+        }
+        let Some(entry) = self.focused_entry() else {
+            return false;
+        };
+        let actions = quick_actions::for_entry(&entry);
+        let Some(last) = actions.len().checked_sub(1) else {
+            return false;
+        };
+        let chosen = self.quick_action.map(|i| i.min(last));
+        if keymap::matches(KeyAction::NextQuickAction, keystroke) {
+            self.quick_action = Some(match chosen {
+                Some(i) if i < last => i + 1,
+                _ => 0,
+            });
+            return true;
+        }
+        if keymap::matches(KeyAction::PreviousQuickAction, keystroke) {
+            self.quick_action = Some(match chosen {
+                Some(i) if i > 0 => i - 1,
+                _ => last,
+            });
+            return true;
+        }
+        let Some(i) = chosen else {
+            return false;
+        };
+        if keymap::matches(KeyAction::PickPrevious, keystroke) {
+            self.quick_action = Some(i.saturating_sub(1));
+        } else if keymap::matches(KeyAction::PickNext, keystroke) {
+            self.quick_action = Some((i + 1).min(last));
+        } else if keymap::matches(KeyAction::Copy, keystroke) {
+            self.quick_action = None;
+            self.run_quick_action(&entry, actions[i], cx);
+        } else if keymap::matches(KeyAction::Cancel, keystroke) {
+            self.quick_action = None;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn run_quick_action(&mut self, entry: &Entry, action: QuickAction, cx: &mut App) {
+        match action {
+            QuickAction::OpenUrl => {
+                cx.open_url(entry.content.trim());
+                self.dismiss();
+            }
+            QuickAction::Reveal => match entry.file_paths().first() {
+                Some(path) => cx.reveal_path(Path::new(path)),
+                None => cx.reveal_path(Path::new(&entry.content)),
+            },
+            QuickAction::TogglePin => self.apply_action(entry.id, EntryAction::TogglePin),
+            QuickAction::Swap => self.apply_action(entry.id, EntryAction::Swap),
+            QuickAction::CopyTrimmed => {
+                if let Some(text) = transforms::Transform::Trim.apply(&entry.content) {
+                    let _ = self.backend.send(BackendCommand::SetClipboard {
+                        content: text,
+                        entry_type: entry.entry_type,
+                    });
+                    self.dismiss();
+                }
+            }
+            QuickAction::Edit => self.open_entry_editor(entry.id),
+        }
+    }
+
+    fn apply_action(&mut self, id: u64, action: EntryAction) {
+        let entry = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned();
+        let Some(entry) = entry.filter(|e| action.applies_to(e)) else {
+            return;
+        };
+        // Pin and remove wait for the entry's last command to be answered
+        if !matches!(action, EntryAction::Resize(_)) && self.is_busy(id) {
+            return;
+        }
+        match action {
+            EntryAction::Resize(preset) => {
+                self.copy_image_with_preset(PathBuf::from(&entry.content), preset)
+            }
+            EntryAction::TogglePin => self.toggle_pin(id),
+            EntryAction::Remove => self.remove_entry(id),
+            EntryAction::Swap => self.swap_entry(id),
+        }
+        self.recent_actions.record(action);
+    }
+
+    /// A strip with the progress of a large bulk edit, then what it changed with Undo.
+    fn render_bulk_bar(
+        theme: Theme,
+        job: &BulkJob,
+        running: bool,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let view_dismiss = view_entity.clone();
+        let noun = if job.total == 1 { "entry" } else { "entries" };
+        let status = if running {
+            format!(
+                "{} {} of {} {}\u{2026}",
+                job.label,
+                job.sent(),
+                job.total,
+                noun
+            )
+        } else {
+            format!("{} {} {}", job.done_label, job.total, noun)
+        };
+        let progress = job.sent() as f32 / job.total.max(1) as f32;
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(status),
+            )
+            .when(running, |el| {
+                el.child(
+                    div()
+                        .w(px(80.0))
+                        .h(px(4.0))
+                        .rounded(px(2.0))
+                        .bg(rgba(theme.surface_border))
+                        .child(
+                            div()
+                                .h_full()
+                                .w(px(80.0 * progress))
+                                .rounded(px(2.0))
+                                .bg(rgb(theme.accent_blue)),
+                        ),
+                )
+            })
+            .when(!running && job.can_undo(), |el| {
+                el.child(
+                    action_button(&theme, "popover-undo-bulk", "Undo", theme.accent_blue).on_click(
+                        move |_, _, app| {
+                            view_entity.update(app, |this, cx| {
+                                this.undo_bulk_job();
+                                cx.notify();
+                            });
+                        },
+                    ),
+                )
+            })
+            .when(!running, |el| {
+                el.child(
+                    action_button(&theme, "popover-dismiss-bulk", "\u{00d7}", theme.text_muted)
+                        .on_click(move |_, _, app| {
+                            view_dismiss.update(app, |this, cx| {
+                                this.finished_bulk_job = None;
+                                cx.notify();
+                            });
+                        }),
+                )
+            })
+    }
+
+    /// The dot at the end of the search field: green while the backend is ready, amber
+    /// while it starts, restarts or fails commands, red once it is gone or speaks another
+    /// protocol version. Anything but ready is spelled out next to it.
+    fn render_backend_status(theme: Theme, status: &BackendStatus) -> impl IntoElement {
+        let color = match status {
+            BackendStatus::Ready => theme.accent_green,
+            BackendStatus::Starting
+            | BackendStatus::Errors { .. }
+            | BackendStatus::Reconnecting { .. } => theme.accent_orange,
+            BackendStatus::Dead { .. } | BackendStatus::Mismatch { .. } => theme.danger,
+        };
+        div()
+            .flex()
+            .flex_shrink_0()
+            .items_center()
+            .gap_1()
+            .when_some(status.label(), |el, label| {
+                el.child(
+                    div()
+                        .max_w(px(180.0))
+                        .truncate()
+                        .text_size(px(10.0))
+                        .text_color(rgb(color))
+                        .child(label),
+                )
+            })
+            .child(div().size(px(6.0)).rounded_full().bg(rgb(color)))
+    }
+
+    /// A strip saying how many entries were just removed, with Undo.
+    fn render_removed_bar(
+        theme: Theme,
+        count: usize,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let view_dismiss = view_entity.clone();
+        let status = if count == 1 {
+            "Removed 1 entry".to_string()
+        } else {
+            format!("Removed {} entries", count)
+        };
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(status),
+            )
+            .child(
+                action_button(&theme, "popover-undo-remove", "Undo", theme.accent_blue).on_click(
+                    move |_, _, app| {
+                        view_entity.update(app, |this, cx| {
+                            this.undo_remove();
+                            cx.notify();
+                        });
+                    },
+                ),
+            )
+            .child(
+                action_button(
+                    &theme,
+                    "popover-dismiss-remove",
+                    "\u{00d7}",
+                    theme.text_muted,
+                )
+                .on_click(move |_, _, app| {
+                    view_dismiss.update(app, |this, cx| {
+                        this.removed_ids = None;
+                        cx.notify();
+                    });
+                }),
+            )
+    }
+
+    /// A warning that the disk is nearly full and images aren't being saved, with
+    /// buttons that free up space.
+    fn render_low_disk_bar(
+        theme: Theme,
+        free_bytes: u64,
+        large: (Vec<u64>, u64),
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let view_purge = view_entity.clone();
+        let (large_ids, large_bytes) = large;
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.danger))
+                    .child(format!(
+                        "Disk almost full ({} MB free); images aren't saved",
+                        free_bytes / (1024 * 1024)
+                    )),
+            )
+            .child(
+                action_button(
+                    &theme,
+                    "popover-clear-thumbnails",
+                    "Clear thumbnails",
+                    theme.accent_blue,
+                )
+                .on_click(move |_, _, app| {
+                    view_entity.update(app, |this, cx| {
+                        let freed = this.thumbnails.clear();
+                        notice::show(format!("Cleared {} KB of thumbnails", freed / 1024));
+                        disk_space::recheck();
+                        cx.notify();
+                    });
+                }),
+            )
+            .when(!large_ids.is_empty(), |el| {
+                let label = format!(
+                    "Delete {} large ({} MB)",
+                    large_ids.len(),
+                    large_bytes.div_ceil(1024 * 1024)
+                );
+                el.child(
+                    action_button(&theme, "popover-purge-large", label, theme.danger).on_click(
+                        move |_, _, app| {
+                            view_purge.update(app, |this, cx| {
+                                for &id in &large_ids {
+                                    this.send_tracked(BackendCommand::PurgeEntry { id });
+                                }
+                                disk_space::recheck();
+                                cx.notify();
+                            });
+                        },
+                    ),
+                )
+            })
+    }
+
+    /// A strip counting the entries on the paste stack, with the chord that pastes the
+    /// next one and Clear.
+    fn render_paste_stack_bar(
+        theme: Theme,
+        count: usize,
+        chord: &str,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.accent_purple))
+                    .child(format!(
+                        "Paste stack: {} queued \u{00b7} {} pastes the next",
+                        count, chord
+                    )),
+            )
+            .child(
+                action_button(&theme, "popover-clear-stack", "Clear", theme.text_secondary)
+                    .on_click(move |_, _, app| {
+                        view_entity.update(app, |_, cx| {
+                            paste_stack::STACK.lock().unwrap().clear();
+                            wake::wake();
+                            cx.notify();
+                        });
+                    }),
+            )
+    }
+
+    /// A strip saying which command didn't go through, with Retry and Dismiss.
+    fn render_failed_command(
+        theme: Theme,
+        failed: &FailedCommand,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let view_retry = view_entity.clone();
+        let retryable = failed.command.is_retryable();
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .bg(rgba(tint(theme.danger, 0x18)))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.danger))
+                    .child(format!(
+                        "{} didn't go through: {}",
+                        failed.command.label(),
+                        failed.reason
+                    )),
+            )
+            // The rest may still land late, so they're left for the user to check
+            .when(retryable, |el| {
+                el.child(
+                    action_button(&theme, "popover-retry-command", "Retry", theme.text_primary)
+                        .on_click(move |_, _, app| {
+                            view_retry.update(app, |this, cx| {
+                                this.retry_failed_command();
+                                cx.notify();
+                            });
+                        }),
+                )
+            })
+            .child(
+                action_button(
+                    &theme,
+                    "popover-dismiss-failure",
+                    "Dismiss",
+                    theme.text_secondary,
+                )
+                .on_click(move |_, _, app| {
+                    view_entity.update(app, |this, cx| {
+                        this.failed_command = None;
+                        cx.notify();
+                    });
+                }),
+            )
+    }
+
+    /// A strip with the latest `notice`, and Dismiss.
+    fn render_notice(
+        theme: Theme,
+        message: String,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .flex_1()
+                    .min_w_0()
+                    .truncate()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(message),
+            )
+            .child(
+                action_button(
+                    &theme,
+                    "popover-dismiss-notice",
+                    "Dismiss",
+                    theme.text_secondary,
+                )
+                .on_click(move |_, _, app| {
+                    notice::dismiss();
+                    view_entity.update(app, |_, cx| cx.notify());
+                }),
+            )
+    }
+
+    fn render_recent_bar(
+        theme: Theme,
+        entry: &Entry,
+        actions: Vec<EntryAction>,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement {
+        let id = entry.id;
+        div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .px_3()
+            .py(px(4.0))
+            .border_t_1()
+            .border_color(rgba(theme.surface_border))
+            .flex_shrink_0()
+            .child(
+                div()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_muted))
+                    .mr_1()
+                    .child("Recent"),
+            )
+            .children(actions.into_iter().enumerate().map(|(i, action)| {
+                let view = view_entity.clone();
+                // The newest action is the one Cmd+. repeats
+                let label = if i == 0 {
+                    format!("{} \u{2318}.", action.label())
+                } else {
+                    action.label()
+                };
+                action_button(
+                    &theme,
+                    format!("popover-recent-{}", i),
+                    label,
+                    theme.text_secondary,
+                )
+                .on_click(move |_, _, app| {
+                    view.update(app, |this, cx| {
+                        this.apply_action(id, action);
+                        cx.notify();
+                    });
+                })
+            }))
+    }
+
+    /// Zips the selected files in the background and copies the archive as a file.
+    fn compress_selected(&mut self) {
+        let entries = self.entries.lock().unwrap().clone();
+        let paths = self.selected_archivable_paths(&entries);
+        if paths.is_empty() {
+            return;
+        }
+        self.selected_ids.clear();
+
+        let backend = self.backend.clone();
+        thread::spawn(move || match archive::zip_paths(&paths) {
+            Ok(archive_path) => {
+                let _ = backend.send(BackendCommand::SetClipboard {
+                    content: archive_path.to_string_lossy().into_owned(),
+                    entry_type: EntryType::File,
+                });
+            }
+            Err(e) => eprintln!("Failed to create archive: {:#}", e),
+        });
+    }
+
+    /// Writes the selected entries to a standalone HTML snapshot in the background and
+    /// copies it as a file, ready to paste into a message or mail.
+    fn export_selected(&mut self) {
+        let entries = self.selected_entries();
+        if entries.is_empty() {
+            return;
+        }
+        self.selected_ids.clear();
+
+        let backend = self.backend.clone();
+        thread::spawn(move || match snapshot::export_html(&entries) {
+            Ok(snapshot_path) => {
+                for entry in &entries {
+                    access_log::record(entry.id, AccessKind::Exported);
+                }
+                let _ = backend.send(BackendCommand::SetClipboard {
+                    content: snapshot_path.to_string_lossy().into_owned(),
+                    entry_type: EntryType::File,
+                });
+            }
+            Err(e) => eprintln!("Failed to export snapshot: {:#}", e),
+        });
+    }
+
+    /// Asks where to save the selection (or, without one, the filtered list or whole
+    /// history) and writes it as JSON, or CSV when the chosen name ends in `.csv`.
+    fn export_history(&mut self, cx: &mut GpuiContext<Self>) {
+        let whole_history =
+            self.selected_ids.is_empty() && self.query.is_empty() && self.type_filter.is_empty();
+        let entries = if !self.selected_ids.is_empty() {
+            self.selected_entries()
+        } else if !whole_history {
+            self.filtered()
+        } else {
+            self.entries.lock().unwrap().clone()
+        };
+        if entries.is_empty() {
+            return;
+        }
+        // The rest of the history comes from the backend once there is somewhere to save it
+        let backend = (whole_history && LOAD_PROGRESS.lock().unwrap().has_more())
+            .then(|| self.backend.clone());
+
+        let path = cx.prompt_for_new_path(&save_directory(), Some("Clipz history.json"));
+        cx.background_executor()
+            .spawn(async move {
+                let written = |path: &Path| {
+                    let entries = match &backend {
+                        Some(backend) => backend.all_entries()?,
+                        None => entries,
+                    };
+                    export::write(path, &entries).map(|()| entries)
+                };
+                match path.await {
+                    Ok(Ok(Some(path))) => match written(&path) {
+                        Ok(entries) => {
+                            for entry in &entries {
+                                access_log::record(entry.id, AccessKind::Exported);
+                            }
+                        }
+                        Err(e) => eprintln!("Failed to export history: {:#}", e),
+                    },
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => eprintln!("Failed to open save dialog: {:#}", e),
+                    Err(_) => {}
+                }
+            })
+            .detach();
+    }
+
+    /// Asks for a file written by Export… and merges its entries into the history,
+    /// then says how that went.
+    fn import_history(&mut self, cx: &mut GpuiContext<Self>) {
+        let paths = cx.prompt_for_paths(gpui::PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Import".into()),
+        });
+        let backend = self.backend.clone();
+        cx.background_executor()
+            .spawn(async move {
+                let path = match paths.await {
+                    Ok(Ok(Some(mut paths))) if !paths.is_empty() => paths.remove(0),
+                    Ok(Ok(_)) | Err(_) => return,
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to open file dialog: {:#}", e);
+                        return;
+                    }
+                };
+                let entries = match export::read(&path) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        eprintln!("Failed to import history: {:#}", e);
+                        notice::show(format!("Import failed: {:#}", e));
+                        return;
+                    }
+                };
+                let mut report = export::ImportReport::default();
+                for batch in export::import_batches(entries) {
+                    let response = backend
+                        .send(BackendCommand::ImportEntries { entries: batch })
+                        .and_then(|pending| pending.wait(Duration::from_secs(5)));
+                    match response {
+                        Ok(BackendMessage::Imported {
+                            added,
+                            existing,
+                            skipped,
+                        }) => {
+                            report.added += added;
+                            report.existing += existing;
+                            report.skipped += skipped;
+                        }
+                        Ok(other) => {
+                            eprintln!("Unexpected response to import: {:?}", other);
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to import history: {:#}", e);
+                            notice::show(format!("Import stopped ({:#}). {}", e, report.summary()));
+                            return;
+                        }
+                    }
+                }
+                notice::show(report.summary());
+            })
+            .detach();
+    }
+
+    /// `idx` is the row's place in `visible_entries`, or `None` for an entry shown
+    /// outside the list.
+    fn render_popover_entry(
+        &self,
+        entry: &Entry,
+        idx: Option<usize>,
+        thumbnail: Option<Thumbnail>,
+        view_entity: gpui::Entity<Self>,
+    ) -> impl IntoElement + 'static {
+        let theme = self.theme;
+        let preview_lines = self.preview_lines;
+        let is_focused = idx.is_some() && self.focused_index == idx;
+        let is_selected = self.selected_ids.contains(&entry.id);
+        let id = entry.id;
+        let content = entry.content.clone();
+        let entry_type = entry.entry_type;
+        let is_current = entry.is_current;
+        let is_pinned = entry.pinned;
+        let stack_position = paste_stack::STACK.lock().unwrap().position(id);
+        // The number key that copies the row, counted from the top of the view
+        let number_badge = idx
+            .and_then(|idx| idx.checked_sub(self.first_visible_entry))
+            .filter(|&row| row < navigation::NUMBER_KEYS)
+            .map(|row| row + 1);
+        let copies = entry.copies;
+        let is_rich = entry.is_rich();
+        let has_formats = entry.formats.len() > 1;
+        let file_count = entry.file_paths().len();
+        let is_busy = self.is_busy(id);
+        let image_path = entry.content.clone();
+        let path_exists = std::path::Path::new(&image_path).exists();
+        let timestamp_str = format_timestamp(entry.timestamp);
+        let ic = icon_color_for_type(&entry.entry_type, &theme);
+        let tl = type_label_for_type(&entry.entry_type);
+        let secret = if self.mask_sensitive {
+            sensitive::detect(entry)
+        } else {
+            None
+        };
+        let is_revealed = self.revealed_ids.contains(&id);
+        // Web pages show their title, with the host under it
+        let page_title = link_titles::title(entry);
+        let link_host = page_title
+            .as_ref()
+            .and_then(|_| link_titles::host(&content))
+            .map(str::to_string);
+
+        let display_label: String = match (secret.filter(|_| !is_revealed), &entry_type) {
+            (Some(kind), _) => kind.masked(),
+            // Images with text read from them are titled by it, not by the file name
+            (None, EntryType::Image) if path_exists => match ocr::text(entry) {
+                Some(text) => image_titles::title(&text),
+                None => filename_from_path(&content),
+            },
+            (None, EntryType::File) if file_count > 1 => format!("{} files", file_count),
+            (None, EntryType::Image | EntryType::File) => {
+                if path_exists {
+                    filename_from_path(&content)
+                } else {
+                    content.clone()
+                }
+            }
+            (None, EntryType::Url) => page_title.unwrap_or_else(|| content.clone()),
+            (None, _) => content.clone(),
+        };
+
+        let row_bg = if is_current {
+            rgba(theme.surface_row_current)
+        } else if is_focused {
+            rgba(theme.surface_row_focused)
+        } else {
+            rgba(theme.surface_row)
+        };
+
+        let view = view_entity.clone();
+        let view_remove = view_entity.clone();
+        let view_pin = view_entity.clone();
+        let view_press = view_entity.clone();
+        let view_drag = view_entity.clone();
+        let view_menu = view_entity.clone();
+        let view_reveal = view_entity.clone();
+        let tags = entry.tags.clone();
+        let entry_id_str = SharedString::from(format!("pop-entry-{}", id));
+
+        // The focused row trades its details for its quick actions
+        let row_actions = if is_focused {
+            quick_actions::for_entry(entry)
+        } else {
+            Vec::new()
+        };
+        let quick_chips: Vec<_> = row_actions
+            .iter()
+            .enumerate()
+            .map(|(i, &action)| {
+                let chosen = self.quick_action == Some(i);
+                let label = action.label(entry);
+                let view = view_entity.clone();
+                let entry = entry.clone();
+                div()
+                    .id(SharedString::from(format!("pop-quick-{}-{}", id, i)))
+                    .px(px(4.0))
+                    .rounded(px(theme.control_radius))
+                    .flex_shrink_0()
+                    .text_size(px(10.0))
+                    .when(chosen, |el| el.bg(rgba(tint(theme.accent_blue, 0x20))))
+                    .text_color(if chosen {
+                        rgb(theme.accent_blue)
+                    } else {
+                        rgb(theme.text_secondary)
+                    })
+                    .hover(|style| style.text_color(rgb(theme.accent_blue)))
+                    .cursor_pointer()
+                    .child(label)
+                    .on_click(move |_, _, app| {
+                        app.stop_propagation();
+                        view.update(app, |this, cx| {
+                            this.run_quick_action(&entry, action, cx);
+                            cx.notify();
+                        });
+                    })
+            })
+            .collect();
+        let meta = div()
+            .flex()
+            .items_center()
+            .gap_1()
+            .child(div().text_color(rgb(ic)).text_size(px(10.0)).child(tl));
+        let meta = if row_actions.is_empty() {
+            meta.when_some(link_host, |el, host| {
+                el.child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_dim))
+                        .child("\u{00b7}"),
+                )
+                .child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_secondary))
+                        .truncate()
+                        .child(host),
+                )
+            })
+            .when(is_pinned, |el| {
+                el.child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_dim))
+                        .child("\u{00b7}"),
+                )
+                .child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.accent_orange))
+                        .child("Pinned"),
+                )
+            })
+            .when_some(stack_position, |el, position| {
+                el.child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_dim))
+                        .child("\u{00b7}"),
+                )
+                .child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.accent_purple))
+                        .child(format!("Stack {}", position)),
+                )
+            })
+            .child(
+                div()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_dim))
+                    .child("\u{00b7}"),
+            )
+            .child(
+                div()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(timestamp_str),
+            )
+            // Repeated copies were coalesced into this entry
+            .when(copies > 1, |el| {
+                el.child(
+                    div()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.accent_blue))
+                        .flex_shrink_0()
+                        .child(format!("\u{00d7}{}", copies)),
+                )
+            })
+            // Selecting it puts every form back; the badge lists them to pick just one
+            .when(has_formats, |el| {
+                let view = view_entity.clone();
+                el.child(
+                    div()
+                        .id(SharedString::from(format!("pop-formats-{}", id)))
+                        .px(px(4.0))
+                        .rounded(px(theme.control_radius))
+                        .bg(rgba(tint(theme.accent_green, 0x20)))
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.accent_green))
+                        .flex_shrink_0()
+                        .cursor_pointer()
+                        .child(format!(
+                            "{} \u{25be}",
+                            if is_rich { "Rich text" } else { "Formats" }
+                        ))
+                        .on_mouse_down(MouseButton::Left, |_, _, app| app.stop_propagation())
+                        .on_click(move |event, _, app| {
+                            app.stop_propagation();
+                            view.update(app, |this, cx| {
+                                this.open_format_menu(id, event.position());
+                                cx.notify();
+                            });
+                        }),
+                )
+            })
+            // Selecting it copies every file; the badge lists them to copy just one
+            .when(file_count > 1, |el| {
+                let view = view_entity.clone();
+                el.child(
+                    div()
+                        .id(SharedString::from(format!("pop-files-{}", id)))
+                        .px(px(4.0))
+                        .rounded(px(theme.control_radius))
+                        .bg(rgba(tint(theme.accent_green, 0x20)))
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.accent_green))
+                        .flex_shrink_0()
+                        .cursor_pointer()
+                        .child("Files \u{25be}")
+                        .on_mouse_down(MouseButton::Left, |_, _, app| app.stop_propagation())
+                        .on_click(move |event, _, app| {
+                            app.stop_propagation();
+                            view.update(app, |this, cx| {
+                                this.open_files_menu(id, event.position());
+                                cx.notify();
+                            });
+                        }),
+                )
+            })
+            .children(tags.into_iter().map(|tag| {
+                div()
+                    .px(px(4.0))
+                    .rounded(px(theme.control_radius))
+                    .bg(rgba(tint(theme.accent_purple, 0x20)))
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.accent_purple))
+                    .flex_shrink_0()
+                    .child(format!("#{}", tag))
+            }))
+        } else {
+            meta.child(
+                div()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_dim))
+                    .child("\u{00b7}"),
+            )
+            .children(quick_chips)
+        };
+
+        let row = div()
+            .id(entry_id_str)
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .px(px(8.0))
+            .py(px(7.0))
+            .bg(row_bg)
+            .rounded(px(theme.row_radius))
+            .border_1()
+            .border_color(if is_selected {
+                rgba(tint(theme.accent_blue, 0x80))
+            } else {
+                rgba(0x00000000)
+            })
+            .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+            .cursor_pointer()
+            .child(
+                div()
+                    .w(px(8.0))
+                    .flex_shrink_0()
+                    .text_size(px(10.0))
+                    .text_color(rgb(theme.text_dim))
+                    .children(number_badge.map(|n| n.to_string())),
+            )
+            .child(if let Some(Thumbnail::Ready(thumbnail)) = thumbnail {
+                // Loaded through FileSystemAssets rather than as a file image source
+                let asset = SharedString::from(thumbnail.to_string_lossy().into_owned());
+                div()
+                    .size(px(28.0))
+                    .rounded(px(theme.control_radius))
+                    .overflow_hidden()
+                    .flex_shrink_0()
+                    .child(img(asset).size(px(28.0)))
+            } else if thumbnail == Some(Thumbnail::Pending) && path_exists {
+                div()
+                    .size(px(28.0))
+                    .rounded(px(theme.control_radius))
+                    .overflow_hidden()
+                    .flex_shrink_0()
+                    .child(match self.thumbnails.placeholder(Path::new(&image_path)) {
+                        Some(colors) => blurred_placeholder(colors).into_any_element(),
+                        None => {
+                            placeholder(("thumbnail-placeholder", id), &theme).into_any_element()
+                        }
+                    })
+            } else if entry_type == EntryType::Color {
+                let swatch_color = parse_hex_color(&content).unwrap_or(theme.accent_pink);
+                div()
+                    .size(px(28.0))
+                    .rounded(px(theme.control_radius))
+                    .bg(rgba(theme.surface_icon_well))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .flex_shrink_0()
+                    .child(
+                        div()
+                            .size(px(16.0))
+                            .rounded(px(4.0))
+                            .bg(rgb(swatch_color))
+                            .border_1()
+                            .border_color(rgba(theme.focus_border)),
+                    )
+            } else if entry_type == EntryType::Url {
+                div()
+                    .size(px(28.0))
+                    .rounded(px(theme.control_radius))
+                    .bg(rgba(theme.surface_icon_well))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .flex_shrink_0()
+                    .text_size(px(13.0))
+                    .text_color(rgb(ic))
+                    .child("\u{2197}")
+            } else {
+                div()
+                    .size(px(28.0))
+                    .rounded(px(theme.control_radius))
+                    .bg(rgba(theme.surface_icon_well))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .flex_shrink_0()
+                    .child(div().size(px(8.0)).rounded_full().bg(rgb(ic)))
+            })
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .min_w_0()
+                    .gap(px(1.0))
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(theme.text_primary))
+                            .line_height(px(ROW_LINE_HEIGHT))
+                            .map(|label| {
+                                if preview_lines > 1 {
+                                    label
+                                        .h(px(ROW_LINE_HEIGHT * preview_lines as f32))
+                                        .line_clamp(preview_lines)
+                                        .text_ellipsis()
+                                } else {
+                                    label.truncate()
+                                }
+                            })
+                            .child(display_label),
+                    )
+                    .child(meta),
+            )
+            .when(secret.is_some(), |el| {
+                el.child(
+                    div()
+                        .id(SharedString::from(format!("pop-reveal-{}", id)))
+                        .h(px(22.0))
+                        .px(px(5.0))
+                        .rounded(px(theme.control_radius))
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .flex_shrink_0()
+                        .text_size(px(10.0))
+                        .text_color(rgb(theme.text_muted))
+                        .hover(|style| {
+                            style
+                                .bg(rgba(tint(theme.accent_blue, 0x18)))
+                                .text_color(rgb(theme.accent_blue))
+                        })
+                        .cursor_pointer()
+                        .child(if is_revealed { "Hide" } else { "Show" })
+                        .on_click(move |_, _, app| {
+                            app.stop_propagation();
+                            view_reveal.update(app, |this, cx| {
+                                if !this.revealed_ids.remove(&id) {
+                                    this.revealed_ids.insert(id);
+                                    access_log::record(id, AccessKind::Viewed);
+                                }
+                                cx.notify();
+                            });
+                        }),
+                )
+            })
+            .child(
+                div()
+                    .id(SharedString::from(format!("pop-pin-{}", id)))
+                    .size(px(22.0))
+                    .rounded(px(theme.control_radius))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .flex_shrink_0()
+                    .text_size(px(9.0))
+                    .text_color(if is_pinned {
+                        rgb(theme.accent_orange)
+                    } else {
+                        rgb(theme.text_muted)
+                    })
+                    .hover(|style| {
+                        style
+                            .bg(rgba(tint(theme.accent_orange, 0x18)))
+                            .text_color(rgb(theme.accent_orange))
+                    })
+                    .cursor_pointer()
+                    .when(is_busy, |el| el.opacity(0.4).cursor_default())
+                    .child(if is_pinned { "\u{2605}" } else { "\u{2606}" })
+                    .on_click(move |_, _, app| {
+                        app.stop_propagation();
+                        view_pin.update(app, |this, cx| {
+                            this.apply_action(id, EntryAction::TogglePin);
+                            cx.notify();
+                        });
+                    }),
+            )
+            .when(!is_current, |el| {
                 el.child(
                     div()
                         .id(SharedString::from(format!("pop-remove-{}", id)))
                         .size(px(22.0))
-                        .rounded(px(6.0))
+                        .rounded(px(theme.control_radius))
                         .flex()
                         .items_center()
                         .justify_center()
                         .flex_shrink_0()
-                        .text_color(rgb(TEXT_MUTED))
-                        .hover(|style| style.bg(rgba(0xff453a20)).text_color(rgb(DANGER)))
+                        .text_color(rgb(theme.text_muted))
+                        .hover(|style| {
+                            style
+                                .bg(rgba(tint(theme.danger, 0x20)))
+                                .text_color(rgb(theme.danger))
+                        })
                         .cursor_pointer()
+                        .when(is_busy, |el| el.opacity(0.4).cursor_default())
                         .text_xs()
                         .child("\u{00d7}")
                         .on_click(move |_, _, app| {
                             app.stop_propagation();
                             view_remove.update(app, |this, cx| {
-                                this.remove_entry(id, legacy_index);
+                                this.apply_action(id, EntryAction::Remove);
                                 cx.notify();
                             });
                         }),
                 )
             })
-            .on_click(move |_, _, app| {
-                view.update(app, |this, cx| {
-                    this.select_entry(id, legacy_index);
-                    // Signal to close popover after selecting
-                    MENU_BAR_CLICKED.store(true, Ordering::SeqCst);
+            .on_mouse_down(MouseButton::Left, move |event, _, app| {
+                view_press.update(app, |this, _| {
+                    this.drag_origin = Some((id, event.position));
+                });
+            })
+            .on_mouse_down(MouseButton::Right, move |event, _, app| {
+                view_menu.update(app, |this, cx| {
+                    this.open_context_menu(id, event.position);
                     cx.notify();
                 });
             })
+            .on_mouse_move(move |event: &MouseMoveEvent, _, app| {
+                if event.pressed_button != Some(MouseButton::Left) {
+                    return;
+                }
+                view_drag.update(app, |this, _| this.maybe_start_drag(id, event.position));
+            })
+            .on_click(move |event, _, app| {
+                view.update(app, |this, cx| {
+                    if event.modifiers().platform {
+                        this.toggle_selected(id);
+                        cx.notify();
+                        return;
+                    }
+                    if event.modifiers().shift {
+                        this.select_range_to(idx, id);
+                        cx.notify();
+                        return;
+                    }
+                    if this.click_behavior == ClickBehavior::Double && event.click_count() < 2 {
+                        this.focused_index = idx;
+                        cx.notify();
+                        return;
+                    }
+                    this.select_entry(id);
+                    this.dismiss();
+                    cx.notify();
+                });
+            });
+
+        // Spacing lives on a wrapper so uniform_list measures the full row height
+        div().px(px(6.0)).pb(px(1.0)).child(row)
+    }
+
+    /// Last row of the list while older entries are left to load. Coming into view is
+    /// enough to load the next page; clicking it does the same.
+    fn render_loader_row(&self, progress: LoadProgress) -> AnyElement {
+        let theme = self.theme;
+        // Building the row means it is in view
+        if !progress.fetching {
+            loading::load_more();
+        }
+        let label = if progress.fetching {
+            "Loading older entries\u{2026}".to_string()
+        } else {
+            format!(
+                "Older entries\u{2026} ({} more)",
+                progress.total - progress.loaded
+            )
+        };
+        let row = div()
+            .id("popover-load-more")
+            .flex()
+            .items_center()
+            .justify_center()
+            .px(px(8.0))
+            .py(px(7.0))
+            .rounded(px(theme.row_radius))
+            .border_1()
+            .border_color(rgba(0x00000000))
+            .cursor_pointer()
+            .hover(|row| row.bg(rgba(theme.surface_row)))
+            .child(
+                // As tall as an entry's icon, so the row matches the others
+                div()
+                    .h(px(28.0))
+                    .flex()
+                    .items_center()
+                    .text_size(px(11.0))
+                    .text_color(rgb(theme.text_secondary))
+                    .child(label),
+            )
+            .on_click(|_, _, _| loading::load_more());
+        div().px(px(6.0)).pb(px(1.0)).child(row).into_any_element()
+    }
+
+    /// Row-shaped placeholder shown below the entries that have arrived so far.
+    fn render_skeleton_row(&self, idx: usize) -> AnyElement {
+        let theme = self.theme;
+        let bar = |name: &'static str, width: f32, height: f32| {
+            div()
+                .w(gpui::relative(width))
+                .h(px(height))
+                .rounded(px(3.0))
+                .overflow_hidden()
+                .child(placeholder((name, idx), &theme))
+        };
+        let row = div()
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .px(px(8.0))
+            .py(px(7.0))
+            .rounded(px(theme.row_radius))
+            .border_1()
+            .border_color(rgba(0x00000000))
+            .bg(rgba(theme.surface_row))
+            .child(
+                div()
+                    .size(px(28.0))
+                    .flex_shrink_0()
+                    .rounded(px(theme.control_radius))
+                    .overflow_hidden()
+                    .child(placeholder(("skeleton-icon", idx), &theme)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .gap(px(5.0))
+                    .child(bar("skeleton-title", 0.7, 10.0))
+                    .child(bar("skeleton-meta", 0.35, 8.0)),
+            );
+        div().px(px(6.0)).pb(px(1.0)).child(row).into_any_element()
+    }
+
+    /// "Today" / "Yesterday" / date header above the first entry of its section. It
+    /// fills an entry row's slot, since every row of the list is the same height.
+    fn render_section_header(&self, section: DaySection, today: chrono::NaiveDate) -> AnyElement {
+        let theme = self.theme;
+        div()
+            .size_full()
+            .flex()
+            .items_end()
+            .px(px(14.0))
+            .pb(px(4.0))
+            .text_size(px(10.0))
+            .font_weight(gpui::FontWeight::SEMIBOLD)
+            .text_color(rgb(theme.text_muted))
+            .child(section.label(today))
+            .into_any_element()
     }
 }
 
+/// Space above the first row of the entry list.
+const LIST_TOP_PADDING: f32 = 6.0;
+// Placeholder rows shown until the first page of the history arrives
+const SKELETON_ROWS: usize = 6;
 impl Render for MenuBarPopover {
     fn render(&mut self, window: &mut Window, cx: &mut GpuiContext<Self>) -> impl IntoElement {
-        let entries = self.entries.lock().unwrap().clone();
-        let entry_count = entries.len();
+        let theme = self.theme;
+        let all_entries = self.entries.lock().unwrap().clone();
+        let entries = self.filtered();
+        let current_entry = all_entries.iter().find(|e| e.is_current).cloned();
+        let entry_count = all_entries.len();
+        let loading = *LOAD_PROGRESS.lock().unwrap();
+        let backend_status = health::STATUS.lock().unwrap().clone();
         let view_entity = cx.entity();
 
         if self.focused_index.is_none() && !entries.is_empty() {
@@ -693,101 +5484,578 @@ impl Render for MenuBarPopover {
                 };
             }
         }
+        // Gives up on a deep link once everything is loaded, or a search hides the entry
+        if let Some(id) = self.pending_focus {
+            if !loading.fetching && !LOAD_ALL.load(Ordering::SeqCst) {
+                self.pending_focus = None;
+                if let Some(idx) = entries.iter().position(|e| e.id == id) {
+                    self.focused_index = Some(idx);
+                    self.pending_scroll = Some(idx);
+                }
+            }
+        }
         let focused_index = self.focused_index;
+        // A deleted snippet may leave the index past the end
+        if self.snippets_open {
+            let count = self.matching_snippets().len();
+            self.snippet_index = self.snippet_index.min(count.saturating_sub(1));
+        }
 
-        let rendered_entries: Vec<_> = entries
+        self.visible_entries = entries.clone();
+        let now = chrono::Local::now();
+        self.list_rows = if self.date_headers && self.query.is_empty() {
+            let sections: Vec<DaySection> = entries
+                .iter()
+                .map(|e| DaySection::for_entry(e, &now))
+                .collect();
+            navigation::with_headers(&sections)
+        } else {
+            (0..entries.len()).map(ListRow::Entry).collect()
+        };
+        if let Some(idx) = self.pending_scroll.take() {
+            let row = navigation::row_of_entry(&self.list_rows, idx);
+            self.scroll_handle.scroll_to_item(row, ScrollStrategy::Top);
+        }
+        // Headers take an entry row's height, so measure the list by an entry
+        let first_entry_row = self
+            .list_rows
             .iter()
-            .enumerate()
-            .map(|(idx, entry)| {
-                Self::render_popover_entry(entry, idx, focused_index, view_entity.clone())
-            })
-            .collect();
+            .position(|row| matches!(row, ListRow::Entry(_)));
+        let today = now.date_naive();
+        // Rows still to come while the history loads, when they would be listed
+        let searching = !self.query.is_empty() || !self.type_filter.is_empty();
+        let skeleton_rows = if loading.is_starting() && !searching {
+            SKELETON_ROWS
+        } else {
+            0
+        };
+        // The backend looks through older entries for most searches; regex ones keep
+        // loading them instead. Arrowing down to the last row loads more like scrolling
+        // to the loader row does
+        let searched_in_backend = searching && search::runs_in_backend(&self.query);
+        let at_end = focused_index.is_some_and(|idx| idx + 1 >= entries.len());
+        if !searched_in_backend && (searching || at_end) && loading.has_more() && !loading.fetching
+        {
+            loading::load_more();
+        }
+        let loader_row = usize::from(loading.has_more() && !searched_in_backend);
+        let match_count = if searched_in_backend {
+            match &self.found {
+                Some(found) if found.total > found.entries.len() => {
+                    format!("Newest {} of {} matches", entries.len(), found.total)
+                }
+                Some(_) => format!(
+                    "{} of {} items",
+                    entries.len(),
+                    entry_count.max(loading.total)
+                ),
+                None => format!("Searching {} items\u{2026}", entry_count.max(loading.total)),
+            }
+        } else if loading.has_more() {
+            format!(
+                "{} matches in {} of {} items\u{2026}",
+                entries.len(),
+                loading.loaded,
+                loading.total
+            )
+        } else {
+            format!("{} of {} items", entries.len(), entry_count)
+        };
+        // Only the rows in view are built each frame
+        let entry_list = uniform_list(
+            "popover-entry-list",
+            self.list_rows.len() + skeleton_rows + loader_row,
+            cx.processor(move |this, range: Range<usize>, _window, cx| {
+                let view = cx.entity();
+                this.first_visible_entry = range
+                    .clone()
+                    .find_map(|row| match this.list_rows.get(row) {
+                        Some(&ListRow::Entry(idx)) => Some(idx),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+                range
+                    .map(|row| match this.list_rows.get(row) {
+                        Some(&ListRow::Header(section)) => {
+                            this.render_section_header(section, today)
+                        }
+                        Some(&ListRow::Entry(idx)) => {
+                            let entry = &this.visible_entries[idx];
+                            let thumbnail = (entry.entry_type == EntryType::Image)
+                                .then(|| this.thumbnails.get(Path::new(&entry.content)));
+                            this.render_popover_entry(entry, Some(idx), thumbnail, view.clone())
+                                .into_any_element()
+                        }
+                        None if loader_row > 0 => this.render_loader_row(loading),
+                        None => this.render_skeleton_row(row),
+                    })
+                    .collect::<Vec<_>>()
+            }),
+        )
+        .with_width_from_item(first_entry_row)
+        .flex_1()
+        .min_h_0()
+        .pt(px(LIST_TOP_PADDING))
+        .pb(px(2.0))
+        .track_scroll(self.scroll_handle.clone());
+        let stacked_count = if self.paste_stack_chord.is_empty() {
+            0
+        } else {
+            paste_stack::STACK.lock().unwrap().len()
+        };
+        // Only batches long enough to watch show progress; any finished one offers Undo
+        let bulk_bar = match (&self.bulk_job, &self.finished_bulk_job) {
+            (Some(job), _) if job.is_large() => Some((job, true)),
+            (None, Some(job)) => Some((job, false)),
+            _ => None,
+        };
+        let selection_bar = (!self.selected_ids.is_empty())
+            .then(|| self.render_selection_bar(&all_entries, view_entity.clone()));
+        let low_disk = disk_space::LOW_SPACE.lock().unwrap().map(|free| {
+            // The oldest large entries are past the pages loaded so far
+            if LOAD_PROGRESS.lock().unwrap().has_more() {
+                loading::load_all();
+            }
+            (free, disk_space::large_entries(&all_entries))
+        });
+        let preview_entry = focused_index
+            .and_then(|idx| entries.get(idx))
+            .filter(|_| self.preview_open)
+            .cloned();
+        let previewed_id = preview_entry
+            .as_ref()
+            .filter(|_| self.shortcuts.is_none() && !self.snippets_open)
+            .map(|e| e.id);
+        if previewed_id != self.previewed_id {
+            if let Some(id) = previewed_id {
+                access_log::record(id, AccessKind::Viewed);
+            }
+            self.previewed_id = previewed_id;
+        }
+        let focused_entry = focused_index.and_then(|idx| entries.get(idx));
+        let focused_image = focused_entry
+            .filter(|e| e.entry_type == EntryType::Image)
+            .filter(|e| Path::new(&e.content).is_file())
+            .map(|e| e.id);
+        let recent_bar = focused_entry.and_then(|entry| {
+            let actions: Vec<EntryAction> = self.recent_actions.for_entry(entry).collect();
+            (!actions.is_empty())
+                .then(|| Self::render_recent_bar(self.theme, entry, actions, view_entity.clone()))
+        });
+
+        // Last line of the regex error is the human-readable part ("error: unclosed group")
+        let query_error: Option<String> = search::Matcher::new(&self.query).err().map(|e| {
+            e.to_string()
+                .lines()
+                .last()
+                .unwrap_or("invalid pattern")
+                .trim_start_matches("error: ")
+                .to_string()
+        });
 
         let view_clear = view_entity.clone();
+        let view_export = view_entity.clone();
+        let view_import = view_entity.clone();
+        let view_hotkey = view_entity.clone();
+        let hotkey_button_label = if self.recording_hotkey {
+            "Press shortcut\u{2026}".to_string()
+        } else {
+            self.hotkey_label.clone()
+        };
         let view_keyboard = view_entity.clone();
         let entry_count_for_keys = entries.len();
 
         window.focus(&self.focus_handle);
 
-        div()
+        let root = div()
             .track_focus(&self.focus_handle)
             .flex()
             .flex_col()
             .size_full()
-            .bg(rgba(SURFACE_BASE))
+            .bg(rgba(theme.surface_base))
             .border_1()
-            .border_color(rgba(SURFACE_BORDER))
-            .rounded_xl()
+            .border_color(rgba(theme.surface_border))
+            .rounded(px(theme.window_radius))
             .overflow_hidden()
-            .text_color(rgb(TEXT_PRIMARY))
-            .on_key_down(move |evt, _, app| {
+            .text_color(rgb(theme.text_primary))
+            .on_key_down(move |evt, window, app| {
                 view_keyboard.update(app, |this, cx| {
-                    let count = entry_count_for_keys;
-                    if count == 0 {
+                    if this.recording_hotkey {
+                        if keymap::matches(KeyAction::Cancel, &evt.keystroke) {
+                            this.recording_hotkey = false;
+                        } else if let Some(chord) = hotkey_chord(&evt.keystroke) {
+                            *PENDING_HOTKEY.lock().unwrap() = Some(chord.clone());
+                            wake::wake();
+                            this.hotkey_label = chord;
+                            this.recording_hotkey = false;
+                        }
+                        cx.notify();
                         return;
                     }
-                    let key_str = format!("{:?}", evt.keystroke.key).to_lowercase();
-                    match key_str.as_str() {
-                        "\"up\"" | "\"arrowup\"" | "up" | "arrowup" => {
-                            let new_idx = if let Some(idx) = this.focused_index {
-                                if idx > 0 {
-                                    idx - 1
-                                } else {
-                                    count - 1
-                                }
-                            } else {
-                                0
-                            };
-                            this.focused_index = Some(new_idx);
-                            this.scroll_handle.scroll_to_item(new_idx);
-                            cx.notify();
+
+                    let keystroke = &evt.keystroke;
+                    // Any key closes the shortcuts overlay
+                    if this.shortcuts.take().is_some() {
+                        cx.notify();
+                        return;
+                    }
+                    let context = this.key_context();
+                    let typed_question_mark = keystroke.key_char.as_deref() == Some("?")
+                        && !keystroke.modifiers.platform
+                        && this.query.is_empty()
+                        && matches!(context, KeyContext::List | KeyContext::Preview);
+                    if keymap::matches(KeyAction::Shortcuts, keystroke) || typed_question_mark {
+                        this.context_menu = None;
+                        this.shortcuts = Some(context);
+                        cx.notify();
+                        return;
+                    }
+                    // Any key closes the QR code too
+                    if this.qr_code.take().is_some() {
+                        cx.notify();
+                        return;
+                    }
+                    // The entry menu takes keystrokes while it is open, only to close it
+                    if this.context_menu.is_some() {
+                        if keymap::matches(KeyAction::Cancel, keystroke) {
+                            this.context_menu = None;
                         }
-                        "\"down\"" | "\"arrowdown\"" | "down" | "arrowdown" => {
-                            let new_idx = if let Some(idx) = this.focused_index {
-                                if idx < count - 1 {
-                                    idx + 1
-                                } else {
-                                    0
-                                }
-                            } else {
-                                0
-                            };
-                            this.focused_index = Some(new_idx);
-                            this.scroll_handle.scroll_to_item(new_idx);
-                            cx.notify();
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_edit_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_tag_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_split_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_snippet_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_archive_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_stats_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_preview_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_quick_action_key(keystroke, cx) {
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_suggestion_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    if keymap::matches(KeyAction::NewWindow, keystroke) {
+                        wake::set(&NEW_WINDOW_REQUESTED);
+                        return;
+                    }
+                    // Cmd+P pins the selection, or the focused entry when nothing is selected
+                    if keymap::matches(KeyAction::Pin, keystroke) {
+                        if !this.selected_ids.is_empty() {
+                            this.pin_selected();
+                        } else if let Some(entry) = this.focused_entry() {
+                            this.apply_action(entry.id, EntryAction::TogglePin);
                         }
-                        "\"enter\"" | "enter" | "\"return\"" | "return" => {
-                            if let Some(idx) = this.focused_index {
-                                let entries = this.entries.lock().unwrap().clone();
-                                if let Some(entry) = entries.get(idx) {
-                                    this.select_entry(entry.id, idx + 1);
-                                    MENU_BAR_CLICKED.store(true, Ordering::SeqCst);
-                                }
-                            }
-                            cx.notify();
+                        cx.notify();
+                        return;
+                    }
+                    // Cmd+Z restores just-removed entries, or reverses the last bulk edit
+                    if keymap::matches(KeyAction::Undo, keystroke) {
+                        if !this.undo_remove() {
+                            this.undo_bulk_job();
+                        }
+                        cx.notify();
+                        return;
+                    }
+                    // Cmd+S swaps the focused entry with the clipboard
+                    if keymap::matches(KeyAction::Swap, keystroke) {
+                        if let Some(entry) = this.focused_entry() {
+                            this.apply_action(entry.id, EntryAction::Swap);
+                        }
+                        cx.notify();
+                        return;
+                    }
+                    // Cmd+Backspace and Delete remove the selection, or the focused entry;
+                    // so does Backspace once there is no search left to delete from, but
+                    // not when held, so clearing a search never runs on into the list
+                    let plain_backspace = keymap::matches(KeyAction::Backspace, keystroke)
+                        && this.query.is_empty()
+                        && !evt.is_held;
+                    if keymap::matches(KeyAction::Remove, keystroke) || plain_backspace {
+                        if !this.selected_ids.is_empty() {
+                            this.remove_selected();
+                        } else {
+                            this.remove_focused();
                         }
-                        "\"escape\"" | "escape" => {
-                            MENU_BAR_CLICKED.store(true, Ordering::SeqCst);
+                        cx.notify();
+                        return;
+                    }
+                    // Cmd+. repeats the latest action on the focused entry
+                    if keymap::matches(KeyAction::Repeat, keystroke) {
+                        let entry = this.focused_entry();
+                        let action = entry.and_then(|e| {
+                            this.recent_actions.for_entry(&e).next().map(|a| (e.id, a))
+                        });
+                        if let Some((id, action)) = action {
+                            this.apply_action(id, action);
+                        }
+                        cx.notify();
+                        return;
+                    }
+                    // Cmd+1..5 toggles the type chips in order
+                    let chip = keymap::matched(KeyAction::ToggleChip, keystroke)
+                        .and_then(|i| EntryType::ALL.get(i));
+                    if let Some(&entry_type) = chip {
+                        this.toggle_type_filter(entry_type);
+                        cx.notify();
+                        return;
+                    }
+                    if keymap::matches(KeyAction::Backspace, keystroke) {
+                        let mut query = this.query.clone();
+                        query.pop();
+                        this.set_query(query);
+                        cx.notify();
+                        return;
+                    }
+                    if this.handle_vim_key(keystroke) {
+                        cx.notify();
+                        return;
+                    }
+                    // Ctrl+1–9 copy the row showing that number; plain 1–9 do once the
+                    // arrows moved into the list, or in Vim mode, and are typed otherwise
+                    let list_focused = this.list_focused || keymap::vim_mode();
+                    let number_row = keymap::matched(KeyAction::QuickCopy, keystroke)
+                        .or(keymap::matched(KeyAction::CopyNumbered, keystroke)
+                            .filter(|_| list_focused && !this.vim_searching));
+                    if let Some(row) = number_row {
+                        let row = this.first_visible_entry + row;
+                        if let Some(entry) = this.visible_entries.get(row) {
+                            this.select_entry(entry.id);
+                            this.dismiss();
+                        }
+                        cx.notify();
+                        return;
+                    }
+                    if !keystroke.modifiers.platform && !keystroke.modifiers.control {
+                        if let Some(text) = keystroke
+                            .key_char
+                            .as_deref()
+                            .filter(|t| !t.chars().any(char::is_control))
+                        {
+                            let query = format!("{}{}", this.query, text);
+                            this.set_query(query);
                             cx.notify();
+                            return;
+                        }
+                    }
+
+                    if keymap::matches(KeyAction::Cancel, keystroke) {
+                        if !this.query.is_empty() {
+                            this.set_query(String::new());
+                        } else if !this.selected_ids.is_empty() {
+                            this.selected_ids.clear();
+                        } else if this.detached {
+                            window.remove_window();
+                        } else {
+                            wake::set(&MENU_BAR_CLICKED);
+                        }
+                        cx.notify();
+                        return;
+                    }
+
+                    let count = entry_count_for_keys;
+                    if count == 0 {
+                        return;
+                    }
+                    let idx = this.focused_index.unwrap_or(0).min(count - 1);
+                    let jump = [(KeyAction::JumpUp, false), (KeyAction::JumpDown, true)]
+                        .into_iter()
+                        .find(|&(action, _)| keymap::matches(action, keystroke));
+                    let section = [
+                        (KeyAction::PreviousSection, false),
+                        (KeyAction::NextSection, true),
+                    ]
+                    .into_iter()
+                    .find(|&(action, _)| keymap::matches(action, keystroke));
+                    let new_idx = match (jump, section) {
+                        (Some((_, forward)), _) => {
+                            Some(navigation::jump(idx, count, navigation::JUMP_STEP, forward))
+                        }
+                        (_, Some((_, forward))) => Some(this.day_group_start(idx, forward)),
+                        _ => None,
+                    };
+                    if let Some(new_idx) = new_idx {
+                        this.move_focus(new_idx, false);
+                        cx.notify();
+                        return;
+                    }
+
+                    let extend_up = keymap::matches(KeyAction::ExtendUp, keystroke);
+                    let extend_down = keymap::matches(KeyAction::ExtendDown, keystroke);
+                    if extend_up || keymap::matches(KeyAction::MoveUp, keystroke) {
+                        let new_idx = match this.focused_index {
+                            Some(idx) if idx > 0 => idx - 1,
+                            Some(_) => count - 1,
+                            None => 0,
+                        };
+                        this.move_focus(new_idx, extend_up);
+                        cx.notify();
+                    } else if extend_down || keymap::matches(KeyAction::MoveDown, keystroke) {
+                        let new_idx = match this.focused_index {
+                            Some(idx) if idx < count - 1 => idx + 1,
+                            _ => 0,
+                        };
+                        this.move_focus(new_idx, extend_down);
+                        cx.notify();
+                    } else if keymap::matches(KeyAction::Copy, keystroke) {
+                        if let Some(entry) = this.focused_entry() {
+                            this.select_entry(entry.id);
+                            this.dismiss();
                         }
-                        _ => {}
+                        cx.notify();
                     }
                 });
             })
-            // Entry list
+            // Search field
             .child(
                 div()
-                    .id(SharedString::from("popover-entry-list"))
                     .flex()
-                    .flex_col()
-                    .flex_1()
-                    .min_h_0()
-                    .overflow_y_scroll()
-                    .track_scroll(&self.scroll_handle)
-                    .pt(px(6.0))
-                    .pb(px(2.0))
-                    .children(rendered_entries),
+                    .items_center()
+                    .px_3()
+                    .py(px(6.0))
+                    .border_b_1()
+                    .border_color(rgba(theme.surface_border))
+                    .flex_shrink_0()
+                    .gap_2()
+                    .text_xs()
+                    .child(if self.query.is_empty() {
+                        div().flex_1().text_color(rgb(theme.text_dim)).child(
+                            if keymap::vim_mode() && !self.vim_searching {
+                                "j/k to move, / to search\u{2026}"
+                            } else {
+                                "Type to search, / for regex\u{2026}"
+                            },
+                        )
+                    } else {
+                        div()
+                            .flex_1()
+                            .min_w_0()
+                            .text_color(rgb(theme.text_primary))
+                            .truncate()
+                            .child(self.query.clone())
+                    })
+                    .when(self.query.starts_with('/'), |el| {
+                        el.child(
+                            div()
+                                .flex_shrink_0()
+                                .text_size(px(10.0))
+                                .text_color(if query_error.is_some() {
+                                    rgb(theme.danger)
+                                } else {
+                                    rgb(theme.accent_purple)
+                                })
+                                .child(query_error.clone().unwrap_or_else(|| "regex".into())),
+                        )
+                    })
+                    .child(Self::render_backend_status(theme, &backend_status)),
+            )
+            .children(self.render_suggestions(view_entity.clone()))
+            .when_some(current_entry, |el, entry| {
+                el.child(self.render_current_card(&entry, view_entity.clone()))
+            })
+            .child(self.render_type_chips(view_entity.clone()))
+            // Entry list, or the focused entry's preview
+            .child(
+                match (self.shortcuts, self.entry_editor.as_ref(), preview_entry) {
+                    (Some(context), _, _) => {
+                        Self::render_shortcuts(theme, context).into_any_element()
+                    }
+                    (None, Some(editor), _) => {
+                        Self::render_entry_editor(theme, editor, view_entity.clone())
+                            .into_any_element()
+                    }
+                    (None, None, _) if self.snippets_open => {
+                        self.render_snippets(view_entity.clone()).into_any_element()
+                    }
+                    (None, None, _) if self.archive_open => {
+                        self.render_archive(view_entity.clone()).into_any_element()
+                    }
+                    (None, None, _) if self.stats_open => self.render_stats().into_any_element(),
+                    (None, None, Some(entry)) => self
+                        .render_preview(&entry, view_entity.clone())
+                        .into_any_element(),
+                    (None, None, None) => entry_list.into_any_element(),
+                },
             )
+            .when(stacked_count > 0, |el| {
+                el.child(Self::render_paste_stack_bar(
+                    theme,
+                    stacked_count,
+                    &self.paste_stack_chord,
+                    view_entity.clone(),
+                ))
+            })
+            .when_some(bulk_bar, |el, (job, running)| {
+                el.child(Self::render_bulk_bar(
+                    theme,
+                    job,
+                    running,
+                    view_entity.clone(),
+                ))
+            })
+            .when_some(self.removed_ids.as_ref(), |el, (ids, _)| {
+                el.child(Self::render_removed_bar(
+                    theme,
+                    ids.len(),
+                    view_entity.clone(),
+                ))
+            })
+            .when_some(low_disk, |el, (free_bytes, large)| {
+                el.child(Self::render_low_disk_bar(
+                    theme,
+                    free_bytes,
+                    large,
+                    view_entity.clone(),
+                ))
+            })
+            .when_some(self.failed_command.as_ref(), |el, failed| {
+                el.child(Self::render_failed_command(
+                    theme,
+                    failed,
+                    view_entity.clone(),
+                ))
+            })
+            .when_some(notice::NOTICE.lock().unwrap().clone(), |el, message| {
+                el.child(Self::render_notice(theme, message, view_entity.clone()))
+            })
+            .children(recent_bar)
+            .when_some(focused_image, |el, id| {
+                el.child(Self::render_preset_bar(self.theme, id, view_entity.clone()))
+            })
+            .when_some(self.tag_editor.as_ref(), |el, editor| {
+                el.child(Self::render_tag_editor(theme, editor))
+            })
+            .when_some(self.split_prompt.as_ref(), |el, prompt| {
+                el.child(Self::render_split_prompt(theme, prompt))
+            })
+            .when_some(self.snippet_prompt.as_ref(), |el, prompt| {
+                el.child(Self::render_snippet_prompt(theme, prompt))
+            })
+            .children(selection_bar)
             // Footer
             .child(
                 div()
@@ -797,90 +6065,281 @@ impl Render for MenuBarPopover {
                     .px_3()
                     .py(px(6.0))
                     .border_t_1()
-                    .border_color(rgba(SURFACE_BORDER))
+                    .border_color(rgba(theme.surface_border))
                     .flex_shrink_0()
                     .child(
                         div()
                             .text_size(px(10.0))
-                            .text_color(rgb(TEXT_SECONDARY))
-                            .child(format!("{} items", entry_count)),
+                            .text_color(rgb(theme.text_secondary))
+                            .child(if loading.is_starting() {
+                                "Loading history\u{2026}".to_string()
+                            } else if !searching {
+                                format!("{} items", entry_count.max(loading.total))
+                            } else {
+                                match_count
+                            }),
                     )
                     .child(
                         div()
                             .flex()
                             .items_center()
                             .gap_2()
+                            .child({
+                                let view_theme = view_entity.clone();
+                                let icon = match theme.name {
+                                    ThemeName::Dark => "\u{2600}",
+                                    ThemeName::Light => "\u{263e}",
+                                };
+                                action_button(&theme, "popover-theme", icon, theme.text_secondary)
+                                    .on_click(move |_, _, app| {
+                                        view_theme.update(app, |this, cx| {
+                                            this.toggle_theme();
+                                            cx.notify();
+                                        });
+                                    })
+                            })
+                            .child(
+                                div()
+                                    .id(SharedString::from("popover-hotkey"))
+                                    .px_2()
+                                    .py(px(2.0))
+                                    .rounded(px(theme.control_radius))
+                                    .text_size(px(10.0))
+                                    .text_color(if self.recording_hotkey {
+                                        rgb(theme.accent_blue)
+                                    } else {
+                                        rgb(theme.text_secondary)
+                                    })
+                                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                                    .cursor_pointer()
+                                    .child(hotkey_button_label)
+                                    .on_click(move |_, _, app| {
+                                        view_hotkey.update(app, |this, cx| {
+                                            this.recording_hotkey = !this.recording_hotkey;
+                                            cx.notify();
+                                        });
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .id(SharedString::from("popover-export"))
+                                    .px_2()
+                                    .py(px(2.0))
+                                    .rounded(px(theme.control_radius))
+                                    .text_size(px(10.0))
+                                    .text_color(rgb(theme.text_secondary))
+                                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                                    .cursor_pointer()
+                                    .child("Export\u{2026}")
+                                    .on_click(move |_, _, app| {
+                                        view_export.update(app, |this, cx| {
+                                            this.export_history(cx);
+                                        });
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .id(SharedString::from("popover-import"))
+                                    .px_2()
+                                    .py(px(2.0))
+                                    .rounded(px(theme.control_radius))
+                                    .text_size(px(10.0))
+                                    .text_color(rgb(theme.text_secondary))
+                                    .hover(|style| style.bg(rgba(theme.surface_row_hover)))
+                                    .cursor_pointer()
+                                    .child("Import\u{2026}")
+                                    .on_click(move |_, _, app| {
+                                        view_import.update(app, |this, cx| {
+                                            this.import_history(cx);
+                                        });
+                                    }),
+                            )
                             .child(
                                 div()
                                     .id(SharedString::from("popover-clear"))
                                     .px_2()
                                     .py(px(2.0))
-                                    .rounded(px(6.0))
+                                    .rounded(px(theme.control_radius))
                                     .text_size(px(10.0))
-                                    .text_color(rgb(TEXT_SECONDARY))
+                                    .text_color(rgb(theme.text_secondary))
                                     .hover(|style| {
-                                        style.bg(rgba(0xff453a18)).text_color(rgb(DANGER))
+                                        style
+                                            .bg(rgba(tint(theme.danger, 0x18)))
+                                            .text_color(rgb(theme.danger))
                                     })
                                     .cursor_pointer()
                                     .child("Clear All")
                                     .on_click(move |_, _, app| {
                                         view_clear.update(app, |this, cx| {
-                                            let _ = this.backend_tx.send("clear".into());
-                                            let _ = this.backend_tx.send("get-entries".into());
+                                            let _ = this.backend.send(BackendCommand::Clear);
                                             cx.notify();
                                         });
                                     }),
                             )
                             .child({
-                                let quit_tx = self.backend_tx.clone();
+                                let quit_backend = self.backend.clone();
                                 div()
                                     .id(SharedString::from("popover-quit"))
                                     .px_2()
                                     .py(px(2.0))
-                                    .rounded(px(6.0))
+                                    .rounded(px(theme.control_radius))
                                     .text_size(px(10.0))
-                                    .text_color(rgb(TEXT_SECONDARY))
+                                    .text_color(rgb(theme.text_secondary))
                                     .hover(|style| {
-                                        style.bg(rgba(0xff453a18)).text_color(rgb(DANGER))
+                                        style
+                                            .bg(rgba(tint(theme.danger, 0x18)))
+                                            .text_color(rgb(theme.danger))
                                     })
                                     .cursor_pointer()
                                     .child("Quit")
                                     .on_click(move |_, _, _app| {
-                                        let _ = quit_tx.send("quit".into());
+                                        let _ = quit_backend.send(BackendCommand::Quit);
                                         thread::sleep(Duration::from_millis(150));
                                         std::process::exit(0);
                                     })
                             }),
                     ),
             )
+            .children(
+                self.context_menu
+                    .as_ref()
+                    .and_then(|menu| self.render_context_menu(menu, view_entity.clone())),
+            )
+            .when_some(self.qr_code.as_ref(), |el, qr_code| {
+                el.child(Self::render_qr_code(theme, qr_code, view_entity.clone()))
+            });
+
+        if self.dock == DockEdge::None {
+            return root.into_any_element();
+        }
+        let edge = self.dock;
+        let width = f32::from(window.bounds().size.width);
+        root.id("popover-docked")
+            .on_hover(|hovered, _, _| {
+                if !*hovered {
+                    wake::set(&DOCK_LEFT);
+                }
+            })
+            .with_animation(
+                "dock-slide",
+                Animation::new(dock::SLIDE_DURATION).with_easing(ease_out_quint()),
+                move |root, delta| {
+                    root.relative()
+                        .left(px(dock::slide_offset(edge, width, delta)))
+                },
+            )
+            .into_any_element()
+    }
+}
+
+// ---------- DockStrip ----------
+
+/// Thin always-present window at the docked screen edge that opens the popover when
+/// the pointer touches it.
+struct DockStrip {
+    theme: Theme,
+}
+
+impl Render for DockStrip {
+    fn render(&mut self, _window: &mut Window, _cx: &mut GpuiContext<Self>) -> impl IntoElement {
+        div()
+            .id("dock-strip")
+            .size_full()
+            .rounded(px(2.0))
+            .bg(rgba(tint(self.theme.accent_blue, 0x60)))
+            .on_hover(|hovered, _, _| {
+                if *hovered {
+                    wake::set(&DOCK_HOVERED);
+                }
+            })
     }
 }
 
 // ---------- AppState (headless, no window) ----------
 
 struct AppState {
+    config: Config,
     backend: Option<BackendHandle>,
     shared_entries: SharedEntries,
-    supports_id_commands: Arc<AtomicBool>,
-    _hotkey_manager: GlobalHotKeyManager,
-    hotkey_rx: Receiver<()>,
+    // The newest part of the history in the backend's order, as it was sent in pages;
+    // `entry-*` changes apply here
+    backend_entries: Vec<Entry>,
+    // How much of the history `backend_entries` holds, published to `LOAD_PROGRESS`
+    load: LoadProgress,
+    // Sharing with Clipz on other Macs, when `[sync]` is enabled
+    lan_sync: Option<LanSync>,
+    thumbnails: Thumbnails,
+    // Reads text in image entries for search, unless `[behavior] ocr` is off
+    recognizer: Option<ocr::Recognizer>,
+    // Fetches page titles for URL entries when `[behavior] fetch_titles` is on
+    title_fetcher: Option<link_titles::TitleFetcher>,
+    hotkey_manager: GlobalHotKeyManager,
+    hotkey: HotKey,
+    // `[hotkey] snippets` chords, inserting the first nine snippets in order
+    snippet_hotkeys: Vec<HotKey>,
+    // `[hotkey] paste_stack` chord, registered only while the paste stack has entries
+    paste_stack_hotkey: Option<HotKey>,
+    paste_stack_registered: bool,
+    // The next paste stack entry isn't loaded; pasted once the rest of the history is
+    paste_stack_waiting: bool,
+    // Ids of pressed global hotkeys
+    hotkey_rx: Receiver<u32>,
     popover_handle: Option<WindowHandle<MenuBarPopover>>,
+    // Cmd+N windows, each with its own search and filters over the same history
+    windows: Vec<WindowHandle<MenuBarPopover>>,
+    // Edge strip for `[window] dock`; None when the popover hangs off the menu bar
+    dock_handle: Option<WindowHandle<DockStrip>>,
+    // App that was frontmost when the popover opened, for auto-paste
+    previous_app: Option<auto_paste::PreviousApp>,
+    // Response to the copy auto-paste waits on before pasting into `previous_app`
+    pending_paste: Option<PendingResponse>,
+    // Recording paused from the status item menu or a startup action
+    paused: bool,
+    // Append mode from the status item menu: copies are joined into one entry
+    appending: bool,
+    // Set once `[startup] actions` have run, so a restarted backend doesn't rerun them
+    startup_done: bool,
+    theme_overrides: ThemeOverrides,
+    theme_watcher: ThemeWatcher,
+    usage: Usage,
+    // Last sweep for sensitive entries past `[privacy] expire_sensitive_secs`
+    last_expiry_check: Instant,
+    // Last sweep for entries past `[history] retention_days`; None sweeps on the next tick
+    last_retention_check: Option<Instant>,
+    // Image capture as last sent to the backend; off while disk space is low
+    image_capture: bool,
+    // Last look at whether the `[behavior] weekly_digest` notification is due
+    last_digest_check: Option<Instant>,
+    // Crashes, restarts and failed commands, behind the dot by the search field
+    health: Health,
+    // The answer to `hello`, sent each time the backend is ready
+    handshake: Option<PendingResponse>,
 }
 
 impl AppState {
     fn toggle_popover(&mut self, cx: &mut App) {
-        if let Some(handle) = self.popover_handle.take() {
-            let _ = handle.update(cx, |_, window, _| {
-                window.remove_window();
-            });
+        if self.popover_handle.is_some() {
+            self.hide_popover(cx);
             return;
         }
 
-        let pos = get_status_item_position();
-        let popover_width = 320.0_f32;
-        let popover_height = 400.0_f32;
+        self.previous_app = auto_paste::frontmost_app();
 
-        let bounds = if let Some(p) = pos {
+        let popover_width = self.config.window.width;
+        let popover_height = self.config.window.height;
+        let docked = cx.primary_display().and_then(|display| {
+            dock::popover_bounds(
+                self.config.window.dock,
+                display.bounds(),
+                popover_width,
+                popover_height,
+            )
+        });
+        let pos = get_status_item_position(popover_width);
+
+        let bounds = if let Some(docked) = docked {
+            docked
+        } else if let Some(p) = pos {
             Bounds {
                 origin: p,
                 size: size(px(popover_width), px(popover_height)),
@@ -889,93 +6348,1100 @@ impl AppState {
             Bounds::centered(None, size(px(popover_width), px(popover_height)), cx)
         };
 
-        let shared = self.shared_entries.clone();
-        let backend_tx = self.backend.as_ref().map(|b| b.tx.clone());
-        let supports_id_commands = self.supports_id_commands.clone();
+        let shared = self.shared_entries.clone();
+        let backend_client = self.backend.as_ref().map(|b| b.client.clone());
+        let config = self.config.clone();
+        let thumbnails = self.thumbnails.clone();
+        let theme_overrides = self.theme_overrides.clone();
+
+        if let Some(client) = backend_client {
+            let handle = cx
+                .open_window(
+                    WindowOptions {
+                        window_bounds: Some(WindowBounds::Windowed(bounds)),
+                        titlebar: None,
+                        focus: true,
+                        show: true,
+                        kind: WindowKind::PopUp,
+                        is_movable: false,
+                        is_resizable: true,
+                        is_minimizable: false,
+                        window_background: WindowBackgroundAppearance::Blurred,
+                        ..Default::default()
+                    },
+                    |window, cx| {
+                        spaces::apply(window, config.window.spaces, false);
+                        cx.new(|cx| {
+                            MenuBarPopover::new(
+                                shared,
+                                client,
+                                thumbnails,
+                                &config,
+                                theme_overrides,
+                                window,
+                                cx,
+                            )
+                        })
+                    },
+                )
+                .ok();
+
+            self.popover_handle = handle;
+        }
+    }
+
+    /// Opens another history window. Unlike the popover it has a title bar, can be
+    /// moved, and stays open until closed; new ones cascade from the screen centre.
+    fn open_window(&mut self, cx: &mut App) {
+        let Some(client) = self.backend.as_ref().map(|b| b.client.clone()) else {
+            return;
+        };
+        self.windows
+            .retain(|handle| handle.update(cx, |_, _, _| ()).is_ok());
+
+        let popover_size = size(px(self.config.window.width), px(self.config.window.height));
+        let mut bounds = Bounds::centered(None, popover_size, cx);
+        let cascade = px(24.0 * self.windows.len() as f32);
+        bounds.origin += point(cascade, cascade);
+
+        let shared = self.shared_entries.clone();
+        let thumbnails = self.thumbnails.clone();
+        let theme_overrides = self.theme_overrides.clone();
+        let config = self.config.clone();
+        let handle = cx.open_window(
+            WindowOptions {
+                window_bounds: Some(WindowBounds::Windowed(bounds)),
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Clipz".into()),
+                    ..Default::default()
+                }),
+                focus: true,
+                show: true,
+                kind: WindowKind::Normal,
+                is_movable: true,
+                is_resizable: true,
+                is_minimizable: true,
+                window_background: WindowBackgroundAppearance::Blurred,
+                ..Default::default()
+            },
+            |window, cx| {
+                cx.new(|cx| {
+                    let mut view = MenuBarPopover::new(
+                        shared,
+                        client,
+                        thumbnails,
+                        &config,
+                        theme_overrides,
+                        window,
+                        cx,
+                    );
+                    view.detached = true;
+                    // No app to paste back into: the window is what was in front
+                    view.auto_paste = false;
+                    view.dock = DockEdge::None;
+                    view
+                })
+            },
+        );
+        match handle {
+            Ok(handle) => {
+                cx.activate(true);
+                self.windows.push(handle);
+            }
+            Err(e) => eprintln!("Failed to open window: {:#}", e),
+        }
+    }
+
+    /// Opens the strip along the docked edge of the main display, if `[window] dock`
+    /// is set.
+    fn open_dock_strip(&mut self, cx: &mut App) {
+        if self.config.window.dock == DockEdge::None {
+            return;
+        }
+        let Some(bounds) = cx.primary_display().and_then(|display| {
+            dock::strip_bounds(
+                self.config.window.dock,
+                display.bounds(),
+                self.config.window.height,
+            )
+        }) else {
+            return;
+        };
+        let theme = self.dock_strip_theme();
+        self.dock_handle = cx
+            .open_window(
+                WindowOptions {
+                    window_bounds: Some(WindowBounds::Windowed(bounds)),
+                    titlebar: None,
+                    focus: false,
+                    show: true,
+                    kind: WindowKind::PopUp,
+                    is_movable: false,
+                    is_resizable: false,
+                    is_minimizable: false,
+                    window_background: WindowBackgroundAppearance::Transparent,
+                    ..Default::default()
+                },
+                |_, cx| cx.new(|_| DockStrip { theme }),
+            )
+            .ok();
+    }
+
+    /// Closes the popover from Escape, the hotkey or the menu bar icon, and gives focus
+    /// back to the app it was opened over, like Spotlight. Clipz has no window left in
+    /// front, so macOS wouldn't do that by itself. A pending auto-paste activates the
+    /// app itself.
+    fn hide_popover(&mut self, cx: &mut App) {
+        self.close_popover(cx);
+        self.take_paste_request();
+        if self.pending_paste.is_some() {
+            return;
+        }
+        if let Some(app) = self.previous_app.take() {
+            auto_paste::activate(app);
+        }
+    }
+
+    /// Closes the popover, saving its size to the config if the user resized it so the
+    /// next one opens the same. Its position isn't saved: it always opens under the menu
+    /// bar icon, or against the `[window] dock` edge.
+    fn close_popover(&mut self, cx: &mut App) {
+        let Some(handle) = self.popover_handle.take() else {
+            return;
+        };
+        let size = handle.update(cx, |_, window, _| {
+            let size = window.bounds().size;
+            window.remove_window();
+            size
+        });
+        let Ok(size) = size else {
+            return;
+        };
+
+        let (width, height) = (f32::from(size.width), f32::from(size.height));
+        let window = &mut self.config.window;
+        if (window.width - width).abs() < 1.0 && (window.height - height).abs() < 1.0 {
+            return;
+        }
+        window.width = width.round();
+        window.height = height.round();
+        if let Err(e) = self.config.save() {
+            eprintln!("Failed to save config: {:#}", e);
+        }
+    }
+
+    /// Opens the popover (if needed) and applies a `clipz://` deep link to it.
+    fn open_deep_link(&mut self, link: DeepLink, cx: &mut App) {
+        if self.popover_handle.is_none() {
+            self.toggle_popover(cx);
+        }
+        let Some(handle) = self.popover_handle else {
+            return;
+        };
+        let _ = handle.update(cx, |popover, _, cx| {
+            match link {
+                DeepLink::Search(query) => popover.set_query(query),
+                DeepLink::Entry(id) => popover.focus_entry(id),
+            }
+            cx.notify();
+        });
+    }
+
+    fn handle_menu_action(&mut self, action: MenuAction, cx: &mut App) {
+        match action {
+            MenuAction::Show => {
+                if self.popover_handle.is_none() {
+                    self.toggle_popover(cx);
+                }
+            }
+            MenuAction::Select(id) => {
+                if let Some(backend) = &self.backend {
+                    let _ = backend.send(BackendCommand::SelectEntry { id });
+                }
+            }
+            MenuAction::TogglePause => {
+                self.paused = !self.paused;
+                status_menu::set_paused(self.paused);
+                if let Some(backend) = &self.backend {
+                    let _ = backend.send(BackendCommand::SetPaused {
+                        paused: self.paused,
+                    });
+                }
+            }
+            MenuAction::ToggleAppend => {
+                self.appending = !self.appending;
+                status_menu::set_appending(self.appending);
+                if let Some(backend) = &self.backend {
+                    let _ = backend.send(BackendCommand::SetAppendMode {
+                        enabled: self.appending,
+                    });
+                }
+            }
+            MenuAction::ClearAccessLog => access_log::purge(None),
+            MenuAction::ExportTimeline => self.export_timeline(cx),
+            MenuAction::SetHistoryLimit(limit) => self.set_history_limit(limit),
+            MenuAction::SetRetention(days) => {
+                self.config.history.retention_days = days;
+                status_menu::set_retention(days);
+                if let Err(e) = self.config.save() {
+                    eprintln!("Failed to save config: {:#}", e);
+                }
+                self.last_retention_check = None;
+            }
+            MenuAction::ToggleLaunchAtLogin => {
+                let enabled = !self.config.behavior.launch_at_login;
+                if let Err(e) = login_item::set_enabled(enabled) {
+                    eprintln!("Failed to update the login item: {:#}", e);
+                    return;
+                }
+                self.config.behavior.launch_at_login = enabled;
+                status_menu::set_launch_at_login(enabled);
+                if let Err(e) = self.config.save() {
+                    eprintln!("Failed to save config: {:#}", e);
+                }
+            }
+            MenuAction::Quit => {
+                if let Some(lan_sync) = self.lan_sync.as_mut() {
+                    lan_sync.stop();
+                }
+                if let Some(backend) = &self.backend {
+                    let _ = backend.send(BackendCommand::Quit);
+                }
+                thread::sleep(Duration::from_millis(150));
+                std::process::exit(0);
+            }
+        }
+    }
+
+    /// Asks where to save, then writes the backend's recent clipboard events there, for
+    /// looking into copies that never showed up or a slow backend.
+    fn export_timeline(&mut self, cx: &mut App) {
+        let Some(backend) = &self.backend else {
+            return;
+        };
+        let client = backend.client.clone();
+        let path = cx.prompt_for_new_path(&save_directory(), Some("Clipz timeline.json"));
+        cx.background_executor()
+            .spawn(async move {
+                let path = match path.await {
+                    Ok(Ok(Some(path))) => path,
+                    Ok(Ok(None)) | Err(_) => return,
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to open save dialog: {:#}", e);
+                        return;
+                    }
+                };
+                let response = client
+                    .send(BackendCommand::GetTimeline { limit: None })
+                    .and_then(|pending| pending.wait(Duration::from_secs(5)));
+                let result = match response {
+                    Ok(BackendMessage::Timeline { data }) => timeline::write(&path, &data),
+                    Ok(other) => Err(anyhow!("unexpected response {:?}", other)),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    eprintln!("Failed to export timeline: {:#}", e);
+                }
+            })
+            .detach();
+    }
+
+    /// Runs the configured `[startup] actions` in order, or only logs them in dry-run mode.
+    fn run_startup_actions(&mut self, cx: &mut App) {
+        self.startup_done = true;
+        let startup = self.config.startup.clone();
+        for action in startup.actions {
+            if startup.dry_run {
+                eprintln!("Startup action (dry run): {}", action.name());
+                continue;
+            }
+            eprintln!("Startup action: {}", action.name());
+            match action {
+                StartupAction::PauseRecording => {
+                    if !self.paused {
+                        self.handle_menu_action(MenuAction::TogglePause, cx);
+                    }
+                }
+                StartupAction::ClearHistory => {
+                    if let Some(backend) = &self.backend {
+                        let _ = backend.send(BackendCommand::Clear);
+                    }
+                }
+                StartupAction::ShowPopover => self.handle_menu_action(MenuAction::Show, cx),
+            }
+        }
+    }
+
+    /// Pastes the snippet at `index` into the app in front, from a `[hotkey] snippets`
+    /// chord. With the popover open, that is the app it was opened over.
+    fn insert_snippet(&mut self, index: usize, cx: &mut App) {
+        let Some(snippet) = SNIPPETS.lock().unwrap().get(index).cloned() else {
+            return;
+        };
+        let Some(backend) = &self.backend else {
+            return;
+        };
+        let response = match backend.send(BackendCommand::SetClipboard {
+            content: snippet.content,
+            entry_type: EntryType::Text,
+        }) {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Failed to insert snippet: {}", e);
+                return;
+            }
+        };
+        if self.popover_handle.is_some() {
+            self.close_popover(cx);
+        } else {
+            self.previous_app = auto_paste::frontmost_app();
+        }
+        self.pending_paste = Some(response);
+    }
+
+    /// Selects the next entry on the paste stack and pastes it into the frontmost app.
+    /// Entries removed from the history since they were queued are skipped; one that
+    /// isn't loaded waits for the rest of the history to tell.
+    fn paste_next_from_stack(&mut self, cx: &mut App) {
+        self.paste_stack_waiting = false;
+        let next = {
+            let mut stack = paste_stack::STACK.lock().unwrap();
+            loop {
+                let Some(id) = stack.pop() else {
+                    break None;
+                };
+                if self.backend_entries.iter().any(|e| e.id == id) {
+                    break Some(id);
+                }
+                if self.load.has_more() {
+                    stack.push(id);
+                    self.paste_stack_waiting = true;
+                    loading::load_all();
+                    return;
+                }
+            }
+        };
+        let (Some(id), Some(backend)) = (next, &self.backend) else {
+            return;
+        };
+        let response = match backend.send(BackendCommand::SelectEntry { id }) {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Failed to paste from the stack: {}", e);
+                return;
+            }
+        };
+        if self.popover_handle.is_some() {
+            self.close_popover(cx);
+        } else {
+            self.previous_app = auto_paste::frontmost_app();
+        }
+        self.pending_paste = Some(response);
+    }
+
+    /// Takes over the copy a popover made with auto-paste on, replacing any earlier
+    /// one, whose paste would now put the wrong thing in.
+    fn take_paste_request(&mut self) {
+        if let Some(response) = PASTE_REQUEST.lock().unwrap().take() {
+            self.pending_paste = Some(response);
+        }
+    }
+
+    /// Pastes into the app the popover was opened over once the copy it waits on is
+    /// answered. A failed copy pastes nothing; it is reported with the other errors.
+    fn check_pending_paste(&mut self) {
+        self.take_paste_request();
+        let Some(result) = self
+            .pending_paste
+            .as_ref()
+            .and_then(PendingResponse::try_result)
+        else {
+            return;
+        };
+        self.pending_paste = None;
+        if result.is_ok() {
+            if let Some(app) = self.previous_app.take() {
+                auto_paste::paste_into(app);
+            }
+        }
+    }
+
+    /// Re-applies the popover's Spaces setting after a Space switch; in the default
+    /// mode this brings an open popover along to the new Space.
+    fn on_space_changed(&mut self, cx: &mut App) {
+        let behavior = self.config.window.spaces;
+        if let Some(handle) = self.popover_handle {
+            let _ = handle.update(cx, |_, window, _| spaces::apply(window, behavior, true));
+        }
+    }
+
+    /// Registers the paste stack chord once something is queued and releases it when the
+    /// stack runs out, so the chord isn't taken from other apps the rest of the time.
+    fn sync_paste_stack_hotkey(&mut self) {
+        let Some(hotkey) = self.paste_stack_hotkey else {
+            return;
+        };
+        let wanted = !paste_stack::STACK.lock().unwrap().is_empty();
+        if wanted == self.paste_stack_registered {
+            return;
+        }
+        let result = if wanted {
+            self.hotkey_manager.register(hotkey)
+        } else {
+            self.hotkey_manager.unregister(hotkey)
+        };
+        match result {
+            Ok(()) => self.paste_stack_registered = wanted,
+            Err(e) => {
+                // Give up rather than retry on every tick
+                eprintln!("Failed to update the paste stack hotkey: {}", e);
+                self.paste_stack_hotkey = None;
+            }
+        }
+    }
+
+    /// Swaps the registered toggle hotkey and persists it to the config file.
+    fn set_toggle_hotkey(&mut self, chord: String) {
+        let new_hotkey = match chord.parse::<HotKey>() {
+            Ok(hotkey) => hotkey,
+            Err(e) => {
+                eprintln!("Invalid hotkey {:?}: {}", chord, e);
+                return;
+            }
+        };
+        if new_hotkey == self.hotkey {
+            return;
+        }
+
+        // Register the new chord first so a failure leaves the old one working
+        if let Err(e) = self.hotkey_manager.register(new_hotkey) {
+            eprintln!("Failed to register hotkey {:?}: {}", chord, e);
+            return;
+        }
+        if let Err(e) = self.hotkey_manager.unregister(self.hotkey) {
+            eprintln!("Failed to unregister previous hotkey: {}", e);
+        }
+        self.hotkey = new_hotkey;
+
+        self.config.hotkey.toggle = chord;
+        if let Err(e) = self.config.save() {
+            eprintln!("Failed to save config: {:#}", e);
+        }
+    }
+
+    /// Picks up edits to theme.toml and restyles an open popover in place.
+    fn reload_theme_overrides(&mut self, cx: &mut App) -> bool {
+        let Some(overrides) = self.theme_watcher.poll() else {
+            return false;
+        };
+        self.theme_overrides = overrides.clone();
+        for handle in self.popover_handle.iter().chain(&self.windows) {
+            let _ = handle.update(cx, |popover, _, _| {
+                popover.theme = overrides.apply(popover.theme.name.theme());
+                popover.theme_overrides = overrides.clone();
+            });
+        }
+        self.restyle_dock_strip(cx);
+        true
+    }
+
+    /// Keeps at most `limit` entries from now on (0 for all of them), trimming the list
+    /// right away rather than waiting for the backend's refresh.
+    fn set_history_limit(&mut self, limit: usize) {
+        self.config.history.max_entries = limit;
+        status_menu::set_history_limit(limit);
+        if let Err(e) = self.config.save() {
+            eprintln!("Failed to save config: {:#}", e);
+        }
+        if let Some(limit) = self.config.history.limit() {
+            trim_to_limit(&mut self.shared_entries.lock().unwrap(), limit);
+        }
+        if let Some(backend) = &self.backend {
+            let _ = backend.send(BackendCommand::SetLimit {
+                limit: self.config.history.limit(),
+            });
+        }
+    }
+
+    fn set_theme(&mut self, name: ThemeName, cx: &mut App) {
+        apply_appearance(&name.theme());
+        self.config.appearance.theme = name;
+        if let Err(e) = self.config.save() {
+            eprintln!("Failed to save config: {:#}", e);
+        }
+        self.restyle_dock_strip(cx);
+    }
 
-        if let Some(tx) = backend_tx {
-            let handle = cx
-                .open_window(
-                    WindowOptions {
-                        window_bounds: Some(WindowBounds::Windowed(bounds)),
-                        titlebar: None,
-                        focus: true,
-                        show: true,
-                        kind: WindowKind::PopUp,
-                        is_movable: false,
-                        is_resizable: false,
-                        is_minimizable: false,
-                        window_background: WindowBackgroundAppearance::Blurred,
-                        ..Default::default()
-                    },
-                    |window, cx| {
-                        cx.new(|cx| {
-                            MenuBarPopover::new(shared, tx, supports_id_commands, window, cx)
-                        })
-                    },
-                )
-                .ok();
+    fn dock_strip_theme(&self) -> Theme {
+        self.theme_overrides
+            .apply(self.config.appearance.theme.theme())
+    }
 
-            self.popover_handle = handle;
+    /// Repaints the dock strip after the theme or theme.toml changed.
+    fn restyle_dock_strip(&self, cx: &mut App) {
+        let theme = self.dock_strip_theme();
+        if let Some(handle) = self.dock_handle {
+            let _ = handle.update(cx, |strip, _, cx| {
+                strip.theme = theme;
+                cx.notify();
+            });
         }
     }
 
-    fn poll_backend(&mut self) -> bool {
+    fn poll_backend(&mut self, cx: &mut App) -> bool {
         let mut entries_changed = false;
+        let mut ready = false;
+        let mut list_changed = false;
+        // Set when a change doesn't fit the list, which then no longer matches the backend's
+        let mut refresh = false;
+        if let (Some(backend), Some(lan_sync)) = (&self.backend, &self.lan_sync) {
+            // Peers' copies go in by when they were made, below the current entry, and
+            // never evict anything; the clipboard stays as it is
+            let entries: Vec<ImportEntry> = lan_sync
+                .take_incoming()
+                .into_iter()
+                .map(ImportEntry::from)
+                .collect();
+            if !entries.is_empty() {
+                let _ = backend.send(BackendCommand::ImportEntries { entries });
+            }
+        }
         if let Some(backend) = &self.backend {
             while let Ok(msg) = backend.rx.try_recv() {
                 match msg {
-                    BackendMessage::Entries { data } => {
-                        if let Ok(mut shared) = self.shared_entries.lock() {
-                            *shared = data;
+                    BackendMessage::Entries {
+                        data,
+                        total,
+                        offset,
+                    } => {
+                        self.load.fetching = false;
+                        if loading::receive(&mut self.backend_entries, data, offset) {
+                            self.load.total = total.unwrap_or(self.backend_entries.len());
+                            list_changed = true;
                         }
-                        entries_changed = true;
                     }
-                    BackendMessage::SelectSuccess
-                    | BackendMessage::RemoveSuccess
-                    | BackendMessage::PinToggled
-                    | BackendMessage::Success => {
-                        if let Err(e) = backend.send("get-entries") {
-                            eprintln!("Failed to refresh entries: {}", e);
+                    BackendMessage::EntryAdded { after, data, total }
+                    | BackendMessage::EntryUpdated { after, data, total } => {
+                        if !loading::place(&mut self.backend_entries, after, data) {
+                            refresh = true;
                         }
+                        self.load.total = total.unwrap_or(self.load.total);
+                        list_changed = true;
                     }
-                    BackendMessage::Ready {
-                        supports_id_commands,
-                    } => {
-                        self.supports_id_commands
-                            .store(supports_id_commands, Ordering::Release);
-                        if let Err(e) = backend.send("get-entries") {
-                            eprintln!("Failed to refresh entries: {}", e);
+                    BackendMessage::EntryRemoved { id, total } => {
+                        self.backend_entries.retain(|e| e.id != id);
+                        self.load.total = total.unwrap_or(self.load.total);
+                        list_changed = true;
+                    }
+                    BackendMessage::SelectSuccess { id } => {
+                        if let Some(id) = id {
+                            self.usage.record(id, unix_now());
+                            access_log::record(id, AccessKind::Copied);
+                            if let Some(entry) = self
+                                .backend_entries
+                                .iter()
+                                .find(|e| e.id == id && e.entry_type == EntryType::File)
+                            {
+                                let content = entry.content.clone();
+                                std::thread::spawn(move || {
+                                    let missing = missing_files(&content);
+                                    if !missing.is_empty() {
+                                        let names: Vec<String> =
+                                            missing.iter().map(|p| filename_from_path(p)).collect();
+                                        notice::show(format!(
+                                            "Left out {} missing: {}",
+                                            if missing.len() == 1 {
+                                                "a file that is"
+                                            } else {
+                                                "files that are"
+                                            },
+                                            names.join(", ")
+                                        ));
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    BackendMessage::Ready => {
+                        ready = true;
+                        self.health.ready();
+                        self.handshake = backend
+                            .send(BackendCommand::Hello {
+                                protocol_version: PROTOCOL_VERSION,
+                            })
+                            .map_err(|e| eprintln!("Failed to send hello: {}", e))
+                            .ok();
+                        if let Err(e) = backend.send(BackendCommand::GetSnippets) {
+                            eprintln!("Failed to load snippets: {}", e);
                         }
+                        // A restarted backend hasn't sent anything the changes could apply to
+                        refresh = true;
+                    }
+                    BackendMessage::Snippets { data } => {
+                        *SNIPPETS.lock().unwrap() = data;
+                        entries_changed = true;
                     }
-                    BackendMessage::Unknown => {}
+                    BackendMessage::Trash { data } => {
+                        *trash::TRASH.lock().unwrap() = data;
+                        entries_changed = true;
+                    }
+                    // Auto-paste watches the response to its own copy instead
+                    BackendMessage::RemoveSuccess
+                    | BackendMessage::PinToggled
+                    | BackendMessage::Success => {}
+                    BackendMessage::Error(err) => {
+                        eprintln!("Backend command failed: {}", err);
+                        self.health.command_failed(err.to_string(), Instant::now());
+                    }
+                    // Only ever asked for by the timeline export, the windows' searches and
+                    // Import…, which wait for them themselves
+                    BackendMessage::Timeline { .. }
+                    | BackendMessage::SearchResults { .. }
+                    | BackendMessage::Imported { .. }
+                    | BackendMessage::Unknown => {}
+                    // Checked through `handshake` instead
+                    BackendMessage::Hello { .. } => {}
                 }
             }
         }
+        // As much as was loaded, so the list doesn't shrink back to one page
+        if refresh {
+            self.load_entries(0, self.backend_entries.len().max(PAGE_SIZE));
+        }
+        if list_changed {
+            self.load.loaded = self.backend_entries.len();
+            self.show_entries();
+            self.refresh_searches(cx);
+            entries_changed = true;
+        }
+        *LOAD_PROGRESS.lock().unwrap() = self.load;
+        if ready {
+            self.restore_backend_state();
+        }
+        if ready && !self.startup_done {
+            self.run_startup_actions(cx);
+        }
         entries_changed
     }
+
+    /// Asks for `limit` entries from `offset` on.
+    fn load_entries(&mut self, offset: usize, limit: usize) {
+        let Some(backend) = &self.backend else {
+            return;
+        };
+        match backend.send(BackendCommand::GetEntriesPage { offset, limit }) {
+            Ok(_) => self.load.fetching = true,
+            Err(e) => eprintln!("Failed to load entries: {}", e),
+        }
+    }
+
+    /// Asks for the next page of older entries, or for all of them, when something
+    /// wants it and there are any left.
+    fn load_more_entries(&mut self) {
+        let more = LOAD_MORE.swap(false, Ordering::SeqCst);
+        if self.load.fetching {
+            return;
+        }
+        let loaded = self.backend_entries.len();
+        if !self.load.has_more() {
+            LOAD_ALL.store(false, Ordering::SeqCst);
+            return;
+        }
+        if LOAD_ALL.swap(false, Ordering::SeqCst) {
+            self.load_entries(loaded, self.load.total - loaded);
+        } else if more {
+            self.load_entries(loaded, PAGE_SIZE);
+        } else {
+            return;
+        }
+        *LOAD_PROGRESS.lock().unwrap() = self.load;
+    }
+
+    /// Shows `backend_entries` in the configured order, after one batch of messages
+    /// rather than each of them, and hands them to everything that follows the history.
+    fn show_entries(&mut self) {
+        let mut data = self.backend_entries.clone();
+        // Only copies since the last offer go out, and those are always in the newest page
+        if let Some(lan_sync) = &mut self.lan_sync {
+            lan_sync.offer(&data);
+        }
+        status_menu::set_recent(&data, self.config.privacy.mask_sensitive);
+        // Entries not loaded yet may still be in the history
+        if !self.load.has_more() {
+            self.usage.prune(&data);
+            self.thumbnails.prune_blurhashes(&data);
+        }
+        self.thumbnails.queue_blurhashes(&data);
+        // The week's copies may be anywhere in the history, pinned ones further down
+        *digest::DIGEST.lock().unwrap() = (!self.load.has_more())
+            .then(|| digest::Digest::compute(&data, &self.usage, unix_now()));
+        if self.config.history.order == EntryOrder::Frecency {
+            // An entry used often can be anywhere in the history, so rank all of it
+            if self.load.has_more() {
+                loading::load_all();
+            }
+            self.usage.sort(&mut data, unix_now());
+        }
+        if let Some(recognizer) = &self.recognizer {
+            recognizer.queue(&data);
+        }
+        if let Some(title_fetcher) = &self.title_fetcher {
+            title_fetcher.queue(&data);
+        }
+        if let Ok(mut shared) = self.shared_entries.lock() {
+            *shared = data;
+        }
+    }
+
+    /// Notices the backend going away, starts it again when due and publishes its
+    /// status for the popover. Returns whether the status changed.
+    fn check_backend(&mut self) -> bool {
+        let now = Instant::now();
+        if let Some(result) = self.handshake.as_ref().and_then(|h| h.try_result()) {
+            self.handshake = None;
+            match result {
+                Ok(BackendMessage::Hello { protocol_version }) => {
+                    self.health.handshake(Some(protocol_version))
+                }
+                // A backend from before the handshake doesn't know `hello`
+                Err(e)
+                    if e.downcast_ref::<CommandError>()
+                        .is_some_and(|e| e.code == "unknown-command") =>
+                {
+                    self.health.handshake(None)
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Handshake failed: {:#}", e),
+            }
+        }
+        if let Some(backend) = self.backend.as_mut() {
+            if !self.health.is_dead() {
+                if let Some(reason) = backend.lost_reason() {
+                    eprintln!("Backend {}", reason);
+                    self.health.lost(reason, now);
+                }
+            }
+            if self.health.restart_due(now) {
+                match backend.restart(&self.config) {
+                    // A fresh backend captures images until told otherwise
+                    Ok(()) => self.image_capture = true,
+                    Err(e) => {
+                        eprintln!("Failed to restart backend: {:#}", e);
+                        self.health.lost(format!("{:#}", e), now);
+                    }
+                }
+            }
+        }
+        self.health.publish(now)
+    }
+
+    /// Tells a newly ready backend what was switched on at runtime, which a restarted
+    /// one has forgotten.
+    fn restore_backend_state(&mut self) {
+        if let Some(backend) = &self.backend {
+            if self.paused {
+                let _ = backend.send(BackendCommand::SetPaused { paused: true });
+            }
+            if self.appending {
+                let _ = backend.send(BackendCommand::SetAppendMode { enabled: true });
+            }
+        }
+        self.sync_image_capture();
+    }
+
+    /// Has each window that is searching look its matches up again, after the history
+    /// changed.
+    fn refresh_searches(&self, cx: &mut App) {
+        for handle in self.popover_handle.iter().chain(&self.windows) {
+            let _ = handle.update(cx, |popover, _, _| {
+                if popover.found.is_some() || popover.pending_search.is_some() {
+                    popover.search_backend();
+                }
+            });
+        }
+    }
+
+    /// Lets each window settle its in-flight commands, repainting those that changed.
+    fn check_in_flight(&mut self, cx: &mut App) {
+        for handle in self.popover_handle.iter().chain(&self.windows) {
+            let _ = handle.update(cx, |popover, _, cx| {
+                if popover.check_in_flight() {
+                    cx.notify();
+                }
+            });
+        }
+    }
+
+    /// Removes unpinned sensitive entries copied more than `expire_sensitive_secs` ago.
+    /// The entry still on the clipboard is kept until something else is copied.
+    fn expire_sensitive_entries(&mut self) {
+        let ttl = self.config.privacy.expire_sensitive_secs;
+        if ttl == 0 || self.last_expiry_check.elapsed() < SENSITIVE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_expiry_check = Instant::now();
+        let Some(backend) = &self.backend else {
+            return;
+        };
+        // Old secrets sit furthest down, past the pages loaded so far; the next check
+        // after the rest arrives reaches them
+        if self.load.has_more() {
+            loading::load_all();
+        }
+        let expired: Vec<u64> = expired(&self.shared_entries.lock().unwrap(), ttl, unix_now())
+            .filter(|e| sensitive::detect(e).is_some())
+            .map(|e| e.id)
+            .collect();
+        // Purged rather than archived, so secrets don't linger in the trash
+        for id in expired {
+            if let Err(e) = backend.send(BackendCommand::PurgeEntry { id }) {
+                eprintln!("Failed to expire entry {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Shows the weekly digest as a notification once a week, when
+    /// `[behavior] weekly_digest` is on.
+    fn send_weekly_digest(&mut self) {
+        if !self.config.behavior.weekly_digest
+            || self
+                .last_digest_check
+                .is_some_and(|at| at.elapsed() < DIGEST_CHECK_INTERVAL)
+        {
+            return;
+        }
+        let now = unix_now();
+        if !digest::notification_due(now) {
+            self.last_digest_check = Some(Instant::now());
+            return;
+        }
+        // Checked again once the rest of the history is in
+        if self.load.has_more() {
+            loading::load_all();
+            return;
+        }
+        self.last_digest_check = Some(Instant::now());
+        let digest = digest::Digest::compute(&self.backend_entries, &self.usage, now);
+        if let Err(e) = digest::notify(&digest) {
+            eprintln!("Failed to show the weekly digest: {:#}", e);
+        }
+        if let Err(e) = digest::mark_sent(now) {
+            eprintln!("Failed to save the digest time: {:#}", e);
+        }
+    }
+
+    /// Stops the backend saving copied images while disk space is low, and starts it
+    /// again once there is room.
+    fn sync_image_capture(&mut self) {
+        let enabled = disk_space::LOW_SPACE.lock().unwrap().is_none();
+        if enabled == self.image_capture {
+            return;
+        }
+        let Some(backend) = &self.backend else {
+            return;
+        };
+        match backend.send(BackendCommand::SetImageCapture { enabled }) {
+            Ok(_) => self.image_capture = enabled,
+            Err(e) => eprintln!("Failed to set image capture: {}", e),
+        }
+    }
+
+    /// Removes unpinned entries copied more than `[history] retention_days` ago, and
+    /// entries whose expiry set from the selection bar has come, once a minute. They
+    /// leave the list at once rather than when the backend answers; returns whether
+    /// any did.
+    fn expire_old_entries(&mut self) -> bool {
+        let days = self.config.history.retention_days;
+        if self
+            .last_retention_check
+            .is_some_and(|at| at.elapsed() < RETENTION_CHECK_INTERVAL)
+        {
+            return false;
+        }
+        self.last_retention_check = Some(Instant::now());
+        let Some(backend) = &self.backend else {
+            return false;
+        };
+        // Old entries, and expiry dates set long ago, are past the pages loaded so far;
+        // the next check after the rest arrives reaches them
+        if self.load.has_more() {
+            loading::load_all();
+        }
+        let stale: Vec<u64> = {
+            let mut entries = self.shared_entries.lock().unwrap();
+            let stale = stale_entries(&entries, days, unix_now());
+            entries.retain(|e| !stale.contains(&e.id));
+            stale
+        };
+        // Past their time already, so they skip the trash
+        for &id in &stale {
+            if let Err(e) = backend.send(BackendCommand::PurgeEntry { id }) {
+                eprintln!("Failed to expire entry {}: {}", id, e);
+            }
+        }
+        !stale.is_empty()
+    }
+
+    /// How long the event loop may sleep before a timed check falls due, or None to
+    /// sleep until something wakes it.
+    fn next_wake(&self, cx: &App) -> Option<Duration> {
+        let now = Instant::now();
+        let mut deadlines: Vec<Instant> = Vec::new();
+        deadlines.extend(self.health.next_change());
+        deadlines.extend(self.next_sensitive_expiry());
+        // Per-entry expiry dates are checked along with retention, so this one always runs
+        deadlines.extend(
+            self.last_retention_check
+                .map(|at| at + RETENTION_CHECK_INTERVAL),
+        );
+        if self.config.behavior.weekly_digest {
+            deadlines.extend(self.last_digest_check.map(|at| at + DIGEST_CHECK_INTERVAL));
+        }
+        for handle in self.popover_handle.iter().chain(&self.windows) {
+            deadlines.push(now + THEME_CHECK_INTERVAL);
+            if let Ok(Some(at)) = handle.read_with(cx, |popover, _| popover.next_deadline()) {
+                deadlines.push(at);
+            }
+        }
+        deadlines
+            .into_iter()
+            .min()
+            .map(|at| at.saturating_duration_since(now))
+    }
+
+    /// When the oldest sensitive entry outlives `expire_sensitive_secs`, though no
+    /// sooner than `expire_sensitive_entries` looks again.
+    fn next_sensitive_expiry(&self) -> Option<Instant> {
+        let ttl = self.config.privacy.expire_sensitive_secs;
+        if ttl == 0 {
+            return None;
+        }
+        let oldest_ms = self
+            .shared_entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| !e.pinned && !e.is_current && sensitive::detect(e).is_some())
+            .map(|e| e.timestamp)
+            .min()?;
+        let due_secs =
+            ((oldest_ms + 999) / 1000).saturating_add(i64::try_from(ttl).unwrap_or(i64::MAX));
+        let wait = Duration::from_secs(due_secs.saturating_sub(unix_now()).max(0) as u64);
+        // None when so far off that it can't be told apart from never
+        let due = Instant::now().checked_add(wait)?;
+        Some(due.max(self.last_expiry_check + SENSITIVE_CHECK_INTERVAL))
+    }
 }
 
-fn start_poll_loop(app_state: Entity<AppState>, cx: &mut App) {
+/// Runs the app's event loop. It sleeps until something calls `wake::wake` (a backend
+/// message, a hotkey, a menu choice, a worker thread) or a timed check falls due, so an
+/// idle app doesn't wake at all.
+fn start_event_loop(app_state: Entity<AppState>, mut wake_rx: UnboundedReceiver<()>, cx: &mut App) {
     let bg_executor = cx.background_executor().clone();
     let async_cx = cx.to_async();
     cx.foreground_executor()
         .spawn(async move {
+            // The first pass picks up whatever happened during launch
+            let mut sleep = Some(Duration::ZERO);
             loop {
-                bg_executor.timer(Duration::from_millis(100)).await;
+                match sleep {
+                    Some(timeout) => {
+                        let _ = wake_rx.next().with_timeout(timeout, &bg_executor).await;
+                    }
+                    None => {
+                        wake_rx.next().await;
+                    }
+                }
+                // One pass handles every wake-up that came in meanwhile
+                while wake_rx.try_recv().is_ok() {}
                 let result = async_cx.update(|cx| {
                     app_state.update(cx, |state, cx| {
                         let mut needs_notify = false;
 
                         // Handle hotkey
-                        while state.hotkey_rx.try_recv().is_ok() {
-                            state.toggle_popover(cx);
+                        while let Ok(id) = state.hotkey_rx.try_recv() {
+                            if id == state.hotkey.id() {
+                                state.toggle_popover(cx);
+                            } else if let Some(index) =
+                                state.snippet_hotkeys.iter().position(|h| h.id() == id)
+                            {
+                                state.insert_snippet(index, cx);
+                            } else if state.paste_stack_hotkey.is_some_and(|h| h.id() == id) {
+                                state.paste_next_from_stack(cx);
+                            }
+                            needs_notify = true;
+                        }
+
+                        if let Some(chord) = PENDING_HOTKEY.lock().unwrap().take() {
+                            state.set_toggle_hotkey(chord);
+                        }
+
+                        if let Some(name) = PENDING_THEME.lock().unwrap().take() {
+                            state.set_theme(name, cx);
+                        }
+
+                        let urls = std::mem::take(&mut *PENDING_URLS.lock().unwrap());
+                        for url in urls {
+                            match DeepLink::parse(&url) {
+                                Some(link) => state.open_deep_link(link, cx),
+                                None => eprintln!("Ignoring unsupported URL: {}", url),
+                            }
+                        }
+
+                        let menu_actions =
+                            std::mem::take(&mut *PENDING_MENU_ACTIONS.lock().unwrap());
+                        for action in menu_actions {
+                            state.handle_menu_action(action, cx);
+                            needs_notify = true;
+                        }
+
+                        if state.poll_backend(cx) {
+                            needs_notify = true;
+                        }
+                        state.check_pending_paste();
+                        state.load_more_entries();
+                        if state.paste_stack_waiting
+                            && !state.load.fetching
+                            && !LOAD_ALL.load(Ordering::SeqCst)
+                        {
+                            state.paste_next_from_stack(cx);
+                        }
+                        if state.check_backend() {
+                            needs_notify = true;
+                        }
+                        state.sync_paste_stack_hotkey();
+                        state.check_in_flight(cx);
+                        state.expire_sensitive_entries();
+                        if state.expire_old_entries() {
+                            needs_notify = true;
+                        }
+                        state.send_weekly_digest();
+                        if disk_space::SPACE_UPDATED.swap(false, Ordering::SeqCst) {
+                            state.sync_image_capture();
+                            needs_notify = true;
+                        }
+
+                        if THUMBNAILS_UPDATED.swap(false, Ordering::SeqCst)
+                            | ocr::OCR_UPDATED.swap(false, Ordering::SeqCst)
+                            | link_titles::TITLES_UPDATED.swap(false, Ordering::SeqCst)
+                            | highlight::HIGHLIGHTS_UPDATED.swap(false, Ordering::SeqCst)
+                            | notice::NOTICE_UPDATED.swap(false, Ordering::SeqCst)
+                        {
                             needs_notify = true;
                         }
 
-                        if state.poll_backend() {
+                        // Pointer on the dock strip slides the popover out, and leaving it
+                        // hides it again
+                        if DOCK_HOVERED.swap(false, Ordering::SeqCst)
+                            && state.popover_handle.is_none()
+                        {
+                            state.toggle_popover(cx);
                             needs_notify = true;
                         }
+                        if DOCK_LEFT.swap(false, Ordering::SeqCst) && state.popover_handle.is_some()
+                        {
+                            state.hide_popover(cx);
+                        }
 
                         // Menu bar click toggle
                         if MENU_BAR_CLICKED.swap(false, Ordering::SeqCst) {
@@ -983,13 +7449,22 @@ fn start_poll_loop(app_state: Entity<AppState>, cx: &mut App) {
                             needs_notify = true;
                         }
 
+                        if NEW_WINDOW_REQUESTED.swap(false, Ordering::SeqCst) {
+                            state.open_window(cx);
+                        }
+
+                        if spaces::SPACE_CHANGED.swap(false, Ordering::SeqCst) {
+                            state.on_space_changed(cx);
+                        }
+
                         // Close popover if it lost focus
                         if POPOVER_SHOULD_CLOSE.swap(false, Ordering::SeqCst) {
-                            if let Some(handle) = state.popover_handle.take() {
-                                let _ = handle.update(cx, |_, window, _| {
-                                    window.remove_window();
-                                });
-                            }
+                            state.close_popover(cx);
+                        }
+
+                        // After the toggles, so a popover opened just now gets the edits too
+                        if state.reload_theme_overrides(cx) {
+                            needs_notify = true;
                         }
 
                         if needs_notify {
@@ -998,11 +7473,17 @@ fn start_poll_loop(app_state: Entity<AppState>, cx: &mut App) {
                                     cx.notify();
                                 });
                             }
+                            // Closed windows fail to update and are dropped here
+                            state
+                                .windows
+                                .retain(|handle| handle.update(cx, |_, _, cx| cx.notify()).is_ok());
                         }
-                    });
+                        state.next_wake(cx)
+                    })
                 });
-                if result.is_err() {
-                    break;
+                match result {
+                    Ok(next) => sleep = next,
+                    Err(_) => break,
                 }
             }
         })
@@ -1015,10 +7496,19 @@ fn set_activation_policy_accessory() {
         let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
         // NSApplicationActivationPolicyAccessory = 1
         let _: () = msg_send![ns_app, setActivationPolicy: 1i64];
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn set_activation_policy_accessory() {}
 
-        // Force dark vibrant appearance so the blur material is always dark,
-        // regardless of wallpaper or system theme.
-        let name = NSString::alloc(nil).init_str("NSAppearanceNameVibrantDark");
+/// Forces the vibrant appearance matching `theme` so the blur material agrees with the
+/// palette, regardless of wallpaper or system theme.
+#[cfg(target_os = "macos")]
+fn apply_appearance(theme: &Theme) {
+    unsafe {
+        let ns_app: id = msg_send![class!(NSApplication), sharedApplication];
+        let name = NSString::alloc(nil).init_str(theme.appearance_name());
         let appearance: id = msg_send![class!(NSAppearance), appearanceNamed: name];
         if !appearance.is_null() {
             let _: () = msg_send![ns_app, setAppearance: appearance];
@@ -1027,86 +7517,314 @@ fn set_activation_policy_accessory() {
 }
 
 #[cfg(not(target_os = "macos"))]
-fn set_activation_policy_accessory() {}
+fn apply_appearance(_theme: &Theme) {}
+
+/// Entries the history takes before reaching `[history] max_entries`; None when it is
+/// unlimited.
+fn history_room() -> Option<usize> {
+    let total = LOAD_PROGRESS.lock().unwrap().total;
+    status_menu::history_limit().map(|limit| limit.saturating_sub(total))
+}
+
+/// Registers the `[hotkey] toggle` chord, or the default one when another app holds
+/// it, and says so in the popover. Should neither work, Clipz still opens from the
+/// menu bar.
+fn register_toggle_hotkey(manager: &GlobalHotKeyManager, config: &HotkeyConfig) -> HotKey {
+    let hotkey = config.toggle_hotkey();
+    let Err(e) = manager.register(hotkey) else {
+        return hotkey;
+    };
+    eprintln!("Failed to register hotkey {:?}: {}", config.toggle, e);
+    let default = HotkeyConfig::default();
+    let fallback = default.toggle_hotkey();
+    if fallback != hotkey && manager.register(fallback).is_ok() {
+        notice::show(format!(
+            "Hotkey {} is taken by another app, so Clipz opens with {} instead",
+            config.toggle, default.toggle
+        ));
+        return fallback;
+    }
+    notice::show(format!(
+        "Hotkey {} is taken by another app; open Clipz from the menu bar or pick another",
+        config.toggle
+    ));
+    hotkey
+}
 
 fn main() {
-    Application::new()
-        .with_assets(FileSystemAssets)
-        .run(|cx: &mut App| {
-            set_activation_policy_accessory();
-            setup_menu_bar_icon();
-
-            let hotkey_manager =
-                GlobalHotKeyManager::new().expect("failed to create hotkey manager");
-            let hotkey = HotKey::new(Some(Modifiers::SUPER | Modifiers::ALT), Code::Equal);
-            hotkey_manager
-                .register(hotkey)
-                .expect("failed to register hotkey");
-
-            let (hotkey_tx, hotkey_rx) = mpsc::channel::<()>();
-            thread::spawn(move || {
-                let receiver = GlobalHotKeyEvent::receiver();
-                loop {
-                    if let Ok(event) = receiver.recv() {
-                        if event.state == HotKeyState::Pressed {
-                            let _ = hotkey_tx.send(());
-                        }
-                    }
-                }
-            });
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match dump::DumpFormat::from_args(&args) {
+        Ok(Some(format)) => dump::run(format),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("clipz: {:#}", e);
+            std::process::exit(2);
+        }
+    }
+    match session::ReplayArgs::from_args(&args) {
+        Ok(Some(replay)) => session::run(replay),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("clipz: {:#}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Err(e) = session::start_recording_from_args(&args) {
+        eprintln!("clipz: {:#}", e);
+        std::process::exit(2);
+    }
+
+    let app = Application::new().with_assets(FileSystemAssets);
+    app.on_open_urls(|urls| {
+        PENDING_URLS.lock().unwrap().extend(urls);
+        wake::wake();
+    });
+    app.run(|cx: &mut App| {
+        let wake_rx = wake::channel();
+        set_activation_policy_accessory();
+        setup_menu_bar_icon();
+        spaces::watch();
+
+        let config = Config::load();
+        keymap::configure(&config.keys, config.behavior.vim_mode);
+        apply_appearance(&config.appearance.theme.theme());
+        status_menu::set_history_limit(config.history.max_entries);
+        status_menu::set_retention(config.history.retention_days);
+        status_menu::set_launch_at_login(config.behavior.launch_at_login);
+        // Registered again on each launch, so the login item follows the app if it moves
+        if config.behavior.launch_at_login {
+            if let Err(e) = login_item::set_enabled(true) {
+                eprintln!("Failed to register the login item: {:#}", e);
+            }
+        }
 
-            let shared_entries: SharedEntries = Arc::new(Mutex::new(Vec::new()));
-            let supports_id_commands = Arc::new(AtomicBool::new(false));
-            let backend = BackendHandle::start().ok();
+        let hotkey_manager = GlobalHotKeyManager::new().expect("failed to create hotkey manager");
+        let hotkey = register_toggle_hotkey(&hotkey_manager, &config.hotkey);
+        let paste_stack_hotkey = config.hotkey.paste_stack_hotkey();
+        let snippet_hotkeys = config.hotkey.snippet_hotkeys();
+        for snippet_hotkey in &snippet_hotkeys {
+            if let Err(e) = hotkey_manager.register(*snippet_hotkey) {
+                eprintln!("Failed to register snippet hotkey: {}", e);
+            }
+        }
 
-            if let Some(ref b) = backend {
-                if let Err(e) = b.send("get-entries") {
-                    eprintln!("Failed to refresh entries: {}", e);
+        let (hotkey_tx, hotkey_rx) = mpsc::channel::<u32>();
+        thread::spawn(move || {
+            let receiver = GlobalHotKeyEvent::receiver();
+            loop {
+                if let Ok(event) = receiver.recv() {
+                    if event.state == HotKeyState::Pressed {
+                        let _ = hotkey_tx.send(event.id);
+                        wake::wake();
+                    }
                 }
             }
+        });
 
-            let app_state = cx.new(|_| AppState {
-                backend,
-                shared_entries,
-                supports_id_commands,
-                _hotkey_manager: hotkey_manager,
-                hotkey_rx,
-                popover_handle: None,
-            });
+        let shared_entries: SharedEntries = Arc::new(Mutex::new(Vec::new()));
+        let mut health = Health::new(Instant::now());
+        let backend = BackendHandle::start(&config)
+            .map_err(|e| {
+                eprintln!("Failed to start backend: {:#}", e);
+                health.failed_to_start(format!("{:#}", e));
+            })
+            .ok();
 
-            start_poll_loop(app_state, cx);
+        // The first page is asked for once the backend is ready; until then the list
+        // shows placeholder rows
+        let load = LoadProgress {
+            fetching: backend.is_some(),
+            ..LoadProgress::default()
+        };
+        *LOAD_PROGRESS.lock().unwrap() = load;
+        let lan_sync = if config.sync.enabled {
+            LanSync::start(&config.sync, unix_now() * 1000)
+                .map_err(|e| eprintln!("Failed to start sync: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        let recognizer = config
+            .behavior
+            .ocr
+            .then(|| ocr::Recognizer::start(!config.privacy.encrypt_history));
+        if config.privacy.access_log {
+            access_log::enable();
+        }
+        let title_fetcher = config
+            .behavior
+            .fetch_titles
+            .then(|| link_titles::TitleFetcher::start(!config.privacy.encrypt_history));
+        disk_space::watch(config.history.low_disk_mb);
+        highlight::start();
+
+        let app_state = cx.new(|_| AppState {
+            config,
+            backend,
+            shared_entries,
+            backend_entries: Vec::new(),
+            load,
+            lan_sync,
+            thumbnails: Thumbnails::new(),
+            recognizer,
+            title_fetcher,
+            hotkey_manager,
+            hotkey,
+            snippet_hotkeys,
+            paste_stack_hotkey,
+            paste_stack_registered: false,
+            paste_stack_waiting: false,
+            hotkey_rx,
+            popover_handle: None,
+            windows: Vec::new(),
+            dock_handle: None,
+            previous_app: None,
+            pending_paste: None,
+            paused: false,
+            appending: false,
+            startup_done: false,
+            theme_overrides: ThemeOverrides::default(),
+            theme_watcher: ThemeWatcher::default(),
+            usage: Usage::load(),
+            last_expiry_check: Instant::now(),
+            last_retention_check: None,
+            image_capture: true,
+            last_digest_check: None,
+            health,
+            handshake: None,
         });
+
+        app_state.update(cx, |state, cx| state.open_dock_strip(cx));
+        start_event_loop(app_state, wake_rx, cx);
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn keystroke(modifiers: gpui::Modifiers, key: &str) -> gpui::Keystroke {
+        gpui::Keystroke {
+            modifiers,
+            key: key.into(),
+            key_char: None,
+        }
+    }
+
+    #[test]
+    fn hotkey_chord_requires_a_modifier_and_known_key() {
+        let cmd_alt = gpui::Modifiers {
+            platform: true,
+            alt: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            hotkey_chord(&keystroke(cmd_alt, "=")).as_deref(),
+            Some("alt+cmd+=")
+        );
+        assert_eq!(
+            hotkey_chord(&keystroke(gpui::Modifiers::default(), "v")),
+            None
+        );
+        assert_eq!(hotkey_chord(&keystroke(cmd_alt, "é")), None);
+    }
+
     #[test]
-    fn backend_message_accepts_id_and_legacy_index_fields() {
-        let from_id: BackendMessage =
-            serde_json::from_str(r#"{"type":"select-success","id":42}"#).unwrap();
-        assert!(matches!(from_id, BackendMessage::SelectSuccess));
+    fn trimming_keeps_current_and_pinned_entries() {
+        let entry = |id: u64, timestamp: i64, pinned: bool| Entry {
+            id,
+            content: id.to_string(),
+            timestamp,
+            is_current: id == 1,
+            pinned,
+            ..Default::default()
+        };
+        let mut entries = vec![
+            entry(1, 10, false),
+            entry(2, 40, false),
+            entry(3, 5, true),
+            entry(4, 20, false),
+            entry(5, 30, false),
+        ];
+        trim_to_limit(&mut entries, 3);
+        let ids: Vec<u64> = entries.iter().map(|e| e.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
 
-        let from_index: BackendMessage =
-            serde_json::from_str(r#"{"type":"remove-success","index":7}"#).unwrap();
-        assert!(matches!(from_index, BackendMessage::RemoveSuccess));
+        trim_to_limit(&mut entries, 1);
+        assert_eq!(entries.len(), 2);
     }
 
     #[test]
-    fn entries_payload_parses_escaped_control_characters() {
-        let msg: BackendMessage = serde_json::from_str(
-            r#"{"type":"entries","data":[{"id":1,"content":"hello\n\b\f","timestamp":1000,"type":"text","isCurrent":true,"pinned":false}]}"#,
-        )
-        .unwrap();
+    fn only_old_unpinned_entries_expire() {
+        let day_ms = 24 * 60 * 60 * 1000;
+        let now = 10 * day_ms / 1000;
+        let entry = |id: u64, age_days: i64, pinned: bool| Entry {
+            id,
+            content: id.to_string(),
+            timestamp: (10 - age_days) * day_ms,
+            is_current: id == 1,
+            pinned,
+            ..Default::default()
+        };
+        let entries = [
+            entry(1, 9, false),
+            entry(2, 8, false),
+            entry(3, 8, true),
+            entry(4, 6, false),
+        ];
+        let ids: Vec<u64> = expired(&entries, 7 * 24 * 60 * 60, now)
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, [2]);
+        assert_eq!(expired(&entries, u64::MAX, now).count(), 0);
+        assert!(stale_entries(&entries, u64::MAX, now).is_empty());
+    }
 
-        match msg {
-            BackendMessage::Entries { data } => {
-                assert_eq!(data.len(), 1);
-                assert_eq!(data[0].content, "hello\n\u{0008}\u{000C}");
-                assert!(data[0].is_current);
-            }
-            _ => panic!("expected entries payload"),
-        }
+    #[test]
+    fn sweeps_reach_entries_past_the_first_page() {
+        let day_ms = 24 * 60 * 60 * 1000;
+        let now = 400 * 24 * 60 * 60;
+        // Newest first, a day apart, the oldest copied 260 days ago
+        let history: Vec<Entry> = (1..=PAGE_SIZE as u64 + 60)
+            .rev()
+            .map(|id| Entry {
+                id,
+                content: id.to_string(),
+                timestamp: (140 + id as i64) * day_ms,
+                tags: if id == 2 {
+                    vec!["expires-1970-01-02".into()]
+                } else {
+                    Vec::new()
+                },
+                ..Default::default()
+            })
+            .collect();
+        let mut entries = Vec::new();
+        assert!(loading::receive(
+            &mut entries,
+            history[..PAGE_SIZE].to_vec(),
+            0
+        ));
+        assert!(stale_entries(&entries, 200, now).is_empty());
+
+        assert!(loading::receive(
+            &mut entries,
+            history[PAGE_SIZE..].to_vec(),
+            PAGE_SIZE
+        ));
+        let mut stale = stale_entries(&entries, 200, now);
+        stale.sort();
+        assert_eq!(stale, (1..=60).collect::<Vec<u64>>());
+        assert_eq!(stale_entries(&entries, 0, now), [2]);
+    }
+
+    #[test]
+    fn only_missing_paths_of_multi_file_entries_are_reported() {
+        let here = env!("CARGO_MANIFEST_DIR");
+        let gone = "/nonexistent/clipz/report.pdf";
+        assert!(missing_files(gone).is_empty());
+        assert_eq!(missing_files(&format!("{}\n{}\n", here, gone)), [gone]);
+        assert!(missing_files(&format!("{}\n{}", here, here)).is_empty());
     }
 }
@@ -0,0 +1,295 @@
+use std::ops::Range;
+
+/// Inline formatting over a byte range of a `Styled` text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InlineStyle {
+    Bold,
+    Italic,
+    Code,
+    Link,
+}
+
+/// Text with its markers removed and the ranges they formatted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Styled {
+    pub text: String,
+    pub styles: Vec<(Range<usize>, InlineStyle)>,
+}
+
+/// A line-level piece of a Markdown document, in the order it appears.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    Heading(usize, Styled),
+    Paragraph(Styled),
+    /// A list item; `depth` counts levels of indentation, `marker` is `•` or `1.`.
+    Item {
+        depth: usize,
+        marker: String,
+        text: Styled,
+    },
+    Quote(Styled),
+    Code(String),
+    Rule,
+}
+
+/// Whether a text entry reads as Markdown: at least two different kinds of syntax, so
+/// a lone bullet list or a `#hashtag` stays plain text.
+pub fn looks_like_markdown(text: &str) -> bool {
+    let mut heading = false;
+    let mut list = false;
+    let mut quote = false;
+    let mut fence = false;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        heading |= heading_level(trimmed).is_some();
+        list |= list_item(line).is_some();
+        quote |= trimmed.starts_with("> ");
+        fence |= trimmed.starts_with("```");
+    }
+    let emphasis = has_pair(text, "**") || has_pair(text, "__");
+    let link = text.contains("](") && text.contains('[');
+    let code = has_pair(text, "`") && !fence;
+    [heading, list, quote, fence, emphasis, link, code]
+        .iter()
+        .filter(|&&kind| kind)
+        .count()
+        >= 2
+}
+
+fn has_pair(text: &str, marker: &str) -> bool {
+    text.find(marker)
+        .and_then(|start| text[start + marker.len()..].find(marker))
+        .is_some_and(|len| len > 0)
+}
+
+fn heading_level(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..]
+        .strip_prefix(' ')
+        .map(|rest| (level, rest.trim_end_matches(['#', ' '])))
+}
+
+/// `(depth, marker, rest)` for `- item`, `* item`, `+ item` and `1. item` lines.
+fn list_item(line: &str) -> Option<(usize, String, &str)> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let trimmed = &line[indent..];
+    let depth = indent / 2;
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(bullet) {
+            let rest = rest
+                .strip_prefix("[ ] ")
+                .map(|r| ("\u{2610}", r))
+                .or_else(|| rest.strip_prefix("[x] ").map(|r| ("\u{2611}", r)));
+            return Some(match rest {
+                Some((check, rest)) => (depth, check.to_string(), rest),
+                None => (depth, "\u{2022}".to_string(), &trimmed[bullet.len()..]),
+            });
+        }
+    }
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && digits <= 9 {
+        if let Some(rest) = trimmed[digits..].strip_prefix(". ") {
+            return Some((depth, trimmed[..digits + 1].to_string(), rest));
+        }
+    }
+    None
+}
+
+fn is_rule(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    line.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|&c| line.chars().all(|l| l == c))
+}
+
+/// Splits `text` into blocks. Consecutive plain lines join into one paragraph; blank
+/// lines end it.
+pub fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some(lines) = &mut code {
+            if trimmed.starts_with("```") {
+                blocks.push(Block::Code(lines.join("\n")));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            flush(&mut paragraph, &mut blocks);
+            code = Some(Vec::new());
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+        } else if let Some((level, rest)) = heading_level(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading(level, inline(rest)));
+        } else if is_rule(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Rule);
+        } else if let Some((depth, marker, rest)) = list_item(line) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Item {
+                depth,
+                marker,
+                text: inline(rest),
+            });
+        } else if let Some(rest) = trimmed.strip_prefix('>') {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Quote(inline(rest.trim_start())));
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    flush(&mut paragraph, &mut blocks);
+    // An unclosed fence runs to the end, as it does on GitHub
+    if let Some(lines) = code {
+        blocks.push(Block::Code(lines.join("\n")));
+    }
+    blocks
+}
+
+/// Strips `**bold**`, `*italic*`, `` `code` `` and `[links](url)` down to their text,
+/// remembering what was formatted. Unmatched markers are kept as written.
+pub fn inline(text: &str) -> Styled {
+    let mut styled = Styled::default();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some((inner, style, len)) = span(rest) {
+            let start = styled.text.len();
+            if style == InlineStyle::Code {
+                styled.text.push_str(inner);
+            } else {
+                // Formatting nests, e.g. a bold link
+                let nested = inline(inner);
+                styled.styles.extend(
+                    nested
+                        .styles
+                        .into_iter()
+                        .map(|(range, s)| (range.start + start..range.end + start, s)),
+                );
+                styled.text.push_str(&nested.text);
+            }
+            styled.styles.push((start..styled.text.len(), style));
+            rest = &rest[len..];
+        } else {
+            styled.text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    styled
+}
+
+/// The span starting at the beginning of `text`: its inner text, style and the byte
+/// length of the whole span including markers.
+fn span(text: &str) -> Option<(&str, InlineStyle, usize)> {
+    if let Some(rest) = text.strip_prefix('`') {
+        let end = rest.find('`')?;
+        return (end > 0).then(|| (&rest[..end], InlineStyle::Code, end + 2));
+    }
+    if let Some(rest) = text.strip_prefix('[') {
+        let close = rest.find("](")?;
+        let url_len = rest[close + 2..].find(')')?;
+        return Some((
+            &rest[..close],
+            InlineStyle::Link,
+            1 + close + 2 + url_len + 1,
+        ));
+    }
+    for (marker, style) in [
+        ("**", InlineStyle::Bold),
+        ("__", InlineStyle::Bold),
+        ("*", InlineStyle::Italic),
+        ("_", InlineStyle::Italic),
+    ] {
+        let Some(rest) = text.strip_prefix(marker) else {
+            continue;
+        };
+        // `* ` is a stray asterisk rather than the start of emphasis
+        if rest.starts_with(' ') {
+            continue;
+        }
+        let Some(end) = rest.find(marker) else {
+            continue;
+        };
+        if end > 0 {
+            return Some((&rest[..end], style, end + 2 * marker.len()));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_markdown_but_not_plain_text() {
+        assert!(looks_like_markdown("# Install\n\nRun `cargo build` first."));
+        assert!(looks_like_markdown(
+            "- see [the docs](https://example.com)\n- **soon**"
+        ));
+        assert!(!looks_like_markdown("- milk\n- eggs\n- bread"));
+        assert!(!looks_like_markdown("#hashtag and 2 * 3 * 4"));
+        assert!(!looks_like_markdown("fn main() {}"));
+    }
+
+    #[test]
+    fn parses_blocks_and_inline_styles() {
+        let blocks = parse(
+            "# Title #\nSome **bold** and\n*soft* wrap.\n\n  1. first\n- [x] done\n> quote\n---\n```\nlet x = 1;\n```",
+        );
+        assert_eq!(blocks.len(), 7);
+        assert_eq!(blocks[0], Block::Heading(1, inline("Title")));
+        let Block::Paragraph(paragraph) = &blocks[1] else {
+            panic!("expected a paragraph, got {:?}", blocks[1]);
+        };
+        assert_eq!(paragraph.text, "Some bold and soft wrap.");
+        assert_eq!(
+            paragraph.styles,
+            [(5..9, InlineStyle::Bold), (14..18, InlineStyle::Italic)]
+        );
+        assert_eq!(
+            blocks[2],
+            Block::Item {
+                depth: 1,
+                marker: "1.".into(),
+                text: inline("first"),
+            }
+        );
+        assert!(matches!(&blocks[3], Block::Item { marker, .. } if marker == "\u{2611}"));
+        assert_eq!(blocks[4], Block::Quote(inline("quote")));
+        assert_eq!(blocks[5], Block::Rule);
+        assert_eq!(blocks[6], Block::Code("let x = 1;".into()));
+    }
+
+    #[test]
+    fn inline_markers_nest_and_unmatched_ones_stay() {
+        let link = inline("see **[docs](https://x.y)** or `a*b*c`");
+        assert_eq!(link.text, "see docs or a*b*c");
+        assert_eq!(
+            link.styles,
+            [
+                (4..8, InlineStyle::Link),
+                (4..8, InlineStyle::Bold),
+                (12..17, InlineStyle::Code)
+            ]
+        );
+        assert_eq!(inline("2 * 3 = 6 and [x").text, "2 * 3 = 6 and [x");
+    }
+}
@@ -0,0 +1,265 @@
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+
+use crate::protocol::Entry;
+
+/// How many rows Alt+Up/Down moves the focus.
+pub const JUMP_STEP: usize = 5;
+
+/// Rows at the top of the list that can be copied with the number keys.
+pub const NUMBER_KEYS: usize = 9;
+
+/// Which date header a row is listed under. Days are calendar dates in the time zone
+/// of `now`, so "Yesterday" starts at the local midnight rather than 24 hours back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaySection {
+    /// Pinned entries, which the backend lists first regardless of age.
+    Pinned,
+    Today,
+    Yesterday,
+    ThisWeek,
+    /// A single older day.
+    Day(NaiveDate),
+}
+
+impl DaySection {
+    pub fn for_entry<Tz: TimeZone>(entry: &Entry, now: &DateTime<Tz>) -> Self {
+        if entry.pinned {
+            DaySection::Pinned
+        } else {
+            DaySection::of(entry.timestamp, now)
+        }
+    }
+
+    pub fn of<Tz: TimeZone>(timestamp_ms: i64, now: &DateTime<Tz>) -> Self {
+        let today = now.date_naive();
+        let Some(copied) = now.timezone().timestamp_millis_opt(timestamp_ms).earliest() else {
+            return DaySection::Today;
+        };
+        let day = copied.date_naive();
+        // Clocks moved back can put a copy after today; it still belongs to today
+        match (today - day).num_days() {
+            ..=0 => DaySection::Today,
+            1 => DaySection::Yesterday,
+            2..=6 => DaySection::ThisWeek,
+            _ => DaySection::Day(day),
+        }
+    }
+
+    /// "Today", "Yesterday", "This week", then dates like "Mar 4" (with the year once
+    /// it's not the current one).
+    pub fn label(self, today: NaiveDate) -> String {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        match self {
+            DaySection::Pinned => "Pinned".into(),
+            DaySection::Today => "Today".into(),
+            DaySection::Yesterday => "Yesterday".into(),
+            DaySection::ThisWeek => "This week".into(),
+            DaySection::Day(day) => {
+                let month = MONTHS[day.month0() as usize];
+                if day.year() == today.year() {
+                    format!("{} {}", month, day.day())
+                } else {
+                    format!("{} {}, {}", month, day.day(), day.year())
+                }
+            }
+        }
+    }
+}
+
+/// A row of the entry list: the header above a date section, or an entry by its index
+/// among the visible entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListRow {
+    Header(DaySection),
+    Entry(usize),
+}
+
+/// The list's rows, with a header before the first entry of each section. `sections`
+/// holds each visible entry's section in list order.
+pub fn with_headers(sections: &[DaySection]) -> Vec<ListRow> {
+    let mut rows = Vec::with_capacity(sections.len() + 8);
+    for (idx, &section) in sections.iter().enumerate() {
+        if idx == 0 || sections[idx - 1] != section {
+            rows.push(ListRow::Header(section));
+        }
+        rows.push(ListRow::Entry(idx));
+    }
+    rows
+}
+
+/// The row to scroll to so entry `idx` is in view, which is its header when it is the
+/// first entry of its section.
+pub fn row_of_entry(rows: &[ListRow], idx: usize) -> usize {
+    match rows.iter().position(|&row| row == ListRow::Entry(idx)) {
+        Some(row) if row > 0 && matches!(rows[row - 1], ListRow::Header(_)) => row - 1,
+        Some(row) => row,
+        None => idx,
+    }
+}
+
+/// (year, month 1–12, day 1–31) for a count of days since 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Moves `idx` by `step` rows, stopping at either end instead of wrapping.
+pub fn jump(idx: usize, count: usize, step: usize, forward: bool) -> usize {
+    if forward {
+        (idx + step).min(count.saturating_sub(1))
+    } else {
+        idx.saturating_sub(step)
+    }
+}
+
+/// Index of the first row of the next day group, or of the previous one.
+///
+/// Going back from inside a group lands on that group's first row before moving to the
+/// previous group. `days` holds each visible row's group (its [`DaySection`]) in list
+/// order.
+pub fn day_group_start<T: PartialEq>(days: &[T], idx: usize, forward: bool) -> usize {
+    let Some(current) = days.get(idx) else {
+        return idx;
+    };
+    if forward {
+        return days[idx..]
+            .iter()
+            .position(|d| d != current)
+            .map_or(idx, |offset| idx + offset);
+    }
+
+    let group_start = |end: usize| {
+        let day = &days[end];
+        days[..end]
+            .iter()
+            .rposition(|d| d != day)
+            .map_or(0, |i| i + 1)
+    };
+    let start = group_start(idx);
+    if start < idx || start == 0 {
+        start
+    } else {
+        group_start(start - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::*;
+
+    #[test]
+    fn jump_clamps_at_both_ends() {
+        assert_eq!(jump(0, 20, JUMP_STEP, true), 5);
+        assert_eq!(jump(18, 20, JUMP_STEP, true), 19);
+        assert_eq!(jump(3, 20, JUMP_STEP, false), 0);
+    }
+
+    #[test]
+    fn day_groups_step_to_first_row() {
+        let days = [0, 0, 0, 1, 1, 3];
+        assert_eq!(day_group_start(&days, 1, true), 3);
+        assert_eq!(day_group_start(&days, 3, true), 5);
+        assert_eq!(day_group_start(&days, 5, true), 5);
+        assert_eq!(day_group_start(&days, 4, false), 3);
+        assert_eq!(day_group_start(&days, 3, false), 0);
+        assert_eq!(day_group_start(&days, 0, false), 0);
+    }
+
+    #[test]
+    fn sections_label_recent_days_and_older_dates() {
+        // 2026-03-10 12:00 UTC
+        let now_secs = 1_773_144_000;
+        let now = Utc.timestamp_opt(now_secs, 0).unwrap();
+        let today = now.date_naive();
+        let at = |days_back: i64| (now_secs - days_back * 86_400) * 1000;
+        assert_eq!(DaySection::of(at(0), &now).label(today), "Today");
+        assert_eq!(DaySection::of(at(1), &now).label(today), "Yesterday");
+        assert_eq!(DaySection::of(at(6), &now), DaySection::ThisWeek);
+        assert_eq!(DaySection::of(at(7), &now).label(today), "Mar 3");
+        assert_eq!(DaySection::of(at(70), &now).label(today), "Dec 30, 2025");
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+    }
+
+    #[test]
+    fn sections_change_at_local_midnight() {
+        let zone = FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = zone.with_ymd_and_hms(2026, 3, 10, 0, 30, 0).unwrap();
+        let at = |d: u32, h: u32, m: u32| {
+            zone.with_ymd_and_hms(2026, 3, d, h, m, 0)
+                .unwrap()
+                .timestamp_millis()
+        };
+        // An hour apart, but either side of midnight
+        assert_eq!(DaySection::of(at(9, 23, 30), &now), DaySection::Yesterday);
+        assert_eq!(DaySection::of(at(10, 0, 10), &now), DaySection::Today);
+        // Just under 48 hours back, but two dates ago
+        assert_eq!(DaySection::of(at(8, 0, 40), &now), DaySection::ThisWeek);
+        assert_eq!(DaySection::of(at(9, 0, 0), &now), DaySection::Yesterday);
+    }
+
+    #[test]
+    fn sections_follow_the_time_zone() {
+        // 2026-03-10 03:00 UTC is still the evening of Mar 9 in New York
+        let copied = Utc
+            .with_ymd_and_hms(2026, 3, 10, 3, 0, 0)
+            .unwrap()
+            .timestamp_millis();
+        let utc_now = Utc.with_ymd_and_hms(2026, 3, 10, 15, 0, 0).unwrap();
+        let new_york = FixedOffset::west_opt(5 * 3600).unwrap();
+        let new_york_now = utc_now.with_timezone(&new_york);
+        assert_eq!(DaySection::of(copied, &utc_now), DaySection::Today);
+        assert_eq!(DaySection::of(copied, &new_york_now), DaySection::Yesterday);
+
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let old = Utc
+            .with_ymd_and_hms(2026, 2, 1, 20, 0, 0)
+            .unwrap()
+            .timestamp_millis();
+        let tokyo_now = utc_now.with_timezone(&tokyo);
+        assert_eq!(
+            DaySection::of(old, &tokyo_now).label(tokyo_now.date_naive()),
+            "Feb 2"
+        );
+        assert_eq!(
+            DaySection::of(old, &utc_now).label(utc_now.date_naive()),
+            "Feb 1"
+        );
+    }
+
+    #[test]
+    fn headers_go_before_each_section() {
+        let today = DaySection::Today;
+        let yesterday = DaySection::Yesterday;
+        let rows = with_headers(&[DaySection::Pinned, today, today, yesterday]);
+        assert_eq!(
+            rows,
+            vec![
+                ListRow::Header(DaySection::Pinned),
+                ListRow::Entry(0),
+                ListRow::Header(today),
+                ListRow::Entry(1),
+                ListRow::Entry(2),
+                ListRow::Header(yesterday),
+                ListRow::Entry(3),
+            ]
+        );
+        assert_eq!(row_of_entry(&rows, 0), 0);
+        assert_eq!(row_of_entry(&rows, 1), 2);
+        assert_eq!(row_of_entry(&rows, 2), 4);
+        assert_eq!(row_of_entry(&rows, 3), 5);
+        assert!(with_headers(&[]).is_empty());
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::{atomic::AtomicBool, Mutex};
+
+use crate::wake;
+
+/// A one-line report of something that finished out of sight, like an import, shown
+/// at the bottom of the popover until dismissed or replaced by the next one.
+pub static NOTICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Set whenever `NOTICE` changes so the event loop repaints.
+pub static NOTICE_UPDATED: AtomicBool = AtomicBool::new(false);
+
+/// Shows `message` in place of any earlier notice. Any thread may call it.
+pub fn show(message: impl Into<String>) {
+    *NOTICE.lock().unwrap() = Some(message.into());
+    wake::set(&NOTICE_UPDATED);
+}
+
+pub fn dismiss() {
+    *NOTICE.lock().unwrap() = None;
+}
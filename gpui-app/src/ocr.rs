@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    protocol::{Entry, EntryType},
+    thumbnails,
+};
+
+/// Text recognized in image entries, by image path. Images queued or without any
+/// text map to an empty string.
+pub static RECOGNIZED: Mutex<BTreeMap<PathBuf, String>> = Mutex::new(BTreeMap::new());
+
+/// Set whenever an image finishes so the event loop knows to repaint.
+pub static OCR_UPDATED: AtomicBool = AtomicBool::new(false);
+
+// Longest excerpt shown in a row, in characters
+const EXCERPT_LEN: usize = 80;
+
+/// Vision's text recognizer, driven through JavaScript for Automation so no Swift
+/// toolchain or extra binary is needed. Prints one recognized line per line.
+#[cfg(target_os = "macos")]
+const VISION_SCRIPT: &str = r#"
+ObjC.import('Vision');
+function run(argv) {
+  const url = $.NSURL.fileURLWithPath(argv[0]);
+  const handler = $.VNImageRequestHandler.alloc.initWithURLOptions(url, $.NSDictionary.dictionary);
+  const request = $.VNRecognizeTextRequest.alloc.init;
+  if (!handler.performRequestsError($.NSArray.arrayWithObject(request), null)) {
+    throw new Error('Vision could not read the image');
+  }
+  const lines = [];
+  const results = request.results;
+  for (let i = 0; i < results.count; i++) {
+    const candidates = results.objectAtIndex(i).topCandidates(1);
+    if (candidates.count > 0) lines.push(candidates.objectAtIndex(0).string.js);
+  }
+  return lines.join('\n');
+}
+"#;
+
+/// Background OCR for image entries, so screenshots of text can be searched.
+///
+/// Images are read one at a time on a worker thread, with the Vision framework on
+/// macOS and `tesseract` elsewhere. Results are cached on disk next to the thumbnails,
+/// keyed by the image bytes, so each image is only ever recognized once; with the
+/// history encrypted they are kept in memory only and images are read again next launch.
+pub struct Recognizer {
+    tx: Sender<PathBuf>,
+}
+
+impl Recognizer {
+    pub fn start(cache_on_disk: bool) -> Self {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+        thread::spawn(move || {
+            for source in rx {
+                let text = if cache_on_disk {
+                    recognize_cached(&source)
+                } else {
+                    recognize(&source)
+                };
+                let text = text.unwrap_or_else(|e| {
+                    eprintln!("Failed to read text in {}: {:#}", source.display(), e);
+                    String::new()
+                });
+                if !text.is_empty() {
+                    RECOGNIZED.lock().unwrap().insert(source, text);
+                    crate::wake::set(&OCR_UPDATED);
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queues the image entries that haven't been looked at yet.
+    pub fn queue(&self, entries: &[Entry]) {
+        let mut recognized = RECOGNIZED.lock().unwrap();
+        for entry in entries {
+            if entry.entry_type != EntryType::Image {
+                continue;
+            }
+            let path = PathBuf::from(&entry.content);
+            if recognized.contains_key(&path) || !path.is_file() {
+                continue;
+            }
+            recognized.insert(path.clone(), String::new());
+            let _ = self.tx.send(path);
+        }
+    }
+}
+
+/// The text recognized in an image entry, which search matches too.
+pub fn text_for<'a>(entry: &Entry, recognized: &'a BTreeMap<PathBuf, String>) -> Option<&'a str> {
+    if entry.entry_type != EntryType::Image {
+        return None;
+    }
+    recognized
+        .get(Path::new(&entry.content))
+        .map(String::as_str)
+        .filter(|text| !text.is_empty())
+}
+
+/// All the text recognized in an image entry, if any.
+pub fn text(entry: &Entry) -> Option<String> {
+    text_for(entry, &RECOGNIZED.lock().unwrap()).map(str::to_string)
+}
+
+/// The recognized text on one line, shortened for a row.
+pub fn excerpt(text: &str) -> String {
+    let joined = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if joined.chars().count() <= EXCERPT_LEN {
+        return joined;
+    }
+    let mut short: String = joined.chars().take(EXCERPT_LEN - 1).collect();
+    short.push('\u{2026}');
+    short
+}
+
+fn recognize_cached(source: &Path) -> Result<String> {
+    let bytes = std::fs::read(source).context("failed to read image")?;
+    let dir = thumbnails::cache_dir().with_file_name("ocr");
+    let cached = dir.join(format!("{:016x}.txt", thumbnails::cache_key(&bytes)));
+    if let Ok(text) = std::fs::read_to_string(&cached) {
+        return Ok(text);
+    }
+
+    let text = recognize(source)?;
+    std::fs::create_dir_all(&dir).context("failed to create OCR cache")?;
+    std::fs::write(&cached, &text).context("failed to cache recognized text")?;
+    Ok(text)
+}
+
+fn recognize(source: &Path) -> Result<String> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("/usr/bin/osascript");
+        command
+            .args(["-l", "JavaScript", "-e", VISION_SCRIPT])
+            .arg(source);
+        command
+    };
+    #[cfg(not(target_os = "macos"))]
+    let mut command = {
+        let mut command = Command::new("tesseract");
+        command.arg(source).args(["stdout", "--psm", "3"]);
+        command
+    };
+
+    let output = command
+        .output()
+        .context("failed to run the text recognizer")?;
+    if !output.status.success() {
+        bail!(
+            "text recognizer exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognized_text_is_searchable_and_shortened() {
+        let image = Entry {
+            id: 1,
+            content: "/tmp/clipz_images/shot.png".into(),
+            entry_type: EntryType::Image,
+            ..Default::default()
+        };
+        let text = Entry {
+            entry_type: EntryType::Text,
+            ..image.clone()
+        };
+        let mut recognized = BTreeMap::new();
+        assert_eq!(text_for(&image, &recognized), None);
+
+        recognized.insert(PathBuf::from(&image.content), "Invoice\n#42".to_string());
+        assert_eq!(text_for(&image, &recognized), Some("Invoice\n#42"));
+        // Only images carry recognized text, even if a text entry names the same path
+        assert_eq!(text_for(&text, &recognized), None);
+
+        assert_eq!(excerpt("  Total:\n\n 12.00  EUR "), "Total: 12.00 EUR");
+        let long = excerpt(&"word ".repeat(40));
+        assert_eq!(long.chars().count(), EXCERPT_LEN);
+        assert!(long.ends_with('\u{2026}'));
+    }
+}
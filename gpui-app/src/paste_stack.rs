@@ -0,0 +1,78 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Entries queued from the popover for pasting one at a time with the `[hotkey]
+/// paste_stack` chord. Shared between the popover windows, which fill it, and the
+/// app state, which pops it when the chord is pressed.
+pub static STACK: Mutex<PasteStack> = Mutex::new(PasteStack::new());
+
+/// Entry ids in the order they will be pasted. Each press of the chord takes the
+/// first one off, so items come out in the order they were queued.
+#[derive(Debug, Default)]
+pub struct PasteStack {
+    ids: VecDeque<u64>,
+}
+
+impl PasteStack {
+    pub const fn new() -> Self {
+        Self {
+            ids: VecDeque::new(),
+        }
+    }
+
+    /// Queues `id` at the end; an entry already queued keeps its place.
+    pub fn push(&mut self, id: u64) {
+        if !self.ids.contains(&id) {
+            self.ids.push_back(id);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.ids.pop_front()
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.ids.retain(|queued| *queued != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// 1 for the entry pasted next, and so on; None when `id` isn't queued.
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.ids
+            .iter()
+            .position(|queued| *queued == id)
+            .map(|i| i + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pastes_in_the_order_queued() {
+        let mut stack = PasteStack::new();
+        for id in [7, 3, 9, 3] {
+            stack.push(id);
+        }
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.position(9), Some(3));
+        assert_eq!(stack.pop(), Some(7));
+        assert_eq!(stack.position(9), Some(2));
+
+        stack.remove(3);
+        assert_eq!(stack.pop(), Some(9));
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+
+/// Runs `command` with `input` on stdin and returns what it printed, for plugins and
+/// formatters. It is killed after `timeout`; exiting non-zero is an error carrying
+/// its stderr. Blocks until then, so call it off the UI thread.
+pub fn run(command: &mut Command, input: Vec<u8>, name: &str, timeout: Duration) -> Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run {}", name))?;
+
+    // Feed and drain every pipe on its own thread, so a big input or a chatty command
+    // can't fill one up and stall
+    let mut stdin = child.stdin.take().context("no stdin")?;
+    thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let output = read_on_thread(child.stdout.take().context("no stdout")?);
+    let errors = read_on_thread(child.stderr.take().context("no stderr")?);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("{} timed out", name);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let output = output.join().unwrap_or_default();
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        bail!("{} exited with {}: {}", name, status, errors.trim());
+    }
+    Ok(output)
+}
+
+/// Whether `error` came from [`run`] not finding the program at all.
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .root_cause()
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::NotFound)
+}
+
+fn read_on_thread(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut text = String::new();
+        let _ = pipe.read_to_string(&mut text);
+        text
+    })
+}
@@ -0,0 +1,185 @@
+use std::{path::PathBuf, process::Command, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    pipe,
+    protocol::{Entry, EntryType},
+};
+
+// Plugins that take longer than this are killed
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An executable in the plugins directory, offered in every entry's context menu.
+///
+/// It is run with the entry as JSON on stdin (the same shape as `--dump`) and
+/// answers on stdout with `{"actions": [...]}`, or with plain text, which is copied
+/// to the clipboard as a new entry. Exiting non-zero or printing nothing does nothing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    path: PathBuf,
+}
+
+/// Something a plugin asks for in its JSON reply.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum PluginAction {
+    /// Put content on the clipboard, recording it as a new entry.
+    Copy {
+        content: String,
+        #[serde(default, rename = "type")]
+        entry_type: EntryType,
+    },
+    /// Record content in the history without touching the clipboard.
+    Add {
+        content: String,
+        #[serde(default, rename = "type")]
+        entry_type: EntryType,
+    },
+    /// Replace the entry's tags.
+    SetTags { tags: Vec<String> },
+    /// Pin or unpin the entry.
+    Pin {
+        #[serde(default = "pinned_default")]
+        pinned: bool,
+    },
+    /// Remove the entry from the history.
+    Remove,
+}
+
+fn pinned_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    actions: Vec<PluginAction>,
+}
+
+/// `plugins/` next to `config.toml`.
+pub fn dir() -> Option<PathBuf> {
+    Config::path().map(|config| config.with_file_name("plugins"))
+}
+
+/// Executables in the plugins directory, by name. Hidden files are skipped and the
+/// extension is dropped from the name, so `title-case.py` shows as "title-case".
+pub fn discover() -> Vec<Plugin> {
+    let Some(read_dir) = dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<Plugin> = read_dir
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            (!name.starts_with('.')).then_some(Plugin { name, path })
+        })
+        .collect();
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+impl Plugin {
+    /// Runs the plugin on `entry` and returns what it asked for. Blocks for up to
+    /// ten seconds, so call it off the UI thread.
+    pub fn run(&self, entry: &Entry) -> Result<Vec<PluginAction>> {
+        let input = serde_json::to_vec(entry)?;
+        let output = pipe::run(&mut Command::new(&self.path), input, &self.name, TIMEOUT)?;
+        parse_output(&output).with_context(|| format!("bad reply from {}", self.name))
+    }
+}
+
+/// A reply is JSON when it starts with `{`; anything else is text to copy.
+fn parse_output(output: &str) -> Result<Vec<PluginAction>> {
+    let trimmed = output.trim_end_matches(['\n', '\r']);
+    if trimmed.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    if trimmed.trim_start().starts_with('{') {
+        let reply: Reply = serde_json::from_str(trimmed)?;
+        return Ok(reply.actions);
+    }
+    Ok(vec![PluginAction::Copy {
+        content: trimmed.to_string(),
+        entry_type: EntryType::Text,
+    }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replies_are_actions_or_text_to_copy() {
+        assert_eq!(
+            parse_output("HELLO WORLD\n").unwrap(),
+            [PluginAction::Copy {
+                content: "HELLO WORLD".into(),
+                entry_type: EntryType::Text,
+            }]
+        );
+        assert!(parse_output("\n").unwrap().is_empty());
+        assert_eq!(
+            parse_output(
+                r#"{"actions":[{"action":"add","content":"https://example.com","type":"url"},{"action":"set-tags","tags":["link"]},{"action":"pin"},{"action":"remove"}]}"#
+            )
+            .unwrap(),
+            [
+                PluginAction::Add {
+                    content: "https://example.com".into(),
+                    entry_type: EntryType::Url,
+                },
+                PluginAction::SetTags {
+                    tags: vec!["link".into()],
+                },
+                PluginAction::Pin { pinned: true },
+                PluginAction::Remove,
+            ]
+        );
+        assert!(parse_output(r#"{"actions":[{"action":"explode"}]}"#).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_chatty_plugin_is_not_blocked_by_stderr() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("clipz-plugin-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "#!/bin/sh\nhead -c 200000 /dev/zero >&2\necho done\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let plugin = Plugin {
+            name: "chatty".into(),
+            path: path.clone(),
+        };
+        let actions = plugin.run(&Entry::text("hi"));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            actions.unwrap(),
+            [PluginAction::Copy {
+                content: "done".into(),
+                entry_type: EntryType::Text,
+            }]
+        );
+    }
+}
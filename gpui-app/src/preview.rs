@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use regex::RegexBuilder;
+
+/// One find-in-preview match: the line it is on and its byte range within that line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub line: usize,
+    pub range: Range<usize>,
+}
+
+/// Case-insensitive literal matches of `query` in each line, in reading order.
+pub fn find_hits(lines: &[&str], query: &str) -> Vec<Hit> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let Ok(re) = RegexBuilder::new(&regex::escape(query))
+        .case_insensitive(true)
+        .build()
+    else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line, text)| {
+            re.find_iter(text).map(move |m| Hit {
+                line,
+                range: m.range(),
+            })
+        })
+        .collect()
+}
+
+/// State of the Cmd+F bar in the preview pane.
+#[derive(Debug, Default)]
+pub struct FindState {
+    pub query: String,
+    /// Index into the hit list of the match currently navigated to.
+    pub current: usize,
+}
+
+impl FindState {
+    pub fn step(&mut self, hit_count: usize, forward: bool) {
+        if hit_count == 0 {
+            self.current = 0;
+            return;
+        }
+        self.current = if forward {
+            (self.current + 1) % hit_count
+        } else {
+            (self.current + hit_count - 1) % hit_count
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_hits_per_line_case_insensitively() {
+        let lines = ["Foo bar foo", "nothing", "a.FOO"];
+        let hits = find_hits(&lines, "foo");
+        assert_eq!(
+            hits,
+            [
+                Hit {
+                    line: 0,
+                    range: 0..3
+                },
+                Hit {
+                    line: 0,
+                    range: 8..11
+                },
+                Hit {
+                    line: 2,
+                    range: 2..5
+                },
+            ]
+        );
+        // The query is literal, not a pattern
+        assert_eq!(find_hits(&lines, ".").len(), 1);
+        assert!(find_hits(&lines, "").is_empty());
+    }
+
+    #[test]
+    fn step_wraps_in_both_directions() {
+        let mut find = FindState::default();
+        find.step(3, false);
+        assert_eq!(find.current, 2);
+        find.step(3, true);
+        assert_eq!(find.current, 0);
+        find.step(0, true);
+        assert_eq!(find.current, 0);
+    }
+}
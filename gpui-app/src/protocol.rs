@@ -0,0 +1,819 @@
+use serde::{Deserialize, Serialize};
+
+/// The protocol this frontend speaks; `protocol.version` in `src/protocol.zig` must
+/// match. Bump both whenever a command or message changes shape.
+pub const PROTOCOL_VERSION: u32 = 7;
+
+// ---------- Commands (frontend → backend) ----------
+
+/// A command sent to the backend. Serialized as the `method`/`params` half of a
+/// JSON-RPC 2.0 request; see [`Request`].
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(tag = "method", content = "params", rename_all = "kebab-case")]
+pub enum Command {
+    /// Tells the backend which protocol this frontend speaks; answered with its own.
+    Hello {
+        #[serde(rename = "protocolVersion")]
+        protocol_version: u32,
+    },
+    GetEntries,
+    /// One page of the history, used while it first loads.
+    #[serde(rename = "get-entries")]
+    GetEntriesPage {
+        offset: usize,
+        limit: usize,
+    },
+    /// Entries matching `query` anywhere in the history, loaded or not; answered with
+    /// `search-results`. `#tag` words and fuzzy matching work as in the search field,
+    /// but not `/regex` queries.
+    Search {
+        query: String,
+        /// Empty keeps every type.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        types: Vec<EntryType>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<usize>,
+        /// Text found in entries that the backend doesn't have, for it to match too.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        texts: Vec<SearchText>,
+    },
+    SelectEntry {
+        id: u64,
+    },
+    /// Like `SelectEntry`, but puts only one of the entry's `formats` on the clipboard.
+    SelectFormat {
+        id: u64,
+        format: Format,
+    },
+    RemoveEntry {
+        id: u64,
+    },
+    /// Takes an entry out of the trash and puts it back where it was.
+    RestoreEntry {
+        id: u64,
+    },
+    /// Removes an entry for good, from the history or the trash.
+    PurgeEntry {
+        id: u64,
+    },
+    GetTrash,
+    EmptyTrash,
+    TogglePin {
+        id: u64,
+    },
+    /// Records what is on the clipboard now, then selects the entry.
+    SwapEntry {
+        id: u64,
+    },
+    /// Puts content on the system clipboard; the backend records it as a new entry.
+    SetClipboard {
+        content: String,
+        #[serde(rename = "type")]
+        entry_type: EntryType,
+    },
+    /// Records content in the history without touching the clipboard.
+    AddEntry {
+        content: String,
+        #[serde(rename = "type")]
+        entry_type: EntryType,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        pinned: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+    },
+    /// Merges entries into the history in one go, for Import… and Split; answered with
+    /// `imported`. Entries already in the history stay as they are and none are evicted.
+    ImportEntries {
+        entries: Vec<ImportEntry>,
+    },
+    /// Stops or resumes recording clipboard changes.
+    SetPaused {
+        paused: bool,
+    },
+    /// While enabled, text copies are joined onto one entry instead of added separately.
+    SetAppendMode {
+        enabled: bool,
+    },
+    /// While disabled, copied image data is skipped instead of written to disk.
+    SetImageCapture {
+        enabled: bool,
+    },
+    /// Replaces an entry's tags; an empty list clears them.
+    SetTags {
+        id: u64,
+        tags: Vec<String>,
+    },
+    GetSnippets,
+    /// Adds a snippet, or replaces the one with `id`; answered with every snippet.
+    SaveSnippet {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+        name: String,
+        content: String,
+    },
+    RemoveSnippet {
+        id: u64,
+    },
+    /// The newest clipboard changes the backend has seen, for diagnosing missed copies.
+    GetTimeline {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<usize>,
+    },
+    /// Changes how many entries the backend keeps, trimming the oldest unpinned ones;
+    /// `None` keeps every entry.
+    SetLimit {
+        limit: Option<usize>,
+    },
+    Clear,
+    Quit,
+}
+
+impl Command {
+    /// The history entry the command acts on, if it is about one.
+    pub fn entry_id(&self) -> Option<u64> {
+        match self {
+            Command::SelectEntry { id }
+            | Command::SelectFormat { id, .. }
+            | Command::RemoveEntry { id }
+            | Command::RestoreEntry { id }
+            | Command::PurgeEntry { id }
+            | Command::TogglePin { id }
+            | Command::SwapEntry { id }
+            | Command::SetTags { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the command again is safe when its first try timed out. The
+    /// first may still land late, so toggles and commands that add or move something
+    /// would then happen twice.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            Command::TogglePin { .. }
+                | Command::SwapEntry { .. }
+                | Command::RemoveEntry { .. }
+                | Command::AddEntry { .. }
+                | Command::SaveSnippet { id: None, .. }
+        )
+    }
+
+    /// What the command does, for telling the user it didn't go through.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Command::Hello { .. } => "Connecting",
+            Command::GetEntries | Command::GetEntriesPage { .. } => "Loading history",
+            Command::Search { .. } => "Searching",
+            Command::SelectEntry { .. } | Command::SelectFormat { .. } => "Copy",
+            Command::RemoveEntry { .. } => "Remove",
+            Command::RestoreEntry { .. } => "Restore",
+            Command::PurgeEntry { .. } => "Delete",
+            Command::GetTrash => "Loading archived entries",
+            Command::EmptyTrash => "Emptying the archive",
+            Command::TogglePin { .. } => "Pin",
+            Command::SwapEntry { .. } => "Swap",
+            Command::SetClipboard { .. } => "Copy",
+            Command::AddEntry { .. } => "Add",
+            Command::ImportEntries { .. } => "Adding entries",
+            Command::SetPaused { .. } => "Pause",
+            Command::SetAppendMode { .. } => "Append mode",
+            Command::SetImageCapture { .. } => "Image capture",
+            Command::SetTags { .. } => "Tagging",
+            Command::GetSnippets => "Loading snippets",
+            Command::SaveSnippet { .. } => "Saving the snippet",
+            Command::RemoveSnippet { .. } => "Removing the snippet",
+            Command::GetTimeline { .. } => "Loading the timeline",
+            Command::SetLimit { .. } => "History size",
+            Command::Clear => "Clear",
+            Command::Quit => "Quit",
+        }
+    }
+}
+
+/// Text Clipz found for an entry with `content`: read in the image at that path, or
+/// the title of the page at that URL.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct SearchText {
+    pub content: String,
+    pub text: String,
+}
+
+/// An exported entry as `import-entries` takes it: what the history needs to put it
+/// back where it was.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct ImportEntry {
+    pub content: String,
+    #[serde(rename = "type")]
+    pub entry_type: EntryType,
+    pub timestamp: i64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl From<Entry> for ImportEntry {
+    fn from(entry: Entry) -> Self {
+        Self {
+            content: entry.content,
+            entry_type: entry.entry_type,
+            timestamp: entry.timestamp,
+            pinned: entry.pinned,
+            tags: entry.tags,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Request {
+    jsonrpc: &'static str,
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+impl Request {
+    pub fn new(id: u64, command: Command) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            command,
+        }
+    }
+}
+
+// ---------- Messages (backend → frontend) ----------
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum BackendMessage {
+    #[serde(rename = "entries")]
+    Entries {
+        data: Vec<Entry>,
+        /// Length of the whole history; `data` may be just one page of it.
+        #[serde(default)]
+        total: Option<usize>,
+        #[serde(default)]
+        offset: usize,
+    },
+    /// A new entry, to go right after the entry `after` (at the top for None). Like
+    /// the other `entry-*` messages, it carries the history's new length in `total`.
+    #[serde(rename = "entry-added")]
+    EntryAdded {
+        after: Option<u64>,
+        data: Entry,
+        #[serde(default)]
+        total: Option<usize>,
+    },
+    /// An entry that changed or moved, to go right after `after` like a new one.
+    #[serde(rename = "entry-updated")]
+    EntryUpdated {
+        after: Option<u64>,
+        data: Entry,
+        #[serde(default)]
+        total: Option<usize>,
+    },
+    /// An entry that left the history, or the loaded part of it.
+    #[serde(rename = "entry-removed")]
+    EntryRemoved {
+        id: u64,
+        #[serde(default)]
+        total: Option<usize>,
+    },
+    /// The newest matches for a `search`, of `total` in the whole history.
+    #[serde(rename = "search-results")]
+    SearchResults {
+        data: Vec<Entry>,
+        #[serde(default)]
+        total: usize,
+    },
+    #[serde(rename = "snippets")]
+    Snippets { data: Vec<Snippet> },
+    #[serde(rename = "trash")]
+    Trash { data: Vec<TrashedEntry> },
+    #[serde(rename = "timeline")]
+    Timeline { data: Vec<ClipboardEvent> },
+    #[serde(rename = "select-success")]
+    SelectSuccess {
+        /// Set when the entry was selected by id (absent for legacy index selects).
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    /// The answer to `import-entries`: entries added, already in the history, and left
+    /// out because it was full.
+    #[serde(rename = "imported")]
+    Imported {
+        added: usize,
+        existing: usize,
+        skipped: usize,
+    },
+    #[serde(rename = "remove-success")]
+    RemoveSuccess,
+    #[serde(rename = "pin-toggled")]
+    PinToggled,
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "error")]
+    Error(CommandError),
+    #[serde(rename = "ready")]
+    Ready,
+    #[serde(rename = "hello")]
+    Hello {
+        #[serde(rename = "protocolVersion")]
+        protocol_version: u32,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// A backend message together with the id of the request it answers, if any.
+/// Broadcasts (e.g. entries pushed after a clipboard change) have no request id.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Envelope {
+    #[serde(default)]
+    #[serde(rename = "requestId")]
+    pub request_id: Option<u64>,
+    #[serde(flatten)]
+    pub message: BackendMessage,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, thiserror::Error)]
+#[error("{message} ({code})")]
+pub struct CommandError {
+    #[serde(default)]
+    pub code: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Entry {
+    pub id: u64,
+    pub content: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    #[serde(rename = "type")]
+    pub entry_type: EntryType,
+    #[serde(default)]
+    #[serde(rename = "isCurrent")]
+    pub is_current: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Hex SHA-256 of the type and content from `hashing.zig`; empty in old exports
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hash: String,
+    /// Times the content was copied; the backend coalesces repeats into one entry.
+    #[serde(default = "one_copy", skip_serializing_if = "is_one_copy")]
+    pub copies: u32,
+    /// Frontmost app at the latest copy; empty when unknown.
+    #[serde(
+        default,
+        rename = "sourceApp",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub source_app: String,
+    /// Its focused window's title, with `[privacy] capture_window_titles` on.
+    #[serde(
+        default,
+        rename = "windowTitle",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub window_title: String,
+    /// Every form a text copy was made in, plain text first; empty when it was only
+    /// plain text. Selecting the entry restores them all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<Format>,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            content: String::new(),
+            timestamp: 0,
+            entry_type: EntryType::default(),
+            is_current: false,
+            pinned: false,
+            tags: Vec::new(),
+            hash: String::new(),
+            copies: 1,
+            source_app: String::new(),
+            window_title: String::new(),
+            formats: Vec::new(),
+        }
+    }
+}
+
+impl Entry {
+    /// A text entry with id 1, for tests.
+    #[cfg(test)]
+    pub fn text(content: &str) -> Self {
+        Self {
+            id: 1,
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Where the entry was copied, like "Safari — Release notes"; None when unknown.
+    pub fn source_label(&self) -> Option<String> {
+        match (self.source_app.as_str(), self.window_title.as_str()) {
+            ("", "") => None,
+            (app, "") => Some(app.to_string()),
+            ("", title) => Some(title.to_string()),
+            (app, title) => Some(format!("{} \u{2014} {}", app, title)),
+        }
+    }
+
+    /// Whether it was copied with styles, as RTF or HTML.
+    pub fn is_rich(&self) -> bool {
+        self.formats
+            .iter()
+            .any(|format| matches!(format, Format::Rtf | Format::Html))
+    }
+
+    /// The paths a file entry holds, one per line, since files copied together are
+    /// kept as one entry. Empty for every other type.
+    pub fn file_paths(&self) -> Vec<&str> {
+        if self.entry_type != EntryType::File {
+            return Vec::new();
+        }
+        self.content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+fn one_copy() -> u32 {
+    1
+}
+
+fn is_one_copy(copies: &u32) -> bool {
+    *copies == 1
+}
+
+/// A removed entry waiting in the trash, see `trash.rs`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TrashedEntry {
+    #[serde(flatten)]
+    pub entry: Entry,
+    /// Unix milliseconds, like `timestamp`.
+    #[serde(rename = "removedAt")]
+    pub removed_at: i64,
+}
+
+/// Named text saved apart from the history, see `snippets.rs`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Snippet {
+    pub id: u64,
+    pub name: String,
+    pub content: String,
+}
+
+/// One clipboard change from `get-timeline` and what the backend did with it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardEvent {
+    /// Unix milliseconds.
+    pub timestamp: i64,
+    pub change_count: i64,
+    /// `recorded`, `unchanged`, `paused`, `excluded`, `unreadable` or `skipped`.
+    pub outcome: String,
+    /// None when the content was never read.
+    #[serde(rename = "type")]
+    pub entry_type: Option<EntryType>,
+    pub size: u64,
+    pub latency_us: u64,
+    pub app: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    #[default]
+    Text,
+    Image,
+    File,
+    Url,
+    Color,
+}
+
+impl EntryType {
+    pub const ALL: [EntryType; 5] = [
+        EntryType::Text,
+        EntryType::Image,
+        EntryType::File,
+        EntryType::Url,
+        EntryType::Color,
+    ];
+}
+
+/// A form an entry can be put on the clipboard in, see `Command::SelectFormat`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    Rtf,
+    Html,
+    Image,
+}
+
+impl Format {
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Text => "Plain Text",
+            Format::Rtf => "Rich Text (RTF)",
+            Format::Html => "HTML",
+            Format::Image => "Image",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_name_the_entry_they_act_on() {
+        assert_eq!(Command::TogglePin { id: 3 }.entry_id(), Some(3));
+        assert_eq!(
+            Command::SetTags {
+                id: 4,
+                tags: Vec::new()
+            }
+            .entry_id(),
+            Some(4)
+        );
+        assert_eq!(Command::Clear.entry_id(), None);
+        assert_eq!(Command::RemoveEntry { id: 1 }.label(), "Remove");
+        assert_eq!(Command::RestoreEntry { id: 5 }.entry_id(), Some(5));
+        assert!(!Command::TogglePin { id: 3 }.is_retryable());
+        assert!(Command::SelectEntry { id: 3 }.is_retryable());
+    }
+
+    #[test]
+    fn backend_message_accepts_id_and_legacy_index_fields() {
+        let from_id: BackendMessage =
+            serde_json::from_str(r#"{"type":"select-success","id":42}"#).unwrap();
+        assert!(matches!(
+            from_id,
+            BackendMessage::SelectSuccess { id: Some(42) }
+        ));
+
+        let from_index: BackendMessage =
+            serde_json::from_str(r#"{"type":"remove-success","index":7}"#).unwrap();
+        assert!(matches!(from_index, BackendMessage::RemoveSuccess));
+    }
+
+    #[test]
+    fn entries_payload_parses_escaped_control_characters() {
+        let msg: BackendMessage = serde_json::from_str(
+            r#"{"type":"entries","data":[{"id":1,"content":"hello\n\b\f","timestamp":1000,"type":"text","isCurrent":true,"pinned":false,"tags":["work"]},{"id":2,"content":"again","timestamp":900,"copies":3,"sourceApp":"Safari","windowTitle":"Docs","formats":["text","html","image"]}]}"#,
+        )
+        .unwrap();
+
+        match msg {
+            BackendMessage::Entries { data, total, .. } => {
+                assert_eq!(total, None);
+                assert_eq!(data.len(), 2);
+                assert_eq!(data[0].content, "hello\n\u{0008}\u{000C}");
+                assert!(data[0].is_current);
+                assert_eq!(data[0].tags, ["work"]);
+                assert_eq!((data[0].copies, data[1].copies), (1, 3));
+                assert_eq!(data[0].source_label(), None);
+                assert_eq!(data[1].source_label().unwrap(), "Safari \u{2014} Docs");
+                assert!(data[0].formats.is_empty());
+                assert_eq!(data[1].formats, [Format::Text, Format::Html, Format::Image]);
+                assert!(data[1].is_rich());
+            }
+            _ => panic!("expected entries payload"),
+        }
+    }
+
+    #[test]
+    fn requests_serialize_as_json_rpc() {
+        let select =
+            serde_json::to_value(Request::new(7, Command::SelectEntry { id: 42 })).unwrap();
+        assert_eq!(
+            select,
+            serde_json::json!({"jsonrpc":"2.0","id":7,"method":"select-entry","params":{"id":42}})
+        );
+
+        let format = serde_json::to_value(Request::new(
+            14,
+            Command::SelectFormat {
+                id: 42,
+                format: Format::Html,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            format,
+            serde_json::json!({"jsonrpc":"2.0","id":14,"method":"select-format","params":{"id":42,"format":"html"}})
+        );
+
+        let entries = serde_json::to_value(Request::new(8, Command::GetEntries)).unwrap();
+        assert_eq!(
+            entries,
+            serde_json::json!({"jsonrpc":"2.0","id":8,"method":"get-entries"})
+        );
+
+        let page = serde_json::to_value(Request::new(
+            10,
+            Command::GetEntriesPage {
+                offset: 50,
+                limit: 50,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            page,
+            serde_json::json!({"jsonrpc":"2.0","id":10,"method":"get-entries","params":{"offset":50,"limit":50}})
+        );
+
+        let set = serde_json::to_value(Request::new(
+            9,
+            Command::SetClipboard {
+                content: "/tmp/a.zip".into(),
+                entry_type: EntryType::File,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            set,
+            serde_json::json!({"jsonrpc":"2.0","id":9,"method":"set-clipboard","params":{"content":"/tmp/a.zip","type":"file"}})
+        );
+
+        let snippet = serde_json::to_value(Request::new(
+            11,
+            Command::SaveSnippet {
+                id: None,
+                name: "sig".into(),
+                content: "--".into(),
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            snippet,
+            serde_json::json!({"jsonrpc":"2.0","id":11,"method":"save-snippet","params":{"name":"sig","content":"--"}})
+        );
+
+        let hello = serde_json::to_value(Request::new(
+            1,
+            Command::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            hello,
+            serde_json::json!({"jsonrpc":"2.0","id":1,"method":"hello","params":{"protocolVersion":PROTOCOL_VERSION}})
+        );
+
+        let search = serde_json::to_value(Request::new(
+            13,
+            Command::Search {
+                query: "#work deploy".into(),
+                types: vec![EntryType::Url],
+                limit: Some(500),
+                texts: vec![SearchText {
+                    content: "https://example.com".into(),
+                    text: "Example".into(),
+                }],
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            search,
+            serde_json::json!({"jsonrpc":"2.0","id":13,"method":"search","params":{"query":"#work deploy","types":["url"],"limit":500,"texts":[{"content":"https://example.com","text":"Example"}]}})
+        );
+
+        let import = serde_json::to_value(Request::new(
+            15,
+            Command::ImportEntries {
+                entries: vec![Entry {
+                    pinned: true,
+                    tags: vec!["work".into()],
+                    hash: "50c3".into(),
+                    ..Entry::text("ssh prod")
+                }
+                .into()],
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            import,
+            serde_json::json!({"jsonrpc":"2.0","id":15,"method":"import-entries","params":{"entries":[{"content":"ssh prod","type":"text","timestamp":0,"pinned":true,"tags":["work"]}]}})
+        );
+
+        let unlimited =
+            serde_json::to_value(Request::new(12, Command::SetLimit { limit: None })).unwrap();
+        assert_eq!(
+            unlimited,
+            serde_json::json!({"jsonrpc":"2.0","id":12,"method":"set-limit","params":{"limit":null}})
+        );
+    }
+
+    #[test]
+    fn file_entries_list_every_copied_path() {
+        let entries: Vec<Entry> = serde_json::from_str(
+            r#"[{"id":1,"content":"/tmp/a.pdf\n/tmp/b.png\n","timestamp":1,"type":"file"},{"id":2,"content":"/tmp/c.zip","timestamp":2,"type":"file"},{"id":3,"content":"a\nb","timestamp":3,"type":"text"}]"#,
+        )
+        .unwrap();
+        assert_eq!(entries[0].file_paths(), ["/tmp/a.pdf", "/tmp/b.png"]);
+        assert_eq!(entries[1].file_paths(), ["/tmp/c.zip"]);
+        assert!(entries[2].file_paths().is_empty());
+    }
+
+    #[test]
+    fn envelope_extracts_request_id_and_error_payload() {
+        let envelope: Envelope = serde_json::from_str(
+            r#"{"type":"error","code":"invalid-id","message":"Invalid id","requestId":3}"#,
+        )
+        .unwrap();
+        assert_eq!(envelope.request_id, Some(3));
+        match envelope.message {
+            BackendMessage::Error(err) => assert_eq!(err.code, "invalid-id"),
+            other => panic!("expected error, got {other:?}"),
+        }
+
+        let broadcast: Envelope = serde_json::from_str(r#"{"type":"entries","data":[]}"#).unwrap();
+        assert_eq!(broadcast.request_id, None);
+
+        let added: Envelope = serde_json::from_str(
+            r#"{"type":"entry-added","after":null,"data":{"id":7,"content":"new","timestamp":1000,"type":"text","isCurrent":true},"total":12}"#,
+        )
+        .unwrap();
+        match added.message {
+            BackendMessage::EntryAdded { after, data, total } => {
+                assert_eq!(after, None);
+                assert_eq!(data.id, 7);
+                assert_eq!(total, Some(12));
+            }
+            other => panic!("expected entry-added, got {other:?}"),
+        }
+        let updated: Envelope = serde_json::from_str(
+            r#"{"type":"entry-updated","after":7,"data":{"id":3,"content":"old","timestamp":900}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            updated.message,
+            BackendMessage::EntryUpdated { after: Some(7), .. }
+        ));
+        let removed: Envelope = serde_json::from_str(r#"{"type":"entry-removed","id":3}"#).unwrap();
+        assert!(matches!(
+            removed.message,
+            BackendMessage::EntryRemoved { id: 3, .. }
+        ));
+        let results: Envelope = serde_json::from_str(
+            r#"{"type":"search-results","requestId":6,"query":"dep","total":2,"data":[{"id":5,"content":"deploy","timestamp":800}]}"#,
+        )
+        .unwrap();
+        match results.message {
+            BackendMessage::SearchResults { data, total } => {
+                assert_eq!(data[0].id, 5);
+                assert_eq!(total, 2);
+            }
+            other => panic!("expected search-results, got {other:?}"),
+        }
+
+        let snippets: Envelope = serde_json::from_str(
+            r#"{"type":"snippets","requestId":4,"data":[{"id":1,"name":"sig","content":"--"}]}"#,
+        )
+        .unwrap();
+        match snippets.message {
+            BackendMessage::Snippets { data } => assert_eq!(data[0].name, "sig"),
+            other => panic!("expected snippets, got {other:?}"),
+        }
+
+        let trash: Envelope = serde_json::from_str(
+            r#"{"type":"trash","data":[{"id":9,"content":"old","timestamp":1700000000000,"type":"text","tags":["work"],"removedAt":1700000060000}]}"#,
+        )
+        .unwrap();
+        match trash.message {
+            BackendMessage::Trash { data } => {
+                assert_eq!(data[0].entry.id, 9);
+                assert_eq!(data[0].entry.tags, ["work"]);
+                assert_eq!(data[0].removed_at, 1_700_000_060_000);
+            }
+            other => panic!("expected trash, got {other:?}"),
+        }
+
+        let timeline: Envelope = serde_json::from_str(
+            r#"{"type":"timeline","requestId":5,"data":[{"timestamp":1700000000000,"changeCount":12,"outcome":"excluded","type":null,"size":0,"latencyUs":0,"app":"1Password"},{"timestamp":1700000001000,"changeCount":13,"outcome":"recorded","type":"text","size":5,"latencyUs":840,"app":null}]}"#,
+        )
+        .unwrap();
+        match timeline.message {
+            BackendMessage::Timeline { data } => {
+                assert_eq!(data[0].app.as_deref(), Some("1Password"));
+                assert_eq!(data[0].entry_type, None);
+                assert_eq!(data[1].entry_type, Some(EntryType::Text));
+                assert_eq!(data[1].latency_us, 840);
+            }
+            other => panic!("expected timeline, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use image::{GrayImage, Luma};
+use qrcode::{EcLevel, QrCode};
+
+use crate::{
+    protocol::{Entry, EntryType},
+    thumbnails,
+};
+
+// Pixels per module; the panel scales the image down, so this keeps the edges crisp
+const MODULE_PX: u32 = 8;
+
+/// Whether an entry can be shown as a QR code: text and URLs, including Wi-Fi
+/// `WIFI:S:…;` strings, which phone cameras offer to join.
+pub fn applies_to(entry: &Entry) -> bool {
+    matches!(entry.entry_type, EntryType::Text | EntryType::Url)
+}
+
+/// Black on white with the standard quiet zone, whatever the theme, since that is
+/// what phone cameras read best.
+fn render(content: &str) -> Result<GrayImage> {
+    let code = QrCode::with_error_correction_level(content, EcLevel::M)
+        .context("too long for a QR code")?;
+    Ok(code
+        .render::<Luma<u8>>()
+        .module_dimensions(MODULE_PX, MODULE_PX)
+        .build())
+}
+
+/// A PNG of `content` as a QR code, cached next to the thumbnails and keyed by the
+/// content, so reopening it is instant.
+pub fn image(content: &str) -> Result<PathBuf> {
+    let dir = thumbnails::cache_dir().with_file_name("qr");
+    let path = dir.join(format!(
+        "{:016x}.png",
+        thumbnails::cache_key(content.as_bytes())
+    ));
+    if path.is_file() {
+        return Ok(path);
+    }
+    let image = render(content)?;
+    std::fs::create_dir_all(&dir).context("failed to create QR cache")?;
+    image.save(&path).context("failed to save QR code")?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_modules_with_a_quiet_zone() {
+        let image = render("WIFI:S:Home;T:WPA;P:hunter2;;").unwrap();
+        assert_eq!(image.width(), image.height());
+        assert_eq!(image.width() % MODULE_PX, 0);
+        // Four light modules of quiet zone, then the dark edge of a finder pattern
+        let edge = 4 * MODULE_PX;
+        assert_eq!(image.get_pixel(edge - 1, edge - 1).0, [255]);
+        assert_eq!(image.get_pixel(edge, edge).0, [0]);
+
+        assert!(render(&"x".repeat(4_000)).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    protocol::{Entry, EntryType},
+    transforms::Transform,
+};
+
+/// Actions offered on the focused row, in order of preference. Tab steps through
+/// them, so the common ones don't need the entry menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuickAction {
+    OpenUrl,
+    Reveal,
+    TogglePin,
+    /// Copies the text with surrounding and trailing whitespace trimmed.
+    CopyTrimmed,
+    Edit,
+    Swap,
+}
+
+/// Actions shown on a row at most.
+const LIMIT: usize = 3;
+
+impl QuickAction {
+    const PREFERENCE: [QuickAction; 6] = [
+        QuickAction::OpenUrl,
+        QuickAction::Reveal,
+        QuickAction::TogglePin,
+        QuickAction::CopyTrimmed,
+        QuickAction::Edit,
+        QuickAction::Swap,
+    ];
+
+    pub fn label(self, entry: &Entry) -> &'static str {
+        match self {
+            QuickAction::OpenUrl => "Open",
+            QuickAction::Reveal => "Show in Finder",
+            QuickAction::TogglePin if entry.pinned => "Unpin",
+            QuickAction::TogglePin => "Pin",
+            QuickAction::CopyTrimmed => "Copy Trimmed",
+            QuickAction::Edit => "Edit",
+            QuickAction::Swap => "Swap",
+        }
+    }
+
+    fn applies_to(self, entry: &Entry) -> bool {
+        match self {
+            QuickAction::OpenUrl => entry.entry_type == EntryType::Url,
+            QuickAction::Reveal => matches!(entry.entry_type, EntryType::Image | EntryType::File),
+            QuickAction::TogglePin => true,
+            QuickAction::CopyTrimmed => {
+                entry.entry_type == EntryType::Text
+                    && Transform::Trim.apply(&entry.content).is_some()
+            }
+            QuickAction::Edit => matches!(entry.entry_type, EntryType::Text | EntryType::Url),
+            // The entry on the clipboard can't be swapped with itself
+            QuickAction::Swap => !entry.is_current,
+        }
+    }
+}
+
+/// The first few actions that make sense for `entry`.
+pub fn for_entry(entry: &Entry) -> Vec<QuickAction> {
+    QuickAction::PREFERENCE
+        .into_iter()
+        .filter(|action| action.applies_to(entry))
+        .take(LIMIT)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str, entry_type: EntryType, is_current: bool) -> Entry {
+        Entry {
+            id: 1,
+            content: content.into(),
+            entry_type,
+            is_current,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn picks_the_preferred_actions_that_apply() {
+        use QuickAction::*;
+
+        let url = entry("https://example.com", EntryType::Url, false);
+        assert_eq!(for_entry(&url), [OpenUrl, TogglePin, Edit]);
+        assert_eq!(
+            for_entry(&entry("  ls -la \n", EntryType::Text, false)),
+            [TogglePin, CopyTrimmed, Edit]
+        );
+        assert_eq!(
+            for_entry(&entry("ls -la", EntryType::Text, false)),
+            [TogglePin, Edit, Swap]
+        );
+        assert_eq!(
+            for_entry(&entry("/tmp/a.png", EntryType::Image, true)),
+            [Reveal, TogglePin]
+        );
+        assert_eq!(TogglePin.label(&url), "Pin");
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    image_presets::ResizePreset,
+    protocol::{Entry, EntryType},
+};
+
+/// Per-entry actions remembered so they can be repeated on the next entry with Cmd+.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryAction {
+    Resize(ResizePreset),
+    TogglePin,
+    Remove,
+    Swap,
+}
+
+impl EntryAction {
+    pub fn label(self) -> String {
+        match self {
+            EntryAction::Resize(preset) => format!("Copy at {}", preset.label()),
+            EntryAction::TogglePin => "Pin / unpin".to_string(),
+            EntryAction::Remove => "Remove".to_string(),
+            EntryAction::Swap => "Swap with clipboard".to_string(),
+        }
+    }
+
+    pub fn applies_to(self, entry: &Entry) -> bool {
+        match self {
+            EntryAction::Resize(_) => entry.entry_type == EntryType::Image,
+            EntryAction::TogglePin => true,
+            // The entry currently on the clipboard can't be removed or swapped with itself
+            EntryAction::Remove | EntryAction::Swap => !entry.is_current,
+        }
+    }
+}
+
+/// The last few actions applied, newest first and without duplicates.
+#[derive(Debug, Default)]
+pub struct RecentActions {
+    actions: Vec<EntryAction>,
+}
+
+impl RecentActions {
+    const CAPACITY: usize = 4;
+
+    pub fn record(&mut self, action: EntryAction) {
+        self.actions.retain(|&a| a != action);
+        self.actions.insert(0, action);
+        self.actions.truncate(Self::CAPACITY);
+    }
+
+    /// Recent actions that make sense for `entry`, newest first.
+    pub fn for_entry<'a>(&'a self, entry: &'a Entry) -> impl Iterator<Item = EntryAction> + 'a {
+        self.actions
+            .iter()
+            .copied()
+            .filter(move |action| action.applies_to(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_type: EntryType) -> Entry {
+        Entry {
+            id: 1,
+            entry_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn records_newest_first_without_duplicates() {
+        let mut recent = RecentActions::default();
+        recent.record(EntryAction::TogglePin);
+        recent.record(EntryAction::Resize(ResizePreset::Scale(50)));
+        recent.record(EntryAction::Remove);
+        recent.record(EntryAction::TogglePin);
+        recent.record(EntryAction::Resize(ResizePreset::MaxWidth(1280)));
+
+        let image = entry(EntryType::Image);
+        assert_eq!(
+            recent.for_entry(&image).collect::<Vec<_>>(),
+            [
+                EntryAction::Resize(ResizePreset::MaxWidth(1280)),
+                EntryAction::TogglePin,
+                EntryAction::Remove,
+                EntryAction::Resize(ResizePreset::Scale(50)),
+            ]
+        );
+        let text = entry(EntryType::Text);
+        assert_eq!(recent.for_entry(&text).next(), Some(EntryAction::TogglePin));
+
+        recent.record(EntryAction::Swap);
+        assert_eq!(recent.for_entry(&text).next(), Some(EntryAction::Swap));
+        let current = Entry {
+            is_current: true,
+            ..text
+        };
+        assert_eq!(
+            recent.for_entry(&current).next(),
+            Some(EntryAction::TogglePin)
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use regex::Regex;
+
+use crate::protocol::{Command, Entry, EntryType, SearchText};
+
+/// Most matches a backend search sends back; the newest ones win.
+pub const RESULT_LIMIT: usize = 500;
+
+/// fzf-style fuzzy matching: every query character must appear in order in the
+/// candidate (case-insensitively), and tighter, word-aligned matches score higher.
+///
+/// Returns `None` when the query doesn't match. An empty query matches everything
+/// with a score of 0. The backend's `fuzzyScore` in search.zig scores the same way, so
+/// the matches it picks for a `search` are the ones ranked best here; change both.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    const MATCH: i64 = 16;
+    const CONSECUTIVE_BONUS: i64 = 24;
+    const BOUNDARY_BONUS: i64 = 20;
+    const FIRST_CHAR_BONUS: i64 = 12;
+    const GAP_PENALTY: i64 = 2;
+
+    let mut query_chars = query.chars().flat_map(char::to_lowercase).peekable();
+    if query_chars.peek().is_none() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+
+    for (pos, ch) in candidate.chars().enumerate() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        if ch.to_lowercase().eq(std::iter::once(wanted)) {
+            score += MATCH;
+            match last_match {
+                Some(last) if last + 1 == pos => score += CONSECUTIVE_BONUS,
+                Some(last) => score -= GAP_PENALTY * (pos - last - 1).min(8) as i64,
+                None => score -= GAP_PENALTY * pos.min(8) as i64,
+            }
+            if pos == 0 {
+                score += FIRST_CHAR_BONUS;
+            }
+            if is_word_boundary(prev, ch) {
+                score += BOUNDARY_BONUS;
+            }
+            last_match = Some(pos);
+            query_chars.next();
+        }
+        prev = Some(ch);
+    }
+
+    if query_chars.peek().is_some() {
+        return None;
+    }
+    Some(score)
+}
+
+fn is_word_boundary(prev: Option<char>, ch: char) -> bool {
+    match prev {
+        None => true,
+        Some(p) => {
+            (!p.is_alphanumeric() && ch.is_alphanumeric())
+                || (p.is_lowercase() && ch.is_uppercase())
+        }
+    }
+}
+
+/// The pattern last compiled for a regex query and what came of it. The list, the
+/// trash and the snippets build their `Matcher` on every render, and the query rarely
+/// changes between them.
+static COMPILED: Mutex<Option<(String, Result<Regex, regex::Error>)>> = Mutex::new(None);
+
+fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut compiled = COMPILED.lock().unwrap();
+    if let Some((_, result)) = compiled.as_ref().filter(|(cached, _)| cached == pattern) {
+        return result.clone();
+    }
+    let result = Regex::new(pattern);
+    *compiled = Some((pattern.to_string(), result.clone()));
+    result
+}
+
+/// How a search query is applied: fuzzy by default, or as a regular expression
+/// when the query starts with `/` (e.g. `/^https?://`).
+pub enum Matcher {
+    Fuzzy(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str) -> Result<Self, regex::Error> {
+        match query.strip_prefix('/') {
+            Some(pattern) => compile(pattern).map(Matcher::Regex),
+            None => Ok(Matcher::Fuzzy(query.to_string())),
+        }
+    }
+
+    /// Indices of matching `candidates`, each a list of fields as in [`rank`]. Fuzzy
+    /// results are ranked best first; regex matches keep their original order.
+    pub fn rank<'a, F>(&self, candidates: impl IntoIterator<Item = F>) -> Vec<usize>
+    where
+        F: IntoIterator<Item = &'a str>,
+    {
+        match self {
+            Matcher::Fuzzy(query) => rank(query, candidates),
+            Matcher::Regex(re) => candidates
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, fields)| {
+                    fields
+                        .into_iter()
+                        .any(|field| re.is_match(field))
+                        .then_some(idx)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Splits `#tag` words out of a search query, returning the tags (lowercased,
+/// without `#`) and the rest of the query. Regex queries are returned untouched.
+pub fn split_tags(query: &str) -> (Vec<String>, String) {
+    if query.starts_with('/') {
+        return (Vec::new(), query.to_string());
+    }
+    let mut tags = Vec::new();
+    let mut rest = Vec::new();
+    for word in query.split(' ') {
+        match word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            Some(tag) => tags.push(tag.to_lowercase()),
+            None => rest.push(word),
+        }
+    }
+    (tags, rest.join(" ").trim().to_string())
+}
+
+/// Whether the backend can run `query` over the whole history. It has no regex
+/// engine, so `/pattern` queries look through the loaded entries instead.
+pub fn runs_in_backend(query: &str) -> bool {
+    !query.starts_with('/')
+}
+
+/// The `search` command finding what `query` and the type chips match anywhere in
+/// the history; None when nothing is filtered or the backend can't run the query.
+///
+/// `found_texts` pairs entry contents with text only the frontend knows, recognized in
+/// images or page titles; the ones the query matches go along so the backend can
+/// match those entries too.
+pub fn backend_search<'a>(
+    query: &str,
+    types: &HashSet<EntryType>,
+    found_texts: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<Command> {
+    if (query.trim().is_empty() && types.is_empty()) || !runs_in_backend(query) {
+        return None;
+    }
+    let (_, text) = split_tags(query);
+    let texts = if text.is_empty() {
+        Vec::new()
+    } else {
+        found_texts
+            .into_iter()
+            .filter(|(_, found)| fuzzy_score(&text, found).is_some())
+            .map(|(content, found)| SearchText {
+                content: content.to_string(),
+                text: found.to_string(),
+            })
+            .collect()
+    };
+    Some(Command::Search {
+        query: query.to_string(),
+        types: EntryType::ALL
+            .into_iter()
+            .filter(|t| types.contains(t))
+            .collect(),
+        limit: Some(RESULT_LIMIT),
+        texts,
+    })
+}
+
+/// What a search matches an entry on, each field on its own: its content, any text
+/// found in it (see [`backend_search`]), and the app and window it was copied from.
+/// The backend's search.zig looks at the same fields.
+pub fn fields<'a>(entry: &'a Entry, found_text: Option<&'a str>) -> [&'a str; 4] {
+    [
+        &entry.content,
+        found_text.unwrap_or_default(),
+        &entry.source_app,
+        &entry.window_title,
+    ]
+}
+
+/// A backend search's answer: the newest matches, of `total` in the history.
+pub struct Found {
+    pub entries: Vec<Entry>,
+    pub total: usize,
+}
+
+/// What a search ranks: the loaded entries, whose recognized text and page titles are
+/// known, then those the backend found further back.
+pub fn with_found<'a>(loaded: &'a [Entry], found: &'a [Entry]) -> Vec<&'a Entry> {
+    let loaded_ids: HashSet<u64> = loaded.iter().map(|e| e.id).collect();
+    loaded
+        .iter()
+        .chain(found.iter().filter(|e| !loaded_ids.contains(&e.id)))
+        .collect()
+}
+
+/// Indices of `candidates` matching `query`, best match first. Each candidate is a
+/// list of fields matched on their own, so a match never runs from one into the next,
+/// and its best field counts. Ties keep their original order, so an empty query
+/// returns every index unchanged.
+pub fn rank<'a, F>(query: &str, candidates: impl IntoIterator<Item = F>) -> Vec<usize>
+where
+    F: IntoIterator<Item = &'a str>,
+{
+    let mut scored: Vec<(usize, i64)> = candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, fields)| {
+            fields
+                .into_iter()
+                .filter_map(|field| fuzzy_score(query, field))
+                .max()
+                .map(|score| (idx, score))
+        })
+        .collect();
+    scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(idx, _)| idx).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_case_insensitively() {
+        assert!(fuzzy_score("gtcl", "git clone").is_some());
+        assert!(fuzzy_score("GTCL", "git clone").is_some());
+        assert!(fuzzy_score("", "anything").is_some());
+        assert!(fuzzy_score("gtcl", "clone git").is_none());
+        // search.zig's test of fuzzyScore expects the same
+        assert_eq!(fuzzy_score("gtcl", "git clone"), Some(136));
+    }
+
+    #[test]
+    fn ranks_tighter_matches_first() {
+        let candidates = ["get the cold lemonade", "cat notes.txt", "git clone repo"].map(|c| [c]);
+        assert_eq!(rank("gtcl", candidates), [2, 0]);
+        assert_eq!(rank("", candidates), [0, 1, 2]);
+    }
+
+    #[test]
+    fn fields_match_on_their_own() {
+        let entry = Entry {
+            source_app: "Terminal".into(),
+            window_title: "clone".into(),
+            ..Entry::text("git")
+        };
+        assert_eq!(rank("gtcl", [fields(&entry, None)]), Vec::<usize>::new());
+        assert_eq!(rank("clone", [fields(&entry, None)]), [0]);
+        assert_eq!(rank("invoice", [fields(&entry, Some("Invoice #42"))]), [0]);
+    }
+
+    #[test]
+    fn slash_prefix_switches_to_regex() {
+        let candidates = ["https://example.com", "notes", "http://localhost"].map(|c| [c]);
+        let matcher = Matcher::new("/^https?://").unwrap();
+        assert_eq!(matcher.rank(candidates), [0, 2]);
+        assert!(Matcher::new("/(unclosed").is_err());
+        // The cached error belongs to that pattern only, and comes back for it
+        assert!(Matcher::new("/(closed)").is_ok());
+        assert!(Matcher::new("/(unclosed").is_err());
+        let again = Matcher::new("/^https?://").unwrap();
+        assert_eq!(again.rank(candidates), [0, 2]);
+        assert!(matches!(Matcher::new("(unclosed"), Ok(Matcher::Fuzzy(_))));
+    }
+
+    #[test]
+    fn hash_words_become_tag_filters() {
+        assert_eq!(
+            split_tags("#Work deploy #api"),
+            (
+                vec!["work".to_string(), "api".to_string()],
+                "deploy".to_string()
+            )
+        );
+        assert_eq!(split_tags("# c#"), (Vec::new(), "# c#".to_string()));
+        assert_eq!(split_tags("/#\\d+"), (Vec::new(), "/#\\d+".to_string()));
+    }
+
+    #[test]
+    fn only_fuzzy_searches_and_type_chips_go_to_the_backend() {
+        let none = HashSet::new();
+        let urls = HashSet::from([EntryType::Url]);
+        assert_eq!(backend_search(" ", &none, []), None);
+        assert_eq!(backend_search("/^https", &urls, []), None);
+        assert_eq!(
+            backend_search("", &urls, [("/tmp/a.png", "Invoice")]),
+            Some(Command::Search {
+                query: String::new(),
+                types: vec![EntryType::Url],
+                limit: Some(RESULT_LIMIT),
+                texts: Vec::new(),
+            })
+        );
+        assert!(backend_search("#work", &none, []).is_some());
+
+        // Only found text the query matches is sent along
+        let found = [("/tmp/a.png", "Invoice #42"), ("/tmp/b.png", "Receipt")];
+        let Some(Command::Search { texts, .. }) = backend_search("#work inv", &none, found) else {
+            panic!("expected a search");
+        };
+        assert_eq!(
+            texts,
+            [SearchText {
+                content: "/tmp/a.png".into(),
+                text: "Invoice #42".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn found_entries_follow_the_loaded_ones() {
+        let entry = |id: u64, content: &str| Entry {
+            id,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let loaded = [entry(9, "new"), entry(8, "loaded")];
+        // The backend's copy of a loaded entry may be older
+        let found = [entry(8, "stale"), entry(2, "old")];
+        let candidates = with_found(&loaded, &found);
+        let contents: Vec<_> = candidates.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, ["new", "loaded", "old"]);
+    }
+}
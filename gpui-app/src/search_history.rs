@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+/// Searches that ended in a copy, newest first, offered again while the query is empty.
+///
+/// Kept in `searches.json` next to the config file, or only while the popover is open
+/// when the history is encrypted.
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+    in_memory: bool,
+}
+
+impl SearchHistory {
+    const CAPACITY: usize = 8;
+
+    fn path() -> Option<PathBuf> {
+        Config::path().map(|config| config.with_file_name("searches.json"))
+    }
+
+    /// `in_memory` is on when the history is encrypted, since searches give away what
+    /// was copied; any file left from before is removed.
+    pub fn load(in_memory: bool) -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if in_memory {
+            let _ = std::fs::remove_file(&path);
+            return Self {
+                queries: Vec::new(),
+                in_memory,
+            };
+        }
+        let queries = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", path.display(), e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Self { queries, in_memory }
+    }
+
+    fn save(&self) -> Result<()> {
+        if self.in_memory {
+            return Ok(());
+        }
+        let path = Self::path().context("no config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context("failed to create config directory")?;
+        }
+        let text = serde_json::to_string(&self.queries)?;
+        std::fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn queries(&self) -> &[String] {
+        &self.queries
+    }
+
+    /// Moves `query` to the front. Single characters aren't worth suggesting.
+    fn push(&mut self, query: &str) -> bool {
+        let query = query.trim();
+        if query.chars().count() < 2 || self.queries.first().is_some_and(|q| q == query) {
+            return false;
+        }
+        self.queries.retain(|q| q != query);
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(Self::CAPACITY);
+        true
+    }
+
+    pub fn record(&mut self, query: &str) {
+        if self.push(query) {
+            if let Err(e) = self.save() {
+                eprintln!("Failed to save searches: {:#}", e);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.queries.len() {
+            self.queries.remove(index);
+            if let Err(e) = self.save() {
+                eprintln!("Failed to save searches: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_first_without_repeats() {
+        let mut history = SearchHistory::default();
+        assert!(history.push("invoice"));
+        assert!(history.push(" token "));
+        assert!(!history.push("token"));
+        assert!(!history.push("x"));
+        assert!(history.push("invoice"));
+        assert_eq!(history.queries(), ["invoice", "token"]);
+
+        for i in 0..10 {
+            history.push(&format!("query {}", i));
+        }
+        assert_eq!(history.queries().len(), SearchHistory::CAPACITY);
+        assert_eq!(history.queries()[0], "query 9");
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fs::File,
+    io::{BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    dump::{self, DumpFormat},
+    loading,
+    protocol::{BackendMessage, Entry, Envelope},
+};
+
+static RECORDING: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Which way a recorded line went. Lines are written as `> {json}` for commands to the
+/// backend and `< {json}` for everything it sent back; anything else the backend
+/// printed, like log output, is kept as `# {line}` and skipped on replay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+    Other,
+}
+
+impl Direction {
+    fn prefix(self) -> &'static str {
+        match self {
+            Direction::Sent => "> ",
+            Direction::Received => "< ",
+            Direction::Other => "# ",
+        }
+    }
+}
+
+/// Value of `--name <value>` or `--name=<value>`.
+fn flag_value(args: &[String], name: &str) -> Result<Option<String>> {
+    let with_equals = format!("{}=", name);
+    for (i, arg) in args.iter().enumerate() {
+        if let Some(value) = arg.strip_prefix(&with_equals) {
+            return Ok(Some(value.to_string()));
+        }
+        if arg == name {
+            return match args.get(i + 1) {
+                Some(value) => Ok(Some(value.clone())),
+                None => bail!("{} needs a file", name),
+            };
+        }
+    }
+    Ok(None)
+}
+
+/// Starts copying the protocol to the file given with `--record`, if any.
+pub fn start_recording_from_args(args: &[String]) -> Result<()> {
+    let Some(path) = flag_value(args, "--record")? else {
+        return Ok(());
+    };
+    let file = File::create(&path).with_context(|| format!("failed to create {}", path))?;
+    let _ = RECORDING.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Appends a protocol line to the recording; does nothing unless `--record` was given.
+pub fn record(direction: Direction, line: &str) {
+    let Some(file) = RECORDING.get() else {
+        return;
+    };
+    let mut file = file.lock().unwrap();
+    let _ = writeln!(file, "{}{}", direction.prefix(), line.trim_end());
+}
+
+/// `--replay <file>`, optionally with `--interactive`.
+pub struct ReplayArgs {
+    path: PathBuf,
+    interactive: bool,
+}
+
+impl ReplayArgs {
+    /// `None` when `--replay` isn't given, so the app starts normally.
+    pub fn from_args(args: &[String]) -> Result<Option<Self>> {
+        Ok(flag_value(args, "--replay")?.map(|path| Self {
+            path: PathBuf::from(path),
+            interactive: args.iter().any(|arg| arg == "--interactive"),
+        }))
+    }
+}
+
+/// One recorded line as seen during a replay, with the list as it stood afterwards.
+pub struct ReplayStep<'a> {
+    pub line_number: usize,
+    pub direction: Direction,
+    pub line: &'a str,
+    /// The message, for received lines.
+    pub message: Option<&'a BackendMessage>,
+    pub entries: &'a [Entry],
+    /// Whether this line changed the list.
+    pub changed: bool,
+}
+
+/// Feeds the backend's side of a recorded session through the same entry handling
+/// as the app, without a backend or a window, and returns the final list.
+///
+/// Pages and changes are folded in like the app does, so the list is the part of the
+/// history it had loaded. Frecency ordering is not applied, since it depends on
+/// usage stored outside the session.
+pub fn replay(session: &str, mut on_step: impl FnMut(&ReplayStep)) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for (i, raw) in session.lines().enumerate() {
+        let line_number = i + 1;
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let (mut direction, line) = if let Some(line) = raw.strip_prefix("> ") {
+            (Direction::Sent, line)
+        } else if let Some(line) = raw.strip_prefix("< ") {
+            (Direction::Received, line)
+        } else {
+            // Output that isn't protocol, untagged in recordings made before `# `
+            (Direction::Other, raw.strip_prefix("# ").unwrap_or(raw))
+        };
+        let envelope = match direction {
+            Direction::Received => serde_json::from_str::<Envelope>(line).ok(),
+            _ => None,
+        };
+        if direction == Direction::Received && envelope.is_none() {
+            direction = Direction::Other;
+        }
+
+        let mut changed = false;
+        let message = match envelope {
+            None => None,
+            Some(envelope) => {
+                match &envelope.message {
+                    BackendMessage::Entries { data, offset, .. } => {
+                        let before = entries.clone();
+                        if loading::receive(&mut entries, data.clone(), *offset) {
+                            changed = entries != before;
+                        }
+                    }
+                    BackendMessage::EntryAdded { after, data, .. }
+                    | BackendMessage::EntryUpdated { after, data, .. } => {
+                        loading::place(&mut entries, *after, data.clone());
+                        changed = true;
+                    }
+                    BackendMessage::EntryRemoved { id, .. } => {
+                        entries.retain(|e| e.id != *id);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+                Some(envelope.message)
+            }
+        };
+
+        on_step(&ReplayStep {
+            line_number,
+            direction,
+            line,
+            message: message.as_ref(),
+            entries: &entries,
+            changed,
+        });
+    }
+    Ok(entries)
+}
+
+/// Replays a session file and exits. By default the final list is printed as JSON,
+/// ready to diff against an expected snapshot; `--interactive` walks through it one
+/// line at a time, showing the list whenever it changes.
+pub fn run(args: ReplayArgs) -> ! {
+    let code = match run_replay(&args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("clipz: {:#}", e);
+            1
+        }
+    };
+    std::process::exit(code)
+}
+
+fn run_replay(args: &ReplayArgs) -> Result<()> {
+    let session = read_session(&args.path)?;
+    let mut out = std::io::stdout().lock();
+    if !args.interactive {
+        let entries = replay(&session, |_| {})?;
+        return dump::write_entries(&mut out, &entries, DumpFormat::Json);
+    }
+
+    let stdin = std::io::stdin();
+    let pause = stdin.is_terminal();
+    let mut input = String::new();
+    replay(&session, |step| {
+        let arrow = step.direction.prefix();
+        let _ = writeln!(out, "{:>4} {}{}", step.line_number, arrow, summarize(step));
+        if step.changed {
+            for entry in step.entries {
+                let _ = writeln!(out, "       {}", describe(entry));
+            }
+        }
+        if pause {
+            let _ = out.flush();
+            input.clear();
+            let _ = stdin.lock().read_line(&mut input);
+        }
+    })?;
+    Ok(())
+}
+
+fn read_session(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn summarize(step: &ReplayStep) -> String {
+    match step.message {
+        Some(BackendMessage::Entries { data, total, .. }) => match total {
+            Some(total) if *total != data.len() => {
+                format!("entries ({} of {})", data.len(), total)
+            }
+            _ => format!("entries ({})", data.len()),
+        },
+        Some(message) => format!("{:?}", message),
+        None => truncate(step.line, 100),
+    }
+}
+
+fn describe(entry: &Entry) -> String {
+    format!(
+        "{}{:>5}{} {}",
+        if entry.is_current { ">" } else { " " },
+        entry.id,
+        if entry.pinned { "*" } else { " " },
+        truncate(&entry.content.replace('\n', "\u{21b5}"), 60)
+    )
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}\u{2026}", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_rebuilds_the_list_the_app_showed() {
+        let session = r#"
+> {"jsonrpc":"2.0","id":1,"method":"get-entries","params":{"offset":0,"limit":2}}
+< {"type":"ready"}
+< {"type":"entries","requestId":1,"total":3,"offset":0,"data":[{"id":3,"content":"c","timestamp":3000,"type":"text","isCurrent":true,"pinned":false,"tags":[]},{"id":2,"content":"b","timestamp":2000,"type":"text","isCurrent":false,"pinned":true,"tags":[]}]}
+< {"type":"entries","total":3,"offset":5,"data":[{"id":9,"content":"stale","timestamp":0,"type":"text"}]}
+> {"jsonrpc":"2.0","id":2,"method":"get-entries","params":{"offset":2,"limit":2}}
+< {"type":"entries","requestId":2,"total":3,"offset":2,"data":[{"id":1,"content":"a","timestamp":1000,"type":"text","isCurrent":false,"pinned":false,"tags":[]}]}
+> {"jsonrpc":"2.0","id":3,"method":"remove-entry","params":{"id":2}}
+< {"type":"error","code":"invalid-id","message":"Invalid id","requestId":3}
+< {"type":"entry-added","after":null,"data":{"id":4,"content":"d","timestamp":4000,"type":"text","isCurrent":true}}
+< {"type":"entry-updated","after":4,"data":{"id":3,"content":"c","timestamp":3000,"type":"text","isCurrent":false}}
+< {"type":"entry-removed","id":1}
+"#;
+        let mut changes = Vec::new();
+        let entries = replay(session, |step| {
+            if step.changed {
+                changes.push((step.line_number, step.entries.len()));
+            }
+        })
+        .unwrap();
+
+        let ids: Vec<u64> = entries.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, [4, 3, 2]);
+        assert!(!entries[1].is_current);
+        assert!(entries[2].pinned);
+        assert_eq!(changes, [(4, 2), (7, 3), (10, 4), (11, 4), (12, 3)]);
+
+        let mut skipped = Vec::new();
+        let entries = replay(
+            "oops\n# debug: polling\n< not json\n< {\"type\":\"entry-removed\",\"id\":1}",
+            |step| {
+                if step.direction == Direction::Other {
+                    skipped.push(step.line.to_string());
+                }
+            },
+        )
+        .unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(skipped, ["oops", "debug: polling", "not json"]);
+    }
+
+    #[test]
+    fn flags_take_a_following_or_attached_value() {
+        let args: Vec<String> = ["--replay", "s.log", "--interactive"]
+            .map(String::from)
+            .to_vec();
+        let replay = ReplayArgs::from_args(&args).unwrap().unwrap();
+        assert_eq!(replay.path, Path::new("s.log"));
+        assert!(replay.interactive);
+
+        let args = vec!["--record=/tmp/s.log".to_string()];
+        assert_eq!(
+            flag_value(&args, "--record").unwrap().as_deref(),
+            Some("/tmp/s.log")
+        );
+        assert!(flag_value(&["--record".to_string()], "--record").is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+use crate::protocol::{Entry, EntryType};
+
+// How long a snapshot is kept for the app it was pasted into to pick it up
+const KEEP_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+// Only these links are made clickable; anything else, like `javascript:`, stays text
+const LINK_SCHEMES: &[&str] = &["http://", "https://", "mailto:"];
+
+/// Directory under the system temp dir where generated snapshots are written.
+fn snapshot_dir() -> PathBuf {
+    std::env::temp_dir().join("clipz_snapshots")
+}
+
+/// Deletes snapshots written more than [`KEEP_FOR`] ago.
+fn remove_old(dir: &Path) {
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for file in files.flatten() {
+        let age = file
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age > KEEP_FOR) {
+            let _ = std::fs::remove_file(file.path());
+        }
+    }
+}
+
+/// Writes `entries` to a single self-contained HTML file and returns its path.
+///
+/// Text is inlined and images are embedded as data URIs, so the file can be sent to
+/// someone who doesn't use Clipz and opened in any browser. Other files are listed by
+/// name only.
+pub fn export_html(entries: &[Entry]) -> Result<PathBuf> {
+    if entries.is_empty() {
+        bail!("nothing to export");
+    }
+
+    let dir = snapshot_dir();
+    std::fs::create_dir_all(&dir).context("failed to create snapshot directory")?;
+    remove_old(&dir);
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let dest = dir.join(format!("Clipz-{}.html", stamp));
+    std::fs::write(&dest, render(entries))
+        .with_context(|| format!("failed to write {}", dest.display()))?;
+
+    Ok(dest)
+}
+
+const STYLE: &str = "body{font:14px -apple-system,sans-serif;max-width:760px;margin:2em auto;\
+padding:0 1em;color:#1d1a15;background:#f7f4ee}\
+article{background:#fff;border-radius:8px;padding:12px 16px;margin:12px 0;\
+box-shadow:0 1px 3px #0002}\
+time{display:block;font-size:11px;color:#8c8374;margin-bottom:6px}\
+pre{white-space:pre-wrap;word-break:break-word;margin:0;font:13px ui-monospace,monospace}\
+img{max-width:100%;border-radius:4px}";
+
+fn render(entries: &[Entry]) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(
+        html,
+        "<title>Clipz snapshot</title>\n<style>{}</style>",
+        STYLE
+    );
+    html.push_str("</head>\n<body>\n<h1>Clipz snapshot</h1>\n");
+    for entry in entries {
+        let _ = writeln!(
+            html,
+            "<article>\n<time data-ms=\"{}\">{}</time>",
+            entry.timestamp,
+            crate::type_label_for_type(&entry.entry_type)
+        );
+        html.push_str(&render_body(entry));
+        html.push_str("\n</article>\n");
+    }
+    // Timestamps are formatted by the reader's browser, in their own time zone
+    html.push_str(
+        "<script>for(const t of document.querySelectorAll('time[data-ms]'))\
+t.textContent+=' \u{00b7} '+new Date(+t.dataset.ms).toLocaleString()</script>\n",
+    );
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_body(entry: &Entry) -> String {
+    let path = Path::new(&entry.content);
+    match entry.entry_type {
+        EntryType::Image => match std::fs::read(path) {
+            Ok(bytes) => format!(
+                "<img alt=\"{}\" src=\"data:{};base64,{}\">",
+                escape(&file_name(path)),
+                image_mime(path),
+                STANDARD.encode(&bytes)
+            ),
+            Err(_) => format!("<p><em>{} (missing)</em></p>", escape(&file_name(path))),
+        },
+        EntryType::File => entry
+            .file_paths()
+            .into_iter()
+            .map(|path| format!("<p>{}</p>", escape(&file_name(Path::new(path)))))
+            .collect(),
+        EntryType::Url => {
+            let url = entry.content.trim();
+            let linkable = LINK_SCHEMES.iter().any(|scheme| {
+                url.get(..scheme.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(scheme))
+            });
+            let url = escape(url);
+            if linkable {
+                format!("<p><a href=\"{}\">{}</a></p>", url, url)
+            } else {
+                format!("<p>{}</p>", url)
+            }
+        }
+        _ => format!("<pre>{}</pre>", escape(&entry.content)),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned())
+}
+
+fn image_mime(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "tif" | "tiff" => "image/tiff",
+        "heic" => "image/heic",
+        _ => "image/png",
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_escaped_and_images_are_inlined() {
+        let text = Entry {
+            id: 1,
+            content: "<b>\"a\" & 'b'</b>".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_body(&text),
+            "<pre>&lt;b&gt;&quot;a&quot; &amp; &#39;b&#39;&lt;/b&gt;</pre>"
+        );
+
+        let path = std::env::temp_dir().join("clipz-snapshot-test.gif");
+        std::fs::write(&path, b"GIF").unwrap();
+        let image = Entry {
+            content: path.to_string_lossy().into_owned(),
+            entry_type: EntryType::Image,
+            ..text
+        };
+        let body = render_body(&image);
+        let _ = std::fs::remove_file(&path);
+        assert!(body.ends_with("src=\"data:image/gif;base64,R0lG\">"));
+    }
+
+    #[test]
+    fn only_web_and_mail_links_are_clickable() {
+        let url = |content: &str| {
+            render_body(&Entry {
+                entry_type: EntryType::Url,
+                ..Entry::text(content)
+            })
+        };
+        assert_eq!(
+            url("HTTPS://example.com"),
+            "<p><a href=\"HTTPS://example.com\">HTTPS://example.com</a></p>"
+        );
+        assert!(url("mailto:jane@example.com").contains("href"));
+        assert_eq!(url("javascript:alert(1)"), "<p>javascript:alert(1)</p>");
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Mutex;
+
+use crate::{protocol::Snippet, search};
+
+/// Longest name suggested from an entry's first line.
+const SUGGESTED_NAME_LEN: usize = 24;
+
+/// Snippets as the backend last sent them, in the order they were created. The first
+/// nine answer to the `[hotkey] snippets` chords.
+pub static SNIPPETS: Mutex<Vec<Snippet>> = Mutex::new(Vec::new());
+
+/// Prompt for naming a snippet saved from an entry through its context menu.
+///
+/// While it is open, typing edits the name instead of the search query.
+pub struct SnippetPrompt {
+    pub content: String,
+    pub name: String,
+}
+
+impl SnippetPrompt {
+    /// Suggests the entry's first non-blank line, shortened, as the name.
+    pub fn new(content: &str) -> Self {
+        let first_line = content.lines().map(str::trim).find(|l| !l.is_empty());
+        Self {
+            content: content.to_string(),
+            name: first_line
+                .unwrap_or_default()
+                .chars()
+                .take(SUGGESTED_NAME_LEN)
+                .collect(),
+        }
+    }
+}
+
+/// Snippets whose name or content matches `query` the way the search field matches
+/// entries: fuzzy and best first, or by regex for `/pattern`. An empty query keeps them
+/// all, in order; an invalid pattern matches nothing.
+pub fn matching(snippets: &[Snippet], query: &str) -> Vec<Snippet> {
+    let Ok(matcher) = search::Matcher::new(query) else {
+        return Vec::new();
+    };
+    let haystacks: Vec<String> = snippets
+        .iter()
+        .map(|s| format!("{}\n{}", s.name, s.content))
+        .collect();
+    matcher
+        .rank(haystacks.iter().map(|text| [text.as_str()]))
+        .into_iter()
+        .map(|idx| snippets[idx].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(id: u64, name: &str, content: &str) -> Snippet {
+        Snippet {
+            id,
+            name: name.into(),
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn snippets_match_by_name_or_content() {
+        let snippets = [
+            snippet(1, "sig", "--\nDuarte"),
+            snippet(2, "reply", "Thanks, looking into it"),
+        ];
+        let ids = |query| -> Vec<u64> {
+            matching(&snippets, query)
+                .iter()
+                .map(|snippet| snippet.id)
+                .collect()
+        };
+        assert_eq!(ids(""), [1, 2]);
+        assert_eq!(ids("reply"), [2]);
+        assert_eq!(ids("duarte"), [1]);
+        assert!(ids("/^Thanks").is_empty());
+        assert_eq!(ids("/(?m)^Thanks"), [2]);
+
+        let prompt = SnippetPrompt::new("\n  fn main() { println!(\"hello, world\") }\n");
+        assert_eq!(prompt.name, "fn main() { println!(\"he");
+    }
+}
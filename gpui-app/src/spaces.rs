@@ -0,0 +1,130 @@
+use std::sync::atomic::AtomicBool;
+
+use crate::config::SpaceBehavior;
+
+/// Set when the user switches Spaces; the event loop re-applies the popover's behavior.
+pub static SPACE_CHANGED: AtomicBool = AtomicBool::new(false);
+
+// NSWindowCollectionBehavior flags
+const CAN_JOIN_ALL_SPACES: u64 = 1 << 0;
+const MOVE_TO_ACTIVE_SPACE: u64 = 1 << 1;
+const MANAGED: u64 = 1 << 2;
+const FULL_SCREEN_AUXILIARY: u64 = 1 << 8;
+
+/// The collection behavior for `behavior`. Every mode can show over full-screen apps,
+/// as the popover always could.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn collection_behavior(behavior: SpaceBehavior) -> u64 {
+    let spaces = match behavior {
+        SpaceBehavior::ActiveSpace => MOVE_TO_ACTIVE_SPACE,
+        SpaceBehavior::AllSpaces => CAN_JOIN_ALL_SPACES,
+        SpaceBehavior::AssignedSpace => MANAGED,
+    };
+    spaces | FULL_SCREEN_AUXILIARY
+}
+
+/// Applies `behavior` to the native window behind `window`, replacing the one gpui
+/// gives pop-up windows (every Space). With `follow`, an active-Space window is also
+/// brought to the Space now in use.
+#[cfg(target_os = "macos")]
+pub fn apply(window: &gpui::Window, behavior: SpaceBehavior, follow: bool) {
+    macos::apply(
+        window,
+        collection_behavior(behavior),
+        follow && behavior == SpaceBehavior::ActiveSpace,
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn apply(_window: &gpui::Window, _behavior: SpaceBehavior, _follow: bool) {}
+
+/// Starts listening for Space switches, which set `SPACE_CHANGED`.
+#[cfg(target_os = "macos")]
+pub fn watch() {
+    macos::watch()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn watch() {}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use cocoa::{
+        base::{id, nil},
+        foundation::NSString,
+    };
+    use objc::{
+        class,
+        declare::ClassDecl,
+        msg_send,
+        runtime::{Object, Sel},
+        sel, sel_impl,
+    };
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    use super::SPACE_CHANGED;
+
+    pub fn apply(window: &gpui::Window, behavior: u64, follow: bool) {
+        let Ok(handle) = window.window_handle() else {
+            return;
+        };
+        let RawWindowHandle::AppKit(handle) = handle.as_raw() else {
+            return;
+        };
+        unsafe {
+            let view = handle.ns_view.as_ptr() as id;
+            let native: id = msg_send![view, window];
+            if native == nil {
+                return;
+            }
+            let _: () = msg_send![native, setCollectionBehavior: behavior];
+            if follow {
+                let _: () = msg_send![native, orderFrontRegardless];
+            }
+        }
+    }
+
+    extern "C" fn space_changed(_this: &Object, _cmd: Sel, _notification: id) {
+        crate::wake::set(&SPACE_CHANGED);
+    }
+
+    pub fn watch() {
+        unsafe {
+            let mut decl = ClassDecl::new("SpaceObserver", class!(NSObject)).unwrap();
+            decl.add_method(
+                sel!(spaceChanged:),
+                space_changed as extern "C" fn(&Object, Sel, id),
+            );
+            let observer: id = msg_send![decl.register(), new];
+
+            // Space switches are posted on the workspace's own notification center
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let center: id = msg_send![workspace, notificationCenter];
+            let name = NSString::alloc(nil).init_str("NSWorkspaceActiveSpaceDidChangeNotification");
+            let _: () = msg_send![center, addObserver: observer selector: sel!(spaceChanged:) name: name object: nil];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_mode_sets_one_spaces_flag() {
+        let flags = [CAN_JOIN_ALL_SPACES, MOVE_TO_ACTIVE_SPACE, MANAGED];
+        for behavior in [
+            SpaceBehavior::ActiveSpace,
+            SpaceBehavior::AllSpaces,
+            SpaceBehavior::AssignedSpace,
+        ] {
+            let mask = collection_behavior(behavior);
+            assert_eq!(flags.iter().filter(|flag| mask & **flag != 0).count(), 1);
+            assert_ne!(mask & FULL_SCREEN_AUXILIARY, 0);
+        }
+        assert_eq!(
+            collection_behavior(SpaceBehavior::AllSpaces),
+            CAN_JOIN_ALL_SPACES | FULL_SCREEN_AUXILIARY
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::protocol::{Entry, EntryType};
+
+/// Prompt for splitting a text entry into one entry per piece, opened with Cmd+L.
+///
+/// While it is open, typing edits the delimiter instead of the search query. An empty
+/// delimiter splits on line breaks.
+pub struct SplitPrompt {
+    pub content: String,
+    pub delimiter: String,
+}
+
+impl SplitPrompt {
+    /// `None` for images, files and colors, which have nothing to split.
+    pub fn new(entry: &Entry) -> Option<Self> {
+        matches!(entry.entry_type, EntryType::Text | EntryType::Url).then(|| Self {
+            content: entry.content.clone(),
+            delimiter: String::new(),
+        })
+    }
+
+    pub fn parts(&self) -> Vec<String> {
+        split_parts(&self.content, &self.delimiter)
+    }
+
+    /// Whether every piece fits in the history, which takes `room` more entries before
+    /// its limit (None when unlimited). A split never pushes older entries out, so one
+    /// that doesn't fit is refused.
+    pub fn fits(&self, room: Option<usize>) -> bool {
+        room.is_none_or(|room| self.parts().len() <= room)
+    }
+}
+
+/// Pieces of `text` between `delimiter`s, or between line breaks when it is empty.
+///
+/// `\t` and `\n` in the delimiter stand for a tab and a line break. Pieces are
+/// trimmed and empty ones dropped, so trailing newlines don't produce blank entries.
+pub fn split_parts(text: &str, delimiter: &str) -> Vec<String> {
+    let delimiter = delimiter.replace("\\t", "\t").replace("\\n", "\n");
+    let pieces: Vec<&str> = if delimiter.is_empty() {
+        text.lines().collect()
+    } else {
+        text.split(delimiter.as_str()).collect()
+    };
+    pieces
+        .into_iter()
+        .map(str::trim)
+        .filter(|piece| !piece.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_lines_or_a_custom_delimiter() {
+        assert_eq!(
+            split_parts("a-1\r\n\n  b-2 \nc-3\n", ""),
+            ["a-1", "b-2", "c-3"]
+        );
+        assert_eq!(split_parts("x, y,,z", ","), ["x", "y", "z"]);
+        assert_eq!(split_parts("id\tname\tage", "\\t"), ["id", "name", "age"]);
+        assert!(split_parts(" \n ", "").is_empty());
+    }
+
+    #[test]
+    fn a_split_fits_only_without_evicting() {
+        let prompt = SplitPrompt::new(&Entry::text("a\nb\nc")).unwrap();
+        assert!(prompt.fits(None));
+        assert!(prompt.fits(Some(3)));
+        assert!(!prompt.fits(Some(2)));
+    }
+}
@@ -0,0 +1,400 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Mutex,
+};
+
+use crate::{
+    protocol::{Entry, EntryType},
+    sensitive,
+};
+
+/// Recent entries listed in the menu.
+const MENU_ENTRY_COUNT: usize = 10;
+const MENU_LABEL_CHARS: usize = 40;
+/// Offered in the History Size submenu; 0 keeps every entry, as in `[history] max_entries`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const HISTORY_SIZES: [usize; 4] = [10, 100, 500, 0];
+/// Offered in the Keep History submenu; 0 keeps entries forever, as in
+/// `[history] retention_days`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+const RETENTION_DAYS: [usize; 4] = [1, 7, 30, 0];
+
+/// Something chosen from the status item's menu, handled on the event loop's next pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum MenuAction {
+    Show,
+    Select(u64),
+    TogglePause,
+    ToggleAppend,
+    ClearAccessLog,
+    ExportTimeline,
+    /// A new `[history] max_entries`; 0 for unlimited.
+    SetHistoryLimit(usize),
+    /// A new `[history] retention_days`; 0 keeps entries forever.
+    SetRetention(u64),
+    ToggleLaunchAtLogin,
+    Quit,
+}
+
+pub static PENDING_MENU_ACTIONS: Mutex<Vec<MenuAction>> = Mutex::new(Vec::new());
+
+// The menu is built inside an AppKit callback, so it reads from copies kept here
+// rather than from the app state.
+static RECENT: Mutex<Vec<(u64, String)>> = Mutex::new(Vec::new());
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static APPENDING: AtomicBool = AtomicBool::new(false);
+static HISTORY_LIMIT: AtomicUsize = AtomicUsize::new(0);
+static RETENTION: AtomicUsize = AtomicUsize::new(0);
+static LAUNCH_AT_LOGIN: AtomicBool = AtomicBool::new(false);
+
+/// Refreshes the entries listed in the menu; call whenever the history changes.
+/// `mask_sensitive` hides likely secrets the same way the popover does.
+pub fn set_recent(entries: &[Entry], mask_sensitive: bool) {
+    *RECENT.lock().unwrap() = entries
+        .iter()
+        .take(MENU_ENTRY_COUNT)
+        .map(|e| match sensitive::detect(e).filter(|_| mask_sensitive) {
+            Some(kind) => (e.id, kind.masked()),
+            None => (e.id, menu_label(e)),
+        })
+        .collect();
+}
+
+pub fn set_paused(paused: bool) {
+    PAUSED.store(paused, Ordering::SeqCst);
+}
+
+pub fn set_appending(appending: bool) {
+    APPENDING.store(appending, Ordering::SeqCst);
+}
+
+/// Marks `limit` as the history size in use; 0 for unlimited.
+pub fn set_history_limit(limit: usize) {
+    HISTORY_LIMIT.store(limit, Ordering::SeqCst);
+}
+
+/// The history size in use; None when unlimited.
+pub fn history_limit() -> Option<usize> {
+    let limit = HISTORY_LIMIT.load(Ordering::SeqCst);
+    (limit > 0).then_some(limit)
+}
+
+/// Marks `days` as the retention in use; 0 for forever.
+pub fn set_retention(days: u64) {
+    RETENTION.store(days as usize, Ordering::SeqCst);
+}
+
+pub fn set_launch_at_login(enabled: bool) {
+    LAUNCH_AT_LOGIN.store(enabled, Ordering::SeqCst);
+}
+
+/// The values to offer, with `current` added in order when it was set by hand. 0, for
+/// no limit, stays last.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn choices(offered: &[usize], current: usize) -> Vec<usize> {
+    let mut values = offered.to_vec();
+    if !values.contains(&current) {
+        let at = values
+            .iter()
+            .position(|&value| value == 0 || value > current);
+        values.insert(at.unwrap_or(values.len()), current);
+    }
+    values
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn history_size_label(size: usize) -> String {
+    match size {
+        0 => "Unlimited".to_string(),
+        size => format!("{} Entries", size),
+    }
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn retention_label(days: usize) -> String {
+    match days {
+        0 => "Forever".to_string(),
+        1 => "1 Day".to_string(),
+        days => format!("{} Days", days),
+    }
+}
+
+/// One-line menu title: file names for files and images, collapsed whitespace otherwise.
+fn menu_label(entry: &Entry) -> String {
+    let file_count = entry.file_paths().len();
+    let text = match entry.entry_type {
+        EntryType::File if file_count > 1 => format!("{} files", file_count),
+        EntryType::Image | EntryType::File => std::path::Path::new(&entry.content)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| entry.content.clone()),
+        _ => entry
+            .content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    if text.chars().count() > MENU_LABEL_CHARS {
+        let truncated: String = text.chars().take(MENU_LABEL_CHARS - 1).collect();
+        format!("{}\u{2026}", truncated)
+    } else {
+        text
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use macos::show;
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::sync::{atomic::Ordering, OnceLock};
+
+    use cocoa::{
+        base::{id, nil},
+        foundation::NSString,
+    };
+    use objc::{
+        class,
+        declare::ClassDecl,
+        msg_send,
+        runtime::{Object, Sel},
+        sel, sel_impl,
+    };
+
+    use super::{
+        choices, history_size_label, retention_label, MenuAction, APPENDING, HISTORY_LIMIT,
+        HISTORY_SIZES, LAUNCH_AT_LOGIN, PAUSED, PENDING_MENU_ACTIONS, RECENT, RETENTION,
+        RETENTION_DAYS,
+    };
+
+    fn push(action: MenuAction) {
+        PENDING_MENU_ACTIONS.lock().unwrap().push(action);
+        crate::wake::wake();
+    }
+
+    extern "C" fn select_entry(_this: &Object, _cmd: Sel, sender: id) {
+        let tag: i64 = unsafe { msg_send![sender, tag] };
+        push(MenuAction::Select(tag as u64));
+    }
+
+    extern "C" fn show_clipz(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::Show);
+    }
+
+    extern "C" fn toggle_pause(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::TogglePause);
+    }
+
+    extern "C" fn toggle_append(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::ToggleAppend);
+    }
+
+    extern "C" fn clear_access_log(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::ClearAccessLog);
+    }
+
+    extern "C" fn export_timeline(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::ExportTimeline);
+    }
+
+    extern "C" fn set_history_limit(_this: &Object, _cmd: Sel, sender: id) {
+        let tag: i64 = unsafe { msg_send![sender, tag] };
+        push(MenuAction::SetHistoryLimit(tag as usize));
+    }
+
+    extern "C" fn set_retention(_this: &Object, _cmd: Sel, sender: id) {
+        let tag: i64 = unsafe { msg_send![sender, tag] };
+        push(MenuAction::SetRetention(tag as u64));
+    }
+
+    extern "C" fn toggle_launch_at_login(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::ToggleLaunchAtLogin);
+    }
+
+    extern "C" fn quit(_this: &Object, _cmd: Sel, _sender: id) {
+        push(MenuAction::Quit);
+    }
+
+    /// Target for every menu item; created once and never released.
+    fn handler() -> id {
+        static HANDLER: OnceLock<usize> = OnceLock::new();
+        *HANDLER.get_or_init(|| unsafe {
+            let mut decl = ClassDecl::new("StatusMenuHandler", class!(NSObject)).unwrap();
+            decl.add_method(
+                sel!(selectEntry:),
+                select_entry as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(showClipz:),
+                show_clipz as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(togglePause:),
+                toggle_pause as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(toggleAppend:),
+                toggle_append as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(clearAccessLog:),
+                clear_access_log as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(exportTimeline:),
+                export_timeline as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(setHistoryLimit:),
+                set_history_limit as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(setRetention:),
+                set_retention as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(
+                sel!(toggleLaunchAtLogin:),
+                toggle_launch_at_login as extern "C" fn(&Object, Sel, id),
+            );
+            decl.add_method(sel!(quit:), quit as extern "C" fn(&Object, Sel, id));
+            let class = decl.register();
+            let handler: id = msg_send![class, new];
+            handler as usize
+        }) as id
+    }
+
+    unsafe fn add_item(menu: id, title: &str, action: Sel, key: &str) -> id {
+        let title = NSString::alloc(nil).init_str(title);
+        let key = NSString::alloc(nil).init_str(key);
+        let item: id = msg_send![class!(NSMenuItem), alloc];
+        let item: id = msg_send![item, initWithTitle: title action: action keyEquivalent: key];
+        let _: () = msg_send![item, setTarget: handler()];
+        let _: () = msg_send![menu, addItem: item];
+        let _: () = msg_send![item, release];
+        item
+    }
+
+    /// Adds an item opening a new submenu, and returns the submenu.
+    unsafe fn add_submenu(menu: id, title: &str) -> id {
+        let submenu: id = msg_send![class!(NSMenu), new];
+        let _: () = msg_send![submenu, setAutoenablesItems: false];
+        let title = NSString::alloc(nil).init_str(title);
+        let key = NSString::alloc(nil).init_str("");
+        let item: id = msg_send![menu, addItemWithTitle: title action: nil keyEquivalent: key];
+        let _: () = msg_send![menu, setSubmenu: submenu forItem: item];
+        let _: () = msg_send![submenu, release];
+        submenu
+    }
+
+    unsafe fn add_separator(menu: id) {
+        let separator: id = msg_send![class!(NSMenuItem), separatorItem];
+        let _: () = msg_send![menu, addItem: separator];
+    }
+
+    /// Pops up the menu under `status_item`: recent entries, Show Clipz, pause, append
+    /// mode, the history size and retention, clearing the access log when it is on, the
+    /// timeline export, launching at login, and quit.
+    pub fn show(status_item: id) {
+        unsafe {
+            let menu: id = msg_send![class!(NSMenu), new];
+            let _: () = msg_send![menu, setAutoenablesItems: false];
+
+            let recent = RECENT.lock().unwrap().clone();
+            if recent.is_empty() {
+                let item = add_item(menu, "No history yet", sel!(selectEntry:), "");
+                let _: () = msg_send![item, setEnabled: false];
+            }
+            for (id, label) in recent {
+                let item = add_item(menu, &label, sel!(selectEntry:), "");
+                let _: () = msg_send![item, setTag: id as i64];
+            }
+
+            add_separator(menu);
+            add_item(menu, "Show Clipz", sel!(showClipz:), "");
+            let pause_title = if PAUSED.load(Ordering::SeqCst) {
+                "Resume Recording"
+            } else {
+                "Pause Recording"
+            };
+            add_item(menu, pause_title, sel!(togglePause:), "");
+            let append_title = if APPENDING.load(Ordering::SeqCst) {
+                "Stop Appending Copies"
+            } else {
+                "Append Copies Together"
+            };
+            add_item(menu, append_title, sel!(toggleAppend:), "");
+            let sizes = add_submenu(menu, "History Size");
+            let limit = HISTORY_LIMIT.load(Ordering::SeqCst);
+            for size in choices(&HISTORY_SIZES, limit) {
+                let item = add_item(sizes, &history_size_label(size), sel!(setHistoryLimit:), "");
+                let _: () = msg_send![item, setTag: size as i64];
+                let _: () = msg_send![item, setState: (size == limit) as i64];
+            }
+            let retention = add_submenu(menu, "Keep History");
+            let kept = RETENTION.load(Ordering::SeqCst);
+            for days in choices(&RETENTION_DAYS, kept) {
+                let item = add_item(retention, &retention_label(days), sel!(setRetention:), "");
+                let _: () = msg_send![item, setTag: days as i64];
+                let _: () = msg_send![item, setState: (days == kept) as i64];
+            }
+            if crate::access_log::is_enabled() {
+                add_item(menu, "Clear Access Log", sel!(clearAccessLog:), "");
+            }
+            add_item(
+                menu,
+                "Export Clipboard Timeline\u{2026}",
+                sel!(exportTimeline:),
+                "",
+            );
+            add_separator(menu);
+            let login = add_item(menu, "Launch at Login", sel!(toggleLaunchAtLogin:), "");
+            let _: () = msg_send![login, setState: LAUNCH_AT_LOGIN.load(Ordering::SeqCst) as i64];
+            add_item(menu, "Quit Clipz", sel!(quit:), "q");
+
+            let _: () = msg_send![status_item, popUpStatusItemMenu: menu];
+            let _: () = msg_send![menu, release];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str, entry_type: EntryType) -> Entry {
+        Entry {
+            id: 1,
+            content: content.into(),
+            entry_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn menu_labels_are_single_line_and_truncated() {
+        assert_eq!(
+            menu_label(&entry("fn main() {\n    run();\n}", EntryType::Text)),
+            "fn main() { run(); }"
+        );
+        assert_eq!(
+            menu_label(&entry("/tmp/shots/Screen Shot.png", EntryType::Image)),
+            "Screen Shot.png"
+        );
+        let long = menu_label(&entry(&"x".repeat(100), EntryType::Text));
+        assert_eq!(long.chars().count(), MENU_LABEL_CHARS);
+        assert!(long.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn choices_include_a_hand_set_value() {
+        assert_eq!(choices(&HISTORY_SIZES, 100), [10, 100, 500, 0]);
+        assert_eq!(choices(&HISTORY_SIZES, 50), [10, 50, 100, 500, 0]);
+        assert_eq!(choices(&HISTORY_SIZES, 2000), [10, 100, 500, 2000, 0]);
+        assert_eq!(choices(&RETENTION_DAYS, 14), [1, 7, 14, 30, 0]);
+        assert_eq!(history_size_label(0), "Unlimited");
+        assert_eq!(history_size_label(500), "500 Entries");
+        assert_eq!(retention_label(1), "1 Day");
+        assert_eq!(retention_label(0), "Forever");
+    }
+}
@@ -0,0 +1,68 @@
+/// Longest tag the backend accepts.
+const MAX_TAG_LEN: usize = 32;
+
+/// Inline editor for the tags of one entry, opened with Cmd+T or a right-click, or for
+/// tags to add to every selected entry.
+///
+/// While it is open, typing edits `text` instead of the search query.
+pub struct TagEditor {
+    /// The entry whose tags are replaced; None when the tags are added to the selection.
+    pub id: Option<u64>,
+    pub text: String,
+}
+
+impl TagEditor {
+    pub fn new(id: u64, tags: &[String]) -> Self {
+        Self {
+            id: Some(id),
+            text: tags.join(" "),
+        }
+    }
+
+    pub fn for_selection() -> Self {
+        Self {
+            id: None,
+            text: String::new(),
+        }
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        parse_tags(&self.text)
+    }
+}
+
+/// Tags typed as space- or comma-separated words, with an optional leading `#`.
+///
+/// Tags are lowercased and reduced to the characters the backend stores (ASCII
+/// letters, digits, `-` and `_`); empty results and repeats are dropped.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let tag: String = word
+            .trim_start_matches('#')
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .map(|c| c.to_ascii_lowercase())
+            .take(MAX_TAG_LEN)
+            .collect();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_normalized_and_deduplicated() {
+        assert_eq!(
+            parse_tags("#Work, api-keys  work #é-draft"),
+            ["work", "api-keys", "-draft"]
+        );
+        assert!(parse_tags("  , # ").is_empty());
+        assert_eq!(parse_tags(&"x".repeat(40))[0].len(), MAX_TAG_LEN);
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Which built-in palette the popover uses.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Dark => Theme::DARK,
+            ThemeName::Light => Theme::LIGHT,
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::Dark,
+        }
+    }
+}
+
+/// Colors and corner radii for the popover. Opaque colors are `0xRRGGBB`; surfaces are
+/// `0xRRGGBBAA` so they blend with the blurred window background.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub name: ThemeName,
+    pub text_primary: u32,
+    pub text_secondary: u32,
+    pub text_muted: u32,
+    pub text_dim: u32,
+    pub accent_blue: u32,
+    pub accent_orange: u32,
+    pub accent_green: u32,
+    pub accent_purple: u32,
+    pub accent_pink: u32,
+    pub danger: u32,
+    pub surface_base: u32,
+    pub surface_border: u32,
+    pub surface_row: u32,
+    pub surface_row_focused: u32,
+    pub surface_row_current: u32,
+    pub surface_row_hover: u32,
+    pub surface_icon_well: u32,
+    pub focus_border: u32,
+    pub window_radius: f32,
+    pub row_radius: f32,
+    pub control_radius: f32,
+}
+
+impl Theme {
+    pub const DARK: Theme = Theme {
+        name: ThemeName::Dark,
+        text_primary: 0xf7f4ee,
+        text_secondary: 0xd7d0c2,
+        text_muted: 0xa69c89,
+        text_dim: 0x8a7f6b,
+        accent_blue: 0x5ac8fa,
+        accent_orange: 0xff9f0a,
+        accent_green: 0x30d158,
+        accent_purple: 0xbf5af2,
+        accent_pink: 0xff375f,
+        danger: 0xff453a,
+        surface_base: 0x14110bf2,
+        surface_border: 0xffffff24,
+        surface_row: 0xffffff08,
+        surface_row_focused: 0xffffff14,
+        surface_row_current: 0xffffff20,
+        surface_row_hover: 0xffffff18,
+        surface_icon_well: 0xffffff10,
+        focus_border: 0xffffff30,
+        window_radius: 12.0,
+        row_radius: 8.0,
+        control_radius: 6.0,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        name: ThemeName::Light,
+        text_primary: 0x1d1a15,
+        text_secondary: 0x3d3830,
+        text_muted: 0x6f675a,
+        text_dim: 0x8c8374,
+        accent_blue: 0x0a84ff,
+        accent_orange: 0xe07b00,
+        accent_green: 0x248a3d,
+        accent_purple: 0x9a45c8,
+        accent_pink: 0xd70f4a,
+        danger: 0xd70015,
+        surface_base: 0xf7f4eef2,
+        surface_border: 0x00000024,
+        surface_row: 0x00000008,
+        surface_row_focused: 0x00000012,
+        surface_row_current: 0x0000001c,
+        surface_row_hover: 0x00000016,
+        surface_icon_well: 0x0000000e,
+        focus_border: 0x00000030,
+        window_radius: 12.0,
+        row_radius: 8.0,
+        control_radius: 6.0,
+    };
+
+    /// The vibrant AppKit appearance matching this palette, so the window blur agrees
+    /// with the colors drawn on top of it.
+    #[cfg(target_os = "macos")]
+    pub fn appearance_name(&self) -> &'static str {
+        match self.name {
+            ThemeName::Dark => "NSAppearanceNameVibrantDark",
+            ThemeName::Light => "NSAppearanceNameVibrantLight",
+        }
+    }
+}
+
+/// User palette from `theme.toml` next to the config file, applied on top of the
+/// selected built-in theme. Every key is optional; colors are `"#rrggbb"`, and surfaces
+/// also accept `"#rrggbbaa"`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeOverrides {
+    pub text_primary: Option<Rgb>,
+    pub text_secondary: Option<Rgb>,
+    pub text_muted: Option<Rgb>,
+    pub text_dim: Option<Rgb>,
+    pub accent_blue: Option<Rgb>,
+    pub accent_orange: Option<Rgb>,
+    pub accent_green: Option<Rgb>,
+    pub accent_purple: Option<Rgb>,
+    pub accent_pink: Option<Rgb>,
+    pub danger: Option<Rgb>,
+    pub surface_base: Option<Rgba>,
+    pub surface_border: Option<Rgba>,
+    pub surface_row: Option<Rgba>,
+    pub surface_row_focused: Option<Rgba>,
+    pub surface_row_current: Option<Rgba>,
+    pub surface_row_hover: Option<Rgba>,
+    pub surface_icon_well: Option<Rgba>,
+    pub focus_border: Option<Rgba>,
+    pub window_radius: Option<f32>,
+    pub row_radius: Option<f32>,
+    pub control_radius: Option<f32>,
+}
+
+impl ThemeOverrides {
+    pub fn path() -> Option<PathBuf> {
+        crate::config::Config::path().map(|config| config.with_file_name("theme.toml"))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        toml::from_str(text).context("invalid theme file")
+    }
+
+    pub fn apply(&self, mut theme: Theme) -> Theme {
+        let colors = [
+            (&mut theme.text_primary, self.text_primary.map(|c| c.0)),
+            (&mut theme.text_secondary, self.text_secondary.map(|c| c.0)),
+            (&mut theme.text_muted, self.text_muted.map(|c| c.0)),
+            (&mut theme.text_dim, self.text_dim.map(|c| c.0)),
+            (&mut theme.accent_blue, self.accent_blue.map(|c| c.0)),
+            (&mut theme.accent_orange, self.accent_orange.map(|c| c.0)),
+            (&mut theme.accent_green, self.accent_green.map(|c| c.0)),
+            (&mut theme.accent_purple, self.accent_purple.map(|c| c.0)),
+            (&mut theme.accent_pink, self.accent_pink.map(|c| c.0)),
+            (&mut theme.danger, self.danger.map(|c| c.0)),
+            (&mut theme.surface_base, self.surface_base.map(|c| c.0)),
+            (&mut theme.surface_border, self.surface_border.map(|c| c.0)),
+            (&mut theme.surface_row, self.surface_row.map(|c| c.0)),
+            (
+                &mut theme.surface_row_focused,
+                self.surface_row_focused.map(|c| c.0),
+            ),
+            (
+                &mut theme.surface_row_current,
+                self.surface_row_current.map(|c| c.0),
+            ),
+            (
+                &mut theme.surface_row_hover,
+                self.surface_row_hover.map(|c| c.0),
+            ),
+            (
+                &mut theme.surface_icon_well,
+                self.surface_icon_well.map(|c| c.0),
+            ),
+            (&mut theme.focus_border, self.focus_border.map(|c| c.0)),
+        ];
+        for (slot, value) in colors {
+            if let Some(value) = value {
+                *slot = value;
+            }
+        }
+        let radii = [
+            (&mut theme.window_radius, self.window_radius),
+            (&mut theme.row_radius, self.row_radius),
+            (&mut theme.control_radius, self.control_radius),
+        ];
+        for (slot, value) in radii {
+            if let Some(value) = value {
+                *slot = value.max(0.0);
+            }
+        }
+        theme
+    }
+}
+
+/// Reloads `theme.toml` whenever its modification time changes.
+#[derive(Default)]
+pub struct ThemeWatcher {
+    modified: Option<SystemTime>,
+    checked: Option<Instant>,
+}
+
+impl ThemeWatcher {
+    /// How long `poll` goes without looking at the file again; it is called every tick.
+    const CHECK_EVERY: Duration = Duration::from_secs(1);
+
+    /// Returns the new overrides if the file changed since it was last looked at. A
+    /// deleted file yields empty overrides; a broken one is reported and ignored.
+    pub fn poll(&mut self) -> Option<ThemeOverrides> {
+        let now = Instant::now();
+        if self
+            .checked
+            .is_some_and(|checked| now.duration_since(checked) < Self::CHECK_EVERY)
+        {
+            return None;
+        }
+        self.checked = Some(now);
+        let path = ThemeOverrides::path()?;
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        if modified.is_none() {
+            return Some(ThemeOverrides::default());
+        }
+        match std::fs::read_to_string(&path)
+            .context("failed to read theme file")
+            .and_then(|text| ThemeOverrides::parse(&text))
+        {
+            Ok(overrides) => Some(overrides),
+            Err(e) => {
+                eprintln!("Ignoring {}: {:#}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// An opaque `"#rrggbb"` color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgb(u32);
+
+/// A `"#rrggbb"` (opaque) or `"#rrggbbaa"` color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rgba(u32);
+
+fn parse_hex(s: &str) -> Result<(u32, usize)> {
+    let hex = s
+        .strip_prefix('#')
+        .ok_or_else(|| anyhow!("color {:?} must start with '#'", s))?;
+    // `from_str_radix` would also take a sign
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("invalid color {:?}", s);
+    }
+    let value = u32::from_str_radix(hex, 16).with_context(|| format!("invalid color {:?}", s))?;
+    Ok((value, hex.len()))
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match parse_hex(&s).map_err(serde::de::Error::custom)? {
+            (value, 6) => Ok(Rgb(value)),
+            _ => Err(serde::de::Error::custom(format!(
+                "expected \"#rrggbb\", got {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgba {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match parse_hex(&s).map_err(serde::de::Error::custom)? {
+            (value, 6) => Ok(Rgba(tint(value, 0xff))),
+            (value, 8) => Ok(Rgba(value)),
+            _ => Err(serde::de::Error::custom(format!(
+                "expected \"#rrggbb\" or \"#rrggbbaa\", got {:?}",
+                s
+            ))),
+        }
+    }
+}
+
+/// `color` (`0xRRGGBB`) with the given alpha, as `0xRRGGBBAA`.
+pub fn tint(color: u32, alpha: u8) -> u32 {
+    (color << 8) | alpha as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_appends_alpha() {
+        assert_eq!(tint(0xff9f0a, 0x18), 0xff9f0a18);
+        assert_eq!(ThemeName::Dark.toggled().theme(), Theme::LIGHT);
+    }
+
+    #[test]
+    fn overrides_apply_on_top_of_the_base_theme() {
+        let overrides = ThemeOverrides::parse(
+            r##"
+            accent_blue = "#112233"
+            surface_base = "#000000"
+            surface_row = "#ffffff10"
+            row_radius = 4
+            "##,
+        )
+        .unwrap();
+        let theme = overrides.apply(Theme::DARK);
+        assert_eq!(theme.accent_blue, 0x112233);
+        assert_eq!(theme.surface_base, 0x000000ff);
+        assert_eq!(theme.surface_row, 0xffffff10);
+        assert_eq!(theme.row_radius, 4.0);
+        assert_eq!(theme.danger, Theme::DARK.danger);
+
+        assert!(ThemeOverrides::parse(r##"accent_blue = "#11223344""##).is_err());
+        assert!(ThemeOverrides::parse(r##"accent_blue = "#+12233""##).is_err());
+        assert!(ThemeOverrides::parse(r##"accent_bleu = "#112233""##).is_err());
+    }
+}
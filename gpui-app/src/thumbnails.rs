@@ -0,0 +1,384 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::protocol::{Entry, EntryType};
+
+// Rows show images at 28pt; 56px keeps them sharp on Retina displays
+const THUMBNAIL_SIZE: u32 = 56;
+// The preview pane is at most a few hundred points wide; twice that covers Retina
+const PREVIEW_SIZE: u32 = 1024;
+// Blurhash components across and down; enough for the rough layout of a screenshot
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+// Images are shrunk to this before hashing, which is linear in the pixel count
+const BLURHASH_SOURCE_SIZE: u32 = 32;
+/// Cells across and down in a decoded placeholder.
+pub const PLACEHOLDER_GRID: usize = 4;
+
+/// Set whenever a thumbnail finishes so the event loop knows to repaint.
+pub static THUMBNAILS_UPDATED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Thumbnail {
+    /// Still being decoded; render a placeholder.
+    Pending,
+    Ready(PathBuf),
+    Failed,
+}
+
+type Key = (PathBuf, u32);
+
+enum BlurhashJob {
+    Hash(PathBuf),
+    /// Delete the hash files not named here.
+    Prune(HashSet<String>),
+}
+
+/// Background thumbnailer for image entries.
+///
+/// Thumbnails are downscaled with `sips` on worker threads and written to a disk cache
+/// keyed by a hash of the image bytes, so neither the list nor the preview ever decodes
+/// full-size screenshots on the render thread, and identical images share one thumbnail
+/// across launches. Row thumbnails and previews have separate workers so a large
+/// preview never holds up the list.
+///
+/// Each image also gets a blurhash, computed on its own worker as soon as the entry
+/// shows up and kept in a small file per image, so rows and the preview can show a
+/// blurred placeholder while the thumbnail is made, and straight away on later
+/// launches. Hashes of images no longer in the history are deleted.
+#[derive(Clone)]
+pub struct Thumbnails {
+    row_tx: Sender<Key>,
+    preview_tx: Sender<Key>,
+    blurhash_tx: Sender<BlurhashJob>,
+    slots: Arc<Mutex<HashMap<Key, Thumbnail>>>,
+    // Blurhash by image path; queued ones are None until hashed
+    blurhashes: Arc<Mutex<HashMap<PathBuf, Option<String>>>>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        let slots: Arc<Mutex<HashMap<Key, Thumbnail>>> = Arc::new(Mutex::new(HashMap::new()));
+        let blurhashes = Arc::new(Mutex::new(HashMap::new()));
+        // Hashes used to share one index, rewritten for every image
+        let _ = std::fs::remove_file(cache_dir().join("blurhashes.json"));
+        Self {
+            row_tx: spawn_worker(slots.clone()),
+            preview_tx: spawn_worker(slots.clone()),
+            blurhash_tx: spawn_blurhash_worker(blurhashes.clone()),
+            slots,
+            blurhashes,
+        }
+    }
+
+    /// Colours of a `PLACEHOLDER_GRID` square grid blurred from `source`, row by row,
+    /// once its blurhash is known.
+    pub fn placeholder(&self, source: &Path) -> Option<Vec<u32>> {
+        let blurhashes = self.blurhashes.lock().unwrap();
+        decode_placeholder(blurhashes.get(source)?.as_deref()?)
+    }
+
+    /// Queues the image entries that haven't been hashed yet.
+    pub fn queue_blurhashes(&self, entries: &[Entry]) {
+        let mut blurhashes = self.blurhashes.lock().unwrap();
+        for entry in entries {
+            if entry.entry_type != EntryType::Image {
+                continue;
+            }
+            let source = PathBuf::from(&entry.content);
+            if blurhashes.contains_key(&source) {
+                continue;
+            }
+            blurhashes.insert(source.clone(), None);
+            let _ = self.blurhash_tx.send(BlurhashJob::Hash(source));
+        }
+    }
+
+    /// Forgets the hashes of images not among `entries`, which must be the whole
+    /// history, and deletes their files in the background.
+    pub fn prune_blurhashes(&self, entries: &[Entry]) {
+        let live: HashSet<PathBuf> = entries
+            .iter()
+            .filter(|entry| entry.entry_type == EntryType::Image)
+            .map(|entry| PathBuf::from(&entry.content))
+            .collect();
+        self.blurhashes
+            .lock()
+            .unwrap()
+            .retain(|source, _| live.contains(source));
+        let names = live
+            .iter()
+            .map(|source| blurhash_file_name(source))
+            .collect();
+        let _ = self.blurhash_tx.send(BlurhashJob::Prune(names));
+    }
+
+    /// The row-sized thumbnail for `source`, queueing it for generation on first use.
+    pub fn get(&self, source: &Path) -> Thumbnail {
+        self.request(source, THUMBNAIL_SIZE, &self.row_tx)
+    }
+
+    /// A copy of `source` scaled to fit the preview pane.
+    pub fn preview(&self, source: &Path) -> Thumbnail {
+        self.request(source, PREVIEW_SIZE, &self.preview_tx)
+    }
+
+    fn request(&self, source: &Path, max_size: u32, tx: &Sender<Key>) -> Thumbnail {
+        let key = (source.to_path_buf(), max_size);
+        let mut slots = self.slots.lock().unwrap();
+        if let Some(thumbnail) = slots.get(&key) {
+            return thumbnail.clone();
+        }
+        slots.insert(key.clone(), Thumbnail::Pending);
+        let _ = tx.send(key);
+        Thumbnail::Pending
+    }
+
+    /// Deletes every cached thumbnail, returning the bytes freed. Blurhashes are kept,
+    /// so rows still show placeholders while the images on screen are thumbnailed again.
+    pub fn clear(&self) -> u64 {
+        self.slots.lock().unwrap().clear();
+        let Ok(dir) = std::fs::read_dir(cache_dir()) else {
+            return 0;
+        };
+        let mut freed = 0;
+        for file in dir.flatten() {
+            let path = file.path();
+            if path.extension() != Some("png".as_ref()) {
+                continue;
+            }
+            let len = file.metadata().map_or(0, |m| m.len());
+            match std::fs::remove_file(&path) {
+                Ok(()) => freed += len,
+                Err(e) => eprintln!("Failed to remove {}: {}", path.display(), e),
+            }
+        }
+        freed
+    }
+}
+
+fn spawn_worker(slots: Arc<Mutex<HashMap<Key, Thumbnail>>>) -> Sender<Key> {
+    let (tx, rx) = mpsc::channel::<Key>();
+    thread::spawn(move || {
+        for (source, max_size) in rx {
+            let thumbnail = match generate(&source, max_size) {
+                Ok(thumbnail) => Thumbnail::Ready(thumbnail),
+                Err(e) => {
+                    eprintln!("Failed to thumbnail {}: {:#}", source.display(), e);
+                    Thumbnail::Failed
+                }
+            };
+            slots.lock().unwrap().insert((source, max_size), thumbnail);
+            crate::wake::set(&THUMBNAILS_UPDATED);
+        }
+    });
+    tx
+}
+
+fn spawn_blurhash_worker(
+    blurhashes: Arc<Mutex<HashMap<PathBuf, Option<String>>>>,
+) -> Sender<BlurhashJob> {
+    let (tx, rx) = mpsc::channel::<BlurhashJob>();
+    thread::spawn(move || {
+        for job in rx {
+            let source = match job {
+                BlurhashJob::Hash(source) => source,
+                BlurhashJob::Prune(live) => {
+                    prune_blurhash_dir(&blurhash_dir(), &live);
+                    continue;
+                }
+            };
+            let file = blurhash_dir().join(blurhash_file_name(&source));
+            let hash = match std::fs::read_to_string(&file) {
+                Ok(hash) => hash,
+                Err(_) => match encode_blurhash(&source) {
+                    Ok(hash) => {
+                        if let Err(e) = save_blurhash(&file, &hash) {
+                            eprintln!("Failed to save blurhash: {:#}", e);
+                        }
+                        hash
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to blurhash {}: {:#}", source.display(), e);
+                        continue;
+                    }
+                },
+            };
+            // Pruned while it was being hashed
+            if let Some(slot) = blurhashes.lock().unwrap().get_mut(&source) {
+                *slot = Some(hash);
+            }
+            crate::wake::set(&THUMBNAILS_UPDATED);
+        }
+    });
+    tx
+}
+
+pub fn cache_dir() -> PathBuf {
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches"));
+    #[cfg(not(target_os = "macos"))]
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+
+    base.unwrap_or_else(std::env::temp_dir)
+        .join("clipz/thumbnails")
+}
+
+/// 64-bit FNV-1a: stable across builds, which std's `DefaultHasher` is not.
+pub fn cache_key(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn cache_file_name(key: u64, max_size: u32) -> String {
+    // Row thumbnails keep the name they had before previews were cached too
+    if max_size == THUMBNAIL_SIZE {
+        format!("{:016x}.png", key)
+    } else {
+        format!("{:016x}-{}.png", key, max_size)
+    }
+}
+
+/// Blurhashes sit next to the thumbnails, so clearing thumbnails leaves them.
+fn blurhash_dir() -> PathBuf {
+    cache_dir().with_file_name("blurhashes")
+}
+
+fn blurhash_file_name(source: &Path) -> String {
+    format!(
+        "{:016x}.txt",
+        cache_key(source.as_os_str().as_encoded_bytes())
+    )
+}
+
+fn save_blurhash(file: &Path, hash: &str) -> Result<()> {
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir).context("failed to create blurhash cache")?;
+    }
+    std::fs::write(file, hash).with_context(|| format!("failed to write {}", file.display()))
+}
+
+/// Deletes the hash files in `dir` not named in `live`.
+fn prune_blurhash_dir(dir: &Path, live: &HashSet<String>) {
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for file in files.flatten() {
+        let name = file.file_name().to_string_lossy().into_owned();
+        if !live.contains(&name) {
+            let _ = std::fs::remove_file(file.path());
+        }
+    }
+}
+
+/// The blurhash of `source`, shrunk with `sips` first: hashing is linear in the pixel
+/// count, so it is never run on the full-size original.
+fn encode_blurhash(source: &Path) -> Result<String> {
+    let small = generate(source, BLURHASH_SOURCE_SIZE)?;
+    let image = image::open(&small);
+    let _ = std::fs::remove_file(&small);
+    let image = image.context("failed to decode thumbnail")?.to_rgba8();
+    let (x, y) = BLURHASH_COMPONENTS;
+    blurhash::encode(x, y, image.width(), image.height(), image.as_raw())
+        .map_err(|e| anyhow!("failed to encode blurhash: {}", e))
+}
+
+fn decode_placeholder(hash: &str) -> Option<Vec<u32>> {
+    let grid = PLACEHOLDER_GRID as u32;
+    let pixels = blurhash::decode(hash, grid, grid, 1.0).ok()?;
+    Some(
+        pixels
+            .chunks_exact(4)
+            .map(|p| u32::from_be_bytes([0, p[0], p[1], p[2]]))
+            .collect(),
+    )
+}
+
+fn generate(source: &Path, max_size: u32) -> Result<PathBuf> {
+    let bytes = std::fs::read(source).context("failed to read image")?;
+    let dir = cache_dir();
+    let dest = dir.join(cache_file_name(cache_key(&bytes), max_size));
+    if dest.exists() {
+        return Ok(dest);
+    }
+
+    std::fs::create_dir_all(&dir).context("failed to create thumbnail cache")?;
+    let output = Command::new("/usr/bin/sips")
+        .arg("-Z")
+        .arg(max_size.to_string())
+        .arg("-s")
+        .arg("format")
+        .arg("png")
+        .arg(source)
+        .arg("--out")
+        .arg(&dest)
+        .output()
+        .context("failed to run sips")?;
+    if !output.status.success() {
+        bail!("sips exited with {}", output.status);
+    }
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_is_fnv1a() {
+        assert_eq!(cache_key(b""), 0xcbf29ce484222325);
+        assert_eq!(cache_key(b"hello"), 0xa430d84680aabd0b);
+        assert_eq!(
+            cache_file_name(0xab, THUMBNAIL_SIZE),
+            "00000000000000ab.png"
+        );
+        assert_eq!(
+            cache_file_name(0xab, PREVIEW_SIZE),
+            "00000000000000ab-1024.png"
+        );
+    }
+
+    #[test]
+    fn blurhash_decodes_to_a_grid_of_colours() {
+        // A flat red 8x8 image hashes to a red placeholder
+        let red: Vec<u8> = [255, 0, 0, 255].repeat(64);
+        let (x, y) = BLURHASH_COMPONENTS;
+        let hash = blurhash::encode(x, y, 8, 8, &red).unwrap();
+        let colors = decode_placeholder(&hash).unwrap();
+        assert_eq!(colors.len(), PLACEHOLDER_GRID * PLACEHOLDER_GRID);
+        // Quantized, so only close to the original
+        assert!(colors.iter().all(|&c| {
+            let [_, r, g, b] = c.to_be_bytes();
+            r >= 0xc0 && g < 0x10 && b < 0x10
+        }));
+        assert_eq!(decode_placeholder("not a hash"), None);
+    }
+
+    #[test]
+    fn pruning_keeps_only_hashes_of_live_images() {
+        let dir = std::env::temp_dir().join(format!("clipz-blurhash-test-{}", std::process::id()));
+        let kept = Path::new("/images/kept.png");
+        let gone = Path::new("/images/gone.png");
+        save_blurhash(&dir.join(blurhash_file_name(kept)), "L00000fQfQfQ").unwrap();
+        save_blurhash(&dir.join(blurhash_file_name(gone)), "L00000fQfQfQ").unwrap();
+
+        let live = HashSet::from([blurhash_file_name(kept)]);
+        prune_blurhash_dir(&dir, &live);
+        assert!(dir.join(blurhash_file_name(kept)).exists());
+        assert!(!dir.join(blurhash_file_name(gone)).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::protocol::ClipboardEvent;
+
+/// Totals shown at the top of a timeline export, so a missed copy or a slow backend
+/// stands out without reading every event.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    events: usize,
+    outcomes: BTreeMap<String, usize>,
+    /// Over the events that reached the history; None when there are none.
+    median_latency_us: Option<u64>,
+    max_latency_us: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineExport<'a> {
+    /// Unix milliseconds, like the event timestamps.
+    exported_at: i64,
+    summary: Summary,
+    events: &'a [ClipboardEvent],
+}
+
+fn summarize(events: &[ClipboardEvent]) -> Summary {
+    let mut outcomes = BTreeMap::new();
+    for event in events {
+        *outcomes.entry(event.outcome.clone()).or_insert(0) += 1;
+    }
+    let mut latencies: Vec<u64> = events
+        .iter()
+        .filter(|event| event.entry_type.is_some())
+        .map(|event| event.latency_us)
+        .collect();
+    latencies.sort_unstable();
+    Summary {
+        events: events.len(),
+        outcomes,
+        median_latency_us: latencies.get(latencies.len() / 2).copied(),
+        max_latency_us: latencies.last().copied(),
+    }
+}
+
+/// Writes the events from `get-timeline`, oldest first, with a summary, as pretty JSON.
+pub fn write(path: &Path, events: &[ClipboardEvent]) -> Result<()> {
+    let export = TimelineExport {
+        exported_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0),
+        summary: summarize(events),
+        events,
+    };
+    let text = serde_json::to_string_pretty(&export)? + "\n";
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::EntryType;
+
+    fn event(outcome: &str, latency_us: u64) -> ClipboardEvent {
+        ClipboardEvent {
+            timestamp: 0,
+            change_count: 0,
+            outcome: outcome.into(),
+            entry_type: (outcome != "excluded").then_some(EntryType::Text),
+            size: 3,
+            latency_us,
+            app: None,
+        }
+    }
+
+    #[test]
+    fn summary_counts_outcomes_and_latencies() {
+        let events = [
+            event("recorded", 900),
+            event("excluded", 0),
+            event("recorded", 300),
+            event("unchanged", 500),
+        ];
+        let summary = summarize(&events);
+        assert_eq!(summary.events, 4);
+        assert_eq!(summary.outcomes["recorded"], 2);
+        assert_eq!(summary.outcomes["excluded"], 1);
+        assert_eq!(summary.median_latency_us, Some(500));
+        assert_eq!(summary.max_latency_us, Some(900));
+
+        assert_eq!(summarize(&[]).max_latency_us, None);
+    }
+}
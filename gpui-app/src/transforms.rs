@@ -0,0 +1,186 @@
+use crate::protocol::{Entry, EntryType};
+
+/// Built-in rewrites offered in a text entry's context menu. The result is put on the
+/// clipboard as a new entry; the stored entry is left as it was.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Trim,
+    Uppercase,
+    Lowercase,
+    PrettyJson,
+    MinifyJson,
+    StripQuotes,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 6] = [
+        Transform::Trim,
+        Transform::Uppercase,
+        Transform::Lowercase,
+        Transform::PrettyJson,
+        Transform::MinifyJson,
+        Transform::StripQuotes,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Transform::Trim => "Trim Whitespace",
+            Transform::Uppercase => "UPPERCASE",
+            Transform::Lowercase => "lowercase",
+            Transform::PrettyJson => "Pretty-print JSON",
+            Transform::MinifyJson => "Minify JSON",
+            Transform::StripQuotes => "Strip Quotes",
+        }
+    }
+
+    /// The rewritten text, or `None` when the transform doesn't apply (not JSON, no
+    /// surrounding quotes) or wouldn't change anything.
+    pub fn apply(self, text: &str) -> Option<String> {
+        let result = match self {
+            // Both ends of the text, and trailing spaces left on each line
+            Transform::Trim => text
+                .trim()
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Transform::Uppercase => text.to_uppercase(),
+            Transform::Lowercase => text.to_lowercase(),
+            Transform::PrettyJson => reformat_json(text, Some("  "))?,
+            Transform::MinifyJson => reformat_json(text, None)?,
+            Transform::StripQuotes => strip_quotes(text)?.to_string(),
+        };
+        (result != text).then_some(result)
+    }
+}
+
+/// Transforms that would change `entry`, in menu order. Only text and URLs are
+/// offered any.
+pub fn available(entry: &Entry) -> Vec<(Transform, String)> {
+    if !matches!(entry.entry_type, EntryType::Text | EntryType::Url) {
+        return Vec::new();
+    }
+    Transform::ALL
+        .into_iter()
+        .filter_map(|transform| Some((transform, transform.apply(&entry.content)?)))
+        .collect()
+}
+
+/// Re-indents valid JSON token by token rather than through `serde_json::Value`, so
+/// key order and number spelling survive. `None` indent puts everything on one line.
+pub fn reformat_json(text: &str, indent: Option<&str>) -> Option<String> {
+    serde_json::from_str::<serde::de::IgnoredAny>(text).ok()?;
+
+    let newline = |out: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            out.push('\n');
+            for _ in 0..depth {
+                out.push_str(indent);
+            }
+        }
+    };
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = text.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '{' | '[' => {
+                out.push(c);
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                // Empty containers stay as `{}` and `[]`
+                if let Some(close) = chars.next_if(|&next| next == '}' || next == ']') {
+                    out.push(close);
+                } else {
+                    depth += 1;
+                    newline(&mut out, depth);
+                }
+            }
+            '}' | ']' => {
+                depth -= 1;
+                newline(&mut out, depth);
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                newline(&mut out, depth);
+            }
+            ':' => {
+                out.push(c);
+                if indent.is_some() {
+                    out.push(' ');
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// The text inside one matching pair of quotes around the whole (trimmed) text.
+fn strip_quotes(text: &str) -> Option<&str> {
+    const PAIRS: [(char, char); 5] = [
+        ('"', '"'),
+        ('\'', '\''),
+        ('`', '`'),
+        ('\u{201c}', '\u{201d}'),
+        ('\u{2018}', '\u{2019}'),
+    ];
+    let text = text.trim();
+    PAIRS.iter().find_map(|&(open, close)| {
+        let inner = text.strip_prefix(open)?.strip_suffix(close)?;
+        Some(inner)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transforms_rewrite_or_decline() {
+        assert_eq!(
+            Transform::Trim.apply("  a  \n b \n\n").as_deref(),
+            Some("a\n b")
+        );
+        assert_eq!(Transform::Trim.apply("tidy"), None);
+        assert_eq!(Transform::Uppercase.apply("ok?").as_deref(), Some("OK?"));
+        assert_eq!(
+            Transform::StripQuotes
+                .apply(" \u{201c}hi\u{201d} ")
+                .as_deref(),
+            Some("hi")
+        );
+        assert_eq!(Transform::StripQuotes.apply("\"unbalanced'"), None);
+
+        let json = r#"{"z": 1.50, "a": [1, {}], "s": "x, {y}: \"z\"", "e": []}"#;
+        assert_eq!(
+            Transform::MinifyJson.apply(json).as_deref(),
+            Some(r#"{"z":1.50,"a":[1,{}],"s":"x, {y}: \"z\"","e":[]}"#)
+        );
+        assert_eq!(
+            Transform::PrettyJson.apply(json).as_deref(),
+            Some(
+                "{\n  \"z\": 1.50,\n  \"a\": [\n    1,\n    {}\n  ],\n  \"s\": \"x, {y}: \\\"z\\\"\",\n  \"e\": []\n}"
+            )
+        );
+        assert_eq!(Transform::PrettyJson.apply("not json"), None);
+    }
+}
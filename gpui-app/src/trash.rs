@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+
+use crate::{protocol::TrashedEntry, search};
+
+/// Days a removed entry stays in the trash before the backend purges it; see
+/// `trash_seconds` in `manager.zig`.
+pub const RETENTION_DAYS: i64 = 30;
+
+/// The trash as the backend last sent it, most recently removed first. The popover
+/// shows it as the Archived view.
+pub static TRASH: Mutex<Vec<TrashedEntry>> = Mutex::new(Vec::new());
+
+/// Whole days left before `entry` is purged, as of `now_secs`; 0 on its last day.
+pub fn days_left(entry: &TrashedEntry, now_secs: i64) -> i64 {
+    let purged_at = entry.removed_at / 1000 + RETENTION_DAYS * 24 * 60 * 60;
+    ((purged_at - now_secs) / (24 * 60 * 60)).max(0)
+}
+
+/// Trashed entries whose content or tags match `query` the way the search field
+/// matches entries. An empty query keeps them all, most recently removed first.
+pub fn matching(entries: &[TrashedEntry], query: &str) -> Vec<TrashedEntry> {
+    let Ok(matcher) = search::Matcher::new(query) else {
+        return Vec::new();
+    };
+    let haystacks: Vec<String> = entries
+        .iter()
+        .map(|t| format!("{}\n{}", t.entry.content, t.entry.tags.join(" ")))
+        .collect();
+    matcher
+        .rank(haystacks.iter().map(|text| [text.as_str()]))
+        .into_iter()
+        .map(|idx| entries[idx].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Entry, EntryType};
+
+    fn trashed(id: u64, content: &str, removed_at: i64) -> TrashedEntry {
+        TrashedEntry {
+            entry: Entry {
+                id,
+                content: content.into(),
+                tags: vec!["work".into()],
+                ..Default::default()
+            },
+            removed_at,
+        }
+    }
+
+    #[test]
+    fn trashed_entries_count_down_and_match_searches() {
+        // 2024-02-27 00:00 UTC
+        let now = 1_708_992_000;
+        let entries = [
+            trashed(1, "meeting notes", now * 1000),
+            trashed(2, "ssh key", (now - 29 * 86_400 - 60) * 1000),
+        ];
+        assert_eq!(days_left(&entries[0], now), 30);
+        assert_eq!(days_left(&entries[1], now), 0);
+        assert_eq!(days_left(&entries[1], now + 86_400), 0);
+
+        let ids = |query| -> Vec<u64> {
+            matching(&entries, query)
+                .iter()
+                .map(|t| t.entry.id)
+                .collect()
+        };
+        assert_eq!(ids(""), [1, 2]);
+        assert_eq!(ids("ssh"), [2]);
+        assert_eq!(ids("work"), [1, 2]);
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    OnceLock,
+};
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+static WAKER: OnceLock<UnboundedSender<()>> = OnceLock::new();
+
+/// Creates the channel the event loop sleeps on. Called once at launch; wake-ups from
+/// before then are dropped, which is fine as the loop's first pass looks at everything.
+pub fn channel() -> UnboundedReceiver<()> {
+    let (tx, rx) = mpsc::unbounded();
+    let _ = WAKER.set(tx);
+    rx
+}
+
+/// Wakes the event loop to act on something that changed: a backend message, a hotkey,
+/// a menu choice, a worker thread's results. Any thread may call it; several wake-ups
+/// before the loop gets to run are handled in one pass.
+pub fn wake() {
+    if let Some(tx) = WAKER.get() {
+        let _ = tx.unbounded_send(());
+    }
+}
+
+/// Sets `flag` and wakes the event loop, which clears it once acted on.
+pub fn set(flag: &AtomicBool) {
+    flag.store(true, Ordering::SeqCst);
+    wake();
+}
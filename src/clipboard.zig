@@ -2,11 +2,16 @@ const std = @import("std");
 const builtin = @import("builtin");
 const config = @import("config.zig");
 const image_storage = @import("image_storage.zig");
+const pasteboard = @import("pasteboard.zig");
 
 pub const ClipboardError = error{
     CommandFailed,
     NoClipboardContent,
     UnsupportedPlatform,
+    // Image data that would have to be written to disk, with `save_image_data` off
+    ImageSkipped,
+    // A file entry none of whose paths exist anymore
+    FilesMissing,
 };
 
 pub const ClipboardType = enum {
@@ -17,9 +22,82 @@ pub const ClipboardType = enum {
     color,
 };
 
+/// Where a copy was made, when known. Borrowed; the history keeps its own copies.
+pub const Source = struct {
+    app: []const u8 = "",
+    window_title: []const u8 = "",
+
+    pub fn isEmpty(self: Source) bool {
+        return self.app.len == 0 and self.window_title.len == 0;
+    }
+};
+
+/// The forms a copy can be put back on the clipboard in. `.text` is the entry's
+/// content itself; the others are kept in its `formats`.
+pub const FormatKind = enum {
+    text,
+    rtf,
+    html,
+    image,
+
+    /// The pasteboard type (UTI) it is read from and written back to.
+    pub fn pasteboardType(self: FormatKind) [*:0]const u8 {
+        return switch (self) {
+            .text => "public.utf8-plain-text",
+            .rtf => "public.rtf",
+            .html => "public.html",
+            .image => "public.png",
+        };
+    }
+};
+
+/// Another representation of a text copy, as the app that copied it offered it.
+pub const Format = struct {
+    kind: FormatKind,
+    /// The RTF or HTML itself; for `.image`, the path of a PNG in the image store.
+    data: []const u8,
+};
+
+/// Largest picture kept with a text copy, in bytes.
+const max_image_format_size = 16 * 1024 * 1024;
+
+/// Frees `formats` and their data. Image files are left alone.
+pub fn freeFormats(allocator: std.mem.Allocator, formats: []const Format) void {
+    for (formats) |format| allocator.free(format.data);
+    if (formats.len > 0) allocator.free(formats);
+}
+
+/// Deletes the image store files behind the `.image` formats.
+pub fn deleteFormatImages(formats: []const Format) void {
+    for (formats) |format| {
+        if (format.kind == .image and image_storage.isTempImagePath(format.data)) {
+            image_storage.deleteImageFile(format.data) catch {};
+        }
+    }
+}
+
+/// The format of kind `kind` in `formats`, if there is one.
+pub fn findFormat(formats: []const Format, kind: FormatKind) ?Format {
+    for (formats) |format| {
+        if (format.kind == kind) return format;
+    }
+    return null;
+}
+
 pub const ClipboardContent = struct {
     content: []const u8,
     type: ClipboardType,
+    source: Source = .{},
+    // Text copies only; owned like `content`
+    formats: []const Format = &.{},
+
+    /// Frees the content and its formats, deleting their image files, for copies
+    /// that aren't kept.
+    pub fn free(self: ClipboardContent, allocator: std.mem.Allocator) void {
+        allocator.free(self.content);
+        deleteFormatImages(self.formats);
+        freeFormats(allocator, self.formats);
+    }
 };
 
 pub fn getContent(allocator: std.mem.Allocator) !ClipboardContent {
@@ -77,6 +155,7 @@ pub fn getContentWithConfig(allocator: std.mem.Allocator, cfg: config.Config) !C
                     return ClipboardContent{
                         .content = final_content,
                         .type = content_type,
+                        .formats = if (content_type == .text) readFormats(allocator, cfg) else &.{},
                     };
                 },
                 .image => {
@@ -92,6 +171,16 @@ pub fn getContentWithConfig(allocator: std.mem.Allocator, cfg: config.Config) !C
                     if (file_result.term.Exited == 0) {
                         const file_content = std.mem.trim(u8, file_result.stdout, " \t\r\n");
                         if (!std.mem.eql(u8, file_content, "no_file")) {
+                            // A copy in the image store outlives the original being moved;
+                            // while image capture is off, or if copying fails, the path is kept
+                            if (cfg.store_images and cfg.save_image_data) {
+                                if (image_storage.copyImageFile(allocator, file_content)) |stored_path| {
+                                    return ClipboardContent{
+                                        .content = stored_path,
+                                        .type = .image,
+                                    };
+                                } else |_| {}
+                            }
                             // We have a file path, return it
                             const final_content = try allocator.dupe(u8, file_content);
                             return ClipboardContent{
@@ -127,6 +216,7 @@ pub fn getContentWithConfig(allocator: std.mem.Allocator, cfg: config.Config) !C
 
                     // Last resort: we know there's image data but can't get file path or meaningful text
                     // Get clipboard info to determine format, then save the image to temp storage
+                    if (!cfg.save_image_data) return ClipboardError.ImageSkipped;
                     const info_result = try std.process.Child.run(.{
                         .allocator = allocator,
                         .argv = &[_][]const u8{ "osascript", "-e", "get (clipboard info) as string" },
@@ -180,6 +270,25 @@ pub fn getContentWithConfig(allocator: std.mem.Allocator, cfg: config.Config) !C
                     };
                 },
                 .file => {
+                    // Several copied files come back as one path per line
+                    if (pasteboard.readFilePaths(allocator)) |paths| {
+                        defer {
+                            for (paths) |path| allocator.free(path);
+                            allocator.free(paths);
+                        }
+                        if (paths.len > 1) {
+                            const joined = try std.mem.join(allocator, "\n", paths);
+                            if (joined.len > cfg.max_content_size) {
+                                allocator.free(joined);
+                                return ClipboardError.NoClipboardContent;
+                            }
+                            return ClipboardContent{
+                                .content = joined,
+                                .type = .file,
+                            };
+                        }
+                    }
+
                     // First try to get file path (for copied files)
                     const file_result = try std.process.Child.run(.{
                         .allocator = allocator,
@@ -251,6 +360,33 @@ pub fn getContentWithConfig(allocator: std.mem.Allocator, cfg: config.Config) !C
     }
 }
 
+/// The other forms offered with a text copy: RTF from native apps, HTML from browsers
+/// and a picture from apps like spreadsheets. RTF and HTML larger than
+/// `max_content_size` are left out, and the picture while image capture is off; the
+/// plain text always stays.
+fn readFormats(allocator: std.mem.Allocator, cfg: config.Config) []const Format {
+    var formats = std.ArrayList(Format){};
+    for ([_]FormatKind{ .rtf, .html }) |kind| {
+        const data = pasteboard.readData(allocator, kind.pasteboardType(), cfg.max_content_size) orelse continue;
+        formats.append(allocator, .{ .kind = kind, .data = data }) catch allocator.free(data);
+    }
+    if (cfg.save_image_data) image: {
+        const data = pasteboard.readData(allocator, FormatKind.image.pasteboardType(), max_image_format_size) orelse break :image;
+        defer allocator.free(data);
+        const path = image_storage.saveImageData(allocator, data) catch break :image;
+        formats.append(allocator, .{ .kind = .image, .data = path }) catch {
+            image_storage.deleteImageFile(path) catch {};
+            allocator.free(path);
+        };
+    }
+    return formats.toOwnedSlice(allocator) catch {
+        deleteFormatImages(formats.items);
+        for (formats.items) |format| allocator.free(format.data);
+        formats.deinit(allocator);
+        return &.{};
+    };
+}
+
 fn getClipboardType(allocator: std.mem.Allocator) !ClipboardType {
     // Check for file URL first — Finder file copies have «class furl» and should
     // be classified as .file, even if they also have image data (e.g. a .png file).
@@ -377,7 +513,21 @@ pub fn setContentWithType(allocator: std.mem.Allocator, content: []const u8, ent
 
             // If this is a file entry, restore it as a file reference on the clipboard
             // so that Finder paste works correctly
-            if (entry_type == .file) {
+            if (entry_type == .file and std.mem.indexOfScalar(u8, content, '\n') != null) {
+                var paths = std.ArrayList([]const u8){};
+                defer paths.deinit(allocator);
+                var lines = std.mem.tokenizeScalar(u8, content, '\n');
+                while (lines.next()) |path| {
+                    if (!validateFilePath(path)) return ClipboardError.CommandFailed;
+                    // Files moved or deleted since the copy are left out, so the rest
+                    // still paste; the frontend tells the user which ones went missing
+                    std.fs.accessAbsolute(path, .{}) catch continue;
+                    try paths.append(allocator, path);
+                }
+                if (paths.items.len == 0) return ClipboardError.FilesMissing;
+                if (pasteboard.writeFilePaths(allocator, paths.items)) return;
+                // Fall through to text if file restore fails
+            } else if (entry_type == .file) {
                 if (!validateFilePath(content)) {
                     return ClipboardError.CommandFailed;
                 }
@@ -481,6 +631,58 @@ pub fn setContentWithType(allocator: std.mem.Allocator, content: []const u8, ent
     }
 }
 
+/// Puts text back on the clipboard together with every form it was copied with, so
+/// each app pastes the one it likes best. Falls back to the plain text alone when the
+/// pasteboard won't take them.
+pub fn setContentWithFormats(allocator: std.mem.Allocator, content: []const u8, formats: []const Format) !void {
+    switch (builtin.os.tag) {
+        .macos => {
+            var items = std.ArrayList(pasteboard.Item){};
+            defer items.deinit(allocator);
+            var images = std.ArrayList([]u8){};
+            defer {
+                for (images.items) |image| allocator.free(image);
+                images.deinit(allocator);
+            }
+
+            try items.append(allocator, .{ .type = FormatKind.text.pasteboardType(), .data = content });
+            for (formats) |format| {
+                const data = switch (format.kind) {
+                    .image => image: {
+                        // A moved or deleted picture just isn't offered
+                        const image = std.fs.cwd().readFileAlloc(allocator, format.data, max_image_format_size) catch continue;
+                        try images.append(allocator, image);
+                        break :image image;
+                    },
+                    else => format.data,
+                };
+                try items.append(allocator, .{ .type = format.kind.pasteboardType(), .data = data });
+            }
+
+            if (pasteboard.writeItems(items.items)) return;
+            return setContentWithType(allocator, content, .text);
+        },
+        else => return ClipboardError.UnsupportedPlatform,
+    }
+}
+
+/// Puts only one form of a text copy on the clipboard: the plain text, the picture,
+/// or the bare RTF or HTML.
+pub fn setContentAsFormat(allocator: std.mem.Allocator, content: []const u8, format: Format) !void {
+    switch (format.kind) {
+        .text => return setContentWithType(allocator, content, .text),
+        .image => return setContentWithType(allocator, format.data, .image),
+        .rtf, .html => switch (builtin.os.tag) {
+            .macos => {
+                if (!pasteboard.writeItems(&.{.{ .type = format.kind.pasteboardType(), .data = format.data }})) {
+                    return ClipboardError.CommandFailed;
+                }
+            },
+            else => return ClipboardError.UnsupportedPlatform,
+        },
+    }
+}
+
 fn isUrl(content: []const u8) bool {
     const prefixes = [_][]const u8{ "http://", "https://", "ftp://", "ftps://" };
     var has_prefix = false;
@@ -1,5 +1,15 @@
 const std = @import("std");
 
+/// Session-only history: what `forget_on_quit` purges when the backend exits.
+pub const ForgetOnQuit = enum {
+    off,
+    unpinned, // Every entry except pinned ones
+    all,
+};
+
+/// `max_entries` for a history that is never trimmed.
+pub const unlimited_entries = std.math.maxInt(usize);
+
 pub const Config = struct {
     // Monitoring intervals (in milliseconds)
     min_poll_interval: u64 = 100, // Minimum polling interval
@@ -13,14 +23,39 @@ pub const Config = struct {
     // Content limits
     max_content_size: usize = 100 * 1024, // 100KB per clipboard entry
     max_fetch_size: usize = 512 * 1024, // 512KB maximum fetch from system
+    save_image_data: bool = true, // Off, images with no file behind them are skipped instead of written to disk
+    store_images: bool = false, // Images copied as files are copied into the image store instead of referenced where they are
+
+    read_only: bool = false, // Never write the history file or session marker; for `--dump`
 
     // History settings
-    max_entries: usize = 10, // Maximum clipboard entries to keep
+    max_entries: usize = 10, // Maximum clipboard entries to keep; unlimited_entries keeps all
+    coalesce_duplicates: bool = true, // Copying content again moves its entry to the top and counts it
+
+    // Privacy settings
+    excluded_apps: []const []const u8 = &.{}, // Bundle IDs or app names whose copies are skipped
+    history_key: ?[32]u8 = null, // Decrypts the history file; from CLIPZ_HISTORY_KEY
+    encrypt_history: bool = false, // Write the history file encrypted with history_key
+    forget_on_quit: ForgetOnQuit = .off, // Purge on exit, and on start in case the last exit was cut short
+    capture_window_titles: bool = false, // Record the focused window's title with each copy
 
     pub fn default() Config {
         return Config{};
     }
 
+    /// Matches the frontmost app's bundle ID or name against `excluded_apps`, ignoring case.
+    pub fn isExcludedApp(self: Config, bundle_id: ?[]const u8, name: ?[]const u8) bool {
+        for (self.excluded_apps) |app| {
+            if (bundle_id) |id| {
+                if (std.ascii.eqlIgnoreCase(app, id)) return true;
+            }
+            if (name) |n| {
+                if (std.ascii.eqlIgnoreCase(app, n)) return true;
+            }
+        }
+        return false;
+    }
+
     pub fn lowPower() Config {
         return Config{
             .min_poll_interval = 250, // Slower polling
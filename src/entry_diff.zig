@@ -0,0 +1,130 @@
+const std = @import("std");
+
+/// An entry as the frontend was sent it: its id and a hash of its JSON.
+pub const Sent = struct {
+    id: u64,
+    hash: u64,
+};
+
+/// Where a new, changed or moved entry goes: `index` in the new list, right after the
+/// entry `after` (null for the top).
+pub const Placed = struct {
+    index: usize,
+    after: ?u64,
+};
+
+/// One `entry-*` message.
+pub const Change = union(enum) {
+    removed: u64,
+    added: Placed,
+    updated: Placed,
+};
+
+/// The changes that turn `previous` into `current`: removals first, then additions
+/// and updates from the top down. Applied in that order, each placed right after the
+/// entry before it, they rebuild `current` exactly. Entries whose JSON didn't change
+/// and that kept their place among the others aren't mentioned.
+pub fn diff(allocator: std.mem.Allocator, previous: []const Sent, current: []const Sent) !std.ArrayList(Change) {
+    var changes = std.ArrayList(Change){};
+    errdefer changes.deinit(allocator);
+
+    var previous_hashes = std.AutoHashMap(u64, u64).init(allocator);
+    defer previous_hashes.deinit();
+    for (previous) |entry| try previous_hashes.put(entry.id, entry.hash);
+
+    var current_hashes = std.AutoHashMap(u64, u64).init(allocator);
+    defer current_hashes.deinit();
+    for (current) |entry| try current_hashes.put(entry.id, entry.hash);
+
+    // Unchanged entries in their old order; walking it alongside `current` finds the
+    // ones that can stay where they are
+    var unchanged = std.ArrayList(u64){};
+    defer unchanged.deinit(allocator);
+    for (previous) |entry| {
+        const hash = current_hashes.get(entry.id) orelse {
+            try changes.append(allocator, .{ .removed = entry.id });
+            continue;
+        };
+        if (hash == entry.hash) try unchanged.append(allocator, entry.id);
+    }
+
+    var placed = std.AutoHashMap(u64, void).init(allocator);
+    defer placed.deinit();
+    var next_unchanged: usize = 0;
+    for (current, 0..) |entry, index| {
+        while (next_unchanged < unchanged.items.len and placed.contains(unchanged.items[next_unchanged])) {
+            next_unchanged += 1;
+        }
+        const previous_hash = previous_hashes.get(entry.id);
+        if (previous_hash == entry.hash and next_unchanged < unchanged.items.len and unchanged.items[next_unchanged] == entry.id) {
+            next_unchanged += 1;
+            continue;
+        }
+        try placed.put(entry.id, {});
+        const place = Placed{ .index = index, .after = if (index == 0) null else current[index - 1].id };
+        try changes.append(allocator, if (previous_hash == null) .{ .added = place } else .{ .updated = place });
+    }
+    return changes;
+}
+
+/// Applies `changes` to a list of ids the way the frontend does, for the tests.
+fn applyIds(allocator: std.mem.Allocator, ids: *std.ArrayList(u64), current: []const Sent, changes: []const Change) !void {
+    for (changes) |change| {
+        switch (change) {
+            .removed => |id| removeId(ids, id),
+            .added, .updated => |place| {
+                const id = current[place.index].id;
+                removeId(ids, id);
+                const at = if (place.after) |after| std.mem.indexOfScalar(u64, ids.items, after).? + 1 else 0;
+                try ids.insert(allocator, at, id);
+            },
+        }
+    }
+}
+
+fn removeId(ids: *std.ArrayList(u64), id: u64) void {
+    if (std.mem.indexOfScalar(u64, ids.items, id)) |at| _ = ids.orderedRemove(at);
+}
+
+fn expectRebuilds(previous: []const Sent, current: []const Sent, expected_changes: usize) !void {
+    const allocator = std.testing.allocator;
+    var changes = try diff(allocator, previous, current);
+    defer changes.deinit(allocator);
+    try std.testing.expectEqual(expected_changes, changes.items.len);
+
+    var ids = std.ArrayList(u64){};
+    defer ids.deinit(allocator);
+    for (previous) |entry| try ids.append(allocator, entry.id);
+    try applyIds(allocator, &ids, current, changes.items);
+
+    try std.testing.expectEqual(current.len, ids.items.len);
+    for (current, ids.items) |entry, id| try std.testing.expectEqual(entry.id, id);
+}
+
+test "a copy sends the new entry and the one it replaced as current" {
+    // 1 was current; 3 is copied, trimming 2
+    try expectRebuilds(
+        &.{ .{ .id = 1, .hash = 10 }, .{ .id = 2, .hash = 20 } },
+        &.{ .{ .id = 3, .hash = 30 }, .{ .id = 1, .hash = 11 } },
+        3,
+    );
+}
+
+test "unchanged entries in place are left out" {
+    const entries = [_]Sent{ .{ .id = 1, .hash = 10 }, .{ .id = 2, .hash = 20 }, .{ .id = 3, .hash = 30 } };
+    try expectRebuilds(&entries, &entries, 0);
+    try expectRebuilds(&entries, &.{ .{ .id = 1, .hash = 10 }, .{ .id = 2, .hash = 21 }, .{ .id = 3, .hash = 30 } }, 1);
+}
+
+test "moved and changed entries land in the new order" {
+    try expectRebuilds(
+        &.{ .{ .id = 1, .hash = 10 }, .{ .id = 2, .hash = 20 }, .{ .id = 3, .hash = 30 } },
+        &.{ .{ .id = 3, .hash = 30 }, .{ .id = 2, .hash = 21 }, .{ .id = 1, .hash = 10 } },
+        2,
+    );
+    try expectRebuilds(
+        &.{ .{ .id = 1, .hash = 10 }, .{ .id = 2, .hash = 20 }, .{ .id = 3, .hash = 30 }, .{ .id = 4, .hash = 40 } },
+        &.{ .{ .id = 4, .hash = 40 }, .{ .id = 5, .hash = 50 }, .{ .id = 2, .hash = 21 }, .{ .id = 1, .hash = 11 } },
+        4,
+    );
+}
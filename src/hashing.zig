@@ -0,0 +1,35 @@
+const std = @import("std");
+const clipboard = @import("clipboard.zig");
+
+const Sha256 = std.crypto.hash.sha2.Sha256;
+
+pub const ContentHash = [Sha256.digest_length]u8;
+pub const hex_length = Sha256.digest_length * 2;
+
+/// Identifies what was copied: SHA-256 over the type name, a zero byte and the
+/// content. Ids and timestamps are left out, so the same copy hashes the same across
+/// reloads, exports and other Macs. For images and files the content is the path.
+pub fn contentHash(entry_type: clipboard.ClipboardType, content: []const u8) ContentHash {
+    var hasher = Sha256.init(.{});
+    hasher.update(@tagName(entry_type));
+    hasher.update(&[_]u8{0});
+    hasher.update(content);
+    var hash: ContentHash = undefined;
+    hasher.final(&hash);
+    return hash;
+}
+
+/// Lowercase hex, the form the hash takes in the JSON API and exports.
+pub fn toHex(hash: ContentHash) [hex_length]u8 {
+    return std.fmt.bytesToHex(hash, .lower);
+}
+
+test "content hash depends on type and content only" {
+    const text = contentHash(.text, "https://example.com");
+    try std.testing.expectEqual(text, contentHash(.text, "https://example.com"));
+    try std.testing.expect(!std.mem.eql(u8, &text, &contentHash(.url, "https://example.com")));
+    try std.testing.expect(!std.mem.eql(u8, &text, &contentHash(.text, "https://example.org")));
+
+    // sha256("text\x00hi"), so other tools can compute it themselves
+    try std.testing.expectEqualStrings("50c3eef8a60a9c745a11c5cd295f9ddea43833cba5a195c3f76bc280e8c90d1f", &toHex(contentHash(.text, "hi")));
+}
@@ -25,10 +25,6 @@ pub fn ensureImageDir() !void {
 }
 
 fn generateImageFilename(allocator: std.mem.Allocator, format: []const u8) ![]const u8 {
-    const timestamp = std.time.timestamp();
-    var prng = std.Random.DefaultPrng.init(@intCast(std.time.milliTimestamp()));
-    const random = prng.random().int(u64);
-
     const ext = if (std.mem.eql(u8, format, "PNG") or std.mem.eql(u8, format, "PNGf"))
         "png"
     else if (std.mem.eql(u8, format, "JPEG"))
@@ -38,6 +34,13 @@ fn generateImageFilename(allocator: std.mem.Allocator, format: []const u8) ![]co
     else
         "png";
 
+    return filenameWithExtension(allocator, ext);
+}
+
+fn filenameWithExtension(allocator: std.mem.Allocator, ext: []const u8) ![]const u8 {
+    const timestamp = std.time.timestamp();
+    var prng = std.Random.DefaultPrng.init(@intCast(std.time.milliTimestamp()));
+    const random = prng.random().int(u64);
     return try std.fmt.allocPrint(allocator, "clipz_{d}_{x}.{s}", .{ timestamp, random, ext });
 }
 
@@ -98,6 +101,101 @@ pub fn saveImageFromClipboard(allocator: std.mem.Allocator, format: []const u8)
     }
 }
 
+/// Writes PNG data to a new file in the image store and returns its path.
+pub fn saveImageData(allocator: std.mem.Allocator, data: []const u8) ![]const u8 {
+    try ensureImageDir();
+
+    const filename = try generateImageFilename(allocator, "PNG");
+    defer allocator.free(filename);
+
+    const file_path = try std.fmt.allocPrint(allocator, "{s}/{s}", .{ IMAGE_STORAGE_DIR, filename });
+    errdefer allocator.free(file_path);
+
+    const file = std.fs.cwd().createFile(file_path, .{ .exclusive = true, .mode = 0o600 }) catch return ImageStorageError.FailedToSaveImage;
+    defer file.close();
+    file.writeAll(data) catch {
+        std.fs.cwd().deleteFile(file_path) catch {};
+        return ImageStorageError.FailedToSaveImage;
+    };
+    return file_path;
+}
+
+/// Largest image file `copyImageFile` takes, in bytes.
+const max_copied_image_size = 64 * 1024 * 1024;
+
+/// Copies the image at `source_path` into the image store and returns the copy's path.
+/// The copy is named for the format its first bytes show, or failing that the source's
+/// extension, so it is decoded the right way later. Files already in the store are not
+/// copied again.
+pub fn copyImageFile(allocator: std.mem.Allocator, source_path: []const u8) ![]const u8 {
+    if (isManagedTempImagePath(source_path)) return allocator.dupe(u8, source_path);
+
+    const source = std.fs.cwd().openFile(source_path, .{}) catch return ImageStorageError.InvalidPath;
+    defer source.close();
+    const stat = source.stat() catch return ImageStorageError.InvalidPath;
+    if (stat.kind != .file or stat.size > max_copied_image_size) return ImageStorageError.InvalidPath;
+
+    var header_buf: [16]u8 = undefined;
+    const header_len = source.readAll(&header_buf) catch return ImageStorageError.InvalidPath;
+    source.seekTo(0) catch return ImageStorageError.InvalidPath;
+    var ext_buf: [max_copied_extension_len]u8 = undefined;
+    const ext = copiedImageExtension(header_buf[0..header_len], source_path, &ext_buf) orelse return ImageStorageError.InvalidPath;
+
+    try ensureImageDir();
+
+    const filename = try filenameWithExtension(allocator, ext);
+    defer allocator.free(filename);
+
+    const file_path = try std.fmt.allocPrint(allocator, "{s}/{s}", .{ IMAGE_STORAGE_DIR, filename });
+    errdefer allocator.free(file_path);
+
+    const file = std.fs.cwd().createFile(file_path, .{ .exclusive = true, .mode = 0o600 }) catch return ImageStorageError.FailedToSaveImage;
+    defer file.close();
+
+    var buf: [64 * 1024]u8 = undefined;
+    while (true) {
+        const n = source.read(&buf) catch {
+            std.fs.cwd().deleteFile(file_path) catch {};
+            return ImageStorageError.FailedToSaveImage;
+        };
+        if (n == 0) break;
+        file.writeAll(buf[0..n]) catch {
+            std.fs.cwd().deleteFile(file_path) catch {};
+            return ImageStorageError.FailedToSaveImage;
+        };
+    }
+    return file_path;
+}
+
+const max_copied_extension_len = 5;
+
+/// The extension a copy of an image starting with `header` is stored under: the one
+/// for its format when the magic bytes are known, else `source_path`'s own, lowercased.
+/// Null when neither says what the file is.
+fn copiedImageExtension(header: []const u8, source_path: []const u8, buf: *[max_copied_extension_len]u8) ?[]const u8 {
+    if (std.mem.startsWith(u8, header, "\x89PNG\r\n\x1a\n")) return "png";
+    if (std.mem.startsWith(u8, header, "\xff\xd8\xff")) return "jpg";
+    if (std.mem.startsWith(u8, header, "GIF87a") or std.mem.startsWith(u8, header, "GIF89a")) return "gif";
+    if (std.mem.startsWith(u8, header, "II*\x00") or std.mem.startsWith(u8, header, "MM\x00*")) return "tiff";
+    if (std.mem.startsWith(u8, header, "BM")) return "bmp";
+    if (header.len >= 12 and std.mem.eql(u8, header[0..4], "RIFF") and std.mem.eql(u8, header[8..12], "WEBP")) return "webp";
+    if (header.len >= 12 and std.mem.eql(u8, header[4..8], "ftyp")) {
+        const brand = header[8..12];
+        for ([_][]const u8{ "heic", "heix", "hevc", "mif1", "msf1" }) |heic| {
+            if (std.mem.eql(u8, brand, heic)) return "heic";
+        }
+        if (std.mem.eql(u8, brand, "avif")) return "avif";
+    }
+
+    const ext = std.fs.path.extension(source_path);
+    if (ext.len < 2 or ext.len - 1 > buf.len) return null;
+    for (ext[1..], 0..) |c, i| {
+        if (!std.ascii.isAlphanumeric(c)) return null;
+        buf[i] = std.ascii.toLower(c);
+    }
+    return buf[0 .. ext.len - 1];
+}
+
 fn isManagedTempImagePath(path: []const u8) bool {
     return std.mem.eql(u8, path, IMAGE_STORAGE_DIR) or std.mem.startsWith(u8, path, IMAGE_STORAGE_PREFIX);
 }
@@ -151,3 +249,14 @@ pub fn compareImageFiles(file1_path: []const u8, file2_path: []const u8) !bool {
     // (very unlikely for two different images to have same size and first 1KB)
     return std.mem.eql(u8, buffer1[0..read_size], buffer2[0..read_size]);
 }
+
+test "copied images are named for the format their bytes show" {
+    var buf: [max_copied_extension_len]u8 = undefined;
+    try std.testing.expectEqualStrings("gif", copiedImageExtension("GIF89a\x01\x00", "/Users/me/cat.png", &buf).?);
+    try std.testing.expectEqualStrings("webp", copiedImageExtension("RIFF\x00\x00\x00\x00WEBPVP8 ", "/Users/me/a", &buf).?);
+    try std.testing.expectEqualStrings("heic", copiedImageExtension("\x00\x00\x00\x18ftypheic", "/Users/me/IMG_1.HEIC", &buf).?);
+    try std.testing.expectEqualStrings("jpg", copiedImageExtension("\xff\xd8\xff\xe0", "/Users/me/photo.tif", &buf).?);
+    // Unknown bytes keep the source's extension, or aren't copied at all
+    try std.testing.expectEqualStrings("jxl", copiedImageExtension("\x00\x00", "/Users/me/art.JXL", &buf).?);
+    try std.testing.expectEqual(@as(?[]const u8, null), copiedImageExtension("\x00\x00", "/Users/me/notes", &buf));
+}
@@ -2,6 +2,12 @@ const std = @import("std");
 const manager = @import("manager.zig");
 const ui = @import("ui.zig");
 const config = @import("config.zig");
+const protocol = @import("protocol.zig");
+const snippets = @import("snippets.zig");
+const hashing = @import("hashing.zig");
+const timeline = @import("timeline.zig");
+const entry_diff = @import("entry_diff.zig");
+const search = @import("search.zig");
 
 pub fn main() !void {
     var gpa = std.heap.GeneralPurposeAllocator(.{}){};
@@ -15,10 +21,17 @@ pub fn main() !void {
     defer std.process.argsFree(allocator, args);
 
     // Parse command line arguments and determine config
-    const parse_result = parseArguments(args) catch |err| {
+    var parse_result = parseArguments(allocator, args) catch |err| {
         printUsage();
         return err;
     };
+    // Freed after the manager, which reads it until its deinit
+    defer allocator.free(parse_result.config.excluded_apps);
+    parse_result.config.history_key = try readHistoryKey(allocator);
+    if (parse_result.config.encrypt_history and parse_result.config.history_key == null) {
+        std.debug.print("--encrypt-history needs a key in {s}\n", .{history_key_env});
+        return error.InvalidArgument;
+    }
 
     var clipboard_manager = try manager.ClipboardManager.initWithConfig(allocator, parse_result.config);
     defer clipboard_manager.deinit();
@@ -32,12 +45,44 @@ pub fn main() !void {
             // JSON API mode for Electron communication
             try runJsonApi(allocator, &clipboard_manager);
         },
+        .dump => try sendClipboardEntries(allocator, std.fs.File.stdout(), &clipboard_manager),
+    }
+}
+
+const history_key_env = "CLIPZ_HISTORY_KEY";
+
+extern "c" fn unsetenv(name: [*:0]const u8) c_int;
+
+/// Reads the 64-hex-digit history key from the environment, then removes it so the
+/// osascript children spawned for clipboard reads don't inherit it.
+fn readHistoryKey(allocator: std.mem.Allocator) !?[32]u8 {
+    const hex = std.process.getEnvVarOwned(allocator, history_key_env) catch |err| switch (err) {
+        error.EnvironmentVariableNotFound => return null,
+        else => return err,
+    };
+    defer {
+        std.crypto.secureZero(u8, hex);
+        allocator.free(hex);
     }
+    _ = unsetenv(history_key_env);
+
+    var key: [32]u8 = undefined;
+    const decoded = std.fmt.hexToBytes(&key, hex) catch {
+        std.debug.print("{s} must be 64 hex digits\n", .{history_key_env});
+        return error.InvalidArgument;
+    };
+    if (decoded.len != key.len) {
+        std.debug.print("{s} must be 64 hex digits\n", .{history_key_env});
+        return error.InvalidArgument;
+    }
+    return key;
 }
 
 const RunMode = enum {
     cli,
     json_api,
+    /// Prints the saved history once and exits, leaving the file as it was.
+    dump,
 };
 
 const ParseResult = struct {
@@ -45,9 +90,21 @@ const ParseResult = struct {
     config: config.Config,
 };
 
-fn parseArguments(args: []const []const u8) !ParseResult {
+/// The returned config's `excluded_apps` is owned by the caller, who frees it with
+/// `allocator`.
+fn parseArguments(allocator: std.mem.Allocator, args: []const []const u8) !ParseResult {
     var mode: RunMode = .cli; // Default to CLI mode
     var cfg = config.Config.default();
+    var max_entries_override: ?usize = null;
+    var encrypt_history = false;
+    var coalesce_duplicates = true;
+    var capture_window_titles = false;
+    var store_images = false;
+    var dump = false;
+    var forget_on_quit: config.ForgetOnQuit = .off;
+    // Points into `args`, which outlive the manager; the list itself goes to the caller
+    var excluded_apps = std.ArrayList([]const u8){};
+    errdefer excluded_apps.deinit(allocator);
 
     if (args.len == 1) {
         return ParseResult{ .mode = mode, .config = cfg };
@@ -59,12 +116,57 @@ fn parseArguments(args: []const []const u8) !ParseResult {
 
         if (std.mem.eql(u8, flag, "--json-api") or std.mem.eql(u8, flag, "-j")) {
             mode = .json_api;
+        } else if (std.mem.eql(u8, flag, "--dump")) {
+            dump = true;
         } else if (std.mem.eql(u8, flag, "--cli") or std.mem.eql(u8, flag, "-c")) {
             mode = .cli;
         } else if (std.mem.eql(u8, flag, "--low-power") or std.mem.eql(u8, flag, "-l")) {
             cfg = config.Config.lowPower();
         } else if (std.mem.eql(u8, flag, "--responsive") or std.mem.eql(u8, flag, "-r")) {
             cfg = config.Config.responsive();
+        } else if (std.mem.eql(u8, flag, "--max-entries") or std.mem.eql(u8, flag, "-m")) {
+            i += 1;
+            if (i >= args.len) {
+                std.debug.print("Missing value for {s}\n", .{flag});
+                return error.InvalidArgument;
+            }
+            const max_entries = if (std.mem.eql(u8, args[i], "unlimited"))
+                config.unlimited_entries
+            else
+                std.fmt.parseInt(usize, args[i], 10) catch {
+                    std.debug.print("Invalid value for {s}: {s}\n", .{ flag, args[i] });
+                    return error.InvalidArgument;
+                };
+            if (max_entries == 0) {
+                std.debug.print("{s} must be at least 1\n", .{flag});
+                return error.InvalidArgument;
+            }
+            max_entries_override = max_entries;
+        } else if (std.mem.eql(u8, flag, "--no-coalesce")) {
+            coalesce_duplicates = false;
+        } else if (std.mem.eql(u8, flag, "--exclude-app") or std.mem.eql(u8, flag, "-x")) {
+            i += 1;
+            if (i >= args.len or args[i].len == 0) {
+                std.debug.print("Missing value for {s}\n", .{flag});
+                return error.InvalidArgument;
+            }
+            try excluded_apps.append(allocator, args[i]);
+        } else if (std.mem.eql(u8, flag, "--store-images")) {
+            store_images = true;
+        } else if (std.mem.eql(u8, flag, "--encrypt-history")) {
+            encrypt_history = true;
+        } else if (std.mem.eql(u8, flag, "--window-titles")) {
+            capture_window_titles = true;
+        } else if (std.mem.eql(u8, flag, "--forget-on-quit")) {
+            i += 1;
+            if (i >= args.len) {
+                std.debug.print("Missing value for {s}\n", .{flag});
+                return error.InvalidArgument;
+            }
+            forget_on_quit = std.meta.stringToEnum(config.ForgetOnQuit, args[i]) orelse {
+                std.debug.print("Invalid value for {s}: {s} (expected off, unpinned or all)\n", .{ flag, args[i] });
+                return error.InvalidArgument;
+            };
         } else if (std.mem.eql(u8, flag, "--help") or std.mem.eql(u8, flag, "-h")) {
             printUsage();
             return error.HelpRequested;
@@ -76,6 +178,22 @@ fn parseArguments(args: []const []const u8) !ParseResult {
         i += 1;
     }
 
+    // Applied after the loop so profile flags don't reset it
+    if (max_entries_override) |max_entries| {
+        cfg.max_entries = max_entries;
+    }
+    cfg.coalesce_duplicates = coalesce_duplicates;
+    cfg.store_images = store_images;
+    // Whatever else is asked for, a dump runs beside the real backend without touching its file
+    if (dump) {
+        mode = .dump;
+        cfg.read_only = true;
+    }
+    cfg.excluded_apps = try excluded_apps.toOwnedSlice(allocator);
+    cfg.encrypt_history = encrypt_history;
+    cfg.forget_on_quit = forget_on_quit;
+    cfg.capture_window_titles = capture_window_titles;
+
     return ParseResult{ .mode = mode, .config = cfg };
 }
 
@@ -88,12 +206,35 @@ fn printUsage() void {
         \\Mode Options:
         \\  -c, --cli       Run in CLI mode (default)
         \\  -j, --json-api  Run in JSON API mode for Electron integration
+        \\  --dump          Print the saved history as an entries message and exit,
+        \\                  without monitoring the clipboard or writing the file
         \\
         \\Performance Options:
         \\  -l, --low-power     Low power mode (slower polling, longer saves)
         \\  -r, --responsive    Responsive mode (faster polling, frequent saves)
         \\  (default)           Balanced mode
         \\
+        \\History Options:
+        \\  -m, --max-entries <n>  Maximum clipboard entries to keep (default 10), or
+        \\                         "unlimited"
+        \\  --no-coalesce          Ignore copies of content already in the history
+        \\                         instead of moving it to the top with a count
+        \\  --store-images         Keep a copy of images copied as files, so entries
+        \\                         still work after the original is moved or deleted
+        \\
+        \\Privacy Options:
+        \\  -x, --exclude-app <app>  Skip copies made while this app is frontmost
+        \\                           (bundle ID or name; repeatable)
+        \\  --encrypt-history        Save history encrypted with the key in
+        \\                           CLIPZ_HISTORY_KEY (64 hex digits). The key is also
+        \\                           used to read an encrypted file without this flag
+        \\  --forget-on-quit <what>  Purge the history when quitting: off, unpinned
+        \\                           (keep pinned entries) or all. Leftovers from a
+        \\                           session cut short by the Mac restarting go at
+        \\                           start; restarting the backend keeps them
+        \\  --window-titles          Record the focused window's title (document name,
+        \\                           browser tab) with each copy; needs Accessibility
+        \\
         \\Other Options:
         \\  -h, --help      Show this help message
         \\
@@ -117,13 +258,16 @@ fn printUsage() void {
 fn sendEntriesCallback(manager_ptr: *manager.ClipboardManager) void {
     const stdout = std.fs.File.stdout();
     const allocator = manager_ptr.allocator;
-    sendClipboardEntries(allocator, stdout, manager_ptr) catch {};
+    sendEntryChanges(allocator, stdout, manager_ptr) catch {};
 }
 
 // NEW: JSON API mode for Electron communication
 fn runJsonApi(allocator: std.mem.Allocator, clipboard_manager: *manager.ClipboardManager) !void {
     clipboard_manager.entries_changed_callback = sendEntriesCallback;
 
+    var snippet_store = try snippets.SnippetStore.init(allocator);
+    defer snippet_store.deinit();
+
     // Start clipboard monitoring in background
     try clipboard_manager.startMonitoring();
     defer clipboard_manager.stopMonitoring();
@@ -132,151 +276,336 @@ fn runJsonApi(allocator: std.mem.Allocator, clipboard_manager: *manager.Clipboar
     const stdout = std.fs.File.stdout();
 
     // Send ready signal with capability flags for frontend compatibility
-    try stdout.writeAll("{\"type\":\"ready\",\"supportsIdCommands\":true}\n");
+    try stdout.writeAll("{\"type\":\"ready\",\"supportsIdCommands\":true,\"supportsRequestIds\":true}\n");
 
-    var buffer: [1024]u8 = undefined;
+    // Large enough for set-clipboard payloads carrying a full entry's content
+    const buffer = try allocator.alloc(u8, clipboard_manager.config.max_fetch_size * 2);
+    defer allocator.free(buffer);
+    const reader = stdin.deprecatedReader();
     while (true) {
-        if (try stdin.deprecatedReader().readUntilDelimiterOrEof(buffer[0..], '\n')) |line| {
-            if (line.len >= buffer.len - 1) {
-                clipboard_manager.stdout_mutex.lock();
-                defer clipboard_manager.stdout_mutex.unlock();
-                try stdout.writeAll("{\"type\":\"error\",\"message\":\"Command too long\"}\n");
-                continue;
-            }
+        if (try readCommandLine(reader, buffer)) |command_line| {
+            const line = switch (command_line) {
+                .line => |line| line,
+                .too_long => {
+                    clipboard_manager.stdout_mutex.lock();
+                    defer clipboard_manager.stdout_mutex.unlock();
+                    try sendError(allocator, stdout, null, .command_too_long, null);
+                    continue;
+                },
+            };
             const trimmed = std.mem.trim(u8, line, " \t\r\n");
+            const request = protocol.parseRequest(allocator, trimmed);
+            defer request.deinit(allocator);
 
-            if (std.mem.eql(u8, trimmed, "quit")) {
+            if (request.command == .quit) {
+                clipboard_manager.endSession();
                 break;
-            } else if (std.mem.eql(u8, trimmed, "get-entries")) {
+            }
+            try handleRequest(allocator, stdout, clipboard_manager, &snippet_store, request);
+        } else {
+            break;
+        }
+    }
+}
+
+const CommandLine = union(enum) {
+    line: []const u8,
+    /// Longer than the buffer; the rest of it was skipped.
+    too_long,
+};
+
+/// Reads the next command from `reader`, or null at the end of input. A line too long
+/// for `buffer` is skipped up to its newline, so the commands after it still work.
+fn readCommandLine(reader: anytype, buffer: []u8) !?CommandLine {
+    const line = reader.readUntilDelimiterOrEof(buffer, '\n') catch |err| switch (err) {
+        error.StreamTooLong => {
+            try reader.skipUntilDelimiterOrEof('\n');
+            return .too_long;
+        },
+        else => return err,
+    };
+    return if (line) |complete| .{ .line = complete } else null;
+}
+
+fn handleRequest(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager, snippet_store: *snippets.SnippetStore, request: protocol.Request) !void {
+    const request_id = request.id;
+
+    switch (request.command) {
+        .quit => {},
+        .hello => |frontend_version| {
+            if (frontend_version != protocol.version) {
+                std.debug.print("Frontend speaks protocol {d}, backend {d}\n", .{ frontend_version, protocol.version });
+            }
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendHello(allocator, stdout, request_id);
+        },
+        .invalid => |code| {
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendError(allocator, stdout, request_id, code, null);
+        },
+        .get_entries => |page| {
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendClipboardEntriesForRequest(allocator, stdout, clipboard_manager, request_id, page);
+        },
+        .search => |payload| {
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSearchResults(allocator, stdout, clipboard_manager, request_id, payload);
+        },
+        .select_entry_id => |entry_id| {
+            clipboard_manager.selectEntryById(entry_id) catch |err| {
                 clipboard_manager.stdout_mutex.lock();
                 defer clipboard_manager.stdout_mutex.unlock();
-                try sendClipboardEntries(allocator, stdout, clipboard_manager);
-            } else if (std.mem.startsWith(u8, trimmed, "select-entry-id:")) {
-                const id_str = trimmed["select-entry-id:".len..];
-                if (std.fmt.parseInt(u64, id_str, 10)) |entry_id| {
-                    clipboard_manager.selectEntryById(entry_id) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendSelectResultById(allocator, stdout, entry_id);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                }
-            } else if (std.mem.startsWith(u8, trimmed, "select-entry:")) {
-                const index_str = trimmed["select-entry:".len..];
-                if (std.fmt.parseInt(usize, index_str, 10)) |index| {
-                    clipboard_manager.selectEntry(index) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendSelectResultByIndex(allocator, stdout, index);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                }
-            } else if (std.mem.startsWith(u8, trimmed, "remove-entry-id:")) {
-                const id_str = trimmed["remove-entry-id:".len..];
-                if (std.fmt.parseInt(u64, id_str, 10)) |entry_id| {
-                    clipboard_manager.removeEntryById(entry_id) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendRemoveResultById(allocator, stdout, entry_id);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                }
-            } else if (std.mem.startsWith(u8, trimmed, "remove-entry:")) {
-                const index_str = trimmed["remove-entry:".len..];
-                if (std.fmt.parseInt(usize, index_str, 10)) |index| {
-                    clipboard_manager.removeEntry(index) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendRemoveResultByIndex(allocator, stdout, index);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                }
-            } else if (std.mem.startsWith(u8, trimmed, "toggle-pin-id:")) {
-                const id_str = trimmed["toggle-pin-id:".len..];
-                if (std.fmt.parseInt(u64, id_str, 10)) |entry_id| {
-                    const pinned = clipboard_manager.togglePinnedById(entry_id) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendPinResultById(allocator, stdout, entry_id, pinned);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid id\"}\n");
-                }
-            } else if (std.mem.startsWith(u8, trimmed, "toggle-pin:")) {
-                const index_str = trimmed["toggle-pin:".len..];
-                if (std.fmt.parseInt(usize, index_str, 10)) |index| {
-                    const pinned = clipboard_manager.togglePinned(index) catch {
-                        clipboard_manager.stdout_mutex.lock();
-                        defer clipboard_manager.stdout_mutex.unlock();
-                        try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                        continue;
-                    };
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try sendPinResultByIndex(allocator, stdout, index, pinned);
-                    try sendClipboardEntries(allocator, stdout, clipboard_manager);
-                } else |_| {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Invalid index\"}\n");
-                }
-            } else if (std.mem.eql(u8, trimmed, "clear")) {
-                clipboard_manager.clearHistory() catch {
-                    clipboard_manager.stdout_mutex.lock();
-                    defer clipboard_manager.stdout_mutex.unlock();
-                    try stdout.writeAll("{\"type\":\"error\",\"message\":\"Failed to clear history\"}\n");
-                    continue;
+                return switch (err) {
+                    error.FilesMissing => sendError(allocator, stdout, request_id, .operation_failed, "None of the copied files exist anymore"),
+                    else => sendError(allocator, stdout, request_id, .invalid_id, null),
                 };
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSelectResultById(allocator, stdout, request_id, entry_id);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .select_format => |payload| {
+            clipboard_manager.selectFormatById(payload.entry_id, payload.kind) catch |err| {
                 clipboard_manager.stdout_mutex.lock();
                 defer clipboard_manager.stdout_mutex.unlock();
-                try stdout.writeAll("{\"type\":\"success\",\"message\":\"History cleared\"}\n");
-            } else {
+                return switch (err) {
+                    error.InvalidIndex => sendError(allocator, stdout, request_id, .invalid_id, null),
+                    error.NoSuchFormat => sendError(allocator, stdout, request_id, .invalid_request, "Entry has no such format"),
+                    else => sendError(allocator, stdout, request_id, .operation_failed, "Failed to copy format"),
+                };
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSelectResultById(allocator, stdout, request_id, payload.entry_id);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .select_entry => |index| {
+            clipboard_manager.selectEntry(index) catch {
                 clipboard_manager.stdout_mutex.lock();
                 defer clipboard_manager.stdout_mutex.unlock();
-                try stdout.writeAll("{\"type\":\"error\",\"message\":\"Unknown command\"}\n");
-            }
-        } else {
-            break;
-        }
+                return sendError(allocator, stdout, request_id, .invalid_index, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSelectResultByIndex(allocator, stdout, request_id, index);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .remove_entry_id => |entry_id| {
+            clipboard_manager.removeEntryById(entry_id) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_id, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendRemoveResultById(allocator, stdout, request_id, entry_id);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .restore_entry_id => |entry_id| {
+            const restored = clipboard_manager.restoreEntryById(entry_id) catch |err| {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return switch (err) {
+                    error.InvalidIndex => sendError(allocator, stdout, request_id, .invalid_id, "Entry is not in the trash"),
+                    error.HistoryFull => sendError(allocator, stdout, request_id, .operation_failed, "History is full of pinned entries"),
+                    else => sendError(allocator, stdout, request_id, .operation_failed, "Failed to restore entry"),
+                };
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, if (restored) "Entry restored" else "Entry already in history");
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+            try sendTrash(allocator, stdout, clipboard_manager, null);
+        },
+        .purge_entry_id => |entry_id| {
+            clipboard_manager.purgeEntryById(entry_id) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_id, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "Entry purged");
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+            try sendTrash(allocator, stdout, clipboard_manager, null);
+        },
+        .get_trash => {
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendTrash(allocator, stdout, clipboard_manager, request_id);
+        },
+        .empty_trash => {
+            clipboard_manager.emptyTrash();
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "Trash emptied");
+            try sendTrash(allocator, stdout, clipboard_manager, null);
+        },
+        .remove_entry => |index| {
+            clipboard_manager.removeEntry(index) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_index, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendRemoveResultByIndex(allocator, stdout, request_id, index);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .toggle_pin_id => |entry_id| {
+            const pinned = clipboard_manager.togglePinnedById(entry_id) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_id, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendPinResultById(allocator, stdout, request_id, entry_id, pinned);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .toggle_pin => |index| {
+            const pinned = clipboard_manager.togglePinned(index) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_index, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendPinResultByIndex(allocator, stdout, request_id, index, pinned);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .swap_entry_id => |entry_id| {
+            clipboard_manager.swapEntryById(entry_id) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_id, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSelectResultById(allocator, stdout, request_id, entry_id);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .set_clipboard => |payload| {
+            clipboard_manager.setClipboardContent(payload.content, payload.entry_type) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .operation_failed, "Failed to set clipboard");
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "Clipboard updated");
+        },
+        .add_entry => |payload| {
+            const added = clipboard_manager.addEntryBelowCurrent(payload.content, payload.entry_type, payload.pinned, payload.tags) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .operation_failed, "Failed to add entry");
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, if (added) "Entry added" else "Entry already in history");
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .import_entries => |entries| {
+            const result = clipboard_manager.importEntries(entries) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                try sendError(allocator, stdout, request_id, .operation_failed, "Failed to import entries");
+                // Some may have gone in before the failure
+                return sendEntryChanges(allocator, stdout, clipboard_manager);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendImportResult(allocator, stdout, request_id, result);
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .set_paused => |paused| {
+            clipboard_manager.setPaused(paused);
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, if (paused) "Monitoring paused" else "Monitoring resumed");
+        },
+        .set_append_mode => |enabled| {
+            clipboard_manager.setAppendMode(enabled);
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, if (enabled) "Append mode on" else "Append mode off");
+        },
+        .set_image_capture => |enabled| {
+            clipboard_manager.setImageCapture(enabled);
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, if (enabled) "Image capture on" else "Image capture off");
+        },
+        .set_tags => |payload| {
+            clipboard_manager.setTagsById(payload.entry_id, payload.tags) catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .invalid_id, null);
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "Tags updated");
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .get_snippets => {
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSnippets(allocator, stdout, snippet_store, request_id);
+        },
+        .save_snippet => |payload| {
+            _ = snippet_store.save(payload.id, payload.name, payload.content) catch |err| {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                if (err == snippets.SnippetError.InvalidId) return sendError(allocator, stdout, request_id, .invalid_id, null);
+                return sendError(allocator, stdout, request_id, .operation_failed, "Failed to save snippet");
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSnippets(allocator, stdout, snippet_store, request_id);
+        },
+        .remove_snippet_id => |snippet_id| {
+            snippet_store.remove(snippet_id) catch |err| {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                if (err == snippets.SnippetError.InvalidId) return sendError(allocator, stdout, request_id, .invalid_id, null);
+                return sendError(allocator, stdout, request_id, .operation_failed, "Failed to remove snippet");
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSnippets(allocator, stdout, snippet_store, request_id);
+        },
+        .get_timeline => |limit| {
+            var events: [timeline.capacity]timeline.Event = undefined;
+            const wanted = if (limit) |n| @min(n, events.len) else events.len;
+            const newest = clipboard_manager.timeline.latest(events[0..wanted]);
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendTimeline(allocator, stdout, request_id, newest);
+        },
+        .set_limit => |max_entries| {
+            const trimmed = clipboard_manager.setMaxEntries(max_entries);
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "History limit updated");
+            if (trimmed) try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
+        .clear => {
+            clipboard_manager.clearHistory() catch {
+                clipboard_manager.stdout_mutex.lock();
+                defer clipboard_manager.stdout_mutex.unlock();
+                return sendError(allocator, stdout, request_id, .operation_failed, "Failed to clear history");
+            };
+            clipboard_manager.stdout_mutex.lock();
+            defer clipboard_manager.stdout_mutex.unlock();
+            try sendSuccess(allocator, stdout, request_id, "History cleared");
+            try sendEntryChanges(allocator, stdout, clipboard_manager);
+        },
     }
 }
 
@@ -296,70 +625,389 @@ fn appendJsonEscapedString(allocator: std.mem.Allocator, output: *std.ArrayList(
     }
 }
 
+/// The entries the frontend holds, as it was sent them in pages or as changes: the
+/// newest part of the history, in order, or all of it when `complete`.
+const SentWindow = struct {
+    entries: std.ArrayList(entry_diff.Sent) = .{},
+    complete: bool = false,
+};
+
+/// What the next change is diffed against, so it can go out as `entry-*` messages;
+/// null until a page from the top has gone out. Guarded by `stdout_mutex`, so it
+/// always matches what was written.
+var sent_window: ?SentWindow = null;
+
 fn sendClipboardEntries(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager) !void {
+    return sendClipboardEntriesForRequest(allocator, stdout, clipboard_manager, null, .{});
+}
+
+/// Writes the entries in `page`. `total` is the full history length, so a client
+/// loading page by page knows how much is left. A page from the top starts the sent
+/// window over and the page right after it extends it, like the frontend's list;
+/// others leave it alone.
+fn sendClipboardEntriesForRequest(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager, request_id: ?u64, page: protocol.EntriesPage) !void {
     var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
     defer manager.ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
 
-    try stdout.writeAll("{\"type\":\"entries\",\"data\":[");
+    const total = snapshot.items.len;
+    const start = @min(page.offset, total);
+    const end = if (page.limit) |limit| @min(total, start + limit) else total;
+    const extends_window = if (sent_window) |window| !window.complete and start == window.entries.items.len and end > start else false;
+    const tracked = start == 0 or extends_window;
+    var sent = std.ArrayList(entry_diff.Sent){};
+    defer sent.deinit(clipboard_manager.allocator);
 
-    for (snapshot.items, 0..) |entry, i| {
+    var request_id_buf: [32]u8 = undefined;
+    const header = try std.fmt.allocPrint(allocator, "{{\"type\":\"entries\"{s},\"total\":{d},\"offset\":{d},\"data\":[", .{ protocol.requestIdField(&request_id_buf, request_id), total, start });
+    defer allocator.free(header);
+    try stdout.writeAll(header);
+
+    for (snapshot.items[start..end], 0..) |entry, i| {
         if (i > 0) {
             try stdout.writeAll(",");
         }
 
-        var escaped_content = std.ArrayList(u8){};
-        defer escaped_content.deinit(allocator);
+        const json_entry = try entryJson(allocator, entry);
+        defer allocator.free(json_entry);
+
+        try stdout.writeAll(json_entry);
+        if (tracked) try sent.append(clipboard_manager.allocator, .{ .id = entry.id, .hash = std.hash.Wyhash.hash(0, json_entry) });
+    }
+
+    try stdout.writeAll("]}\n");
+    if (!tracked) return;
+    if (start == 0) {
+        rememberSent(clipboard_manager.allocator, &sent, end == total);
+    } else {
+        const window = &sent_window.?;
+        try window.entries.appendSlice(clipboard_manager.allocator, sent.items);
+        window.complete = end == total;
+    }
+}
 
-        try appendJsonEscapedString(allocator, &escaped_content, entry.content);
+/// Makes `sent` the window the next changes are diffed against, taking its items.
+fn rememberSent(allocator: std.mem.Allocator, sent: *std.ArrayList(entry_diff.Sent), complete: bool) void {
+    if (sent_window) |*previous| previous.entries.deinit(allocator);
+    sent_window = .{ .entries = sent.*, .complete = complete };
+    sent.* = .{};
+}
+
+/// Tells the frontend what changed in the entries it holds: one `entry-removed`,
+/// `entry-added` or `entry-updated` message per entry, each with the history's new
+/// `total`, rather than the whole list again. A window short of the whole history
+/// keeps its length, so entries pushed past its end are removed from it. Sends the
+/// whole list when there is nothing to diff against.
+fn sendEntryChanges(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager) !void {
+    const previous = sent_window orelse return sendClipboardEntries(allocator, stdout, clipboard_manager);
 
-        const entry_type_str = switch (entry.entry_type) {
-            .text => "text",
-            .image => "image",
-            .file => "file",
-            .url => "url",
-            .color => "color",
+    var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
+    defer manager.ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    const total = snapshot.items.len;
+    const window_len = if (previous.complete) total else @min(total, previous.entries.items.len);
+
+    // Each entry's JSON, kept for the ones that turn out to have changed
+    var jsons = std.ArrayList([]u8){};
+    defer {
+        for (jsons.items) |json| allocator.free(json);
+        jsons.deinit(allocator);
+    }
+    var current = std.ArrayList(entry_diff.Sent){};
+    defer current.deinit(clipboard_manager.allocator);
+    for (snapshot.items[0..window_len]) |entry| {
+        const json_entry = try entryJson(allocator, entry);
+        jsons.append(allocator, json_entry) catch |err| {
+            allocator.free(json_entry);
+            return err;
         };
-        const json_entry = try std.fmt.allocPrint(allocator, "{{\"id\":{d},\"content\":\"{s}\",\"timestamp\":{d},\"type\":\"{s}\",\"isCurrent\":{s},\"pinned\":{s}}}", .{ entry.id, escaped_content.items, entry.timestamp * 1000, entry_type_str, if (entry.is_current) "true" else "false", if (entry.pinned) "true" else "false" });
+        try current.append(clipboard_manager.allocator, .{ .id = entry.id, .hash = std.hash.Wyhash.hash(0, json_entry) });
+    }
+
+    var changes = try entry_diff.diff(allocator, previous.entries.items, current.items);
+    defer changes.deinit(allocator);
+
+    var response = std.ArrayList(u8){};
+    defer response.deinit(allocator);
+    const writer = response.writer(allocator);
+    for (changes.items) |change| {
+        switch (change) {
+            .removed => |id| try writer.print("{{\"type\":\"entry-removed\",\"id\":{d},\"total\":{d}}}\n", .{ id, total }),
+            .added, .updated => |place| {
+                try writer.print("{{\"type\":\"{s}\",\"after\":", .{if (change == .added) "entry-added" else "entry-updated"});
+                if (place.after) |after| try writer.print("{d}", .{after}) else try response.appendSlice(allocator, "null");
+                try writer.print(",\"total\":{d},\"data\":{s}}}\n", .{ total, jsons.items[place.index] });
+            },
+        }
+    }
+    try stdout.writeAll(response.items);
+    rememberSent(clipboard_manager.allocator, &current, window_len == total);
+}
+
+const SearchMatch = struct {
+    index: usize,
+    score: i64,
+
+    fn better(_: void, a: SearchMatch, b: SearchMatch) bool {
+        return a.score > b.score;
+    }
+};
+
+/// Entries matching `payload`, best match first and then newest first, answering
+/// `search`. `total` counts every match, so a client that asked for fewer knows how
+/// many were left out. The results aren't part of the sent window; changes to them
+/// come from searching again.
+fn sendSearchResults(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager, request_id: ?u64, payload: protocol.Search) !void {
+    var query = try search.Query.parse(allocator, payload.query);
+    defer query.deinit(allocator);
+    var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
+    defer manager.ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+
+    var found_texts = std.StringHashMap([]const u8).init(allocator);
+    defer found_texts.deinit();
+    for (payload.texts) |text| try found_texts.put(text.content, text.text);
+
+    var matches = std.ArrayList(SearchMatch){};
+    defer matches.deinit(allocator);
+    for (snapshot.items, 0..) |entry, index| {
+        if (payload.types.count() > 0 and !payload.types.contains(entry.entry_type)) continue;
+        const score = query.score(.{
+            .content = entry.content,
+            .tags = entry.tags,
+            .source_app = entry.source_app,
+            .window_title = entry.window_title,
+            .found_text = found_texts.get(entry.content) orelse "",
+        }) orelse continue;
+        try matches.append(allocator, .{ .index = index, .score = score });
+    }
+    // Stable, so entries scoring the same stay newest first
+    std.mem.sort(SearchMatch, matches.items, {}, SearchMatch.better);
+    const total = matches.items.len;
+    const sent = if (payload.limit) |limit| @min(limit, total) else total;
+
+    var data = std.ArrayList(u8){};
+    defer data.deinit(allocator);
+    for (matches.items[0..sent], 0..) |match, position| {
+        if (position > 0) try data.append(allocator, ',');
+        const json_entry = try entryJson(allocator, snapshot.items[match.index]);
         defer allocator.free(json_entry);
+        try data.appendSlice(allocator, json_entry);
+    }
 
-        try stdout.writeAll(json_entry);
+    var escaped_query = std.ArrayList(u8){};
+    defer escaped_query.deinit(allocator);
+    try appendJsonEscapedString(allocator, &escaped_query, payload.query);
+
+    var request_id_buf: [32]u8 = undefined;
+    var response = std.ArrayList(u8){};
+    defer response.deinit(allocator);
+    try response.writer(allocator).print("{{\"type\":\"search-results\"{s},\"query\":\"{s}\",\"total\":{d},\"data\":[", .{ protocol.requestIdField(&request_id_buf, request_id), escaped_query.items, total });
+    try response.appendSlice(allocator, data.items);
+    try response.appendSlice(allocator, "]}\n");
+    try stdout.writeAll(response.items);
+}
+
+/// One entry as the JSON object the frontend reads; trashed ones add `removedAt`.
+fn entryJson(allocator: std.mem.Allocator, entry: manager.DisplayEntrySnapshot) ![]u8 {
+    var escaped_content = std.ArrayList(u8){};
+    defer escaped_content.deinit(allocator);
+
+    try appendJsonEscapedString(allocator, &escaped_content, entry.content);
+
+    const entry_type_str = switch (entry.entry_type) {
+        .text => "text",
+        .image => "image",
+        .file => "file",
+        .url => "url",
+        .color => "color",
+    };
+    // Tags are validated on the way in, so they can be written without escaping
+    var tags_json = std.ArrayList(u8){};
+    defer tags_json.deinit(allocator);
+    if (entry.tags.len > 0) {
+        var tags = std.mem.splitScalar(u8, entry.tags, ',');
+        while (tags.next()) |tag| {
+            if (tags_json.items.len > 0) try tags_json.append(allocator, ',');
+            try tags_json.writer(allocator).print("\"{s}\"", .{tag});
+        }
     }
 
-    try stdout.writeAll("]}\n");
+    var escaped_app = std.ArrayList(u8){};
+    defer escaped_app.deinit(allocator);
+    try appendJsonEscapedString(allocator, &escaped_app, entry.source_app);
+    var escaped_title = std.ArrayList(u8){};
+    defer escaped_title.deinit(allocator);
+    try appendJsonEscapedString(allocator, &escaped_title, entry.window_title);
+
+    // Only the kinds; the forms themselves stay in the backend
+    var formats_json = std.ArrayList(u8){};
+    defer formats_json.deinit(allocator);
+    if (entry.formats.count() > 0) {
+        try formats_json.appendSlice(allocator, ",\"formats\":[");
+        var kinds = entry.formats.iterator();
+        var first = true;
+        while (kinds.next()) |kind| {
+            if (!first) try formats_json.append(allocator, ',');
+            try formats_json.writer(allocator).print("\"{s}\"", .{@tagName(kind)});
+            first = false;
+        }
+        try formats_json.append(allocator, ']');
+    }
+
+    const hash_hex = hashing.toHex(entry.hash);
+    const json = try std.fmt.allocPrint(allocator, "{{\"id\":{d},\"content\":\"{s}\",\"timestamp\":{d},\"type\":\"{s}\",\"isCurrent\":{s},\"pinned\":{s},\"tags\":[{s}],\"hash\":\"{s}\",\"copies\":{d},\"sourceApp\":\"{s}\",\"windowTitle\":\"{s}\"{s}}}", .{ entry.id, escaped_content.items, entry.timestamp * 1000, entry_type_str, if (entry.is_current) "true" else "false", if (entry.pinned) "true" else "false", tags_json.items, &hash_hex, entry.copies, escaped_app.items, escaped_title.items, formats_json.items });
+    const removed_at = entry.removed_at orelse return json;
+    defer allocator.free(json);
+    return std.fmt.allocPrint(allocator, "{s},\"removedAt\":{d}}}", .{ json[0 .. json.len - 1], removed_at * 1000 });
+}
+
+/// Every entry in the trash, most recently removed first, answering `get-trash` and
+/// after each change to it.
+fn sendTrash(allocator: std.mem.Allocator, stdout: std.fs.File, clipboard_manager: *manager.ClipboardManager, request_id: ?u64) !void {
+    var snapshot = try clipboard_manager.snapshotTrash(allocator);
+    defer manager.ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+
+    var request_id_buf: [32]u8 = undefined;
+    var response = std.ArrayList(u8){};
+    defer response.deinit(allocator);
+    try response.writer(allocator).print("{{\"type\":\"trash\"{s},\"data\":[", .{protocol.requestIdField(&request_id_buf, request_id)});
+    for (snapshot.items, 0..) |entry, i| {
+        if (i > 0) try response.append(allocator, ',');
+        const json_entry = try entryJson(allocator, entry);
+        defer allocator.free(json_entry);
+        try response.appendSlice(allocator, json_entry);
+    }
+    try response.appendSlice(allocator, "]}\n");
+    try stdout.writeAll(response.items);
+}
+
+/// Every snippet, answering `get-snippets` and after each change to them.
+fn sendSnippets(allocator: std.mem.Allocator, stdout: std.fs.File, snippet_store: *snippets.SnippetStore, request_id: ?u64) !void {
+    var request_id_buf: [32]u8 = undefined;
+    var response = std.ArrayList(u8){};
+    defer response.deinit(allocator);
+    try response.writer(allocator).print("{{\"type\":\"snippets\"{s},\"data\":", .{protocol.requestIdField(&request_id_buf, request_id)});
+    try snippet_store.writeJson(allocator, &response);
+    try response.appendSlice(allocator, "}\n");
+    try stdout.writeAll(response.items);
+}
+
+/// Recent clipboard events, oldest first, answering `get-timeline`.
+fn sendTimeline(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, events: []const timeline.Event) !void {
+    var request_id_buf: [32]u8 = undefined;
+    var response = std.ArrayList(u8){};
+    defer response.deinit(allocator);
+    const writer = response.writer(allocator);
+    try writer.print("{{\"type\":\"timeline\"{s},\"data\":[", .{protocol.requestIdField(&request_id_buf, request_id)});
+
+    for (events, 0..) |*event, i| {
+        if (i > 0) try response.append(allocator, ',');
+        try writer.print("{{\"timestamp\":{d},\"changeCount\":{d},\"outcome\":\"{s}\",", .{ event.timestamp, event.change_count, event.outcome.name() });
+        if (event.entry_type) |entry_type| {
+            try writer.print("\"type\":\"{s}\",", .{@tagName(entry_type)});
+        } else {
+            try response.appendSlice(allocator, "\"type\":null,");
+        }
+        try writer.print("\"size\":{d},\"latencyUs\":{d},\"app\":", .{ event.size, event.latency_us });
+        if (event.app_len > 0) {
+            try response.append(allocator, '"');
+            try appendJsonEscapedString(allocator, &response, event.app());
+            try response.append(allocator, '"');
+        } else {
+            try response.appendSlice(allocator, "null");
+        }
+        try response.append(allocator, '}');
+    }
+
+    try response.appendSlice(allocator, "]}\n");
+    try stdout.writeAll(response.items);
+}
+
+fn sendSelectResultById(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, entry_id: u64) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"select-success\",\"id\":{d}{s}}}\n", .{ entry_id, protocol.requestIdField(&request_id_buf, request_id) });
+    defer allocator.free(response);
+    try stdout.writeAll(response);
 }
 
-fn sendSelectResultById(allocator: std.mem.Allocator, stdout: std.fs.File, entry_id: u64) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"select-success\",\"id\":{d}}}\n", .{entry_id});
+fn sendSelectResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, index: usize) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"select-success\",\"index\":{d}{s}}}\n", .{ index, protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
 
-fn sendSelectResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, index: usize) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"select-success\",\"index\":{d}}}\n", .{index});
+fn sendRemoveResultById(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, entry_id: u64) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"remove-success\",\"id\":{d}{s}}}\n", .{ entry_id, protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
 
-fn sendRemoveResultById(allocator: std.mem.Allocator, stdout: std.fs.File, entry_id: u64) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"remove-success\",\"id\":{d}}}\n", .{entry_id});
+fn sendRemoveResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, index: usize) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"remove-success\",\"index\":{d}{s}}}\n", .{ index, protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
 
-fn sendRemoveResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, index: usize) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"remove-success\",\"index\":{d}}}\n", .{index});
+fn sendPinResultById(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, entry_id: u64, pinned: bool) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"pin-toggled\",\"id\":{d},\"pinned\":{s}{s}}}\n", .{ entry_id, if (pinned) "true" else "false", protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
 
-fn sendPinResultById(allocator: std.mem.Allocator, stdout: std.fs.File, entry_id: u64, pinned: bool) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"pin-toggled\",\"id\":{d},\"pinned\":{s}}}\n", .{ entry_id, if (pinned) "true" else "false" });
+fn sendImportResult(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, result: manager.ClipboardManager.ImportResult) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"imported\",\"added\":{d},\"existing\":{d},\"skipped\":{d}{s}}}\n", .{ result.added, result.existing, result.skipped, protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
 
-fn sendPinResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, index: usize, pinned: bool) !void {
-    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"pin-toggled\",\"index\":{d},\"pinned\":{s}}}\n", .{ index, if (pinned) "true" else "false" });
+fn sendPinResultByIndex(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, index: usize, pinned: bool) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"pin-toggled\",\"index\":{d},\"pinned\":{s}{s}}}\n", .{ index, if (pinned) "true" else "false", protocol.requestIdField(&request_id_buf, request_id) });
     defer allocator.free(response);
     try stdout.writeAll(response);
 }
+
+fn sendHello(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"hello\",\"protocolVersion\":{d}{s}}}\n", .{ protocol.version, protocol.requestIdField(&request_id_buf, request_id) });
+    defer allocator.free(response);
+    try stdout.writeAll(response);
+}
+
+fn sendSuccess(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, message: []const u8) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"success\",\"message\":\"{s}\"{s}}}\n", .{ message, protocol.requestIdField(&request_id_buf, request_id) });
+    defer allocator.free(response);
+    try stdout.writeAll(response);
+}
+
+fn sendError(allocator: std.mem.Allocator, stdout: std.fs.File, request_id: ?u64, code: protocol.ErrorCode, message: ?[]const u8) !void {
+    var request_id_buf: [32]u8 = undefined;
+    const response = try std.fmt.allocPrint(allocator, "{{\"type\":\"error\",\"code\":\"{s}\",\"message\":\"{s}\"{s}}}\n", .{ code.name(), message orelse code.defaultMessage(), protocol.requestIdField(&request_id_buf, request_id) });
+    defer allocator.free(response);
+    try stdout.writeAll(response);
+}
+
+test "a command line too long for the buffer is skipped, not fatal" {
+    var stream = std.io.fixedBufferStream("get-entries\n" ++ "x" ** 40 ++ "\nquit\n");
+    const reader = stream.reader();
+    var buffer: [16]u8 = undefined;
+
+    try std.testing.expectEqualStrings("get-entries", (try readCommandLine(reader, &buffer)).?.line);
+    try std.testing.expect((try readCommandLine(reader, &buffer)).? == .too_long);
+    try std.testing.expectEqualStrings("quit", (try readCommandLine(reader, &buffer)).?.line);
+    try std.testing.expectEqual(@as(?CommandLine, null), try readCommandLine(reader, &buffer));
+}
+
+test "excluded apps are handed to the caller, and freed on a bad argument" {
+    const allocator = std.testing.allocator;
+    const result = try parseArguments(allocator, &.{ "clipz", "-x", "Safari", "--exclude-app", "1Password" });
+    defer allocator.free(result.config.excluded_apps);
+    try std.testing.expectEqual(@as(usize, 2), result.config.excluded_apps.len);
+    try std.testing.expectEqualStrings("1Password", result.config.excluded_apps[1]);
+
+    try std.testing.expectError(error.InvalidArgument, parseArguments(allocator, &.{ "clipz", "-x", "Safari", "--bogus" }));
+}
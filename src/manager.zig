@@ -1,13 +1,21 @@
 const std = @import("std");
+const builtin = @import("builtin");
 const clipboard = @import("clipboard.zig");
 const ui = @import("ui.zig");
 const persistence = @import("persistence.zig");
 const config = @import("config.zig");
 const image_storage = @import("image_storage.zig");
 const pasteboard = @import("pasteboard.zig");
+const hashing = @import("hashing.zig");
+const timeline = @import("timeline.zig");
+const protocol = @import("protocol.zig");
 
 pub const ClipboardManagerError = error{
     InvalidIndex,
+    // Restoring needs room, and every entry in the history is pinned
+    HistoryFull,
+    // `select-format` for a form the entry wasn't copied with
+    NoSuchFormat,
 };
 
 pub const ClipboardEntry = struct {
@@ -16,6 +24,17 @@ pub const ClipboardEntry = struct {
     timestamp: i64,
     entry_type: clipboard.ClipboardType,
     pinned: bool = false,
+    // Comma-separated tags; when non-empty, owned by the same allocator as `content`
+    tags: []const u8 = "",
+    // hashing.contentHash of the type and content; update it whenever either changes
+    hash: hashing.ContentHash,
+    // Times the content was copied; repeats coalesce into one entry and bump this
+    copies: u32 = 1,
+    // Frontmost app and window title at the latest copy; owned like `tags` when non-empty
+    source_app: []const u8 = "",
+    window_title: []const u8 = "",
+    // Other forms of the latest copy, restored with the text; owned like `content`
+    formats: []const clipboard.Format = &.{},
 
     pub fn create(allocator: std.mem.Allocator, id: u64, content: []const u8, entry_type: clipboard.ClipboardType) !ClipboardEntry {
         const content_copy = try allocator.dupe(u8, content);
@@ -25,14 +44,57 @@ pub const ClipboardEntry = struct {
             .timestamp = std.time.timestamp(),
             .entry_type = entry_type,
             .pinned = false,
+            .hash = hashing.contentHash(entry_type, content),
         };
     }
 
     pub fn free(self: ClipboardEntry, allocator: std.mem.Allocator) void {
         allocator.free(self.content);
+        if (self.tags.len > 0) allocator.free(self.tags);
+        if (self.source_app.len > 0) allocator.free(self.source_app);
+        if (self.window_title.len > 0) allocator.free(self.window_title);
+        clipboard.freeFormats(allocator, self.formats);
+    }
+
+    /// Takes over `formats`, those of the latest copy, dropping the old ones and
+    /// their image files.
+    pub fn setFormats(self: *ClipboardEntry, allocator: std.mem.Allocator, formats: []const clipboard.Format) void {
+        clipboard.deleteFormatImages(self.formats);
+        clipboard.freeFormats(allocator, self.formats);
+        self.formats = formats;
+    }
+
+    /// Every form the entry can be put on the clipboard in, the content's own first.
+    pub fn formatKinds(self: ClipboardEntry) std.EnumSet(clipboard.FormatKind) {
+        var kinds = std.EnumSet(clipboard.FormatKind).initOne(.text);
+        for (self.formats) |format| kinds.insert(format.kind);
+        return kinds;
+    }
+
+    /// Replaces the source with a copy of `source`. An unknown source keeps the old one.
+    pub fn setSource(self: *ClipboardEntry, allocator: std.mem.Allocator, source: clipboard.Source) !void {
+        if (source.isEmpty()) return;
+        const app = if (source.app.len > 0) try allocator.dupe(u8, source.app) else "";
+        errdefer if (app.len > 0) allocator.free(app);
+        const title = if (source.window_title.len > 0) try allocator.dupe(u8, source.window_title) else "";
+        if (self.source_app.len > 0) allocator.free(self.source_app);
+        if (self.window_title.len > 0) allocator.free(self.window_title);
+        self.source_app = app;
+        self.window_title = title;
     }
 };
 
+/// How long a removed entry stays in the trash before it is purged, in seconds.
+pub const trash_seconds: i64 = 30 * 24 * 60 * 60;
+
+/// A removed entry, kept with the position it had so restoring can put it back
+/// there. Its image file is only deleted once the entry is purged.
+pub const TrashedEntry = struct {
+    entry: ClipboardEntry,
+    index: usize,
+    removed_at: i64,
+};
+
 pub const DisplayEntrySnapshot = struct {
     id: u64,
     content: []const u8,
@@ -40,14 +102,28 @@ pub const DisplayEntrySnapshot = struct {
     entry_type: clipboard.ClipboardType,
     pinned: bool,
     is_current: bool,
+    tags: []const u8,
+    hash: hashing.ContentHash,
+    copies: u32,
+    source_app: []const u8,
+    window_title: []const u8,
+    // Set for text entries copied with other forms too; `.text` is always in it then
+    formats: std.EnumSet(clipboard.FormatKind) = .initEmpty(),
+    // When the entry was removed, for entries in the trash
+    removed_at: ?i64 = null,
 
     pub fn free(self: DisplayEntrySnapshot, allocator: std.mem.Allocator) void {
         allocator.free(self.content);
+        if (self.tags.len > 0) allocator.free(self.tags);
+        if (self.source_app.len > 0) allocator.free(self.source_app);
+        if (self.window_title.len > 0) allocator.free(self.window_title);
     }
 };
 
 pub const ClipboardManager = struct {
     entries: std.ArrayList(ClipboardEntry),
+    // Entries removed in the last `trash_seconds`, oldest removal first
+    trash: std.ArrayList(TrashedEntry) = .empty,
     allocator: std.mem.Allocator,
     max_entries: usize,
     next_entry_id: u64,
@@ -55,12 +131,25 @@ pub const ClipboardManager = struct {
     state_mutex: std.Thread.Mutex = .{},
     monitor_thread: ?std.Thread = null,
     should_monitor: std.atomic.Value(bool) = std.atomic.Value(bool).init(false),
+    // While set, clipboard changes are skipped instead of recorded
+    paused: std.atomic.Value(bool) = std.atomic.Value(bool).init(false),
+    // While set, text copies are joined onto one accumulator entry instead of added
+    append_mode: std.atomic.Value(bool) = std.atomic.Value(bool).init(false),
+    // Off while disk space is low, so copied images aren't written to disk
+    image_capture: std.atomic.Value(bool) = std.atomic.Value(bool).init(true),
+    // Accumulator for append mode; null until the first copy after switching it on
+    append_target_id: ?u64 = null,
+    // Pasteboard change count after our own last write, so the monitor doesn't count
+    // it as a repeated copy; guarded by state_mutex
+    own_change_count: i64 = -1,
     persistence: persistence.Persistence,
     // Batched persistence fields
     dirty_flag: std.atomic.Value(bool) = std.atomic.Value(bool).init(false),
     last_save_time: std.atomic.Value(i64) = std.atomic.Value(i64).init(0),
     // Configuration
     config: config.Config,
+    // Recent clipboard changes and what became of them, for `get-timeline`
+    timeline: timeline.Timeline = .{},
     // Callback for notifying when entries change (for JSON API)
     entries_changed_callback: ?*const fn (*ClipboardManager) void = null,
     // Mutex for thread-safe stdout writes (used in JSON API mode)
@@ -76,7 +165,11 @@ pub const ClipboardManager = struct {
         return initWithConfigAndPersistence(allocator, cfg, pers);
     }
 
-    fn initWithConfigAndPersistence(allocator: std.mem.Allocator, cfg: config.Config, pers: persistence.Persistence) !ClipboardManager {
+    fn initWithConfigAndPersistence(allocator: std.mem.Allocator, cfg: config.Config, pers_without_key: persistence.Persistence) !ClipboardManager {
+        var pers = pers_without_key;
+        pers.key = cfg.history_key;
+        pers.encrypt = cfg.encrypt_history;
+
         var manager = ClipboardManager{
             .entries = .empty,
             .allocator = allocator,
@@ -88,6 +181,7 @@ pub const ClipboardManager = struct {
         };
 
         try manager.loadFromPersistence();
+        manager.beginSession();
 
         return manager;
     }
@@ -105,6 +199,11 @@ pub const ClipboardManager = struct {
             entry.free(self.allocator);
         }
         self.entries.deinit(self.allocator);
+        // Trashed entries are saved, so their image files stay
+        for (self.trash.items) |trashed| {
+            trashed.entry.free(self.allocator);
+        }
+        self.trash.deinit(self.allocator);
         if (self.last_content) |content| {
             self.allocator.free(content);
             self.last_content = null;
@@ -118,34 +217,53 @@ pub const ClipboardManager = struct {
                 entry.free(self.allocator);
             }
             loaded_result.entries.deinit(self.allocator);
+            for (loaded_result.trash.items) |trashed| {
+                trashed.entry.free(self.allocator);
+            }
+            loaded_result.trash.deinit(self.allocator);
         }
 
         while (loaded_result.entries.items.len > self.max_entries) {
             const eviction_index = findOldestUnpinnedEntry(loaded_result.entries.items) orelse 0;
             const removed = loaded_result.entries.orderedRemove(eviction_index);
-            self.allocator.free(removed.content);
+            removed.free(self.allocator);
         }
 
         for (loaded_result.entries.items) |entry| {
             const content_copy = try self.allocator.dupe(u8, entry.content);
-            const new_entry = ClipboardEntry{
+            const tags_copy = if (entry.tags.len > 0) try self.allocator.dupe(u8, entry.tags) else "";
+            var new_entry = ClipboardEntry{
                 .id = entry.id,
                 .content = content_copy,
                 .timestamp = entry.timestamp,
                 .entry_type = entry.entry_type,
                 .pinned = entry.pinned,
+                .tags = tags_copy,
+                .hash = entry.hash,
+                .copies = entry.copies,
             };
+            errdefer new_entry.free(self.allocator);
+            try new_entry.setSource(self.allocator, .{ .app = entry.source_app, .window_title = entry.window_title });
+            new_entry.formats = try dupeFormats(self.allocator, entry.formats);
             try self.entries.append(self.allocator, new_entry);
         }
         self.next_entry_id = loaded_result.next_entry_id;
         if (self.next_entry_id == 0) self.next_entry_id = 1;
+
+        self.trash = loaded_result.trash;
+        loaded_result.trash = .empty;
+        if (self.purgeTrashLocked(std.time.timestamp())) {
+            self.dirty_flag.store(true, .release);
+        }
     }
 
     fn saveToPersistenceLocked(self: *ClipboardManager) !void {
-        try self.persistence.saveEntries(self.allocator, self.entries.items, self.next_entry_id);
+        if (self.config.read_only) return;
+        try self.persistence.saveEntries(self.allocator, self.entries.items, self.trash.items, self.next_entry_id);
     }
 
-    pub fn addEntry(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !void {
+    /// Returns false when the content was a duplicate and nothing changed.
+    pub fn addEntry(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
         var entry_added = false;
         {
             self.state_mutex.lock();
@@ -153,8 +271,193 @@ pub const ClipboardManager = struct {
             entry_added = try self.addEntryLocked(clipboard_content);
         }
 
-        if (!entry_added) return;
+        if (entry_added) self.notifyEntriesChanged();
+        return entry_added;
+    }
+
+    /// Like `addEntry`, but a copy of content already in the history moves that entry
+    /// to the top and counts the copy instead of being ignored. `change_count` is the
+    /// pasteboard's at the copy: our own writes, and copies whose count is unknown
+    /// (polling without a change count sees the same content again and again), are
+    /// added as usual. Returns whether anything changed.
+    pub fn recordCopy(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent, change_count: i64) !bool {
+        var changed = false;
+        {
+            self.state_mutex.lock();
+            defer self.state_mutex.unlock();
+            changed = if (change_count == -1 or change_count == self.own_change_count)
+                try self.addEntryLocked(clipboard_content)
+            else
+                try self.coalesceLocked(clipboard_content);
+        }
+
+        if (changed) self.notifyEntriesChanged();
+        return changed;
+    }
+
+    fn coalesceLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
+        const real_index = self.findDuplicateLocked(clipboard_content) orelse {
+            return self.addEntryLocked(clipboard_content);
+        };
+        const existing = self.entries.orderedRemove(real_index);
+        if (clipboard_content.type == .image and image_storage.isTempImagePath(clipboard_content.content) and
+            !std.mem.eql(u8, existing.content, clipboard_content.content))
+        {
+            image_storage.deleteImageFile(clipboard_content.content) catch {};
+        }
+        self.allocator.free(clipboard_content.content);
+
+        var promoted = existing;
+        promoted.copies +|= 1;
+        promoted.timestamp = std.time.timestamp();
+        promoted.setFormats(self.allocator, clipboard_content.formats);
+        // Out of memory keeps the source of the earlier copy
+        promoted.setSource(self.allocator, clipboard_content.source) catch {};
+        self.entries.appendAssumeCapacity(promoted);
+
+        if (self.last_content) |last| {
+            self.allocator.free(last);
+        }
+        self.last_content = try self.allocator.dupe(u8, promoted.content);
+
+        self.dirty_flag.store(true, .release);
+        self.trySavePersistenceLocked();
+        return true;
+    }
+
+    fn noteOwnWriteLocked(self: *ClipboardManager) void {
+        self.own_change_count = pasteboard.getChangeCount() orelse -1;
+    }
+
+    /// Append-mode counterpart of `addEntry`. The first text copy starts the accumulator;
+    /// later ones are added to it on a new line and the joined text is put back on the
+    /// clipboard, so pasting gives the whole collection. Other types are added as usual.
+    pub fn appendEntry(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
+        var changed = false;
+        var joined: ?[]u8 = null;
+        defer if (joined) |text| self.allocator.free(text);
+        {
+            self.state_mutex.lock();
+            defer self.state_mutex.unlock();
+            changed = try self.appendEntryLocked(clipboard_content, &joined);
+        }
+
+        // Written without the lock; setting the clipboard spawns osascript
+        const written = if (joined) |text| self.setOwnText(text) else {};
+        if (changed) self.notifyEntriesChanged();
+        try written;
+        return changed;
+    }
+
+    fn setOwnText(self: *ClipboardManager, text: []const u8) !void {
+        try clipboard.setContentWithType(self.allocator, text, .text);
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        self.noteOwnWriteLocked();
+    }
+
+    /// Joins the copy onto the accumulator. The joined text for the clipboard is
+    /// returned in `joined`, for the caller to write once the lock is released.
+    fn appendEntryLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent, joined_out: *?[]u8) !bool {
+        if (clipboard_content.type != .text and clipboard_content.type != .url) {
+            return self.addEntryLocked(clipboard_content);
+        }
+
+        const target_index = if (self.append_target_id) |id| self.findRealIndexByIdLocked(id) else null;
+        const real_index = target_index orelse {
+            return self.startAccumulatorLocked(clipboard_content);
+        };
+        const target = &self.entries.items[real_index];
+
+        // Our own write of the joined text coming back from the monitor
+        if (std.mem.eql(u8, target.content, clipboard_content.content)) {
+            clipboard_content.free(self.allocator);
+            return false;
+        }
+        if (target.content.len + 1 + clipboard_content.content.len > self.config.max_content_size) {
+            return self.startAccumulatorLocked(clipboard_content);
+        }
+
+        const joined = try std.mem.concat(self.allocator, u8, &.{ target.content, "\n", clipboard_content.content });
+        // The joined text is plain; other forms of the parts don't carry over
+        clipboard_content.free(self.allocator);
+        self.allocator.free(target.content);
+        target.content = joined;
+        target.setFormats(self.allocator, &.{});
+        target.entry_type = .text;
+        target.hash = hashing.contentHash(.text, joined);
+        target.timestamp = std.time.timestamp();
+        target.setSource(self.allocator, clipboard_content.source) catch {};
+
+        // Promote the accumulator to current; the caller makes the clipboard match it
+        const accumulator = self.entries.orderedRemove(real_index);
+        self.entries.appendAssumeCapacity(accumulator);
+        joined_out.* = try self.allocator.dupe(u8, accumulator.content);
+
+        if (self.last_content) |last| {
+            self.allocator.free(last);
+        }
+        self.last_content = try self.allocator.dupe(u8, accumulator.content);
+
+        self.dirty_flag.store(true, .release);
+        self.trySavePersistenceLocked();
+        return true;
+    }
+
+    /// Adds the copy as usual and makes the resulting entry the accumulator. A copy
+    /// already in the history promotes the existing entry instead.
+    fn startAccumulatorLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
+        const content_hash = hashing.contentHash(clipboard_content.type, clipboard_content.content);
+        var existing_index: ?usize = null;
+        for (self.entries.items, 0..) |entry, index| {
+            if (std.mem.eql(u8, &entry.hash, &content_hash)) {
+                existing_index = index;
+            }
+        }
+
+        const added = try self.addEntryLocked(clipboard_content);
+        if (!added) {
+            const index = existing_index orelse return false;
+            const existing = self.entries.orderedRemove(index);
+            self.entries.appendAssumeCapacity(existing);
+            self.dirty_flag.store(true, .release);
+        }
+        if (self.entries.items.len == 0) return added;
+        self.append_target_id = self.entries.items[self.entries.items.len - 1].id;
+        return true;
+    }
+
+    pub fn setAppendMode(self: *ClipboardManager, enabled: bool) void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        self.append_mode.store(enabled, .release);
+        self.append_target_id = null;
+    }
+
+    /// Changes how many entries are kept, dropping the oldest unpinned ones that no
+    /// longer fit. The current entry and pinned ones are never dropped, so the history
+    /// can stay above a small limit. Returns whether anything was removed.
+    pub fn setMaxEntries(self: *ClipboardManager, max_entries: usize) bool {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        self.max_entries = max_entries;
+        var removed = false;
+        while (self.entries.items.len > max_entries) {
+            const older = self.entries.items[0 .. self.entries.items.len - 1];
+            const eviction_index = findOldestUnpinnedEntry(older) orelse break;
+            const oldest = self.entries.orderedRemove(eviction_index);
+            deleteEntry(self.allocator, oldest);
+            removed = true;
+        }
+        if (removed) {
+            self.dirty_flag.store(true, .release);
+            self.forceSavePersistenceLocked();
+        }
+        return removed;
+    }
 
+    fn notifyEntriesChanged(self: *ClipboardManager) void {
         if (self.entries_changed_callback) |callback| {
             self.stdout_mutex.lock();
             defer self.stdout_mutex.unlock();
@@ -166,65 +469,180 @@ pub const ClipboardManager = struct {
         }
     }
 
-    fn addEntryLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
-        // Check if content already exists in any entry
-        for (self.entries.items) |existing_entry| {
-            if (std.mem.eql(u8, existing_entry.content, clipboard_content.content) and
-                existing_entry.entry_type == clipboard_content.type)
-            {
-                // Free the clipboard content since we're not using it
-                self.allocator.free(clipboard_content.content);
-                return false; // Don't add duplicate content
+    /// Records content in the history without touching the system clipboard. It lands
+    /// just below the current entry, so the current one keeps matching the clipboard.
+    /// `pinned` and `tags` (comma-separated, already validated) carry over imported
+    /// entries. Returns false when the content is already in the history.
+    pub fn addEntryBelowCurrent(self: *ClipboardManager, content: []const u8, entry_type: clipboard.ClipboardType, pinned: bool, tags: []const u8) !bool {
+        const content_copy = try self.allocator.dupe(u8, content);
+        const tags_copy = if (tags.len > 0) try self.allocator.dupe(u8, tags) else "";
+
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        const added = self.insertEntryLocked(.{ .content = content_copy, .type = entry_type }, true) catch |err| {
+            if (tags_copy.len > 0) self.allocator.free(tags_copy);
+            return err;
+        };
+        if (!added) {
+            if (tags_copy.len > 0) self.allocator.free(tags_copy);
+            return false;
+        }
+
+        const items = self.entries.items;
+        const entry = &items[if (items.len > 1) items.len - 2 else 0];
+        entry.pinned = pinned;
+        entry.tags = tags_copy;
+        // The insert may already have saved without them
+        self.dirty_flag.store(true, .release);
+        return true;
+    }
+
+    /// What `importEntries` did with each entry it was given.
+    pub const ImportResult = struct {
+        added: usize = 0,
+        /// Content already in the history, which is left as it is.
+        existing: usize = 0,
+        /// Left out because the history is full.
+        skipped: usize = 0,
+    };
+
+    /// Merges entries from an export or a peer into the history under one lock, saving
+    /// once. Each keeps its timestamp, pin and tags and goes among the others by when
+    /// it was copied, but never above the current entry. Content already in the history
+    /// takes the later of the two timestamps. An import never evicts anything: once the
+    /// history is full, the rest are skipped.
+    pub fn importEntries(self: *ClipboardManager, imported: []const protocol.ImportEntry) !ImportResult {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        // Whatever was added before a failure is still saved
+        defer self.forceSavePersistenceLocked();
+
+        var result = ImportResult{};
+        for (imported) |item| {
+            if (self.findDuplicateLocked(.{ .content = item.content, .type = item.entry_type })) |index| {
+                if (item.timestamp) |timestamp| self.refreshTimestampLocked(index, timestamp);
+                result.existing += 1;
+                continue;
+            }
+            if (self.entries.items.len >= self.max_entries) {
+                result.skipped += 1;
+                continue;
             }
+
+            var entry = try ClipboardEntry.create(self.allocator, self.next_entry_id, item.content, item.entry_type);
+            errdefer entry.free(self.allocator);
+            if (item.timestamp) |timestamp| entry.timestamp = timestamp;
+            entry.pinned = item.pinned;
+            if (item.tags.len > 0) entry.tags = try self.allocator.dupe(u8, item.tags);
+            try self.entries.insert(self.allocator, self.importIndexLocked(entry.timestamp), entry);
+
+            self.next_entry_id +%= 1;
+            if (self.next_entry_id == 0) self.next_entry_id = 1;
+            self.dirty_flag.store(true, .release);
+            result.added += 1;
+        }
+        return result;
+    }
+
+    /// Moves the entry at `index` to where a copy made at `timestamp` goes, when that is
+    /// later than its own. The current entry stays where it is.
+    fn refreshTimestampLocked(self: *ClipboardManager, index: usize, timestamp: i64) void {
+        if (self.entries.items[index].timestamp >= timestamp) return;
+        self.dirty_flag.store(true, .release);
+        if (index + 1 == self.entries.items.len) {
+            self.entries.items[index].timestamp = timestamp;
+            return;
+        }
+        var entry = self.entries.orderedRemove(index);
+        entry.timestamp = timestamp;
+        // The slot just freed leaves room for it
+        self.entries.insertAssumeCapacity(self.importIndexLocked(timestamp), entry);
+    }
+
+    /// Where an entry copied at `timestamp` goes: after the entries copied before it,
+    /// but below the current one, which matches the clipboard.
+    fn importIndexLocked(self: *ClipboardManager, timestamp: i64) usize {
+        const items = self.entries.items;
+        var index: usize = 0;
+        while (index + 1 < items.len and items[index].timestamp <= timestamp) index += 1;
+        return index;
+    }
+
+    fn addEntryLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) !bool {
+        return self.insertEntryLocked(clipboard_content, false);
+    }
+
+    /// Index of the entry holding the same content, if any.
+    fn findDuplicateLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent) ?usize {
+        const new_hash = hashing.contentHash(clipboard_content.type, clipboard_content.content);
+        for (self.entries.items, 0..) |existing_entry, index| {
+            if (std.mem.eql(u8, &existing_entry.hash, &new_hash)) return index;
         }
 
         // Special handling for images: check if we already have the same image
         // by comparing file contents (since file paths are always unique)
         if (clipboard_content.type == .image and image_storage.isTempImagePath(clipboard_content.content)) {
-            for (self.entries.items) |existing_entry| {
+            for (self.entries.items, 0..) |existing_entry, index| {
                 if (existing_entry.entry_type == .image and
                     image_storage.isTempImagePath(existing_entry.content))
                 {
-                    // Compare the image files to see if they're the same
                     if (image_storage.compareImageFiles(existing_entry.content, clipboard_content.content) catch false) {
-                        // Same image, delete the new file and skip adding
-                        image_storage.deleteImageFile(clipboard_content.content) catch {};
-                        self.allocator.free(clipboard_content.content);
-                        return false; // Don't add duplicate image
+                        return index;
                     }
                 }
             }
         }
+        return null;
+    }
 
-        const entry = try ClipboardEntry.create(self.allocator, self.next_entry_id, clipboard_content.content, clipboard_content.type);
+    fn insertEntryLocked(self: *ClipboardManager, clipboard_content: clipboard.ClipboardContent, below_current: bool) !bool {
+        if (self.findDuplicateLocked(clipboard_content)) |index| {
+            // Same image in a new file: delete the new file and skip adding
+            if (clipboard_content.type == .image and image_storage.isTempImagePath(clipboard_content.content) and
+                !std.mem.eql(u8, self.entries.items[index].content, clipboard_content.content))
+            {
+                image_storage.deleteImageFile(clipboard_content.content) catch {};
+            }
+            // Free the clipboard content since we're not using it
+            clipboard_content.free(self.allocator);
+            return false; // Don't add duplicate content
+        }
+
+        var entry = ClipboardEntry.create(self.allocator, self.next_entry_id, clipboard_content.content, clipboard_content.type) catch |err| {
+            clipboard_content.free(self.allocator);
+            return err;
+        };
         // Free the original clipboard content since we made a copy
         self.allocator.free(clipboard_content.content);
+        entry.formats = clipboard_content.formats;
+        entry.setSource(self.allocator, clipboard_content.source) catch |err| {
+            entry.free(self.allocator);
+            return err;
+        };
         self.next_entry_id +%= 1;
         if (self.next_entry_id == 0) self.next_entry_id = 1;
 
         if (self.entries.items.len >= self.max_entries) {
             const eviction_index = self.findOldestUnpinnedIndex() orelse {
                 // All entries are pinned, so we ignore the new clipboard item.
-                if (entry.entry_type == .image and image_storage.isTempImagePath(entry.content)) {
-                    image_storage.deleteImageFile(entry.content) catch {};
-                }
-                entry.free(self.allocator);
+                deleteEntry(self.allocator, entry);
                 return false;
             };
 
             const oldest = self.entries.orderedRemove(eviction_index);
-            if (oldest.entry_type == .image and image_storage.isTempImagePath(oldest.content)) {
-                image_storage.deleteImageFile(oldest.content) catch {};
-            }
-            oldest.free(self.allocator);
+            deleteEntry(self.allocator, oldest);
         }
 
-        try self.entries.append(self.allocator, entry);
+        if (below_current and self.entries.items.len > 0) {
+            try self.entries.insert(self.allocator, self.entries.items.len - 1, entry);
+        } else {
+            try self.entries.append(self.allocator, entry);
 
-        if (self.last_content) |last| {
-            self.allocator.free(last);
+            if (self.last_content) |last| {
+                self.allocator.free(last);
+            }
+            self.last_content = try self.allocator.dupe(u8, entry.content);
         }
-        self.last_content = try self.allocator.dupe(u8, entry.content);
 
         // Mark as dirty for batched persistence
         self.dirty_flag.store(true, .release);
@@ -284,22 +702,56 @@ pub const ClipboardManager = struct {
 
         for (0..self.entries.items.len) |display_index| {
             const real_index = self.getRealIndexForDisplayPositionLocked(display_index) orelse continue;
-            const entry = self.entries.items[real_index];
-            const content_copy = try allocator.dupe(u8, entry.content);
-
-            try snapshot.append(allocator, .{
-                .id = entry.id,
-                .content = content_copy,
-                .timestamp = entry.timestamp,
-                .entry_type = entry.entry_type,
-                .pinned = entry.pinned,
-                .is_current = display_index == 0,
-            });
+            try snapshot.append(allocator, try snapshotEntry(allocator, self.entries.items[real_index], display_index == 0));
         }
 
         return snapshot;
     }
 
+    /// The entries in the trash, most recently removed first.
+    pub fn snapshotTrash(self: *ClipboardManager, allocator: std.mem.Allocator) !std.ArrayList(DisplayEntrySnapshot) {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        var snapshot = std.ArrayList(DisplayEntrySnapshot){};
+        errdefer freeDisplayEntriesSnapshot(allocator, &snapshot);
+
+        var index = self.trash.items.len;
+        while (index > 0) {
+            index -= 1;
+            const trashed = self.trash.items[index];
+            var entry = try snapshotEntry(allocator, trashed.entry, false);
+            entry.removed_at = trashed.removed_at;
+            snapshot.append(allocator, entry) catch |err| {
+                entry.free(allocator);
+                return err;
+            };
+        }
+        return snapshot;
+    }
+
+    fn snapshotEntry(allocator: std.mem.Allocator, entry: ClipboardEntry, is_current: bool) !DisplayEntrySnapshot {
+        const content_copy = try allocator.dupe(u8, entry.content);
+        const tags_copy = if (entry.tags.len > 0) try allocator.dupe(u8, entry.tags) else "";
+        const app_copy = if (entry.source_app.len > 0) try allocator.dupe(u8, entry.source_app) else "";
+        const title_copy = if (entry.window_title.len > 0) try allocator.dupe(u8, entry.window_title) else "";
+
+        return .{
+            .id = entry.id,
+            .content = content_copy,
+            .timestamp = entry.timestamp,
+            .entry_type = entry.entry_type,
+            .pinned = entry.pinned,
+            .is_current = is_current,
+            .tags = tags_copy,
+            .hash = entry.hash,
+            .copies = entry.copies,
+            .source_app = app_copy,
+            .window_title = title_copy,
+            .formats = if (entry.formats.len > 0) entry.formatKinds() else .initEmpty(),
+        };
+    }
+
     pub fn freeDisplayEntriesSnapshot(allocator: std.mem.Allocator, snapshot: *std.ArrayList(DisplayEntrySnapshot)) void {
         for (snapshot.items) |entry| {
             entry.free(allocator);
@@ -371,6 +823,7 @@ pub const ClipboardManager = struct {
                 std.Thread.sleep(self.config.min_poll_interval * std.time.ns_per_ms);
                 save_counter += 1;
                 if (save_counter >= self.config.force_save_cycles) {
+                    self.purgeExpiredTrash();
                     self.trySavePersistence();
                     save_counter = 0;
                 }
@@ -378,14 +831,72 @@ pub const ClipboardManager = struct {
             }
             last_change_count = current_change_count;
 
-            const clipboard_content = clipboard.getContent(self.allocator) catch |err| switch (err) {
+            const seen_at = std.time.microTimestamp();
+            var event = timeline.Event{
+                .timestamp = @divTrunc(seen_at, std.time.us_per_ms),
+                .change_count = current_change_count,
+                .outcome = .recorded,
+            };
+
+            // Copies made while paused are dropped for good, not picked up on resume.
+            // Checked before the frontmost app is looked up, since nothing needs it then.
+            if (self.paused.load(.acquire)) {
+                event.outcome = .paused;
+                self.timeline.record(event);
+                std.Thread.sleep(self.config.min_poll_interval * std.time.ns_per_ms);
+                continue;
+            }
+
+            // Looked up once and shared by the exclusion check and the entry's source
+            const frontmost = pasteboard.getFrontmostApp();
+            if (frontmost) |*app| {
+                if (app.name() orelse app.bundleId()) |app_name| event.setApp(app_name);
+            }
+
+            // Same for copies made in an excluded app (e.g. a password manager)
+            if (self.config.excluded_apps.len > 0) {
+                if (frontmost) |*app| {
+                    if (self.config.isExcludedApp(app.bundleId(), app.name())) {
+                        event.outcome = .excluded;
+                        self.timeline.record(event);
+                        std.Thread.sleep(self.config.min_poll_interval * std.time.ns_per_ms);
+                        continue;
+                    }
+                }
+            }
+
+            // Kept with the entry; the title only when asked for, since it can be private
+            var source = clipboard.Source{};
+            var title_buf: [pasteboard.max_title_len]u8 = undefined;
+            if (frontmost) |*app| {
+                source.app = app.name() orelse app.bundleId() orelse "";
+                if (self.config.capture_window_titles) {
+                    source.window_title = pasteboard.getFocusedWindowTitle(app.pid, &title_buf) orelse "";
+                }
+            }
+
+            var read_config = config.Config.default();
+            read_config.save_image_data = self.image_capture.load(.acquire);
+            read_config.store_images = self.config.store_images;
+            var clipboard_content = clipboard.getContentWithConfig(self.allocator, read_config) catch |err| switch (err) {
+                clipboard.ClipboardError.ImageSkipped => {
+                    event.outcome = .skipped;
+                    event.entry_type = .image;
+                    self.timeline.record(event);
+                    std.Thread.sleep(self.config.min_poll_interval * std.time.ns_per_ms);
+                    continue;
+                },
                 clipboard.ClipboardError.NoClipboardContent => {
+                    event.outcome = .unreadable;
+                    self.timeline.record(event);
                     consecutive_failures += 1;
                     const delay_ms: u64 = @min(self.config.max_poll_interval, self.config.min_poll_interval + (consecutive_failures * 50));
                     std.Thread.sleep(delay_ms * std.time.ns_per_ms);
                     continue;
                 },
                 clipboard.ClipboardError.CommandFailed => {
+                    event.outcome = .unreadable;
+                    self.timeline.record(event);
                     consecutive_failures += 1;
                     const delay_ms: u64 = @min(self.config.max_poll_interval, self.config.min_poll_interval + (consecutive_failures * 50));
                     std.Thread.sleep(delay_ms * std.time.ns_per_ms);
@@ -394,7 +905,19 @@ pub const ClipboardManager = struct {
                 else => return err,
             };
 
-            try self.addEntry(clipboard_content);
+            clipboard_content.source = source;
+            // Read these now, since adding hands the content over to the history
+            event.entry_type = clipboard_content.type;
+            event.size = clipboard_content.content.len;
+            const added = if (self.append_mode.load(.acquire))
+                try self.appendEntry(clipboard_content)
+            else if (self.config.coalesce_duplicates)
+                try self.recordCopy(clipboard_content, current_change_count)
+            else
+                try self.addEntry(clipboard_content);
+            event.outcome = if (added) .recorded else .unchanged;
+            event.latency_us = @intCast(@max(0, std.time.microTimestamp() - seen_at));
+            self.timeline.record(event);
             consecutive_failures = 0;
             std.Thread.sleep(self.config.min_poll_interval * std.time.ns_per_ms);
         }
@@ -409,6 +932,14 @@ pub const ClipboardManager = struct {
         std.debug.print("> ", .{});
     }
 
+    pub fn setPaused(self: *ClipboardManager, paused: bool) void {
+        self.paused.store(paused, .release);
+    }
+
+    pub fn setImageCapture(self: *ClipboardManager, enabled: bool) void {
+        self.image_capture.store(enabled, .release);
+    }
+
     pub fn stopMonitoring(self: *ClipboardManager) void {
         if (self.monitor_thread) |thread| {
             std.debug.print("Signaling monitor thread to stop...\n", .{});
@@ -424,9 +955,22 @@ pub const ClipboardManager = struct {
         }
     }
 
-    fn selectRealIndexLocked(self: *ClipboardManager, real_index: usize) !void {
+    /// Puts the entry on the clipboard and makes it current: only its `kind` form when
+    /// given, else all of them.
+    fn selectRealIndexLocked(self: *ClipboardManager, real_index: usize, kind: ?clipboard.FormatKind) !void {
         const entry = self.entries.items[real_index];
-        try clipboard.setContentWithType(self.allocator, entry.content, entry.entry_type);
+        if (kind) |only| {
+            const format = if (only == .text)
+                clipboard.Format{ .kind = .text, .data = entry.content }
+            else
+                clipboard.findFormat(entry.formats, only) orelse return error.NoSuchFormat;
+            try clipboard.setContentAsFormat(self.allocator, entry.content, format);
+        } else if (entry.formats.len > 0) {
+            try clipboard.setContentWithFormats(self.allocator, entry.content, entry.formats);
+        } else {
+            try clipboard.setContentWithType(self.allocator, entry.content, entry.entry_type);
+        }
+        self.noteOwnWriteLocked();
 
         const selected_entry = self.entries.orderedRemove(real_index);
         try self.entries.append(self.allocator, selected_entry);
@@ -451,7 +995,7 @@ pub const ClipboardManager = struct {
             const real_index = self.getRealIndexForDisplayPositionLocked(index - 1) orelse {
                 return error.InvalidIndex;
             };
-            try self.selectRealIndexLocked(real_index);
+            try self.selectRealIndexLocked(real_index, null);
         }
         ui.printEntries(self);
     }
@@ -463,19 +1007,184 @@ pub const ClipboardManager = struct {
         const real_index = self.findRealIndexByIdLocked(entry_id) orelse {
             return error.InvalidIndex;
         };
-        try self.selectRealIndexLocked(real_index);
+        try self.selectRealIndexLocked(real_index, null);
+    }
+
+    /// Like `selectEntryById`, with only the entry's `kind` form put on the clipboard.
+    pub fn selectFormatById(self: *ClipboardManager, entry_id: u64, kind: clipboard.FormatKind) !void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        const real_index = self.findRealIndexByIdLocked(entry_id) orelse {
+            return error.InvalidIndex;
+        };
+        try self.selectRealIndexLocked(real_index, kind);
+    }
+
+    /// Trades the entry with whatever is on the system clipboard. The clipboard's
+    /// content is recorded first (even while paused, since the user asked for it),
+    /// then the entry is copied back and promoted to current.
+    pub fn swapEntryById(self: *ClipboardManager, entry_id: u64) !void {
+        // Fetched before taking the lock; reading the clipboard spawns osascript
+        const current: ?clipboard.ClipboardContent = clipboard.getContent(self.allocator) catch null;
+
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        const real_index = self.findRealIndexByIdLocked(entry_id) orelse {
+            if (current) |content| {
+                if (content.type == .image and image_storage.isTempImagePath(content.content)) {
+                    image_storage.deleteImageFile(content.content) catch {};
+                }
+                content.free(self.allocator);
+            }
+            return error.InvalidIndex;
+        };
+
+        // Move the entry to the newest slot so recording the clipboard can't evict it
+        const entry = self.entries.orderedRemove(real_index);
+        self.entries.appendAssumeCapacity(entry);
+
+        if (current) |content| {
+            _ = try self.addEntryLocked(content);
+        }
+        const swapped_index = self.findRealIndexByIdLocked(entry_id) orelse return error.InvalidIndex;
+        try self.selectRealIndexLocked(swapped_index, null);
+    }
+
+    /// Puts arbitrary content on the system clipboard. The monitor thread records it
+    /// as a new entry on its next poll, exactly like an external copy.
+    pub fn setClipboardContent(self: *ClipboardManager, content: []const u8, entry_type: clipboard.ClipboardType) !void {
+        try clipboard.setContentWithType(self.allocator, content, entry_type);
     }
 
+    /// Moves the entry to the trash.
     fn removeRealIndexLocked(self: *ClipboardManager, real_index: usize) void {
+        const now = std.time.timestamp();
+        _ = self.purgeTrashLocked(now);
+
         const entry_to_remove = self.entries.orderedRemove(real_index);
+        self.trash.append(self.allocator, .{ .entry = entry_to_remove, .index = real_index, .removed_at = now }) catch {
+            // Without room in the trash the entry is removed for good
+            deleteEntry(self.allocator, entry_to_remove);
+        };
 
-        // Clean up image file if it is a temp image path
-        if (entry_to_remove.entry_type == .image and image_storage.isTempImagePath(entry_to_remove.content)) {
-            image_storage.deleteImageFile(entry_to_remove.content) catch {};
+        // Force-save immediately for user-initiated deletions
+        self.dirty_flag.store(true, .release);
+        self.forceSavePersistenceLocked();
+    }
+
+    /// Purges trashed entries older than `trash_seconds` as of `now`, or all of them.
+    /// Returns whether any were.
+    fn purgeTrashLocked(self: *ClipboardManager, now: ?i64) bool {
+        var keep_from: usize = 0;
+        for (self.trash.items) |trashed| {
+            if (now) |time| {
+                if (time - trashed.removed_at < trash_seconds) break;
+            }
+            deleteEntry(self.allocator, trashed.entry);
+            keep_from += 1;
+        }
+        if (keep_from == 0) return false;
+        const kept = self.trash.items.len - keep_from;
+        std.mem.copyForwards(TrashedEntry, self.trash.items[0..kept], self.trash.items[keep_from..]);
+        self.trash.items.len = kept;
+        return true;
+    }
+
+    /// Purges trashed entries that have run out their time; the monitor calls this
+    /// every so often.
+    pub fn purgeExpiredTrash(self: *ClipboardManager) void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        if (self.purgeTrashLocked(std.time.timestamp())) {
+            self.dirty_flag.store(true, .release);
+        }
+    }
+
+    fn dupeFormats(allocator: std.mem.Allocator, formats: []const clipboard.Format) ![]const clipboard.Format {
+        if (formats.len == 0) return &.{};
+        const copies = try allocator.alloc(clipboard.Format, formats.len);
+        var duped: usize = 0;
+        errdefer {
+            for (copies[0..duped]) |format| allocator.free(format.data);
+            allocator.free(copies);
+        }
+        for (formats, copies) |format, *copy| {
+            copy.* = .{ .kind = format.kind, .data = try allocator.dupe(u8, format.data) };
+            duped += 1;
+        }
+        return copies;
+    }
+
+    /// Frees an entry that has left the history, with its image files.
+    fn deleteEntry(allocator: std.mem.Allocator, entry: ClipboardEntry) void {
+        if (entry.entry_type == .image and image_storage.isTempImagePath(entry.content)) {
+            image_storage.deleteImageFile(entry.content) catch {};
+        }
+        clipboard.deleteFormatImages(entry.formats);
+        entry.free(allocator);
+    }
+
+    fn findTrashIndexByIdLocked(self: *ClipboardManager, entry_id: u64) ?usize {
+        for (self.trash.items, 0..) |trashed, index| {
+            if (trashed.entry.id == entry_id) return index;
+        }
+        return null;
+    }
+
+    /// Takes an entry out of the trash and puts it back where it was, with its id,
+    /// pins and tags. It goes below the current entry at most, so the current one
+    /// keeps matching the clipboard. Returns false when the same content was copied
+    /// again meanwhile; the trashed copy is dropped then.
+    pub fn restoreEntryById(self: *ClipboardManager, entry_id: u64) !bool {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        const trash_index = self.findTrashIndexByIdLocked(entry_id) orelse return error.InvalidIndex;
+        const trashed = self.trash.items[trash_index];
+        if (self.findDuplicateLocked(.{ .content = trashed.entry.content, .type = trashed.entry.entry_type }) != null) {
+            deleteEntry(self.allocator, self.trash.orderedRemove(trash_index).entry);
+            self.dirty_flag.store(true, .release);
+            self.forceSavePersistenceLocked();
+            return false;
+        }
+
+        try self.entries.ensureUnusedCapacity(self.allocator, 1);
+        if (self.entries.items.len >= self.max_entries) {
+            const eviction_index = self.findOldestUnpinnedIndex() orelse return error.HistoryFull;
+            deleteEntry(self.allocator, self.entries.orderedRemove(eviction_index));
+        }
+        const len = self.entries.items.len;
+        const position = if (len == 0) 0 else @min(trashed.index, len - 1);
+        self.entries.insertAssumeCapacity(position, self.trash.orderedRemove(trash_index).entry);
+
+        self.dirty_flag.store(true, .release);
+        self.forceSavePersistenceLocked();
+        return true;
+    }
+
+    /// Removes an entry for good, from the history or the trash, skipping the trash.
+    pub fn purgeEntryById(self: *ClipboardManager, entry_id: u64) !void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        if (self.findRealIndexByIdLocked(entry_id)) |real_index| {
+            deleteEntry(self.allocator, self.entries.orderedRemove(real_index));
+        } else {
+            const trash_index = self.findTrashIndexByIdLocked(entry_id) orelse return error.InvalidIndex;
+            deleteEntry(self.allocator, self.trash.orderedRemove(trash_index).entry);
         }
-        entry_to_remove.free(self.allocator);
 
-        // Force-save immediately for user-initiated deletions
+        self.dirty_flag.store(true, .release);
+        self.forceSavePersistenceLocked();
+    }
+
+    pub fn emptyTrash(self: *ClipboardManager) void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        if (!self.purgeTrashLocked(null)) return;
         self.dirty_flag.store(true, .release);
         self.forceSavePersistenceLocked();
     }
@@ -538,6 +1247,23 @@ pub const ClipboardManager = struct {
         return self.togglePinnedRealIndexLocked(real_index);
     }
 
+    /// Replaces an entry's tags with `tags` (comma-separated, already validated).
+    pub fn setTagsById(self: *ClipboardManager, entry_id: u64, tags: []const u8) !void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+
+        const real_index = self.findRealIndexByIdLocked(entry_id) orelse {
+            return error.InvalidIndex;
+        };
+        const tags_copy = if (tags.len > 0) try self.allocator.dupe(u8, tags) else "";
+        const entry = &self.entries.items[real_index];
+        if (entry.tags.len > 0) self.allocator.free(entry.tags);
+        entry.tags = tags_copy;
+
+        self.dirty_flag.store(true, .release);
+        self.forceSavePersistenceLocked();
+    }
+
     pub fn clearHistory(self: *ClipboardManager) !void {
         var removed_any = false;
         {
@@ -562,11 +1288,7 @@ pub const ClipboardManager = struct {
 
                 removed_any = true;
 
-                // Clean up image file if it's a temp image path
-                if (entry.entry_type == .image and image_storage.isTempImagePath(entry.content)) {
-                    image_storage.deleteImageFile(entry.content) catch {};
-                }
-                entry.free(self.allocator);
+                deleteEntry(self.allocator, entry);
             }
 
             if (!removed_any) return;
@@ -581,6 +1303,71 @@ pub const ClipboardManager = struct {
         ui.printEntries(self);
     }
 
+    /// Marks a session as running for `forget_on_quit`, first forgetting what a session
+    /// cut short by the Mac restarting left behind. A backend restarted in the same boot
+    /// (after a crash, or by the frontend) keeps the history.
+    fn beginSession(self: *ClipboardManager) void {
+        if (self.config.read_only) return;
+        var path_buf: [std.fs.max_path_bytes]u8 = undefined;
+        const marker_path = self.sessionMarkerPath(&path_buf) orelse return;
+        if (self.config.forget_on_quit == .off) {
+            std.fs.cwd().deleteFile(marker_path) catch {};
+            return;
+        }
+
+        var boot_buf: [64]u8 = undefined;
+        const boot_id = currentBootId(&boot_buf) orelse "";
+        var marker_buf: [64]u8 = undefined;
+        if (std.fs.cwd().readFile(marker_path, &marker_buf)) |marker_boot_id| {
+            if (!std.mem.eql(u8, marker_boot_id, boot_id)) {
+                self.forgetHistoryLocked(self.config.forget_on_quit == .unpinned);
+            }
+        } else |_| {}
+        std.fs.cwd().writeFile(.{ .sub_path = marker_path, .data = boot_id }) catch {};
+    }
+
+    /// Ends the session on an explicit quit: forgets the history if `forget_on_quit`
+    /// asks for it and clears the marker `beginSession` left.
+    pub fn endSession(self: *ClipboardManager) void {
+        if (self.config.forget_on_quit == .off or self.config.read_only) return;
+        self.forgetHistory(self.config.forget_on_quit == .unpinned);
+        var path_buf: [std.fs.max_path_bytes]u8 = undefined;
+        const marker_path = self.sessionMarkerPath(&path_buf) orelse return;
+        std.fs.cwd().deleteFile(marker_path) catch {};
+    }
+
+    /// Next to the history file, holding the boot the running session started in.
+    fn sessionMarkerPath(self: *const ClipboardManager, buf: []u8) ?[]const u8 {
+        return std.fmt.bufPrint(buf, "{s}.session", .{self.persistence.getFilePath()}) catch null;
+    }
+
+    /// Drops every entry, or every unpinned one, and saves right away. Used for
+    /// `forget_on_quit`; unlike `clearHistory` the current entry goes too.
+    pub fn forgetHistory(self: *ClipboardManager, keep_pinned: bool) void {
+        self.state_mutex.lock();
+        defer self.state_mutex.unlock();
+        self.forgetHistoryLocked(keep_pinned);
+    }
+
+    fn forgetHistoryLocked(self: *ClipboardManager, keep_pinned: bool) void {
+        var write_index: usize = 0;
+        for (self.entries.items) |entry| {
+            if (keep_pinned and entry.pinned) {
+                self.entries.items[write_index] = entry;
+                write_index += 1;
+                continue;
+            }
+            deleteEntry(self.allocator, entry);
+        }
+        const trash_emptied = self.purgeTrashLocked(null);
+        if (write_index == self.entries.items.len and !trash_emptied) return;
+
+        self.entries.items.len = write_index;
+        self.append_target_id = null;
+        self.dirty_flag.store(true, .release);
+        self.forceSavePersistenceLocked();
+    }
+
     pub fn clean(self: *ClipboardManager) !void {
         self.state_mutex.lock();
         defer self.state_mutex.unlock();
@@ -591,14 +1378,11 @@ pub const ClipboardManager = struct {
 
         // Free memory for all entries and clean up image files
         for (self.entries.items) |entry| {
-            // Clean up image file if it's a temp image path
-            if (entry.entry_type == .image and image_storage.isTempImagePath(entry.content)) {
-                image_storage.deleteImageFile(entry.content) catch {};
-            }
-            entry.free(self.allocator);
+            deleteEntry(self.allocator, entry);
         }
 
         self.entries.clearRetainingCapacity();
+        _ = self.purgeTrashLocked(null);
         self.last_content = null;
 
         // Clear the persistence file completely
@@ -616,7 +1400,7 @@ fn noopEntriesChanged(_: *ClipboardManager) void {}
 
 fn addTextEntry(allocator: std.mem.Allocator, clipboard_manager: *ClipboardManager, value: []const u8) !void {
     const content = try allocator.dupe(u8, value);
-    try clipboard_manager.addEntry(.{
+    _ = try clipboard_manager.addEntry(.{
         .content = content,
         .type = .text,
     });
@@ -629,6 +1413,23 @@ fn findSnapshotEntryByContent(entries: []const DisplayEntrySnapshot, value: []co
     return null;
 }
 
+/// Tells one boot of the machine from the next; null where that isn't known.
+fn currentBootId(buf: []u8) ?[]const u8 {
+    switch (builtin.os.tag) {
+        .macos => {
+            var boot_time: std.posix.timeval = undefined;
+            var len: usize = @sizeOf(std.posix.timeval);
+            std.posix.sysctlbynameZ("kern.boottime", &boot_time, &len, null, 0) catch return null;
+            return std.fmt.bufPrint(buf, "{d}.{d}", .{ boot_time.sec, boot_time.usec }) catch null;
+        },
+        .linux => {
+            const boot_id = std.fs.cwd().readFile("/proc/sys/kernel/random/boot_id", buf) catch return null;
+            return std.mem.trim(u8, boot_id, " \n");
+        },
+        else => return null,
+    }
+}
+
 const WriterContext = struct {
     allocator: std.mem.Allocator,
     clipboard_manager: *ClipboardManager,
@@ -756,6 +1557,197 @@ test "stable IDs survive pinning and new entries" {
     try std.testing.expectEqual(c_before.id, c_after.id);
 }
 
+test "forget on quit keeps only pinned entries across a restart" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-forget-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.forget_on_quit = .unpinned;
+
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+        try addTextEntry(allocator, &clipboard_manager, "keep");
+        try addTextEntry(allocator, &clipboard_manager, "session");
+        try addTextEntry(allocator, &clipboard_manager, "current");
+
+        var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
+        defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+        const keep = findSnapshotEntryByContent(snapshot.items, "keep") orelse return error.TestUnexpectedResult;
+        _ = try clipboard_manager.togglePinnedById(keep.id);
+        clipboard_manager.endSession();
+    }
+
+    var reopened = try ClipboardManager.initWithPersistencePath(allocator, config.Config.default(), persistence_path);
+    defer reopened.deinit();
+    var snapshot = try reopened.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    try std.testing.expectEqual(@as(usize, 1), snapshot.items.len);
+    try std.testing.expectEqualStrings("keep", snapshot.items[0].content);
+}
+
+test "forget on quit leaves the history of a backend that was only restarted" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-restart-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    const marker_path = try std.fmt.allocPrint(allocator, "{s}.session", .{persistence_path});
+    defer allocator.free(marker_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(marker_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.forget_on_quit = .all;
+
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+        try addTextEntry(allocator, &clipboard_manager, "first");
+        try addTextEntry(allocator, &clipboard_manager, "second");
+    }
+
+    // Started again without quitting, like after a crash or `--dump`
+    {
+        var restarted = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer restarted.deinit();
+        var snapshot = try restarted.snapshotDisplayEntries(allocator);
+        defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+        try std.testing.expectEqual(@as(usize, 2), snapshot.items.len);
+    }
+
+    // A marker from another boot means the session never got to quit
+    try std.fs.cwd().writeFile(.{ .sub_path = marker_path, .data = "an earlier boot" });
+    var rebooted = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer rebooted.deinit();
+    var snapshot = try rebooted.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    try std.testing.expectEqual(@as(usize, 0), snapshot.items.len);
+}
+
+test "a read-only manager leaves the history file alone" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-read-only-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    const marker_path = try std.fmt.allocPrint(allocator, "{s}.session", .{persistence_path});
+    defer allocator.free(marker_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(marker_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+        try addTextEntry(allocator, &clipboard_manager, "first");
+        try addTextEntry(allocator, &clipboard_manager, "second");
+    }
+
+    // Even a session that looks cut short isn't forgotten by a dump
+    try std.fs.cwd().writeFile(.{ .sub_path = marker_path, .data = "an earlier boot" });
+    var read_only_cfg = cfg;
+    read_only_cfg.read_only = true;
+    read_only_cfg.forget_on_quit = .all;
+    {
+        var dump = try ClipboardManager.initWithPersistencePath(allocator, read_only_cfg, persistence_path);
+        defer dump.deinit();
+        var snapshot = try dump.snapshotDisplayEntries(allocator);
+        defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+        try std.testing.expectEqual(@as(usize, 2), snapshot.items.len);
+    }
+
+    var reopened = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer reopened.deinit();
+    var snapshot = try reopened.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    try std.testing.expectEqual(@as(usize, 2), snapshot.items.len);
+}
+
+test "imported entries keep their timestamps and never evict" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-import-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.max_entries = 3;
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+    try addTextEntry(allocator, &clipboard_manager, "recent");
+    try addTextEntry(allocator, &clipboard_manager, "current");
+
+    const result = try clipboard_manager.importEntries(&.{
+        .{ .content = "old", .entry_type = .text, .timestamp = 1000, .pinned = true, .tags = "work" },
+        .{ .content = "recent", .entry_type = .text, .timestamp = 2000 },
+        .{ .content = "no room", .entry_type = .text, .timestamp = 3000 },
+    });
+    try std.testing.expectEqual(@as(usize, 1), result.added);
+    try std.testing.expectEqual(@as(usize, 1), result.existing);
+    try std.testing.expectEqual(@as(usize, 1), result.skipped);
+
+    const items = clipboard_manager.entries.items;
+    try std.testing.expectEqual(@as(usize, 3), items.len);
+    try std.testing.expectEqualStrings("old", items[0].content);
+    try std.testing.expectEqual(@as(i64, 1000), items[0].timestamp);
+    try std.testing.expect(items[0].pinned);
+    try std.testing.expectEqualStrings("work", items[0].tags);
+    try std.testing.expectEqualStrings("current", items[2].content);
+
+    // A newer one still goes below the current entry
+    clipboard_manager.max_entries = 4;
+    const newer = try clipboard_manager.importEntries(&.{.{ .content = "future", .entry_type = .text, .timestamp = std.math.maxInt(i64) }});
+    try std.testing.expectEqual(@as(usize, 1), newer.added);
+    try std.testing.expectEqualStrings("future", clipboard_manager.entries.items[2].content);
+    try std.testing.expectEqualStrings("current", clipboard_manager.entries.items[3].content);
+}
+
+test "importing content already in the history keeps the later timestamp" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-import-refresh-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+    try addTextEntry(allocator, &clipboard_manager, "shared");
+    try addTextEntry(allocator, &clipboard_manager, "local");
+    try addTextEntry(allocator, &clipboard_manager, "current");
+    const copied_at = clipboard_manager.entries.items[0].timestamp;
+
+    // An older copy elsewhere changes nothing
+    _ = try clipboard_manager.importEntries(&.{.{ .content = "shared", .entry_type = .text, .timestamp = 1000 }});
+    try std.testing.expectEqualStrings("shared", clipboard_manager.entries.items[0].content);
+    try std.testing.expectEqual(copied_at, clipboard_manager.entries.items[0].timestamp);
+
+    // A later one moves it up, still below the current entry
+    const result = try clipboard_manager.importEntries(&.{.{ .content = "shared", .entry_type = .text, .timestamp = copied_at + 60 }});
+    try std.testing.expectEqual(@as(usize, 1), result.existing);
+    const items = clipboard_manager.entries.items;
+    try std.testing.expectEqual(@as(usize, 3), items.len);
+    try std.testing.expectEqualStrings("local", items[0].content);
+    try std.testing.expectEqualStrings("shared", items[1].content);
+    try std.testing.expectEqual(copied_at + 60, items[1].timestamp);
+    try std.testing.expectEqualStrings("current", items[2].content);
+}
+
 test "entry IDs are not reused after remove and re-add" {
     const allocator = std.testing.allocator;
     const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-id-reuse-{d}.json", .{std.time.nanoTimestamp()});
@@ -822,3 +1814,264 @@ test "concurrent addEntry and snapshotDisplayEntries stay consistent" {
     try std.testing.expect(final_snapshot.items.len <= cfg.max_entries);
     try std.testing.expect(final_snapshot.items.len > 0);
 }
+
+test "encrypted history reloads only with its key" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-encrypted-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.history_key = [_]u8{7} ** 32;
+    cfg.encrypt_history = true;
+
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+        try addTextEntry(allocator, &clipboard_manager, "hunter2");
+    }
+
+    const raw = try std.fs.cwd().readFileAlloc(allocator, persistence_path, 1024 * 1024);
+    defer allocator.free(raw);
+    try std.testing.expect(std.mem.indexOf(u8, raw, "hunter2") == null);
+
+    var reloaded = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer reloaded.deinit();
+    try std.testing.expectEqualStrings("hunter2", reloaded.entries.items[0].content);
+
+    var wrong_key = cfg;
+    wrong_key.history_key = [_]u8{8} ** 32;
+    try std.testing.expectError(persistence.PersistenceError.HistoryDecryptFailed, ClipboardManager.initWithPersistencePath(allocator, wrong_key, persistence_path));
+
+    var no_key = cfg;
+    no_key.history_key = null;
+    no_key.encrypt_history = false;
+    try std.testing.expectError(persistence.PersistenceError.HistoryKeyMissing, ClipboardManager.initWithPersistencePath(allocator, no_key, persistence_path));
+}
+
+test "repeated copies coalesce into one counted entry" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-coalesce-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.max_entries = 20;
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+    try addTextEntry(allocator, &clipboard_manager, "a");
+    try addTextEntry(allocator, &clipboard_manager, "b");
+    var change_count: i64 = 10;
+    for ([_][]const u8{ "Notes", "Safari" }) |app| {
+        change_count += 1;
+        const content = try allocator.dupe(u8, "a");
+        const source = clipboard.Source{ .app = app, .window_title = "Release plan" };
+        try std.testing.expect(try clipboard_manager.recordCopy(.{ .content = content, .type = .text, .source = source }, change_count));
+    }
+
+    var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    try std.testing.expectEqual(@as(usize, 2), snapshot.items.len);
+    try std.testing.expectEqualStrings("a", snapshot.items[0].content);
+    try std.testing.expect(snapshot.items[0].is_current);
+    try std.testing.expectEqual(@as(u32, 3), snapshot.items[0].copies);
+    try std.testing.expectEqual(@as(u32, 1), snapshot.items[1].copies);
+    // The source is that of the latest copy
+    try std.testing.expectEqualStrings("Safari", snapshot.items[0].source_app);
+    try std.testing.expectEqualStrings("Release plan", snapshot.items[0].window_title);
+    try std.testing.expectEqualStrings("", snapshot.items[1].source_app);
+
+    // Seeing the same change again, or our own write, isn't another copy
+    clipboard_manager.own_change_count = change_count + 1;
+    for ([_]i64{ -1, change_count + 1 }) |count| {
+        const content = try allocator.dupe(u8, "a");
+        try std.testing.expect(!try clipboard_manager.recordCopy(.{ .content = content, .type = .text }, count));
+    }
+    try std.testing.expectEqual(@as(u32, 3), clipboard_manager.entries.items[1].copies);
+}
+
+test "setMaxEntries trims the oldest unpinned entries" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-limit-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.max_entries = 20;
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+    for ([_][]const u8{ "a", "b", "c", "d", "e" }) |value| {
+        try addTextEntry(allocator, &clipboard_manager, value);
+    }
+    _ = try clipboard_manager.togglePinnedById(clipboard_manager.entries.items[0].id);
+
+    try std.testing.expect(clipboard_manager.setMaxEntries(3));
+    var snapshot = try clipboard_manager.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    try std.testing.expectEqual(@as(usize, 3), snapshot.items.len);
+    try std.testing.expect(findSnapshotEntryByContent(snapshot.items, "a") != null);
+    try std.testing.expect(findSnapshotEntryByContent(snapshot.items, "b") == null);
+    try std.testing.expect(findSnapshotEntryByContent(snapshot.items, "e") != null);
+
+    // The pinned entry and the current one stay even below the limit
+    try std.testing.expect(clipboard_manager.setMaxEntries(1));
+    try std.testing.expectEqual(@as(usize, 2), clipboard_manager.entries.items.len);
+
+    try std.testing.expect(!clipboard_manager.setMaxEntries(config.unlimited_entries));
+    try addTextEntry(allocator, &clipboard_manager, "f");
+    try std.testing.expectEqual(@as(usize, 3), clipboard_manager.entries.items.len);
+}
+
+test "removed entries can be restored in place from the trash" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-restore-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.max_entries = 20;
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+    try addTextEntry(allocator, &clipboard_manager, "a");
+    try addTextEntry(allocator, &clipboard_manager, "b");
+    try addTextEntry(allocator, &clipboard_manager, "c");
+
+    const a_id = clipboard_manager.entries.items[0].id;
+    const c_id = clipboard_manager.entries.items[2].id;
+    _ = try clipboard_manager.togglePinnedById(a_id);
+    try clipboard_manager.removeEntryById(a_id);
+    try std.testing.expectEqual(@as(usize, 2), clipboard_manager.entries.items.len);
+
+    try std.testing.expect(try clipboard_manager.restoreEntryById(a_id));
+    try std.testing.expectEqual(a_id, clipboard_manager.entries.items[0].id);
+    try std.testing.expect(clipboard_manager.entries.items[0].pinned);
+    try std.testing.expectError(error.InvalidIndex, clipboard_manager.restoreEntryById(a_id));
+
+    // The current entry comes back below the new current one
+    try clipboard_manager.removeEntryById(c_id);
+    try addTextEntry(allocator, &clipboard_manager, "d");
+    try std.testing.expect(try clipboard_manager.restoreEntryById(c_id));
+    try std.testing.expectEqual(c_id, clipboard_manager.entries.items[2].id);
+    try std.testing.expectEqualStrings("d", clipboard_manager.entries.items[3].content);
+
+    // Copying the same content again makes the trashed copy redundant
+    const b_id = clipboard_manager.entries.items[1].id;
+    try clipboard_manager.removeEntryById(b_id);
+    try addTextEntry(allocator, &clipboard_manager, "b");
+    try std.testing.expect(!try clipboard_manager.restoreEntryById(b_id));
+
+    // Past its time in the trash the entry is gone for good
+    try clipboard_manager.removeEntryById(a_id);
+    clipboard_manager.trash.items[0].removed_at -= trash_seconds;
+    clipboard_manager.purgeExpiredTrash();
+    try std.testing.expectError(error.InvalidIndex, clipboard_manager.restoreEntryById(a_id));
+}
+
+test "the trash survives a restart until purged" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-trash-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+    cfg.max_entries = 20;
+
+    var a_id: u64 = 0;
+    var b_id: u64 = 0;
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+        try addTextEntry(allocator, &clipboard_manager, "a");
+        try addTextEntry(allocator, &clipboard_manager, "b");
+        try addTextEntry(allocator, &clipboard_manager, "c");
+        a_id = clipboard_manager.entries.items[0].id;
+        b_id = clipboard_manager.entries.items[1].id;
+        try clipboard_manager.removeEntryById(a_id);
+        try clipboard_manager.removeEntryById(b_id);
+    }
+
+    var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer clipboard_manager.deinit();
+    clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+    var trash = try clipboard_manager.snapshotTrash(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &trash);
+    try std.testing.expectEqual(@as(usize, 2), trash.items.len);
+    try std.testing.expectEqual(b_id, trash.items[0].id);
+    try std.testing.expect(trash.items[0].removed_at != null);
+
+    try clipboard_manager.purgeEntryById(b_id);
+    try std.testing.expectError(error.InvalidIndex, clipboard_manager.restoreEntryById(b_id));
+    try std.testing.expect(try clipboard_manager.restoreEntryById(a_id));
+    try std.testing.expectEqualStrings("a", clipboard_manager.entries.items[0].content);
+    try std.testing.expectEqual(@as(usize, 0), clipboard_manager.trash.items.len);
+}
+
+test "other forms of a copy survive a restart and follow the latest copy" {
+    const allocator = std.testing.allocator;
+    const persistence_path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-formats-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(persistence_path);
+    std.fs.deleteFileAbsolute(persistence_path) catch {};
+    defer std.fs.deleteFileAbsolute(persistence_path) catch {};
+
+    var cfg = config.Config.default();
+    cfg.batch_save_interval = 3600;
+
+    {
+        var clipboard_manager = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+        defer clipboard_manager.deinit();
+        clipboard_manager.entries_changed_callback = noopEntriesChanged;
+
+        const native = try ClipboardManager.dupeFormats(allocator, &.{
+            .{ .kind = .rtf, .data = "{\\rtf1 \\b bold\\b0}" },
+            .{ .kind = .html, .data = "<b>bold</b>" },
+        });
+        try std.testing.expect(try clipboard_manager.addEntry(.{ .content = try allocator.dupe(u8, "bold"), .type = .text, .formats = native }));
+
+        // Copying it again from a browser replaces them with the browser's
+        const browser = try ClipboardManager.dupeFormats(allocator, &.{
+            .{ .kind = .html, .data = "<b>bold</b>\n\x00\xff" },
+            .{ .kind = .image, .data = "/tmp/clipz_images/clipz-test-missing.png" },
+        });
+        try std.testing.expect(try clipboard_manager.recordCopy(.{ .content = try allocator.dupe(u8, "bold"), .type = .text, .formats = browser }, 5));
+        try std.testing.expectEqual(@as(usize, 1), clipboard_manager.entries.items.len);
+
+        // A form it wasn't copied with can't be picked
+        try std.testing.expectError(error.NoSuchFormat, clipboard_manager.selectFormatById(clipboard_manager.entries.items[0].id, .rtf));
+    }
+
+    var reloaded = try ClipboardManager.initWithPersistencePath(allocator, cfg, persistence_path);
+    defer reloaded.deinit();
+    const formats = reloaded.entries.items[0].formats;
+    try std.testing.expectEqual(@as(usize, 2), formats.len);
+    try std.testing.expectEqual(clipboard.FormatKind.html, formats[0].kind);
+    try std.testing.expectEqualStrings("<b>bold</b>\n\x00\xff", formats[0].data);
+    try std.testing.expectEqualStrings("/tmp/clipz_images/clipz-test-missing.png", formats[1].data);
+
+    var snapshot = try reloaded.snapshotDisplayEntries(allocator);
+    defer ClipboardManager.freeDisplayEntriesSnapshot(allocator, &snapshot);
+    const kinds = snapshot.items[0].formats;
+    try std.testing.expect(kinds.contains(.text) and kinds.contains(.html) and kinds.contains(.image));
+    try std.testing.expect(!kinds.contains(.rtf));
+}
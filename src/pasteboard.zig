@@ -9,9 +9,30 @@ const c = struct {
     extern "c" fn objc_getClass(name: [*:0]const u8) ?Class;
     extern "c" fn sel_registerName(name: [*:0]const u8) ?SEL;
     extern "c" fn objc_msgSend() void;
+
+    // Accessibility, from ApplicationServices
+    extern "c" fn AXUIElementCreateApplication(pid: i32) ?id;
+    extern "c" fn AXUIElementCopyAttributeValue(element: id, attribute: id, value: *?id) i32;
+    extern "c" fn CFRelease(object: id) void;
+
+    // CoreGraphics, from ApplicationServices
+    extern "c" fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) ?id;
+
+    extern "c" fn objc_autoreleasePoolPush() ?*anyopaque;
+    extern "c" fn objc_autoreleasePoolPop(pool: ?*anyopaque) void;
 };
 
-const cc: @import("std").builtin.CallingConvention = .c;
+// kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements
+const on_screen_windows: u32 = (1 << 0) | (1 << 4);
+
+/// Longest window title kept, in bytes.
+pub const max_title_len = 256;
+
+/// Longest app name or bundle ID kept, in bytes.
+pub const max_app_len = 128;
+
+const std = @import("std");
+const cc: std.builtin.CallingConvention = .c;
 
 pub fn getChangeCount() ?i64 {
     const NSPasteboard = c.objc_getClass("NSPasteboard") orelse return null;
@@ -26,3 +47,267 @@ pub fn getChangeCount() ?i64 {
     const msgSend_count: *const fn (c.id, c.SEL) callconv(cc) i64 = @ptrCast(&c.objc_msgSend);
     return msgSend_count(pasteboard, changeCountSel);
 }
+
+fn generalPasteboard() ?c.id {
+    const NSPasteboard = c.objc_getClass("NSPasteboard") orelse return null;
+    const generalPasteboardSel = c.sel_registerName("generalPasteboard") orelse return null;
+    const msgSend_class: *const fn (c.Class, c.SEL) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    return msgSend_class(NSPasteboard, generalPasteboardSel);
+}
+
+/// A copy of the general pasteboard's data for the type `pasteboard_type` (a UTI such
+/// as `public.rtf`); null when there is none, or it is empty or longer than `max_len`.
+pub fn readData(allocator: std.mem.Allocator, pasteboard_type: [*:0]const u8, max_len: usize) ?[]u8 {
+    const pasteboard = generalPasteboard() orelse return null;
+    const dataForTypeSel = c.sel_registerName("dataForType:") orelse return null;
+    const lengthSel = c.sel_registerName("length") orelse return null;
+    const bytesSel = c.sel_registerName("bytes") orelse return null;
+
+    const msgSend_data: *const fn (c.id, c.SEL, c.id) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_len: *const fn (c.id, c.SEL) callconv(cc) usize = @ptrCast(&c.objc_msgSend);
+    const msgSend_bytes: *const fn (c.id, c.SEL) callconv(cc) ?[*]const u8 = @ptrCast(&c.objc_msgSend);
+
+    // [pasteboard dataForType:type]
+    const data = msgSend_data(pasteboard, dataForTypeSel, nsString(pasteboard_type) orelse return null) orelse return null;
+    const len = msgSend_len(data, lengthSel);
+    if (len == 0 or len > max_len) return null;
+    const bytes = msgSend_bytes(data, bytesSel) orelse return null;
+    return allocator.dupe(u8, bytes[0..len]) catch null;
+}
+
+/// Raw data for one pasteboard type (a UTI such as `public.html`).
+pub const Item = struct {
+    type: [*:0]const u8,
+    data: []const u8,
+};
+
+/// Replaces what is on the general pasteboard with `items`, all in one change, so
+/// apps pasting it pick the type they prefer. Returns false if any of it fails.
+pub fn writeItems(items: []const Item) bool {
+    const pasteboard = generalPasteboard() orelse return false;
+    const NSData = c.objc_getClass("NSData") orelse return false;
+    const clearContentsSel = c.sel_registerName("clearContents") orelse return false;
+    const setDataSel = c.sel_registerName("setData:forType:") orelse return false;
+    const dataWithBytesSel = c.sel_registerName("dataWithBytes:length:") orelse return false;
+
+    const msgSend_clear: *const fn (c.id, c.SEL) callconv(cc) i64 = @ptrCast(&c.objc_msgSend);
+    const msgSend_set: *const fn (c.id, c.SEL, c.id, c.id) callconv(cc) bool = @ptrCast(&c.objc_msgSend);
+    const msgSend_bytes: *const fn (c.Class, c.SEL, [*]const u8, usize) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+
+    _ = msgSend_clear(pasteboard, clearContentsSel);
+    for (items) |item| {
+        // [NSData dataWithBytes:length:], then [pasteboard setData:forType:]
+        const data = msgSend_bytes(NSData, dataWithBytesSel, item.data.ptr, item.data.len) orelse return false;
+        const data_type = nsString(item.type) orelse return false;
+        if (!msgSend_set(pasteboard, setDataSel, data, data_type)) return false;
+    }
+    return true;
+}
+
+/// The paths of the file URLs on the general pasteboard, one per copied file; null
+/// when it holds none.
+pub fn readFilePaths(allocator: std.mem.Allocator) ?[][]u8 {
+    const pasteboard = generalPasteboard() orelse return null;
+    const NSURL = c.objc_getClass("NSURL") orelse return null;
+    const pasteboardItemsSel = c.sel_registerName("pasteboardItems") orelse return null;
+    const countSel = c.sel_registerName("count") orelse return null;
+    const objectAtIndexSel = c.sel_registerName("objectAtIndex:") orelse return null;
+    const stringForTypeSel = c.sel_registerName("stringForType:") orelse return null;
+    const URLWithStringSel = c.sel_registerName("URLWithString:") orelse return null;
+    const pathSel = c.sel_registerName("path") orelse return null;
+
+    const msgSend_obj: *const fn (c.id, c.SEL) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_count: *const fn (c.id, c.SEL) callconv(cc) usize = @ptrCast(&c.objc_msgSend);
+    const msgSend_index: *const fn (c.id, c.SEL, usize) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_string: *const fn (c.id, c.SEL, c.id) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_url: *const fn (c.Class, c.SEL, c.id) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+
+    const file_url_type = nsString("public.file-url") orelse return null;
+    // [pasteboard pasteboardItems]
+    const items = msgSend_obj(pasteboard, pasteboardItemsSel) orelse return null;
+    const count = msgSend_count(items, countSel);
+
+    // Returning null skips errdefer, so whatever wasn't handed back is freed here;
+    // toOwnedSlice leaves the list empty
+    var paths = std.ArrayList([]u8){};
+    defer {
+        for (paths.items) |path| allocator.free(path);
+        paths.deinit(allocator);
+    }
+    for (0..count) |i| {
+        // [[NSURL URLWithString:[item stringForType:@"public.file-url"]] path]
+        const item = msgSend_index(items, objectAtIndexSel, i) orelse continue;
+        const url_string = msgSend_string(item, stringForTypeSel, file_url_type) orelse continue;
+        const url = msgSend_url(NSURL, URLWithStringSel, url_string) orelse continue;
+        const path = nsStringSlice(msgSend_obj(url, pathSel)) orelse continue;
+        const path_copy = allocator.dupe(u8, path) catch return null;
+        paths.append(allocator, path_copy) catch {
+            allocator.free(path_copy);
+            return null;
+        };
+    }
+    if (paths.items.len == 0) return null;
+    return paths.toOwnedSlice(allocator) catch null;
+}
+
+/// Replaces what is on the general pasteboard with file URLs for `paths`, so Finder
+/// pastes the files themselves. Returns false if any of it fails.
+pub fn writeFilePaths(allocator: std.mem.Allocator, paths: []const []const u8) bool {
+    const pasteboard = generalPasteboard() orelse return false;
+    const NSURL = c.objc_getClass("NSURL") orelse return false;
+    const NSMutableArray = c.objc_getClass("NSMutableArray") orelse return false;
+    const arraySel = c.sel_registerName("array") orelse return false;
+    const fileURLWithPathSel = c.sel_registerName("fileURLWithPath:") orelse return false;
+    const addObjectSel = c.sel_registerName("addObject:") orelse return false;
+    const clearContentsSel = c.sel_registerName("clearContents") orelse return false;
+    const writeObjectsSel = c.sel_registerName("writeObjects:") orelse return false;
+
+    const msgSend_class: *const fn (c.Class, c.SEL) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_url: *const fn (c.Class, c.SEL, c.id) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_add: *const fn (c.id, c.SEL, c.id) callconv(cc) void = @ptrCast(&c.objc_msgSend);
+    const msgSend_clear: *const fn (c.id, c.SEL) callconv(cc) i64 = @ptrCast(&c.objc_msgSend);
+    const msgSend_write: *const fn (c.id, c.SEL, c.id) callconv(cc) bool = @ptrCast(&c.objc_msgSend);
+
+    // [NSMutableArray array], with [NSURL fileURLWithPath:] for each path
+    const urls = msgSend_class(NSMutableArray, arraySel) orelse return false;
+    for (paths) |path| {
+        const path_z = allocator.dupeZ(u8, path) catch return false;
+        defer allocator.free(path_z);
+        const string = nsString(path_z.ptr) orelse return false;
+        const url = msgSend_url(NSURL, fileURLWithPathSel, string) orelse return false;
+        msgSend_add(urls, addObjectSel, url);
+    }
+
+    _ = msgSend_clear(pasteboard, clearContentsSel);
+    return msgSend_write(pasteboard, writeObjectsSel, urls);
+}
+
+/// The app a copy most likely came from. Holds its own copies of the strings, so it
+/// outlives the autorelease pool they were read in.
+pub const FrontmostApp = struct {
+    pid: i32,
+    bundle_id_buf: [max_app_len]u8 = undefined,
+    bundle_id_len: usize = 0,
+    name_buf: [max_app_len]u8 = undefined,
+    name_len: usize = 0,
+
+    pub fn bundleId(self: *const FrontmostApp) ?[]const u8 {
+        return if (self.bundle_id_len > 0) self.bundle_id_buf[0..self.bundle_id_len] else null;
+    }
+
+    pub fn name(self: *const FrontmostApp) ?[]const u8 {
+        return if (self.name_len > 0) self.name_buf[0..self.name_len] else null;
+    }
+};
+
+/// The owner of the frontmost on-screen window, read when a change is seen to tell
+/// which app most likely made the copy. `[NSWorkspace frontmostApplication]` would
+/// go stale here: it is only updated from a run loop, which the monitor thread lacks.
+pub fn getFrontmostApp() ?FrontmostApp {
+    // Nothing on the calling thread drains autoreleased objects otherwise
+    const pool = c.objc_autoreleasePoolPush();
+    defer c.objc_autoreleasePoolPop(pool);
+
+    const pid = frontmostWindowOwner() orelse return null;
+
+    const NSRunningApplication = c.objc_getClass("NSRunningApplication") orelse return null;
+    const runningApplicationSel = c.sel_registerName("runningApplicationWithProcessIdentifier:") orelse return null;
+    const bundleIdentifierSel = c.sel_registerName("bundleIdentifier") orelse return null;
+    const localizedNameSel = c.sel_registerName("localizedName") orelse return null;
+
+    const msgSend_app: *const fn (c.Class, c.SEL, i32) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_obj: *const fn (c.id, c.SEL) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+
+    const app = msgSend_app(NSRunningApplication, runningApplicationSel, pid) orelse return null;
+
+    var result = FrontmostApp{ .pid = pid };
+    if (nsStringSlice(msgSend_obj(app, bundleIdentifierSel))) |bundle_id| {
+        result.bundle_id_len = copyUtf8(&result.bundle_id_buf, bundle_id).len;
+    }
+    if (nsStringSlice(msgSend_obj(app, localizedNameSel))) |app_name| {
+        result.name_len = copyUtf8(&result.name_buf, app_name).len;
+    }
+    return result;
+}
+
+/// Process ID of the first normal-layer window in the on-screen list, which comes
+/// front to back. Reading the owner needs no screen recording permission.
+fn frontmostWindowOwner() ?i32 {
+    const windows = c.CGWindowListCopyWindowInfo(on_screen_windows, 0) orelse return null;
+    defer c.CFRelease(windows);
+
+    const countSel = c.sel_registerName("count") orelse return null;
+    const objectAtIndexSel = c.sel_registerName("objectAtIndex:") orelse return null;
+    const objectForKeySel = c.sel_registerName("objectForKey:") orelse return null;
+    const intValueSel = c.sel_registerName("intValue") orelse return null;
+
+    const msgSend_count: *const fn (c.id, c.SEL) callconv(cc) u64 = @ptrCast(&c.objc_msgSend);
+    const msgSend_index: *const fn (c.id, c.SEL, u64) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_key: *const fn (c.id, c.SEL, c.id) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    const msgSend_int: *const fn (c.id, c.SEL) callconv(cc) i32 = @ptrCast(&c.objc_msgSend);
+
+    const layer_key = nsString("kCGWindowLayer") orelse return null;
+    const owner_key = nsString("kCGWindowOwnerPID") orelse return null;
+
+    const count = msgSend_count(windows, countSel);
+    var index: u64 = 0;
+    while (index < count) : (index += 1) {
+        const info = msgSend_index(windows, objectAtIndexSel, index) orelse continue;
+        const layer = msgSend_key(info, objectForKeySel, layer_key) orelse continue;
+        // Menu bar, Dock and overlays sit above layer 0
+        if (msgSend_int(layer, intValueSel) != 0) continue;
+        const owner = msgSend_key(info, objectForKeySel, owner_key) orelse continue;
+        return msgSend_int(owner, intValueSel);
+    }
+    return null;
+}
+
+/// Title of the focused window of the app with `pid` (the document name, the browser
+/// tab), copied into `buf` and cut at a UTF-8 boundary if it doesn't fit. Needs the
+/// Accessibility permission; null without it, or when the window has no title.
+pub fn getFocusedWindowTitle(pid: i32, buf: []u8) ?[]const u8 {
+    const pool = c.objc_autoreleasePoolPush();
+    defer c.objc_autoreleasePoolPop(pool);
+
+    const app = c.AXUIElementCreateApplication(pid) orelse return null;
+    defer c.CFRelease(app);
+
+    var window: ?c.id = null;
+    // kAXErrorSuccess
+    if (c.AXUIElementCopyAttributeValue(app, nsString("AXFocusedWindow") orelse return null, &window) != 0) return null;
+    const focused = window orelse return null;
+    defer c.CFRelease(focused);
+
+    var title: ?c.id = null;
+    if (c.AXUIElementCopyAttributeValue(focused, nsString("AXTitle") orelse return null, &title) != 0) return null;
+    const title_string = title orelse return null;
+    defer c.CFRelease(title_string);
+
+    const text = nsStringSlice(title_string) orelse return null;
+    const copied = copyUtf8(buf, text);
+    return if (copied.len > 0) copied else null;
+}
+
+/// Copies `text` into `buf`, cut at a UTF-8 boundary if it doesn't fit.
+fn copyUtf8(buf: []u8, text: []const u8) []u8 {
+    var len = @min(text.len, buf.len);
+    while (len > 0 and len < text.len and (text[len] & 0xc0) == 0x80) len -= 1;
+    @memcpy(buf[0..len], text[0..len]);
+    return buf[0..len];
+}
+
+/// An autoreleased NSString, which CoreFoundation takes as a CFStringRef.
+fn nsString(literal: [*:0]const u8) ?c.id {
+    const NSString = c.objc_getClass("NSString") orelse return null;
+    const stringWithUTF8StringSel = c.sel_registerName("stringWithUTF8String:") orelse return null;
+    const msgSend_string: *const fn (c.Class, c.SEL, [*:0]const u8) callconv(cc) ?c.id = @ptrCast(&c.objc_msgSend);
+    return msgSend_string(NSString, stringWithUTF8StringSel, literal);
+}
+
+fn nsStringSlice(string: ?c.id) ?[]const u8 {
+    const str = string orelse return null;
+    const utf8StringSel = c.sel_registerName("UTF8String") orelse return null;
+    const msgSend_utf8: *const fn (c.id, c.SEL) callconv(cc) ?[*:0]const u8 = @ptrCast(&c.objc_msgSend);
+    const bytes = msgSend_utf8(str, utf8StringSel) orelse return null;
+    return std.mem.span(bytes);
+}
@@ -1,22 +1,315 @@
 const std = @import("std");
 const manager = @import("manager.zig");
 const clipboard = @import("clipboard.zig");
+const protocol = @import("protocol.zig");
+const hashing = @import("hashing.zig");
 
 pub const LoadResult = struct {
     entries: std.ArrayList(manager.ClipboardEntry),
+    /// Removed entries still in the trash, oldest removal first.
+    trash: std.ArrayList(manager.TrashedEntry) = .empty,
     next_entry_id: u64,
 };
 
-fn hasEntryId(entries: []const manager.ClipboardEntry, entry_id: u64) bool {
+fn hasEntryId(entries: []const manager.ClipboardEntry, trash: []const manager.TrashedEntry, entry_id: u64) bool {
     for (entries) |entry| {
         if (entry.id == entry_id) return true;
     }
+    for (trash) |trashed| {
+        if (trashed.entry.id == entry_id) return true;
+    }
     return false;
 }
 
+/// One entry object from the history file, with the fields its `version` has. The
+/// id is zero when the file has none. Null when the object isn't an entry.
+fn parseEntry(allocator: std.mem.Allocator, item: std.json.Value, version: i64) !?manager.ClipboardEntry {
+    if (item != .object) return null;
+    const content_field = item.object.get("content") orelse return null;
+    const timestamp_field = item.object.get("timestamp") orelse return null;
+    if (content_field != .string or timestamp_field != .integer) return null;
+
+    const content_str = content_field.string;
+    const timestamp = timestamp_field.integer;
+
+    // Handle entry type - default to text for backward compatibility
+    var entry_type: clipboard.ClipboardType = .text;
+    if (version >= 2) {
+        if (item.object.get("type")) |type_field| {
+            if (type_field == .string) {
+                const type_str = type_field.string;
+                if (std.mem.eql(u8, type_str, "image")) {
+                    entry_type = .image;
+                } else if (std.mem.eql(u8, type_str, "file")) {
+                    entry_type = .file;
+                } else if (std.mem.eql(u8, type_str, "url")) {
+                    entry_type = .url;
+                } else if (std.mem.eql(u8, type_str, "color")) {
+                    entry_type = .color;
+                } else {
+                    entry_type = .text;
+                }
+            }
+        }
+    }
+
+    var pinned = false;
+    if (version >= 3) {
+        if (item.object.get("pinned")) |pinned_field| {
+            if (pinned_field == .bool) {
+                pinned = pinned_field.bool;
+            }
+        }
+    }
+
+    var tags: []const u8 = "";
+    if (version >= 5) {
+        if (item.object.get("tags")) |tags_field| {
+            if (tags_field == .string and protocol.isValidTagList(tags_field.string)) {
+                tags = tags_field.string;
+            }
+        }
+    }
+
+    var copies: u32 = 1;
+    if (version >= 6) {
+        if (item.object.get("copies")) |copies_field| {
+            if (copies_field == .integer and copies_field.integer > 0) {
+                copies = std.math.cast(u32, copies_field.integer) orelse std.math.maxInt(u32);
+            }
+        }
+    }
+
+    var source = clipboard.Source{};
+    if (version >= 7) {
+        if (item.object.get("source_app")) |app_field| {
+            if (app_field == .string) source.app = app_field.string;
+        }
+        if (item.object.get("window_title")) |title_field| {
+            if (title_field == .string) source.window_title = title_field.string;
+        }
+    }
+
+    var formats = std.ArrayList(clipboard.Format){};
+    errdefer {
+        for (formats.items) |format| allocator.free(format.data);
+        formats.deinit(allocator);
+    }
+    if (version >= 10) {
+        if (item.object.get("formats")) |formats_field| {
+            if (formats_field == .array) {
+                for (formats_field.array.items) |format_field| {
+                    const format = try parseFormat(allocator, format_field, "kind") orelse continue;
+                    formats.append(allocator, format) catch |err| {
+                        allocator.free(format.data);
+                        return err;
+                    };
+                }
+            }
+        }
+    } else if (version == 9) {
+        // At most one, RTF or HTML
+        if (item.object.get("rich")) |rich_field| {
+            if (try parseFormat(allocator, rich_field, "format")) |format| {
+                formats.append(allocator, format) catch |err| {
+                    allocator.free(format.data);
+                    return err;
+                };
+            }
+        }
+    }
+    const formats_copy = try formats.toOwnedSlice(allocator);
+    errdefer clipboard.freeFormats(allocator, formats_copy);
+
+    // Zero when missing; the caller hands out a free one
+    var entry_id: u64 = 0;
+    if (version >= 4) {
+        if (item.object.get("id")) |id_field| {
+            if (id_field == .integer and id_field.integer > 0) {
+                entry_id = std.math.cast(u64, id_field.integer) orelse 0;
+            }
+        }
+    }
+
+    const content_copy = try allocator.dupe(u8, content_str);
+    errdefer allocator.free(content_copy);
+    const tags_copy = if (tags.len > 0) try allocator.dupe(u8, tags) else "";
+    var entry = manager.ClipboardEntry{
+        .id = entry_id,
+        .content = content_copy,
+        .timestamp = timestamp,
+        .entry_type = entry_type,
+        .pinned = pinned,
+        .tags = tags_copy,
+        .hash = hashing.contentHash(entry_type, content_str),
+        .copies = copies,
+        .formats = formats_copy,
+    };
+    entry.setSource(allocator, source) catch |err| {
+        if (tags_copy.len > 0) allocator.free(tags_copy);
+        return err;
+    };
+    return entry;
+}
+
+/// One of an entry's other forms: `{"kind": "html", "data": <base64>}`, or for a
+/// picture `{"kind": "image", "path": ...}`; version 9 named the kind `format`. Null
+/// when it is malformed.
+fn parseFormat(allocator: std.mem.Allocator, value: std.json.Value, kind_name: []const u8) !?clipboard.Format {
+    if (value != .object) return null;
+    const kind_field = value.object.get(kind_name) orelse return null;
+    if (kind_field != .string) return null;
+    const kind = std.meta.stringToEnum(clipboard.FormatKind, kind_field.string) orelse return null;
+    switch (kind) {
+        // The content itself
+        .text => return null,
+        .image => {
+            const path_field = value.object.get("path") orelse return null;
+            if (path_field != .string) return null;
+            return .{ .kind = kind, .data = try allocator.dupe(u8, path_field.string) };
+        },
+        .rtf, .html => {
+            const data_field = value.object.get("data") orelse return null;
+            if (data_field != .string) return null;
+            const decoder = std.base64.standard.Decoder;
+            const size = decoder.calcSizeForSlice(data_field.string) catch return null;
+            const data = try allocator.alloc(u8, size);
+            decoder.decode(data, data_field.string) catch {
+                allocator.free(data);
+                return null;
+            };
+            return .{ .kind = kind, .data = data };
+        },
+    }
+}
+
+/// `proposed` unless it is zero or already used by a loaded entry, in which case the
+/// next free id. Keeps `next_entry_id` past every id handed out.
+fn uniqueEntryId(entries: []const manager.ClipboardEntry, trash: []const manager.TrashedEntry, proposed: u64, next_entry_id: *u64) u64 {
+    var entry_id = if (proposed == 0) next_entry_id.* else proposed;
+    while (hasEntryId(entries, trash, entry_id)) {
+        entry_id = next_entry_id.*;
+        next_entry_id.* +%= 1;
+        if (next_entry_id.* == 0) next_entry_id.* = 1;
+    }
+    if (entry_id >= next_entry_id.*) {
+        next_entry_id.* = entry_id +% 1;
+        if (next_entry_id.* == 0) next_entry_id.* = 1;
+    }
+    return entry_id;
+}
+
+/// Writes an entry's fields, one per line, without a newline after the last.
+fn writeEntryFields(writer: anytype, entry: manager.ClipboardEntry) !void {
+    try writer.print("      \"id\": {d},\n", .{entry.id});
+    try writer.writeAll("      \"content\": ");
+    try writeJsonString(writer, entry.content);
+    try writer.writeAll(",\n");
+    try writer.print("      \"timestamp\": {d},\n", .{entry.timestamp});
+    try writer.print("      \"type\": \"{s}\",\n", .{@tagName(entry.entry_type)});
+    try writer.print("      \"pinned\": {s},\n", .{if (entry.pinned) "true" else "false"});
+    try writer.print("      \"tags\": \"{s}\",\n", .{entry.tags});
+    try writer.print("      \"copies\": {d},\n", .{entry.copies});
+    try writer.writeAll("      \"source_app\": ");
+    try writeJsonString(writer, entry.source_app);
+    try writer.writeAll(",\n      \"window_title\": ");
+    try writeJsonString(writer, entry.window_title);
+    if (entry.formats.len > 0) {
+        try writer.writeAll(",\n      \"formats\": [");
+        for (entry.formats, 0..) |format, i| {
+            if (i > 0) try writer.writeAll(", ");
+            try writer.print("{{\"kind\": \"{s}\", ", .{@tagName(format.kind)});
+            if (format.kind == .image) {
+                try writer.writeAll("\"path\": ");
+                try writeJsonString(writer, format.data);
+            } else {
+                try writer.writeAll("\"data\": \"");
+                try writeBase64(writer, format.data);
+                try writer.writeByte('"');
+            }
+            try writer.writeByte('}');
+        }
+        try writer.writeByte(']');
+    }
+}
+
+/// Writes `data` base64-encoded, a chunk at a time.
+fn writeBase64(writer: anytype, data: []const u8) !void {
+    const encoder = std.base64.standard.Encoder;
+    // A multiple of 3, so only the last chunk is padded
+    const chunk_len = 3 * 256;
+    var buf: [4 * 256]u8 = undefined;
+    var rest = data;
+    while (rest.len > 0) {
+        const chunk = rest[0..@min(chunk_len, rest.len)];
+        try writer.writeAll(encoder.encode(&buf, chunk));
+        rest = rest[chunk.len..];
+    }
+}
+
+/// Encrypted history files start with this, followed by the nonce, the tag and the
+/// XChaCha20-Poly1305 ciphertext of the usual JSON document.
+const encrypted_magic = "CLIPZENC1\n";
+const Aead = std.crypto.aead.chacha_poly.XChaCha20Poly1305;
+
+pub const PersistenceError = error{
+    // The file is encrypted but no key was given; refuse to load rather than overwrite it
+    HistoryKeyMissing,
+    HistoryDecryptFailed,
+};
+
+/// Writes `text` as a JSON string, quotes included.
+fn writeJsonString(writer: anytype, text: []const u8) !void {
+    try writer.writeByte('"');
+    for (text) |c| {
+        switch (c) {
+            '"' => try writer.writeAll("\\\""),
+            '\\' => try writer.writeAll("\\\\"),
+            '\n' => try writer.writeAll("\\n"),
+            '\r' => try writer.writeAll("\\r"),
+            '\t' => try writer.writeAll("\\t"),
+            0x00...0x08, 0x0b, 0x0c, 0x0e...0x1f => try writer.print("\\u{0:0>4}", .{c}),
+            else => try writer.writeByte(c),
+        }
+    }
+    try writer.writeByte('"');
+}
+
+fn encrypt(allocator: std.mem.Allocator, plaintext: []const u8, key: [32]u8) ![]u8 {
+    const header_len = encrypted_magic.len + Aead.nonce_length + Aead.tag_length;
+    const out = try allocator.alloc(u8, header_len + plaintext.len);
+    @memcpy(out[0..encrypted_magic.len], encrypted_magic);
+
+    var nonce: [Aead.nonce_length]u8 = undefined;
+    std.crypto.random.bytes(&nonce);
+    @memcpy(out[encrypted_magic.len..][0..Aead.nonce_length], &nonce);
+
+    var tag: [Aead.tag_length]u8 = undefined;
+    Aead.encrypt(out[header_len..], &tag, plaintext, encrypted_magic, nonce, key);
+    @memcpy(out[encrypted_magic.len + Aead.nonce_length ..][0..Aead.tag_length], &tag);
+    return out;
+}
+
+fn decrypt(allocator: std.mem.Allocator, data: []const u8, key: [32]u8) ![]u8 {
+    const header_len = encrypted_magic.len + Aead.nonce_length + Aead.tag_length;
+    if (data.len < header_len) return PersistenceError.HistoryDecryptFailed;
+
+    const nonce = data[encrypted_magic.len..][0..Aead.nonce_length].*;
+    const tag = data[encrypted_magic.len + Aead.nonce_length ..][0..Aead.tag_length].*;
+    const out = try allocator.alloc(u8, data.len - header_len);
+    Aead.decrypt(out, data[header_len..], tag, encrypted_magic, nonce, key) catch {
+        allocator.free(out);
+        return PersistenceError.HistoryDecryptFailed;
+    };
+    return out;
+}
+
 pub const Persistence = struct {
     file_path: [256]u8,
     file_path_len: usize,
+    // Set from config by the manager; see Config.history_key and Config.encrypt_history
+    key: ?[32]u8 = null,
+    encrypt: bool = false,
 
     pub fn init(allocator: std.mem.Allocator) !Persistence {
         const home_dir = try std.process.getEnvVarOwned(allocator, "HOME");
@@ -43,7 +336,7 @@ pub const Persistence = struct {
         };
     }
 
-    pub fn saveEntries(self: *Persistence, allocator: std.mem.Allocator, entries: []const manager.ClipboardEntry, next_entry_id: u64) !void {
+    pub fn saveEntries(self: *Persistence, allocator: std.mem.Allocator, entries: []const manager.ClipboardEntry, trash: []const manager.TrashedEntry, next_entry_id: u64) !void {
         var arena = std.heap.ArenaAllocator.init(allocator);
         defer arena.deinit();
         const arena_allocator = arena.allocator();
@@ -52,37 +345,24 @@ pub const Persistence = struct {
         var writer = json.writer(arena_allocator);
 
         try writer.writeAll("{\n");
-        try writer.print("  \"version\": 4,\n", .{});
+        try writer.print("  \"version\": 10,\n", .{});
         try writer.print("  \"next_id\": {d},\n", .{next_entry_id});
         try writer.print("  \"entries\": [\n", .{});
 
         for (entries, 0..) |entry, i| {
             try writer.writeAll("    {\n");
-            try writer.print("      \"id\": {d},\n", .{entry.id});
-            try writer.writeAll("      \"content\": \"");
-            for (entry.content) |c| {
-                switch (c) {
-                    '"' => try writer.writeAll("\\\""),
-                    '\\' => try writer.writeAll("\\\\"),
-                    '\n' => try writer.writeAll("\\n"),
-                    '\r' => try writer.writeAll("\\r"),
-                    '\t' => try writer.writeAll("\\t"),
-                    0x00...0x08, 0x0b, 0x0c, 0x0e...0x1f => try writer.print("\\u{0:0>4}", .{c}),
-                    else => try writer.writeByte(c),
-                }
-            }
-            try writer.writeAll("\",\n");
-            try writer.print("      \"timestamp\": {d},\n", .{entry.timestamp});
-            try writer.print("      \"type\": \"{s}\",\n", .{@tagName(entry.entry_type)});
-            try writer.print("      \"pinned\": {s}\n", .{if (entry.pinned) "true" else "false"});
-
-            if (i < entries.len - 1) {
-                try writer.writeAll("    },\n");
-            } else {
-                try writer.writeAll("    }\n");
-            }
+            try writeEntryFields(writer, entry);
+            try writer.writeAll(if (i < entries.len - 1) "\n    },\n" else "\n    }\n");
         }
 
+        try writer.writeAll("  ],\n");
+        try writer.writeAll("  \"trash\": [\n");
+        for (trash, 0..) |trashed, i| {
+            try writer.writeAll("    {\n");
+            try writeEntryFields(writer, trashed.entry);
+            try writer.print(",\n      \"removed_at\": {d},\n      \"index\": {d}", .{ trashed.removed_at, trashed.index });
+            try writer.writeAll(if (i < trash.len - 1) "\n    },\n" else "\n    }\n");
+        }
         try writer.writeAll("  ]\n");
         try writer.writeAll("}\n");
 
@@ -96,7 +376,12 @@ pub const Persistence = struct {
 
             std.posix.fchmod(file.handle, 0o600) catch {};
 
-            try file.writeAll(json.items);
+            if (self.encrypt) {
+                const key = self.key orelse return PersistenceError.HistoryKeyMissing;
+                try file.writeAll(try encrypt(arena_allocator, json.items, key));
+            } else {
+                try file.writeAll(json.items);
+            }
             try file.sync();
         }
 
@@ -111,6 +396,13 @@ pub const Persistence = struct {
             }
             entries.deinit(allocator);
         }
+        var trash = std.ArrayList(manager.TrashedEntry){};
+        errdefer {
+            for (trash.items) |trashed| {
+                trashed.entry.free(allocator);
+            }
+            trash.deinit(allocator);
+        }
         var next_entry_id: u64 = 1;
 
         const file = std.fs.cwd().openFile(self.getFilePath(), .{}) catch |err| switch (err) {
@@ -123,7 +415,11 @@ pub const Persistence = struct {
         defer arena.deinit();
         const arena_allocator = arena.allocator();
 
-        const content = try file.readToEndAlloc(arena_allocator, 10 * 1024 * 1024);
+        const raw_content = try file.readToEndAlloc(arena_allocator, 10 * 1024 * 1024);
+        const content = if (std.mem.startsWith(u8, raw_content, encrypted_magic)) blk: {
+            const key = self.key orelse return PersistenceError.HistoryKeyMissing;
+            break :blk try decrypt(arena_allocator, raw_content, key);
+        } else raw_content;
 
         // Try to parse JSON, but if it fails, return empty entries instead of crashing
         var parsed = std.json.parseFromSlice(std.json.Value, arena_allocator, content, .{}) catch |err| {
@@ -142,72 +438,34 @@ pub const Persistence = struct {
         if (entries_array != .array) return .{ .entries = entries, .next_entry_id = next_entry_id };
 
         for (entries_array.array.items) |item| {
-            if (item != .object) continue;
-            const content_field = item.object.get("content") orelse continue;
-            const timestamp_field = item.object.get("timestamp") orelse continue;
-            if (content_field != .string or timestamp_field != .integer) continue;
-
-            const content_str = content_field.string;
-            const timestamp = timestamp_field.integer;
-
-            // Handle entry type - default to text for backward compatibility
-            var entry_type: clipboard.ClipboardType = .text;
-            if (version >= 2) {
-                if (item.object.get("type")) |type_field| {
-                    if (type_field == .string) {
-                        const type_str = type_field.string;
-                        if (std.mem.eql(u8, type_str, "image")) {
-                            entry_type = .image;
-                        } else if (std.mem.eql(u8, type_str, "file")) {
-                            entry_type = .file;
-                        } else if (std.mem.eql(u8, type_str, "url")) {
-                            entry_type = .url;
-                        } else if (std.mem.eql(u8, type_str, "color")) {
-                            entry_type = .color;
-                        } else {
-                            entry_type = .text;
-                        }
-                    }
-                }
-            }
-
-            var pinned = false;
-            if (version >= 3) {
-                if (item.object.get("pinned")) |pinned_field| {
-                    if (pinned_field == .bool) {
-                        pinned = pinned_field.bool;
-                    }
-                }
-            }
-
-            var entry_id = next_entry_id;
-            if (version >= 4) {
-                if (item.object.get("id")) |id_field| {
-                    if (id_field == .integer and id_field.integer > 0) {
-                        entry_id = std.math.cast(u64, id_field.integer) orelse next_entry_id;
-                    }
-                }
-            }
-            if (entry_id == 0) entry_id = next_entry_id;
-            while (hasEntryId(entries.items, entry_id)) {
-                entry_id = next_entry_id;
-                next_entry_id +%= 1;
-                if (next_entry_id == 0) next_entry_id = 1;
-            }
-
-            const content_copy = try allocator.dupe(u8, content_str);
-            const entry = manager.ClipboardEntry{
-                .id = entry_id,
-                .content = content_copy,
-                .timestamp = timestamp,
-                .entry_type = entry_type,
-                .pinned = pinned,
+            var entry = try parseEntry(allocator, item, version) orelse continue;
+            entry.id = uniqueEntryId(entries.items, trash.items, entry.id, &next_entry_id);
+            entries.append(allocator, entry) catch |err| {
+                entry.free(allocator);
+                return err;
             };
-            try entries.append(allocator, entry);
+        }
 
-            if (entry_id >= next_entry_id) {
-                next_entry_id = entry_id +% 1;
-                if (next_entry_id == 0) next_entry_id = 1;
+        if (version >= 8) trash: {
+            const trash_array = root.object.get("trash") orelse break :trash;
+            if (trash_array != .array) break :trash;
+            for (trash_array.array.items) |item| {
+                var entry = try parseEntry(allocator, item, version) orelse continue;
+                const removed_at = item.object.get("removed_at");
+                const index = item.object.get("index");
+                if (removed_at == null or removed_at.? != .integer or index == null or index.? != .integer or index.?.integer < 0) {
+                    entry.free(allocator);
+                    continue;
+                }
+                entry.id = uniqueEntryId(entries.items, trash.items, entry.id, &next_entry_id);
+                trash.append(allocator, .{
+                    .entry = entry,
+                    .index = std.math.cast(usize, index.?.integer) orelse 0,
+                    .removed_at = removed_at.?.integer,
+                }) catch |err| {
+                    entry.free(allocator);
+                    return err;
+                };
             }
         }
 
@@ -224,6 +482,7 @@ pub const Persistence = struct {
 
         return .{
             .entries = entries,
+            .trash = trash,
             .next_entry_id = next_entry_id,
         };
     }
@@ -0,0 +1,839 @@
+const std = @import("std");
+const clipboard = @import("clipboard.zig");
+const config = @import("config.zig");
+
+/// Commands understood by the JSON API.
+///
+/// Requests arrive either as legacy plain-text lines (`select-entry-id:42`) or as
+/// JSON-RPC 2.0 style objects (`{"jsonrpc":"2.0","id":7,"method":"select-entry","params":{"id":42}}`).
+/// Both forms are parsed into the same `Command` so the dispatcher doesn't care which one was used.
+/// Bumped whenever a command or message changes shape. The frontend sends its own with
+/// `hello` and is told this one, so a backend from another build is caught up front.
+pub const version: u32 = 7;
+
+pub const Command = union(enum) {
+    /// The frontend's protocol version; answered with the backend's.
+    hello: u32,
+    get_entries: EntriesPage,
+    search: Search,
+    select_entry_id: u64,
+    select_entry: usize,
+    /// Puts only one form of an entry on the clipboard.
+    select_format: SelectFormat,
+    remove_entry_id: u64,
+    remove_entry: usize,
+    /// Takes an entry out of the trash and back into the history.
+    restore_entry_id: u64,
+    /// Removes an entry for good, from the history or the trash.
+    purge_entry_id: u64,
+    get_trash,
+    empty_trash,
+    toggle_pin_id: u64,
+    toggle_pin: usize,
+    swap_entry_id: u64,
+    set_clipboard: SetClipboard,
+    add_entry: AddEntry,
+    /// Entries from an export, merged into the history as one change.
+    import_entries: []ImportEntry,
+    set_paused: bool,
+    set_append_mode: bool,
+    /// Off, copied image data is skipped instead of saved; used while disk space is low.
+    set_image_capture: bool,
+    set_tags: SetTags,
+    get_snippets,
+    save_snippet: SaveSnippet,
+    remove_snippet_id: u64,
+    /// The newest clipboard events, at most this many; null for all that are kept.
+    get_timeline: ?usize,
+    /// New history size; `config.unlimited_entries` when there is no limit.
+    set_limit: usize,
+    clear,
+    quit,
+    invalid: ErrorCode,
+};
+
+/// Slice of the history requested by `get-entries`; the default is all of it.
+pub const EntriesPage = struct {
+    offset: usize = 0,
+    limit: ?usize = null,
+};
+
+/// Entries matching `query` anywhere in the history, best match first; see `search.zig`.
+pub const Search = struct {
+    query: []const u8,
+    /// Entry types to keep; empty keeps every type.
+    types: std.EnumSet(clipboard.ClipboardType) = .initEmpty(),
+    /// Most matches to send; null for all of them.
+    limit: ?usize = null,
+    /// Text only the frontend knows about some entries, matched like their own fields.
+    texts: []SearchText = &.{},
+};
+
+/// Text the frontend found for entries with `content`: read in the image at that path
+/// or the title of the page at that URL.
+pub const SearchText = struct {
+    content: []const u8,
+    text: []const u8,
+
+    pub fn deinit(self: SearchText, allocator: std.mem.Allocator) void {
+        allocator.free(self.content);
+        allocator.free(self.text);
+    }
+};
+
+pub const SelectFormat = struct {
+    entry_id: u64,
+    kind: clipboard.FormatKind,
+};
+
+pub const SetClipboard = struct {
+    content: []const u8,
+    entry_type: clipboard.ClipboardType,
+};
+
+pub const AddEntry = struct {
+    content: []const u8,
+    entry_type: clipboard.ClipboardType,
+    pinned: bool = false,
+    /// Validated tags joined with commas, as in `SetTags`.
+    tags: []const u8 = "",
+};
+
+/// An entry in `import-entries`: `add-entry`'s fields plus when it was last copied.
+pub const ImportEntry = struct {
+    content: []const u8,
+    entry_type: clipboard.ClipboardType,
+    /// Seconds since the epoch, like `ClipboardEntry.timestamp`; null for now.
+    timestamp: ?i64 = null,
+    pinned: bool = false,
+    /// Validated tags joined with commas, as in `SetTags`.
+    tags: []const u8 = "",
+
+    pub fn deinit(self: ImportEntry, allocator: std.mem.Allocator) void {
+        allocator.free(self.content);
+        if (self.tags.len > 0) allocator.free(self.tags);
+    }
+};
+
+pub const SetTags = struct {
+    entry_id: u64,
+    /// Validated tags joined with commas; empty clears them.
+    tags: []const u8,
+};
+
+/// `save-snippet` without an id adds a snippet; with one it replaces that snippet.
+pub const SaveSnippet = struct {
+    id: ?u64 = null,
+    name: []const u8,
+    content: []const u8,
+};
+
+/// Longest tag accepted by `set-tags`.
+pub const max_tag_len = 32;
+
+/// Tags are limited to ASCII letters, digits, `-` and `_`, so they can be stored
+/// comma-separated and written into JSON without escaping.
+pub fn isValidTag(tag: []const u8) bool {
+    if (tag.len == 0 or tag.len > max_tag_len) return false;
+    for (tag) |c| {
+        if (!std.ascii.isAlphanumeric(c) and c != '-' and c != '_') return false;
+    }
+    return true;
+}
+
+/// Whether `tags` is a comma-separated list of valid tags (or empty).
+pub fn isValidTagList(tags: []const u8) bool {
+    if (tags.len == 0) return true;
+    var it = std.mem.splitScalar(u8, tags, ',');
+    while (it.next()) |tag| {
+        if (!isValidTag(tag)) return false;
+    }
+    return true;
+}
+
+pub const ErrorCode = enum {
+    unknown_command,
+    invalid_request,
+    invalid_id,
+    invalid_index,
+    command_too_long,
+    operation_failed,
+
+    pub fn name(self: ErrorCode) []const u8 {
+        return switch (self) {
+            .unknown_command => "unknown-command",
+            .invalid_request => "invalid-request",
+            .invalid_id => "invalid-id",
+            .invalid_index => "invalid-index",
+            .command_too_long => "command-too-long",
+            .operation_failed => "operation-failed",
+        };
+    }
+
+    pub fn defaultMessage(self: ErrorCode) []const u8 {
+        return switch (self) {
+            .unknown_command => "Unknown command",
+            .invalid_request => "Invalid request",
+            .invalid_id => "Invalid id",
+            .invalid_index => "Invalid index",
+            .command_too_long => "Command too long",
+            .operation_failed => "Operation failed",
+        };
+    }
+};
+
+pub const Request = struct {
+    // Correlation id echoed back as "requestId" on the response; null for legacy commands
+    id: ?u64 = null,
+    command: Command,
+
+    /// Frees payloads copied out of the JSON document.
+    pub fn deinit(self: Request, allocator: std.mem.Allocator) void {
+        switch (self.command) {
+            .set_clipboard => |payload| allocator.free(payload.content),
+            .add_entry => |payload| {
+                allocator.free(payload.content);
+                if (payload.tags.len > 0) allocator.free(payload.tags);
+            },
+            .import_entries => |entries| {
+                for (entries) |entry| entry.deinit(allocator);
+                allocator.free(entries);
+            },
+            .set_tags => |payload| allocator.free(payload.tags),
+            .search => |payload| {
+                allocator.free(payload.query);
+                for (payload.texts) |text| text.deinit(allocator);
+                if (payload.texts.len > 0) allocator.free(payload.texts);
+            },
+            .save_snippet => |payload| {
+                allocator.free(payload.name);
+                allocator.free(payload.content);
+            },
+            else => {},
+        }
+    }
+};
+
+pub fn parseRequest(allocator: std.mem.Allocator, line: []const u8) Request {
+    if (line.len > 0 and line[0] == '{') {
+        return parseJsonRequest(allocator, line);
+    }
+    return .{ .command = parseLegacyCommand(allocator, line) };
+}
+
+fn parseLegacyCommand(allocator: std.mem.Allocator, line: []const u8) Command {
+    if (std.mem.eql(u8, line, "quit")) return .quit;
+    if (std.mem.eql(u8, line, "get-entries")) return .{ .get_entries = .{} };
+    if (std.mem.eql(u8, line, "clear")) return .clear;
+
+    if (std.mem.startsWith(u8, line, "select-entry-id:")) {
+        const entry_id = std.fmt.parseInt(u64, line["select-entry-id:".len..], 10) catch return .{ .invalid = .invalid_id };
+        return .{ .select_entry_id = entry_id };
+    }
+    if (std.mem.startsWith(u8, line, "select-entry:")) {
+        const index = std.fmt.parseInt(usize, line["select-entry:".len..], 10) catch return .{ .invalid = .invalid_index };
+        return .{ .select_entry = index };
+    }
+    if (std.mem.startsWith(u8, line, "remove-entry-id:")) {
+        const entry_id = std.fmt.parseInt(u64, line["remove-entry-id:".len..], 10) catch return .{ .invalid = .invalid_id };
+        return .{ .remove_entry_id = entry_id };
+    }
+    if (std.mem.startsWith(u8, line, "remove-entry:")) {
+        const index = std.fmt.parseInt(usize, line["remove-entry:".len..], 10) catch return .{ .invalid = .invalid_index };
+        return .{ .remove_entry = index };
+    }
+    if (std.mem.startsWith(u8, line, "toggle-pin-id:")) {
+        const entry_id = std.fmt.parseInt(u64, line["toggle-pin-id:".len..], 10) catch return .{ .invalid = .invalid_id };
+        return .{ .toggle_pin_id = entry_id };
+    }
+    if (std.mem.startsWith(u8, line, "search:")) {
+        const query = allocator.dupe(u8, line["search:".len..]) catch return .{ .invalid = .operation_failed };
+        return .{ .search = .{ .query = query } };
+    }
+    if (std.mem.startsWith(u8, line, "toggle-pin:")) {
+        const index = std.fmt.parseInt(usize, line["toggle-pin:".len..], 10) catch return .{ .invalid = .invalid_index };
+        return .{ .toggle_pin = index };
+    }
+
+    return .{ .invalid = .unknown_command };
+}
+
+fn parseJsonRequest(allocator: std.mem.Allocator, line: []const u8) Request {
+    var request = Request{ .command = .{ .invalid = .invalid_request } };
+
+    var parsed = std.json.parseFromSlice(std.json.Value, allocator, line, .{}) catch return request;
+    defer parsed.deinit();
+    const root = parsed.value;
+    if (root != .object) return request;
+
+    if (root.object.get("id")) |id_field| {
+        if (id_field == .integer and id_field.integer >= 0) {
+            request.id = @intCast(id_field.integer);
+        }
+    }
+
+    const method_field = root.object.get("method") orelse return request;
+    if (method_field != .string) return request;
+    const method = method_field.string;
+    const params = root.object.get("params");
+
+    if (std.mem.eql(u8, method, "hello")) {
+        request.command = helloParams(params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "get-entries")) {
+        request.command = if (entriesPageParams(params)) |page| .{ .get_entries = page } else .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "search")) {
+        request.command = searchParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "clear")) {
+        request.command = .clear;
+    } else if (std.mem.eql(u8, method, "quit")) {
+        request.command = .quit;
+    } else if (std.mem.eql(u8, method, "select-entry")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .select_entry_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "select-format")) {
+        request.command = if (entryIdParam(params) == null) .{ .invalid = .invalid_id } else selectFormatParams(params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "remove-entry")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .remove_entry_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "restore-entry")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .restore_entry_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "purge-entry")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .purge_entry_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "get-trash")) {
+        request.command = .get_trash;
+    } else if (std.mem.eql(u8, method, "empty-trash")) {
+        request.command = .empty_trash;
+    } else if (std.mem.eql(u8, method, "toggle-pin")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .toggle_pin_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "swap-entry")) {
+        request.command = if (entryIdParam(params)) |entry_id| .{ .swap_entry_id = entry_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "set-paused")) {
+        request.command = if (boolParam(params, "paused")) |paused| .{ .set_paused = paused } else .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "set-append-mode")) {
+        request.command = if (boolParam(params, "enabled")) |enabled| .{ .set_append_mode = enabled } else .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "set-image-capture")) {
+        request.command = if (boolParam(params, "enabled")) |enabled| .{ .set_image_capture = enabled } else .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "set-tags")) {
+        request.command = if (entryIdParam(params) == null) .{ .invalid = .invalid_id } else setTagsParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "set-clipboard")) {
+        request.command = setClipboardParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "add-entry")) {
+        request.command = addEntryParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "import-entries")) {
+        request.command = importEntriesParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "get-snippets")) {
+        request.command = .get_snippets;
+    } else if (std.mem.eql(u8, method, "save-snippet")) {
+        request.command = saveSnippetParams(allocator, params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "remove-snippet")) {
+        request.command = if (entryIdParam(params)) |snippet_id| .{ .remove_snippet_id = snippet_id } else .{ .invalid = .invalid_id };
+    } else if (std.mem.eql(u8, method, "get-timeline")) {
+        request.command = getTimelineParams(params) orelse .{ .invalid = .invalid_request };
+    } else if (std.mem.eql(u8, method, "set-limit")) {
+        request.command = setLimitParams(params) orelse .{ .invalid = .invalid_request };
+    } else {
+        request.command = .{ .invalid = .unknown_command };
+    }
+
+    return request;
+}
+
+fn entryIdParam(params: ?std.json.Value) ?u64 {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const id_field = value.object.get("id") orelse return null;
+    if (id_field != .integer or id_field.integer <= 0) return null;
+    return @intCast(id_field.integer);
+}
+
+fn boolParam(params: ?std.json.Value, name: []const u8) ?bool {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const field = value.object.get(name) orelse return null;
+    if (field != .bool) return null;
+    return field.bool;
+}
+
+fn entriesPageParams(params: ?std.json.Value) ?EntriesPage {
+    const value = params orelse return .{};
+    if (value != .object) return null;
+    var page: EntriesPage = .{};
+    if (value.object.get("offset")) |offset_field| {
+        if (offset_field != .integer or offset_field.integer < 0) return null;
+        page.offset = @intCast(offset_field.integer);
+    }
+    if (value.object.get("limit")) |limit_field| {
+        if (limit_field != .integer or limit_field.integer <= 0) return null;
+        page.limit = @intCast(limit_field.integer);
+    }
+    return page;
+}
+
+/// `search` takes a `query`, which may be empty, plus optional `types`, a positive
+/// `limit` and `texts`, each with a `content` and its `text`.
+fn searchParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const query_field = value.object.get("query") orelse return null;
+    if (query_field != .string) return null;
+
+    var search: Search = .{ .query = undefined };
+    if (value.object.get("types")) |types_field| {
+        if (types_field != .array) return null;
+        for (types_field.array.items) |type_field| {
+            if (type_field != .string) return null;
+            search.types.insert(std.meta.stringToEnum(clipboard.ClipboardType, type_field.string) orelse return null);
+        }
+    }
+    if (value.object.get("limit")) |limit_field| {
+        if (limit_field != .integer or limit_field.integer <= 0) return null;
+        search.limit = @intCast(limit_field.integer);
+    }
+    if (value.object.get("texts")) |texts_field| {
+        if (texts_field != .array) return null;
+        search.texts = parseSearchTexts(allocator, texts_field.array.items) orelse return null;
+    }
+    search.query = allocator.dupe(u8, query_field.string) catch {
+        for (search.texts) |text| text.deinit(allocator);
+        if (search.texts.len > 0) allocator.free(search.texts);
+        return null;
+    };
+    return .{ .search = search };
+}
+
+/// Each of `fields` as a `SearchText`; null, with nothing left allocated, at the first
+/// one that isn't valid.
+fn parseSearchTexts(allocator: std.mem.Allocator, fields: []const std.json.Value) ?[]SearchText {
+    const texts = allocator.alloc(SearchText, fields.len) catch return null;
+    for (fields, 0..) |field, parsed| {
+        texts[parsed] = searchTextParams(allocator, field) orelse {
+            for (texts[0..parsed]) |text| text.deinit(allocator);
+            allocator.free(texts);
+            return null;
+        };
+    }
+    return texts;
+}
+
+fn searchTextParams(allocator: std.mem.Allocator, value: std.json.Value) ?SearchText {
+    if (value != .object) return null;
+    const content_field = value.object.get("content") orelse return null;
+    const text_field = value.object.get("text") orelse return null;
+    if (content_field != .string or text_field != .string) return null;
+    const content = allocator.dupe(u8, content_field.string) catch return null;
+    const text = allocator.dupe(u8, text_field.string) catch {
+        allocator.free(content);
+        return null;
+    };
+    return .{ .content = content, .text = text };
+}
+
+/// `hello` takes the frontend's `protocolVersion`.
+fn helloParams(params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const version_field = value.object.get("protocolVersion") orelse return null;
+    if (version_field != .integer or version_field.integer < 0 or version_field.integer > std.math.maxInt(u32)) return null;
+    return .{ .hello = @intCast(version_field.integer) };
+}
+
+/// `get-timeline` takes an optional positive `limit`, the same as `get-entries`.
+fn getTimelineParams(params: ?std.json.Value) ?Command {
+    const value = params orelse return .{ .get_timeline = null };
+    if (value != .object) return null;
+    const limit_field = value.object.get("limit") orelse return .{ .get_timeline = null };
+    if (limit_field != .integer or limit_field.integer <= 0) return null;
+    return .{ .get_timeline = @intCast(limit_field.integer) };
+}
+
+/// `set-limit` takes a positive `limit`, or null to keep every entry.
+fn setLimitParams(params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const limit_field = value.object.get("limit") orelse return null;
+    if (limit_field == .null) return .{ .set_limit = config.unlimited_entries };
+    if (limit_field != .integer or limit_field.integer <= 0) return null;
+    return .{ .set_limit = @intCast(limit_field.integer) };
+}
+
+/// `select-format` takes the entry's `id` and a `format`: `text`, `rtf`, `html` or
+/// `image`.
+fn selectFormatParams(params: ?std.json.Value) ?Command {
+    const entry_id = entryIdParam(params) orelse return null;
+    const format_field = params.?.object.get("format") orelse return null;
+    if (format_field != .string) return null;
+    const kind = std.meta.stringToEnum(clipboard.FormatKind, format_field.string) orelse return null;
+    return .{ .select_format = .{ .entry_id = entry_id, .kind = kind } };
+}
+
+fn setTagsParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const entry_id = entryIdParam(params) orelse return null;
+    const tags_field = params.?.object.get("tags") orelse return null;
+    const tags = joinTags(allocator, tags_field) orelse return null;
+    return .{ .set_tags = .{ .entry_id = entry_id, .tags = tags } };
+}
+
+/// Validates a JSON array of tags and joins them with commas.
+fn joinTags(allocator: std.mem.Allocator, tags_field: std.json.Value) ?[]const u8 {
+    if (tags_field != .array) return null;
+
+    var joined = std.ArrayList(u8){};
+    defer joined.deinit(allocator);
+    for (tags_field.array.items) |tag_field| {
+        if (tag_field != .string or !isValidTag(tag_field.string)) return null;
+        if (joined.items.len > 0) joined.append(allocator, ',') catch return null;
+        joined.appendSlice(allocator, tag_field.string) catch return null;
+    }
+
+    return joined.toOwnedSlice(allocator) catch null;
+}
+
+/// `add-entry` takes the `set-clipboard` params plus optional `pinned` and `tags`, so
+/// imported entries keep them.
+fn addEntryParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const command = setClipboardParams(allocator, params) orelse return null;
+    var entry: AddEntry = .{ .content = command.set_clipboard.content, .entry_type = command.set_clipboard.entry_type };
+    const object = params.?.object;
+
+    if (object.get("pinned")) |pinned_field| {
+        if (pinned_field != .bool) {
+            allocator.free(entry.content);
+            return null;
+        }
+        entry.pinned = pinned_field.bool;
+    }
+    if (object.get("tags")) |tags_field| {
+        entry.tags = joinTags(allocator, tags_field) orelse {
+            allocator.free(entry.content);
+            return null;
+        };
+    }
+    return .{ .add_entry = entry };
+}
+
+/// `import-entries` takes `entries`, each with `add-entry`'s params and an optional
+/// `timestamp` in milliseconds, as entries are sent. One bad entry fails the batch.
+fn importEntriesParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const entries_field = value.object.get("entries") orelse return null;
+    if (entries_field != .array) return null;
+
+    // Returning null skips errdefer, so the entries parsed so far are freed here
+    var entries = std.ArrayList(ImportEntry){};
+    if (parseImportEntries(allocator, entries_field.array.items, &entries)) {
+        if (entries.toOwnedSlice(allocator)) |owned| return .{ .import_entries = owned } else |_| {}
+    }
+    for (entries.items) |entry| entry.deinit(allocator);
+    entries.deinit(allocator);
+    return null;
+}
+
+/// Appends each of `fields` to `entries`; false at the first one that isn't valid.
+fn parseImportEntries(allocator: std.mem.Allocator, fields: []const std.json.Value, entries: *std.ArrayList(ImportEntry)) bool {
+    for (fields) |entry_field| {
+        const command = addEntryParams(allocator, entry_field) orelse return false;
+        const added = command.add_entry;
+        var entry: ImportEntry = .{ .content = added.content, .entry_type = added.entry_type, .pinned = added.pinned, .tags = added.tags };
+        if (entry_field.object.get("timestamp")) |timestamp_field| {
+            if (timestamp_field != .integer or timestamp_field.integer < 0) {
+                entry.deinit(allocator);
+                return false;
+            }
+            entry.timestamp = @divTrunc(timestamp_field.integer, std.time.ms_per_s);
+        }
+        entries.append(allocator, entry) catch {
+            entry.deinit(allocator);
+            return false;
+        };
+    }
+    return true;
+}
+
+fn setClipboardParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const content_field = value.object.get("content") orelse return null;
+    if (content_field != .string or content_field.string.len == 0) return null;
+
+    var entry_type: clipboard.ClipboardType = .text;
+    if (value.object.get("type")) |type_field| {
+        if (type_field != .string) return null;
+        entry_type = std.meta.stringToEnum(clipboard.ClipboardType, type_field.string) orelse return null;
+    }
+
+    const content = allocator.dupe(u8, content_field.string) catch return null;
+    return .{ .set_clipboard = .{ .content = content, .entry_type = entry_type } };
+}
+
+/// `save-snippet` needs a non-empty `name` and `content`; `id` is optional.
+fn saveSnippetParams(allocator: std.mem.Allocator, params: ?std.json.Value) ?Command {
+    const value = params orelse return null;
+    if (value != .object) return null;
+    const name_field = value.object.get("name") orelse return null;
+    const content_field = value.object.get("content") orelse return null;
+    if (name_field != .string or name_field.string.len == 0) return null;
+    if (content_field != .string or content_field.string.len == 0) return null;
+
+    var snippet: SaveSnippet = .{ .name = undefined, .content = undefined };
+    if (value.object.get("id")) |_| {
+        snippet.id = entryIdParam(params) orelse return null;
+    }
+    snippet.name = allocator.dupe(u8, name_field.string) catch return null;
+    snippet.content = allocator.dupe(u8, content_field.string) catch {
+        allocator.free(snippet.name);
+        return null;
+    };
+    return .{ .save_snippet = snippet };
+}
+
+/// Formats the `,"requestId":N` fragment appended to responses, or an empty string
+/// when the request carried no id.
+pub fn requestIdField(buf: []u8, request_id: ?u64) []const u8 {
+    const id = request_id orelse return "";
+    return std.fmt.bufPrint(buf, ",\"requestId\":{d}", .{id}) catch "";
+}
+
+test "legacy commands parse without a request id" {
+    const allocator = std.testing.allocator;
+
+    const select = parseRequest(allocator, "select-entry-id:42");
+    try std.testing.expect(select.id == null);
+    try std.testing.expectEqual(@as(u64, 42), select.command.select_entry_id);
+
+    const bad = parseRequest(allocator, "remove-entry:abc");
+    try std.testing.expectEqual(ErrorCode.invalid_index, bad.command.invalid);
+
+    const unknown = parseRequest(allocator, "frobnicate");
+    try std.testing.expectEqual(ErrorCode.unknown_command, unknown.command.invalid);
+}
+
+test "json requests carry their id and params" {
+    const allocator = std.testing.allocator;
+
+    const select = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"select-entry\",\"params\":{\"id\":42}}");
+    try std.testing.expectEqual(@as(?u64, 7), select.id);
+    try std.testing.expectEqual(@as(u64, 42), select.command.select_entry_id);
+
+    const missing_param = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":8,\"method\":\"remove-entry\"}");
+    try std.testing.expectEqual(@as(?u64, 8), missing_param.id);
+    try std.testing.expectEqual(ErrorCode.invalid_id, missing_param.command.invalid);
+
+    const restore = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"restore-entry\",\"params\":{\"id\":42}}");
+    try std.testing.expectEqual(@as(u64, 42), restore.command.restore_entry_id);
+
+    const purge = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":10,\"method\":\"purge-entry\",\"params\":{\"id\":42}}");
+    try std.testing.expectEqual(@as(u64, 42), purge.command.purge_entry_id);
+
+    const trash = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":11,\"method\":\"get-trash\"}");
+    try std.testing.expect(trash.command == .get_trash);
+
+    const format = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":12,\"method\":\"select-format\",\"params\":{\"id\":42,\"format\":\"html\"}}");
+    try std.testing.expectEqual(@as(u64, 42), format.command.select_format.entry_id);
+    try std.testing.expectEqual(clipboard.FormatKind.html, format.command.select_format.kind);
+
+    const unknown_format = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":13,\"method\":\"select-format\",\"params\":{\"id\":42,\"format\":\"pdf\"}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, unknown_format.command.invalid);
+
+    const malformed = parseRequest(allocator, "{not json");
+    try std.testing.expect(malformed.id == null);
+    try std.testing.expectEqual(ErrorCode.invalid_request, malformed.command.invalid);
+}
+
+test "get-entries takes an optional page" {
+    const allocator = std.testing.allocator;
+
+    const all = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":21,\"method\":\"get-entries\"}");
+    try std.testing.expectEqual(@as(usize, 0), all.command.get_entries.offset);
+    try std.testing.expect(all.command.get_entries.limit == null);
+
+    const page = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":22,\"method\":\"get-entries\",\"params\":{\"offset\":50,\"limit\":50}}");
+    try std.testing.expectEqual(@as(usize, 50), page.command.get_entries.offset);
+    try std.testing.expectEqual(@as(?usize, 50), page.command.get_entries.limit);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":23,\"method\":\"get-entries\",\"params\":{\"limit\":0}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+}
+
+test "search takes a query with optional types and limit" {
+    const allocator = std.testing.allocator;
+
+    const legacy = parseRequest(allocator, "search:git #work");
+    defer legacy.deinit(allocator);
+    try std.testing.expectEqualStrings("git #work", legacy.command.search.query);
+    try std.testing.expectEqual(@as(usize, 0), legacy.command.search.types.count());
+
+    const search = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":24,\"method\":\"search\",\"params\":{\"query\":\"\",\"types\":[\"url\"],\"limit\":500}}");
+    defer search.deinit(allocator);
+    try std.testing.expectEqualStrings("", search.command.search.query);
+    try std.testing.expect(search.command.search.types.contains(.url));
+    try std.testing.expect(!search.command.search.types.contains(.text));
+    try std.testing.expectEqual(@as(?usize, 500), search.command.search.limit);
+
+    const bad_type = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":25,\"method\":\"search\",\"params\":{\"query\":\"a\",\"types\":[\"video\"]}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad_type.command.invalid);
+
+    const texts = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":26,\"method\":\"search\",\"params\":{\"query\":\"inv\",\"texts\":[{\"content\":\"/tmp/a.png\",\"text\":\"Invoice\"}]}}");
+    defer texts.deinit(allocator);
+    try std.testing.expectEqual(@as(usize, 1), texts.command.search.texts.len);
+    try std.testing.expectEqualStrings("/tmp/a.png", texts.command.search.texts[0].content);
+    try std.testing.expectEqualStrings("Invoice", texts.command.search.texts[0].text);
+
+    // The first text is freed when the second is missing its content
+    const bad_text = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":27,\"method\":\"search\",\"params\":{\"query\":\"a\",\"texts\":[{\"content\":\"x\",\"text\":\"y\"},{\"text\":\"z\"}]}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad_text.command.invalid);
+}
+
+test "hello carries the frontend's protocol version" {
+    const allocator = std.testing.allocator;
+
+    const hello = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"hello\",\"params\":{\"protocolVersion\":1}}");
+    try std.testing.expectEqual(@as(?u64, 1), hello.id);
+    try std.testing.expectEqual(@as(u32, 1), hello.command.hello);
+
+    const missing = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"hello\"}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, missing.command.invalid);
+}
+
+test "get-timeline takes an optional limit" {
+    const allocator = std.testing.allocator;
+
+    const all = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":24,\"method\":\"get-timeline\"}");
+    try std.testing.expectEqual(@as(?u64, 24), all.id);
+    try std.testing.expect(all.command.get_timeline == null);
+
+    const some = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":25,\"method\":\"get-timeline\",\"params\":{\"limit\":20}}");
+    try std.testing.expectEqual(@as(?usize, 20), some.command.get_timeline);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":26,\"method\":\"get-timeline\",\"params\":{\"limit\":-1}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+}
+
+test "set-limit takes a positive limit or null" {
+    const allocator = std.testing.allocator;
+
+    const limited = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":3,\"method\":\"set-limit\",\"params\":{\"limit\":500}}");
+    try std.testing.expectEqual(@as(usize, 500), limited.command.set_limit);
+
+    const unlimited = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":4,\"method\":\"set-limit\",\"params\":{\"limit\":null}}");
+    try std.testing.expectEqual(config.unlimited_entries, unlimited.command.set_limit);
+
+    const zero = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":5,\"method\":\"set-limit\",\"params\":{\"limit\":0}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, zero.command.invalid);
+
+    const missing = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":6,\"method\":\"set-limit\"}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, missing.command.invalid);
+}
+
+test "set-clipboard copies its content out of the request" {
+    const allocator = std.testing.allocator;
+
+    const request = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":9,\"method\":\"set-clipboard\",\"params\":{\"content\":\"/tmp/a.zip\",\"type\":\"file\"}}");
+    defer request.deinit(allocator);
+    try std.testing.expectEqualStrings("/tmp/a.zip", request.command.set_clipboard.content);
+    try std.testing.expectEqual(clipboard.ClipboardType.file, request.command.set_clipboard.entry_type);
+
+    const bad_type = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":10,\"method\":\"set-clipboard\",\"params\":{\"content\":\"x\",\"type\":\"bogus\"}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad_type.command.invalid);
+}
+
+test "set-paused requires a boolean" {
+    const allocator = std.testing.allocator;
+
+    const pause = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":11,\"method\":\"set-paused\",\"params\":{\"paused\":true}}");
+    try std.testing.expect(pause.command.set_paused);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":12,\"method\":\"set-paused\",\"params\":{\"paused\":1}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+
+    const append = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":19,\"method\":\"set-append-mode\",\"params\":{\"enabled\":false}}");
+    try std.testing.expect(!append.command.set_append_mode);
+
+    const images = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":20,\"method\":\"set-image-capture\",\"params\":{\"enabled\":false}}");
+    try std.testing.expect(!images.command.set_image_capture);
+}
+
+test "set-tags joins valid tags and rejects the rest" {
+    const allocator = std.testing.allocator;
+
+    const request = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":13,\"method\":\"set-tags\",\"params\":{\"id\":5,\"tags\":[\"work\",\"api-keys\"]}}");
+    defer request.deinit(allocator);
+    try std.testing.expectEqual(@as(u64, 5), request.command.set_tags.entry_id);
+    try std.testing.expectEqualStrings("work,api-keys", request.command.set_tags.tags);
+
+    const cleared = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":14,\"method\":\"set-tags\",\"params\":{\"id\":5,\"tags\":[]}}");
+    defer cleared.deinit(allocator);
+    try std.testing.expectEqualStrings("", cleared.command.set_tags.tags);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":15,\"method\":\"set-tags\",\"params\":{\"id\":5,\"tags\":[\"a,b\"]}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+}
+
+test "swap-entry takes an entry id" {
+    const allocator = std.testing.allocator;
+
+    const swap = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":16,\"method\":\"swap-entry\",\"params\":{\"id\":9}}");
+    try std.testing.expectEqual(@as(u64, 9), swap.command.swap_entry_id);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":17,\"method\":\"swap-entry\",\"params\":{}}");
+    try std.testing.expectEqual(ErrorCode.invalid_id, bad.command.invalid);
+}
+
+test "add-entry carries content like set-clipboard" {
+    const allocator = std.testing.allocator;
+
+    const request = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":18,\"method\":\"add-entry\",\"params\":{\"content\":\"line 2\"}}");
+    defer request.deinit(allocator);
+    try std.testing.expectEqualStrings("line 2", request.command.add_entry.content);
+    try std.testing.expectEqual(clipboard.ClipboardType.text, request.command.add_entry.entry_type);
+    try std.testing.expect(!request.command.add_entry.pinned);
+    try std.testing.expectEqualStrings("", request.command.add_entry.tags);
+}
+
+test "add-entry keeps pin and tags for imports" {
+    const allocator = std.testing.allocator;
+
+    const request = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":19,\"method\":\"add-entry\",\"params\":{\"content\":\"ssh prod\",\"pinned\":true,\"tags\":[\"work\",\"ops\"]}}");
+    defer request.deinit(allocator);
+    try std.testing.expect(request.command.add_entry.pinned);
+    try std.testing.expectEqualStrings("work,ops", request.command.add_entry.tags);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":20,\"method\":\"add-entry\",\"params\":{\"content\":\"x\",\"tags\":[\"no spaces\"]}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+}
+
+test "import-entries takes a batch of entries with their timestamps" {
+    const allocator = std.testing.allocator;
+
+    const request = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":21,\"method\":\"import-entries\",\"params\":{\"entries\":[{\"content\":\"old\",\"timestamp\":1700000000123,\"pinned\":true,\"tags\":[\"work\"]},{\"content\":\"https://ziglang.org\",\"type\":\"url\"}]}}");
+    defer request.deinit(allocator);
+    const entries = request.command.import_entries;
+    try std.testing.expectEqual(@as(usize, 2), entries.len);
+    try std.testing.expectEqualStrings("old", entries[0].content);
+    try std.testing.expectEqual(@as(?i64, 1700000000), entries[0].timestamp);
+    try std.testing.expect(entries[0].pinned);
+    try std.testing.expectEqualStrings("work", entries[0].tags);
+    try std.testing.expectEqual(clipboard.ClipboardType.url, entries[1].entry_type);
+    try std.testing.expectEqual(@as(?i64, null), entries[1].timestamp);
+
+    const bad = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":22,\"method\":\"import-entries\",\"params\":{\"entries\":[{\"content\":\"fine\"},{\"content\":\"\"}]}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, bad.command.invalid);
+}
+
+test "save-snippet takes a name, content and optional id" {
+    const allocator = std.testing.allocator;
+
+    const added = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":24,\"method\":\"save-snippet\",\"params\":{\"name\":\"sig\",\"content\":\"--\\nDuarte\"}}");
+    defer added.deinit(allocator);
+    try std.testing.expect(added.command.save_snippet.id == null);
+    try std.testing.expectEqualStrings("sig", added.command.save_snippet.name);
+    try std.testing.expectEqualStrings("--\nDuarte", added.command.save_snippet.content);
+
+    const updated = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":25,\"method\":\"save-snippet\",\"params\":{\"id\":3,\"name\":\"sig\",\"content\":\"x\"}}");
+    defer updated.deinit(allocator);
+    try std.testing.expectEqual(@as(?u64, 3), updated.command.save_snippet.id);
+
+    const unnamed = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":26,\"method\":\"save-snippet\",\"params\":{\"name\":\"\",\"content\":\"x\"}}");
+    try std.testing.expectEqual(ErrorCode.invalid_request, unnamed.command.invalid);
+
+    const remove = parseRequest(allocator, "{\"jsonrpc\":\"2.0\",\"id\":27,\"method\":\"remove-snippet\",\"params\":{\"id\":3}}");
+    try std.testing.expectEqual(@as(u64, 3), remove.command.remove_snippet_id);
+}
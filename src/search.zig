@@ -0,0 +1,210 @@
+const std = @import("std");
+
+/// The parts of an entry a search looks at.
+pub const Searchable = struct {
+    content: []const u8,
+    /// Comma-separated, as stored.
+    tags: []const u8 = "",
+    source_app: []const u8 = "",
+    window_title: []const u8 = "",
+    /// Text the frontend found for the content, read in an image or a page's title;
+    /// see `protocol.SearchText`.
+    found_text: []const u8 = "",
+};
+
+/// A `search` query, read the way the frontend's search field reads it: `#tag` words
+/// keep entries with a tag starting with that text, and every character of the rest
+/// must appear in order, ignoring ASCII case, within one of the content, the text
+/// found in it, or the app or window it was copied from. Entries are scored like the
+/// frontend ranks them, so a limited search sends the ones it would show first.
+pub const Query = struct {
+    /// Lowercased, without the tag words.
+    text: []u8,
+    /// Lowercased, without `#`.
+    tags: std.ArrayList([]u8) = .{},
+
+    pub fn parse(allocator: std.mem.Allocator, raw: []const u8) !Query {
+        var tags = std.ArrayList([]u8){};
+        errdefer {
+            for (tags.items) |tag| allocator.free(tag);
+            tags.deinit(allocator);
+        }
+        var rest = std.ArrayList(u8){};
+        defer rest.deinit(allocator);
+
+        var words = std.mem.splitScalar(u8, raw, ' ');
+        var first = true;
+        while (words.next()) |word| {
+            if (word.len > 1 and word[0] == '#') {
+                const tag = try std.ascii.allocLowerString(allocator, word[1..]);
+                tags.append(allocator, tag) catch |err| {
+                    allocator.free(tag);
+                    return err;
+                };
+                continue;
+            }
+            if (!first) try rest.append(allocator, ' ');
+            try rest.appendSlice(allocator, word);
+            first = false;
+        }
+
+        const text = try std.ascii.allocLowerString(allocator, std.mem.trim(u8, rest.items, " \t\r\n"));
+        return .{ .text = text, .tags = tags };
+    }
+
+    pub fn deinit(self: *Query, allocator: std.mem.Allocator) void {
+        allocator.free(self.text);
+        for (self.tags.items) |tag| allocator.free(tag);
+        self.tags.deinit(allocator);
+    }
+
+    /// The entry's best field score, or null when it doesn't match. Each field is
+    /// matched on its own, so a match never runs from one into the next.
+    pub fn score(self: Query, entry: Searchable) ?i64 {
+        for (self.tags.items) |tag| {
+            if (!hasTagStartingWith(entry.tags, tag)) return null;
+        }
+        var best: ?i64 = null;
+        for ([_][]const u8{ entry.content, entry.found_text, entry.source_app, entry.window_title }) |field| {
+            const field_score = fuzzyScore(self.text, field) orelse continue;
+            if (best == null or field_score > best.?) best = field_score;
+        }
+        return best;
+    }
+
+    pub fn matches(self: Query, entry: Searchable) bool {
+        return self.score(entry) != null;
+    }
+};
+
+fn hasTagStartingWith(tags: []const u8, prefix: []const u8) bool {
+    if (tags.len == 0) return false;
+    var it = std.mem.splitScalar(u8, tags, ',');
+    while (it.next()) |tag| {
+        if (std.mem.startsWith(u8, tag, prefix)) return true;
+    }
+    return false;
+}
+
+/// The frontend's `fuzzy_score` in search.rs: every character of `needle` (lowercase)
+/// must appear in order in `haystack`, and tighter, word-aligned matches score higher.
+/// Null when it doesn't match; an empty needle scores 0. Change both together. Only
+/// ASCII letters are compared without case here.
+pub fn fuzzyScore(needle: []const u8, haystack: []const u8) ?i64 {
+    const match_score = 16;
+    const consecutive_bonus = 24;
+    const boundary_bonus = 20;
+    const first_char_bonus = 12;
+    const gap_penalty = 2;
+
+    var needle_index: usize = 0;
+    if (needle.len == 0) return 0;
+    var wanted = nextChar(needle, &needle_index);
+    var matched_all = false;
+
+    var score: i64 = 0;
+    var prev: ?u21 = null;
+    var last_match: ?usize = null;
+    var haystack_index: usize = 0;
+    var pos: usize = 0;
+    while (haystack_index < haystack.len) : (pos += 1) {
+        const c = nextChar(haystack, &haystack_index);
+        if (toLower(c) == wanted) {
+            score += match_score;
+            if (last_match) |last| {
+                if (last + 1 == pos) {
+                    score += consecutive_bonus;
+                } else {
+                    score -= gap_penalty * @as(i64, @intCast(@min(pos - last - 1, 8)));
+                }
+            } else {
+                score -= gap_penalty * @as(i64, @intCast(@min(pos, 8)));
+            }
+            if (pos == 0) score += first_char_bonus;
+            if (isWordBoundary(prev, c)) score += boundary_bonus;
+            last_match = pos;
+            if (needle_index == needle.len) {
+                matched_all = true;
+                break;
+            }
+            wanted = nextChar(needle, &needle_index);
+        }
+        prev = c;
+    }
+    return if (matched_all) score else null;
+}
+
+/// The character starting at `bytes[index.*]`, moving `index` past it. Invalid UTF-8
+/// reads as one character per byte.
+fn nextChar(bytes: []const u8, index: *usize) u21 {
+    const start = index.*;
+    const len = std.unicode.utf8ByteSequenceLength(bytes[start]) catch 1;
+    if (len > 1 and start + len <= bytes.len) {
+        if (std.unicode.utf8Decode(bytes[start..][0..len])) |c| {
+            index.* += len;
+            return c;
+        } else |_| {}
+    }
+    index.* += 1;
+    return bytes[start];
+}
+
+fn toLower(c: u21) u21 {
+    return if (c < 0x80) std.ascii.toLower(@intCast(c)) else c;
+}
+
+fn isAlphanumeric(c: u21) bool {
+    return c >= 0x80 or std.ascii.isAlphanumeric(@intCast(c));
+}
+
+fn isWordBoundary(prev: ?u21, c: u21) bool {
+    const p = prev orelse return true;
+    if (!isAlphanumeric(p) and isAlphanumeric(c)) return true;
+    return p < 0x80 and c < 0x80 and std.ascii.isLower(@intCast(p)) and std.ascii.isUpper(@intCast(c));
+}
+
+test "characters match in order, ignoring case" {
+    const allocator = std.testing.allocator;
+    var query = try Query.parse(allocator, "GtCl");
+    defer query.deinit(allocator);
+
+    try std.testing.expect(query.matches(.{ .content = "git clone" }));
+    try std.testing.expect(!query.matches(.{ .content = "clone git" }));
+    // Where it was copied and text found in it count too, but each on its own
+    try std.testing.expect(query.matches(.{ .content = "notes", .window_title = "git clone" }));
+    try std.testing.expect(query.matches(.{ .content = "/tmp/shot.png", .found_text = "Git Clone" }));
+    try std.testing.expect(!query.matches(.{ .content = "git", .source_app = "Terminal", .window_title = "clone" }));
+
+    var empty = try Query.parse(allocator, "");
+    defer empty.deinit(allocator);
+    try std.testing.expect(empty.matches(.{ .content = "anything" }));
+}
+
+test "scores match the frontend's fuzzy_score" {
+    // search.rs's test of fuzzy_score expects the same
+    try std.testing.expectEqual(@as(?i64, 136), fuzzyScore("gtcl", "git clone"));
+    try std.testing.expectEqual(@as(?i64, 0), fuzzyScore("", "anything"));
+    try std.testing.expectEqual(@as(?i64, null), fuzzyScore("gtcl", "clone git"));
+    // Tighter matches rank first
+    try std.testing.expect(fuzzyScore("gtcl", "git clone repo").? > fuzzyScore("gtcl", "get the cold lemonade").?);
+    // Positions count characters, not bytes
+    try std.testing.expectEqual(fuzzyScore("ab", "\u{e9}ab"), fuzzyScore("ab", "xab"));
+}
+
+test "hash words keep entries with a matching tag" {
+    const allocator = std.testing.allocator;
+    var query = try Query.parse(allocator, "#Work deploy #api");
+    defer query.deinit(allocator);
+    try std.testing.expectEqualStrings("deploy", query.text);
+    try std.testing.expectEqual(@as(usize, 2), query.tags.items.len);
+
+    try std.testing.expect(query.matches(.{ .content = "deploy now", .tags = "api-v2,work" }));
+    try std.testing.expect(!query.matches(.{ .content = "deploy now", .tags = "work" }));
+    try std.testing.expect(!query.matches(.{ .content = "deploy now" }));
+
+    // A lone `#` is text like any other
+    var hash = try Query.parse(allocator, "# c#");
+    defer hash.deinit(allocator);
+    try std.testing.expectEqualStrings("# c#", hash.text);
+    try std.testing.expectEqual(@as(usize, 0), hash.tags.items.len);
+}
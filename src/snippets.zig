@@ -0,0 +1,236 @@
+const std = @import("std");
+
+/// A named piece of text kept apart from the clipboard history: new copies, the
+/// history limit and Clear never touch it.
+pub const Snippet = struct {
+    id: u64,
+    name: []const u8,
+    content: []const u8,
+
+    fn free(self: Snippet, allocator: std.mem.Allocator) void {
+        allocator.free(self.name);
+        allocator.free(self.content);
+    }
+};
+
+pub const SnippetError = error{
+    InvalidId,
+};
+
+/// Snippets saved from the frontend, in the order they were created, persisted to
+/// `~/.clipz_snippets.json` on every change.
+///
+/// Only the JSON API request loop uses it, so unlike the history it needs no lock.
+pub const SnippetStore = struct {
+    allocator: std.mem.Allocator,
+    snippets: std.ArrayList(Snippet) = .empty,
+    next_id: u64 = 1,
+    file_path: [256]u8 = undefined,
+    file_path_len: usize = 0,
+
+    pub fn init(allocator: std.mem.Allocator) !SnippetStore {
+        const home_dir = try std.process.getEnvVarOwned(allocator, "HOME");
+        defer allocator.free(home_dir);
+
+        var file_path: [256]u8 = undefined;
+        const file_path_slice = try std.fmt.bufPrint(&file_path, "{s}/.clipz_snippets.json", .{home_dir});
+        return initWithPath(allocator, file_path_slice);
+    }
+
+    pub fn initWithPath(allocator: std.mem.Allocator, path: []const u8) !SnippetStore {
+        if (path.len > 256) return error.PathTooLong;
+
+        var store = SnippetStore{ .allocator = allocator, .file_path_len = path.len };
+        std.mem.copyForwards(u8, store.file_path[0..path.len], path);
+        errdefer store.deinit();
+        try store.load();
+        return store;
+    }
+
+    pub fn deinit(self: *SnippetStore) void {
+        for (self.snippets.items) |snippet| {
+            snippet.free(self.allocator);
+        }
+        self.snippets.deinit(self.allocator);
+    }
+
+    fn getFilePath(self: *const SnippetStore) []const u8 {
+        return self.file_path[0..self.file_path_len];
+    }
+
+    fn indexOf(self: *const SnippetStore, id: u64) ?usize {
+        for (self.snippets.items, 0..) |snippet, i| {
+            if (snippet.id == id) return i;
+        }
+        return null;
+    }
+
+    /// Replaces the snippet with `id`, or adds a new one when `id` is null, and
+    /// returns its id.
+    pub fn save(self: *SnippetStore, id: ?u64, name: []const u8, content: []const u8) !u64 {
+        const snippet_id = blk: {
+            var index: ?usize = null;
+            if (id) |existing| index = self.indexOf(existing) orelse return SnippetError.InvalidId;
+
+            const name_copy = try self.allocator.dupe(u8, name);
+            errdefer self.allocator.free(name_copy);
+            const content_copy = try self.allocator.dupe(u8, content);
+            errdefer self.allocator.free(content_copy);
+
+            if (index) |i| {
+                const old = self.snippets.items[i];
+                self.snippets.items[i] = .{ .id = old.id, .name = name_copy, .content = content_copy };
+                old.free(self.allocator);
+                break :blk old.id;
+            }
+            const new_id = self.next_id;
+            try self.snippets.append(self.allocator, .{ .id = new_id, .name = name_copy, .content = content_copy });
+            self.next_id +%= 1;
+            if (self.next_id == 0) self.next_id = 1;
+            break :blk new_id;
+        };
+        try self.persist();
+        return snippet_id;
+    }
+
+    pub fn remove(self: *SnippetStore, id: u64) !void {
+        const index = self.indexOf(id) orelse return SnippetError.InvalidId;
+        const removed = self.snippets.orderedRemove(index);
+        removed.free(self.allocator);
+        try self.persist();
+    }
+
+    /// Appends the snippets as a JSON array of `{"id","name","content"}` objects, the
+    /// shape both the file and the `snippets` response use.
+    pub fn writeJson(self: *const SnippetStore, allocator: std.mem.Allocator, output: *std.ArrayList(u8)) !void {
+        try output.append(allocator, '[');
+        for (self.snippets.items, 0..) |snippet, i| {
+            if (i > 0) try output.append(allocator, ',');
+            try output.writer(allocator).print("{{\"id\":{d},\"name\":\"", .{snippet.id});
+            try appendEscaped(allocator, output, snippet.name);
+            try output.appendSlice(allocator, "\",\"content\":\"");
+            try appendEscaped(allocator, output, snippet.content);
+            try output.appendSlice(allocator, "\"}");
+        }
+        try output.append(allocator, ']');
+    }
+
+    fn persist(self: *SnippetStore) !void {
+        var json = std.ArrayList(u8){};
+        defer json.deinit(self.allocator);
+        try json.writer(self.allocator).print("{{\"version\":1,\"next_id\":{d},\"snippets\":", .{self.next_id});
+        try self.writeJson(self.allocator, &json);
+        try json.appendSlice(self.allocator, "}\n");
+
+        const temp_path = try std.fmt.allocPrint(self.allocator, "{s}.tmp", .{self.getFilePath()});
+        defer self.allocator.free(temp_path);
+        errdefer std.fs.cwd().deleteFile(temp_path) catch {};
+
+        {
+            const file = try std.fs.cwd().createFile(temp_path, .{ .truncate = true });
+            defer file.close();
+            std.posix.fchmod(file.handle, 0o600) catch {};
+            try file.writeAll(json.items);
+            try file.sync();
+        }
+
+        try std.posix.rename(temp_path, self.getFilePath());
+    }
+
+    fn load(self: *SnippetStore) !void {
+        const file = std.fs.cwd().openFile(self.getFilePath(), .{}) catch |err| switch (err) {
+            error.FileNotFound => return,
+            else => return err,
+        };
+        defer file.close();
+
+        var arena = std.heap.ArenaAllocator.init(self.allocator);
+        defer arena.deinit();
+        const arena_allocator = arena.allocator();
+
+        const content = try file.readToEndAlloc(arena_allocator, 10 * 1024 * 1024);
+        // A damaged file loses the snippets rather than keeping the backend from starting
+        var parsed = std.json.parseFromSlice(std.json.Value, arena_allocator, content, .{}) catch |err| {
+            std.debug.print("Failed to parse snippets file: {}\n", .{err});
+            return;
+        };
+        defer parsed.deinit();
+        const root = parsed.value;
+        if (root != .object) return;
+
+        const items = root.object.get("snippets") orelse return;
+        if (items != .array) return;
+        for (items.array.items) |item| {
+            if (item != .object) continue;
+            const id_field = item.object.get("id") orelse continue;
+            const name_field = item.object.get("name") orelse continue;
+            const content_field = item.object.get("content") orelse continue;
+            if (id_field != .integer or id_field.integer <= 0) continue;
+            if (name_field != .string or content_field != .string) continue;
+
+            const id: u64 = @intCast(id_field.integer);
+            if (self.indexOf(id) != null) continue;
+
+            const name_copy = try self.allocator.dupe(u8, name_field.string);
+            errdefer self.allocator.free(name_copy);
+            const content_copy = try self.allocator.dupe(u8, content_field.string);
+            errdefer self.allocator.free(content_copy);
+            try self.snippets.append(self.allocator, .{ .id = id, .name = name_copy, .content = content_copy });
+            if (id >= self.next_id) self.next_id = id +% 1;
+        }
+
+        if (root.object.get("next_id")) |next_id_field| {
+            if (next_id_field == .integer and next_id_field.integer > 0) {
+                const next_id = std.math.cast(u64, next_id_field.integer) orelse self.next_id;
+                if (next_id > self.next_id) self.next_id = next_id;
+            }
+        }
+        if (self.next_id == 0) self.next_id = 1;
+    }
+};
+
+fn appendEscaped(allocator: std.mem.Allocator, output: *std.ArrayList(u8), value: []const u8) !void {
+    for (value) |char| {
+        switch (char) {
+            '"' => try output.appendSlice(allocator, "\\\""),
+            '\\' => try output.appendSlice(allocator, "\\\\"),
+            '\n' => try output.appendSlice(allocator, "\\n"),
+            '\r' => try output.appendSlice(allocator, "\\r"),
+            '\t' => try output.appendSlice(allocator, "\\t"),
+            0x00...0x08, 0x0b, 0x0c, 0x0e...0x1f => try output.writer(allocator).print("\\u{X:0>4}", .{char}),
+            else => try output.append(allocator, char),
+        }
+    }
+}
+
+test "snippets survive a reload and keep their ids" {
+    const allocator = std.testing.allocator;
+    const path = try std.fmt.allocPrint(allocator, "/tmp/clipz-test-snippets-{d}.json", .{std.time.nanoTimestamp()});
+    defer allocator.free(path);
+    std.fs.deleteFileAbsolute(path) catch {};
+    defer std.fs.deleteFileAbsolute(path) catch {};
+
+    {
+        var store = try SnippetStore.initWithPath(allocator, path);
+        defer store.deinit();
+        const reply = try store.save(null, "reply", "Thanks, \"looking\" into it");
+        const sig = try store.save(null, "sig", "--\nDuarte");
+        _ = try store.save(reply, "thanks", "Thanks!");
+        try store.remove(sig);
+        try std.testing.expectError(SnippetError.InvalidId, store.remove(sig));
+        try std.testing.expectError(SnippetError.InvalidId, store.save(99, "x", "y"));
+    }
+
+    var store = try SnippetStore.initWithPath(allocator, path);
+    defer store.deinit();
+    try std.testing.expectEqual(@as(usize, 1), store.snippets.items.len);
+    try std.testing.expectEqualStrings("thanks", store.snippets.items[0].name);
+    try std.testing.expectEqual(@as(u64, 1), store.snippets.items[0].id);
+    // Removed ids are not handed out again
+    try std.testing.expectEqual(@as(u64, 3), try store.save(null, "new", "n"));
+
+    var json = std.ArrayList(u8){};
+    defer json.deinit(allocator);
+    try store.writeJson(allocator, &json);
+    try std.testing.expectEqualStrings("[{\"id\":1,\"name\":\"thanks\",\"content\":\"Thanks!\"},{\"id\":3,\"name\":\"new\",\"content\":\"n\"}]", json.items);
+}
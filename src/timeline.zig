@@ -0,0 +1,112 @@
+const std = @import("std");
+const clipboard = @import("clipboard.zig");
+
+/// How many clipboard events are kept; older ones are overwritten.
+pub const capacity = 256;
+
+// Longest source app name kept, in bytes
+const max_app_len = 64;
+
+/// What the monitor did with a clipboard change.
+pub const Outcome = enum {
+    recorded,
+    /// Nothing was added: usually the content was already in the history, or every
+    /// slot is pinned.
+    unchanged,
+    paused,
+    /// Made in one of `excluded_apps`.
+    excluded,
+    /// The change was seen but its content couldn't be read.
+    unreadable,
+    /// Image data left unsaved while image capture was off for low disk space.
+    skipped,
+
+    pub fn name(self: Outcome) []const u8 {
+        return @tagName(self);
+    }
+};
+
+/// One clipboard change as the monitor saw it. Fixed size, so recording one never
+/// allocates.
+pub const Event = struct {
+    /// Unix milliseconds when the change was seen.
+    timestamp: i64,
+    change_count: i64,
+    outcome: Outcome,
+    entry_type: ?clipboard.ClipboardType = null,
+    /// Bytes read from the clipboard; for images and files, the length of the path.
+    size: usize = 0,
+    /// From seeing the change to the history being updated.
+    latency_us: u64 = 0,
+    app_buf: [max_app_len]u8 = undefined,
+    app_len: usize = 0,
+
+    /// Keeps the frontmost app's name, cut at a UTF-8 boundary if it is too long.
+    pub fn setApp(self: *Event, app: []const u8) void {
+        var len = @min(app.len, max_app_len);
+        while (len > 0 and len < app.len and (app[len] & 0xc0) == 0x80) len -= 1;
+        @memcpy(self.app_buf[0..len], app[0..len]);
+        self.app_len = len;
+    }
+
+    pub fn app(self: *const Event) []const u8 {
+        return self.app_buf[0..self.app_len];
+    }
+};
+
+/// The latest clipboard events, for `get-timeline` when diagnosing missed copies or
+/// slow processing. Kept in memory only and never persisted.
+pub const Timeline = struct {
+    events: [capacity]Event = undefined,
+    len: usize = 0,
+    next: usize = 0,
+    mutex: std.Thread.Mutex = .{},
+
+    pub fn record(self: *Timeline, event: Event) void {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+        self.events[self.next] = event;
+        self.next = (self.next + 1) % capacity;
+        if (self.len < capacity) self.len += 1;
+    }
+
+    /// Copies the newest events that fit in `out`, oldest first, and returns the part
+    /// of `out` that was filled.
+    pub fn latest(self: *Timeline, out: []Event) []Event {
+        self.mutex.lock();
+        defer self.mutex.unlock();
+        const count = @min(out.len, self.len);
+        const start = (self.next + capacity - count) % capacity;
+        for (0..count) |i| {
+            out[i] = self.events[(start + i) % capacity];
+        }
+        return out[0..count];
+    }
+};
+
+test "timeline keeps the newest events in order" {
+    var timeline = Timeline{};
+    for (0..capacity + 3) |i| {
+        timeline.record(.{ .timestamp = @intCast(i), .change_count = @intCast(i), .outcome = .recorded });
+    }
+
+    var out: [5]Event = undefined;
+    const newest = timeline.latest(&out);
+    try std.testing.expectEqual(@as(usize, 5), newest.len);
+    try std.testing.expectEqual(@as(i64, capacity - 2), newest[0].change_count);
+    try std.testing.expectEqual(@as(i64, capacity + 2), newest[4].change_count);
+
+    var all: [capacity]Event = undefined;
+    try std.testing.expectEqual(@as(usize, capacity), timeline.latest(&all).len);
+}
+
+test "long app names are cut at a character boundary" {
+    var event = Event{ .timestamp = 0, .change_count = 0, .outcome = .excluded };
+    event.setApp("Terminal");
+    try std.testing.expectEqualStrings("Terminal", event.app());
+
+    // 63 ASCII bytes, then a two-byte character straddling the limit
+    const long = "a" ** 63 ++ "\u{e9}";
+    event.setApp(long);
+    try std.testing.expectEqual(@as(usize, 63), event.app().len);
+}
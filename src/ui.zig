@@ -59,6 +59,7 @@ pub const ClipboardUI = struct {
                         std.debug.print("Persistence file: {s}\n", .{path});
                     },
                     .exit => {
+                        self.clipboard.endSession();
                         std.debug.print("Goodbye!\n", .{});
                         break;
                     },